@@ -0,0 +1,431 @@
+//! Tauri commands for @-mention autocompletion in the prompt box
+//!
+//! Candidates come from:
+//! - Entity names in the user's knowledge graph: those in the hot-node cache,
+//!   ranked by how recently they were read, plus the nodes whose embeddings
+//!   are most similar to what was typed once the embedding model has loaded
+//! - Saved command names (ranked by how recently they were updated)
+//! - File paths in the active workspace
+//!
+//! The filesystem, the graph database and the embedding model all block, so
+//! they are read on blocking threads: the graph is opened on one, and
+//! `SqliteStore` runs each query, similarity searches included, on one.
+
+use crate::profiles::command_md::CommandManager;
+use crate::state::graph::UserGraph;
+use crate::state::AppState;
+use chrono::{DateTime, Utc};
+use facet_graph::{Node, VectorStore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+/// Default number of candidates returned
+const DEFAULT_LIMIT: usize = 20;
+
+/// Maximum number of filesystem entries visited per request
+const MAX_FILES_SCANNED: usize = 5_000;
+
+/// Directories never descended into when listing workspace files
+const IGNORED_DIRS: &[&str] = &["target", "node_modules", "dist", "build"];
+
+/// Least embedding similarity for an entity whose name doesn't match the
+/// typed text to be offered anyway
+const MIN_ENTITY_SIMILARITY: f32 = 0.6;
+
+// ============================================================================
+// Request / Response Types
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionRequest {
+    /// Text typed after the `@`
+    pub partial: String,
+    /// Workspace directory to search for file paths
+    pub workspace_root: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CompletionKind {
+    /// Graph entity, labelled with its `name` property
+    Entity,
+    File,
+    Command,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompletionCandidate {
+    pub kind: CompletionKind,
+    /// Text inserted after the `@`
+    pub label: String,
+    /// Secondary text shown next to the label
+    pub detail: Option<String>,
+    pub score: f32,
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// Get completion candidates for an @-mention
+#[tauri::command]
+pub async fn get_mention_completions(
+    request: CompletionRequest,
+    state: State<'_, AppState>,
+) -> Result<Vec<CompletionCandidate>, String> {
+    let now = Utc::now();
+    let limit = request.limit.unwrap_or(DEFAULT_LIMIT);
+    let mut candidates = Vec::new();
+
+    // Saved commands and graph entities for the logged-in user
+    let session = state
+        .user_session
+        .lock()
+        .await
+        .as_ref()
+        .map(|session| (session.username.clone(), session.get_encryption_key()));
+    if let Some((username, key)) = session {
+        let manager = CommandManager::new(username.clone(), key);
+        match manager.list_commands() {
+            Ok(commands) => {
+                for command in commands {
+                    if let Some(score) = score_candidate(
+                        &request.partial,
+                        &command.command_name,
+                        Some(command.updated_at),
+                        now,
+                    ) {
+                        candidates.push(CompletionCandidate {
+                            kind: CompletionKind::Command,
+                            label: command.command_name,
+                            detail: Some(command.description),
+                            score,
+                        });
+                    }
+                }
+            }
+            Err(e) => log::warn!("Failed to list commands for completion: {}", e),
+        }
+
+        match entity_candidates(&state, &username, &request.partial, limit).await {
+            Ok(entities) => candidates.extend(entities),
+            Err(e) => log::warn!("Failed to read graph for completion: {}", e),
+        }
+    }
+
+    // Workspace files
+    if let Some(root) = request.workspace_root.map(PathBuf::from) {
+        let partial = request.partial.clone();
+        let files =
+            tokio::task::spawn_blocking(move || workspace_file_candidates(&root, &partial, now))
+                .await
+                .map_err(|e| format!("Failed to list workspace files: {}", e))?;
+        candidates.extend(files);
+    }
+
+    Ok(rank_candidates(candidates, limit))
+}
+
+// ============================================================================
+// Ranking
+// ============================================================================
+
+/// Scores how well `label` completes `partial`, or None if it doesn't match
+///
+/// Prefix matches beat substring matches, which beat in-order subsequence
+/// matches. Shorter labels win ties, and recently used items get a boost that
+/// halves every week.
+fn score_candidate(
+    partial: &str,
+    label: &str,
+    last_used: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Option<f32> {
+    let partial = partial.to_lowercase();
+    let lower = label.to_lowercase();
+
+    let match_score = if partial.is_empty() {
+        0.5
+    } else if lower.starts_with(&partial) {
+        1.0
+    } else if lower.contains(&partial) {
+        0.7
+    } else if is_subsequence(&partial, &lower) {
+        0.4
+    } else {
+        return None;
+    };
+
+    let length_penalty = 1.0 / (1.0 + lower.len() as f32 / 100.0);
+
+    let recency_boost = last_used
+        .map(|t| {
+            let days = (now - t).num_seconds().max(0) as f32 / 86_400.0;
+            0.5 * 0.5f32.powf(days / 7.0)
+        })
+        .unwrap_or(0.0);
+
+    Some(match_score * length_penalty + recency_boost)
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut chars = haystack.chars();
+    needle.chars().all(|c| chars.any(|h| h == c))
+}
+
+/// Sorts candidates by score (highest first) and keeps the top `limit`
+fn rank_candidates(
+    mut candidates: Vec<CompletionCandidate>,
+    limit: usize,
+) -> Vec<CompletionCandidate> {
+    candidates.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.label.cmp(&b.label))
+    });
+    candidates.truncate(limit);
+    candidates
+}
+
+// ============================================================================
+// Graph Entities
+// ============================================================================
+
+/// Lists graph entities whose name completes `partial`
+///
+/// Entities come from the hot-node cache and, once the embedding model has
+/// loaded, from a similarity search for `partial`. Showing a search hit
+/// doesn't make it hot; only reading it elsewhere does.
+async fn entity_candidates(
+    state: &AppState,
+    username: &str,
+    partial: &str,
+    limit: usize,
+) -> Result<Vec<CompletionCandidate>, String> {
+    let store = {
+        let mut graph = state.graph.lock().await;
+        if graph.as_ref().map(|g| g.username.as_str()) != Some(username) {
+            let username = username.to_string();
+            let opened = tokio::task::spawn_blocking(move || UserGraph::open(&username))
+                .await
+                .map_err(|e| e.to_string())??;
+            *graph = Some(opened);
+        }
+        graph
+            .as_ref()
+            .map(|g| g.store.clone())
+            .ok_or("Graph not open")?
+    };
+
+    // Node id -> (node, rank in the hot-node cache, similarity)
+    let mut found: HashMap<String, (Node, Option<usize>, Option<f32>)> = HashMap::new();
+    for (rank, node) in store.hot_nodes().into_iter().enumerate() {
+        found.insert(node.id.clone(), (node, Some(rank), None));
+    }
+
+    if let Some(embedder) = state.embedder.get().filter(|_| !partial.trim().is_empty()) {
+        let text = partial.to_string();
+        let vector = tokio::task::spawn_blocking(move || embedder.embed(&text))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("Failed to embed completion text: {}", e))?;
+        let hits = store
+            .search_nodes(vector, limit, None)
+            .await
+            .map_err(|e| e.to_string())?;
+        for (node, similarity) in hits {
+            found.entry(node.id.clone()).or_insert((node, None, None)).2 = Some(similarity);
+        }
+    }
+
+    let now = Utc::now();
+    Ok(found
+        .into_values()
+        .filter_map(|(node, hot_rank, similarity)| {
+            let name = node.properties.get("name")?.as_str()?.to_string();
+            let score = score_entity(partial, &name, hot_rank, similarity, now)?;
+            Some(CompletionCandidate {
+                kind: CompletionKind::Entity,
+                label: name,
+                detail: Some(node.label),
+                score,
+            })
+        })
+        .collect())
+}
+
+/// Scores an entity named `name`, or None if it shouldn't be offered
+///
+/// Entities score like other candidates on their name, plus a boost for being
+/// in the hot-node cache that halves every eight places down it, plus their
+/// embedding similarity to `partial`. An entity whose name doesn't match is
+/// still offered if it is similar enough.
+fn score_entity(
+    partial: &str,
+    name: &str,
+    hot_rank: Option<usize>,
+    similarity: Option<f32>,
+    now: DateTime<Utc>,
+) -> Option<f32> {
+    let similarity = similarity.unwrap_or(0.0);
+    let name_score = match score_candidate(partial, name, None, now) {
+        Some(score) => score,
+        None if similarity >= MIN_ENTITY_SIMILARITY => 0.0,
+        None => return None,
+    };
+
+    let hot_boost = hot_rank
+        .map(|rank| 0.5 * 0.5f32.powf(rank as f32 / 8.0))
+        .unwrap_or(0.0);
+
+    Some(name_score + hot_boost + similarity)
+}
+
+// ============================================================================
+// Workspace Files
+// ============================================================================
+
+/// Lists matching files under `root`, skipping hidden and build directories
+fn workspace_file_candidates(
+    root: &Path,
+    partial: &str,
+    now: DateTime<Utc>,
+) -> Vec<CompletionCandidate> {
+    let mut candidates = Vec::new();
+    let mut stack = vec![root.to_path_buf()];
+    let mut scanned = 0;
+
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            scanned += 1;
+            if scanned > MAX_FILES_SCANNED {
+                return candidates;
+            }
+
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+
+            if file_type.is_dir() {
+                if !IGNORED_DIRS.contains(&name.as_str()) {
+                    stack.push(path);
+                }
+                continue;
+            }
+
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            let label = relative.to_string_lossy().to_string();
+
+            let modified = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .map(DateTime::<Utc>::from);
+
+            if let Some(score) = score_candidate(partial, &label, modified, now) {
+                candidates.push(CompletionCandidate {
+                    kind: CompletionKind::File,
+                    label,
+                    detail: None,
+                    score,
+                });
+            }
+        }
+    }
+
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_prefix_beats_substring_and_subsequence() {
+        let now = Utc::now();
+        let prefix = score_candidate("rep", "report", None, now).unwrap();
+        let substring = score_candidate("rep", "weekly-report", None, now).unwrap();
+        let subsequence = score_candidate("rpt", "report", None, now).unwrap();
+
+        assert!(prefix > substring);
+        assert!(substring > subsequence);
+        assert!(score_candidate("xyz", "report", None, now).is_none());
+    }
+
+    #[test]
+    fn test_recent_items_rank_higher() {
+        let now = Utc::now();
+        let recent = score_candidate("check", "check-prices", Some(now), now).unwrap();
+        let stale =
+            score_candidate("check", "check-stock", Some(now - Duration::days(60)), now).unwrap();
+
+        assert!(recent > stale);
+    }
+
+    #[test]
+    fn test_entity_scores() {
+        let now = Utc::now();
+        let cold = score_entity("ali", "Alice", None, None, now).unwrap();
+        let hot = score_entity("ali", "Alice", Some(0), None, now).unwrap();
+        let cooling = score_entity("ali", "Alice", Some(16), None, now).unwrap();
+        let similar = score_entity("ali", "Alice", None, Some(0.8), now).unwrap();
+
+        assert!(hot > cooling && cooling > cold);
+        assert!(similar > cold);
+
+        // A name that doesn't match needs a close embedding
+        assert!(score_entity("ali", "Bob", Some(0), Some(0.3), now).is_none());
+        assert!(score_entity("ali", "Bob", None, Some(0.7), now).is_some());
+    }
+
+    #[test]
+    fn test_workspace_files_skip_hidden_and_build_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::create_dir_all(dir.path().join("target")).unwrap();
+        std::fs::create_dir_all(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "").unwrap();
+        std::fs::write(dir.path().join("target/main.rs"), "").unwrap();
+        std::fs::write(dir.path().join(".git/main.rs"), "").unwrap();
+
+        let candidates = workspace_file_candidates(dir.path(), "main", Utc::now());
+        let labels: Vec<_> = candidates.iter().map(|c| c.label.as_str()).collect();
+
+        assert_eq!(labels.len(), 1);
+        assert!(labels[0].ends_with("main.rs"));
+        assert!(labels[0].starts_with("src"));
+    }
+
+    #[test]
+    fn test_rank_candidates_truncates() {
+        let candidates = (0..5)
+            .map(|i| CompletionCandidate {
+                kind: CompletionKind::File,
+                label: format!("file{}", i),
+                detail: None,
+                score: i as f32,
+            })
+            .collect();
+
+        let ranked = rank_candidates(candidates, 2);
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].label, "file4");
+    }
+}
@@ -1,4 +1,5 @@
 mod agent;
+mod completion;
 mod developer_mode;
 mod feedback;
 mod logging;
@@ -6,6 +7,7 @@ mod profiles;
 pub mod query;
 
 pub use agent::*;
+pub use completion::*;
 pub use developer_mode::*;
 pub use feedback::*;
 pub use logging::*;
@@ -11,10 +11,12 @@ use crate::profiles::{
     auth::{AuthError, AuthService},
     command_md::{CommandExecutor, CommandManager},
     manager::UserManager,
-    storage::{load_user_profile, save_user_profile},
-    types::{Command, CommandInfo, UserConfig},
+    storage::{load_user_profile, save_user_config, save_user_profile},
+    types::{Command, CommandInfo, UsageTotals, UserConfig},
 };
 use crate::state::AppState;
+use facet_server::api::usage::UsageResponse;
+use facet_server::profile_usage::DEFAULT_PROFILE;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tauri::State;
@@ -260,6 +262,64 @@ pub async fn has_users() -> Result<ProfileResult<bool>, String> {
     }
 }
 
+/// Fold the embedded facet-server's usage totals into the current user's stats
+///
+/// The user's usage is the sum over their browser profiles, plus the
+/// server's `default` profile for requests the app sent without one. The
+/// totals replace the previous ones, so refreshing twice counts nothing twice.
+///
+/// # Returns
+/// Updated UserConfig if successful, error message if the server can't be
+/// reached or the config can't be saved
+#[tauri::command]
+pub async fn refresh_usage(
+    state: State<'_, AppState>,
+) -> Result<ProfileResult<UserConfig>, String> {
+    let response = match fetch_usage().await {
+        Ok(response) => response,
+        Err(e) => {
+            log::warn!("⚠️  Failed to fetch usage: {}", e);
+            return Ok(ProfileResult::error(e));
+        }
+    };
+
+    let mut user_session = state.user_session.lock().await;
+    let Some(session) = user_session.as_mut() else {
+        return Ok(ProfileResult::error("No active session".to_string()));
+    };
+
+    let mut usage = UsageTotals::default();
+    for (profile, totals) in &response.profiles {
+        if profile == DEFAULT_PROFILE || session.config.browser_profiles.contains_key(profile) {
+            usage.add(totals);
+        }
+    }
+    session.config.stats.usage = usage;
+
+    let encryption_key = session.get_encryption_key();
+    match save_user_config(&session.username, &session.config, &encryption_key, None) {
+        Ok(_) => Ok(ProfileResult::success(session.config.clone())),
+        Err(e) => {
+            log::error!("❌ Failed to save usage: {}", e);
+            Ok(ProfileResult::error(e.to_string()))
+        }
+    }
+}
+
+/// Fetches the usage of every profile from the embedded facet-server
+async fn fetch_usage() -> Result<UsageResponse, String> {
+    let response = reqwest::get("http://localhost:8443/api/v1/usage")
+        .await
+        .map_err(|e| format!("Failed to connect to facet-server: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("facet-server returned {}", response.status()));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid usage response: {}", e))
+}
+
 // ============================================================================
 // Command System Commands (Phase 3 - Markdown-based)
 // ============================================================================
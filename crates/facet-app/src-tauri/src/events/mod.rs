@@ -1,3 +1,4 @@
+use facet_core::llm::DownloadProgress;
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter};
 
@@ -15,6 +16,13 @@ pub enum DebugEvent {
     },
     /// Chrome download completed
     ChromeDownloaded { path: String, version: String },
+    /// Local model weights download progress
+    ModelDownloadProgress {
+        file: String,
+        downloaded: u64,
+        total: u64,
+        percent: u8,
+    },
     /// Chrome is launching
     ChromeLaunching { message: String },
     /// Chrome successfully launched
@@ -109,6 +117,23 @@ pub fn emit_chrome_downloading(app: &AppHandle, message: impl Into<String>) -> R
     .emit(app)
 }
 
+pub fn emit_model_download_progress(
+    app: &AppHandle,
+    progress: &DownloadProgress,
+) -> Result<(), String> {
+    let percent = match progress.total_bytes {
+        0 => 100,
+        total => (progress.downloaded_bytes.min(total) * 100 / total) as u8,
+    };
+    DebugEvent::ModelDownloadProgress {
+        file: progress.file.clone(),
+        downloaded: progress.downloaded_bytes,
+        total: progress.total_bytes,
+        percent,
+    }
+    .emit(app)
+}
+
 #[allow(dead_code)]
 pub fn emit_chrome_launching(app: &AppHandle, message: impl Into<String>) -> Result<(), String> {
     DebugEvent::ChromeLaunching {
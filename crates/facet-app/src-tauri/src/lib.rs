@@ -13,6 +13,8 @@ mod state;
 
 use state::AppState;
 
+use facet_core::llm::manager::{LlmManager, DEFAULT_IDLE_TIMEOUT};
+use facet_core::llm::{LocalLlm, LocalLlmConfig};
 use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -36,6 +38,21 @@ pub fn run() {
             let state = app.state::<AppState>();
             let webdriver_mode = state.webdriver_mode.clone();
 
+            // The local model the app and the embedded server share, loaded
+            // on first use; its download is reported to the frontend
+            let handle = app.handle().clone();
+            let manager = LlmManager::with_idle_timeout(
+                move || {
+                    LocalLlm::load(LocalLlmConfig::default(), &mut |progress| {
+                        events::emit_model_download_progress(&handle, progress).ok();
+                    })
+                },
+                DEFAULT_IDLE_TIMEOUT,
+            );
+            if let Err(e) = manager.and_then(LlmManager::set_global) {
+                log::warn!("Failed to set up the local model: {}", e);
+            }
+
             // Spawn the embedded facet-server
             tauri::async_runtime::spawn(async move {
                 log::info!("🚀 Starting embedded facet-server...");
@@ -115,6 +132,7 @@ pub fn run() {
             commands::get_user_profile,
             commands::update_user_profile,
             commands::has_users,
+            commands::refresh_usage,
             // Browser session management commands (Phase 2)
             commands::browser::launch_browser_session,
             commands::browser::close_browser_session,
@@ -128,6 +146,8 @@ pub fn run() {
             commands::delete_command,
             commands::build_command_prompt,
             commands::get_static_cdp,
+            // Prompt box autocompletion
+            commands::get_mention_completions,
             // Logging commands
             commands::log_frontend_message,
             commands::get_logs,
@@ -0,0 +1,65 @@
+//! The logged-in user's knowledge graph, as the app reads it
+//!
+//! The graph is opened on first use and read through a node cache, so lookups
+//! repeated while the user types stay in memory. The embedding model used to
+//! rank graph nodes by similarity loads in the background the first time it
+//! is asked for; until it is ready, callers go without it.
+
+use crate::profiles::storage;
+use facet_core::embedding::{Embedder, EmbeddingModel};
+use facet_graph::cache::{CacheConfig, CachedStore};
+use facet_graph::sqlite_store::SqliteStore;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+/// A user's graph database behind a node cache
+pub struct UserGraph {
+    pub username: String,
+    /// Cheap to clone; clones share the cache
+    pub store: CachedStore<SqliteStore>,
+}
+
+impl UserGraph {
+    /// Opens `username`'s graph, creating it if missing
+    ///
+    /// Blocks on the database, so call it off the async runtime.
+    pub fn open(username: &str) -> Result<Self, String> {
+        let path = storage::get_graph_path(username, None).map_err(|e| e.to_string())?;
+        let store = SqliteStore::new(path).map_err(|e| format!("Failed to open graph: {}", e))?;
+
+        Ok(Self {
+            username: username.to_string(),
+            store: CachedStore::new(store, CacheConfig::default()),
+        })
+    }
+}
+
+/// Embedding model loaded on a blocking thread the first time it is needed
+#[derive(Clone, Default)]
+pub struct BackgroundEmbedder {
+    model: Arc<OnceLock<Arc<Embedder>>>,
+    started: Arc<AtomicBool>,
+}
+
+impl BackgroundEmbedder {
+    /// Returns the model if it has loaded, and starts loading it otherwise
+    ///
+    /// A model that fails to load is not retried until the app restarts.
+    pub fn get(&self) -> Option<Arc<Embedder>> {
+        if let Some(model) = self.model.get() {
+            return Some(model.clone());
+        }
+
+        if !self.started.swap(true, Ordering::SeqCst) {
+            let model = self.model.clone();
+            tokio::task::spawn_blocking(move || match Embedder::new(EmbeddingModel::default()) {
+                Ok(embedder) => {
+                    let _ = model.set(Arc::new(embedder));
+                    log::info!("Embedding model loaded");
+                }
+                Err(e) => log::warn!("Failed to load embedding model: {}", e),
+            });
+        }
+        None
+    }
+}
@@ -1,5 +1,8 @@
+pub mod graph;
+
 use crate::developer_mode::DevTestServer;
 use crate::profiles::auth::UserSession;
+use graph::{BackgroundEmbedder, UserGraph};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -15,6 +18,10 @@ pub struct AppState {
     pub http_client: reqwest::Client,
     /// Webdriver mode enabled (detected at startup)
     pub webdriver_mode: Arc<Mutex<bool>>,
+    /// Knowledge graph of the logged-in user, opened on first use
+    pub graph: Arc<Mutex<Option<UserGraph>>>,
+    /// Embedding model for ranking graph nodes, loaded on first use
+    pub embedder: BackgroundEmbedder,
 }
 
 impl AppState {
@@ -28,6 +35,8 @@ impl AppState {
             user_session: Arc::new(Mutex::new(None)),
             http_client: reqwest::Client::new(),
             webdriver_mode: Arc::new(Mutex::new(false)),
+            graph: Arc::new(Mutex::new(None)),
+            embedder: BackgroundEmbedder::default(),
         }
     }
 }
@@ -3,6 +3,7 @@ export type DebugEventType =
   | { type: 'ChromeDownloading'; data: { message: string } }
   | { type: 'ChromeDownloadProgress'; data: { downloaded: number; total: number; percent: number } }
   | { type: 'ChromeDownloaded'; data: { path: string; version: string } }
+  | { type: 'ModelDownloadProgress'; data: { file: string; downloaded: number; total: number; percent: number } }
   | { type: 'ChromeLaunching'; data: { message: string } }
   | { type: 'ChromeLaunched'; data: { message: string } }
   | { type: 'PageNavigating'; data: { url: string } }
@@ -174,6 +175,19 @@ export interface UserStats {
   total_commands_run: number;
   total_sessions: number;
   commands_created: number;
+  usage: UsageTotals;
+}
+
+/**
+ * Token usage and cost of facet-server requests, summed
+ */
+export interface UsageTotals {
+  requests: number;
+  input_tokens: number;
+  output_tokens: number;
+  cache_creation_input_tokens: number;
+  cache_read_input_tokens: number;
+  cost_usd: number;
 }
 
 /**
@@ -275,6 +275,28 @@ export async function getCurrentUser(): Promise<UserConfig | null> {
   }
 }
 
+/**
+ * Refresh the current user's token usage
+ * Folds facet-server's usage totals into the user's stats
+ *
+ * @returns Promise<boolean> - True if the usage was refreshed
+ */
+export async function refreshUsage(): Promise<boolean> {
+  try {
+    const result = await invoke<ProfileResult<UserConfig>>('refresh_usage');
+
+    if (result.success && result.data) {
+      currentUser.set(result.data);
+      return true;
+    } else {
+      // Server not up yet or not logged in; keep the last totals
+      return false;
+    }
+  } catch {
+    return false;
+  }
+}
+
 /**
  * Update user profile markdown content
  * Saves the user-profile.md file with new content
@@ -0,0 +1,77 @@
+use anyhow::{bail, Result};
+use clap::Subcommand;
+use facet_graph::backend::{BackendKind, StoreConfig};
+use facet_graph::diff::diff_snapshots;
+use facet_graph::runs::rollback_run;
+use facet_graph::snapshot::GraphSnapshot;
+use std::path::PathBuf;
+
+#[derive(Subcommand)]
+pub enum GraphCommand {
+    /// Show what changed between two graph snapshots
+    Diff {
+        /// Snapshot taken before the change
+        snapshot_a: PathBuf,
+        /// Snapshot taken after the change
+        snapshot_b: PathBuf,
+        /// Print the diff as JSON instead of a readable report
+        #[arg(long)]
+        json: bool,
+    },
+    /// Revert everything an ingestion or memory-extraction run wrote
+    RollbackRun {
+        /// Id the run wrote its changes under
+        run_id: String,
+        /// Graph database the run wrote to
+        #[arg(long)]
+        graph: PathBuf,
+        /// Storage backend of the graph: surreal or sqlite
+        #[arg(long, default_value = "surreal")]
+        backend: String,
+        /// Print the report as JSON instead of a readable summary
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+pub async fn run(command: GraphCommand) -> Result<()> {
+    match command {
+        GraphCommand::Diff {
+            snapshot_a,
+            snapshot_b,
+            json,
+        } => {
+            let before = GraphSnapshot::load(&snapshot_a)?;
+            let after = GraphSnapshot::load(&snapshot_b)?;
+            let diff = diff_snapshots(&before, &after);
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&diff)?);
+            } else {
+                print!("{}", diff);
+            }
+        }
+        GraphCommand::RollbackRun {
+            run_id,
+            graph,
+            backend,
+            json,
+        } => {
+            let backend = match backend.as_str() {
+                "surreal" => BackendKind::Surreal,
+                "sqlite" => BackendKind::Sqlite,
+                other => bail!("Unknown backend {}; expected surreal or sqlite", other),
+            };
+            let store = StoreConfig::new(backend, graph).open().await?;
+            let report = rollback_run(&store, &run_id).await?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{}", report);
+            }
+        }
+    }
+
+    Ok(())
+}
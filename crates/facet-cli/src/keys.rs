@@ -0,0 +1,72 @@
+use anyhow::Result;
+use clap::Subcommand;
+use facet_server::api_keys::ApiKeyStore;
+use std::path::PathBuf;
+
+#[derive(Subcommand)]
+pub enum KeysCommand {
+    /// Create a key; it is printed once and cannot be shown again
+    Create {
+        /// Name to list the key under, e.g. the app that will use it
+        name: String,
+    },
+    /// List keys, revoked ones included
+    List {
+        /// Print the keys as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Revoke a key so requests can no longer authenticate with it
+    Revoke {
+        /// Id of the key, as listed
+        id: String,
+    },
+}
+
+pub fn run(command: KeysCommand, store: Option<PathBuf>) -> Result<()> {
+    let path = match store {
+        Some(path) => path,
+        None => ApiKeyStore::default_path()?,
+    };
+    let store = ApiKeyStore::open(&path)?;
+
+    match command {
+        KeysCommand::Create { name } => {
+            let created = store.create(&name)?;
+            println!("Created key {} ({})", created.info.id, created.info.name);
+            println!();
+            println!("    {}", created.key);
+            println!();
+            println!("Send it as `Authorization: Bearer <key>`. It won't be shown again.");
+            println!(
+                "The server accepts it if auth.api_keys_path is set to {}",
+                path.display()
+            );
+        }
+        KeysCommand::List { json } => {
+            let keys = store.list()?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&keys)?);
+            } else if keys.is_empty() {
+                println!("No API keys in {}", path.display());
+            } else {
+                for key in keys {
+                    let status = match &key.revoked_at {
+                        Some(revoked_at) => format!("revoked {}", revoked_at),
+                        None => "active".to_string(),
+                    };
+                    println!(
+                        "{}  {:<24}  created {}  {}",
+                        key.id, key.name, key.created_at, status
+                    );
+                }
+            }
+        }
+        KeysCommand::Revoke { id } => {
+            let key = store.revoke(&id)?;
+            println!("Revoked key {} ({})", key.id, key.name);
+        }
+    }
+
+    Ok(())
+}
@@ -1,15 +1,53 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 // use facet_webdriver::{ChromeDriver, ConnectionMode};
 
+mod graph;
+mod keys;
+mod models;
+
 #[derive(Parser)]
 #[command(name = "facet")]
 #[command(version = "0.1.0")]
 #[command(about = "Facet CLI", long_about = None)]
-struct Cli {}
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Inspect and maintain the knowledge graph
+    Graph {
+        #[command(subcommand)]
+        command: graph::GraphCommand,
+    },
+    /// Create, list and revoke the API keys the server accepts
+    Keys {
+        /// API key store to manage (default: ~/.facet/api_keys.json)
+        #[arg(long, global = true)]
+        store: Option<PathBuf>,
+        #[command(subcommand)]
+        command: keys::KeysCommand,
+    },
+    /// Manage the local language models
+    Models {
+        #[command(subcommand)]
+        command: models::ModelsCommand,
+    },
+}
 
 #[tokio::main]
-async fn main() {
-    let _cli = Cli::parse();
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    if let Some(command) = cli.command {
+        return match command {
+            Command::Graph { command } => graph::run(command).await,
+            Command::Keys { store, command } => keys::run(command, store),
+            Command::Models { command } => models::run(command),
+        };
+    }
 
     println!("Facet CLI v0.1.0");
     println!("================\n");
@@ -0,0 +1,228 @@
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use facet_core::llm::{
+    bench, DownloadProgress, LocalLlm, LocalLlmConfig, ModelDir, ModelWeights, Precision,
+};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Section of the CLI config file holding the `LocalLlmConfig`
+const CONFIG_SECTION: &str = "local_llm";
+
+#[derive(Subcommand)]
+pub enum ModelsCommand {
+    /// Download the local model ahead of time and check that it loads
+    Download {
+        /// Weights to fetch: auto, full, q4 or q5
+        #[arg(long, value_parser = parse_precision)]
+        precision: Option<Precision>,
+        /// Only check the weights already downloaded
+        #[arg(long)]
+        offline: bool,
+    },
+    /// Use the model in a local directory instead of downloading one
+    Import {
+        /// Directory with tokenizer.json and either a .gguf file or
+        /// config.json and .safetensors files
+        dir: PathBuf,
+    },
+    /// Merge a LoRA adapter into the local model whenever it loads
+    Adapter {
+        /// PEFT adapter directory (adapter_model.safetensors and
+        /// adapter_config.json) or a .safetensors file
+        path: Option<PathBuf>,
+        /// Stop using the adapter
+        #[arg(long, conflicts_with = "path")]
+        remove: bool,
+    },
+    /// Measure speed and extraction quality of the local model, as JSON
+    Bench {
+        /// Weights to compare, e.g. --precision q4 --precision full; the
+        /// configured ones if not given
+        #[arg(long = "precision", value_parser = parse_precision)]
+        precisions: Vec<Precision>,
+        /// Write the reports to this file instead of printing them
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// ~/.facet/config.toml
+fn config_path() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("Could not determine home directory")?;
+    Ok(home.join(".facet").join("config.toml"))
+}
+
+/// The whole config file, so saving keeps the sections this module
+/// doesn't know about
+fn read_config() -> Result<toml::Table> {
+    let path = config_path()?;
+    if !path.exists() {
+        return Ok(toml::Table::new());
+    }
+    let text = std::fs::read_to_string(&path)?;
+    toml::from_str(&text).with_context(|| format!("Invalid config file {}", path.display()))
+}
+
+fn local_llm_config(config: &toml::Table) -> Result<LocalLlmConfig> {
+    match config.get(CONFIG_SECTION) {
+        Some(section) => Ok(section.clone().try_into()?),
+        None => Ok(LocalLlmConfig::default()),
+    }
+}
+
+fn parse_precision(value: &str) -> Result<Precision, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_lowercase())).map_err(|_| {
+        format!(
+            "unknown precision '{}', expected auto, full, q4 or q5",
+            value
+        )
+    })
+}
+
+pub fn run(command: ModelsCommand) -> Result<()> {
+    match command {
+        ModelsCommand::Download { precision, offline } => {
+            let mut config = local_llm_config(&read_config()?)?;
+            if let Some(precision) = precision {
+                config.precision = precision;
+            }
+            config.offline |= offline;
+            if let Some(dir) = &config.model_dir {
+                println!("Using the model imported from {}", dir.display());
+            }
+            if let Some(lora) = &config.lora {
+                println!("Merging the LoRA adapter {}", lora.display());
+            }
+            let llm = LocalLlm::load(config, &mut print_progress)?;
+            println!(
+                "Model ready: {:?} weights on {:?}",
+                llm.precision(),
+                llm.device_info().device
+            );
+        }
+        ModelsCommand::Import { dir } => {
+            let dir = dir
+                .canonicalize()
+                .with_context(|| format!("Cannot find {}", dir.display()))?;
+            let model_dir = ModelDir::scan(&dir)?;
+            match &model_dir.weights {
+                ModelWeights::Gguf(path) => println!("Found {}", path.display()),
+                ModelWeights::Safetensors { shards, .. } => {
+                    println!("Found {} safetensors files", shards.len())
+                }
+            }
+
+            let mut config = read_config()?;
+            let mut local = local_llm_config(&config)?;
+            local.model_dir = Some(dir.clone());
+            local.precision = model_dir.precision();
+            let path = write_local_llm_config(&mut config, &local)?;
+            println!(
+                "Imported {} ({:?}); settings saved to {}",
+                dir.display(),
+                local.precision,
+                path.display()
+            );
+        }
+        ModelsCommand::Adapter { path, remove } => {
+            let mut config = read_config()?;
+            let mut local = local_llm_config(&config)?;
+            match path {
+                Some(path) => {
+                    let path = path
+                        .canonicalize()
+                        .with_context(|| format!("Cannot find {}", path.display()))?;
+                    local.lora = Some(path);
+                }
+                None if remove => local.lora = None,
+                None => {
+                    match &local.lora {
+                        Some(path) => println!("Using the LoRA adapter {}", path.display()),
+                        None => println!("No LoRA adapter set"),
+                    }
+                    return Ok(());
+                }
+            }
+            let saved = write_local_llm_config(&mut config, &local)?;
+            match &local.lora {
+                Some(path) => println!(
+                    "The local model will load with {}; settings saved to {}",
+                    path.display(),
+                    saved.display()
+                ),
+                None => println!(
+                    "LoRA adapter removed; settings saved to {}",
+                    saved.display()
+                ),
+            }
+        }
+        ModelsCommand::Bench { precisions, output } => {
+            let config = local_llm_config(&read_config()?)?;
+            let precisions = if precisions.is_empty() {
+                vec![config.precision]
+            } else {
+                precisions
+            };
+            let mut reports = Vec::new();
+            for precision in precisions {
+                let config = LocalLlmConfig {
+                    precision,
+                    ..config.clone()
+                };
+                eprintln!("Benchmarking {:?} weights...", precision);
+                let mut llm = LocalLlm::load(config, &mut print_progress)?;
+                let model = format!("{:?} on {:?}", llm.precision(), llm.device_info().device);
+                reports.push(bench::run_bench(&mut llm, &model)?);
+            }
+            let json = serde_json::to_string_pretty(&reports)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, json)?;
+                    eprintln!("Reports written to {}", path.display());
+                }
+                None => println!("{}", json),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Stores `local` in its section of `config` and writes the file back,
+/// returning its path
+fn write_local_llm_config(config: &mut toml::Table, local: &LocalLlmConfig) -> Result<PathBuf> {
+    config.insert(CONFIG_SECTION.to_string(), toml::Value::try_from(local)?);
+    let path = config_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, toml::to_string_pretty(config)?)?;
+    Ok(path)
+}
+
+/// On stderr, so it doesn't mix with output like `bench`'s JSON
+fn print_progress(progress: &DownloadProgress) {
+    let mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+    let percent = if progress.total_bytes > 0 {
+        progress.downloaded_bytes as f64 * 100.0 / progress.total_bytes as f64
+    } else {
+        0.0
+    };
+    let eta = progress
+        .eta
+        .map(|eta| format!(", {}s left", eta.as_secs()))
+        .unwrap_or_default();
+    eprint!(
+        "\r{}: {:.0}% ({:.1}/{:.1} MB{})   ",
+        progress.file,
+        percent,
+        mb(progress.downloaded_bytes),
+        mb(progress.total_bytes),
+        eta
+    );
+    if progress.is_done() {
+        eprintln!();
+    }
+    let _ = std::io::stderr().flush();
+}
@@ -0,0 +1,117 @@
+//! Cost of generating tokens with `LocalLlm` against re-feeding the window
+//!
+//! Run with `cargo bench -p facet-core --bench generation`.
+//!
+//! Both sides use the same small Phi-3 with zeroed weights, so no download
+//! is needed and the numbers only reflect the work per step. The re-feed
+//! baseline runs the whole sequence through the model for every token, so
+//! its time per token grows with the output; `LocalLlm::generate` feeds
+//! only the newest token against the KV cache and stays flat.
+
+use candle_core::{DType, Device, Tensor};
+use candle_nn::{Activation, VarBuilder};
+use candle_transformers::models::phi3::{Config, Model};
+use facet_core::llm::{GenerationParams, Llm, LocalLlm};
+use std::time::{Duration, Instant};
+use tokenizers::models::wordlevel::WordLevel;
+use tokenizers::pre_tokenizers::whitespace::Whitespace;
+use tokenizers::Tokenizer;
+
+const PROMPT_TOKENS: usize = 64;
+const LENGTHS: [usize; 4] = [32, 64, 128, 256];
+
+fn config() -> Config {
+    Config {
+        // Well below the stop token ids, so every run generates all tokens
+        vocab_size: 1024,
+        hidden_act: Activation::Silu,
+        hidden_size: 256,
+        intermediate_size: 1024,
+        num_hidden_layers: 4,
+        num_attention_heads: 8,
+        num_key_value_heads: 8,
+        rms_norm_eps: 1e-5,
+        rope_theta: 10_000.0,
+        bos_token_id: None,
+        eos_token_id: None,
+        rope_scaling: None,
+        max_position_embeddings: 4096,
+        original_max_position_embeddings: None,
+        partial_rotary_factor: None,
+        tie_word_embeddings: false,
+    }
+}
+
+fn model(device: &Device) -> Model {
+    Model::new(&config(), VarBuilder::zeros(DType::F32, device)).unwrap()
+}
+
+fn tokenizer() -> Tokenizer {
+    let vocab = (0..config().vocab_size as u32)
+        .map(|id| (format!("t{id}"), id))
+        .collect();
+    let model = WordLevel::builder()
+        .vocab(vocab)
+        .unk_token("t0".to_string())
+        .build()
+        .unwrap();
+    let mut tokenizer = Tokenizer::new(model);
+    tokenizer.with_pre_tokenizer(Some(Whitespace {}));
+    tokenizer
+}
+
+/// Generation without a cache: every step runs the full sequence
+fn refeed(model: &mut Model, device: &Device, tokens: usize) -> Duration {
+    let mut sequence = vec![1u32; PROMPT_TOKENS];
+    let start = Instant::now();
+    for _ in 0..tokens {
+        model.clear_kv_cache();
+        let input = Tensor::new(sequence.as_slice(), device)
+            .unwrap()
+            .unsqueeze(0)
+            .unwrap();
+        let logits = model.forward(&input, 0).unwrap();
+        let next = logits
+            .squeeze(0)
+            .unwrap()
+            .squeeze(0)
+            .unwrap()
+            .argmax(0)
+            .unwrap()
+            .to_scalar::<u32>()
+            .unwrap();
+        sequence.push(next);
+    }
+    start.elapsed()
+}
+
+fn cached(llm: &mut LocalLlm, prompt: &str, tokens: usize) -> Duration {
+    let start = Instant::now();
+    llm.generate(prompt, &GenerationParams::new(tokens))
+        .unwrap();
+    start.elapsed()
+}
+
+fn main() {
+    let device = Device::Cpu;
+    let mut baseline = model(&device);
+    let mut llm = LocalLlm::from_parts(model(&device), tokenizer(), device.clone());
+    let prompt = vec!["t1"; PROMPT_TOKENS].join(" ");
+
+    // Warm up allocations before timing
+    refeed(&mut baseline, &device, 4);
+    cached(&mut llm, &prompt, 4);
+
+    println!("{PROMPT_TOKENS}-token prompt, time to generate N tokens");
+    for tokens in LENGTHS {
+        let refeed = refeed(&mut baseline, &device, tokens);
+        let cached = cached(&mut llm, &prompt, tokens);
+        println!(
+            "{:>4} tokens: re-feed {:>8.1} ms  kv-cache {:>8.1} ms  {:>5.1}x",
+            tokens,
+            refeed.as_secs_f64() * 1000.0,
+            cached.as_secs_f64() * 1000.0,
+            refeed.as_secs_f64() / cached.as_secs_f64()
+        );
+    }
+}
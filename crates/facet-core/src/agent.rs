@@ -5,21 +5,79 @@
 
 use anyhow::Result;
 use facet_graph::{GraphStore, VectorStore};
+use serde_json::{json, Value};
 use std::sync::Arc;
 
-/// Tool that the agent can use
+/// Tool that the agent can use, and that the model can call through a
+/// `ToolRunner`
 pub trait Tool: Send + Sync {
-    /// Execute the tool with the given input
-    fn execute(&self, input: &str) -> Result<String>;
+    /// Execute the tool with the given arguments, an object that has at
+    /// least the properties `parameters` requires
+    fn execute(&self, arguments: &Value) -> Result<String>;
 
     /// Get the tool's name
     fn name(&self) -> &str;
 
     /// Get the tool's description for the agent
     fn description(&self) -> &str;
+
+    /// JSON schema of the arguments object; no arguments by default
+    fn parameters(&self) -> Value {
+        json!({"type": "object", "properties": {}})
+    }
+}
+
+/// A tool made from a closure, for tools without state of their own
+pub struct FnTool<F> {
+    name: String,
+    description: String,
+    parameters: Value,
+    run: F,
+}
+
+impl<F> FnTool<F>
+where
+    F: Fn(&Value) -> Result<String> + Send + Sync,
+{
+    pub fn new(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: Value,
+        run: F,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            parameters,
+            run,
+        }
+    }
+}
+
+impl<F> Tool for FnTool<F>
+where
+    F: Fn(&Value) -> Result<String> + Send + Sync,
+{
+    fn execute(&self, arguments: &Value) -> Result<String> {
+        (self.run)(arguments)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters(&self) -> Value {
+        self.parameters.clone()
+    }
 }
 
 /// The custom RAG agent
+// The stores are read once the stubbed methods below are implemented
+#[allow(dead_code)]
 pub struct Agent {
     graph_store: Arc<dyn GraphStore>,
     vector_store: Arc<dyn VectorStore>,
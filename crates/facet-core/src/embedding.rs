@@ -0,0 +1,274 @@
+//! Local Sentence Embeddings
+//!
+//! Turns text into vectors for the graph's `VectorStore` with a small BERT
+//! sentence model run through candle, so no text leaves the machine to be
+//! embedded.
+
+use anyhow::{Error as E, Result};
+use async_trait::async_trait;
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config as BertConfig};
+use facet_graph::retriever;
+use facet_graph::GraphError;
+use hf_hub::{api::sync::Api, Repo, RepoType};
+use serde::{Deserialize, Serialize};
+use tokenizers::{PaddingParams, PaddingStrategy, Tokenizer, TruncationParams};
+
+/// Sentence models `Embedder` can load
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmbeddingModel {
+    /// BAAI/bge-small-en-v1.5, 384 dimensions
+    #[default]
+    BgeSmallEn,
+    /// sentence-transformers/all-MiniLM-L6-v2, 384 dimensions
+    AllMiniLmL6,
+}
+
+/// How token vectors are combined into one vector per text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pooling {
+    /// The first ([CLS]) token, as BGE is trained
+    Cls,
+    /// The mean over the non-padding tokens, as sentence-transformers are
+    Mean,
+}
+
+impl EmbeddingModel {
+    fn repo(self) -> &'static str {
+        match self {
+            EmbeddingModel::BgeSmallEn => "BAAI/bge-small-en-v1.5",
+            EmbeddingModel::AllMiniLmL6 => "sentence-transformers/all-MiniLM-L6-v2",
+        }
+    }
+
+    pub fn pooling(self) -> Pooling {
+        match self {
+            EmbeddingModel::BgeSmallEn => Pooling::Cls,
+            EmbeddingModel::AllMiniLmL6 => Pooling::Mean,
+        }
+    }
+}
+
+/// Tokens per text the models accept; longer texts are truncated
+const MAX_TOKENS: usize = 512;
+
+pub struct Embedder {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    device: Device,
+    pooling: Pooling,
+    dimension: usize,
+}
+
+impl Embedder {
+    /// Downloads `model` (or uses the Hugging Face cache) and loads it on
+    /// the CPU
+    pub fn new(model: EmbeddingModel) -> Result<Self> {
+        let device = Device::Cpu;
+        let api = Api::new()?;
+        let repo = api.repo(Repo::new(model.repo().to_string(), RepoType::Model));
+
+        let config: BertConfig = serde_json::from_slice(&std::fs::read(repo.get("config.json")?)?)?;
+        let tokenizer = Tokenizer::from_file(repo.get("tokenizer.json")?).map_err(E::msg)?;
+        let weights = repo.get("model.safetensors")?;
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[weights], DType::F32, &device)? };
+        let bert = BertModel::load(vb, &config)?;
+
+        Self::from_parts(bert, &config, tokenizer, device, model.pooling())
+    }
+
+    /// Wraps an already loaded BERT model and its tokenizer
+    pub fn from_parts(
+        model: BertModel,
+        config: &BertConfig,
+        mut tokenizer: Tokenizer,
+        device: Device,
+        pooling: Pooling,
+    ) -> Result<Self> {
+        // Batches are padded to their longest text; the attention mask keeps
+        // padding out of the result
+        tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::BatchLongest,
+            pad_id: config.pad_token_id as u32,
+            ..Default::default()
+        }));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_TOKENS.min(config.max_position_embeddings),
+                ..Default::default()
+            }))
+            .map_err(E::msg)?;
+
+        Ok(Self {
+            model,
+            tokenizer,
+            device,
+            pooling,
+            dimension: config.hidden_size,
+        })
+    }
+
+    /// Length of the vectors this model produces
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    /// Checks the model against the dimension a store was configured with
+    /// (e.g. `SurrealStore::embedding_dimension`), so a mismatch shows up
+    /// when the app starts rather than on the first write
+    pub fn ensure_dimension(&self, expected: usize) -> Result<(), GraphError> {
+        if self.dimension != expected {
+            return Err(GraphError::DimensionMismatch {
+                expected,
+                actual: self.dimension,
+            });
+        }
+        Ok(())
+    }
+
+    /// A unit-length embedding of `text`
+    pub fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let mut embeddings = self.embed_batch(&[text])?;
+        Ok(embeddings.remove(0))
+    }
+
+    /// Unit-length embeddings of `texts`, in order, computed in one pass
+    pub fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(E::msg)?;
+
+        let ids: Vec<Tensor> = encodings
+            .iter()
+            .map(|e| Tensor::new(e.get_ids(), &self.device))
+            .collect::<candle_core::Result<_>>()?;
+        let masks: Vec<Tensor> = encodings
+            .iter()
+            .map(|e| Tensor::new(e.get_attention_mask(), &self.device))
+            .collect::<candle_core::Result<_>>()?;
+        let input_ids = Tensor::stack(&ids, 0)?;
+        let attention_mask = Tensor::stack(&masks, 0)?;
+        let token_type_ids = input_ids.zeros_like()?;
+
+        // (batch, tokens, hidden)
+        let hidden = self
+            .model
+            .forward(&input_ids, &token_type_ids, Some(&attention_mask))?;
+        let pooled = match self.pooling {
+            Pooling::Cls => hidden.narrow(1, 0, 1)?.squeeze(1)?,
+            Pooling::Mean => {
+                let mask = attention_mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+                let summed = hidden.broadcast_mul(&mask)?.sum(1)?;
+                let counts = mask.sum(1)?.clamp(1f32, f32::MAX)?;
+                summed.broadcast_div(&counts)?
+            }
+        };
+        let norms = pooled
+            .sqr()?
+            .sum_keepdim(1)?
+            .sqrt()?
+            .clamp(f32::EPSILON, f32::MAX)?;
+        Ok(pooled.broadcast_div(&norms)?.to_vec2()?)
+    }
+}
+
+#[async_trait]
+impl retriever::Embedder for Embedder {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, GraphError> {
+        Embedder::embed(self, text)
+            .map_err(|e| GraphError::Storage(format!("Embedding failed: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_nn::VarMap;
+    use candle_transformers::models::bert::HiddenAct;
+    use tokenizers::models::wordlevel::WordLevel;
+    use tokenizers::pre_tokenizers::whitespace::Whitespace;
+
+    /// A two-layer BERT with random weights and a word-per-token tokenizer
+    fn tiny(pooling: Pooling) -> Embedder {
+        let words = [
+            "[PAD]", "the", "graph", "stores", "notes", "about", "people",
+        ];
+        let config = BertConfig {
+            vocab_size: words.len(),
+            hidden_size: 16,
+            num_hidden_layers: 2,
+            num_attention_heads: 2,
+            intermediate_size: 32,
+            hidden_act: HiddenAct::Gelu,
+            max_position_embeddings: 64,
+            ..Default::default()
+        };
+        let device = Device::Cpu;
+        let varmap = VarMap::new();
+        let vb = VarBuilder::from_varmap(&varmap, DType::F32, &device);
+        let model = BertModel::load(vb, &config).unwrap();
+
+        let vocab = words
+            .iter()
+            .enumerate()
+            .map(|(id, word)| (word.to_string(), id as u32))
+            .collect();
+        let wordlevel = WordLevel::builder()
+            .vocab(vocab)
+            .unk_token("[PAD]".to_string())
+            .build()
+            .unwrap();
+        let mut tokenizer = Tokenizer::new(wordlevel);
+        tokenizer.with_pre_tokenizer(Some(Whitespace {}));
+
+        Embedder::from_parts(model, &config, tokenizer, device, pooling).unwrap()
+    }
+
+    #[test]
+    fn test_embed_batch_matches_single_embeddings() {
+        for pooling in [Pooling::Cls, Pooling::Mean] {
+            let embedder = tiny(pooling);
+            assert_eq!(embedder.dimension(), 16);
+
+            // Different lengths, so the shorter text is padded in the batch
+            let texts = ["the graph stores notes about people", "people"];
+            let batch = embedder.embed_batch(&texts).unwrap();
+            assert_eq!(batch.len(), 2);
+            for (text, vector) in texts.iter().zip(&batch) {
+                assert_eq!(vector.len(), 16);
+                let norm: f32 = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+                assert!((norm - 1.0).abs() < 1e-4);
+
+                let single = embedder.embed(text).unwrap();
+                for (a, b) in single.iter().zip(vector) {
+                    assert!(
+                        (a - b).abs() < 1e-4,
+                        "{:?} pooling differs with padding",
+                        pooling
+                    );
+                }
+            }
+            assert_ne!(batch[0], batch[1]);
+            assert!(embedder.embed_batch(&[]).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_ensure_dimension() {
+        let embedder = tiny(Pooling::Mean);
+        assert!(embedder.ensure_dimension(16).is_ok());
+        assert!(matches!(
+            embedder.ensure_dimension(384),
+            Err(GraphError::DimensionMismatch {
+                expected: 384,
+                actual: 16
+            })
+        ));
+    }
+}
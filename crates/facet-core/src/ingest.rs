@@ -3,7 +3,8 @@
 //! This module handles ingesting documents into the knowledge graph.
 
 use anyhow::Result;
-use facet_graph::{GraphStore, Node, VectorStore};
+use facet_graph::{GraphStore, VectorStore};
+use std::ops::Range;
 use std::path::Path;
 use std::sync::Arc;
 
@@ -23,6 +24,8 @@ pub struct DocumentChunk {
 }
 
 /// The document ingestion pipeline
+// The stores are read once the stubbed methods below are implemented
+#[allow(dead_code)]
 pub struct IngestionPipeline {
     graph_store: Arc<dyn GraphStore>,
     vector_store: Arc<dyn VectorStore>,
@@ -53,23 +56,41 @@ impl IngestionPipeline {
     }
 
     /// Extract entities from text using local LLM
+    #[allow(dead_code)]
     async fn extract_entities(&self, _text: &str) -> Result<Vec<Entity>> {
         todo!("Implement entity extraction using local LLM")
     }
 
     /// Infer relationships between entities
+    #[allow(dead_code)]
     async fn infer_relationships(&self, _entities: &[Entity]) -> Result<Vec<Relationship>> {
         todo!("Implement relationship inference")
     }
 }
 
 /// Extracted entity
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
 pub struct Entity {
     pub name: String,
+    #[serde(rename = "type")]
     pub entity_type: String,
+    #[serde(default)]
     pub properties: serde_json::Value,
 }
 
+/// Extracted fact, `subject` `relation` `object`, e.g. Alice works_at Acme
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Triple {
+    pub subject: String,
+    pub relation: String,
+    pub object: String,
+    /// How sure the model is of the fact, from 0 to 1
+    pub confidence: f32,
+    /// Byte offsets of the passage the fact was read from, when the model's
+    /// quote of it is found in the text
+    pub span: Option<Range<usize>>,
+}
+
 /// Inferred relationship
 pub struct Relationship {
     pub source: String,
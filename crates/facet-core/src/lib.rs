@@ -1,8 +1,12 @@
 pub mod agent;
 pub mod claude;
 pub mod context;
+pub mod embedding;
 pub mod ingest;
 pub mod llm;
 pub mod memory;
+pub mod ner;
+pub mod pii;
 pub mod pruning;
+pub mod redaction;
 pub mod search;
@@ -0,0 +1,229 @@
+//! Benchmark and quality evaluation
+//!
+//! `run_bench` measures how fast a backend generates (time to first token,
+//! tokens per second) and how well it does the extraction the app relies
+//! on, on a small built-in suite: PII it has to redact, and facts it has to
+//! find as triples. Reports serialize to JSON, so runs over several models
+//! (`facet models bench`) can be compared to pick one for the hardware.
+//!
+//! Extraction is scored by recall: the share of expected PII values that
+//! no longer appear in the redacted text, and of expected facts found with
+//! matching subject and object. Relations are phrased too freely to compare.
+
+use super::{GenerationParams, Llm};
+use anyhow::Result;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Prompt the speed run continues
+const SPEED_PROMPT: &str = "Write a short paragraph about the history of the printing press.";
+
+/// Tokens the speed run generates
+const SPEED_TOKENS: usize = 128;
+
+/// Text with the PII values that have to be redacted from it
+const PII_CASES: &[(&str, &[&str])] = &[
+    (
+        "Please call Maria Gonzalez at 555-0142 about the invoice.",
+        &["Maria Gonzalez", "555-0142"],
+    ),
+    (
+        "Send the contract to j.smith@example.com and copy Peter Novak.",
+        &["j.smith@example.com", "Peter Novak"],
+    ),
+    (
+        "Our new office is at 12 Harbour Street, Bristol; ask for Aiko Tanaka.",
+        &["12 Harbour Street", "Aiko Tanaka"],
+    ),
+];
+
+/// Text with the facts, as subject and object, that have to be found in it
+const TRIPLE_CASES: &[(&str, &[(&str, &str)])] = &[
+    (
+        "Alice Chen works at Acme Corp. Acme Corp is based in Oslo.",
+        &[("Alice Chen", "Acme Corp"), ("Acme Corp", "Oslo")],
+    ),
+    (
+        "Project Falcon is led by Omar Haddad and funded by Northwind.",
+        &[
+            ("Project Falcon", "Omar Haddad"),
+            ("Project Falcon", "Northwind"),
+        ],
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpeedReport {
+    pub prompt_tokens: usize,
+    pub generated_tokens: usize,
+    pub time_to_first_token_ms: f64,
+    /// Generated tokens over the time after the first one
+    pub tokens_per_second: f64,
+    pub total_ms: f64,
+}
+
+/// How one case of the suite went
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CaseReport {
+    pub text: String,
+    /// Share of what was expected that the model got, from 0 to 1
+    pub score: f64,
+    /// What it missed
+    pub missed: Vec<String>,
+    /// Why the case failed to run, if it did; it scores 0
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchReport {
+    pub model: String,
+    pub speed: SpeedReport,
+    /// Mean score of the PII cases
+    pub pii_accuracy: f64,
+    /// Mean score of the triple cases
+    pub triple_accuracy: f64,
+    pub pii_cases: Vec<CaseReport>,
+    pub triple_cases: Vec<CaseReport>,
+}
+
+/// Benchmarks `llm`, reported under the name `model`. Fails only if the
+/// speed run does; a failing case scores 0 and the suite goes on.
+pub fn run_bench<L: Llm + ?Sized>(llm: &mut L, model: &str) -> Result<BenchReport> {
+    let speed = measure_speed(llm)?;
+    let pii_cases: Vec<CaseReport> = PII_CASES
+        .iter()
+        .map(|(text, expected)| pii_case(llm, text, expected))
+        .collect();
+    let triple_cases: Vec<CaseReport> = TRIPLE_CASES
+        .iter()
+        .map(|(text, expected)| triple_case(llm, text, expected))
+        .collect();
+    Ok(BenchReport {
+        model: model.to_string(),
+        speed,
+        pii_accuracy: mean(&pii_cases),
+        triple_accuracy: mean(&triple_cases),
+        pii_cases,
+        triple_cases,
+    })
+}
+
+fn measure_speed<L: Llm + ?Sized>(llm: &mut L) -> Result<SpeedReport> {
+    let prompt = llm.format_prompt("", SPEED_PROMPT);
+    let params = GenerationParams::precise(SPEED_TOKENS);
+    let started = Instant::now();
+    let mut first_token = None;
+    let output = llm.generate_stream(&prompt, &params, &mut |_| {
+        first_token.get_or_insert_with(|| started.elapsed());
+        Ok(())
+    })?;
+    let total = started.elapsed();
+    let first_token = first_token.unwrap_or(total);
+
+    let generated_tokens = llm.count_tokens(&output)?;
+    let decoding = total.saturating_sub(first_token);
+    let tokens_per_second = if decoding > Duration::ZERO {
+        generated_tokens.saturating_sub(1) as f64 / decoding.as_secs_f64()
+    } else {
+        0.0
+    };
+    Ok(SpeedReport {
+        prompt_tokens: llm.count_tokens(&prompt)?,
+        generated_tokens,
+        time_to_first_token_ms: first_token.as_secs_f64() * 1000.0,
+        tokens_per_second,
+        total_ms: total.as_secs_f64() * 1000.0,
+    })
+}
+
+fn pii_case<L: Llm + ?Sized>(llm: &mut L, text: &str, expected: &[&str]) -> CaseReport {
+    let missed = llm.extract_pii(text).map(|(redacted, _)| {
+        expected
+            .iter()
+            .filter(|value| redacted.contains(**value))
+            .map(|value| value.to_string())
+            .collect()
+    });
+    case_report(text, expected.len(), missed)
+}
+
+fn triple_case<L: Llm + ?Sized>(llm: &mut L, text: &str, expected: &[(&str, &str)]) -> CaseReport {
+    let missed = llm.extract_triples(text).map(|triples| {
+        expected
+            .iter()
+            .filter(|(subject, object)| {
+                !triples.iter().any(|triple| {
+                    triple.subject.eq_ignore_ascii_case(subject)
+                        && triple.object.eq_ignore_ascii_case(object)
+                })
+            })
+            .map(|(subject, object)| format!("{} -> {}", subject, object))
+            .collect()
+    });
+    case_report(text, expected.len(), missed)
+}
+
+fn case_report(text: &str, expected: usize, missed: Result<Vec<String>>) -> CaseReport {
+    match missed {
+        Ok(missed) => CaseReport {
+            text: text.to_string(),
+            score: (expected - missed.len()) as f64 / expected.max(1) as f64,
+            missed,
+            error: None,
+        },
+        Err(e) => CaseReport {
+            text: text.to_string(),
+            score: 0.0,
+            missed: Vec::new(),
+            error: Some(format!("{:#}", e)),
+        },
+    }
+}
+
+fn mean(cases: &[CaseReport]) -> f64 {
+    if cases.is_empty() {
+        return 0.0;
+    }
+    cases.iter().map(|case| case.score).sum::<f64>() / cases.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::scripted_replies;
+    use super::*;
+
+    #[test]
+    fn test_bench_scores_the_suite() {
+        let redacted = |text: &str| format!(r#"{{"redacted_text": "{}", "pii": {{}}}}"#, text);
+        let mut replies = vec!["The printing press spread quickly.".to_string()];
+        // First case fully redacted, second half, third not at all
+        replies.push(redacted(
+            "Please call [NAME_1] at [PHONE_1] about the invoice.",
+        ));
+        replies.push(redacted(
+            "Send the contract to [EMAIL_1] and copy Peter Novak.",
+        ));
+        replies.push(redacted(PII_CASES[2].0));
+        replies.push(
+            r#"{"triples": [{"subject": "alice chen", "relation": "works_at", "object": "Acme Corp", "confidence": 0.9}]}"#
+                .to_string(),
+        );
+        replies.push("no json".to_string());
+        let replies: Vec<&str> = replies.iter().map(String::as_str).collect();
+        let mut llm = scripted_replies(&replies);
+
+        let report = run_bench(&mut llm, "scripted").unwrap();
+        assert_eq!(report.speed.generated_tokens, 5);
+        assert!(report.speed.time_to_first_token_ms <= report.speed.total_ms);
+        assert_eq!(report.pii_cases[1].missed, ["Peter Novak"]);
+        assert!((report.pii_accuracy - 0.5).abs() < 1e-9);
+        assert_eq!(report.triple_cases[0].missed, ["Acme Corp -> Oslo"]);
+        // The last reply repeats: malformed output fails the case
+        assert!(report.triple_cases[1].error.is_some());
+        assert!((report.triple_accuracy - 0.25).abs() < 1e-9);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["model"], "scripted");
+        assert!(json["speed"]["tokens_per_second"].is_number());
+    }
+}
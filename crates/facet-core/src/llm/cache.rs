@@ -0,0 +1,343 @@
+//! Response cache
+//!
+//! `CachedLlm` wraps a backend and keeps its replies on disk, so asking
+//! the same thing again (e.g. summarizing a document a second time) costs
+//! nothing. Entries are addressed by a SHA-256 of the model, the prompt and
+//! the sampling settings, one file each, and are encrypted with the
+//! profile's key when one is given, like the rest of a profile. They expire
+//! after a TTL, and the least recently used are evicted once the cache
+//! outgrows its size limit.
+//!
+//! A hit returns the reply generated the first time, also for sampled
+//! calls whose output would otherwise vary; wrap only the backends whose
+//! calls should be reused.
+
+use super::{ChatMessage, GenerationParams, Llm, PromptTemplates};
+use anyhow::{Context, Result};
+use facet_types::profiles::crypto::{decrypt_file, encrypt_file, EncryptionKey};
+use facet_types::profiles::storage;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How long an entry is used unless configured otherwise: a week
+pub const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Size of all entries together unless configured otherwise: 64MB
+pub const DEFAULT_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Extension of entry files, so nothing else in the directory is evicted
+const ENTRY_EXTENSION: &str = "reply";
+
+/// Replies kept in one directory
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+    max_bytes: u64,
+    key: Option<EncryptionKey>,
+}
+
+impl ResponseCache {
+    /// A cache in `dir`, which is created if needed
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Cannot create LLM cache {}", dir.display()))?;
+        Ok(Self {
+            dir,
+            ttl: DEFAULT_TTL,
+            max_bytes: DEFAULT_MAX_BYTES,
+            key: None,
+        })
+    }
+
+    /// The cache of `username`'s profile, encrypted with the profile's key
+    pub fn for_user(username: &str, key: EncryptionKey, base_dir: Option<&Path>) -> Result<Self> {
+        Ok(Self::open(storage::get_llm_cache_dir(username, base_dir)?)?.with_key(key))
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Encrypts entries with `key` (AES-256-GCM)
+    pub fn with_key(mut self, key: EncryptionKey) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// The address of a reply: a hex SHA-256 of everything that shapes it
+    pub fn key(model: &str, prompt: &str, params: &GenerationParams) -> Result<String> {
+        let params = serde_json::to_vec(params)?;
+        let mut hasher = Sha256::new();
+        for part in [model.as_bytes(), prompt.as_bytes(), params.as_slice()] {
+            // Length-prefixed, so the parts can't run into each other
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+        Ok(hex::encode(hasher.finalize()))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, ENTRY_EXTENSION))
+    }
+
+    /// The reply stored under `key`, unless it has expired. An entry that
+    /// can't be read (e.g. written under another key) counts as a miss.
+    pub fn get(&self, key: &str) -> Option<String> {
+        let path = self.path(key);
+        let modified = std::fs::metadata(&path).ok()?.modified().ok()?;
+        if modified.elapsed().unwrap_or_default() > self.ttl {
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+        let bytes = std::fs::read(&path).ok()?;
+        let bytes = match &self.key {
+            Some(key) => decrypt_file(&bytes, key).ok()?,
+            None => bytes,
+        };
+        let reply = String::from_utf8(bytes).ok()?;
+        // Touched, so eviction goes by last use
+        if let Ok(file) = std::fs::File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(reply)
+    }
+
+    /// Stores `reply` under `key`, then evicts entries until the cache fits
+    /// its size limit again
+    pub fn insert(&self, key: &str, reply: &str) -> Result<()> {
+        let bytes = match &self.key {
+            Some(key) => encrypt_file(reply.as_bytes(), key)?,
+            None => reply.as_bytes().to_vec(),
+        };
+        std::fs::write(self.path(key), bytes)?;
+        self.evict()
+    }
+
+    /// Removes every entry
+    pub fn clear(&self) -> Result<()> {
+        for (path, _) in self.entries()? {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Entry files with their metadata
+    fn entries(&self) -> Result<Vec<(PathBuf, std::fs::Metadata)>> {
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == ENTRY_EXTENSION) {
+                entries.push((path.clone(), std::fs::metadata(&path)?));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Drops expired entries, then the least recently used ones until the
+    /// rest fit in `max_bytes`
+    fn evict(&self) -> Result<()> {
+        let mut entries = Vec::new();
+        for (path, metadata) in self.entries()? {
+            let modified = metadata.modified()?;
+            if modified.elapsed().unwrap_or_default() > self.ttl {
+                std::fs::remove_file(&path)?;
+            } else {
+                entries.push((modified, metadata.len(), path));
+            }
+        }
+        entries.sort();
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            std::fs::remove_file(&path)?;
+            total -= len;
+        }
+        Ok(())
+    }
+}
+
+/// A backend whose replies go through a `ResponseCache`. `model` names
+/// the backend's model in the cache keys, so one cache can serve several.
+pub struct CachedLlm<L> {
+    inner: L,
+    cache: ResponseCache,
+    model: String,
+}
+
+impl<L: Llm> CachedLlm<L> {
+    pub fn new(inner: L, cache: ResponseCache, model: impl Into<String>) -> Self {
+        Self {
+            inner,
+            cache,
+            model: model.into(),
+        }
+    }
+
+    pub fn cache(&self) -> &ResponseCache {
+        &self.cache
+    }
+
+    pub fn into_inner(self) -> L {
+        self.inner
+    }
+}
+
+impl<L: Llm> Llm for CachedLlm<L> {
+    /// A cached reply is streamed to `on_token` in one piece
+    fn generate_stream(
+        &mut self,
+        prompt: &str,
+        params: &GenerationParams,
+        on_token: &mut dyn FnMut(&str) -> Result<()>,
+    ) -> Result<String> {
+        let key = ResponseCache::key(&self.model, prompt, params)?;
+        if let Some(reply) = self.cache.get(&key) {
+            params.check_cancelled()?;
+            on_token(&reply)?;
+            return Ok(reply);
+        }
+        let reply = self.inner.generate_stream(prompt, params, on_token)?;
+        self.cache.insert(&key, &reply)?;
+        Ok(reply)
+    }
+
+    /// The prompts that miss go to the backend as one batch
+    fn generate_batch(
+        &mut self,
+        prompts: &[String],
+        params: &GenerationParams,
+    ) -> Result<Vec<String>> {
+        params.check_cancelled()?;
+        let keys = prompts
+            .iter()
+            .map(|prompt| ResponseCache::key(&self.model, prompt, params))
+            .collect::<Result<Vec<_>>>()?;
+        let mut replies: Vec<Option<String>> = keys.iter().map(|key| self.cache.get(key)).collect();
+        let misses: Vec<usize> = (0..prompts.len())
+            .filter(|&i| replies[i].is_none())
+            .collect();
+        if !misses.is_empty() {
+            let batch: Vec<String> = misses.iter().map(|&i| prompts[i].clone()).collect();
+            let generated = self.inner.generate_batch(&batch, params)?;
+            for (i, reply) in misses.into_iter().zip(generated) {
+                self.cache.insert(&keys[i], &reply)?;
+                replies[i] = Some(reply);
+            }
+        }
+        Ok(replies.into_iter().flatten().collect())
+    }
+
+    fn embed(&mut self, text: &str) -> Result<Vec<f32>> {
+        self.inner.embed(text)
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<usize> {
+        self.inner.count_tokens(text)
+    }
+
+    fn context_tokens(&self) -> usize {
+        self.inner.context_tokens()
+    }
+
+    fn format_prompt(&self, system: &str, user: &str) -> String {
+        self.inner.format_prompt(system, user)
+    }
+
+    fn format_chat(&self, messages: &[ChatMessage]) -> String {
+        self.inner.format_chat(messages)
+    }
+
+    fn templates(&self) -> &PromptTemplates {
+        self.inner.templates()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::scripted_replies;
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("facet-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_identical_calls_hit_the_cache() {
+        let dir = temp_dir("llm-cache");
+        let cache = ResponseCache::open(&dir)
+            .unwrap()
+            .with_key(EncryptionKey::from_bytes(vec![7; 32]));
+        let mut llm = CachedLlm::new(scripted_replies(&["first", "second"]), cache, "phi3");
+
+        let params = GenerationParams::precise(10);
+        assert_eq!(llm.generate("Summarize this", &params).unwrap(), "first");
+        assert_eq!(llm.generate("Summarize this", &params).unwrap(), "first");
+        // Another prompt or other settings are another entry
+        assert_eq!(llm.generate("Summarize that", &params).unwrap(), "second");
+        assert_eq!(
+            llm.generate("Summarize this", &GenerationParams::precise(20))
+                .unwrap(),
+            "second"
+        );
+
+        // Encrypted at rest, and unreadable under another key
+        let key = ResponseCache::key("phi3", "Summarize this", &params).unwrap();
+        let stored = std::fs::read(dir.join(format!("{}.reply", key))).unwrap();
+        assert!(!String::from_utf8_lossy(&stored).contains("first"));
+        let other = ResponseCache::open(&dir)
+            .unwrap()
+            .with_key(EncryptionKey::from_bytes(vec![8; 32]));
+        assert_eq!(other.get(&key), None);
+
+        // In a batch only the misses are generated, in order
+        let prompts = ["Summarize this", "Summarize those"].map(String::from);
+        assert_eq!(
+            llm.generate_batch(&prompts, &params).unwrap(),
+            ["first", "second"]
+        );
+
+        let inner = llm.into_inner();
+        assert_eq!(inner.prompts.len(), 4);
+        assert_eq!(inner.prompts[3], "Summarize those");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_entries_expire_and_are_evicted() {
+        let dir = temp_dir("llm-cache-limits");
+        let cache = ResponseCache::open(&dir).unwrap().with_max_bytes(10);
+        // Apart, so the modification times differ
+        let tick = || std::thread::sleep(Duration::from_millis(10));
+        cache.insert("a", "12345").unwrap();
+        tick();
+        cache.insert("b", "12345").unwrap();
+        tick();
+        assert_eq!(cache.get("a").as_deref(), Some("12345"));
+        tick();
+        // Over the limit: "b" was used least recently
+        cache.insert("c", "12345").unwrap();
+        assert_eq!(cache.get("b"), None);
+        assert!(cache.get("a").is_some() && cache.get("c").is_some());
+
+        let expired = ResponseCache::open(&dir).unwrap().with_ttl(Duration::ZERO);
+        tick();
+        assert_eq!(expired.get("a"), None);
+        assert!(!dir.join("a.reply").exists());
+
+        cache.clear().unwrap();
+        assert_eq!(cache.get("c"), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,438 @@
+//! Multi-turn conversations
+//!
+//! A `ChatSession` keeps the message history of one conversation and turns
+//! it into a prompt with the backend's chat template on every turn. When the
+//! history outgrows the model's context, whole turns are left out of the
+//! prompt before it is tokenized, so the model never sees half a message;
+//! the session's `TruncationStrategy` picks which. The system message and
+//! the newest message always stay, and the history itself keeps
+//! everything, for the chat view to show.
+
+use super::{templates, GenerationParams, Llm};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Context a session fits its prompt into unless told otherwise; that of
+/// `LocalLlm`
+pub const DEFAULT_CONTEXT_TOKENS: usize = 2048;
+
+/// Which turns a session leaves out of the prompt once the conversation
+/// no longer fits in the context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TruncationStrategy {
+    /// The oldest turns
+    #[default]
+    DropOldest,
+    /// The turns after the first `keep_first`, which often set up the
+    /// task; those go too only if nothing else fits next to them
+    TruncateMiddle { keep_first: usize },
+    /// The oldest turns, with a summary of them written by the model in
+    /// their place. `summary_tokens` is kept free in the context for it.
+    Summarize { summary_tokens: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::System,
+            content: content.into(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::User,
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::Assistant,
+            content: content.into(),
+        }
+    }
+}
+
+/// Phi-3's chat template, ending with the assistant's turn opened
+pub(crate) fn phi3_chat(messages: &[ChatMessage]) -> String {
+    let mut prompt = String::new();
+    for message in messages {
+        let tag = match message.role {
+            ChatRole::System => "<|system|>",
+            ChatRole::User => "<|user|>",
+            ChatRole::Assistant => "<|assistant|>",
+        };
+        prompt.push_str(&format!("{}\n{}<|end|>\n", tag, message.content));
+    }
+    prompt.push_str("<|assistant|>\n");
+    prompt
+}
+
+fn speaker(role: ChatRole) -> &'static str {
+    match role {
+        ChatRole::System => "System",
+        ChatRole::User => "User",
+        ChatRole::Assistant => "Assistant",
+    }
+}
+
+/// A plain-text transcript, for backends that apply the model's template
+/// themselves
+pub(crate) fn transcript(messages: &[ChatMessage]) -> String {
+    let mut prompt = String::new();
+    for message in messages {
+        prompt.push_str(&format!(
+            "{}: {}\n\n",
+            speaker(message.role),
+            message.content
+        ));
+    }
+    prompt.push_str("Assistant:");
+    prompt
+}
+
+/// One conversation with a model. The backend is passed to each `send`, so
+/// a session can move between threads, e.g. into an `LlmHandle::run` job.
+#[derive(Debug, Clone)]
+pub struct ChatSession {
+    system: Option<String>,
+    messages: Vec<ChatMessage>,
+    params: GenerationParams,
+    context_tokens: usize,
+    truncation: TruncationStrategy,
+    /// How many messages from the start of the history the summary covers,
+    /// and the summary, once `Summarize` has left turns out
+    summary: Option<(usize, String)>,
+}
+
+impl ChatSession {
+    pub fn new() -> Self {
+        Self {
+            system: None,
+            messages: Vec::new(),
+            params: GenerationParams::default(),
+            context_tokens: DEFAULT_CONTEXT_TOKENS,
+            truncation: TruncationStrategy::default(),
+            summary: None,
+        }
+    }
+
+    pub fn with_system(mut self, system: impl Into<String>) -> Self {
+        self.system = Some(system.into());
+        self
+    }
+
+    /// Sampling settings for every reply; `max_tokens` is also kept free
+    /// in the context for the reply
+    pub fn with_params(mut self, params: GenerationParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// The model's context length in tokens
+    pub fn with_context_tokens(mut self, context_tokens: usize) -> Self {
+        self.context_tokens = context_tokens;
+        self
+    }
+
+    /// Which turns to leave out once the conversation outgrows the context
+    pub fn with_truncation(mut self, truncation: TruncationStrategy) -> Self {
+        self.truncation = truncation;
+        self
+    }
+
+    /// The conversation so far, without the system message
+    pub fn messages(&self) -> &[ChatMessage] {
+        &self.messages
+    }
+
+    /// Forgets the conversation, keeping the system message
+    pub fn clear(&mut self) {
+        self.messages.clear();
+        self.summary = None;
+    }
+
+    /// Sends `message` and returns the reply. Both join the history only
+    /// if the reply succeeds, so a failed or cancelled turn can be retried.
+    pub fn send(&mut self, llm: &mut dyn Llm, message: &str) -> Result<String> {
+        self.send_stream(llm, message, &mut |_| Ok(()))
+    }
+
+    /// Like `send`, streaming the reply to `on_token` as `Llm::generate_stream`
+    /// does
+    pub fn send_stream(
+        &mut self,
+        llm: &mut dyn Llm,
+        message: &str,
+        on_token: &mut dyn FnMut(&str) -> Result<()>,
+    ) -> Result<String> {
+        let user = ChatMessage::user(message);
+        let prompt = self.prompt(llm, &user)?;
+        let reply = llm.generate_stream(&prompt, &self.params, on_token)?;
+        self.messages.push(user);
+        self.messages.push(ChatMessage::assistant(reply.clone()));
+        Ok(reply)
+    }
+
+    /// The prompt for the next turn: as much history as fits next to the
+    /// reply, leaving out whole turns as the truncation strategy says
+    fn prompt(&mut self, llm: &mut dyn Llm, user: &ChatMessage) -> Result<String> {
+        let budget = self.context_tokens.saturating_sub(self.params.max_tokens);
+        let end = self.messages.len();
+        match self.truncation {
+            TruncationStrategy::DropOldest => {
+                let start = self.fit(llm, 0, 0, user, budget)?.unwrap_or(end);
+                Ok(self.render(llm, None, 0, start, user))
+            }
+            TruncationStrategy::TruncateMiddle { keep_first } => {
+                let kept = (0..keep_first).fold(0, |i, _| self.next_turn(i));
+                let (kept, start) = match self.fit(llm, kept, kept, user, budget)? {
+                    Some(start) => (kept, start),
+                    None => (0, self.fit(llm, 0, 0, user, budget)?.unwrap_or(end)),
+                };
+                Ok(self.render(llm, None, kept, start, user))
+            }
+            TruncationStrategy::Summarize { summary_tokens } => {
+                // Turns already summarized stay left out
+                let covered = self.summary.as_ref().map_or(0, |(covered, _)| *covered);
+                let budget = budget.saturating_sub(summary_tokens);
+                let start = self.fit(llm, 0, covered, user, budget)?.unwrap_or(end);
+                if start == 0 {
+                    return Ok(self.render(llm, None, 0, 0, user));
+                }
+                let summary = self.summarize(llm, start, summary_tokens)?;
+                Ok(self.render(llm, Some(&summary), 0, start, user))
+            }
+        }
+    }
+
+    /// Where the next turn after the one at `from` begins
+    fn next_turn(&self, from: usize) -> usize {
+        // A turn is the user's message and the reply to it
+        let mut next = from + 1;
+        while self
+            .messages
+            .get(next)
+            .is_some_and(|m| m.role != ChatRole::User)
+        {
+            next += 1;
+        }
+        next.min(self.messages.len())
+    }
+
+    /// The first turn, from `from` on, where the history can resume after
+    /// the first `kept` messages so the prompt fits in `budget`; `None` if
+    /// not even the newest message alone fits
+    fn fit(
+        &self,
+        llm: &dyn Llm,
+        kept: usize,
+        from: usize,
+        user: &ChatMessage,
+        budget: usize,
+    ) -> Result<Option<usize>> {
+        let mut start = from;
+        loop {
+            if llm.count_tokens(&self.render(llm, None, kept, start, user))? <= budget {
+                return Ok(Some(start));
+            }
+            if start == self.messages.len() {
+                return Ok(None);
+            }
+            start = self.next_turn(start);
+        }
+    }
+
+    /// The system message, the summary, the first `kept` messages and
+    /// those from `start` on, then `user`, in the model's chat template
+    fn render(
+        &self,
+        llm: &dyn Llm,
+        summary: Option<&str>,
+        kept: usize,
+        start: usize,
+        user: &ChatMessage,
+    ) -> String {
+        let mut messages: Vec<ChatMessage> = self.system.iter().map(ChatMessage::system).collect();
+        if let Some(summary) = summary {
+            messages.push(ChatMessage::system(format!(
+                "Summary of the conversation so far: {}",
+                summary
+            )));
+        }
+        messages.extend_from_slice(&self.messages[..kept]);
+        messages.extend_from_slice(&self.messages[start..]);
+        messages.push(user.clone());
+        llm.format_chat(&messages)
+    }
+
+    /// A summary of the history before `end`. The one from an earlier turn
+    /// is extended with the turns left out since, rather than summarizing
+    /// the whole history again.
+    fn summarize(&mut self, llm: &mut dyn Llm, end: usize, max_tokens: usize) -> Result<String> {
+        let (covered, mut text) = self.summary.clone().unwrap_or_default();
+        if covered == end {
+            return Ok(text);
+        }
+        for message in &self.messages[covered..end] {
+            text.push_str(&format!(
+                "\n\n{}: {}",
+                speaker(message.role),
+                message.content
+            ));
+        }
+        let prompt = llm.template_prompt(templates::SYNTHESIZE, text.trim())?;
+        let params = GenerationParams {
+            cancel: self.params.cancel.clone(),
+            ..GenerationParams::precise(max_tokens)
+        };
+        let summary = llm.generate(&prompt, &params)?;
+        self.summary = Some((end, summary.clone()));
+        Ok(summary)
+    }
+}
+
+impl Default for ChatSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::scripted;
+    use super::*;
+
+    #[test]
+    fn test_session_keeps_history_in_the_prompt() {
+        let mut llm = scripted("Hi there ");
+        let mut session = ChatSession::new().with_system("Be brief.");
+        assert_eq!(session.send(&mut llm, "Hello").unwrap(), "Hi there");
+        assert_eq!(session.send(&mut llm, "Again").unwrap(), "Hi there");
+
+        assert_eq!(
+            llm.prompts[1],
+            "<|system|>\nBe brief.<|end|>\n<|user|>\nHello<|end|>\n<|assistant|>\nHi there<|end|>\n\
+             <|user|>\nAgain<|end|>\n<|assistant|>\n"
+        );
+        assert_eq!(
+            session.messages(),
+            [
+                ChatMessage::user("Hello"),
+                ChatMessage::assistant("Hi there"),
+                ChatMessage::user("Again"),
+                ChatMessage::assistant("Hi there"),
+            ]
+        );
+        session.clear();
+        assert!(session.messages().is_empty());
+    }
+
+    #[test]
+    fn test_session_drops_oldest_turns_to_fit() {
+        let mut llm = scripted("ok ");
+        // ScriptedLlm counts words: two per message plus the opened reply,
+        // so with two tokens kept for the reply there is room for one turn
+        let mut session = ChatSession::new()
+            .with_system("sys")
+            .with_params(GenerationParams::new(2))
+            .with_context_tokens(11);
+        for message in ["one", "two", "three", "four"] {
+            session.send(&mut llm, message).unwrap();
+        }
+
+        let last = llm.prompts.last().unwrap();
+        assert!(last.contains("sys") && last.contains("three") && last.contains("four"));
+        assert!(!last.contains("one") && !last.contains("two"));
+        // The history keeps everything
+        assert_eq!(session.messages().len(), 8);
+
+        // Too small for any history: the system and new message still go
+        let mut session = ChatSession::new().with_system("sys").with_context_tokens(1);
+        session.send(&mut llm, "first").unwrap();
+        session.send(&mut llm, "second").unwrap();
+        let last = llm.prompts.last().unwrap();
+        assert!(last.contains("sys") && last.contains("second") && !last.contains("first"));
+    }
+
+    #[test]
+    fn test_truncate_middle_keeps_the_first_turns() {
+        let mut llm = scripted("ok ");
+        // Room for the system and new message plus two turns
+        let mut session = ChatSession::new()
+            .with_system("sys")
+            .with_params(GenerationParams::new(2))
+            .with_context_tokens(15)
+            .with_truncation(TruncationStrategy::TruncateMiddle { keep_first: 1 });
+        for message in ["one", "two", "three", "four"] {
+            session.send(&mut llm, message).unwrap();
+        }
+        let last = llm.prompts.last().unwrap();
+        assert!(last.contains("one") && last.contains("three") && last.contains("four"));
+        assert!(!last.contains("two"));
+
+        // The first turns go too when they don't fit
+        let mut session = ChatSession::new()
+            .with_system("sys")
+            .with_context_tokens(1)
+            .with_truncation(TruncationStrategy::TruncateMiddle { keep_first: 1 });
+        session.send(&mut llm, "first").unwrap();
+        session.send(&mut llm, "second").unwrap();
+        let last = llm.prompts.last().unwrap();
+        assert!(last.contains("sys") && last.contains("second") && !last.contains("first"));
+    }
+
+    #[test]
+    fn test_summarize_replaces_left_out_turns() {
+        let mut llm = scripted("ok ");
+        // Room for the system and new message, one turn and the summary
+        let mut session = ChatSession::new()
+            .with_system("sys")
+            .with_params(GenerationParams::new(2))
+            .with_context_tokens(19)
+            .with_truncation(TruncationStrategy::Summarize { summary_tokens: 8 });
+        for message in ["one", "two", "three", "four"] {
+            session.send(&mut llm, message).unwrap();
+        }
+
+        // A summary when "three" left "one" out, then one extending it
+        // with "two" for "four"
+        assert_eq!(llm.prompts.len(), 6);
+        assert!(llm.prompts[2].contains("Summarize") && llm.prompts[2].contains("User: one"));
+        let extended = &llm.prompts[4];
+        assert!(extended.contains("ok\n\nUser: two\n\nAssistant: ok"));
+        assert!(!extended.contains("one"));
+
+        let last = llm.prompts.last().unwrap();
+        assert!(last.contains("Summary of the conversation so far: ok"));
+        assert!(last.contains("three") && last.contains("four") && !last.contains("two"));
+        assert_eq!(session.messages().len(), 8);
+    }
+
+    #[test]
+    fn test_failed_turn_is_not_recorded() {
+        let mut llm = scripted("never seen ");
+        let mut session = ChatSession::new();
+        let err = session
+            .send_stream(&mut llm, "Hello", &mut |_| anyhow::bail!("stopped"))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "stopped");
+        assert!(session.messages().is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+//! Answers with citations
+//!
+//! `synthesize_with_sources` answers a question from retrieved chunks and
+//! has the model cite them inline, `[1]`, `[2][3]`, by their number in the
+//! prompt (the `answer_with_sources` template). The numbers in the answer
+//! are mapped back to the chunks' node ids, so every claim can link to the
+//! document it came from. Chunks are taken best first, as many as fit in
+//! the context next to the answer.
+
+use super::{pack_chunks, templates, GenerationParams, Llm};
+use anyhow::Result;
+use facet_graph::Node;
+use serde::{Deserialize, Serialize};
+
+/// Tokens kept free for the answer
+const ANSWER_TOKENS: usize = 512;
+
+/// A retrieved chunk of text and the node it came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Source {
+    pub node_id: String,
+    pub text: String,
+}
+
+impl Source {
+    pub fn new(node_id: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            node_id: node_id.into(),
+            text: text.into(),
+        }
+    }
+
+    /// A node's `content`, or its `content_preview` if it has no content
+    pub fn from_node(node: &Node) -> Self {
+        let text = ["content", "content_preview"]
+            .iter()
+            .find_map(|key| node.properties.get(*key).and_then(|v| v.as_str()))
+            .unwrap_or("");
+        Self::new(node.id.clone(), text)
+    }
+}
+
+/// A `[n]` in the answer and the node it points to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Citation {
+    /// The number as it appears in the answer
+    pub number: usize,
+    pub node_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CitedAnswer {
+    /// The answer, with its `[n]` markers left in for rendering as links
+    pub answer: String,
+    /// Each source the answer cites, once, by number
+    pub citations: Vec<Citation>,
+}
+
+impl CitedAnswer {
+    /// The node `[number]` points to
+    pub fn node_id(&self, number: usize) -> Option<&str> {
+        self.citations
+            .iter()
+            .find(|citation| citation.number == number)
+            .map(|citation| citation.node_id.as_str())
+    }
+}
+
+/// An answer to `question` from `sources`, best first, citing them by
+/// number. Sources that don't fit in the context are left out, and
+/// numbers the model makes up that point to no source are dropped from
+/// the citations.
+pub fn synthesize_with_sources<L: Llm + ?Sized>(
+    llm: &mut L,
+    question: &str,
+    sources: &[Source],
+) -> Result<CitedAnswer> {
+    let overhead = llm.count_tokens(&prompt(llm, question, "")?)?;
+    let budget = llm
+        .context_tokens()
+        .saturating_sub(ANSWER_TOKENS + overhead);
+    // Numbered in the order they are offered, so the numbers stay the same
+    // whichever of them fit
+    let numbered: Vec<String> = sources
+        .iter()
+        .enumerate()
+        .map(|(i, source)| format!("[{}] {}\n", i + 1, source.text.trim()))
+        .collect();
+    let packed = pack_chunks(&numbered, budget, |chunk| llm.count_tokens(chunk))?;
+    if packed.is_empty() && !sources.is_empty() {
+        anyhow::bail!(
+            "None of the {} sources fit in the model's context",
+            sources.len()
+        );
+    }
+
+    let prompt = prompt(llm, question, packed.concat().trim_end())?;
+    let answer = llm.generate(&prompt, &GenerationParams::precise(ANSWER_TOKENS))?;
+    let citations = cited_numbers(&answer)
+        .into_iter()
+        .filter(|&number| {
+            number <= sources.len() && packed.contains(&numbered[number - 1].as_str())
+        })
+        .map(|number| Citation {
+            number,
+            node_id: sources[number - 1].node_id.clone(),
+        })
+        .collect();
+    Ok(CitedAnswer { answer, citations })
+}
+
+fn prompt<L: Llm + ?Sized>(llm: &L, question: &str, sources: &str) -> Result<String> {
+    let system = llm
+        .templates()
+        .get(templates::ANSWER_WITH_SOURCES)?
+        .render(&[("sources", sources)])?;
+    Ok(llm.format_prompt(&system, question))
+}
+
+/// The numbers cited in `answer`, once each in order of first use, from
+/// `[1]` as well as `[1, 2]`; zero is never a source
+fn cited_numbers(answer: &str) -> Vec<usize> {
+    let mut numbers = Vec::new();
+    let mut rest = answer;
+    while let Some(start) = rest.find('[') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find(']') else {
+            break;
+        };
+        let parsed: Option<Vec<usize>> = rest[..end]
+            .split(',')
+            .map(|n| n.trim().parse().ok().filter(|&n| n > 0))
+            .collect();
+        for number in parsed.into_iter().flatten() {
+            if !numbers.contains(&number) {
+                numbers.push(number);
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    numbers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::scripted;
+    use super::*;
+
+    #[test]
+    fn test_citations_map_to_node_ids() {
+        let mut llm = scripted("Ada moved to London [2] in 1833 [1, 2]. She liked tea [7].");
+        let sources = [
+            Source::new("note-1", "Ada met Babbage in 1833."),
+            Source::new("mail-2", "Ada lives in London."),
+        ];
+        let cited = synthesize_with_sources(&mut llm, "Where does Ada live?", &sources).unwrap();
+
+        assert_eq!(
+            cited.citations,
+            [
+                Citation {
+                    number: 2,
+                    node_id: "mail-2".to_string()
+                },
+                Citation {
+                    number: 1,
+                    node_id: "note-1".to_string()
+                },
+            ]
+        );
+        assert_eq!(cited.node_id(1), Some("note-1"));
+        // A number without a source is no citation
+        assert_eq!(cited.node_id(7), None);
+        assert!(llm.prompts[0].contains("[2] Ada lives in London."));
+        assert!(llm.prompts[0].contains("Where does Ada live?"));
+    }
+
+    #[test]
+    fn test_cited_numbers() {
+        assert_eq!(cited_numbers("a [3][1] b [3] c [1,2]"), [3, 1, 2]);
+        assert!(cited_numbers("see [note] or [0] or [1").is_empty());
+    }
+}
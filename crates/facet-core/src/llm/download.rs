@@ -0,0 +1,203 @@
+//! Fetching model files from the Hugging Face Hub
+//!
+//! The Phi-3 weights are several gigabytes, so the first load can take a
+//! long time. Downloads report their progress through a callback the CLI
+//! and the app turn into a progress bar, and offline mode only uses files
+//! already in the Hugging Face cache so an air-gapped or metered machine
+//! fails fast instead of hanging on the network.
+
+use anyhow::Result;
+use hf_hub::api::sync::{Api, ApiBuilder};
+use hf_hub::api::Progress;
+use hf_hub::{Cache, Repo, RepoType};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How often a download in progress is reported
+const REPORT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Where a model file download has got to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DownloadProgress {
+    /// Repository and file, e.g. `microsoft/Phi-3-mini-4k-instruct-gguf/Phi-3-mini-4k-instruct-q4.gguf`
+    pub file: String,
+    pub downloaded_bytes: u64,
+    pub total_bytes: u64,
+    /// Estimated time left, once anything has arrived
+    pub eta: Option<Duration>,
+}
+
+impl DownloadProgress {
+    pub fn is_done(&self) -> bool {
+        self.downloaded_bytes >= self.total_bytes
+    }
+}
+
+/// Resolves model files to local paths, downloading what isn't cached
+pub(crate) struct ModelFiles<'a> {
+    cache: Cache,
+    api: Option<Api>,
+    on_progress: &'a mut dyn FnMut(&DownloadProgress),
+}
+
+impl<'a> ModelFiles<'a> {
+    /// Uses the default Hugging Face cache. `offline` never touches the
+    /// network.
+    pub(crate) fn new(
+        offline: bool,
+        on_progress: &'a mut dyn FnMut(&DownloadProgress),
+    ) -> Result<Self> {
+        let cache = Cache::from_env();
+        let api = if offline {
+            None
+        } else {
+            Some(
+                ApiBuilder::from_cache(cache.clone())
+                    .with_progress(false)
+                    .build()?,
+            )
+        };
+        Ok(Self {
+            cache,
+            api,
+            on_progress,
+        })
+    }
+
+    pub(crate) fn get(&mut self, repo: &str, file: &str) -> Result<PathBuf> {
+        let hub_repo = Repo::new(repo.to_string(), RepoType::Model);
+        if let Some(path) = self.cache.repo(hub_repo.clone()).get(file) {
+            return Ok(path);
+        }
+        let Some(api) = &self.api else {
+            anyhow::bail!(
+                "{}/{} is not downloaded and offline mode is on; download it on a connected machine \
+                 into {} or turn offline mode off",
+                repo,
+                file,
+                self.cache.path().display()
+            );
+        };
+        let reporter = Reporter::new(format!("{}/{}", repo, file), &mut *self.on_progress);
+        Ok(api.repo(hub_repo).download_with_progress(file, reporter)?)
+    }
+}
+
+/// Turns hf-hub's progress calls into `DownloadProgress` reports
+struct Reporter<'a> {
+    progress: DownloadProgress,
+    started: Instant,
+    reported: Option<Instant>,
+    on_progress: &'a mut dyn FnMut(&DownloadProgress),
+}
+
+impl<'a> Reporter<'a> {
+    fn new(file: String, on_progress: &'a mut dyn FnMut(&DownloadProgress)) -> Self {
+        Self {
+            progress: DownloadProgress {
+                file,
+                downloaded_bytes: 0,
+                total_bytes: 0,
+                eta: None,
+            },
+            started: Instant::now(),
+            reported: None,
+            on_progress,
+        }
+    }
+
+    fn report(&mut self) {
+        let progress = &mut self.progress;
+        if progress.downloaded_bytes > 0 {
+            let left = progress
+                .total_bytes
+                .saturating_sub(progress.downloaded_bytes);
+            progress.eta = Some(
+                self.started
+                    .elapsed()
+                    .mul_f64(left as f64 / progress.downloaded_bytes as f64),
+            );
+        }
+        (self.on_progress)(progress);
+        self.reported = Some(Instant::now());
+    }
+}
+
+impl Progress for Reporter<'_> {
+    fn init(&mut self, size: usize, _filename: &str) {
+        self.progress.total_bytes = size as u64;
+        self.started = Instant::now();
+        self.report();
+    }
+
+    fn update(&mut self, size: usize) {
+        self.progress.downloaded_bytes += size as u64;
+        if self
+            .reported
+            .is_none_or(|reported| reported.elapsed() >= REPORT_INTERVAL)
+        {
+            self.report();
+        }
+    }
+
+    fn finish(&mut self) {
+        self.progress.downloaded_bytes = self.progress.total_bytes;
+        self.report();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reporter_reports_start_throttled_updates_and_end() {
+        let mut reports = Vec::new();
+        let mut on_progress = |progress: &DownloadProgress| reports.push(progress.clone());
+        let mut reporter = Reporter::new("org/model/weights.gguf".to_string(), &mut on_progress);
+        reporter.init(1000, "weights.gguf");
+        for _ in 0..10 {
+            reporter.update(50);
+        }
+        reporter.finish();
+
+        // Updates straight after the first report are held back
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].total_bytes, 1000);
+        assert_eq!(reports[0].downloaded_bytes, 0);
+        assert_eq!(reports[0].eta, None);
+        assert!(reports[1].is_done());
+        assert_eq!(reports[1].eta, Some(Duration::ZERO));
+        assert_eq!(reports[1].file, "org/model/weights.gguf");
+    }
+
+    #[test]
+    fn test_offline_uses_only_the_cache() {
+        let dir = std::env::temp_dir().join(format!("facet-hf-cache-{}", std::process::id()));
+        let repo = Repo::new("org/model".to_string(), RepoType::Model);
+        let cache = Cache::new(dir.clone());
+        cache.repo(repo.clone()).create_ref("abc123").unwrap();
+        let snapshot = dir
+            .join(repo.folder_name())
+            .join("snapshots")
+            .join("abc123");
+        std::fs::create_dir_all(&snapshot).unwrap();
+        std::fs::write(snapshot.join("config.json"), "{}").unwrap();
+
+        let mut on_progress = |_: &DownloadProgress| panic!("nothing is downloaded");
+        let mut files = ModelFiles {
+            cache,
+            api: None,
+            on_progress: &mut on_progress,
+        };
+        assert_eq!(
+            files.get("org/model", "config.json").unwrap(),
+            snapshot.join("config.json")
+        );
+        let err = files.get("org/model", "model.safetensors").unwrap_err();
+        assert!(err.to_string().contains("offline mode is on"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
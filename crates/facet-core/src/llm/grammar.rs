@@ -0,0 +1,344 @@
+//! JSON grammar for constrained decoding
+//!
+//! `JsonGrammar` recognises a JSON object or array one character at a time.
+//! With `GenerationParams::json` set, `LocalLlm` asks it which vocabulary
+//! tokens could continue the output and masks out every other one, so the
+//! result always parses instead of relying on the model following the
+//! prompt.
+
+/// An open object or array
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Container {
+    Object,
+    Array,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    None,
+    Backslash,
+    /// Hex digits of a `\u` escape still to come
+    Unicode(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Number {
+    Minus,
+    Zero,
+    Int,
+    Dot,
+    Fraction,
+    Exponent,
+    ExponentSign,
+    ExponentDigits,
+}
+
+impl Number {
+    /// Whether the number may end here
+    fn is_complete(self) -> bool {
+        matches!(
+            self,
+            Number::Zero | Number::Int | Number::Fraction | Number::ExponentDigits
+        )
+    }
+
+    fn next(self, c: char) -> Option<Number> {
+        match (self, c) {
+            (Number::Minus, '0') => Some(Number::Zero),
+            (Number::Minus, '1'..='9') => Some(Number::Int),
+            (Number::Int, '0'..='9') => Some(Number::Int),
+            (Number::Zero | Number::Int, '.') => Some(Number::Dot),
+            (Number::Dot | Number::Fraction, '0'..='9') => Some(Number::Fraction),
+            (Number::Zero | Number::Int | Number::Fraction, 'e' | 'E') => Some(Number::Exponent),
+            (Number::Exponent, '+' | '-') => Some(Number::ExponentSign),
+            (Number::Exponent | Number::ExponentSign | Number::ExponentDigits, '0'..='9') => {
+                Some(Number::ExponentDigits)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Before the top-level object or array
+    Start,
+    /// After `:` or `,` in an array
+    Value,
+    /// After `[`: a value or `]`
+    ArrayFirst,
+    /// After `{`: a key or `}`
+    ObjectFirst,
+    /// After `,` in an object
+    Key,
+    String {
+        key: bool,
+        escape: Escape,
+    },
+    /// After a key
+    Colon,
+    /// After a value: `,` or the closing bracket
+    AfterValue,
+    Number(Number),
+    /// The rest of `true`, `false` or `null`
+    Literal(&'static str),
+    /// The top-level value is closed
+    Done,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct JsonGrammar {
+    stack: Vec<Container>,
+    state: State,
+}
+
+impl JsonGrammar {
+    pub(crate) fn new() -> Self {
+        Self {
+            stack: Vec::new(),
+            state: State::Start,
+        }
+    }
+
+    /// Whether a whole JSON value has been read
+    pub(crate) fn is_complete(&self) -> bool {
+        self.state == State::Done
+    }
+
+    /// Whether `text` could come next; leaves the grammar as it is
+    pub(crate) fn accepts(&self, text: &str) -> bool {
+        !text.is_empty() && self.clone().feed_str(text)
+    }
+
+    /// Reads `text`, returning false (and leaving the grammar in an
+    /// unspecified state) if it isn't valid here
+    pub(crate) fn feed_str(&mut self, text: &str) -> bool {
+        text.chars().all(|c| self.feed(c))
+    }
+
+    /// The shortest text that closes everything still open, for output
+    /// cut short by the token limit
+    pub(crate) fn completion(&self) -> String {
+        let mut text = match self.state {
+            State::Start => "{".to_string(),
+            State::Value => "null".to_string(),
+            State::Key => "\"\": null".to_string(),
+            State::String { key, escape } => {
+                let escape = match escape {
+                    Escape::None => String::new(),
+                    Escape::Backslash => "\\".to_string(),
+                    Escape::Unicode(left) => "0".repeat(left as usize),
+                };
+                let value = if key { ": null" } else { "" };
+                format!("{}\"{}", escape, value)
+            }
+            State::Colon => ": null".to_string(),
+            State::Number(number) if !number.is_complete() => "0".to_string(),
+            State::Literal(rest) => rest.to_string(),
+            _ => String::new(),
+        };
+        let start = self.state == State::Start;
+        for container in self
+            .stack
+            .iter()
+            .rev()
+            .chain(start.then_some(&Container::Object))
+        {
+            text.push(match container {
+                Container::Object => '}',
+                Container::Array => ']',
+            });
+        }
+        text
+    }
+
+    pub(crate) fn feed(&mut self, c: char) -> bool {
+        let whitespace = matches!(c, ' ' | '\t' | '\n' | '\r');
+        match self.state {
+            State::Start => match c {
+                '{' => self.open(Container::Object),
+                '[' => self.open(Container::Array),
+                _ => whitespace,
+            },
+            State::Value | State::ArrayFirst if whitespace => true,
+            State::ArrayFirst if c == ']' => self.close(),
+            State::Value | State::ArrayFirst => self.begin_value(c),
+            State::ObjectFirst if c == '}' => self.close(),
+            State::ObjectFirst | State::Key => match c {
+                '"' => self.enter(State::String {
+                    key: true,
+                    escape: Escape::None,
+                }),
+                _ => whitespace,
+            },
+            State::String { key, escape } => self.string(key, escape, c),
+            State::Colon => match c {
+                ':' => self.enter(State::Value),
+                _ => whitespace,
+            },
+            State::AfterValue => match (c, self.stack.last()) {
+                (',', Some(Container::Object)) => self.enter(State::Key),
+                (',', Some(Container::Array)) => self.enter(State::Value),
+                ('}', Some(Container::Object)) | (']', Some(Container::Array)) => self.close(),
+                _ => whitespace,
+            },
+            State::Number(number) => match number.next(c) {
+                Some(next) => self.enter(State::Number(next)),
+                None if number.is_complete() => {
+                    self.state = State::AfterValue;
+                    self.feed(c)
+                }
+                None => false,
+            },
+            State::Literal(rest) => match rest.strip_prefix(c) {
+                Some("") => self.enter(State::AfterValue),
+                Some(rest) => self.enter(State::Literal(rest)),
+                None => false,
+            },
+            State::Done => whitespace,
+        }
+    }
+
+    fn enter(&mut self, state: State) -> bool {
+        self.state = state;
+        true
+    }
+
+    fn open(&mut self, container: Container) -> bool {
+        self.stack.push(container);
+        self.enter(match container {
+            Container::Object => State::ObjectFirst,
+            Container::Array => State::ArrayFirst,
+        })
+    }
+
+    fn close(&mut self) -> bool {
+        self.stack.pop();
+        self.enter(if self.stack.is_empty() {
+            State::Done
+        } else {
+            State::AfterValue
+        })
+    }
+
+    fn begin_value(&mut self, c: char) -> bool {
+        match c {
+            '{' => self.open(Container::Object),
+            '[' => self.open(Container::Array),
+            '"' => self.enter(State::String {
+                key: false,
+                escape: Escape::None,
+            }),
+            '-' => self.enter(State::Number(Number::Minus)),
+            '0' => self.enter(State::Number(Number::Zero)),
+            '1'..='9' => self.enter(State::Number(Number::Int)),
+            't' => self.enter(State::Literal("rue")),
+            'f' => self.enter(State::Literal("alse")),
+            'n' => self.enter(State::Literal("ull")),
+            _ => false,
+        }
+    }
+
+    fn string(&mut self, key: bool, escape: Escape, c: char) -> bool {
+        let escape = match (escape, c) {
+            (Escape::None, '"') => {
+                return self.enter(if key { State::Colon } else { State::AfterValue });
+            }
+            (Escape::None, '\\') => Escape::Backslash,
+            (Escape::None, c) if c < ' ' => return false,
+            (Escape::None, _) => Escape::None,
+            (Escape::Backslash, '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't') => Escape::None,
+            (Escape::Backslash, 'u') => Escape::Unicode(4),
+            (Escape::Unicode(left), c) if c.is_ascii_hexdigit() => match left {
+                1 => Escape::None,
+                _ => Escape::Unicode(left - 1),
+            },
+            _ => return false,
+        };
+        self.enter(State::String { key, escape })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid(text: &str) -> bool {
+        let mut grammar = JsonGrammar::new();
+        grammar.feed_str(text) && grammar.is_complete()
+    }
+
+    #[test]
+    fn test_grammar_accepts_json() {
+        for text in [
+            "{}",
+            " [ ] ",
+            r#"{"a": 1, "b": [true, false, null], "c": {"d": "e\"\u00e9\n"}}"#,
+            "[-0.5e+3, 10, 0, 1.25E2, \"\", []]",
+            r#"{"redacted_text": "Call [PHONE_1]", "pii": {"[PHONE_1]": "555-0100"}}"#,
+        ] {
+            assert!(valid(text), "{text}");
+            assert!(serde_json::from_str::<serde_json::Value>(text).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_grammar_rejects_invalid_json() {
+        for text in [
+            "\"top-level string\"",
+            "{a: 1}",
+            "{\"a\" 1}",
+            "[1,]",
+            "[01]",
+            "[1.]",
+            "{\"a\": tru}",
+            "{\"a\": \"\\x\"}",
+            "{\"a\": \"line\nbreak\"}",
+            "{} {}",
+            "[}",
+        ] {
+            assert!(!valid(text), "{text}");
+        }
+    }
+
+    #[test]
+    fn test_completion_closes_cut_off_output() {
+        for partial in [
+            "",
+            "{",
+            "{\"ke",
+            "{\"key\"",
+            "{\"key\": ",
+            "{\"key\": [1, {\"a\": \"b\\",
+            "{\"key\": \"\\u00",
+            "[-",
+            "[1.",
+            "[2e+",
+            "[tr",
+            "[{}, ",
+            "{\"a\": 1, ",
+        ] {
+            let mut grammar = JsonGrammar::new();
+            assert!(grammar.feed_str(partial), "{partial}");
+            let text = format!("{}{}", partial, grammar.completion());
+            assert!(valid(&text), "{text}");
+            assert!(
+                serde_json::from_str::<serde_json::Value>(&text).is_ok(),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_accepts_checks_without_consuming() {
+        let mut grammar = JsonGrammar::new();
+        assert!(grammar.feed_str("{\"pii\": "));
+        assert!(grammar.accepts("{"));
+        assert!(grammar.accepts("\"x"));
+        assert!(!grammar.accepts("}"));
+        assert!(!grammar.accepts(""));
+        assert!(grammar.feed_str("{}}"));
+        assert!(grammar.is_complete());
+        assert!(!grammar.accepts(","));
+    }
+}
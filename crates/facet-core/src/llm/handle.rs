@@ -0,0 +1,265 @@
+//! Async access to a blocking `Llm`
+//!
+//! Inference is CPU/GPU bound and the backends block while they run, which
+//! would stall the tokio runtime of the server or the Tauri app. An
+//! `LlmHandle` moves the backend onto a dedicated worker thread and queues
+//! requests to it over a channel: callers `.await` their turn and never
+//! block, and requests run one at a time in the order they were made.
+
+use super::{ChatMessage, GenerationParams, Llm};
+use anyhow::{Context, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+/// Given the backend, or why there is none: a lazily loaded one can fail
+/// to load (see `LlmManager`)
+type Job = Box<dyn FnOnce(Result<&mut dyn Llm>) + Send>;
+
+/// Cheap to clone; the worker stops once every handle is dropped and the
+/// queued requests have run
+#[derive(Clone)]
+pub struct LlmHandle {
+    jobs: mpsc::UnboundedSender<Job>,
+}
+
+impl LlmHandle {
+    /// Moves `llm` onto a new worker thread
+    pub fn spawn<L: Llm + 'static>(llm: L) -> Result<Self> {
+        let (jobs, receiver) = mpsc::unbounded_channel();
+        thread::Builder::new()
+            .name("llm-worker".to_string())
+            .spawn(move || run_worker(llm, receiver))?;
+        Ok(Self { jobs })
+    }
+
+    /// Builds the backend on the worker thread, so loading a model doesn't
+    /// block the caller either
+    pub async fn load<L, F>(load: F) -> Result<Self>
+    where
+        L: Llm + 'static,
+        F: FnOnce() -> Result<L> + Send + 'static,
+    {
+        let (jobs, receiver) = mpsc::unbounded_channel();
+        let (loaded, ready) = oneshot::channel();
+        thread::Builder::new()
+            .name("llm-worker".to_string())
+            .spawn(move || match load() {
+                Ok(llm) => {
+                    let _ = loaded.send(Ok(()));
+                    run_worker(llm, receiver);
+                }
+                Err(e) => {
+                    let _ = loaded.send(Err(e));
+                }
+            })?;
+        ready.await.context("LLM worker stopped while loading")??;
+        Ok(Self { jobs })
+    }
+
+    /// A handle whose worker only loads the backend for the first request,
+    /// drops it once no request has come for `idle`, and loads it again
+    /// for the next. `loaded` tracks whether it is in memory.
+    pub(super) fn lazy<L, F>(load: F, idle: Duration, loaded: Arc<AtomicBool>) -> Result<Self>
+    where
+        L: Llm + 'static,
+        F: Fn() -> Result<L> + Send + 'static,
+    {
+        let (jobs, receiver) = mpsc::unbounded_channel();
+        thread::Builder::new()
+            .name("llm-worker".to_string())
+            .spawn(move || run_lazy_worker(load, idle, loaded, receiver))?;
+        Ok(Self { jobs })
+    }
+
+    /// Runs `job` against the backend once the requests queued before it
+    /// are done. Dropping the returned future before then skips the job;
+    /// to stop one that is running, cancel its `GenerationParams::cancel`.
+    pub async fn run<R, F>(&self, job: F) -> Result<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut dyn Llm) -> Result<R> + Send + 'static,
+    {
+        let (reply, response) = oneshot::channel();
+        self.jobs
+            .send(Box::new(move |llm: Result<&mut dyn Llm>| {
+                // The caller stopped waiting while this was queued
+                if reply.is_closed() {
+                    return;
+                }
+                let _ = reply.send(llm.and_then(job));
+            }))
+            .ok()
+            .context("LLM worker has stopped")?;
+        response.await.context("LLM worker has stopped")?
+    }
+
+    pub async fn generate(&self, prompt: &str, params: GenerationParams) -> Result<String> {
+        let prompt = prompt.to_string();
+        self.run(move |llm| llm.generate(&prompt, &params)).await
+    }
+
+    /// Like `generate`, sending each piece of text to `tokens` as it is
+    /// produced. Dropping the receiver stops generation early.
+    pub async fn generate_stream(
+        &self,
+        prompt: &str,
+        params: GenerationParams,
+        tokens: mpsc::UnboundedSender<String>,
+    ) -> Result<String> {
+        let prompt = prompt.to_string();
+        self.run(move |llm| {
+            llm.generate_stream(&prompt, &params, &mut |piece| {
+                tokens
+                    .send(piece.to_string())
+                    .ok()
+                    .context("Token receiver was dropped")
+            })
+        })
+        .await
+    }
+
+    pub async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let text = text.to_string();
+        self.run(move |llm| llm.embed(&text)).await
+    }
+
+    pub async fn count_tokens(&self, text: &str) -> Result<usize> {
+        let text = text.to_string();
+        self.run(move |llm| llm.count_tokens(&text)).await
+    }
+
+    pub async fn fits_in_context(&self, messages: Vec<ChatMessage>, max: usize) -> Result<bool> {
+        self.run(move |llm| llm.fits_in_context(&messages, max))
+            .await
+    }
+
+    pub async fn synthesize(&self, text: &str) -> Result<String> {
+        let text = text.to_string();
+        self.run(move |llm| llm.synthesize(&text)).await
+    }
+
+    pub async fn optimize_prompt(&self, query: &str) -> Result<String> {
+        let query = query.to_string();
+        self.run(move |llm| llm.optimize_prompt(&query)).await
+    }
+}
+
+fn run_worker<L: Llm>(mut llm: L, mut jobs: mpsc::UnboundedReceiver<Job>) {
+    while let Some(job) = jobs.blocking_recv() {
+        job(Ok(&mut llm));
+    }
+}
+
+fn run_lazy_worker<L, F>(
+    load: F,
+    idle: Duration,
+    loaded: Arc<AtomicBool>,
+    mut jobs: mpsc::UnboundedReceiver<Job>,
+) where
+    L: Llm,
+    F: Fn() -> Result<L>,
+{
+    // Only for waiting on the next job with a timeout
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            while let Some(job) = jobs.blocking_recv() {
+                job(Err(anyhow::anyhow!("Cannot start the LLM worker: {}", e)));
+            }
+            return;
+        }
+    };
+    let mut llm: Option<L> = None;
+    loop {
+        let job = if llm.is_some() {
+            // Built inside the runtime, whose timer it needs
+            match runtime.block_on(async { tokio::time::timeout(idle, jobs.recv()).await }) {
+                Ok(job) => job,
+                Err(_) => {
+                    llm = None;
+                    loaded.store(false, Ordering::SeqCst);
+                    continue;
+                }
+            }
+        } else {
+            jobs.blocking_recv()
+        };
+        let Some(job) = job else {
+            break;
+        };
+        if llm.is_none() {
+            // A failed load fails this job only; the next one tries again
+            match load() {
+                Ok(loaded_llm) => {
+                    llm = Some(loaded_llm);
+                    loaded.store(true, Ordering::SeqCst);
+                }
+                Err(e) => {
+                    job(Err(e.context("Cannot load the LLM")));
+                    continue;
+                }
+            }
+        }
+        if let Some(llm) = &mut llm {
+            job(Ok(llm));
+        }
+    }
+    loaded.store(false, Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::{scripted, ScriptedLlm};
+    use super::*;
+
+    #[tokio::test]
+    async fn test_requests_run_on_the_worker_in_order() {
+        let handle = LlmHandle::spawn(scripted("Queued reply ")).unwrap();
+
+        let thread = handle
+            .run(|_| Ok(thread::current().name().map(str::to_string)))
+            .await
+            .unwrap();
+        assert_eq!(thread.as_deref(), Some("llm-worker"));
+
+        // Concurrent callers queue up and all get their answer
+        let (a, b, c) = tokio::join!(
+            handle.generate("one", GenerationParams::new(10)),
+            handle.generate("two", GenerationParams::new(10)),
+            handle.count_tokens("three words here"),
+        );
+        assert_eq!(a.unwrap(), "Queued reply");
+        assert_eq!(b.unwrap(), "Queued reply");
+        assert_eq!(c.unwrap(), 3);
+
+        let (tokens, mut received) = mpsc::unbounded_channel();
+        let output = handle
+            .generate_stream("hi", GenerationParams::new(10), tokens)
+            .await
+            .unwrap();
+        assert_eq!(output, "Queued reply");
+        let mut pieces = Vec::new();
+        while let Some(piece) = received.recv().await {
+            pieces.push(piece);
+        }
+        assert_eq!(pieces, ["Queued ", "reply "]);
+
+        assert!(handle.embed("text").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_reports_errors() {
+        let handle = LlmHandle::load(|| Ok(scripted("Loaded"))).await.unwrap();
+        assert_eq!(handle.clone().synthesize("text").await.unwrap(), "Loaded");
+
+        let failed =
+            LlmHandle::load(|| -> Result<ScriptedLlm> { anyhow::bail!("no weights") }).await;
+        assert_eq!(failed.err().unwrap().to_string(), "no weights");
+    }
+}
@@ -1,127 +1,1186 @@
-use anyhow::{Error as E, Result};
-use candle_core::{DType, Device, Tensor};
+use super::download::{DownloadProgress, ModelFiles};
+use super::grammar::JsonGrammar;
+use super::lora::LoraAdapter;
+use super::{GenerationParams, Llm, LlmError, PromptTemplates};
+use anyhow::{bail, Context, Error as E, Result};
+use candle_core::quantized::gguf_file;
+use candle_core::{DType, Device, DeviceLocation, Tensor};
 use candle_nn::VarBuilder;
-use candle_transformers::generation::LogitsProcessor;
+use candle_transformers::generation::{LogitsProcessor, Sampling};
 use candle_transformers::models::phi3::{Config as Phi3Config, Model as Phi3};
-use hf_hub::{api::sync::Api, Repo, RepoType};
+use candle_transformers::models::quantized_phi3::ModelWeights as QuantizedPhi3;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 use tokenizers::Tokenizer;
 
+/// Tokens of context the model attends to when generating
+const CONTEXT_WINDOW: usize = 2048;
+
+/// Recent tokens the repetition penalty looks at
+const REPEAT_LAST_N: usize = 64;
+
+/// Prompts `generate_batch` tokenizes ahead of generation
+const TOKENIZE_AHEAD: usize = 4;
+
+const MODEL_REPO: &str = "microsoft/Phi-3-mini-4k-instruct";
+
+/// Memory the F32 model needs: ~15GB of weights plus working space
+const FULL_PRECISION_BYTES: u64 = 16 * 1024 * 1024 * 1024;
+
+/// Which Phi-3 weights `LocalLlm` loads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    /// `Full` if there is enough free memory for it, otherwise `Q4`
+    #[default]
+    Auto,
+    /// F32 safetensors, ~15GB of RAM
+    Full,
+    /// 4-bit GGUF, ~2.5GB of RAM
+    Q4,
+    /// 5-bit GGUF, ~3GB of RAM
+    Q5,
+}
+
+impl Precision {
+    /// Resolves `Auto` given the free memory in bytes, if known. When it
+    /// isn't known the full model is tried, as before quantized support.
+    pub fn resolve(self, available_memory: Option<u64>) -> Precision {
+        match (self, available_memory) {
+            (Precision::Auto, Some(bytes)) if bytes < FULL_PRECISION_BYTES => Precision::Q4,
+            (Precision::Auto, _) => Precision::Full,
+            (precision, _) => precision,
+        }
+    }
+
+    /// Hugging Face repo and file of the GGUF weights
+    fn gguf(self) -> Option<(&'static str, &'static str)> {
+        match self {
+            Precision::Q4 => Some(("microsoft/Phi-3-mini-4k-instruct-gguf", "Phi-3-mini-4k-instruct-q4.gguf")),
+            Precision::Q5 => Some(("bartowski/Phi-3-mini-4k-instruct-GGUF", "Phi-3-mini-4k-instruct-Q5_K_M.gguf")),
+            Precision::Auto | Precision::Full => None,
+        }
+    }
+}
+
+/// Where `LocalLlm` runs inference
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceChoice {
+    /// The first CUDA GPU (with the `cuda` feature), then Metal, then CPU
+    #[default]
+    Auto,
+    Cpu,
+    /// CUDA GPU by ordinal; needs the `cuda` feature
+    Cuda(usize),
+    /// Metal GPU by ordinal
+    Metal(usize),
+}
+
+impl DeviceChoice {
+    /// Opens the device. An explicit choice that isn't available is an
+    /// error rather than a silent fall back to the CPU.
+    fn open(self) -> Result<Device> {
+        match self {
+            DeviceChoice::Auto => {
+                #[cfg(feature = "cuda")]
+                if let Ok(device) = Device::new_cuda(0) {
+                    return Ok(device);
+                }
+                Ok(Device::new_metal(0).unwrap_or(Device::Cpu))
+            }
+            DeviceChoice::Cpu => Ok(Device::Cpu),
+            DeviceChoice::Cuda(ordinal) => {
+                if !cfg!(feature = "cuda") {
+                    anyhow::bail!("CUDA device {} requested, but facet-core was built without the `cuda` feature", ordinal);
+                }
+                Ok(Device::new_cuda(ordinal)?)
+            }
+            DeviceChoice::Metal(ordinal) => Ok(Device::new_metal(ordinal)?),
+        }
+    }
+}
+
+/// The device a `LocalLlm` was loaded on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    /// Never `Auto`
+    pub device: DeviceChoice,
+    /// Free memory in bytes: VRAM on CUDA, system memory on Metal
+    /// (unified) and the CPU. `None` where it can't be read.
+    pub free_memory: Option<u64>,
+}
+
+impl DeviceInfo {
+    fn of(device: &Device) -> Self {
+        match device.location() {
+            DeviceLocation::Cpu => DeviceInfo {
+                device: DeviceChoice::Cpu,
+                free_memory: available_memory(),
+            },
+            DeviceLocation::Cuda { gpu_id } => DeviceInfo {
+                device: DeviceChoice::Cuda(gpu_id),
+                free_memory: cuda_free_memory(device),
+            },
+            DeviceLocation::Metal { gpu_id } => DeviceInfo {
+                device: DeviceChoice::Metal(gpu_id),
+                free_memory: available_memory(),
+            },
+        }
+    }
+}
+
+/// Limits that abort a generation which has stalled, e.g. on a CPU too
+/// slow for the model, instead of holding up every request behind it.
+/// Checked between tokens; a generation that breaks one fails with
+/// `LlmError::TimedOut`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Watchdog {
+    /// Longest one generation may take, prompt processing included
+    pub timeout_secs: Option<f64>,
+    /// Slowest acceptable output rate once `grace_secs` of generating
+    /// have passed
+    pub min_tokens_per_second: Option<f64>,
+    pub grace_secs: f64,
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self {
+            timeout_secs: Some(300.0),
+            min_tokens_per_second: Some(0.5),
+            grace_secs: 10.0,
+        }
+    }
+}
+
+impl Watchdog {
+    /// Never aborts
+    pub fn disabled() -> Self {
+        Self {
+            timeout_secs: None,
+            min_tokens_per_second: None,
+            grace_secs: 0.0,
+        }
+    }
+
+    /// `started` is when the call began, `decoding` when the first token
+    /// came out and `tokens` how many have since
+    fn check(&self, started: Instant, decoding: Option<Instant>, tokens: usize) -> Result<(), LlmError> {
+        let elapsed = started.elapsed();
+        if self.timeout_secs.is_some_and(|timeout| elapsed.as_secs_f64() > timeout) {
+            return Err(LlmError::TimedOut { elapsed, tokens });
+        }
+        if let (Some(min_rate), Some(decoding)) = (self.min_tokens_per_second, decoding) {
+            let secs = decoding.elapsed().as_secs_f64();
+            if secs > self.grace_secs && (tokens as f64) / secs < min_rate {
+                return Err(LlmError::TimedOut { elapsed, tokens });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Settings for loading a `LocalLlm`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LocalLlmConfig {
+    #[serde(default)]
+    pub precision: Precision,
+    #[serde(default)]
+    pub device: DeviceChoice,
+    #[serde(default)]
+    pub watchdog: Watchdog,
+    /// Only use weights already downloaded; loading fails if they aren't
+    #[serde(default)]
+    pub offline: bool,
+    /// Load the model from this directory instead of the Hugging Face Hub
+    /// (see `ModelDir`); `precision` is then whatever the directory holds
+    #[serde(default)]
+    pub model_dir: Option<PathBuf>,
+    /// A LoRA adapter (see `LoraAdapter`) merged into the weights as they
+    /// load. Needs the full-precision weights, so `Auto` loads those.
+    #[serde(default)]
+    pub lora: Option<PathBuf>,
+}
+
+/// A model kept in a local directory, for machines that can't reach the
+/// Hugging Face Hub: `tokenizer.json` next to either one `.gguf` file or
+/// `config.json` and the `.safetensors` shards
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelDir {
+    pub tokenizer: PathBuf,
+    pub weights: ModelWeights,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelWeights {
+    Gguf(PathBuf),
+    /// Shards in name order
+    Safetensors { config: PathBuf, shards: Vec<PathBuf> },
+}
+
+impl ModelDir {
+    /// Finds the model files in `dir`, failing if any are missing
+    pub fn scan(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir).with_context(|| format!("Cannot read model directory {}", dir.display()))? {
+            let path = entry?.path();
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+        files.sort();
+        let with_extension = |extension: &str| -> Vec<PathBuf> {
+            files.iter().filter(|path| path.extension().is_some_and(|e| e == extension)).cloned().collect()
+        };
+
+        let tokenizer = dir.join("tokenizer.json");
+        if !files.contains(&tokenizer) {
+            anyhow::bail!("No tokenizer.json in {}", dir.display());
+        }
+        let ggufs = with_extension("gguf");
+        let weights = match ggufs.as_slice() {
+            [gguf] => ModelWeights::Gguf(gguf.clone()),
+            [] => {
+                let config = dir.join("config.json");
+                let shards = with_extension("safetensors");
+                if !files.contains(&config) || shards.is_empty() {
+                    anyhow::bail!(
+                        "No model weights in {}: expected a .gguf file, or config.json and .safetensors files",
+                        dir.display()
+                    );
+                }
+                ModelWeights::Safetensors { config, shards }
+            }
+            _ => anyhow::bail!("Several .gguf files in {}; keep only the one to use", dir.display()),
+        };
+        Ok(Self { tokenizer, weights })
+    }
+
+    /// `Full` for safetensors; for GGUF, the quantization its name says
+    pub fn precision(&self) -> Precision {
+        match &self.weights {
+            ModelWeights::Safetensors { .. } => Precision::Full,
+            ModelWeights::Gguf(path) => {
+                let name = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+                if name.contains("q5") {
+                    Precision::Q5
+                } else {
+                    Precision::Q4
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "cuda")]
+fn cuda_free_memory(device: &Device) -> Option<u64> {
+    let Device::Cuda(cuda) = device else {
+        return None;
+    };
+    let (free, _total) = cuda.cuda_stream().context().mem_get_info().ok()?;
+    Some(free as u64)
+}
+
+#[cfg(not(feature = "cuda"))]
+fn cuda_free_memory(_device: &Device) -> Option<u64> {
+    None
+}
+
+/// Free memory in bytes, where the platform reports it
+fn available_memory() -> Option<u64> {
+    if cfg!(target_os = "linux") {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+        parse_meminfo(&meminfo)
+    } else if cfg!(target_os = "macos") {
+        // Unified memory: the total is the best cheap estimate
+        let output = std::process::Command::new("sysctl").args(["-n", "hw.memsize"]).output().ok()?;
+        String::from_utf8(output.stdout).ok()?.trim().parse().ok()
+    } else {
+        None
+    }
+}
+
+/// `MemAvailable` from /proc/meminfo, in bytes
+fn parse_meminfo(meminfo: &str) -> Option<u64> {
+    let line = meminfo.lines().find(|line| line.starts_with("MemAvailable:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// The loaded weights, full precision or quantized
+enum Model {
+    Full(Phi3),
+    Quantized(QuantizedPhi3),
+}
+
+impl Model {
+    fn load_gguf(path: &Path, device: &Device) -> Result<Self> {
+        let mut file = std::fs::File::open(path)?;
+        let content = gguf_file::Content::read(&mut file).map_err(|e| e.with_path(path))?;
+        Ok(Model::Quantized(QuantizedPhi3::from_gguf(false, content, &mut file, device)?))
+    }
+
+    fn load_safetensors(config: &Path, shards: &[PathBuf], lora: Option<&Path>, device: &Device) -> Result<Self> {
+        let config: Phi3Config = serde_json::from_slice(&std::fs::read(config)?)?;
+        let vb = match lora {
+            None => unsafe { VarBuilder::from_mmaped_safetensors(shards, DType::F32, device)? },
+            Some(lora) => {
+                // Merging needs the weights in memory rather than mapped
+                let mut weights = HashMap::new();
+                for shard in shards {
+                    weights.extend(candle_core::safetensors::load(shard, device)?);
+                }
+                LoraAdapter::load(lora, device)?.merge(&mut weights)?;
+                VarBuilder::from_tensors(weights, DType::F32, device)
+            }
+        };
+        Ok(Model::Full(Phi3::new(&config, vb)?))
+    }
+
+    fn load_dir(files: &ModelDir, lora: Option<&Path>, device: &Device) -> Result<Self> {
+        match &files.weights {
+            ModelWeights::Gguf(path) if lora.is_some() => anyhow::bail!(
+                "A LoRA adapter can only be merged into full-precision weights, not {}",
+                path.display()
+            ),
+            ModelWeights::Gguf(path) => Model::load_gguf(path, device),
+            ModelWeights::Safetensors { config, shards } => Model::load_safetensors(config, shards, lora, device),
+        }
+    }
+
+    /// Logits for the last position of `input`, which starts at `offset`
+    fn forward(&mut self, input: &Tensor, offset: usize) -> candle_core::Result<Tensor> {
+        match self {
+            Model::Full(model) => model.forward(input, offset)?.squeeze(0)?.squeeze(0),
+            Model::Quantized(model) => model.forward(input, offset)?.squeeze(0),
+        }
+    }
+
+    fn clear_kv_cache(&mut self) {
+        match self {
+            Model::Full(model) => model.clear_kv_cache(),
+            // Cleared by the model itself whenever a forward starts at 0
+            Model::Quantized(_) => {}
+        }
+    }
+}
+
 pub struct LocalLlm {
-    model: Phi3,
+    model: Model,
+    precision: Precision,
+    device_info: DeviceInfo,
     tokenizer: Tokenizer,
     device: Device,
-    logits_processor: LogitsProcessor,
+    watchdog: Watchdog,
+    /// Text of each vocabulary token for constrained decoding, worked out
+    /// on first use
+    json_pieces: Option<Vec<Option<String>>>,
+    templates: PromptTemplates,
 }
 
 impl LocalLlm {
+    /// Loads the weights anew on every call; share one model through an
+    /// `LlmManager` instead
     pub fn new() -> Result<Self> {
-        let device = Device::new_metal(0).unwrap_or(Device::Cpu);
-        
-        let api = Api::new()?;
-        let repo = api.repo(Repo::new("microsoft/Phi-3-mini-4k-instruct".to_string(), RepoType::Model));
-        // let repo = api.repo(Repo::new("microsoft/Phi-3.5-mini-instruct".to_string(), RepoType::Model));
-
-        let tokenizer_filename = repo.get("tokenizer.json")?;
-        let config_filename = repo.get("config.json")?;
-        let model_filenames = vec![
-            repo.get("model-00001-of-00002.safetensors")?,
-            repo.get("model-00002-of-00002.safetensors")?,
-        ];
+        Self::with_config(LocalLlmConfig::default())
+    }
+
+    /// Loads the weights `config.precision` asks for on `config.device`.
+    /// `Auto` picks the quantized model when the full one wouldn't fit in
+    /// the device's free memory.
+    pub fn with_config(config: LocalLlmConfig) -> Result<Self> {
+        Self::load(config, &mut |_| {})
+    }
 
+    /// Like `with_config`, calling `on_progress` while weights that aren't
+    /// cached yet are downloaded
+    pub fn load(config: LocalLlmConfig, on_progress: &mut dyn FnMut(&DownloadProgress)) -> Result<Self> {
+        if let Some(dir) = &config.model_dir {
+            let files = ModelDir::scan(dir)?;
+            return Ok(Self::from_files(&files, config.device, config.lora.as_deref())?.with_watchdog(config.watchdog));
+        }
+        let device = config.device.open()?;
+        let precision = match (config.precision, &config.lora) {
+            (Precision::Auto, Some(_)) => Precision::Full,
+            (precision, _) => precision.resolve(DeviceInfo::of(&device).free_memory),
+        };
+        if config.lora.is_some() && precision != Precision::Full {
+            anyhow::bail!("A LoRA adapter can only be merged into full-precision weights, not {:?}", precision);
+        }
+        let mut files = ModelFiles::new(config.offline, on_progress)?;
+
+        let tokenizer_filename = files.get(MODEL_REPO, "tokenizer.json")?;
         let tokenizer = Tokenizer::from_file(tokenizer_filename).map_err(E::msg)?;
-        let config: Phi3Config = serde_json::from_slice(&std::fs::read(config_filename)?)?;
-        
-        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&model_filenames, DType::F32, &device)? };
-        let model = Phi3::new(&config, vb)?;
 
-        Ok(Self {
+        let model = match precision.gguf() {
+            Some((gguf_repo, gguf_file)) => {
+                let path = files.get(gguf_repo, gguf_file)?;
+                Model::load_gguf(&path, &device)?
+            }
+            None => {
+                let config_filename = files.get(MODEL_REPO, "config.json")?;
+                let model_filenames = vec![
+                    files.get(MODEL_REPO, "model-00001-of-00002.safetensors")?,
+                    files.get(MODEL_REPO, "model-00002-of-00002.safetensors")?,
+                ];
+                Model::load_safetensors(&config_filename, &model_filenames, config.lora.as_deref(), &device)?
+            }
+        };
+
+        Ok(Self::from_model(model, precision, tokenizer, device).with_watchdog(config.watchdog))
+    }
+
+    /// Loads the model in `dir` (see `ModelDir`) without going near the
+    /// network
+    pub fn from_dir(dir: impl AsRef<Path>, device: DeviceChoice) -> Result<Self> {
+        Self::from_files(&ModelDir::scan(dir)?, device, None)
+    }
+
+    fn from_files(files: &ModelDir, device: DeviceChoice, lora: Option<&Path>) -> Result<Self> {
+        let device = device.open()?;
+        let tokenizer = Tokenizer::from_file(&files.tokenizer).map_err(E::msg)?;
+        let model = Model::load_dir(files, lora, &device)?;
+        Ok(Self::from_model(model, files.precision(), tokenizer, device))
+    }
+
+    /// Wraps an already loaded model and tokenizer, e.g. local weights
+    pub fn from_parts(model: Phi3, tokenizer: Tokenizer, device: Device) -> Self {
+        Self::from_model(Model::Full(model), Precision::Full, tokenizer, device)
+    }
+
+    fn from_model(model: Model, precision: Precision, tokenizer: Tokenizer, device: Device) -> Self {
+        Self {
             model,
+            precision,
+            device_info: DeviceInfo::of(&device),
             tokenizer,
             device,
-            logits_processor: LogitsProcessor::new(299792458, Some(0.7), Some(0.9)),
-        })
+            watchdog: Watchdog::default(),
+            json_pieces: None,
+            templates: PromptTemplates::new(),
+        }
     }
 
-    fn format_prompt(&self, system: &str, user: &str) -> String {
-        format!("<|user|>\n{}\n{}\n<|end|>\n<|assistant|>\n", system, user)
+    pub fn with_watchdog(mut self, watchdog: Watchdog) -> Self {
+        self.watchdog = watchdog;
+        self
     }
 
-    pub fn generate(&mut self, prompt: &str, max_tokens: usize) -> Result<String> {
-        let tokens = self.tokenizer.encode(prompt, true).map_err(E::msg)?;
-        let mut tokens = tokens.get_ids().to_vec();
-        let mut generated_tokens = Vec::new();
-
-        for _ in 0..max_tokens {
-            let input = Tensor::new(&tokens[tokens.len().saturating_sub(2048)..], &self.device)?.unsqueeze(0)?;
-            let input_ids = &tokens[tokens.len().saturating_sub(2048)..];
-            let pos = input_ids.len(); // Current position in the sequence
-            let logits = self.model.forward(&input, pos)?; // Fix: passed pos
-            let logits = logits.squeeze(0)?.squeeze(0)?.to_dtype(DType::F32)?;
-            
-            let next_token = self.logits_processor.sample(&logits)?;
+    /// Prompts for the helpers, e.g. with a profile's overrides
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// The weights that were loaded; never `Auto`
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    /// The device inference runs on, with its free memory at load time
+    pub fn device_info(&self) -> DeviceInfo {
+        self.device_info
+    }
+
+    /// `generate_stream` for a prompt already tokenized
+    fn generate_tokens(
+        &mut self,
+        mut tokens: Vec<u32>,
+        params: &GenerationParams,
+        on_token: &mut dyn FnMut(&str) -> Result<()>,
+    ) -> Result<String> {
+        let started = Instant::now();
+        let mut decoding = None;
+        let mut logits_processor = logits_processor(params);
+        let mut grammar = params.json.then(JsonGrammar::new);
+        if params.json && self.json_pieces.is_none() {
+            self.json_pieces = Some(token_pieces(&self.tokenizer));
+        }
+        let pieces = self.json_pieces.as_deref().unwrap_or_default();
+        let mut decoder = TokenDecoder::default();
+        // Decoded output so far, and how much of it has been streamed
+        let mut text = String::new();
+        let mut emitted = 0;
+
+        let end_token = self.tokenizer.token_to_id("<|end|>").unwrap_or(32000);
+        let eos_token = self.tokenizer.token_to_id("<|endoftext|>").unwrap_or(32007);
+
+        // Cutting the prompt would drop the instructions at its start
+        let prompt_len = tokens.len();
+        if prompt_len >= CONTEXT_WINDOW {
+            return Err(LlmError::ContextExceeded {
+                tokens: prompt_len,
+                limit: CONTEXT_WINDOW,
+            }
+            .into());
+        }
+
+        // The prompt is fed once to fill the KV cache; after that each step
+        // only feeds the token sampled last. `tokens` is what the model
+        // attends to, `cached` how much of it is in the cache.
+        self.model.clear_kv_cache();
+        let mut cached = 0;
+
+        for _ in 0..params.max_tokens {
+            params.check_cancelled()?;
+            self.watchdog.check(started, decoding, decoder.tokens.len())?;
+            if tokens.len() > CONTEXT_WINDOW {
+                trim_context(&mut tokens, prompt_len, CONTEXT_WINDOW);
+                cached = 0;
+                self.model.clear_kv_cache();
+            }
+            let input = Tensor::new(&tokens[cached..], &self.device)?.unsqueeze(0)?;
+            let logits = self.model.forward(&input, cached)?;
+            cached = tokens.len();
+            decoding.get_or_insert_with(Instant::now);
+            let mut logits = logits.to_dtype(DType::F32)?;
+            if params.repetition_penalty != 1.0 {
+                let generated = &decoder.tokens;
+                let recent = &generated[generated.len().saturating_sub(REPEAT_LAST_N)..];
+                logits = candle_transformers::utils::apply_repeat_penalty(&logits, params.repetition_penalty, recent)?;
+            }
+
+            if let Some(grammar) = &grammar {
+                logits = mask_logits(&logits, grammar, pieces)?;
+            }
+
+            let next_token = logits_processor.sample(&logits)?;
+            if next_token == end_token || next_token == eos_token {
+                break;
+            }
             tokens.push(next_token);
-            generated_tokens.push(next_token);
+            if let (Some(grammar), Some(Some(piece))) = (&mut grammar, pieces.get(next_token as usize)) {
+                grammar.feed_str(piece);
+            }
+            let complete = grammar.as_ref().is_some_and(JsonGrammar::is_complete);
+
+            let Some(piece) = decoder.push(&self.tokenizer, next_token)? else {
+                // Part of a character, or a token that adds no text
+                if complete {
+                    break;
+                }
+                continue;
+            };
+            text.push_str(&piece);
 
-            if next_token == self.tokenizer.token_to_id("<|end|>").unwrap_or(32000) 
-                || next_token == self.tokenizer.token_to_id("<|endoftext|>").unwrap_or(32007) {
+            // A stop sequence can't start in text already streamed, which
+            // held back anything that might begin one
+            let held = &text[emitted..];
+            let stop = params.find_stop(held).map(|at| emitted + at);
+            let end = stop.unwrap_or_else(|| emitted + params.streamable_len(held));
+            emitted = stream_piece(&text, emitted, end, on_token)?;
+            if stop.is_some() || complete {
                 break;
             }
         }
 
-        let decoded = self.tokenizer.decode(&generated_tokens, true).map_err(E::msg)?;
-        Ok(decoded.replace("<|end|>", "").trim().to_string())
+        text.push_str(&decoder.rest(&self.tokenizer)?);
+        if let Some(end) = params.find_stop(&text) {
+            text.truncate(end);
+        }
+        // Cut off by the token limit: close what is open so the JSON parses
+        if let Some(grammar) = grammar.filter(|grammar| !grammar.is_complete()) {
+            text.push_str(&grammar.completion());
+        }
+        // Text held back for a stop sequence that never completed
+        stream_piece(&text, emitted, text.len(), on_token)?;
+        Ok(text.replace("<|end|>", "").trim().to_string())
     }
+}
 
-    pub fn synthesize(&mut self, text: &str) -> Result<String> {
-        let prompt = self.format_prompt(
-            "You are a helpful assistant. Summarize the following text concisely.",
-            text
-        );
-        self.generate(&prompt, 500)
-    }
-
-    pub fn extract_pii(&mut self, text: &str) -> Result<(String, std::collections::HashMap<String, String>)> {
-        let system_prompt = "You are a privacy expert. Identify Personal Identifiable Information (PII) such as Names, Emails, Phone Numbers, and Addresses. 
-Return the output in JSON format: {\"redacted_text\": \"...\", \"pii\": {\"PLACEHOLDER\": \"ORIGINAL_VALUE\"}}. 
-Replace PII with placeholders like [NAME_1], [EMAIL_1].";
-        
-        let prompt = self.format_prompt(system_prompt, text);
-        let output = self.generate(&prompt, 1000)?;
-        
-        // Attempt to parse JSON. If failure, return original (fail-safe) or basic regex based redaction.
-        // For now, assuming model adheres to instruction for this alpha implementation.
-        #[derive(serde::Deserialize)]
-        struct PiiResult {
-            redacted_text: String,
-            pii: std::collections::HashMap<String, String>,
-        }
-
-        // Find JSON block in output if wrapped in markdown codefence
-        let json_str = if let Some(start) = output.find("```json") {
-             if let Some(end) = output[start..].find("```") {
-                 // skip "```json" (7 chars) and take until next ```
-                 // tricky indexing, let's just clean it
-                 output.replace("```json", "").replace("```", "")
-             } else {
-                 output.clone()
-             }
-        } else {
-            output.clone()
+/// Drops the oldest generated tokens from a context that outgrew `window`
+///
+/// The prompt is kept whole. Of the generated tokens, the newest fill half
+/// the room the prompt leaves, so the cache isn't rebuilt every step.
+fn trim_context(tokens: &mut Vec<u32>, prompt_len: usize, window: usize) {
+    let keep = ((window - prompt_len) / 2).max(1);
+    tokens.drain(prompt_len..tokens.len() - keep);
+}
+
+/// Streams `text[emitted..end]`, if there is any
+///
+/// # Returns
+/// How much of `text` has been streamed now
+fn stream_piece(
+    text: &str,
+    emitted: usize,
+    end: usize,
+    on_token: &mut dyn FnMut(&str) -> Result<()>,
+) -> Result<usize> {
+    let Some(piece) = text.get(emitted..end) else {
+        bail!("Cannot stream output bytes {}..{} of {}", emitted, end, text.len());
+    };
+    if !piece.is_empty() {
+        on_token(piece)?;
+    }
+    Ok(end)
+}
+
+/// Decodes generated tokens as they come, like candle's `TokenOutputStream`
+///
+/// Tokens alone don't carry their leading spaces, and a character may span
+/// several byte tokens. So each step decodes from `prefix_offset`, the
+/// tokens whose text was returned last, and returns what comes after the
+/// text they decode to; earlier tokens are never decoded again.
+#[derive(Default)]
+struct TokenDecoder {
+    tokens: Vec<u32>,
+    prefix_offset: usize,
+    read_offset: usize,
+}
+
+impl TokenDecoder {
+    /// Adds a generated token
+    ///
+    /// # Returns
+    /// The text it completes, or None while it ends in part of a character
+    fn push(&mut self, tokenizer: &Tokenizer, token: u32) -> Result<Option<String>> {
+        self.tokens.push(token);
+        let (prefix, text) = self.decode(tokenizer)?;
+        if text.len() <= prefix.len() || text.ends_with('\u{FFFD}') {
+            return Ok(None);
+        }
+        // The new token changed how the earlier ones decode, splitting a
+        // character of theirs; wait for a token that settles it
+        let Some(piece) = text.get(prefix.len()..) else {
+            return Ok(None);
+        };
+        let piece = piece.to_string();
+        self.prefix_offset = self.read_offset;
+        self.read_offset = self.tokens.len();
+        Ok(Some(piece))
+    }
+
+    /// Returns the text of the tokens `push` has not returned yet, even if
+    /// it ends in part of a character
+    fn rest(&self, tokenizer: &Tokenizer) -> Result<String> {
+        let (prefix, text) = self.decode(tokenizer)?;
+        match (prefix.len()..=text.len()).find(|&at| text.is_char_boundary(at)) {
+            Some(at) => Ok(text[at..].to_string()),
+            None => Ok(String::new()),
+        }
+    }
+
+    /// Decodes the tokens from `prefix_offset` up to `read_offset`, and up
+    /// to the last one
+    fn decode(&self, tokenizer: &Tokenizer) -> Result<(String, String)> {
+        let prefix = tokenizer
+            .decode(&self.tokens[self.prefix_offset..self.read_offset], true)
+            .map_err(E::msg)?;
+        let text = tokenizer
+            .decode(&self.tokens[self.prefix_offset..], true)
+            .map_err(E::msg)?;
+        Ok((prefix, text))
+    }
+}
+
+/// What each vocabulary token adds to the text, by id. `None` for special
+/// tokens, which never belong in the output.
+fn token_pieces(tokenizer: &Tokenizer) -> Vec<Option<String>> {
+    let special = tokenizer.get_added_tokens_decoder();
+    let mut pieces = vec![None; tokenizer.get_vocab_size(true)];
+    for (token, id) in tokenizer.get_vocab(true) {
+        if special.get(&id).is_some_and(|added| added.special) {
+            continue;
+        }
+        let piece = match token.strip_prefix("<0x").and_then(|hex| hex.strip_suffix('>')) {
+            // Byte fallback; bytes of a multi-byte character only fit in a
+            // string, which is what the replacement character stands for
+            Some(hex) => match u8::from_str_radix(hex, 16) {
+                Ok(byte) if byte.is_ascii() => (byte as char).to_string(),
+                Ok(_) => '\u{FFFD}'.to_string(),
+                Err(_) => token,
+            },
+            // SentencePiece marks a leading space with '▁'
+            None => token.replace('\u{2581}', " "),
+        };
+        if let Some(slot) = pieces.get_mut(id as usize) {
+            *slot = Some(piece);
+        }
+    }
+    pieces
+}
+
+/// `logits` with every token `grammar` can't take next ruled out
+fn mask_logits(logits: &Tensor, grammar: &JsonGrammar, pieces: &[Option<String>]) -> Result<Tensor> {
+    let mut values = logits.to_vec1::<f32>()?;
+    for (id, value) in values.iter_mut().enumerate() {
+        let allowed = pieces
+            .get(id)
+            .and_then(Option::as_deref)
+            .is_some_and(|piece| grammar.accepts(piece));
+        if !allowed {
+            *value = f32::NEG_INFINITY;
+        }
+    }
+    Ok(Tensor::new(values, logits.device())?)
+}
+
+/// A sampler set up as `params` asks
+fn logits_processor(params: &GenerationParams) -> LogitsProcessor {
+    let seed = params.seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or(299792458)
+    });
+    let temperature = params.temperature;
+    let sampling = if temperature <= 0.0 {
+        Sampling::ArgMax
+    } else {
+        match (params.top_k, params.top_p) {
+            (None, None) => Sampling::All { temperature },
+            (Some(k), None) => Sampling::TopK { k, temperature },
+            (None, Some(p)) => Sampling::TopP { p, temperature },
+            (Some(k), Some(p)) => Sampling::TopKThenTopP { k, p, temperature },
+        }
+    };
+    LogitsProcessor::from_sampling(seed, sampling)
+}
+
+impl Llm for LocalLlm {
+    fn generate_stream(
+        &mut self,
+        prompt: &str,
+        params: &GenerationParams,
+        on_token: &mut dyn FnMut(&str) -> Result<()>,
+    ) -> Result<String> {
+        params.check_cancelled()?;
+        let tokens = self.tokenizer.encode(prompt, true).map_err(E::msg)?;
+        self.generate_tokens(tokens.get_ids().to_vec(), params, on_token)
+    }
+
+    /// Tokenizes the prompts on another thread, a few ahead of the one being
+    /// generated for, while the model is shared between them. The sequences
+    /// aren't batched into one forward pass: the model's attention has no
+    /// padding mask, so prompts of different lengths can't share one.
+    fn generate_batch(&mut self, prompts: &[String], params: &GenerationParams) -> Result<Vec<String>> {
+        let tokenizer = self.tokenizer.clone();
+        let (sender, receiver) = std::sync::mpsc::sync_channel(TOKENIZE_AHEAD);
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                for prompt in prompts {
+                    let tokens = tokenizer.encode(prompt.as_str(), true).map_err(E::msg);
+                    let tokens = tokens.map(|tokens| tokens.get_ids().to_vec());
+                    // Stops once generation has failed and hung up
+                    if sender.send(tokens).is_err() {
+                        break;
+                    }
+                }
+            });
+            // Consumed here, so a failure drops the receiver and ends the
+            // tokenizing thread
+            let outputs: Result<Vec<String>> = receiver
+                .into_iter()
+                .map(|tokens| {
+                    params.check_cancelled()?;
+                    self.generate_tokens(tokens?, params, &mut |_| Ok(()))
+                })
+                .collect();
+            outputs
+        })
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<usize> {
+        let tokens = self.tokenizer.encode(text, true).map_err(E::msg)?;
+        Ok(tokens.get_ids().len())
+    }
+
+    fn context_tokens(&self) -> usize {
+        CONTEXT_WINDOW
+    }
+
+    fn templates(&self) -> &PromptTemplates {
+        &self.templates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_util::sync::CancellationToken;
+
+    #[test]
+    fn test_precision_resolves_by_free_memory() {
+        let gb = 1024 * 1024 * 1024;
+        assert_eq!(Precision::Auto.resolve(Some(8 * gb)), Precision::Q4);
+        assert_eq!(Precision::Auto.resolve(Some(32 * gb)), Precision::Full);
+        assert_eq!(Precision::Auto.resolve(None), Precision::Full);
+        assert_eq!(Precision::Q5.resolve(Some(8 * gb)), Precision::Q5);
+        assert_eq!(Precision::Full.resolve(Some(8 * gb)), Precision::Full);
+
+        let meminfo = "MemTotal:       16315220 kB\nMemFree:         1022144 kB\nMemAvailable:    8157608 kB\n";
+        assert_eq!(parse_meminfo(meminfo), Some(8157608 * 1024));
+        assert_eq!(parse_meminfo("MemTotal: 1 kB"), None);
+
+        let config: LocalLlmConfig = serde_json::from_str(r#"{"precision": "q5"}"#).unwrap();
+        assert_eq!(config.precision, Precision::Q5);
+        let config: LocalLlmConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(config.precision, Precision::Auto);
+    }
+
+    #[test]
+    fn test_logits_processor_follows_params() {
+        let logits = Tensor::new(&[0.1f32, 2.0, 0.5, 1.9], &Device::Cpu).unwrap();
+
+        let mut greedy = logits_processor(&GenerationParams::precise(1));
+        for _ in 0..5 {
+            assert_eq!(greedy.sample(&logits).unwrap(), 1);
+        }
+
+        let seeded = GenerationParams {
+            temperature: 5.0,
+            top_p: None,
+            seed: Some(7),
+            ..GenerationParams::default()
+        };
+        let sample = |params: &GenerationParams| {
+            let mut processor = logits_processor(params);
+            (0..20).map(|_| processor.sample(&logits).unwrap()).collect::<Vec<_>>()
         };
+        assert_eq!(sample(&seeded), sample(&seeded));
+
+        // Only the two most likely tokens are ever picked
+        let top_k = GenerationParams {
+            top_k: Some(2),
+            ..seeded
+        };
+        assert!(sample(&top_k).iter().all(|token| *token == 1 || *token == 3));
+    }
+
+    /// A tiny Phi-3 with zeroed weights: greedy sampling always picks
+    /// token 0, which decodes as "t0"
+    /// A tiny Phi-3 as JSON, so it can also be written to a model directory
+    const TINY_CONFIG: &str = r#"{
+        "vocab_size": 8, "hidden_act": "silu", "hidden_size": 16, "intermediate_size": 32,
+        "num_hidden_layers": 1, "num_attention_heads": 2, "num_key_value_heads": 2,
+        "rms_norm_eps": 1e-5, "rope_theta": 10000.0, "bos_token_id": null,
+        "eos_token_id": null, "rope_scaling": null, "max_position_embeddings": 64,
+        "tie_word_embeddings": false
+    }"#;
 
-        if let Ok(res) = serde_json::from_str::<PiiResult>(&json_str) {
-            Ok((res.redacted_text, res.pii))
-        } else {
-            // Fallback: Return original if parsing fails
-             Ok((text.to_string(), std::collections::HashMap::new()))
+    /// Words t0..t7, one token each
+    fn tiny_tokenizer() -> Tokenizer {
+        let words: Vec<String> = (0..8).map(|id| format!("t{id}")).collect();
+        word_tokenizer(&words)
+    }
+
+    /// One token per word, in order, split on whitespace
+    fn word_tokenizer(words: &[impl AsRef<str>]) -> Tokenizer {
+        use tokenizers::models::wordlevel::WordLevel;
+        use tokenizers::pre_tokenizers::whitespace::WhitespaceSplit;
+
+        let vocab = words
+            .iter()
+            .enumerate()
+            .map(|(id, word)| (word.as_ref().to_string(), id as u32))
+            .collect();
+        let wordlevel = WordLevel::builder()
+            .vocab(vocab)
+            .unk_token(words[0].as_ref().to_string())
+            .build()
+            .unwrap();
+        let mut tokenizer = Tokenizer::new(wordlevel);
+        tokenizer.with_pre_tokenizer(Some(WhitespaceSplit));
+        tokenizer
+    }
+
+    /// The tiny Phi-3 with every weight zero, so it always picks token 0
+    fn zeroed_llm() -> LocalLlm {
+        let config: Phi3Config = serde_json::from_str(TINY_CONFIG).unwrap();
+        let device = Device::Cpu;
+        let model = Phi3::new(&config, VarBuilder::zeros(DType::F32, &device)).unwrap();
+        LocalLlm::from_parts(model, tiny_tokenizer(), device)
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("facet-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_model_dir_scan() {
+        let dir = temp_dir("model-dir-scan");
+        assert!(ModelDir::scan(&dir).unwrap_err().to_string().contains("tokenizer.json"));
+
+        std::fs::write(dir.join("tokenizer.json"), "{}").unwrap();
+        assert!(ModelDir::scan(&dir).unwrap_err().to_string().contains("No model weights"));
+
+        for file in ["config.json", "model-00002-of-00002.safetensors", "model-00001-of-00002.safetensors"] {
+            std::fs::write(dir.join(file), "").unwrap();
         }
+        let model_dir = ModelDir::scan(&dir).unwrap();
+        assert_eq!(model_dir.tokenizer, dir.join("tokenizer.json"));
+        assert_eq!(
+            model_dir.weights,
+            ModelWeights::Safetensors {
+                config: dir.join("config.json"),
+                shards: vec![
+                    dir.join("model-00001-of-00002.safetensors"),
+                    dir.join("model-00002-of-00002.safetensors"),
+                ],
+            }
+        );
+        assert_eq!(model_dir.precision(), Precision::Full);
+
+        // A GGUF file wins over safetensors, but it has to be unambiguous
+        std::fs::write(dir.join("Phi-3-mini-4k-instruct-Q5_K_M.gguf"), "").unwrap();
+        let model_dir = ModelDir::scan(&dir).unwrap();
+        assert_eq!(model_dir.weights, ModelWeights::Gguf(dir.join("Phi-3-mini-4k-instruct-Q5_K_M.gguf")));
+        assert_eq!(model_dir.precision(), Precision::Q5);
+        std::fs::write(dir.join("Phi-3-mini-4k-instruct-q4.gguf"), "").unwrap();
+        assert!(ModelDir::scan(&dir).unwrap_err().to_string().contains("Several .gguf"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_dir_loads_local_weights() {
+        let dir = temp_dir("model-dir-load");
+        let config: Phi3Config = serde_json::from_str(TINY_CONFIG).unwrap();
+        let varmap = candle_nn::VarMap::new();
+        Phi3::new(&config, VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu)).unwrap();
+        varmap.save(dir.join("model.safetensors")).unwrap();
+        std::fs::write(dir.join("config.json"), TINY_CONFIG).unwrap();
+        tiny_tokenizer().save(dir.join("tokenizer.json"), false).unwrap();
+
+        let config = LocalLlmConfig {
+            model_dir: Some(dir.clone()),
+            device: DeviceChoice::Cpu,
+            ..Default::default()
+        };
+        let mut llm = LocalLlm::with_config(config).unwrap();
+        assert_eq!(llm.precision(), Precision::Full);
+        assert_eq!(llm.count_tokens("t1 t2 t3").unwrap(), 3);
+        llm.generate("t1 t2", &GenerationParams::precise(3)).unwrap();
+
+        // With a LoRA adapter on the output layer, and one for another model
+        let lora_dir = dir.join("adapter");
+        std::fs::create_dir_all(&lora_dir).unwrap();
+        let lora = |target: &str| -> HashMap<String, Tensor> {
+            [("lora_A", (1usize, 16usize)), ("lora_B", (8, 1))]
+                .into_iter()
+                .map(|(half, shape)| {
+                    let name = format!("base_model.model.{}.{}.weight", target, half);
+                    (name, Tensor::ones(shape, DType::F32, &Device::Cpu).unwrap())
+                })
+                .collect()
+        };
+        candle_core::safetensors::save(&lora("lm_head"), lora_dir.join("adapter_model.safetensors")).unwrap();
+        std::fs::write(lora_dir.join("adapter_config.json"), r#"{"r": 1, "lora_alpha": 2}"#).unwrap();
+        candle_core::safetensors::save(&lora("model.vision_proj"), dir.join("other.lora")).unwrap();
+
+        let config = LocalLlmConfig {
+            model_dir: Some(dir.clone()),
+            device: DeviceChoice::Cpu,
+            lora: Some(lora_dir),
+            ..Default::default()
+        };
+        let mut llm = LocalLlm::with_config(config.clone()).unwrap();
+        llm.generate("t1 t2", &GenerationParams::precise(3)).unwrap();
+        let config = LocalLlmConfig {
+            lora: Some(dir.join("other.lora")),
+            ..config
+        };
+        let err = LocalLlm::with_config(config).err().unwrap();
+        assert!(err.to_string().contains("which the model doesn't have"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_generate_stops_at_stop_sequence() {
+        let mut llm = zeroed_llm();
+        let params = GenerationParams::precise(6);
+        assert_eq!(llm.generate("t1 t2", &params).unwrap(), "t0 t0 t0 t0 t0 t0");
+
+        let mut pieces = Vec::new();
+        let params = GenerationParams::precise(20).with_stop(["0 t0 t0"]);
+        let output = llm
+            .generate_stream("t1 t2", &params, &mut |piece| {
+                pieces.push(piece.to_string());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(output, "t");
+        // The partial match was held back, not streamed
+        assert_eq!(pieces.concat(), "t");
+    }
+
+    #[test]
+    fn test_token_decoder() {
+        use tokenizers::decoders::byte_fallback::ByteFallback;
+
+        // Each token once, with the space before it
+        let tokenizer = tiny_tokenizer();
+        let mut decoder = TokenDecoder::default();
+        let pieces: Vec<String> = [1, 2, 3]
+            .into_iter()
+            .map(|token| decoder.push(&tokenizer, token).unwrap().unwrap())
+            .collect();
+        assert_eq!(pieces, ["t1", " t2", " t3"]);
+        assert_eq!((decoder.prefix_offset, decoder.read_offset), (2, 3));
+        assert_eq!(decoder.rest(&tokenizer).unwrap(), "");
+
+        // A character spelled out in bytes comes out whole
+        let mut tokenizer = word_tokenizer(&["a", "<0xC3>", "<0xA9>"]);
+        tokenizer.with_decoder(Some(ByteFallback::new()));
+        let mut decoder = TokenDecoder::default();
+        assert_eq!(decoder.push(&tokenizer, 0).unwrap().as_deref(), Some("a"));
+        assert_eq!(decoder.push(&tokenizer, 1).unwrap(), None);
+        assert_eq!(decoder.rest(&tokenizer).unwrap(), "\u{FFFD}");
+        assert_eq!(decoder.push(&tokenizer, 2).unwrap().as_deref(), Some("é"));
+        assert_eq!(decoder.rest(&tokenizer).unwrap(), "");
+    }
+
+    #[test]
+    fn test_trim_context_keeps_prompt() {
+        let mut tokens: Vec<u32> = (0..12).collect();
+        trim_context(&mut tokens, 4, 10);
+        assert_eq!(tokens, [0, 1, 2, 3, 9, 10, 11]);
+
+        // A prompt one short of the window keeps the token to feed next
+        let mut tokens: Vec<u32> = (0..11).collect();
+        trim_context(&mut tokens, 9, 10);
+        assert_eq!(tokens, [0, 1, 2, 3, 4, 5, 6, 7, 8, 10]);
     }
 
-    pub fn optimize_prompt(&mut self, query: &str) -> Result<String> {
-        let prompt = self.format_prompt(
-            "You are a prompt engineer. Rewrite the following query to be more precise and optimized for an LLM rag search.",
-            query
+    #[test]
+    fn test_prompt_longer_than_context_fails() {
+        let mut llm = zeroed_llm();
+        let prompt = "t1 ".repeat(CONTEXT_WINDOW);
+        let err = llm
+            .generate(&prompt, &GenerationParams::precise(1))
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LlmError>(),
+            Some(&LlmError::ContextExceeded {
+                tokens: CONTEXT_WINDOW,
+                limit: CONTEXT_WINDOW,
+            })
         );
-        self.generate(&prompt, 200)
+    }
+
+    #[test]
+    fn test_generate_batch() {
+        let mut llm = zeroed_llm();
+        let params = GenerationParams::precise(3);
+        let prompts = ["t1 t2", "t2", "t1"].map(String::from);
+        let outputs = llm.generate_batch(&prompts, &params).unwrap();
+        assert_eq!(outputs, ["t0 t0 t0"; 3]);
+        assert!(llm.generate_batch(&[], &params).unwrap().is_empty());
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let err = llm
+            .generate_batch(&prompts, &params.with_cancel(cancel))
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<LlmError>(), Some(&LlmError::Cancelled));
+    }
+
+    #[test]
+    fn test_generate_can_be_cancelled() {
+        let mut llm = zeroed_llm();
+        let cancel = CancellationToken::new();
+        let params = GenerationParams::precise(50).with_cancel(cancel.clone());
+
+        let mut pieces = 0;
+        let err = llm
+            .generate_stream("t1", &params, &mut |_| {
+                pieces += 1;
+                if pieces == 2 {
+                    cancel.cancel();
+                }
+                Ok(())
+            })
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<LlmError>(), Some(&LlmError::Cancelled));
+        assert_eq!(pieces, 2);
+
+        // Already cancelled: nothing runs
+        let err = llm.generate("t1", &params).unwrap_err();
+        assert_eq!(err.downcast_ref::<LlmError>(), Some(&LlmError::Cancelled));
+    }
+
+    #[test]
+    fn test_watchdog() {
+        let ago = |secs| Instant::now() - std::time::Duration::from_secs(secs);
+        let watchdog = Watchdog {
+            timeout_secs: Some(60.0),
+            min_tokens_per_second: Some(1.0),
+            grace_secs: 10.0,
+        };
+        assert!(watchdog.check(ago(30), None, 0).is_ok());
+        assert!(watchdog.check(ago(30), Some(ago(5)), 0).is_ok(), "still in grace");
+        assert!(watchdog.check(ago(30), Some(ago(20)), 40).is_ok());
+        assert!(matches!(
+            watchdog.check(ago(30), Some(ago(20)), 5),
+            Err(LlmError::TimedOut { tokens: 5, .. })
+        ));
+        assert!(matches!(
+            watchdog.check(ago(90), Some(ago(80)), 1000),
+            Err(LlmError::TimedOut { tokens: 1000, .. })
+        ));
+        assert!(Watchdog::disabled().check(ago(90), Some(ago(80)), 0).is_ok());
+
+        let mut llm = zeroed_llm().with_watchdog(Watchdog {
+            timeout_secs: Some(0.0),
+            ..Watchdog::disabled()
+        });
+        let err = llm.generate("t1", &GenerationParams::precise(5)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<LlmError>(),
+            Some(LlmError::TimedOut { tokens: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_json_output_is_constrained() {
+        let mut llm = zeroed_llm();
+        // The zeroed model always prefers the first token it may pick
+        llm.tokenizer = word_tokenizer(&["}", "]", "[", "{", "\"", "a", ":", "1"]);
+        assert_eq!(llm.generate("{", &GenerationParams::precise(4)).unwrap(), "} } } }");
+
+        let params = GenerationParams::precise(20).with_json();
+        assert_eq!(llm.generate("{", &params).unwrap(), "[ ]");
+        // Out of tokens before the array is closed
+        let params = GenerationParams::precise(1).with_json();
+        assert_eq!(llm.generate("{", &params).unwrap(), "[]");
+
+        let mut grammar = JsonGrammar::new();
+        assert!(grammar.feed_str("[1, "));
+        let pieces = token_pieces(&llm.tokenizer);
+        let logits = Tensor::zeros(8, DType::F32, &Device::Cpu).unwrap();
+        let masked = mask_logits(&logits, &grammar, &pieces).unwrap().to_vec1::<f32>().unwrap();
+        let allowed: Vec<&str> = masked
+            .iter()
+            .zip(&pieces)
+            .filter(|(logit, _)| logit.is_finite())
+            .filter_map(|(_, piece)| piece.as_deref())
+            .collect();
+        assert_eq!(allowed, ["[", "{", "\"", "1"]);
+    }
+
+    #[test]
+    fn test_device_choice() {
+        let config: LocalLlmConfig = serde_json::from_str(r#"{"device": {"cuda": 1}}"#).unwrap();
+        assert_eq!(config.device, DeviceChoice::Cuda(1));
+        let config: LocalLlmConfig = serde_json::from_str(r#"{"device": "cpu"}"#).unwrap();
+        assert_eq!(config.device, DeviceChoice::Cpu);
+        assert_eq!(LocalLlmConfig::default().device, DeviceChoice::Auto);
+
+        let cpu = DeviceChoice::Cpu.open().unwrap();
+        assert_eq!(DeviceInfo::of(&cpu).device, DeviceChoice::Cpu);
+        if !cfg!(feature = "cuda") {
+            let err = DeviceChoice::Cuda(0).open().unwrap_err();
+            assert!(err.to_string().contains("`cuda` feature"));
+        }
     }
 }
@@ -0,0 +1,144 @@
+//! One warm model for the whole process
+//!
+//! Loading the local model reads gigabytes of weights, so callers
+//! shouldn't each build their own `LocalLlm`. An `LlmManager` loads it once,
+//! on the first request made through any of its handles, and drops it
+//! again when no request has come for a while, to give the memory back.
+//! The next request loads it again. `LlmManager::global` is the manager
+//! the app and the server share.
+
+use super::{Llm, LlmHandle, LocalLlm, LocalLlmConfig};
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+/// How long the model stays loaded without requests unless configured
+/// otherwise
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+static GLOBAL: OnceLock<LlmManager> = OnceLock::new();
+
+pub struct LlmManager {
+    handle: LlmHandle,
+    loaded: Arc<AtomicBool>,
+    idle_timeout: Duration,
+}
+
+impl LlmManager {
+    /// A manager of the backend `load` builds, unloaded after
+    /// `DEFAULT_IDLE_TIMEOUT`. Nothing is loaded until the first request.
+    pub fn new<L, F>(load: F) -> Result<Self>
+    where
+        L: Llm + 'static,
+        F: Fn() -> Result<L> + Send + 'static,
+    {
+        Self::with_idle_timeout(load, DEFAULT_IDLE_TIMEOUT)
+    }
+
+    pub fn with_idle_timeout<L, F>(load: F, idle_timeout: Duration) -> Result<Self>
+    where
+        L: Llm + 'static,
+        F: Fn() -> Result<L> + Send + 'static,
+    {
+        let loaded = Arc::new(AtomicBool::new(false));
+        let handle = LlmHandle::lazy(load, idle_timeout, loaded.clone())?;
+        Ok(Self {
+            handle,
+            loaded,
+            idle_timeout,
+        })
+    }
+
+    /// A manager of the local model as `config` sets it up
+    pub fn local(config: LocalLlmConfig, idle_timeout: Duration) -> Result<Self> {
+        Self::with_idle_timeout(move || LocalLlm::with_config(config.clone()), idle_timeout)
+    }
+
+    /// The process-wide manager: the one `set_global` installed, or else
+    /// one of the local model with the default config
+    pub fn global() -> Result<&'static LlmManager> {
+        if let Some(manager) = GLOBAL.get() {
+            return Ok(manager);
+        }
+        let manager = Self::local(LocalLlmConfig::default(), DEFAULT_IDLE_TIMEOUT)?;
+        // Another thread may have got there first; its manager wins
+        Ok(GLOBAL.get_or_init(|| manager))
+    }
+
+    /// Makes `manager` the one `global` returns. Fails if `global` was
+    /// already used or set.
+    pub fn set_global(manager: LlmManager) -> Result<()> {
+        GLOBAL
+            .set(manager)
+            .map_err(|_| anyhow::anyhow!("The global LLM manager is already set"))
+    }
+
+    /// A handle to the shared backend. Requests from all handles queue up
+    /// on the same worker; the first loads the model if it isn't loaded.
+    pub fn handle(&self) -> LlmHandle {
+        self.handle.clone()
+    }
+
+    /// Whether the model is in memory right now
+    pub fn is_loaded(&self) -> bool {
+        self.loaded.load(Ordering::SeqCst)
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::{scripted, ScriptedLlm};
+    use super::super::GenerationParams;
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[tokio::test]
+    async fn test_model_is_loaded_once_and_unloaded_when_idle() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let counter = loads.clone();
+        let manager = LlmManager::with_idle_timeout(
+            move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(scripted("Warm"))
+            },
+            Duration::from_millis(100),
+        )
+        .unwrap();
+        assert!(!manager.is_loaded());
+
+        let (a, b) = (manager.handle(), manager.handle());
+        let params = GenerationParams::new(5);
+        assert_eq!(a.generate("one", params.clone()).await.unwrap(), "Warm");
+        assert_eq!(b.generate("two", params.clone()).await.unwrap(), "Warm");
+        assert!(manager.is_loaded());
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(!manager.is_loaded());
+        assert_eq!(a.generate("three", params).await.unwrap(), "Warm");
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_load_is_retried() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let manager = LlmManager::new(move || -> Result<ScriptedLlm> {
+            if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                anyhow::bail!("no weights");
+            }
+            Ok(scripted("Loaded"))
+        })
+        .unwrap();
+
+        let handle = manager.handle();
+        let err = handle.synthesize("text").await.unwrap_err();
+        assert_eq!(format!("{:#}", err), "Cannot load the LLM: no weights");
+        assert_eq!(handle.synthesize("text").await.unwrap(), "Loaded");
+    }
+}
@@ -1,5 +1,13 @@
 use crate::claude::ClaudeClient;
+use crate::ingest::{Entity, Triple};
+use crate::pii::{self, PiiDetector};
+use crate::redaction::RedactionPreview;
 use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::Duration;
+use thiserror::Error;
 
 // TODO: Re-enable OpenAI support by adding the `_api` feature to async-openai
 // use async_openai::{
@@ -8,8 +16,396 @@ use anyhow::Result;
 // };
 
 // Local module
+pub mod bench;
+pub use bench::BenchReport;
+
+pub mod cache;
+pub use cache::{CachedLlm, ResponseCache};
+
+pub mod citations;
+pub use citations::{Citation, CitedAnswer, Source};
+
+pub mod download;
+pub use download::DownloadProgress;
+
+pub mod templates;
+pub use templates::{PromptTemplate, PromptTemplates};
+
+pub mod lora;
+pub use lora::LoraAdapter;
+
 pub mod local;
-pub use local::LocalLlm;
+pub use local::{
+    DeviceChoice, DeviceInfo, LocalLlm, LocalLlmConfig, ModelDir, ModelWeights, Precision, Watchdog,
+};
+
+pub mod chat;
+pub use chat::{ChatMessage, ChatRole, ChatSession, TruncationStrategy};
+
+pub(crate) mod grammar;
+
+pub mod handle;
+pub use handle::LlmHandle;
+
+pub mod manager;
+pub use manager::LlmManager;
+
+pub mod params;
+pub use params::GenerationParams;
+pub use tokio_util::sync::CancellationToken;
+
+pub mod ollama;
+pub use ollama::{OllamaLlm, OllamaModel};
+
+pub mod query;
+pub use query::{QueryPlan, SubQuery};
+
+pub mod remote;
+pub use remote::{RemoteLlm, RemoteLlmConfig};
+
+pub mod structured;
+pub use structured::{generate_structured, MalformedOutput};
+
+pub mod summarize;
+
+pub mod tools;
+pub use tools::{ToolCall, ToolRun, ToolRunner};
+
+#[cfg(test)]
+pub(crate) mod test_support;
+
+/// Ways generation can end early. Backends return these through
+/// `anyhow::Error`; `downcast_ref::<LlmError>()` tells them apart.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum LlmError {
+    #[error("Generation was cancelled")]
+    Cancelled,
+    /// Stopped by the backend's watchdog: too slow, or running too long
+    #[error("Generation timed out after {:.1}s ({tokens} tokens)", elapsed.as_secs_f64())]
+    TimedOut { elapsed: Duration, tokens: usize },
+    /// The prompt leaves no room in the model's context window
+    #[error("Context exceeded: the prompt is {tokens} tokens, the context window {limit}")]
+    ContextExceeded { tokens: usize, limit: usize },
+}
+
+/// What `extract_pii` asks the model for
+static PII_SCHEMA: LazyLock<Value> = LazyLock::new(|| {
+    json!({
+        "type": "object",
+        "properties": {
+            "redacted_text": {"type": "string"},
+            "pii": {"type": "object", "additionalProperties": {"type": "string"}},
+        },
+        "required": ["redacted_text", "pii"],
+    })
+});
+
+/// What `extract_entities` asks the model for
+static ENTITIES_SCHEMA: LazyLock<Value> = LazyLock::new(|| {
+    json!({
+        "type": "object",
+        "properties": {
+            "entities": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "name": {"type": "string"},
+                        "type": {"type": "string"},
+                        "properties": {"type": "object"},
+                    },
+                    "required": ["name", "type"],
+                },
+            },
+        },
+        "required": ["entities"],
+    })
+});
+
+/// What `extract_triples` asks the model for
+static TRIPLES_SCHEMA: LazyLock<Value> = LazyLock::new(|| {
+    json!({
+        "type": "object",
+        "properties": {
+            "triples": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "subject": {"type": "string"},
+                        "relation": {"type": "string"},
+                        "object": {"type": "string"},
+                        "confidence": {"type": "number", "minimum": 0, "maximum": 1},
+                        "evidence": {"type": "string"},
+                    },
+                    "required": ["subject", "relation", "object", "confidence"],
+                },
+            },
+        },
+        "required": ["triples"],
+    })
+});
+
+/// Rough token count for backends that don't expose their tokenizer:
+/// about four characters per token for English text
+fn estimate_token_count(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// As many of `chunks` as fit in `budget` tokens together, in their order,
+/// for filling a prompt with retrieved context. Chunks are taken best
+/// first; one too big for what is left is skipped so smaller ones after it
+/// can still go in.
+pub fn pack_chunks<S: AsRef<str>>(
+    chunks: &[S],
+    budget: usize,
+    count_tokens: impl Fn(&str) -> Result<usize>,
+) -> Result<Vec<&str>> {
+    let mut left = budget;
+    let mut packed = Vec::new();
+    for chunk in chunks {
+        let tokens = count_tokens(chunk.as_ref())?;
+        if tokens <= left {
+            left -= tokens;
+            packed.push(chunk.as_ref());
+        }
+    }
+    Ok(packed)
+}
+
+/// A text generation backend. The prompt helpers (`synthesize`,
+/// `extract_pii`, `optimize_prompt`) are built on `generate`, so they behave
+/// the same whichever model is behind it.
+pub trait Llm: Send {
+    /// Generates up to `params.max_tokens` tokens after `prompt` and
+    /// returns them, trimmed, without the stop token
+    fn generate(&mut self, prompt: &str, params: &GenerationParams) -> Result<String> {
+        self.generate_stream(prompt, params, &mut |_| Ok(()))
+    }
+
+    /// Like `generate`, but hands each piece of text to `on_token` as soon as
+    /// it is sampled. Pieces are only emitted once they decode to complete
+    /// characters; the stop token and `params.stop` sequences are never
+    /// emitted. Returning an error from `on_token` stops generation. Returns
+    /// the full (trimmed) output.
+    fn generate_stream(
+        &mut self,
+        prompt: &str,
+        params: &GenerationParams,
+        on_token: &mut dyn FnMut(&str) -> Result<()>,
+    ) -> Result<String>;
+
+    /// Generates for each of `prompts`, returning the outputs in the same
+    /// order; for indexing, which runs the same helper over many chunks.
+    /// Backends that can overlap the work override this; the default
+    /// generates one prompt after the other.
+    fn generate_batch(
+        &mut self,
+        prompts: &[String],
+        params: &GenerationParams,
+    ) -> Result<Vec<String>> {
+        prompts
+            .iter()
+            .map(|prompt| self.generate(prompt, params))
+            .collect()
+    }
+
+    /// An embedding of `text`, for backends whose model can produce one
+    fn embed(&mut self, _text: &str) -> Result<Vec<f32>> {
+        anyhow::bail!("This LLM backend does not produce embeddings")
+    }
+
+    /// How many tokens `text` takes up in the model's context
+    fn count_tokens(&self, text: &str) -> Result<usize>;
+
+    /// How many tokens of prompt and output the model attends to
+    fn context_tokens(&self) -> usize {
+        chat::DEFAULT_CONTEXT_TOKENS
+    }
+
+    /// Wraps a system and user message in the model's chat template. The
+    /// default is Phi-3's.
+    fn format_prompt(&self, system: &str, user: &str) -> String {
+        format!("<|user|>\n{}\n{}\n<|end|>\n<|assistant|>\n", system, user)
+    }
+
+    /// Renders a conversation in the model's chat template, ready for the
+    /// assistant's next reply. The default is Phi-3's.
+    fn format_chat(&self, messages: &[ChatMessage]) -> String {
+        chat::phi3_chat(messages)
+    }
+
+    /// Whether `messages`, rendered with `format_chat`, take up at most
+    /// `max` tokens
+    fn fits_in_context(&self, messages: &[ChatMessage], max: usize) -> Result<bool> {
+        Ok(self.count_tokens(&self.format_chat(messages))? <= max)
+    }
+
+    /// The prompt templates the helpers below use; the built-in ones unless
+    /// the backend was given others
+    fn templates(&self) -> &PromptTemplates {
+        &templates::DEFAULT_TEMPLATES
+    }
+
+    /// The helper prompt from template `name` with `text` as its input:
+    /// filled into the template's `{{text}}` slot, or else sent as the user
+    /// message
+    fn template_prompt(&self, name: &str, text: &str) -> Result<String> {
+        let template = self.templates().get(name)?;
+        let system = template.render(&[("text", text)])?;
+        if template.variables().contains(&"text") {
+            Ok(self.format_prompt(&system, ""))
+        } else {
+            Ok(self.format_prompt(&system, text))
+        }
+    }
+
+    /// The request from template `name` with `text` as its input, as one
+    /// message for `generate_structured`: `text` filled into the template's
+    /// `{{text}}` slot, or else after the template
+    fn template_request(&self, name: &str, text: &str) -> Result<String> {
+        let template = self.templates().get(name)?;
+        let instructions = template.render(&[("text", text)])?;
+        if template.variables().contains(&"text") {
+            Ok(instructions)
+        } else {
+            Ok(format!("{}\n{}", instructions, text))
+        }
+    }
+
+    /// A summary of `text`; text too long for the context is summarized
+    /// in chunks first (see `summarize`)
+    fn synthesize(&mut self, text: &str) -> Result<String> {
+        summarize::summarize(self, text, 500)
+    }
+
+    /// An answer to `question` from retrieved `chunks`, best first, with
+    /// `[n]` citations mapped back to the chunks' node ids (see
+    /// `citations`)
+    fn synthesize_with_sources(
+        &mut self,
+        question: &str,
+        chunks: &[Source],
+    ) -> Result<CitedAnswer> {
+        citations::synthesize_with_sources(self, question, chunks)
+    }
+
+    /// The text with its PII replaced by placeholders, and what each
+    /// placeholder stands for. Output the model gets wrong every time
+    /// leaves the text as it was.
+    fn extract_pii(&mut self, text: &str) -> Result<(String, HashMap<String, String>)> {
+        #[derive(serde::Deserialize)]
+        struct PiiResult {
+            /// Left out by some models when there is nothing to redact
+            redacted_text: Option<String>,
+            #[serde(default)]
+            pii: HashMap<String, String>,
+        }
+
+        let request = self.template_request(templates::EXTRACT_PII, text)?;
+        match structured::generate_structured::<PiiResult, _>(self, &request, &PII_SCHEMA) {
+            Ok(res) => Ok((
+                res.redacted_text.unwrap_or_else(|| text.to_string()),
+                res.pii,
+            )),
+            Err(e) if e.is::<MalformedOutput>() => Ok((text.to_string(), HashMap::new())),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Redacts the PII with a fixed shape deterministically, then finds the
+    /// rest with the detector's NER model, or with PII extraction if it has
+    /// none or the NER model fails, and returns both as one reviewable
+    /// preview
+    fn preview_redaction(&mut self, text: &str) -> Result<RedactionPreview> {
+        self.preview_redaction_with(&pii::DEFAULT_DETECTOR, text)
+    }
+
+    /// `preview_redaction` with a profile's own detector
+    fn preview_redaction_with(
+        &mut self,
+        detector: &PiiDetector,
+        text: &str,
+    ) -> Result<RedactionPreview> {
+        let mut detected = detector.redact(text);
+        let patterns_only = detected.clone();
+        if let Ok(true) = detector.redact_entities(&mut detected) {
+            return Ok(detected);
+        }
+        let (_, found) = self.extract_pii(&patterns_only.redacted_text)?;
+        Ok(patterns_only.merge(found))
+    }
+
+    /// People, organisations, places and other named things in `text`, for
+    /// the knowledge graph
+    fn extract_entities(&mut self, text: &str) -> Result<Vec<Entity>> {
+        #[derive(serde::Deserialize)]
+        struct Entities {
+            entities: Vec<Entity>,
+        }
+        let request = self.template_request(templates::EXTRACT_ENTITIES, text)?;
+        let parsed: Entities = structured::generate_structured(self, &request, &ENTITIES_SCHEMA)?;
+        Ok(parsed.entities)
+    }
+
+    /// The facts stated in `text` as subject-relation-object triples, for
+    /// growing the knowledge graph on ingestion. Triples with an empty part
+    /// are dropped; a span is where the model's quote of its source passage
+    /// first occurs in `text`.
+    fn extract_triples(&mut self, text: &str) -> Result<Vec<Triple>> {
+        #[derive(serde::Deserialize)]
+        struct Extracted {
+            subject: String,
+            relation: String,
+            object: String,
+            confidence: f32,
+            #[serde(default)]
+            evidence: String,
+        }
+        #[derive(serde::Deserialize)]
+        struct Triples {
+            triples: Vec<Extracted>,
+        }
+        let request = self.template_request(templates::EXTRACT_TRIPLES, text)?;
+        let parsed: Triples = structured::generate_structured(self, &request, &TRIPLES_SCHEMA)?;
+        Ok(parsed
+            .triples
+            .into_iter()
+            .filter(|t| {
+                [&t.subject, &t.relation, &t.object]
+                    .iter()
+                    .all(|part| !part.trim().is_empty())
+            })
+            .map(|t| {
+                let evidence = t.evidence.trim();
+                let span = (!evidence.is_empty())
+                    .then(|| text.find(evidence))
+                    .flatten()
+                    .map(|start| start..start + evidence.len());
+                Triple {
+                    subject: t.subject.trim().to_string(),
+                    relation: t.relation.trim().to_string(),
+                    object: t.object.trim().to_string(),
+                    confidence: t.confidence.clamp(0.0, 1.0),
+                    span,
+                }
+            })
+            .collect())
+    }
+
+    /// `query` rewritten for search; `plan_query` also splits it up and
+    /// expands it for embedding
+    fn optimize_prompt(&mut self, query: &str) -> Result<String> {
+        let prompt = self.template_prompt(templates::OPTIMIZE_PROMPT, query)?;
+        self.generate(&prompt, &GenerationParams::new(200))
+    }
+
+    /// `question` rewritten for search, split into sub-queries, each with
+    /// a hypothetical answer to embed (see `query`)
+    fn plan_query(&mut self, question: &str) -> Result<QueryPlan> {
+        query::plan_query(self, question)
+    }
+}
 
 pub enum LlmProvider {
     // OpenAI(Client<async_openai::config::OpenAIConfig>, String),
@@ -48,6 +444,11 @@ impl LlmClient {
         Self::new_claude(None)
     }
 
+    /// An estimate: the Claude CLI exposes no tokenizer
+    pub fn count_tokens(&self, text: &str) -> usize {
+        estimate_token_count(text)
+    }
+
     pub async fn complete(&self, prompt: &str, system_prompt: Option<&str>) -> Result<String> {
         match &self.provider {
             // TODO: Re-enable OpenAI support
@@ -93,3 +494,96 @@ impl LlmClient {
         ClaudeClient::is_available().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::scripted;
+    use super::*;
+
+    #[test]
+    fn test_prompt_helpers_use_generate() {
+        let mut llm = scripted(
+            "```json\n{\"redacted_text\": \"Call [NAME_1]\", \"pii\": {\"[NAME_1]\": \"Alice\"}}\n```",
+        );
+        let (redacted, pii) = llm.extract_pii("Call Alice").unwrap();
+        assert_eq!(redacted, "Call [NAME_1]");
+        assert_eq!(pii.get("[NAME_1]").map(String::as_str), Some("Alice"));
+        assert!(llm.prompts[0].starts_with("<|system|>\nReply with only JSON"));
+        assert!(llm.prompts[0].contains("<|user|>\nYou are a privacy expert."));
+        assert!(llm.prompts[0].ends_with("Call Alice<|end|>\n<|assistant|>\n"));
+
+        // Output that isn't the expected JSON leaves the text as it was
+        let mut llm = scripted("I can't help with that.");
+        let (redacted, pii) = llm.extract_pii("Call Alice").unwrap();
+        assert_eq!(redacted, "Call Alice");
+        assert!(pii.is_empty());
+
+        let mut llm = scripted(
+            "{\"entities\": [{\"name\": \"Alice\", \"type\": \"Person\"}, \
+             {\"name\": \"Acme\", \"type\": \"Organization\", \"properties\": {\"city\": \"Oslo\"}}]}",
+        );
+        let entities = llm.extract_entities("Alice works at Acme in Oslo").unwrap();
+        assert_eq!(entities.len(), 2);
+        assert_eq!(entities[0].name, "Alice");
+        assert_eq!(entities[0].entity_type, "Person");
+        assert_eq!(entities[1].properties["city"], "Oslo");
+        assert!(scripted("not json").extract_entities("text").is_err());
+
+        let text = "Alice works at Acme. Acme is based in Oslo.";
+        let mut llm = scripted(
+            "{\"triples\": [{\"subject\": \"Alice\", \"relation\": \"works_at\", \"object\": \"Acme\", \
+             \"confidence\": 0.9, \"evidence\": \"Alice works at Acme.\"}, \
+             {\"subject\": \"Acme\", \"relation\": \"based_in\", \"object\": \"Oslo\", \
+             \"confidence\": 1.5, \"evidence\": \"not in the text\"}, \
+             {\"subject\": \"\", \"relation\": \"is\", \"object\": \"Oslo\", \"confidence\": 0.2}]}",
+        );
+        let triples = llm.extract_triples(text).unwrap();
+        assert_eq!(triples.len(), 2);
+        assert_eq!(
+            triples[0],
+            Triple {
+                subject: "Alice".to_string(),
+                relation: "works_at".to_string(),
+                object: "Acme".to_string(),
+                confidence: 0.9,
+                span: Some(0..20),
+            }
+        );
+        assert_eq!(triples[1].confidence, 1.0);
+        assert_eq!(triples[1].span, None);
+        assert!(llm.prompts[0].contains("\"evidence\""));
+
+        let mut llm = scripted("A short summary. ");
+        let mut pieces = Vec::new();
+        let summary = llm
+            .generate_stream("text", &GenerationParams::new(10), &mut |piece| {
+                pieces.push(piece.to_string());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(summary, "A short summary.");
+        assert_eq!(pieces, ["A ", "short ", "summary. "]);
+        assert_eq!(llm.synthesize("Some long text").unwrap(), "A short summary.");
+        assert!(llm.embed("text").is_err());
+        assert_eq!(llm.count_tokens("two words").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_token_budget() {
+        let llm = scripted("");
+        // ScriptedLlm counts words; Phi-3's template adds a tag per message
+        // and one for the opened reply
+        let messages = [ChatMessage::system("be brief"), ChatMessage::user("hi")];
+        assert!(llm.fits_in_context(&messages, 6).unwrap());
+        assert!(!llm.fits_in_context(&messages, 5).unwrap());
+
+        let chunks = ["one two three", "four five six seven", "eight", "nine ten"];
+        let count = |text: &str| llm.count_tokens(text);
+        assert_eq!(
+            pack_chunks(&chunks, 6, count).unwrap(),
+            ["one two three", "eight", "nine ten"]
+        );
+        assert!(pack_chunks(&chunks, 0, count).unwrap().is_empty());
+        assert_eq!(pack_chunks(&chunks, 100, count).unwrap(), chunks);
+    }
+}
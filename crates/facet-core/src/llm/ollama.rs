@@ -0,0 +1,309 @@
+//! LLM backend for a local Ollama server
+//!
+//! Uses Ollama's HTTP API, so models the user already pulled are reused
+//! instead of downloading Phi-3 again. Calls block until Ollama answers; run
+//! them off the async runtime.
+
+use super::{chat, estimate_token_count, ChatMessage, GenerationParams, Llm, PromptTemplates};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::{BufRead, BufReader};
+
+pub const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+
+/// A model installed in Ollama, as listed by `/api/tags`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OllamaModel {
+    pub name: String,
+    /// Size on disk in bytes
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub modified_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    models: Vec<OllamaModel>,
+}
+
+/// One line of a streamed `/api/generate` response
+#[derive(Deserialize)]
+struct GenerateChunk {
+    #[serde(default)]
+    response: String,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// Turns an error status into an error carrying Ollama's message
+fn check(response: reqwest::blocking::Response) -> Result<reqwest::blocking::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let text = response.text().unwrap_or_default();
+    match serde_json::from_str::<ErrorResponse>(&text) {
+        Ok(body) => anyhow::bail!("Ollama error ({}): {}", status, body.error),
+        Err(_) => anyhow::bail!("Ollama error ({}): {}", status, text),
+    }
+}
+
+pub struct OllamaLlm {
+    client: reqwest::blocking::Client,
+    base_url: String,
+    model: String,
+    templates: PromptTemplates,
+}
+
+impl OllamaLlm {
+    /// Talks to Ollama on its default port, generating with `model`
+    /// (e.g. "phi3" or "llama3.2:3b")
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::blocking::Client::new(),
+            base_url: DEFAULT_OLLAMA_URL.to_string(),
+            model: model.into(),
+            templates: PromptTemplates::new(),
+        }
+    }
+
+    /// Prompts for the helpers, e.g. with a profile's overrides
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Models installed in this Ollama
+    pub fn list_models(&self) -> Result<Vec<OllamaModel>> {
+        let response = self
+            .client
+            .get(format!("{}/api/tags", self.base_url))
+            .send()
+            .context("Ollama is not reachable")?;
+        let response = check(response)?;
+        Ok(response.json::<TagsResponse>()?.models)
+    }
+}
+
+impl Llm for OllamaLlm {
+    fn generate_stream(
+        &mut self,
+        prompt: &str,
+        params: &GenerationParams,
+        on_token: &mut dyn FnMut(&str) -> Result<()>,
+    ) -> Result<String> {
+        let mut options = json!({
+            "num_predict": params.max_tokens,
+            "temperature": params.temperature,
+            "repeat_penalty": params.repetition_penalty,
+        });
+        if let Some(top_p) = params.top_p {
+            options["top_p"] = json!(top_p);
+        }
+        if let Some(top_k) = params.top_k {
+            options["top_k"] = json!(top_k);
+        }
+        if let Some(seed) = params.seed {
+            options["seed"] = json!(seed);
+        }
+        if !params.stop.is_empty() {
+            options["stop"] = json!(params.stop);
+        }
+        let mut body = json!({
+            "model": self.model,
+            "prompt": prompt,
+            "stream": true,
+            "options": options,
+        });
+        if params.json {
+            body["format"] = params.json_schema.clone().unwrap_or_else(|| json!("json"));
+        }
+        params.check_cancelled()?;
+        let response = self
+            .client
+            .post(format!("{}/api/generate", self.base_url))
+            .json(&body)
+            .send()
+            .context("Ollama is not reachable")?;
+        let response = check(response)?;
+
+        // Newline-delimited JSON, one chunk per line
+        let mut output = String::new();
+        // Cancelling drops the response, which closes the connection and
+        // makes Ollama stop generating
+        for line in BufReader::new(response).lines() {
+            params.check_cancelled()?;
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let chunk: GenerateChunk = serde_json::from_str(&line)?;
+            if let Some(error) = chunk.error {
+                anyhow::bail!("Ollama error: {}", error);
+            }
+            if !chunk.response.is_empty() {
+                on_token(&chunk.response)?;
+                output.push_str(&chunk.response);
+            }
+            if chunk.done {
+                break;
+            }
+        }
+        Ok(output.trim().to_string())
+    }
+
+    fn embed(&mut self, text: &str) -> Result<Vec<f32>> {
+        let response = self
+            .client
+            .post(format!("{}/api/embed", self.base_url))
+            .json(&json!({"model": self.model, "input": text}))
+            .send()
+            .context("Ollama is not reachable")?;
+        let response = check(response)?;
+        response
+            .json::<EmbedResponse>()?
+            .embeddings
+            .into_iter()
+            .next()
+            .context("Ollama returned no embedding")
+    }
+
+    /// Ollama doesn't expose its tokenizer, so this is an estimate
+    fn count_tokens(&self, text: &str) -> Result<usize> {
+        Ok(estimate_token_count(text))
+    }
+
+    fn templates(&self) -> &PromptTemplates {
+        &self.templates
+    }
+
+    /// Ollama applies the model's own chat template, so the messages are
+    /// sent as plain text
+    fn format_prompt(&self, system: &str, user: &str) -> String {
+        format!("{}\n\n{}", system, user)
+    }
+
+    fn format_chat(&self, messages: &[ChatMessage]) -> String {
+        chat::transcript(messages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::serve;
+    use super::super::{templates, PromptTemplate};
+    use super::*;
+
+    #[test]
+    fn test_ollama_generate_embed_and_list() {
+        let (url, server) = serve(vec![
+            "{\"response\":\"Hello\",\"done\":false}\n{\"response\":\" world\",\"done\":false}\n{\"response\":\"\",\"done\":true}\n",
+            "{\"embeddings\":[[0.1,0.2,0.3]]}",
+            "{\"models\":[{\"name\":\"phi3:latest\",\"size\":2176178913}]}",
+        ]);
+        let mut llm = OllamaLlm::new("phi3").with_base_url(format!("{}/", url));
+
+        let params = GenerationParams::precise(10)
+            .with_stop(["\n\n"])
+            .with_json();
+        let mut pieces = Vec::new();
+        let output = llm
+            .generate_stream("Say hello", &params, &mut |piece| {
+                pieces.push(piece.to_string());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(output, "Hello world");
+        assert_eq!(pieces, ["Hello", " world"]);
+
+        assert_eq!(llm.embed("hello").unwrap(), vec![0.1, 0.2, 0.3]);
+
+        let models = llm.list_models().unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].name, "phi3:latest");
+
+        let requests = server.join().unwrap();
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["format"], "json");
+        assert_eq!(
+            body["options"],
+            json!({
+                "num_predict": 10,
+                "temperature": 0.0,
+                "repeat_penalty": 1.0,
+                "seed": 0,
+                "stop": ["\n\n"]
+            })
+        );
+        let requests: Vec<String> = requests.into_iter().map(|r| r.line).collect();
+        assert_eq!(
+            requests,
+            [
+                "POST /api/generate HTTP/1.1",
+                "POST /api/embed HTTP/1.1",
+                "GET /api/tags HTTP/1.1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ollama_reports_errors() {
+        let (url, server) = serve(vec!["{\"error\":\"model 'nope' not found\"}\n"]);
+        let mut llm = OllamaLlm::new("nope").with_base_url(url);
+        let err = llm.generate("hi", &GenerationParams::new(10)).unwrap_err();
+        assert!(err.to_string().contains("model 'nope' not found"));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_ollama_uses_template_overrides() {
+        let reply = "{\"response\":\"Done\",\"done\":true}\n";
+        let (url, server) = serve(vec![reply, reply]);
+        let mut templates = PromptTemplates::new();
+        templates.insert(PromptTemplate::new(
+            templates::SYNTHESIZE,
+            "Summarize for a child:\n{{text}}",
+        ));
+        let mut llm = OllamaLlm::new("phi3")
+            .with_base_url(url)
+            .with_templates(templates);
+        assert_eq!(llm.synthesize("Long text").unwrap(), "Done");
+        assert_eq!(llm.optimize_prompt("query").unwrap(), "Done");
+
+        let requests = server.join().unwrap();
+        let prompts: Vec<String> = requests
+            .iter()
+            .map(|r| {
+                let body: serde_json::Value = serde_json::from_str(&r.body).unwrap();
+                body["prompt"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(prompts[0], "Summarize for a child:\nLong text\n\n");
+        // The built-in template, with the query as the user message
+        assert!(prompts[1].starts_with("You are a prompt engineer."));
+        assert!(prompts[1].ends_with("\n\nquery"));
+    }
+}
@@ -0,0 +1,171 @@
+//! Per-call sampling settings
+
+use super::LlmError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio_util::sync::CancellationToken;
+
+/// How a single `generate` call samples. Backends apply what their API
+/// supports; everything here is understood by `LocalLlm` and Ollama.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationParams {
+    /// Upper bound on generated tokens
+    pub max_tokens: usize,
+    /// 0 always picks the most likely token
+    pub temperature: f64,
+    /// Nucleus sampling: only the most likely tokens covering this much
+    /// probability are considered
+    pub top_p: Option<f64>,
+    /// Only the `top_k` most likely tokens are considered
+    pub top_k: Option<usize>,
+    /// Penalty for repeating recent tokens; 1.0 disables it
+    pub repetition_penalty: f32,
+    /// Fixed seed for reproducible output; a fresh one per call when unset
+    pub seed: Option<u64>,
+    /// Generation ends as soon as the output contains one of these, e.g.
+    /// "```" after a code block. The stop sequence itself is not returned.
+    pub stop: Vec<String>,
+    /// Constrain the output to one JSON object or array. `LocalLlm`
+    /// enforces this while decoding; Ollama and OpenAI-compatible servers
+    /// are asked for their JSON mode.
+    pub json: bool,
+    /// With `json`, the JSON schema the output should match. Ollama and
+    /// OpenAI-compatible servers enforce it; `LocalLlm` only enforces JSON.
+    pub json_schema: Option<Value>,
+    /// Lets the caller abort generation part way, e.g. when the user
+    /// cancels; the call then fails with `LlmError::Cancelled`
+    #[serde(skip)]
+    pub cancel: Option<CancellationToken>,
+}
+
+impl Default for GenerationParams {
+    fn default() -> Self {
+        Self {
+            max_tokens: 256,
+            temperature: 0.7,
+            top_p: Some(0.9),
+            top_k: None,
+            repetition_penalty: 1.0,
+            seed: None,
+            stop: Vec::new(),
+            json: false,
+            json_schema: None,
+            cancel: None,
+        }
+    }
+}
+
+impl GenerationParams {
+    /// The default, creative sampling with room for `max_tokens`
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens,
+            ..Self::default()
+        }
+    }
+
+    /// Greedy sampling with a fixed seed, for output that has to follow a
+    /// format (e.g. JSON) and should not vary between runs
+    pub fn precise(max_tokens: usize) -> Self {
+        Self {
+            max_tokens,
+            temperature: 0.0,
+            top_p: None,
+            top_k: None,
+            repetition_penalty: 1.0,
+            seed: Some(0),
+            stop: Vec::new(),
+            json: false,
+            json_schema: None,
+            cancel: None,
+        }
+    }
+
+    pub fn with_stop<I, S>(mut self, stop: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.stop = stop.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_json(mut self) -> Self {
+        self.json = true;
+        self
+    }
+
+    /// JSON output matching `schema`
+    pub fn with_json_schema(mut self, schema: Value) -> Self {
+        self.json = true;
+        self.json_schema = Some(schema);
+        self
+    }
+
+    pub fn with_cancel(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Fails once the caller has cancelled; backends check this between
+    /// tokens
+    pub(crate) fn check_cancelled(&self) -> Result<(), LlmError> {
+        match &self.cancel {
+            Some(cancel) if cancel.is_cancelled() => Err(LlmError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    /// Where `text` ends once the stop sequences are applied: at the first
+    /// stop sequence it contains, if any
+    pub(crate) fn find_stop(&self, text: &str) -> Option<usize> {
+        self.stop
+            .iter()
+            .filter(|stop| !stop.is_empty())
+            .filter_map(|stop| text.find(stop.as_str()))
+            .min()
+    }
+
+    /// How much of `text` can be streamed without giving away the start of
+    /// a stop sequence that later tokens may complete
+    pub(crate) fn streamable_len(&self, text: &str) -> usize {
+        let held = self
+            .stop
+            .iter()
+            .flat_map(|stop| {
+                // Longest proper prefix of `stop` that `text` ends with
+                stop.char_indices()
+                    .skip(1)
+                    .map(|(i, _)| &stop[..i])
+                    .filter(|prefix| text.ends_with(prefix))
+                    .map(str::len)
+            })
+            .max()
+            .unwrap_or(0);
+        text.len() - held
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stop_sequences() {
+        let params = GenerationParams::new(10).with_stop(["```", "\n\n###"]);
+        assert_eq!(params.find_stop("{\"a\": 1}\n```\nmore"), Some(9));
+        assert_eq!(params.find_stop("one\n\n###two```"), Some(3));
+        assert_eq!(params.find_stop("nothing here"), None);
+
+        // A possible start of a stop sequence is held back
+        assert_eq!(params.streamable_len("text\n\n#"), 4);
+        assert_eq!(params.streamable_len("text``"), 4);
+        assert_eq!(params.streamable_len("text#"), 5);
+        assert_eq!(params.streamable_len("naïve"), "naïve".len());
+
+        let none = GenerationParams::new(10);
+        assert_eq!(none.find_stop("```"), None);
+        assert_eq!(none.streamable_len("```"), 3);
+    }
+}
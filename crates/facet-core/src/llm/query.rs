@@ -0,0 +1,146 @@
+//! Query planning
+//!
+//! `plan_query` turns a question into what the retriever runs: the question
+//! rewritten for search (as `optimize_prompt` does), split into sub-queries
+//! when it asks several things at once, each with a hypothetical answer to
+//! embed in place of the query (HyDE). A passage that answers a question
+//! tends to lie closer to the documents that do than the question itself.
+//!
+//! The decomposition comes from the `plan_query` template as structured
+//! output; the hypothetical answers from `hypothetical_answer`, one prompt
+//! per sub-query.
+
+use super::{structured, templates, GenerationParams, Llm, MalformedOutput};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::LazyLock;
+
+/// Sub-queries a plan keeps; the rest are dropped
+pub const MAX_SUB_QUERIES: usize = 5;
+
+/// Tokens of a hypothetical answer
+const HYPOTHETICAL_TOKENS: usize = 150;
+
+/// What `plan_query` asks the model for
+static PLAN_SCHEMA: LazyLock<Value> = LazyLock::new(|| {
+    json!({
+        "type": "object",
+        "properties": {
+            "rewritten": {"type": "string"},
+            "sub_queries": {"type": "array", "items": {"type": "string"}},
+        },
+        "required": ["rewritten", "sub_queries"],
+    })
+});
+
+/// One search the retriever runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubQuery {
+    pub query: String,
+    /// A passage that would answer `query`; empty if none was generated
+    pub hypothetical_answer: String,
+}
+
+impl SubQuery {
+    /// The text to embed for this search: the hypothetical answer, or the
+    /// query if there is none
+    pub fn embedding_text(&self) -> &str {
+        if self.hypothetical_answer.is_empty() {
+            &self.query
+        } else {
+            &self.hypothetical_answer
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryPlan {
+    pub question: String,
+    /// The whole question rephrased for search
+    pub rewritten: String,
+    /// At least one; one per part of a multi-part question
+    pub sub_queries: Vec<SubQuery>,
+}
+
+/// The plan for answering `question`. Output the model gets wrong every
+/// time plans the question as one search as it was asked.
+pub fn plan_query<L: Llm + ?Sized>(llm: &mut L, question: &str) -> Result<QueryPlan> {
+    #[derive(Deserialize)]
+    struct Plan {
+        rewritten: String,
+        sub_queries: Vec<String>,
+    }
+
+    let request = llm.template_request(templates::PLAN_QUERY, question)?;
+    let (rewritten, queries) =
+        match structured::generate_structured::<Plan, _>(llm, &request, &PLAN_SCHEMA) {
+            Ok(plan) => (plan.rewritten, plan.sub_queries),
+            Err(e) if e.is::<MalformedOutput>() => (question.to_string(), Vec::new()),
+            Err(e) => return Err(e),
+        };
+    let rewritten = match rewritten.trim() {
+        "" => question.to_string(),
+        rewritten => rewritten.to_string(),
+    };
+    let mut queries: Vec<String> = queries
+        .iter()
+        .map(|query| query.trim().to_string())
+        .filter(|query| !query.is_empty())
+        .collect();
+    queries.dedup();
+    queries.truncate(MAX_SUB_QUERIES);
+    if queries.is_empty() {
+        queries.push(rewritten.clone());
+    }
+
+    let params = GenerationParams::precise(HYPOTHETICAL_TOKENS);
+    let mut sub_queries = Vec::with_capacity(queries.len());
+    for query in queries {
+        let prompt = llm.template_prompt(templates::HYPOTHETICAL_ANSWER, &query)?;
+        let hypothetical_answer = llm.generate(&prompt, &params)?;
+        sub_queries.push(SubQuery {
+            query,
+            hypothetical_answer,
+        });
+    }
+    Ok(QueryPlan {
+        question: question.to_string(),
+        rewritten,
+        sub_queries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::scripted_replies;
+    use super::*;
+
+    #[test]
+    fn test_multi_part_questions_are_decomposed() {
+        let mut llm = scripted_replies(&[
+            r#"{"rewritten": "Ada Lovelace home city and employer",
+                "sub_queries": ["Where does Ada live?", " ", "Who does Ada work for?"]}"#,
+            "Ada lives in London.",
+            "Ada works for Acme.",
+        ]);
+        let plan = llm
+            .plan_query("Where does Ada live and who does she work for?")
+            .unwrap();
+
+        assert_eq!(plan.rewritten, "Ada Lovelace home city and employer");
+        assert_eq!(plan.sub_queries.len(), 2);
+        assert_eq!(plan.sub_queries[1].query, "Who does Ada work for?");
+        assert_eq!(plan.sub_queries[1].embedding_text(), "Ada works for Acme.");
+        assert!(llm.prompts[1].contains("Where does Ada live?"));
+    }
+
+    #[test]
+    fn test_malformed_plan_searches_the_question() {
+        let mut llm = scripted_replies(&["no", "no", "no", ""]);
+        let plan = llm.plan_query("Where does Ada live?").unwrap();
+        assert_eq!(plan.rewritten, "Where does Ada live?");
+        assert_eq!(plan.sub_queries.len(), 1);
+        assert_eq!(plan.sub_queries[0].embedding_text(), "Where does Ada live?");
+    }
+}
@@ -0,0 +1,517 @@
+//! LLM backend for servers speaking the OpenAI-compatible chat API
+//!
+//! Works with llama.cpp's server, vLLM, LM Studio and hosted endpoints.
+//! Because text may leave the machine, the backend is off unless
+//! `RemoteLlmConfig::enabled` is set, and by default every prompt goes
+//! through local PII redaction (and the profile's confirmation policy)
+//! before it is sent; placeholders in the reply are swapped back for the
+//! real values before it is returned. Calls block until the server
+//! answers; run them off the async runtime.
+
+use super::{chat, estimate_token_count, ChatMessage, GenerationParams, Llm, PromptTemplates};
+use crate::pii::{self, PiiDetector};
+use crate::redaction::{RedactionPolicy, RedactionPreview, RedactionSession};
+use anyhow::{Context, Result};
+use facet_types::profiles::RedactionPreferences;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::{BufRead, BufReader};
+
+pub const DEFAULT_REMOTE_URL: &str = "http://localhost:8080/v1";
+
+fn default_remote_url() -> String {
+    DEFAULT_REMOTE_URL.to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Settings for a `RemoteLlm`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteLlmConfig {
+    /// Nothing is sent anywhere unless this is set
+    #[serde(default)]
+    pub enabled: bool,
+    /// API root including the version, e.g. `http://localhost:8080/v1`
+    #[serde(default = "default_remote_url")]
+    pub base_url: String,
+    pub model: String,
+    /// Sent as a bearer token when set
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Redact PII locally before sending. Turning this off sends prompts
+    /// exactly as given.
+    #[serde(default = "default_true")]
+    pub redact_pii: bool,
+}
+
+impl RemoteLlmConfig {
+    /// A disabled config for `model`; set `enabled` to use it
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            enabled: false,
+            base_url: default_remote_url(),
+            model: model.into(),
+            api_key: None,
+            redact_pii: true,
+        }
+    }
+}
+
+/// The local step every outgoing text passes through
+struct Redaction {
+    /// Local model for the PII only a model finds; `None` redacts with
+    /// patterns alone
+    redactor: Option<Box<dyn Llm>>,
+    policy: RedactionPolicy,
+    confirm: Box<dyn Fn(&RedactionPreview) -> bool + Send>,
+}
+
+/// One `data:` event of a streamed chat completion
+#[derive(Deserialize)]
+struct ChatChunk {
+    choices: Vec<ChunkChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChunkChoice {
+    #[serde(default)]
+    delta: Delta,
+}
+
+#[derive(Deserialize, Default)]
+struct Delta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+}
+
+pub struct RemoteLlm {
+    client: reqwest::blocking::Client,
+    config: RemoteLlmConfig,
+    redaction: Option<Redaction>,
+    /// Replaces the default `PiiDetector` in redaction
+    detector: Option<PiiDetector>,
+    templates: PromptTemplates,
+}
+
+impl RemoteLlm {
+    /// Fails unless `config.enabled` is set
+    pub fn new(config: RemoteLlmConfig) -> Result<Self> {
+        if !config.enabled {
+            anyhow::bail!(
+                "The remote LLM backend is disabled; enable it in the config to send text to {}",
+                config.base_url
+            );
+        }
+        Ok(Self {
+            client: reqwest::blocking::Client::new(),
+            config: RemoteLlmConfig {
+                base_url: config.base_url.trim_end_matches('/').to_string(),
+                ..config
+            },
+            redaction: None,
+            detector: None,
+            templates: PromptTemplates::new(),
+        })
+    }
+
+    /// Redacts outgoing text with `redactor`, a local model, and applies
+    /// `policy`. `confirm` is asked whenever the policy wants the user to
+    /// review a preview; returning `false` cancels the request.
+    pub fn with_redaction<F>(
+        mut self,
+        redactor: Box<dyn Llm>,
+        policy: RedactionPolicy,
+        confirm: F,
+    ) -> Self
+    where
+        F: Fn(&RedactionPreview) -> bool + Send + 'static,
+    {
+        self.redaction = Some(Redaction {
+            redactor: Some(redactor),
+            policy,
+            confirm: Box::new(confirm),
+        });
+        self
+    }
+
+    /// Like `with_redaction` without a local model: only the PII
+    /// `PiiDetector` recognises (emails, phone numbers, card numbers, ...,
+    /// and names and places if it has a NER model) is redacted
+    pub fn with_pattern_redaction<F>(mut self, policy: RedactionPolicy, confirm: F) -> Self
+    where
+        F: Fn(&RedactionPreview) -> bool + Send + 'static,
+    {
+        self.redaction = Some(Redaction {
+            redactor: None,
+            policy,
+            confirm: Box::new(confirm),
+        });
+        self
+    }
+
+    /// `with_pattern_redaction` under a profile's redaction preferences.
+    /// A profile with redaction enabled turns it on even where the config
+    /// turned it off; one without leaves the config to decide.
+    pub fn with_profile_redaction<F>(
+        mut self,
+        preferences: &RedactionPreferences,
+        confirm: F,
+    ) -> Self
+    where
+        F: Fn(&RedactionPreview) -> bool + Send + 'static,
+    {
+        if preferences.enabled {
+            self.config.redact_pii = true;
+        }
+        self.with_pattern_redaction(preferences.into(), confirm)
+    }
+
+    /// Redacts with a profile's PII categories and custom patterns
+    pub fn with_pii_detector(mut self, detector: PiiDetector) -> Self {
+        self.detector = Some(detector);
+        self
+    }
+
+    /// Prompts for the helpers, e.g. with a profile's overrides
+    pub fn with_templates(mut self, templates: PromptTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    pub fn config(&self) -> &RemoteLlmConfig {
+        &self.config
+    }
+
+    /// The text that may be sent in place of `text`, and the session for
+    /// rehydrating the reply if anything was redacted
+    fn outgoing(&mut self, text: &str) -> Result<(String, Option<RedactionSession>)> {
+        if !self.config.redact_pii {
+            return Ok((text.to_string(), None));
+        }
+        let redaction = self.redaction.as_mut().context(
+            "PII redaction is required before sending to a remote LLM, but no redactor is set",
+        )?;
+        let detector = self.detector.as_ref().unwrap_or(&pii::DEFAULT_DETECTOR);
+        let preview = match redaction.redactor.as_mut() {
+            Some(redactor) => redactor.preview_redaction_with(detector, text)?,
+            None => {
+                let mut preview = detector.redact(text);
+                detector.redact_entities(&mut preview)?;
+                preview
+            }
+        };
+        let session = if preview.is_empty() {
+            None
+        } else {
+            Some(RedactionSession::new(&preview)?)
+        };
+        let confirm = &redaction.confirm;
+        let text = redaction
+            .policy
+            .approve(preview, |preview| confirm(preview))?;
+        Ok((text, session))
+    }
+
+    fn post(&self, path: &str, body: serde_json::Value) -> Result<reqwest::blocking::Response> {
+        let mut request = self
+            .client
+            .post(format!("{}{}", self.config.base_url, path))
+            .json(&body);
+        if let Some(key) = &self.config.api_key {
+            request = request.bearer_auth(key);
+        }
+        let response = request.send().context("The remote LLM is not reachable")?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().unwrap_or_default();
+            anyhow::bail!("Remote LLM error ({}): {}", status, text);
+        }
+        Ok(response)
+    }
+}
+
+impl Llm for RemoteLlm {
+    fn generate_stream(
+        &mut self,
+        prompt: &str,
+        params: &GenerationParams,
+        on_token: &mut dyn FnMut(&str) -> Result<()>,
+    ) -> Result<String> {
+        params.check_cancelled()?;
+        let (prompt, session) = self.outgoing(prompt)?;
+        let mut body = json!({
+            "model": self.config.model,
+            "messages": [{"role": "user", "content": prompt}],
+            "max_tokens": params.max_tokens,
+            "temperature": params.temperature,
+            "stream": true,
+        });
+        if let Some(top_p) = params.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        if let Some(seed) = params.seed {
+            body["seed"] = json!(seed);
+        }
+        if !params.stop.is_empty() {
+            body["stop"] = json!(params.stop);
+        }
+        match (&params.json_schema, params.json) {
+            (Some(schema), true) => {
+                body["response_format"] = json!({
+                    "type": "json_schema",
+                    "json_schema": {"name": "output", "schema": schema},
+                });
+            }
+            (None, true) => body["response_format"] = json!({"type": "json_object"}),
+            (_, false) => {}
+        }
+        // Not part of the OpenAI API, but understood by llama.cpp and vLLM;
+        // only sent when set so strict servers keep working
+        if let Some(top_k) = params.top_k {
+            body["top_k"] = json!(top_k);
+        }
+        if params.repetition_penalty != 1.0 {
+            body["repetition_penalty"] = json!(params.repetition_penalty);
+        }
+        let response = self.post("/chat/completions", body)?;
+
+        // Placeholders in the reply are shown with their real values
+        let mut rehydrator = session.as_ref().map(RedactionSession::open).transpose()?;
+
+        // Server-sent events: `data: {chunk}` lines, ending with `data: [DONE]`
+        let mut output = String::new();
+        for line in BufReader::new(response).lines() {
+            params.check_cancelled()?;
+            let line = line?;
+            let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                continue;
+            };
+            if data == "[DONE]" {
+                break;
+            }
+            let chunk: ChatChunk = serde_json::from_str(data)?;
+            for choice in chunk.choices {
+                if let Some(content) = choice.delta.content.filter(|c| !c.is_empty()) {
+                    let content = match rehydrator.as_mut() {
+                        Some(rehydrator) => rehydrator.push(&content),
+                        None => content,
+                    };
+                    if !content.is_empty() {
+                        on_token(&content)?;
+                        output.push_str(&content);
+                    }
+                }
+            }
+        }
+        if let Some(rest) = rehydrator.as_mut().map(|r| r.finish()) {
+            if !rest.is_empty() {
+                on_token(&rest)?;
+                output.push_str(&rest);
+            }
+        }
+        Ok(output.trim().to_string())
+    }
+
+    fn embed(&mut self, text: &str) -> Result<Vec<f32>> {
+        let (text, _) = self.outgoing(text)?;
+        let response = self.post(
+            "/embeddings",
+            json!({"model": self.config.model, "input": text}),
+        )?;
+        response
+            .json::<EmbeddingResponse>()?
+            .data
+            .into_iter()
+            .next()
+            .map(|data| data.embedding)
+            .context("The remote LLM returned no embedding")
+    }
+
+    /// The API has no tokenizer endpoint, so this is an estimate
+    fn count_tokens(&self, text: &str) -> Result<usize> {
+        Ok(estimate_token_count(text))
+    }
+
+    fn templates(&self) -> &PromptTemplates {
+        &self.templates
+    }
+
+    /// The server applies the model's chat template, so the messages are
+    /// sent as plain text
+    fn format_prompt(&self, system: &str, user: &str) -> String {
+        format!("{}\n\n{}", system, user)
+    }
+
+    fn format_chat(&self, messages: &[ChatMessage]) -> String {
+        chat::transcript(messages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::{scripted, serve};
+    use super::*;
+    use crate::pii::{CustomPattern, PiiConfig};
+
+    fn enabled(base_url: &str, redact_pii: bool) -> RemoteLlmConfig {
+        RemoteLlmConfig {
+            enabled: true,
+            base_url: base_url.to_string(),
+            api_key: Some("secret".to_string()),
+            redact_pii,
+            ..RemoteLlmConfig::new("local-model")
+        }
+    }
+
+    const REDACTED: &str =
+        "{\"redacted_text\": \"Email [EMAIL_1]\", \"pii\": {\"[EMAIL_1]\": \"alice@example.com\"}}";
+
+    #[test]
+    fn test_remote_llm_is_opt_in() {
+        let config: RemoteLlmConfig = serde_json::from_str(r#"{"model": "m"}"#).unwrap();
+        assert!(!config.enabled);
+        assert!(config.redact_pii);
+        assert_eq!(config.base_url, DEFAULT_REMOTE_URL);
+        assert!(RemoteLlm::new(config).is_err());
+
+        // Redaction is on by default and needs a redactor
+        let mut llm = RemoteLlm::new(enabled("http://127.0.0.1:9", true)).unwrap();
+        let err = llm
+            .generate("Email alice@example.com", &GenerationParams::new(10))
+            .unwrap_err();
+        assert!(err.to_string().contains("no redactor"));
+    }
+
+    #[test]
+    fn test_remote_llm_sends_redacted_text() {
+        let (url, server) = serve(vec![
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n\
+             data: {\"choices\":[{\"delta\":{\"content\":\"Sent\"}}]}\n\n\
+             data: {\"choices\":[{\"delta\":{\"content\":\" to [EMA\"}}]}\n\n\
+             data: {\"choices\":[{\"delta\":{\"content\":\"IL_1]\"}}]}\n\n\
+             data: [DONE]\n\n",
+            "{\"data\":[{\"embedding\":[0.5,0.25]}]}",
+        ]);
+        let mut llm = RemoteLlm::new(enabled(&format!("{}/v1/", url), true))
+            .unwrap()
+            .with_redaction(
+                Box::new(scripted(REDACTED)),
+                RedactionPolicy::default(),
+                |preview| preview.replacements.len() == 1,
+            );
+
+        let mut pieces = Vec::new();
+        let output = llm
+            .generate_stream(
+                "Email alice@example.com",
+                &GenerationParams::new(10),
+                &mut |piece| {
+                    pieces.push(piece.to_string());
+                    Ok(())
+                },
+            )
+            .unwrap();
+        // The reply is rehydrated, even with the placeholder split
+        assert_eq!(output, "Sent to alice@example.com");
+        assert_eq!(pieces, ["Sent", " to ", "alice@example.com"]);
+        assert_eq!(
+            llm.embed("Email alice@example.com").unwrap(),
+            vec![0.5, 0.25]
+        );
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests[0].line, "POST /v1/chat/completions HTTP/1.1");
+        assert_eq!(requests[1].line, "POST /v1/embeddings HTTP/1.1");
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["max_tokens"], 10);
+        assert_eq!(body["top_p"], 0.9);
+        assert!(body.get("top_k").is_none() && body.get("seed").is_none());
+        assert!(body.get("response_format").is_none());
+        for request in &requests {
+            assert!(request.body.contains("Email [EMAIL_1]"));
+            assert!(!request.body.contains("alice@example.com"));
+        }
+    }
+
+    #[test]
+    fn test_remote_llm_rejected_redaction_sends_nothing() {
+        // Nothing listens on this port, so a request would fail differently
+        let mut llm = RemoteLlm::new(enabled("http://127.0.0.1:9", true))
+            .unwrap()
+            .with_redaction(
+                Box::new(scripted(REDACTED)),
+                RedactionPolicy::default(),
+                |_| false,
+            );
+        let err = llm
+            .generate("Email alice@example.com", &GenerationParams::new(10))
+            .unwrap_err();
+        assert!(err.to_string().contains("rejected"));
+    }
+
+    #[test]
+    fn test_remote_llm_profile_redaction() {
+        let (url, server) = serve(vec!["{\"data\":[{\"embedding\":[1.0]}]}"]);
+        let preferences = RedactionPreferences {
+            enabled: true,
+            confirm_before_send: true,
+            auto_approve_threshold: 1,
+        };
+        // The profile turns redaction back on, and its threshold lets a
+        // single replacement through unasked
+        let mut llm = RemoteLlm::new(enabled(&url, false))
+            .unwrap()
+            .with_profile_redaction(&preferences, |_| panic!("should not ask"));
+        assert!(llm.config().redact_pii);
+        llm.embed("Email alice@example.com").unwrap();
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].body.contains("Email [EMAIL_1]"));
+    }
+
+    #[test]
+    fn test_remote_llm_pattern_redaction_needs_no_model() {
+        let embedding = "{\"data\":[{\"embedding\":[1.0]}]}";
+        let (url, server) = serve(vec![embedding, embedding]);
+        let mut llm = RemoteLlm::new(enabled(&url, true))
+            .unwrap()
+            .with_pattern_redaction(RedactionPolicy::default(), |preview| {
+                preview.replacements.len() == 2
+            });
+        llm.embed("Alice: alice@example.com, 555-123-4567").unwrap();
+
+        // A profile that only redacts its employee IDs
+        let config = PiiConfig {
+            kinds: Vec::new(),
+            custom: vec![CustomPattern {
+                name: "EMPLOYEE_ID".to_string(),
+                pattern: r"EMP-\d+".to_string(),
+            }],
+        };
+        let mut llm = RemoteLlm::new(enabled(&url, true))
+            .unwrap()
+            .with_pattern_redaction(RedactionPolicy::default(), |preview| {
+                preview.replacements[0].category == "EMPLOYEE_ID"
+            })
+            .with_pii_detector(PiiDetector::from_config(&config).unwrap());
+        llm.embed("EMP-42: alice@example.com").unwrap();
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].body.contains("Alice: [EMAIL_1], [PHONE_1]"));
+        assert!(requests[1]
+            .body
+            .contains("[EMPLOYEE_ID_1]: alice@example.com"));
+    }
+}
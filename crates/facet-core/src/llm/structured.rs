@@ -0,0 +1,146 @@
+//! Typed structured output
+//!
+//! `generate_structured` asks the model for JSON matching a schema and
+//! parses it into a Rust type. The schema goes into the prompt (the
+//! `structured_output` template) and to the backend as
+//! `GenerationParams::json_schema`. Output that still doesn't parse as the
+//! type is sent back with the parse error for the model to correct, a few
+//! times before giving up.
+
+use super::{templates, ChatMessage, GenerationParams, Llm};
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use thiserror::Error;
+
+/// Replies `generate_structured` tries before giving up
+pub const MAX_ATTEMPTS: usize = 3;
+
+/// No reply parsed as the type asked for. Returned through
+/// `anyhow::Error`, like `LlmError`, so callers with a fallback can tell it
+/// apart from generation failing.
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("The model returned malformed output {attempts} times; last: {error}")]
+pub struct MalformedOutput {
+    pub attempts: usize,
+    /// Why the last reply didn't parse
+    pub error: String,
+}
+
+/// The model's reply to `prompt`, a request in plain text, as a `T`
+/// matching `schema`. Greedy, with room for 1000 tokens.
+pub fn generate_structured<T, L>(llm: &mut L, prompt: &str, schema: &Value) -> Result<T>
+where
+    T: DeserializeOwned,
+    L: Llm + ?Sized,
+{
+    generate_structured_with(llm, prompt, schema, &GenerationParams::precise(1000))
+}
+
+/// `generate_structured` sampling with `params`
+pub fn generate_structured_with<T, L>(
+    llm: &mut L,
+    prompt: &str,
+    schema: &Value,
+    params: &GenerationParams,
+) -> Result<T>
+where
+    T: DeserializeOwned,
+    L: Llm + ?Sized,
+{
+    let instructions = llm
+        .templates()
+        .get(templates::STRUCTURED_OUTPUT)?
+        .render(&[("schema", &serde_json::to_string_pretty(schema)?)])?;
+    let mut messages = vec![ChatMessage::system(instructions), ChatMessage::user(prompt)];
+    let params = params.clone().with_json_schema(schema.clone());
+
+    let mut error = String::new();
+    for _ in 0..MAX_ATTEMPTS {
+        let chat = llm.format_chat(&messages);
+        let output = llm.generate(&chat, &params)?;
+        match parse(&output) {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                error = e.to_string();
+                messages.push(ChatMessage::assistant(output));
+                messages.push(ChatMessage::user(format!(
+                    "That does not match the schema: {}. Reply again with only the corrected JSON.",
+                    e
+                )));
+            }
+        }
+    }
+    Err(MalformedOutput {
+        attempts: MAX_ATTEMPTS,
+        error,
+    }
+    .into())
+}
+
+/// `output` as a `T`, also when a backend that doesn't enforce JSON put it
+/// in a markdown code fence
+fn parse<T: DeserializeOwned>(output: &str) -> serde_json::Result<T> {
+    let output = output.trim();
+    let json = output
+        .strip_prefix("```json")
+        .or_else(|| output.strip_prefix("```"))
+        .and_then(|fenced| fenced.strip_suffix("```"))
+        .unwrap_or(output);
+    serde_json::from_str(json.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::{scripted, scripted_replies};
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct City {
+        name: String,
+        population: u64,
+    }
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {"name": {"type": "string"}, "population": {"type": "integer"}},
+            "required": ["name", "population"],
+        })
+    }
+
+    #[test]
+    fn test_output_parses_into_the_type() {
+        let mut llm = scripted("```json\n{\"name\": \"Oslo\", \"population\": 709000}\n```");
+        let city: City = generate_structured(&mut llm, "The capital of Norway", &schema()).unwrap();
+        assert_eq!(
+            city,
+            City {
+                name: "Oslo".to_string(),
+                population: 709000
+            }
+        );
+        assert!(llm.prompts[0].contains("\"required\": ["));
+        assert!(llm.prompts[0].contains("The capital of Norway"));
+    }
+
+    #[test]
+    fn test_malformed_output_is_retried() {
+        let mut llm = scripted_replies(&[
+            r#"{"name": "Oslo"}"#,
+            r#"{"name": "Oslo", "population": 709000}"#,
+        ]);
+        let city: City = generate_structured(&mut llm, "The capital of Norway", &schema()).unwrap();
+        assert_eq!(city.population, 709000);
+        assert_eq!(llm.prompts.len(), 2);
+        assert!(llm.prompts[1].contains("missing field `population`"));
+
+        let mut llm = scripted("Oslo, I think");
+        let err = generate_structured::<City, _>(&mut llm, "The capital", &schema()).unwrap_err();
+        let malformed = err.downcast_ref::<MalformedOutput>().unwrap();
+        assert_eq!(malformed.attempts, MAX_ATTEMPTS);
+        assert_eq!(llm.prompts.len(), MAX_ATTEMPTS);
+    }
+}
@@ -0,0 +1,139 @@
+//! Summaries of text longer than the context
+//!
+//! Text that fits in the model's context next to its summary is summarized
+//! in one prompt. Longer text is summarized map-reduce style: split into
+//! chunks that fit, each chunk summarized on its own, and the summaries
+//! joined and summarized again, for as many rounds as it takes to fit one
+//! prompt. The chunks of a round don't depend on each other, so they go to
+//! the backend as one batch.
+
+use super::{templates, GenerationParams, Llm};
+use anyhow::Result;
+
+/// A summary of `text` in up to `max_tokens` tokens, however long `text`
+/// is, with the backend's `synthesize` template
+pub fn summarize<L: Llm + ?Sized>(llm: &mut L, text: &str, max_tokens: usize) -> Result<String> {
+    let overhead = llm.count_tokens(&llm.template_prompt(templates::SYNTHESIZE, "")?)?;
+    let budget = llm.context_tokens().saturating_sub(max_tokens + overhead);
+    if budget == 0 {
+        anyhow::bail!(
+            "The model's context of {} tokens has no room for text to summarize",
+            llm.context_tokens()
+        );
+    }
+
+    let params = GenerationParams::new(max_tokens);
+    let mut text = text.to_string();
+    loop {
+        let chunks = chunk_text(&text, budget, |chunk| llm.count_tokens(chunk))?;
+        if chunks.len() <= 1 {
+            let prompt = llm.template_prompt(templates::SYNTHESIZE, &text)?;
+            return llm.generate(&prompt, &params);
+        }
+        let prompts = chunks
+            .iter()
+            .map(|chunk| llm.template_prompt(templates::SYNTHESIZE, chunk))
+            .collect::<Result<Vec<_>>>()?;
+        let summaries = llm.generate_batch(&prompts, &params)?;
+        let merged = summaries.join("\n\n");
+        // Summaries as long as their chunks would never fit
+        if llm.count_tokens(&merged)? >= llm.count_tokens(&text)? {
+            anyhow::bail!("The chunk summaries are no shorter than the text they summarize");
+        }
+        text = merged;
+    }
+}
+
+/// `text` in chunks of at most `budget` tokens: as many whole paragraphs as
+/// fit, and paragraphs too long for a chunk of their own split between
+/// words
+fn chunk_text(
+    text: &str,
+    budget: usize,
+    count_tokens: impl Fn(&str) -> Result<usize>,
+) -> Result<Vec<String>> {
+    let mut pieces = Vec::new();
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        split_words(paragraph, budget, &count_tokens, &mut pieces)?;
+    }
+
+    let mut chunks: Vec<String> = Vec::new();
+    let mut used = 0;
+    for (piece, tokens) in pieces {
+        match chunks.last_mut() {
+            Some(chunk) if used + tokens <= budget => {
+                chunk.push_str("\n\n");
+                chunk.push_str(&piece);
+                used += tokens;
+            }
+            _ => {
+                chunks.push(piece);
+                used = tokens;
+            }
+        }
+    }
+    Ok(chunks)
+}
+
+/// Adds `text` to `pieces` with its token count, halved between words
+/// until each half fits in `budget`. A single word too long stays whole.
+fn split_words(
+    text: &str,
+    budget: usize,
+    count_tokens: &impl Fn(&str) -> Result<usize>,
+    pieces: &mut Vec<(String, usize)>,
+) -> Result<()> {
+    let tokens = count_tokens(text)?;
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if tokens <= budget || words.len() < 2 {
+        pieces.push((text.to_string(), tokens));
+        return Ok(());
+    }
+    let (first, second) = words.split_at(words.len() / 2);
+    split_words(&first.join(" "), budget, count_tokens, pieces)?;
+    split_words(&second.join(" "), budget, count_tokens, pieces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::scripted;
+    use super::*;
+
+    #[test]
+    fn test_chunks_fit_the_budget() {
+        let words = |text: &str| Ok(text.split_whitespace().count());
+        let text = "one two\n\nthree four five\n\nsix\n\nseven eight nine ten eleven";
+        assert_eq!(
+            chunk_text(text, 4, words).unwrap(),
+            [
+                "one two",
+                "three four five\n\nsix",
+                "seven eight",
+                "nine ten eleven"
+            ]
+        );
+        assert_eq!(chunk_text(text, 100, words).unwrap().len(), 1);
+        assert!(chunk_text("\n\n", 5, words).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_long_text_is_summarized_in_rounds() {
+        let mut llm = scripted("A short summary. ");
+        assert_eq!(
+            summarize(&mut llm, "Short text", 500).unwrap(),
+            "A short summary."
+        );
+        assert_eq!(llm.prompts.len(), 1);
+
+        // ScriptedLlm counts words and has 2048 tokens of context, so this
+        // takes two chunks and then a summary of their summaries
+        let mut llm = scripted("A short summary. ");
+        let text = "word ".repeat(3000);
+        assert_eq!(summarize(&mut llm, &text, 500).unwrap(), "A short summary.");
+        assert_eq!(llm.prompts.len(), 3);
+        assert!(llm.prompts[2].contains("A short summary.\n\nA short summary."));
+        assert!(!llm.prompts[2].contains("word"));
+
+        assert!(summarize(&mut llm, "text", 4000).is_err());
+    }
+}
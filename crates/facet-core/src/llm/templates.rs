@@ -0,0 +1,330 @@
+//! Prompt templates
+//!
+//! The system prompts of the `Llm` helpers (`synthesize`, `extract_pii`,
+//! `extract_entities`, `extract_triples`, `optimize_prompt`, `plan_query`,
+//! `synthesize_with_sources`), of
+//! `ToolRunner` and of `generate_structured` are named templates, so a profile
+//! or a command can replace one without recompiling. A template is text
+//! with `{{variable}}` slots; the helpers fill `{{text}}` with their input,
+//! and a template without that slot gets the input as the user message.
+//!
+//! Overrides are markdown files with a `template` name in their frontmatter,
+//! the way commands are stored:
+//!
+//! ```markdown
+//! ---
+//! template: synthesize
+//! description: Summaries as bullet points
+//! ---
+//! Summarize the following text as at most five bullet points.
+//! ```
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::LazyLock;
+
+pub const SYNTHESIZE: &str = "synthesize";
+pub const EXTRACT_PII: &str = "extract_pii";
+pub const EXTRACT_ENTITIES: &str = "extract_entities";
+pub const EXTRACT_TRIPLES: &str = "extract_triples";
+pub const OPTIMIZE_PROMPT: &str = "optimize_prompt";
+pub const PLAN_QUERY: &str = "plan_query";
+/// A passage answering a query, embedded in its place
+pub const HYPOTHETICAL_ANSWER: &str = "hypothetical_answer";
+/// `ToolRunner`'s system prompt; `{{tools}}` is the tools as JSON
+pub const CALL_TOOLS: &str = "call_tools";
+/// `generate_structured`'s system prompt; `{{schema}}` is the JSON schema
+pub const STRUCTURED_OUTPUT: &str = "structured_output";
+/// `synthesize_with_sources`' system prompt; `{{sources}}` is the numbered
+/// sources
+pub const ANSWER_WITH_SOURCES: &str = "answer_with_sources";
+
+/// The templates every registry starts with
+const BUILT_IN: [(&str, &str); 10] = [
+    (
+        SYNTHESIZE,
+        "You are a helpful assistant. Summarize the following text concisely.",
+    ),
+    (
+        EXTRACT_PII,
+        "You are a privacy expert. Identify Personal Identifiable Information (PII) such as Names, Emails, Phone Numbers, and Addresses.
+Return the output in JSON format: {\"redacted_text\": \"...\", \"pii\": {\"PLACEHOLDER\": \"ORIGINAL_VALUE\"}}.
+Replace PII with placeholders like [NAME_1], [EMAIL_1].",
+    ),
+    (
+        EXTRACT_ENTITIES,
+        "Extract the named entities (people, organizations, places, projects, products) from the following text. \
+Return JSON: {\"entities\": [{\"name\": \"...\", \"type\": \"Person\", \"properties\": {}}]}.",
+    ),
+    (
+        EXTRACT_TRIPLES,
+        "Extract the facts stated in the following text as subject-relation-object triples, e.g. Alice works_at Acme. \
+Use short snake_case relations, give your confidence in each fact from 0 to 1, and quote the passage it comes from exactly. \
+Return JSON: {\"triples\": [{\"subject\": \"...\", \"relation\": \"...\", \"object\": \"...\", \"confidence\": 0.9, \"evidence\": \"...\"}]}.",
+    ),
+    (
+        OPTIMIZE_PROMPT,
+        "You are a prompt engineer. Rewrite the following query to be more precise and optimized for an LLM rag search.",
+    ),
+    (
+        PLAN_QUERY,
+        "You plan searches of the user's documents. Rewrite the following question to be precise and optimized for search, \
+and split it into one self-contained search query per thing it asks; a question that asks one thing is one query. \
+Return JSON: {\"rewritten\": \"...\", \"sub_queries\": [\"...\"]}.",
+    ),
+    (
+        HYPOTHETICAL_ANSWER,
+        "Write a short passage, as it might appear in a document, that answers the following query. \
+Make up plausible details where you don't know them.",
+    ),
+    (
+        CALL_TOOLS,
+        "You can use these tools, each with a JSON schema of its arguments:
+{{tools}}
+
+Reply with one JSON object. To use a tool: {\"tool\": \"<name>\", \"arguments\": {...}}; its result comes in the next message. \
+Once you can answer without more tools: {\"answer\": \"<your answer>\"}.",
+    ),
+    (
+        STRUCTURED_OUTPUT,
+        "Reply with only JSON that matches this JSON schema, without any other text:
+{{schema}}",
+    ),
+    (
+        ANSWER_WITH_SOURCES,
+        "Answer the question using ONLY the numbered sources below. After every claim, cite the sources it \
+comes from by number in square brackets, e.g. [1] or [2][3]. If the sources don't contain the answer, say so.
+
+{{sources}}",
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTemplate {
+    pub name: String,
+    pub text: String,
+}
+
+impl PromptTemplate {
+    pub fn new(name: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            text: text.into(),
+        }
+    }
+
+    /// Parses a markdown template: frontmatter naming it, then its text
+    pub fn from_markdown(markdown: &str) -> Result<Self> {
+        let (frontmatter, body) = split_frontmatter(markdown)
+            .context("A prompt template needs frontmatter with its `template` name")?;
+        let name = frontmatter
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(key, _)| key.trim() == "template")
+            .map(|(_, value)| value.trim().trim_matches(|c| c == '"' || c == '\''))
+            .filter(|name| !name.is_empty())
+            .context("The template's frontmatter has no `template` name")?;
+        Ok(Self::new(name, body.trim()))
+    }
+
+    /// The names of the `{{variable}}` slots, in order of appearance
+    pub fn variables(&self) -> Vec<&str> {
+        let mut variables = Vec::new();
+        let mut rest = self.text.as_str();
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start..].find("}}") else {
+                break;
+            };
+            let name = rest[start + 2..start + end].trim();
+            if !variables.contains(&name) {
+                variables.push(name);
+            }
+            rest = &rest[start + end + 2..];
+        }
+        variables
+    }
+
+    /// The text with every slot filled; fails if a slot has no value
+    pub fn render(&self, values: &[(&str, &str)]) -> Result<String> {
+        let mut rendered = String::with_capacity(self.text.len());
+        let mut rest = self.text.as_str();
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start..].find("}}") else {
+                break;
+            };
+            let name = rest[start + 2..start + end].trim();
+            let value = values
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| *value)
+                .with_context(|| {
+                    format!(
+                        "Prompt template {} needs a value for {{{{{}}}}}",
+                        self.name, name
+                    )
+                })?;
+            rendered.push_str(&rest[..start]);
+            rendered.push_str(value);
+            rest = &rest[start + end + 2..];
+        }
+        rendered.push_str(rest);
+        Ok(rendered)
+    }
+}
+
+/// The frontmatter and the rest of a markdown file, if it has frontmatter
+fn split_frontmatter(markdown: &str) -> Option<(&str, &str)> {
+    let rest = markdown.trim_start().strip_prefix("---")?;
+    let end = rest.find("\n---")?;
+    let body = &rest[end + 4..];
+    Some((&rest[..end], body.strip_prefix('\n').unwrap_or(body)))
+}
+
+/// Templates by name: the built-in ones, with any overrides on top
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTemplates {
+    templates: HashMap<String, PromptTemplate>,
+}
+
+impl PromptTemplates {
+    pub fn new() -> Self {
+        Self {
+            templates: BUILT_IN
+                .iter()
+                .map(|(name, text)| (name.to_string(), PromptTemplate::new(*name, *text)))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<&PromptTemplate> {
+        self.templates
+            .get(name)
+            .with_context(|| format!("No prompt template named {}", name))
+    }
+
+    /// Adds `template`, replacing one with the same name
+    pub fn insert(&mut self, template: PromptTemplate) {
+        self.templates.insert(template.name.clone(), template);
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.templates.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    /// Loads every markdown template in `dir`, e.g. a profile's prompts or
+    /// commands folder; other markdown files (commands without a
+    /// `template` name) are skipped. Returns how many were loaded.
+    pub fn load_dir(&mut self, dir: &Path) -> Result<usize> {
+        let mut paths: Vec<_> = std::fs::read_dir(dir)
+            .with_context(|| format!("Cannot read prompt templates from {}", dir.display()))?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<std::io::Result<_>>()?;
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "md"));
+        paths.sort();
+
+        let mut loaded = 0;
+        for path in paths {
+            let markdown = std::fs::read_to_string(&path)?;
+            let is_template = split_frontmatter(&markdown).is_some_and(|(frontmatter, _)| {
+                frontmatter
+                    .lines()
+                    .any(|line| line.trim_start().starts_with("template:"))
+            });
+            if is_template {
+                let template = PromptTemplate::from_markdown(&markdown)
+                    .with_context(|| format!("Invalid prompt template {}", path.display()))?;
+                self.insert(template);
+                loaded += 1;
+            }
+        }
+        Ok(loaded)
+    }
+}
+
+impl Default for PromptTemplates {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What `Llm::templates` returns unless a backend has its own
+pub(crate) static DEFAULT_TEMPLATES: LazyLock<PromptTemplates> =
+    LazyLock::new(PromptTemplates::new);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_fills_variables() {
+        let template = PromptTemplate::new("t", "Reply in {{ language }} to {{name}}: {{name}}.");
+        assert_eq!(template.variables(), ["language", "name"]);
+        assert_eq!(
+            template
+                .render(&[("name", "Ada"), ("language", "French"), ("unused", "x")])
+                .unwrap(),
+            "Reply in French to Ada: Ada."
+        );
+        let err = template.render(&[("name", "Ada")]).unwrap_err();
+        assert!(err.to_string().contains("{{language}}"));
+        // Values are not themselves rendered
+        assert_eq!(
+            PromptTemplate::new("t", "{{a}} {{b}}")
+                .render(&[("a", "{{b}}"), ("b", "x")])
+                .unwrap(),
+            "{{b}} x"
+        );
+    }
+
+    #[test]
+    fn test_markdown_overrides_built_ins() {
+        let dir = std::env::temp_dir().join(format!("facet-templates-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("summary.md"),
+            "---\ntemplate: synthesize\ndescription: Bullets\n---\nSummarize as bullets:\n\n{{text}}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("command.md"),
+            "---\ncommand_name: navigate\n---\n# Navigate\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.txt"), "not markdown").unwrap();
+
+        let mut templates = PromptTemplates::new();
+        assert_eq!(templates.load_dir(&dir).unwrap(), 1);
+        assert_eq!(
+            templates.get(SYNTHESIZE).unwrap().text,
+            "Summarize as bullets:\n\n{{text}}"
+        );
+        assert!(templates
+            .get(EXTRACT_PII)
+            .unwrap()
+            .text
+            .contains("privacy expert"));
+        assert_eq!(
+            templates.names(),
+            [
+                ANSWER_WITH_SOURCES,
+                CALL_TOOLS,
+                EXTRACT_ENTITIES,
+                EXTRACT_PII,
+                EXTRACT_TRIPLES,
+                HYPOTHETICAL_ANSWER,
+                OPTIMIZE_PROMPT,
+                PLAN_QUERY,
+                STRUCTURED_OUTPUT,
+                SYNTHESIZE
+            ]
+        );
+        assert!(templates.get("missing").is_err());
+
+        std::fs::write(dir.join("broken.md"), "---\ntemplate:\n---\nText").unwrap();
+        assert!(templates.load_dir(&dir).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
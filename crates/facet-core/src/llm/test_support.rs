@@ -0,0 +1,105 @@
+//! Test doubles for the LLM backends
+
+use super::{GenerationParams, Llm};
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+/// Replies with a fixed text, streamed a word at a time; or first with
+/// each of `queued` in turn
+pub struct ScriptedLlm {
+    pub reply: String,
+    pub queued: VecDeque<String>,
+    pub prompts: Vec<String>,
+}
+
+impl Llm for ScriptedLlm {
+    fn generate_stream(
+        &mut self,
+        prompt: &str,
+        _params: &GenerationParams,
+        on_token: &mut dyn FnMut(&str) -> Result<()>,
+    ) -> Result<String> {
+        self.prompts.push(prompt.to_string());
+        let reply = self
+            .queued
+            .pop_front()
+            .unwrap_or_else(|| self.reply.clone());
+        for word in reply.split_inclusive(' ') {
+            on_token(word)?;
+        }
+        Ok(reply.trim().to_string())
+    }
+
+    fn count_tokens(&self, text: &str) -> Result<usize> {
+        Ok(text.split_whitespace().count())
+    }
+}
+
+pub fn scripted(reply: &str) -> ScriptedLlm {
+    ScriptedLlm {
+        reply: reply.to_string(),
+        queued: VecDeque::new(),
+        prompts: Vec::new(),
+    }
+}
+
+/// Replies with each of `replies` in turn, then with the last one
+pub fn scripted_replies(replies: &[&str]) -> ScriptedLlm {
+    let mut llm = scripted(replies.last().copied().unwrap_or_default());
+    llm.queued = replies.iter().map(|reply| reply.to_string()).collect();
+    llm
+}
+
+/// An HTTP server that answers each request with the next canned body and
+/// hands back the requests it saw, as request line and body
+pub struct Request {
+    pub line: String,
+    pub body: String,
+}
+
+pub fn serve(bodies: Vec<&'static str>) -> (String, thread::JoinHandle<Vec<Request>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = thread::spawn(move || {
+        let mut requests = Vec::new();
+        for body in bodies {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut request_body = vec![0u8; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+            requests.push(Request {
+                line: line.trim().to_string(),
+                body: String::from_utf8(request_body).unwrap(),
+            });
+
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        }
+        requests
+    });
+    (url, handle)
+}
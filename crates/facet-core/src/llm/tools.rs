@@ -0,0 +1,249 @@
+//! Tool calling
+//!
+//! A `ToolRunner` lets the model act through `Tool`s, e.g. to query the
+//! graph or open a web page. Each tool's name, description and JSON schema
+//! go into the system prompt (the `call_tools` template), and the model
+//! replies with one JSON object: either a call,
+//! `{"tool": "search", "arguments": {"query": "..."}}`, or its final
+//! `{"answer": "..."}`. Replies are constrained to JSON while decoding (see
+//! `GenerationParams::json`), so they parse. The runner executes each call
+//! and sends the result back as the next message, until the model answers
+//! or runs out of steps.
+
+use super::{templates, ChatMessage, GenerationParams, Llm};
+use crate::agent::Tool;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Calls a run may make before the model has to have answered
+pub const DEFAULT_MAX_STEPS: usize = 8;
+
+/// One tool call the model made
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub tool: String,
+    pub arguments: Value,
+    /// What the tool returned, or why the call failed; the model is shown
+    /// either, so it can try again
+    pub result: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolRun {
+    pub answer: String,
+    /// Every call the model made, in order
+    pub calls: Vec<ToolCall>,
+}
+
+/// A reply of the model's
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Reply {
+    Call {
+        tool: String,
+        #[serde(default)]
+        arguments: Value,
+    },
+    Answer {
+        answer: String,
+    },
+}
+
+/// The tools the model may call, and how a run samples. The backend is
+/// passed to each `run`, as with `ChatSession`.
+pub struct ToolRunner {
+    tools: Vec<Box<dyn Tool>>,
+    params: GenerationParams,
+    max_steps: usize,
+}
+
+impl ToolRunner {
+    pub fn new() -> Self {
+        Self {
+            tools: Vec::new(),
+            params: GenerationParams::precise(512),
+            max_steps: DEFAULT_MAX_STEPS,
+        }
+    }
+
+    pub fn with_tool(mut self, tool: impl Tool + 'static) -> Self {
+        self.register(Box::new(tool));
+        self
+    }
+
+    /// Adds `tool`, replacing one with the same name
+    pub fn register(&mut self, tool: Box<dyn Tool>) {
+        self.tools.retain(|t| t.name() != tool.name());
+        self.tools.push(tool);
+    }
+
+    /// Sampling for each reply, which is always constrained to JSON
+    pub fn with_params(mut self, params: GenerationParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    pub fn tool_names(&self) -> Vec<&str> {
+        self.tools.iter().map(|tool| tool.name()).collect()
+    }
+
+    /// Has the model answer `request`, calling tools as it sees fit. Fails
+    /// if a reply is neither a call nor an answer, or if the model is still
+    /// calling tools after `max_steps` calls.
+    pub fn run(&self, llm: &mut dyn Llm, request: &str) -> Result<ToolRun> {
+        let mut messages = vec![
+            ChatMessage::system(self.system_prompt(llm)?),
+            ChatMessage::user(request),
+        ];
+        let params = self.params.clone().with_json();
+        let mut calls = Vec::new();
+        loop {
+            let prompt = llm.format_chat(&messages);
+            let output = llm.generate(&prompt, &params)?;
+            let reply: Reply = serde_json::from_str(&output)
+                .map_err(|e| anyhow::anyhow!("The model returned a malformed tool call: {}", e))?;
+            let (tool, arguments) = match reply {
+                Reply::Answer { answer } => return Ok(ToolRun { answer, calls }),
+                Reply::Call { tool, arguments } => (tool, arguments),
+            };
+            if calls.len() == self.max_steps {
+                anyhow::bail!(
+                    "The model was still calling tools after {} calls",
+                    self.max_steps
+                );
+            }
+
+            let arguments = match arguments {
+                Value::Null => json!({}),
+                arguments => arguments,
+            };
+            let result = self
+                .call(&tool, &arguments)
+                .unwrap_or_else(|e| format!("Error: {}", e));
+            messages.push(ChatMessage::assistant(output));
+            messages.push(ChatMessage::user(format!("Result of {}: {}", tool, result)));
+            calls.push(ToolCall {
+                tool,
+                arguments,
+                result,
+            });
+        }
+    }
+
+    fn system_prompt(&self, llm: &dyn Llm) -> Result<String> {
+        let tools: Vec<Value> = self
+            .tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "name": tool.name(),
+                    "description": tool.description(),
+                    "parameters": tool.parameters(),
+                })
+            })
+            .collect();
+        let tools = serde_json::to_string_pretty(&tools)?;
+        llm.templates()
+            .get(templates::CALL_TOOLS)?
+            .render(&[("tools", &tools)])
+    }
+
+    fn call(&self, name: &str, arguments: &Value) -> Result<String> {
+        let tool = self
+            .tools
+            .iter()
+            .find(|tool| tool.name() == name)
+            .ok_or_else(|| anyhow::anyhow!("There is no tool named {}", name))?;
+        check_arguments(&tool.parameters(), arguments)?;
+        tool.execute(arguments)
+    }
+}
+
+impl Default for ToolRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Catches the malformed calls a model makes most: arguments that aren't
+/// an object, or that leave out a property the schema requires
+fn check_arguments(schema: &Value, arguments: &Value) -> Result<()> {
+    let Some(arguments) = arguments.as_object() else {
+        anyhow::bail!("The arguments have to be a JSON object");
+    };
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str);
+    for name in required {
+        if !arguments.contains_key(name) {
+            anyhow::bail!("Missing argument {}", name);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::scripted_replies;
+    use super::*;
+    use crate::agent::FnTool;
+
+    fn lookup() -> FnTool<impl Fn(&Value) -> Result<String> + Send + Sync> {
+        FnTool::new(
+            "lookup",
+            "Finds a person's city",
+            json!({
+                "type": "object",
+                "properties": {"name": {"type": "string"}},
+                "required": ["name"],
+            }),
+            |arguments: &Value| match arguments["name"].as_str() {
+                Some("Ada") => Ok("London".to_string()),
+                _ => anyhow::bail!("unknown person"),
+            },
+        )
+    }
+
+    #[test]
+    fn test_run_calls_tools_until_answered() {
+        let mut llm = scripted_replies(&[
+            r#"{"tool": "lookup", "arguments": {}}"#,
+            r#"{"tool": "lookup", "arguments": {"name": "Ada"}}"#,
+            r#"{"answer": "Ada lives in London."}"#,
+        ]);
+        let runner = ToolRunner::new().with_tool(lookup());
+        let run = runner.run(&mut llm, "Where does Ada live?").unwrap();
+
+        assert_eq!(run.answer, "Ada lives in London.");
+        assert_eq!(run.calls.len(), 2);
+        // A bad call is reported back for the model to retry
+        assert_eq!(run.calls[0].result, "Error: Missing argument name");
+        assert_eq!(run.calls[1].result, "London");
+        assert!(llm.prompts[0].contains("\"name\": \"lookup\""));
+        assert!(llm.prompts[0].contains("Finds a person's city"));
+        assert!(llm.prompts[2].contains("Result of lookup: London"));
+    }
+
+    #[test]
+    fn test_run_gives_up() {
+        let call = r#"{"tool": "missing"}"#;
+        let mut llm = scripted_replies(&[call]);
+        let runner = ToolRunner::new().with_tool(lookup()).with_max_steps(2);
+        let err = runner.run(&mut llm, "Loop forever").unwrap_err();
+        assert!(err.to_string().contains("after 2 calls"));
+        assert_eq!(llm.prompts.len(), 3);
+        assert!(llm.prompts[1].contains("There is no tool named missing"));
+
+        let mut llm = scripted_replies(&["I think the answer is 42"]);
+        assert!(runner.run(&mut llm, "What is it?").is_err());
+    }
+}
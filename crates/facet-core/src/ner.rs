@@ -0,0 +1,390 @@
+//! Named Entity Recognition for PII
+//!
+//! Names, organisations and places have no fixed shape, so `PiiDetector`'s
+//! patterns can't find them. A small BERT token-classification model run
+//! through candle can, in tens of milliseconds on the CPU. Attached to a
+//! detector with `PiiDetector::with_ner`, it replaces the generative model
+//! in `Llm::preview_redaction`, which is then only the fallback.
+
+use crate::pii::PiiMatch;
+use anyhow::{Error as E, Result};
+use candle_core::{DType, Device, Tensor};
+use candle_nn::{Linear, Module, VarBuilder};
+use candle_transformers::models::bert::{BertModel, Config as BertConfig};
+use hf_hub::{api::sync::Api, Repo, RepoType};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use tokenizers::models::wordpiece::WordPiece;
+use tokenizers::normalizers::bert::BertNormalizer;
+use tokenizers::pre_tokenizers::bert::BertPreTokenizer;
+use tokenizers::processors::bert::BertProcessing;
+use tokenizers::{Encoding, Tokenizer, TruncationParams};
+
+/// Token-classification models `EntityRecognizer` can load
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NerModel {
+    /// dslim/bert-base-NER, CoNLL-2003 labels
+    #[default]
+    BertBaseNer,
+    /// dslim/bert-large-NER, slower and somewhat more accurate
+    BertLargeNer,
+}
+
+impl NerModel {
+    fn repo(self) -> &'static str {
+        match self {
+            NerModel::BertBaseNer => "dslim/bert-base-NER",
+            NerModel::BertLargeNer => "dslim/bert-large-NER",
+        }
+    }
+}
+
+/// Tokens per window; longer texts are read in overlapping windows
+const MAX_TOKENS: usize = 512;
+
+/// Tokens repeated between windows, so an entity cut by one window is whole
+/// in the next
+const WINDOW_OVERLAP: usize = 32;
+
+/// The placeholder category for an entity type, or `None` for types that
+/// aren't PII (CoNLL's MISC: nationalities, events, ...)
+fn category(entity_type: &str) -> Option<&'static str> {
+    match entity_type {
+        "PER" | "PERSON" => Some("NAME"),
+        "LOC" | "LOCATION" | "GPE" => Some("LOCATION"),
+        "ORG" | "ORGANIZATION" => Some("ORG"),
+        _ => None,
+    }
+}
+
+/// `config.json`'s label names, which `BertConfig` doesn't read
+#[derive(Deserialize)]
+struct LabelConfig {
+    id2label: HashMap<String, String>,
+}
+
+pub struct EntityRecognizer {
+    model: BertModel,
+    classifier: Linear,
+    labels: Vec<String>,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+impl EntityRecognizer {
+    /// Downloads `model` (or uses the Hugging Face cache) and loads it on
+    /// the CPU
+    pub fn new(model: NerModel) -> Result<Self> {
+        let device = Device::Cpu;
+        let api = Api::new()?;
+        let repo = api.repo(Repo::new(model.repo().to_string(), RepoType::Model));
+
+        let config_json = std::fs::read(repo.get("config.json")?)?;
+        let config: BertConfig = serde_json::from_slice(&config_json)?;
+        let label_config: LabelConfig = serde_json::from_slice(&config_json)?;
+        let mut labels: Vec<(usize, String)> = label_config
+            .id2label
+            .into_iter()
+            .map(|(id, label)| Ok((id.parse()?, label)))
+            .collect::<Result<_>>()?;
+        labels.sort();
+        let labels: Vec<String> = labels.into_iter().map(|(_, label)| label).collect();
+
+        // Older NER repos only ship the WordPiece vocabulary
+        let tokenizer = match repo.get("tokenizer.json") {
+            Ok(path) => Tokenizer::from_file(path).map_err(E::msg)?,
+            Err(_) => wordpiece_tokenizer(&repo.get("vocab.txt")?)?,
+        };
+        let weights = repo.get("model.safetensors")?;
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[weights], DType::F32, &device)? };
+        let bert = BertModel::load(vb.clone(), &config)?;
+        let classifier = candle_nn::linear(config.hidden_size, labels.len(), vb.pp("classifier"))?;
+
+        Self::from_parts(bert, classifier, &config, labels, tokenizer, device)
+    }
+
+    /// Wraps an already loaded BERT model, its classification head (one
+    /// output per label, in order) and its tokenizer
+    pub fn from_parts(
+        model: BertModel,
+        classifier: Linear,
+        config: &BertConfig,
+        labels: Vec<String>,
+        mut tokenizer: Tokenizer,
+        device: Device,
+    ) -> Result<Self> {
+        let max_length = MAX_TOKENS.min(config.max_position_embeddings);
+        tokenizer.with_padding(None);
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length,
+                stride: WINDOW_OVERLAP.min(max_length / 4),
+                ..Default::default()
+            }))
+            .map_err(E::msg)?;
+
+        Ok(Self {
+            model,
+            classifier,
+            labels,
+            tokenizer,
+            device,
+        })
+    }
+
+    /// The names, places and organisations in `text`, in order and without
+    /// overlaps
+    pub fn entities(&self, text: &str) -> Result<Vec<PiiMatch>> {
+        let encoding = self.tokenizer.encode(text, true).map_err(E::msg)?;
+        let mut spans = Vec::new();
+        for window in std::iter::once(&encoding).chain(encoding.get_overflowing()) {
+            let labels: Vec<&str> = self
+                .classify(window)?
+                .into_iter()
+                .map(|id| self.labels.get(id as usize).map_or("O", String::as_str))
+                .collect();
+            spans.extend(entity_spans(
+                &labels,
+                window.get_word_ids(),
+                window.get_offsets(),
+            ));
+        }
+
+        spans.sort_by_key(|(_, range)| (range.start, std::cmp::Reverse(range.end)));
+        let mut entities: Vec<PiiMatch> = Vec::new();
+        for (category, range) in spans {
+            if entities
+                .last()
+                .is_some_and(|last| range.start < last.range.end)
+            {
+                continue;
+            }
+            entities.push(PiiMatch {
+                category: category.to_string(),
+                value: text[range.clone()].to_string(),
+                range,
+            });
+        }
+        Ok(entities)
+    }
+
+    /// The most likely label id for each token of `encoding`
+    fn classify(&self, encoding: &Encoding) -> Result<Vec<u32>> {
+        let input_ids = Tensor::new(encoding.get_ids(), &self.device)?.unsqueeze(0)?;
+        let attention_mask =
+            Tensor::new(encoding.get_attention_mask(), &self.device)?.unsqueeze(0)?;
+        let token_type_ids = input_ids.zeros_like()?;
+        // (1, tokens, hidden) -> (1, tokens, labels)
+        let hidden = self
+            .model
+            .forward(&input_ids, &token_type_ids, Some(&attention_mask))?;
+        let logits = self.classifier.forward(&hidden)?;
+        Ok(logits.argmax(2)?.squeeze(0)?.to_vec1()?)
+    }
+}
+
+/// Joins per-token BIO labels into entity byte ranges. A word split into
+/// several tokens takes the label of its first; `I-` continues an entity of
+/// the same type and anything else ends it.
+fn entity_spans(
+    labels: &[&str],
+    word_ids: &[Option<u32>],
+    offsets: &[(usize, usize)],
+) -> Vec<(&'static str, Range<usize>)> {
+    let mut spans = Vec::new();
+    let mut current: Option<(&'static str, Range<usize>)> = None;
+    let mut previous_word = None;
+    for ((label, &word), &(start, end)) in labels.iter().zip(word_ids).zip(offsets) {
+        // [CLS], [SEP] and the like
+        if word.is_none() {
+            spans.extend(current.take());
+            previous_word = None;
+            continue;
+        }
+        let same_word = word == previous_word;
+        previous_word = word;
+        if same_word {
+            if let Some((_, range)) = current.as_mut() {
+                range.end = end;
+            }
+            continue;
+        }
+
+        let (tag, entity_type) = label.split_once('-').unwrap_or((label, ""));
+        let Some(category) = category(entity_type) else {
+            spans.extend(current.take());
+            continue;
+        };
+        match current.as_mut() {
+            Some((current_category, range)) if tag == "I" && *current_category == category => {
+                range.end = end;
+            }
+            _ => {
+                spans.extend(current.take());
+                current = Some((category, start..end));
+            }
+        }
+    }
+    spans.extend(current);
+    spans
+}
+
+/// A cased BERT tokenizer from a `vocab.txt`
+fn wordpiece_tokenizer(vocab: &Path) -> Result<Tokenizer> {
+    let vocab = vocab
+        .to_str()
+        .ok_or_else(|| E::msg("Non-UTF-8 vocabulary path"))?;
+    let wordpiece = WordPiece::from_file(vocab)
+        .unk_token("[UNK]".to_string())
+        .build()
+        .map_err(E::msg)?;
+    let mut tokenizer = Tokenizer::new(wordpiece);
+    let special = |token: &str| {
+        tokenizer
+            .token_to_id(token)
+            .map(|id| (token.to_string(), id))
+            .ok_or_else(|| E::msg(format!("{} is missing from the vocabulary", token)))
+    };
+    let processor = BertProcessing::new(special("[SEP]")?, special("[CLS]")?);
+    tokenizer
+        .with_normalizer(Some(BertNormalizer::new(true, true, None, false)))
+        .with_pre_tokenizer(Some(BertPreTokenizer))
+        .with_post_processor(Some(processor));
+    Ok(tokenizer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::test_support::scripted;
+    use crate::llm::Llm;
+    use crate::pii::PiiDetector;
+    use candle_nn::VarMap;
+    use candle_transformers::models::bert::HiddenAct;
+
+    #[test]
+    fn test_entity_spans_join_bio_labels_and_subwords() {
+        // [CLS] Ada Love ##lace met Bob at Acme in Paris [SEP]
+        let labels = [
+            "O", "B-PER", "I-PER", "O", "O", "B-PER", "O", "B-ORG", "O", "B-LOC", "O",
+        ];
+        let words = [
+            None,
+            Some(0),
+            Some(1),
+            Some(1),
+            Some(2),
+            Some(3),
+            Some(4),
+            Some(5),
+            Some(6),
+            Some(7),
+            None,
+        ];
+        let offsets = [
+            (0, 0),
+            (0, 3),
+            (4, 8),
+            (8, 13),
+            (14, 17),
+            (18, 21),
+            (22, 24),
+            (25, 29),
+            (30, 32),
+            (33, 38),
+            (0, 0),
+        ];
+        assert_eq!(
+            entity_spans(&labels, &words, &offsets),
+            [
+                ("NAME", 0..13),
+                ("NAME", 18..21),
+                ("ORG", 25..29),
+                ("LOCATION", 33..38)
+            ]
+        );
+
+        // B- starts a new entity, I- of another type and MISC end one
+        let labels = ["B-PER", "B-PER", "I-LOC", "B-MISC"];
+        let words = [Some(0), Some(1), Some(2), Some(3)];
+        let offsets = [(0, 1), (2, 3), (4, 5), (6, 7)];
+        assert_eq!(
+            entity_spans(&labels, &words, &offsets),
+            [("NAME", 0..1), ("NAME", 2..3), ("LOCATION", 4..5)]
+        );
+    }
+
+    /// A one-layer BERT whose head ignores the hidden state and always says
+    /// I-PER, so every word joins one name
+    fn tiny() -> EntityRecognizer {
+        let vocab_path =
+            std::env::temp_dir().join(format!("facet-ner-vocab-{}.txt", std::process::id()));
+        std::fs::write(
+            &vocab_path,
+            "[PAD]\n[UNK]\n[CLS]\n[SEP]\nAda\nLove\n##lace\nwrote\nnotes\n",
+        )
+        .unwrap();
+        let tokenizer = wordpiece_tokenizer(&vocab_path).unwrap();
+        std::fs::remove_file(&vocab_path).unwrap();
+
+        let config = BertConfig {
+            vocab_size: 9,
+            hidden_size: 16,
+            num_hidden_layers: 1,
+            num_attention_heads: 2,
+            intermediate_size: 32,
+            hidden_act: HiddenAct::Gelu,
+            max_position_embeddings: 8,
+            ..Default::default()
+        };
+        let device = Device::Cpu;
+        let varmap = VarMap::new();
+        let vb = VarBuilder::from_varmap(&varmap, DType::F32, &device);
+        let model = BertModel::load(vb, &config).unwrap();
+        let labels = ["O", "B-PER", "I-PER"].map(String::from).to_vec();
+        let weight = Tensor::zeros((3, 16), DType::F32, &device).unwrap();
+        let bias = Tensor::new(&[0f32, 0., 1.], &device).unwrap();
+        let classifier = Linear::new(weight, Some(bias));
+        EntityRecognizer::from_parts(model, classifier, &config, labels, tokenizer, device).unwrap()
+    }
+
+    #[test]
+    fn test_recognizer_runs_the_model_over_windows() {
+        let recognizer = tiny();
+        let entities = recognizer.entities("Ada Lovelace wrote").unwrap();
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities[0].category, "NAME");
+        assert_eq!(entities[0].value, "Ada Lovelace wrote");
+
+        // Longer than one window: the windows overlap, and their entities
+        // are merged without overlaps
+        let long = "Ada Lovelace wrote notes Ada wrote notes";
+        let entities = recognizer.entities(long).unwrap();
+        assert!(!entities.is_empty());
+        for pair in entities.windows(2) {
+            assert!(pair[0].range.end <= pair[1].range.start);
+        }
+        for entity in &entities {
+            assert_eq!(&long[entity.range.clone()], entity.value);
+        }
+    }
+
+    #[test]
+    fn test_ner_replaces_the_llm_pass() {
+        let mut llm = scripted("{}");
+        let detector = PiiDetector::new().with_ner(tiny());
+        let preview = llm
+            .preview_redaction_with(&detector, "Ada Lovelace")
+            .unwrap();
+        assert_eq!(preview.redacted_text, "[NAME_1]");
+        assert_eq!(preview.replacements[0].original, "Ada Lovelace");
+        assert!(llm.prompts.is_empty());
+
+        // Without a NER model the LLM is asked
+        llm.preview_redaction("Ada Lovelace").unwrap();
+        assert_eq!(llm.prompts.len(), 1);
+    }
+}
@@ -43,6 +43,7 @@ mod tests {
             id: "doc1".to_string(),
             label: "Document".to_string(),
             properties: json!({"status": "active"}),
+            partition_id: "personal".to_string(),
         };
         manager.graph_store.add_node(node.clone()).await.unwrap();
 
@@ -0,0 +1,457 @@
+//! Redaction Preview and Confirmation
+//!
+//! Turns the output of PII extraction into a reviewable preview and decides,
+//! per profile policy, whether the user must confirm it before the redacted
+//! prompt is sent to a remote backend. A `RedactionSession` keeps the
+//! placeholder map of one request, encrypted, to put the real values back
+//! into the reply.
+
+use facet_types::profiles::crypto::{decrypt_file, encrypt_file, EncryptionKey};
+use facet_types::profiles::RedactionPreferences;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RedactionError {
+    #[error("Redaction rejected by user")]
+    Rejected,
+
+    #[error("Redaction session error: {0}")]
+    Session(String),
+}
+
+/// A single value that will be replaced before sending
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Replacement {
+    /// Placeholder that appears in the redacted text (e.g. `[NAME_1]`)
+    pub placeholder: String,
+    /// Original value the placeholder stands for
+    pub original: String,
+    /// What kind of PII it is, e.g. `EMAIL` or a profile's custom category
+    #[serde(default)]
+    pub category: String,
+}
+
+/// Everything the user needs to see before a redacted prompt leaves the device
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RedactionPreview {
+    pub redacted_text: String,
+    pub replacements: Vec<Replacement>,
+}
+
+impl RedactionPreview {
+    /// Builds a preview from `Llm::extract_pii` output
+    pub fn from_pii(redacted_text: String, pii: HashMap<String, String>) -> Self {
+        let mut replacements: Vec<Replacement> = pii
+            .into_iter()
+            .map(|(placeholder, original)| Replacement {
+                category: placeholder_kind(&placeholder),
+                placeholder,
+                original,
+            })
+            .collect();
+        replacements.sort_by(|a, b| a.placeholder.cmp(&b.placeholder));
+
+        Self {
+            redacted_text,
+            replacements,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.replacements.is_empty()
+    }
+
+    /// Adds the findings of a second pass over `redacted_text` (usually the
+    /// LLM's). Only its values are used: each is replaced here, numbered
+    /// after the placeholders already in the preview, so a model rewriting
+    /// the text or reusing a placeholder name can't undo the first pass.
+    pub fn merge(mut self, pii: HashMap<String, String>) -> Self {
+        let mut found: Vec<(String, String)> = pii
+            .into_iter()
+            .map(|(placeholder, original)| (placeholder_kind(&placeholder), original))
+            .collect();
+        // Longest first, so "Alice Smith" isn't split by "Alice"
+        found.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.cmp(b)));
+        for (kind, original) in found {
+            self.redact_value(&kind, &original);
+        }
+        self
+    }
+
+    /// Replaces every occurrence of `original` with a `[KIND_n]` placeholder,
+    /// reusing the one it already has
+    pub fn redact_value(&mut self, kind: &str, original: &str) {
+        let original = original.trim();
+        if original.is_empty()
+            || !self.redacted_text.contains(original)
+            || self
+                .replacements
+                .iter()
+                .any(|r| r.placeholder.contains(original) || original.contains(&r.placeholder))
+        {
+            return;
+        }
+        let placeholder = match self.replacements.iter().find(|r| r.original == original) {
+            Some(existing) => existing.placeholder.clone(),
+            None => {
+                let prefix = format!("[{}_", kind);
+                let n = self
+                    .replacements
+                    .iter()
+                    .filter(|r| r.placeholder.starts_with(&prefix))
+                    .count();
+                let placeholder = format!("{}{}]", prefix, n + 1);
+                self.replacements.push(Replacement {
+                    placeholder: placeholder.clone(),
+                    original: original.to_string(),
+                    category: kind.to_string(),
+                });
+                placeholder
+            }
+        };
+        self.redacted_text = self.redacted_text.replace(original, &placeholder);
+    }
+}
+
+/// `NAME` for `[NAME_1]`; `PII` when the placeholder has no recognisable kind
+fn placeholder_kind(placeholder: &str) -> String {
+    let inner = placeholder.trim_matches(|c| c == '[' || c == ']');
+    let kind = inner
+        .rsplit_once('_')
+        .map_or(inner, |(kind, _)| kind)
+        .to_ascii_uppercase();
+    if !kind.is_empty() && kind.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        kind
+    } else {
+        "PII".to_string()
+    }
+}
+
+/// Per-profile confirmation policy
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedactionPolicy {
+    /// Ask the user before sending a redacted prompt
+    pub confirm_before_send: bool,
+    /// Previews with at most this many replacements skip confirmation
+    pub auto_approve_threshold: usize,
+}
+
+impl Default for RedactionPolicy {
+    fn default() -> Self {
+        Self {
+            confirm_before_send: true,
+            auto_approve_threshold: 0,
+        }
+    }
+}
+
+impl From<&RedactionPreferences> for RedactionPolicy {
+    fn from(preferences: &RedactionPreferences) -> Self {
+        Self {
+            confirm_before_send: preferences.confirm_before_send,
+            auto_approve_threshold: preferences.auto_approve_threshold,
+        }
+    }
+}
+
+/// Outcome of checking a preview against the policy
+#[derive(Debug, Clone, PartialEq)]
+pub enum RedactionDecision {
+    /// Send without asking
+    AutoApproved,
+    /// Show the preview and wait for the user
+    NeedsConfirmation(RedactionPreview),
+}
+
+impl RedactionPolicy {
+    pub fn review(&self, preview: RedactionPreview) -> RedactionDecision {
+        if !self.confirm_before_send
+            || preview.is_empty()
+            || preview.replacements.len() <= self.auto_approve_threshold
+        {
+            RedactionDecision::AutoApproved
+        } else {
+            RedactionDecision::NeedsConfirmation(preview)
+        }
+    }
+
+    /// Applies the policy and returns the text that may be sent.
+    ///
+    /// `confirm` is only called when the preview needs confirmation; returning
+    /// `false` aborts the request.
+    pub fn approve<F>(
+        &self,
+        preview: RedactionPreview,
+        confirm: F,
+    ) -> Result<String, RedactionError>
+    where
+        F: FnOnce(&RedactionPreview) -> bool,
+    {
+        match self.review(preview.clone()) {
+            RedactionDecision::AutoApproved => Ok(preview.redacted_text),
+            RedactionDecision::NeedsConfirmation(preview) => {
+                if confirm(&preview) {
+                    Ok(preview.redacted_text)
+                } else {
+                    Err(RedactionError::Rejected)
+                }
+            }
+        }
+    }
+}
+
+/// The placeholder map of one redacted request, for putting the real values
+/// back into the reply. The map is sealed with AES-256-GCM under a key that
+/// exists only in this session, and only decrypted while rehydrating.
+pub struct RedactionSession {
+    key: EncryptionKey,
+    sealed: Vec<u8>,
+}
+
+impl RedactionSession {
+    pub fn new(preview: &RedactionPreview) -> Result<Self, RedactionError> {
+        let mut key = vec![0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        let key = EncryptionKey::from_bytes(key);
+        let map: Vec<(&str, &str)> = preview
+            .replacements
+            .iter()
+            .map(|r| (r.placeholder.as_str(), r.original.as_str()))
+            .collect();
+        let plaintext =
+            serde_json::to_vec(&map).map_err(|e| RedactionError::Session(e.to_string()))?;
+        let sealed =
+            encrypt_file(&plaintext, &key).map_err(|e| RedactionError::Session(e.to_string()))?;
+        Ok(Self { key, sealed })
+    }
+
+    /// `text` with every placeholder replaced by the value it stands for
+    pub fn rehydrate(&self, text: &str) -> Result<String, RedactionError> {
+        Ok(self.open()?.rehydrate(text))
+    }
+
+    /// Decrypts the map for rehydrating a streamed reply piece by piece
+    pub fn open(&self) -> Result<Rehydrator, RedactionError> {
+        let plaintext = decrypt_file(&self.sealed, &self.key)
+            .map_err(|e| RedactionError::Session(e.to_string()))?;
+        let mut map: Vec<(String, String)> = serde_json::from_slice(&plaintext)
+            .map_err(|e| RedactionError::Session(e.to_string()))?;
+        // `[NAME_10]` before `[NAME_1]`
+        map.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        Ok(Rehydrator {
+            map,
+            pending: String::new(),
+        })
+    }
+}
+
+/// Rehydrates a reply as it streams in. A placeholder can arrive split
+/// over several pieces, so text from an unclosed `[` on is held back until
+/// the placeholder is complete.
+pub struct Rehydrator {
+    map: Vec<(String, String)>,
+    pending: String,
+}
+
+impl Rehydrator {
+    pub fn rehydrate(&self, text: &str) -> String {
+        self.map
+            .iter()
+            .fold(text.to_string(), |text, (placeholder, original)| {
+                text.replace(placeholder.as_str(), original)
+            })
+    }
+
+    /// The rehydrated text that is ready to show after `piece`
+    pub fn push(&mut self, piece: &str) -> String {
+        self.pending.push_str(piece);
+        let held = match self.pending.rfind('[') {
+            Some(open)
+                if !self.pending[open..].contains(']')
+                    && self.could_start(&self.pending[open..]) =>
+            {
+                open
+            }
+            _ => self.pending.len(),
+        };
+        let ready: String = self.pending.drain(..held).collect();
+        self.rehydrate(&ready)
+    }
+
+    /// Whatever is still held back, once the reply has ended
+    pub fn finish(&mut self) -> String {
+        let rest = std::mem::take(&mut self.pending);
+        self.rehydrate(&rest)
+    }
+
+    fn could_start(&self, text: &str) -> bool {
+        self.map
+            .iter()
+            .any(|(placeholder, _)| placeholder.starts_with(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preview(count: usize) -> RedactionPreview {
+        let pii = (1..=count)
+            .map(|i| (format!("[NAME_{}]", i), format!("Person {}", i)))
+            .collect();
+        RedactionPreview::from_pii("Hello [NAME_1]".to_string(), pii)
+    }
+
+    #[test]
+    fn test_preview_from_pii_is_sorted() {
+        let preview = preview(3);
+        let placeholders: Vec<_> = preview
+            .replacements
+            .iter()
+            .map(|r| r.placeholder.as_str())
+            .collect();
+        assert_eq!(placeholders, vec!["[NAME_1]", "[NAME_2]", "[NAME_3]"]);
+    }
+
+    #[test]
+    fn test_review_respects_threshold() {
+        let policy = RedactionPolicy {
+            confirm_before_send: true,
+            auto_approve_threshold: 2,
+        };
+        assert_eq!(policy.review(preview(2)), RedactionDecision::AutoApproved);
+        assert!(matches!(
+            policy.review(preview(3)),
+            RedactionDecision::NeedsConfirmation(_)
+        ));
+    }
+
+    #[test]
+    fn test_review_without_confirmation() {
+        let policy = RedactionPolicy {
+            confirm_before_send: false,
+            auto_approve_threshold: 0,
+        };
+        assert_eq!(policy.review(preview(5)), RedactionDecision::AutoApproved);
+    }
+
+    #[test]
+    fn test_policy_from_profile_preferences() {
+        let preferences = RedactionPreferences {
+            enabled: true,
+            confirm_before_send: true,
+            auto_approve_threshold: 2,
+        };
+        let policy = RedactionPolicy::from(&preferences);
+        assert_eq!(policy.review(preview(2)), RedactionDecision::AutoApproved);
+        assert!(matches!(
+            policy.review(preview(3)),
+            RedactionDecision::NeedsConfirmation(_)
+        ));
+
+        let policy = RedactionPolicy::from(&RedactionPreferences {
+            confirm_before_send: false,
+            ..preferences
+        });
+        assert_eq!(policy.review(preview(3)), RedactionDecision::AutoApproved);
+    }
+
+    #[test]
+    fn test_approve_calls_confirm_and_honours_rejection() {
+        let policy = RedactionPolicy::default();
+
+        let text = policy.approve(preview(1), |p| p.replacements.len() == 1);
+        assert_eq!(text.unwrap(), "Hello [NAME_1]");
+
+        let rejected = policy.approve(preview(1), |_| false);
+        assert_eq!(rejected.unwrap_err(), RedactionError::Rejected);
+
+        // Nothing to redact, so the callback is never consulted
+        let empty = policy.approve(preview(0), |_| panic!("should not ask"));
+        assert!(empty.is_ok());
+    }
+
+    #[test]
+    fn test_merge_renumbers_second_pass_findings() {
+        let mut detected = RedactionPreview::from_pii(
+            "Alice Smith (alice@example.com) met Alice and Bob".to_string(),
+            HashMap::new(),
+        );
+        detected.redact_value("EMAIL", "alice@example.com");
+        // The model's own text and numbering are ignored, and values it
+        // reports that are gone or part of a placeholder are skipped
+        let pii = [
+            ("[NAME_1]", "Alice"),
+            ("[NAME_2]", "Alice Smith"),
+            ("[PERSON_7]", "Bob"),
+            ("[EMAIL_1]", "alice@example.com"),
+            ("[X_1]", "EMAIL"),
+        ]
+        .into_iter()
+        .map(|(p, o)| (p.to_string(), o.to_string()))
+        .collect();
+        let merged = detected.merge(pii);
+
+        assert_eq!(
+            merged.redacted_text,
+            "[NAME_1] ([EMAIL_1]) met [NAME_2] and [PERSON_1]"
+        );
+        let placeholders: Vec<_> = merged
+            .replacements
+            .iter()
+            .map(|r| (r.placeholder.as_str(), r.original.as_str()))
+            .collect();
+        assert_eq!(
+            placeholders,
+            [
+                ("[EMAIL_1]", "alice@example.com"),
+                ("[NAME_1]", "Alice Smith"),
+                ("[NAME_2]", "Alice"),
+                ("[PERSON_1]", "Bob"),
+            ]
+        );
+        let categories: Vec<_> = merged
+            .replacements
+            .iter()
+            .map(|r| r.category.as_str())
+            .collect();
+        assert_eq!(categories, ["EMAIL", "NAME", "NAME", "PERSON"]);
+    }
+
+    #[test]
+    fn test_session_rehydrates_reply() {
+        let mut preview = RedactionPreview::from_pii(
+            "Ask Alice or Bob, or email alice@example.com".to_string(),
+            HashMap::new(),
+        );
+        for name in ["Alice", "Bob"] {
+            preview.redact_value("NAME", name);
+        }
+        preview.redact_value("EMAIL", "alice@example.com");
+        let session = RedactionSession::new(&preview).unwrap();
+        assert!(!session.sealed.windows(5).any(|w| w == b"Alice"));
+
+        assert_eq!(
+            session
+                .rehydrate("[NAME_1] and [NAME_2] share [EMAIL_1]; [NAME_3] is unknown")
+                .unwrap(),
+            "Alice and Bob share alice@example.com; [NAME_3] is unknown"
+        );
+
+        // Split placeholders are held back until complete; other brackets
+        // pass straight through
+        let mut rehydrator = session.open().unwrap();
+        let mut shown = String::new();
+        for piece in ["Hi [NA", "ME_2", "] ", "[see ", "docs] [EM", "AIL_1"] {
+            let ready = rehydrator.push(piece);
+            assert!(!ready.contains("[NA") && !ready.contains("[EM"));
+            shown.push_str(&ready);
+        }
+        assert_eq!(shown, "Hi Bob [see docs] ");
+        shown.push_str(&rehydrator.finish());
+        assert_eq!(shown, "Hi Bob [see docs] [EMAIL_1");
+    }
+}
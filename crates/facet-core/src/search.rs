@@ -1,10 +1,19 @@
-use crate::llm::LlmClient;
+use crate::llm::{pack_chunks, LlmClient};
 use anyhow::Result;
 use facet_graph::ingest::IngestionPipeline;
 use facet_graph::query::GraphQuery;
 use facet_graph::{GraphError, GraphStore, Node, VectorStore};
 use std::sync::Arc;
 
+/// Context window the answer prompt is packed into
+const CONTEXT_TOKENS: usize = 4096;
+
+/// Tokens kept free for the answer
+const ANSWER_TOKENS: usize = 512;
+
+/// Documents retrieved for a question; as many as fit go in the prompt
+const CANDIDATES: usize = 20;
+
 pub struct SearchManager<S: GraphStore + VectorStore> {
     query_engine: GraphQuery<S>,
     ingestion_pipeline: Arc<IngestionPipeline<S>>,
@@ -35,29 +44,41 @@ impl<S: GraphStore + VectorStore + Clone> SearchManager<S> {
     pub async fn ask(&self, query_text: &str) -> Result<String> {
         // 1. Retrieve Context
         let nodes = self
-            .search(query_text, 5)
+            .search(query_text, CANDIDATES)
             .await
             .map_err(|e| anyhow::anyhow!("Search failed: {}", e))?;
 
-        // 2. Assemble Context
-        let context_str = nodes
+        // 2. Construct Prompt
+        let system_prompt = "You are Robert, a helpful AI assistant with access to the user's personal documents. \
+        Answer the user's question based ONLY on the provided context. If the context doesn't contain the answer, say so.";
+
+        // 3. Assemble Context: whole documents, best match first, as many
+        // as fit next to the prompt and the answer
+        let chunks: Vec<String> = nodes
             .iter()
             .map(|n| {
-                let content = n
-                    .properties
-                    .get("content_preview")
-                    .and_then(|v| v.as_str())
+                let content = ["content", "content_preview"]
+                    .iter()
+                    .find_map(|key| n.properties.get(*key).and_then(|v| v.as_str()))
                     .unwrap_or("");
-                format!("- [{}]: {}", n.label, content)
+                format!("- [{}]: {}\n", n.label, content)
             })
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        // 3. Construct Prompt
-        let system_prompt = "You are Robert, a helpful AI assistant with access to the user's personal documents. \
-        Answer the user's question based ONLY on the provided context. If the context doesn't contain the answer, say so.";
+            .collect();
+        let fixed = self.llm_client.count_tokens(system_prompt)
+            + self
+                .llm_client
+                .count_tokens(&format!("Context:\n\n\nQuestion: {}", query_text));
+        let budget = CONTEXT_TOKENS.saturating_sub(ANSWER_TOKENS + fixed);
+        let packed = pack_chunks(&chunks, budget, |chunk| {
+            Ok(self.llm_client.count_tokens(chunk))
+        })?;
+        let context_str = packed.concat();
 
-        let user_prompt = format!("Context:\n{}\n\nQuestion: {}", context_str, query_text);
+        let user_prompt = format!(
+            "Context:\n{}\n\nQuestion: {}",
+            context_str.trim_end(),
+            query_text
+        );
 
         // 4. Generate Answer
         self.llm_client
@@ -1,4 +1,4 @@
-use facet_core::llm::LocalLlm;
+use facet_core::llm::{Llm, LocalLlm};
 
 #[tokio::test]
 #[ignore] // Ignored by default as it downloads model (~2GB) and requires Metal/CPU
@@ -413,11 +413,7 @@ async fn clone_repo_with_lfs(
 
         // Add progress callback for Git operations
         callbacks.pack_progress(|stage, current, total| {
-            let pct = if total > 0 {
-                (100 * current) / total
-            } else {
-                0
-            };
+            let pct = (100 * current).checked_div(total).unwrap_or(0);
             progress_bar.set_message(format!(
                 "Cloning: {:?} {}% ({}/{})",
                 stage, pct, current, total
@@ -0,0 +1,95 @@
+//! Throughput of concurrent `get_neighbors` calls on one `SurrealStore`
+//!
+//! Run with `cargo bench -p facet-graph --bench concurrent_neighbors`.
+//!
+//! Every round makes the same number of calls, spread over 1 to 16 tasks
+//! that each hold a clone of the store. When calls wait on a shared
+//! connection the rate stays flat as tasks are added; otherwise it grows
+//! until the worker threads are saturated.
+
+use facet_graph::surreal_store::SurrealStore;
+use facet_graph::{Edge, GraphStore, Node};
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+const NODES: usize = 500;
+const EDGES_PER_NODE: usize = 8;
+const CALLS: usize = 4_000;
+const TASKS: [usize; 5] = [1, 2, 4, 8, 16];
+
+async fn seed(store: &SurrealStore) {
+    let nodes = (0..NODES)
+        .map(|i| Node {
+            id: format!("n{i}"),
+            label: "Entity".to_string(),
+            properties: json!({"name": format!("Node {i}")}),
+            partition_id: "personal".to_string(),
+        })
+        .collect();
+    store.add_nodes(nodes).await.unwrap();
+
+    let edges = (0..NODES)
+        .flat_map(|i| {
+            (1..=EDGES_PER_NODE).map(move |k| Edge {
+                source: format!("n{i}"),
+                target: format!("n{}", (i + k * 37) % NODES),
+                relation: "related_to".to_string(),
+                weight: 1.0,
+                partition_id: "personal".to_string(),
+                valid_from: None,
+                valid_to: None,
+                cross_partition: false,
+            })
+        })
+        .collect();
+    store.add_edges(edges).await.unwrap();
+}
+
+async fn run_round(store: &SurrealStore, tasks: usize) -> Duration {
+    let per_task = CALLS / tasks;
+    let start = Instant::now();
+    let handles: Vec<_> = (0..tasks)
+        .map(|t| {
+            let store = store.clone();
+            tokio::spawn(async move {
+                for i in 0..per_task {
+                    let id = format!("n{}", (t * per_task + i) % NODES);
+                    let neighbors = store.get_neighbors(&id).await.unwrap();
+                    assert_eq!(neighbors.len(), EDGES_PER_NODE);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    runtime.block_on(async {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SurrealStore::new(dir.path().join("bench.db"))
+            .await
+            .unwrap();
+        seed(&store).await;
+        // Warm up caches before timing
+        run_round(&store, 1).await;
+
+        println!("{CALLS} get_neighbors calls, {NODES} nodes x {EDGES_PER_NODE} edges");
+        for tasks in TASKS {
+            let elapsed = run_round(&store, tasks).await;
+            println!(
+                "{:>3} tasks: {:>8.1} ms  {:>9.0} calls/s",
+                tasks,
+                elapsed.as_secs_f64() * 1000.0,
+                CALLS as f64 / elapsed.as_secs_f64()
+            );
+        }
+    });
+}
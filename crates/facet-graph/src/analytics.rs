@@ -0,0 +1,269 @@
+//! Graph-wide scores and groupings computed over one partition at a time
+//!
+//! Results are written back into node properties so retrieval can read them
+//! straight off the node, e.g. to boost central entities.
+
+use crate::ephemeral_graph::EphemeralGraph;
+use crate::transaction::GraphTransaction;
+use crate::{GraphError, GraphStore};
+use std::collections::HashMap;
+
+/// Property holding a node's PageRank within its partition
+pub const PAGERANK_PROPERTY: &str = "pagerank";
+/// Property holding a node's degree centrality within its partition
+pub const DEGREE_PROPERTY: &str = "degree_centrality";
+/// Property holding the id of the community a node belongs to
+pub const COMMUNITY_PROPERTY: &str = "community_id";
+
+/// Upper bound on label propagation rounds; it usually settles in a handful
+const COMMUNITY_MAX_ITERATIONS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CentralityOptions {
+    pub damping: f64,
+    pub iterations: usize,
+}
+
+impl Default for CentralityOptions {
+    fn default() -> Self {
+        Self {
+            damping: 0.85,
+            iterations: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Centrality {
+    pub pagerank: f64,
+    pub degree: f64,
+}
+
+/// Computes PageRank and degree centrality for every node in the partition,
+/// counting only edges that stay inside it.
+pub async fn compute_centrality<S: GraphStore + ?Sized>(
+    store: &S,
+    partition_id: &str,
+    options: CentralityOptions,
+) -> Result<HashMap<String, Centrality>, GraphError> {
+    let snapshot = store.snapshot(Some(partition_id)).await?;
+    let graph = EphemeralGraph::from_nodes_and_edges(snapshot.nodes, snapshot.edges);
+
+    let pagerank = graph.pagerank(options.damping, options.iterations);
+    let degree = graph.degree_centrality();
+
+    Ok(pagerank
+        .into_iter()
+        .map(|(id, pagerank)| {
+            let degree = degree.get(&id).copied().unwrap_or_default();
+            (id, Centrality { pagerank, degree })
+        })
+        .collect())
+}
+
+/// Computes centrality for the partition and stores it on each node under
+/// `PAGERANK_PROPERTY` and `DEGREE_PROPERTY`, in a single transaction.
+pub async fn update_centrality<S: GraphStore + ?Sized>(
+    store: &S,
+    partition_id: &str,
+    options: CentralityOptions,
+) -> Result<HashMap<String, Centrality>, GraphError> {
+    let scores = compute_centrality(store, partition_id, options).await?;
+
+    let mut tx = GraphTransaction::new();
+    for mut node in store.query_by_partition(partition_id).await? {
+        let Some(score) = scores.get(&node.id) else {
+            continue;
+        };
+        if !node.properties.is_object() {
+            node.properties = serde_json::json!({});
+        }
+        node.properties[PAGERANK_PROPERTY] = serde_json::json!(score.pagerank);
+        node.properties[DEGREE_PROPERTY] = serde_json::json!(score.degree);
+        tx.update_node(node);
+    }
+    store.commit_transaction(tx).await?;
+
+    Ok(scores)
+}
+
+/// A group of densely connected nodes within one partition
+#[derive(Debug, Clone, PartialEq)]
+pub struct Community {
+    /// `<partition>-<n>`, numbered from 0 by descending size
+    pub id: String,
+    pub members: Vec<String>,
+}
+
+/// Splits the partition into communities with label propagation, counting
+/// only edges that stay inside it. Isolated nodes form their own community.
+pub async fn detect_communities<S: GraphStore + ?Sized>(
+    store: &S,
+    partition_id: &str,
+) -> Result<Vec<Community>, GraphError> {
+    let snapshot = store.snapshot(Some(partition_id)).await?;
+    let graph = EphemeralGraph::from_nodes_and_edges(snapshot.nodes, snapshot.edges);
+
+    Ok(graph
+        .label_propagation(COMMUNITY_MAX_ITERATIONS)
+        .into_iter()
+        .enumerate()
+        .map(|(i, members)| Community {
+            id: format!("{}-{}", partition_id, i),
+            members,
+        })
+        .collect())
+}
+
+/// Detects communities in the partition and tags each node with its
+/// `COMMUNITY_PROPERTY`, in a single transaction.
+pub async fn update_communities<S: GraphStore + ?Sized>(
+    store: &S,
+    partition_id: &str,
+) -> Result<Vec<Community>, GraphError> {
+    let communities = detect_communities(store, partition_id).await?;
+    let membership: HashMap<&str, &str> = communities
+        .iter()
+        .flat_map(|c| c.members.iter().map(|m| (m.as_str(), c.id.as_str())))
+        .collect();
+
+    let mut tx = GraphTransaction::new();
+    for mut node in store.query_by_partition(partition_id).await? {
+        let Some(community) = membership.get(node.id.as_str()) else {
+            continue;
+        };
+        if !node.properties.is_object() {
+            node.properties = serde_json::json!({});
+        }
+        node.properties[COMMUNITY_PROPERTY] = serde_json::json!(community);
+        tx.update_node(node);
+    }
+    store.commit_transaction(tx).await?;
+
+    Ok(communities)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mocks::MockGraphStore;
+    use crate::{Edge, Node};
+    use serde_json::json;
+
+    async fn star(store: &MockGraphStore) {
+        for (id, partition) in [
+            ("hub", "personal"),
+            ("a", "personal"),
+            ("b", "personal"),
+            ("c", "personal"),
+            ("w", "work"),
+        ] {
+            store
+                .add_node(Node {
+                    id: id.to_string(),
+                    label: "Entity".to_string(),
+                    properties: json!({"name": id}),
+                    partition_id: partition.to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        for (source, partition) in [("a", "personal"), ("b", "personal"), ("c", "personal")] {
+            store
+                .add_edge(Edge {
+                    source: source.to_string(),
+                    target: "hub".to_string(),
+                    relation: "mentions".to_string(),
+                    weight: 1.0,
+                    partition_id: partition.to_string(),
+                    valid_from: None,
+                    valid_to: None,
+                    cross_partition: false,
+                })
+                .await
+                .unwrap();
+        }
+        // Crosses partitions, so it does not count
+        store
+            .add_edge(Edge {
+                source: "w".to_string(),
+                target: "a".to_string(),
+                relation: "mentions".to_string(),
+                weight: 1.0,
+                partition_id: "work".to_string(),
+                valid_from: None,
+                valid_to: None,
+                cross_partition: false,
+            })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_compute_centrality_per_partition() {
+        let store = MockGraphStore::new();
+        star(&store).await;
+
+        let scores = compute_centrality(&store, "personal", CentralityOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(scores.len(), 4);
+        assert!(scores["hub"].pagerank > scores["a"].pagerank);
+        assert!((scores["hub"].degree - 1.0).abs() < 1e-9);
+        assert!((scores["a"].degree - 1.0 / 3.0).abs() < 1e-9);
+        assert!(!scores.contains_key("w"));
+    }
+
+    #[tokio::test]
+    async fn test_update_centrality_persists_scores() {
+        let store = MockGraphStore::new();
+        star(&store).await;
+
+        let scores = update_centrality(&store, "personal", CentralityOptions::default())
+            .await
+            .unwrap();
+
+        let hub = store.get_node("hub").await.unwrap();
+        assert_eq!(hub.properties["name"], "hub");
+        assert_eq!(
+            hub.properties[PAGERANK_PROPERTY].as_f64().unwrap(),
+            scores["hub"].pagerank
+        );
+        assert_eq!(hub.properties[DEGREE_PROPERTY].as_f64().unwrap(), 1.0);
+
+        // Other partitions are left alone
+        let w = store.get_node("w").await.unwrap();
+        assert!(w.properties.get(PAGERANK_PROPERTY).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_communities_tags_nodes() {
+        let store = MockGraphStore::new();
+        star(&store).await;
+        store
+            .add_node(Node {
+                id: "loner".to_string(),
+                label: "Entity".to_string(),
+                properties: json!({}),
+                partition_id: "personal".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let communities = update_communities(&store, "personal").await.unwrap();
+
+        assert_eq!(communities.len(), 2);
+        assert_eq!(communities[0].id, "personal-0");
+        assert_eq!(communities[0].members, vec!["a", "b", "c", "hub"]);
+        assert_eq!(communities[1].members, vec!["loner"]);
+
+        let hub = store.get_node("hub").await.unwrap();
+        assert_eq!(hub.properties[COMMUNITY_PROPERTY], "personal-0");
+        assert_eq!(hub.properties["name"], "hub");
+        let loner = store.get_node("loner").await.unwrap();
+        assert_eq!(loner.properties[COMMUNITY_PROPERTY], "personal-1");
+        let w = store.get_node("w").await.unwrap();
+        assert!(w.properties.get(COMMUNITY_PROPERTY).is_none());
+    }
+}
@@ -0,0 +1,352 @@
+//! Config-driven choice of storage backend
+//!
+//! `StoreConfig::open` returns an `AnyStore`, which forwards every call to
+//! either SurrealDB (the default) or SQLite when the `sqlite` feature is on.
+
+use crate::snapshot::GraphSnapshot;
+use crate::surreal_store::{SurrealStore, VectorIndex};
+use crate::transaction::GraphTransaction;
+use crate::{
+    CrossPartitionPolicy, Direction, Edge, GraphError, GraphStore, Node, NodeVersion, PageRequest,
+    PartitionInfo, StoredEmbedding, VectorStore,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[cfg(feature = "sqlite")]
+use crate::sqlite_store::SqliteStore;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    #[default]
+    Surreal,
+    Sqlite,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoreConfig {
+    #[serde(default)]
+    pub backend: BackendKind,
+    pub path: PathBuf,
+    /// Vector index to create on the SurrealDB backend. SQLite always scans.
+    #[serde(default)]
+    pub vector_index: Option<VectorIndex>,
+    /// Turn on node history when opening (see `AnyStore::set_history`)
+    #[serde(default)]
+    pub history: bool,
+    /// What to do with edges between partitions
+    #[serde(default)]
+    pub cross_partition: CrossPartitionPolicy,
+    /// Length every embedding must have on the SurrealDB backend (see
+    /// `SurrealStore::with_embedding_dimension`)
+    #[serde(default)]
+    pub embedding_dimension: Option<usize>,
+}
+
+impl StoreConfig {
+    pub fn new(backend: BackendKind, path: PathBuf) -> Self {
+        Self {
+            backend,
+            path,
+            vector_index: None,
+            history: false,
+            cross_partition: CrossPartitionPolicy::default(),
+            embedding_dimension: None,
+        }
+    }
+
+    pub fn with_vector_index(mut self, index: VectorIndex) -> Self {
+        self.vector_index = Some(index);
+        self
+    }
+
+    pub fn with_history(mut self) -> Self {
+        self.history = true;
+        self
+    }
+
+    pub fn with_cross_partition_policy(mut self, policy: CrossPartitionPolicy) -> Self {
+        self.cross_partition = policy;
+        self
+    }
+
+    pub fn with_embedding_dimension(mut self, dimension: usize) -> Self {
+        self.embedding_dimension = Some(dimension);
+        self
+    }
+
+    pub async fn open(&self) -> Result<AnyStore, GraphError> {
+        let store = self.open_backend().await?;
+        if self.history {
+            store.set_history(true).await?;
+        }
+        Ok(store)
+    }
+
+    async fn open_backend(&self) -> Result<AnyStore, GraphError> {
+        match self.backend {
+            BackendKind::Surreal => {
+                let store = match self.vector_index {
+                    Some(index) => {
+                        SurrealStore::with_vector_index(self.path.clone(), index).await?
+                    }
+                    None => SurrealStore::new(self.path.clone()).await?,
+                };
+                let store = match self.embedding_dimension {
+                    Some(dimension) => store.with_embedding_dimension(dimension),
+                    None => store,
+                };
+                Ok(AnyStore::Surreal(
+                    store.with_cross_partition_policy(self.cross_partition),
+                ))
+            }
+            #[cfg(feature = "sqlite")]
+            BackendKind::Sqlite => Ok(AnyStore::Sqlite(
+                SqliteStore::new(self.path.clone())?
+                    .with_cross_partition_policy(self.cross_partition),
+            )),
+            #[cfg(not(feature = "sqlite"))]
+            BackendKind::Sqlite => Err(GraphError::Storage(
+                "facet-graph was built without the `sqlite` feature".to_string(),
+            )),
+        }
+    }
+}
+
+/// A store opened from `StoreConfig`
+#[derive(Clone)]
+pub enum AnyStore {
+    Surreal(SurrealStore),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteStore),
+}
+
+impl AnyStore {
+    /// Turns node history on or off in the underlying database
+    pub async fn set_history(&self, enabled: bool) -> Result<(), GraphError> {
+        match self {
+            AnyStore::Surreal(store) => store.set_history(enabled).await,
+            #[cfg(feature = "sqlite")]
+            AnyStore::Sqlite(store) => store.set_history(enabled),
+        }
+    }
+}
+
+macro_rules! dispatch {
+    ($self:ident, $store:ident => $call:expr) => {
+        match $self {
+            AnyStore::Surreal($store) => $call,
+            #[cfg(feature = "sqlite")]
+            AnyStore::Sqlite($store) => $call,
+        }
+    };
+}
+
+#[async_trait]
+impl GraphStore for AnyStore {
+    async fn add_node(&self, node: Node) -> Result<(), GraphError> {
+        dispatch!(self, s => s.add_node(node).await)
+    }
+
+    async fn add_edge(&self, edge: Edge) -> Result<(), GraphError> {
+        dispatch!(self, s => s.add_edge(edge).await)
+    }
+
+    async fn get_node(&self, id: &str) -> Result<Node, GraphError> {
+        dispatch!(self, s => s.get_node(id).await)
+    }
+
+    async fn get_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+        dispatch!(self, s => s.get_neighbors(id).await)
+    }
+
+    async fn get_incoming_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+        dispatch!(self, s => s.get_incoming_neighbors(id).await)
+    }
+
+    async fn update_node(&self, node: Node) -> Result<(), GraphError> {
+        dispatch!(self, s => s.update_node(node).await)
+    }
+
+    async fn get_node_history(&self, id: &str) -> Result<Vec<NodeVersion>, GraphError> {
+        dispatch!(self, s => s.get_node_history(id).await)
+    }
+
+    async fn traverse(
+        &self,
+        id: &str,
+        depth: usize,
+        direction: Direction,
+        relation_filter: Option<&[&str]>,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        dispatch!(self, s => s.traverse(id, depth, direction, relation_filter).await)
+    }
+
+    async fn upsert_node(&self, node: Node) -> Result<(), GraphError> {
+        dispatch!(self, s => s.upsert_node(node).await)
+    }
+
+    async fn patch_node(&self, id: &str, patch: serde_json::Value) -> Result<Node, GraphError> {
+        dispatch!(self, s => s.patch_node(id, patch).await)
+    }
+
+    async fn add_nodes(&self, nodes: Vec<Node>) -> Result<(), GraphError> {
+        dispatch!(self, s => s.add_nodes(nodes).await)
+    }
+
+    async fn add_edges(&self, edges: Vec<Edge>) -> Result<(), GraphError> {
+        dispatch!(self, s => s.add_edges(edges).await)
+    }
+
+    async fn delete_node(&self, id: &str, cascade: bool) -> Result<(), GraphError> {
+        dispatch!(self, s => s.delete_node(id, cascade).await)
+    }
+
+    async fn delete_edge(
+        &self,
+        source: &str,
+        relation: &str,
+        target: &str,
+    ) -> Result<(), GraphError> {
+        dispatch!(self, s => s.delete_edge(source, relation, target).await)
+    }
+
+    async fn update_edge(&self, edge: Edge) -> Result<(), GraphError> {
+        dispatch!(self, s => s.update_edge(edge).await)
+    }
+
+    async fn increment_edge_weight(
+        &self,
+        source: &str,
+        relation: &str,
+        target: &str,
+        delta: f32,
+    ) -> Result<f32, GraphError> {
+        dispatch!(self, s => s.increment_edge_weight(source, relation, target, delta).await)
+    }
+
+    async fn commit_transaction(&self, tx: GraphTransaction) -> Result<(), GraphError> {
+        dispatch!(self, s => s.commit_transaction(tx).await)
+    }
+
+    async fn search_text(&self, query: &str, limit: usize) -> Result<Vec<(Node, f32)>, GraphError> {
+        dispatch!(self, s => s.search_text(query, limit).await)
+    }
+
+    async fn query_by_partition(&self, partition_id: &str) -> Result<Vec<Node>, GraphError> {
+        dispatch!(self, s => s.query_by_partition(partition_id).await)
+    }
+
+    async fn query_by_label(
+        &self,
+        label: &str,
+        partition_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Node>, GraphError> {
+        dispatch!(self, s => s.query_by_label(label, partition_id, limit).await)
+    }
+
+    async fn node_exists(&self, id: &str) -> Result<bool, GraphError> {
+        dispatch!(self, s => s.node_exists(id).await)
+    }
+
+    async fn count_nodes(&self, partition_id: Option<&str>) -> Result<usize, GraphError> {
+        dispatch!(self, s => s.count_nodes(partition_id).await)
+    }
+
+    async fn count_edges(&self, relation: Option<&str>) -> Result<usize, GraphError> {
+        dispatch!(self, s => s.count_edges(relation).await)
+    }
+
+    async fn snapshot(&self, partition_id: Option<&str>) -> Result<GraphSnapshot, GraphError> {
+        dispatch!(self, s => s.snapshot(partition_id).await)
+    }
+
+    async fn query_by_partition_page(
+        &self,
+        partition_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Node>, GraphError> {
+        dispatch!(self, s => s.query_by_partition_page(partition_id, page).await)
+    }
+
+    async fn get_neighbors_in_partition(
+        &self,
+        id: &str,
+        partition_id: &str,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        dispatch!(self, s => s.get_neighbors_in_partition(id, partition_id).await)
+    }
+
+    async fn create_partition(&self, partition_id: &str) -> Result<(), GraphError> {
+        dispatch!(self, s => s.create_partition(partition_id).await)
+    }
+
+    async fn list_partitions(&self) -> Result<Vec<PartitionInfo>, GraphError> {
+        dispatch!(self, s => s.list_partitions().await)
+    }
+
+    async fn rename_partition(&self, from: &str, to: &str) -> Result<(), GraphError> {
+        dispatch!(self, s => s.rename_partition(from, to).await)
+    }
+
+    async fn delete_partition(&self, partition_id: &str, cascade: bool) -> Result<(), GraphError> {
+        dispatch!(self, s => s.delete_partition(partition_id, cascade).await)
+    }
+}
+
+#[async_trait]
+impl VectorStore for AnyStore {
+    async fn add_embedding(
+        &self,
+        id: &str,
+        vector: Vec<f32>,
+        field: Option<&str>,
+    ) -> Result<(), GraphError> {
+        dispatch!(self, s => s.add_embedding(id, vector, field).await)
+    }
+
+    async fn search(
+        &self,
+        vector: Vec<f32>,
+        limit: usize,
+        field: Option<&str>,
+    ) -> Result<Vec<(String, f32)>, GraphError> {
+        dispatch!(self, s => s.search(vector, limit, field).await)
+    }
+
+    async fn add_embeddings(
+        &self,
+        embeddings: Vec<(String, Vec<f32>)>,
+        field: Option<&str>,
+    ) -> Result<(), GraphError> {
+        dispatch!(self, s => s.add_embeddings(embeddings, field).await)
+    }
+
+    async fn remove_embedding(&self, id: &str) -> Result<(), GraphError> {
+        dispatch!(self, s => s.remove_embedding(id).await)
+    }
+
+    async fn clear_embeddings(&self, partition_id: &str) -> Result<(), GraphError> {
+        dispatch!(self, s => s.clear_embeddings(partition_id).await)
+    }
+
+    async fn search_nodes(
+        &self,
+        vector: Vec<f32>,
+        limit: usize,
+        field: Option<&str>,
+    ) -> Result<Vec<(Node, f32)>, GraphError> {
+        dispatch!(self, s => s.search_nodes(vector, limit, field).await)
+    }
+
+    async fn get_embeddings(&self, id: &str) -> Result<Vec<StoredEmbedding>, GraphError> {
+        dispatch!(self, s => s.get_embeddings(id).await)
+    }
+
+    async fn list_embeddings(&self) -> Result<Vec<StoredEmbedding>, GraphError> {
+        dispatch!(self, s => s.list_embeddings().await)
+    }
+}
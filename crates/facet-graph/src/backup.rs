@@ -0,0 +1,130 @@
+//! Whole-store backups
+//!
+//! `backup` writes every node, edge and embedding of a store to one
+//! gzip-compressed JSON file; `restore` rebuilds an empty store from it.
+//! The file carries a format version so older backups stay readable.
+
+use crate::transaction::GraphTransaction;
+use crate::{Edge, GraphError, GraphStore, Node, StoredEmbedding, VectorStore};
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/// Format version written by `backup`
+pub const BACKUP_VERSION: u32 = 1;
+
+/// Contents of a backup file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GraphBackup {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub nodes: Vec<Node>,
+    #[serde(default)]
+    pub edges: Vec<Edge>,
+    #[serde(default)]
+    pub embeddings: Vec<StoredEmbedding>,
+}
+
+/// What a backup or restore covered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupSummary {
+    pub nodes: usize,
+    pub edges: usize,
+    pub embeddings: usize,
+}
+
+impl GraphBackup {
+    pub fn summary(&self) -> BackupSummary {
+        BackupSummary {
+            nodes: self.nodes.len(),
+            edges: self.edges.len(),
+            embeddings: self.embeddings.len(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), GraphError> {
+        let file = File::create(path).map_err(|e| {
+            GraphError::Storage(format!("Failed to write backup {}: {}", path.display(), e))
+        })?;
+        let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+        serde_json::to_writer(&mut encoder, self)
+            .map_err(|e| GraphError::Storage(format!("Failed to serialize backup: {}", e)))?;
+        encoder.finish().map_err(|e| {
+            GraphError::Storage(format!("Failed to write backup {}: {}", path.display(), e))
+        })?;
+        Ok(())
+    }
+
+    /// Reads a backup, rejecting versions newer than this build understands
+    pub fn load(path: &Path) -> Result<Self, GraphError> {
+        let file = File::open(path).map_err(|e| {
+            GraphError::Storage(format!("Failed to read backup {}: {}", path.display(), e))
+        })?;
+        let backup: GraphBackup = serde_json::from_reader(GzDecoder::new(BufReader::new(file)))
+            .map_err(|e| GraphError::Storage(format!("Invalid backup: {}", e)))?;
+        if backup.version > BACKUP_VERSION {
+            return Err(GraphError::Storage(format!(
+                "Unsupported backup version {} (newest supported is {})",
+                backup.version, BACKUP_VERSION
+            )));
+        }
+        Ok(backup)
+    }
+}
+
+/// Writes everything in `store` to `path`
+pub async fn backup<S: GraphStore + VectorStore + ?Sized>(
+    store: &S,
+    path: &Path,
+) -> Result<BackupSummary, GraphError> {
+    let snapshot = store.snapshot(None).await?;
+    let backup = GraphBackup {
+        version: BACKUP_VERSION,
+        created_at: Utc::now(),
+        nodes: snapshot.nodes,
+        edges: snapshot.edges,
+        embeddings: store.list_embeddings().await?,
+    };
+    backup.save(path)?;
+    Ok(backup.summary())
+}
+
+/// Loads the backup at `path` into `store`, which should be empty: a node
+/// that already exists makes the restore fail. Nodes and edges are written
+/// in one transaction, then the embeddings.
+pub async fn restore<S: GraphStore + VectorStore + ?Sized>(
+    store: &S,
+    path: &Path,
+) -> Result<BackupSummary, GraphError> {
+    let backup = GraphBackup::load(path)?;
+    let summary = backup.summary();
+
+    let mut tx = GraphTransaction::new();
+    for node in backup.nodes {
+        tx.add_node(node);
+    }
+    for edge in backup.edges {
+        tx.add_edge(edge);
+    }
+    store.commit_transaction(tx).await?;
+
+    let mut by_field: BTreeMap<Option<String>, Vec<(String, Vec<f32>)>> = BTreeMap::new();
+    for embedding in backup.embeddings {
+        by_field
+            .entry(embedding.field)
+            .or_default()
+            .push((embedding.id, embedding.vector));
+    }
+    for (field, embeddings) in by_field {
+        store.add_embeddings(embeddings, field.as_deref()).await?;
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,713 @@
+//! Read-through cache for hot nodes and neighborhoods
+//!
+//! Retrieval keeps coming back to the same central nodes. `CachedStore`
+//! wraps any store and keeps the most recently used `get_node`,
+//! `get_neighbors` and `get_incoming_neighbors` results in memory. Writes
+//! made through the wrapper drop the entries they affect; writes made to the
+//! inner store directly are not seen, so wrap the only handle that writes.
+//!
+//! Traversals, paths and subgraphs built from the default `GraphStore`
+//! methods read through the cache as well.
+
+use crate::snapshot::GraphSnapshot;
+use crate::transaction::{GraphOp, GraphTransaction};
+use crate::{
+    Direction, Edge, GraphError, GraphStore, Node, NodeVersion, PageRequest, PartitionInfo,
+    StoredEmbedding, VectorStore,
+};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// How many entries each cache holds before evicting the least recently
+/// used one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// Nodes from `get_node`
+    pub nodes: usize,
+    /// Neighbor lists, counted separately per direction
+    pub neighborhoods: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            nodes: 10_000,
+            neighborhoods: 1_000,
+        }
+    }
+}
+
+/// Cache activity since the store was created or last reset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /// Entries dropped because a write changed them
+    pub invalidations: u64,
+}
+
+impl CacheStats {
+    /// Share of lookups answered from the cache
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// A map that evicts its least recently used entry when full
+struct Lru<V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<String, (V, u64)>,
+    // Last use -> key, oldest first
+    order: BTreeMap<u64, String>,
+}
+
+impl<V: Clone> Lru<V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<V> {
+        self.tick += 1;
+        let (value, used) = self.entries.get_mut(key)?;
+        self.order.remove(used);
+        *used = self.tick;
+        self.order.insert(self.tick, key.to_string());
+        Some(value.clone())
+    }
+
+    /// Returns the number of entries evicted to make room
+    fn insert(&mut self, key: String, value: V) -> u64 {
+        if self.capacity == 0 {
+            return 0;
+        }
+        self.remove(&key);
+        let mut evicted = 0;
+        while self.entries.len() >= self.capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+            evicted += 1;
+        }
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (value, self.tick));
+        evicted
+    }
+
+    fn remove(&mut self, key: &str) -> bool {
+        match self.entries.remove(key) {
+            Some((_, used)) => {
+                self.order.remove(&used);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops every entry `keep` rejects and returns how many went
+    fn retain(&mut self, mut keep: impl FnMut(&V) -> bool) -> u64 {
+        let stale: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, (value, _))| !keep(value))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &stale {
+            self.remove(key);
+        }
+        stale.len() as u64
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Every value, most recently used first, without touching the order
+    fn recent(&self) -> Vec<V>
+    where
+        V: Clone,
+    {
+        self.order
+            .values()
+            .rev()
+            .filter_map(|key| self.entries.get(key).map(|(value, _)| value.clone()))
+            .collect()
+    }
+
+    fn clear(&mut self) -> u64 {
+        let count = self.entries.len() as u64;
+        self.entries.clear();
+        self.order.clear();
+        count
+    }
+}
+
+type Neighborhood = Vec<(Edge, Node)>;
+
+struct CacheState {
+    nodes: Lru<Node>,
+    outgoing: Lru<Neighborhood>,
+    incoming: Lru<Neighborhood>,
+    stats: CacheStats,
+    // Bumped by every invalidation, so a read that raced with a write does
+    // not put back what the write just dropped
+    generation: u64,
+}
+
+impl CacheState {
+    fn invalidate_node(&mut self, id: &str) {
+        let mut dropped = 0;
+        dropped += self.nodes.remove(id) as u64;
+        dropped += self.outgoing.remove(id) as u64;
+        dropped += self.incoming.remove(id) as u64;
+        // Neighbor lists carry a copy of the node on the far side
+        let mentions = |list: &Neighborhood| list.iter().any(|(_, n)| n.id == id);
+        dropped += self.outgoing.retain(|list| !mentions(list));
+        dropped += self.incoming.retain(|list| !mentions(list));
+        self.stats.invalidations += dropped;
+        self.generation += 1;
+    }
+
+    fn invalidate_edge(&mut self, source: &str, target: &str) {
+        let dropped = self.outgoing.remove(source) as u64 + self.incoming.remove(target) as u64;
+        self.stats.invalidations += dropped;
+        self.generation += 1;
+    }
+
+    fn invalidate_op(&mut self, op: &GraphOp) {
+        match op {
+            GraphOp::AddNode(node) | GraphOp::UpdateNode(node) => self.invalidate_node(&node.id),
+            GraphOp::DeleteNode { id, .. } => self.invalidate_node(id),
+            GraphOp::AddEdge(edge) => self.invalidate_edge(&edge.source, &edge.target),
+            GraphOp::DeleteEdge { source, target, .. } => self.invalidate_edge(source, target),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.stats.invalidations +=
+            self.nodes.clear() + self.outgoing.clear() + self.incoming.clear();
+        self.generation += 1;
+    }
+}
+
+/// A store that answers repeated node and neighbor reads from memory and
+/// passes everything else straight through.
+///
+/// Clones share one cache, so every handle sees the others' writes.
+#[derive(Clone)]
+pub struct CachedStore<S> {
+    inner: S,
+    state: Arc<Mutex<CacheState>>,
+}
+
+impl<S> CachedStore<S> {
+    pub fn new(inner: S, config: CacheConfig) -> Self {
+        Self {
+            inner,
+            state: Arc::new(Mutex::new(CacheState {
+                nodes: Lru::new(config.nodes),
+                outgoing: Lru::new(config.neighborhoods),
+                incoming: Lru::new(config.neighborhoods),
+                stats: CacheStats::default(),
+                generation: 0,
+            })),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.state.lock().unwrap().stats
+    }
+
+    pub fn reset_stats(&self) {
+        self.state.lock().unwrap().stats = CacheStats::default();
+    }
+
+    /// Number of cached nodes and neighbor lists
+    pub fn len(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.nodes.len() + state.outgoing.len() + state.incoming.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The cached nodes, most recently used first
+    ///
+    /// Reading them does not count as using them, so this neither reorders
+    /// the cache nor moves its stats.
+    pub fn hot_nodes(&self) -> Vec<Node> {
+        self.state.lock().unwrap().nodes.recent()
+    }
+
+    /// Drops every cached entry, e.g. after writing to the inner store
+    /// directly
+    pub fn clear(&self) {
+        self.state.lock().unwrap().clear();
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn invalidate(&self, f: impl FnOnce(&mut CacheState)) {
+        f(&mut self.state.lock().unwrap());
+    }
+
+    /// Looks `key` up in the cache `pick` selects, or loads it with `load`
+    /// and caches the result. Failed loads are not cached.
+    async fn read_through<V: Clone>(
+        &self,
+        key: &str,
+        pick: impl Fn(&mut CacheState) -> &mut Lru<V>,
+        load: impl std::future::Future<Output = Result<V, GraphError>>,
+    ) -> Result<V, GraphError> {
+        let generation = {
+            let mut state = self.state.lock().unwrap();
+            if let Some(value) = pick(&mut state).get(key) {
+                state.stats.hits += 1;
+                return Ok(value);
+            }
+            state.stats.misses += 1;
+            state.generation
+        };
+
+        let value = load.await?;
+
+        let mut state = self.state.lock().unwrap();
+        if state.generation == generation {
+            let evicted = pick(&mut state).insert(key.to_string(), value.clone());
+            state.stats.evictions += evicted;
+        }
+        Ok(value)
+    }
+}
+
+#[async_trait]
+impl<S: GraphStore> GraphStore for CachedStore<S> {
+    async fn add_node(&self, node: Node) -> Result<(), GraphError> {
+        let id = node.id.clone();
+        let result = self.inner.add_node(node).await;
+        self.invalidate(|s| s.invalidate_node(&id));
+        result
+    }
+
+    async fn add_edge(&self, edge: Edge) -> Result<(), GraphError> {
+        let (source, target) = (edge.source.clone(), edge.target.clone());
+        let result = self.inner.add_edge(edge).await;
+        self.invalidate(|s| s.invalidate_edge(&source, &target));
+        result
+    }
+
+    async fn get_node(&self, id: &str) -> Result<Node, GraphError> {
+        self.read_through(id, |s| &mut s.nodes, self.inner.get_node(id))
+            .await
+    }
+
+    async fn get_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.read_through(id, |s| &mut s.outgoing, self.inner.get_neighbors(id))
+            .await
+    }
+
+    async fn get_incoming_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.read_through(
+            id,
+            |s| &mut s.incoming,
+            self.inner.get_incoming_neighbors(id),
+        )
+        .await
+    }
+
+    async fn update_node(&self, node: Node) -> Result<(), GraphError> {
+        let id = node.id.clone();
+        let result = self.inner.update_node(node).await;
+        self.invalidate(|s| s.invalidate_node(&id));
+        result
+    }
+
+    async fn get_node_history(&self, id: &str) -> Result<Vec<NodeVersion>, GraphError> {
+        self.inner.get_node_history(id).await
+    }
+
+    async fn traverse(
+        &self,
+        id: &str,
+        depth: usize,
+        direction: Direction,
+        relation_filter: Option<&[&str]>,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.inner
+            .traverse(id, depth, direction, relation_filter)
+            .await
+    }
+
+    async fn upsert_node(&self, node: Node) -> Result<(), GraphError> {
+        let id = node.id.clone();
+        let result = self.inner.upsert_node(node).await;
+        self.invalidate(|s| s.invalidate_node(&id));
+        result
+    }
+
+    async fn patch_node(&self, id: &str, patch: serde_json::Value) -> Result<Node, GraphError> {
+        let result = self.inner.patch_node(id, patch).await;
+        self.invalidate(|s| s.invalidate_node(id));
+        result
+    }
+
+    async fn add_nodes(&self, nodes: Vec<Node>) -> Result<(), GraphError> {
+        let ids: Vec<String> = nodes.iter().map(|n| n.id.clone()).collect();
+        let result = self.inner.add_nodes(nodes).await;
+        self.invalidate(|s| ids.iter().for_each(|id| s.invalidate_node(id)));
+        result
+    }
+
+    async fn add_edges(&self, edges: Vec<Edge>) -> Result<(), GraphError> {
+        let ends: Vec<(String, String)> = edges
+            .iter()
+            .map(|e| (e.source.clone(), e.target.clone()))
+            .collect();
+        let result = self.inner.add_edges(edges).await;
+        self.invalidate(|s| {
+            for (source, target) in &ends {
+                s.invalidate_edge(source, target);
+            }
+        });
+        result
+    }
+
+    async fn delete_node(&self, id: &str, cascade: bool) -> Result<(), GraphError> {
+        let result = self.inner.delete_node(id, cascade).await;
+        self.invalidate(|s| s.invalidate_node(id));
+        result
+    }
+
+    async fn delete_edge(
+        &self,
+        source: &str,
+        relation: &str,
+        target: &str,
+    ) -> Result<(), GraphError> {
+        let result = self.inner.delete_edge(source, relation, target).await;
+        self.invalidate(|s| s.invalidate_edge(source, target));
+        result
+    }
+
+    async fn update_edge(&self, edge: Edge) -> Result<(), GraphError> {
+        let (source, target) = (edge.source.clone(), edge.target.clone());
+        let result = self.inner.update_edge(edge).await;
+        self.invalidate(|s| s.invalidate_edge(&source, &target));
+        result
+    }
+
+    async fn increment_edge_weight(
+        &self,
+        source: &str,
+        relation: &str,
+        target: &str,
+        delta: f32,
+    ) -> Result<f32, GraphError> {
+        let result = self
+            .inner
+            .increment_edge_weight(source, relation, target, delta)
+            .await;
+        self.invalidate(|s| s.invalidate_edge(source, target));
+        result
+    }
+
+    async fn commit_transaction(&self, tx: GraphTransaction) -> Result<(), GraphError> {
+        // Backends without atomic commits may apply part of a failed batch,
+        // so invalidate whatever the outcome
+        let ops = tx.ops().to_vec();
+        let result = self.inner.commit_transaction(tx).await;
+        self.invalidate(|s| ops.iter().for_each(|op| s.invalidate_op(op)));
+        result
+    }
+
+    async fn search_text(&self, query: &str, limit: usize) -> Result<Vec<(Node, f32)>, GraphError> {
+        self.inner.search_text(query, limit).await
+    }
+
+    async fn query_by_partition(&self, partition_id: &str) -> Result<Vec<Node>, GraphError> {
+        self.inner.query_by_partition(partition_id).await
+    }
+
+    async fn query_by_label(
+        &self,
+        label: &str,
+        partition_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Node>, GraphError> {
+        self.inner.query_by_label(label, partition_id, limit).await
+    }
+
+    async fn node_exists(&self, id: &str) -> Result<bool, GraphError> {
+        self.inner.node_exists(id).await
+    }
+
+    async fn count_nodes(&self, partition_id: Option<&str>) -> Result<usize, GraphError> {
+        self.inner.count_nodes(partition_id).await
+    }
+
+    async fn count_edges(&self, relation: Option<&str>) -> Result<usize, GraphError> {
+        self.inner.count_edges(relation).await
+    }
+
+    async fn snapshot(&self, partition_id: Option<&str>) -> Result<GraphSnapshot, GraphError> {
+        self.inner.snapshot(partition_id).await
+    }
+
+    async fn query_by_partition_page(
+        &self,
+        partition_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Node>, GraphError> {
+        self.inner.query_by_partition_page(partition_id, page).await
+    }
+
+    async fn get_neighbors_in_partition(
+        &self,
+        id: &str,
+        partition_id: &str,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.inner
+            .get_neighbors_in_partition(id, partition_id)
+            .await
+    }
+
+    async fn create_partition(&self, partition_id: &str) -> Result<(), GraphError> {
+        self.inner.create_partition(partition_id).await
+    }
+
+    async fn list_partitions(&self) -> Result<Vec<PartitionInfo>, GraphError> {
+        self.inner.list_partitions().await
+    }
+
+    async fn rename_partition(&self, from: &str, to: &str) -> Result<(), GraphError> {
+        let result = self.inner.rename_partition(from, to).await;
+        self.clear();
+        result
+    }
+
+    async fn delete_partition(&self, partition_id: &str, cascade: bool) -> Result<(), GraphError> {
+        let result = self.inner.delete_partition(partition_id, cascade).await;
+        self.clear();
+        result
+    }
+}
+
+#[async_trait]
+impl<S: VectorStore> VectorStore for CachedStore<S> {
+    async fn add_embedding(
+        &self,
+        id: &str,
+        vector: Vec<f32>,
+        field: Option<&str>,
+    ) -> Result<(), GraphError> {
+        self.inner.add_embedding(id, vector, field).await
+    }
+
+    async fn search(
+        &self,
+        vector: Vec<f32>,
+        limit: usize,
+        field: Option<&str>,
+    ) -> Result<Vec<(String, f32)>, GraphError> {
+        self.inner.search(vector, limit, field).await
+    }
+
+    async fn add_embeddings(
+        &self,
+        embeddings: Vec<(String, Vec<f32>)>,
+        field: Option<&str>,
+    ) -> Result<(), GraphError> {
+        self.inner.add_embeddings(embeddings, field).await
+    }
+
+    async fn remove_embedding(&self, id: &str) -> Result<(), GraphError> {
+        self.inner.remove_embedding(id).await
+    }
+
+    async fn clear_embeddings(&self, partition_id: &str) -> Result<(), GraphError> {
+        self.inner.clear_embeddings(partition_id).await
+    }
+
+    async fn search_nodes(
+        &self,
+        vector: Vec<f32>,
+        limit: usize,
+        field: Option<&str>,
+    ) -> Result<Vec<(Node, f32)>, GraphError> {
+        self.inner.search_nodes(vector, limit, field).await
+    }
+
+    async fn get_embeddings(&self, id: &str) -> Result<Vec<StoredEmbedding>, GraphError> {
+        self.inner.get_embeddings(id).await
+    }
+
+    async fn list_embeddings(&self) -> Result<Vec<StoredEmbedding>, GraphError> {
+        self.inner.list_embeddings().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mocks::MockGraphStore;
+    use serde_json::json;
+
+    fn node(id: &str, name: &str) -> Node {
+        Node {
+            id: id.to_string(),
+            label: "Person".to_string(),
+            properties: json!({"name": name}),
+            partition_id: "personal".to_string(),
+        }
+    }
+
+    fn edge(source: &str, target: &str) -> Edge {
+        Edge {
+            source: source.to_string(),
+            target: target.to_string(),
+            relation: "KNOWS".to_string(),
+            weight: 1.0,
+            partition_id: "personal".to_string(),
+            valid_from: None,
+            valid_to: None,
+            cross_partition: false,
+        }
+    }
+
+    async fn seeded(config: CacheConfig) -> CachedStore<MockGraphStore> {
+        let store = CachedStore::new(MockGraphStore::new(), config);
+        for (id, name) in [("a", "Alice"), ("b", "Bob"), ("c", "Carol")] {
+            store.add_node(node(id, name)).await.unwrap();
+        }
+        store.add_edge(edge("a", "b")).await.unwrap();
+        store.reset_stats();
+        store
+    }
+
+    #[tokio::test]
+    async fn test_repeated_reads_hit_the_cache() {
+        let store = seeded(CacheConfig::default()).await;
+
+        for _ in 0..3 {
+            assert_eq!(
+                store.get_node("a").await.unwrap().properties["name"],
+                "Alice"
+            );
+            assert_eq!(store.get_neighbors("a").await.unwrap().len(), 1);
+        }
+        let stats = store.stats();
+        assert_eq!((stats.hits, stats.misses), (4, 2));
+        assert!((stats.hit_rate() - 4.0 / 6.0).abs() < 1e-9);
+
+        // Misses are not cached
+        assert!(store.get_node("missing").await.is_err());
+        assert!(store.get_node("missing").await.is_err());
+        assert_eq!(store.stats().misses, 4);
+        assert_eq!(store.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_writes_invalidate_affected_entries() {
+        let store = seeded(CacheConfig::default()).await;
+        store.get_node("b").await.unwrap();
+        store.get_neighbors("a").await.unwrap();
+        store.get_incoming_neighbors("b").await.unwrap();
+
+        // The neighbor list of "a" holds a copy of "b"
+        store.update_node(node("b", "Robert")).await.unwrap();
+        assert_eq!(
+            store.get_node("b").await.unwrap().properties["name"],
+            "Robert"
+        );
+        assert_eq!(
+            store.get_neighbors("a").await.unwrap()[0].1.properties["name"],
+            "Robert"
+        );
+
+        store.add_edge(edge("a", "c")).await.unwrap();
+        assert_eq!(store.get_neighbors("a").await.unwrap().len(), 2);
+        store
+            .increment_edge_weight("a", "KNOWS", "c", 2.0)
+            .await
+            .unwrap();
+        let weights: Vec<f32> = store
+            .get_neighbors("a")
+            .await
+            .unwrap()
+            .iter()
+            .map(|(e, _)| e.weight)
+            .collect();
+        assert!(weights.contains(&3.0));
+
+        let mut tx = GraphTransaction::new();
+        tx.delete_edge("a", "KNOWS", "b");
+        store.commit_transaction(tx).await.unwrap();
+        assert!(store.get_incoming_neighbors("b").await.unwrap().is_empty());
+
+        store.delete_node("c", true).await.unwrap();
+        assert!(store.get_node("c").await.is_err());
+        assert!(store.get_neighbors("a").await.unwrap().is_empty());
+        assert!(store.stats().invalidations > 0);
+    }
+
+    #[tokio::test]
+    async fn test_evicts_least_recently_used() {
+        let store = seeded(CacheConfig {
+            nodes: 2,
+            neighborhoods: 2,
+        })
+        .await;
+
+        store.get_node("a").await.unwrap();
+        store.get_node("b").await.unwrap();
+        // Touch "a" so "b" is the oldest
+        store.get_node("a").await.unwrap();
+        store.get_node("c").await.unwrap();
+        assert_eq!(store.stats().evictions, 1);
+
+        store.reset_stats();
+        store.get_node("a").await.unwrap();
+        store.get_node("b").await.unwrap();
+        assert_eq!((store.stats().hits, store.stats().misses), (1, 1));
+
+        store.clear();
+        assert!(store.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hot_nodes_most_recent_first() {
+        let store = seeded(CacheConfig::default()).await;
+        assert!(store.hot_nodes().is_empty());
+
+        store.get_node("a").await.unwrap();
+        store.get_node("b").await.unwrap();
+        store.get_node("a").await.unwrap();
+        store.reset_stats();
+
+        let ids: Vec<String> = store.hot_nodes().into_iter().map(|n| n.id).collect();
+        assert_eq!(ids, ["a", "b"]);
+        assert_eq!((store.stats().hits, store.stats().misses), (0, 0));
+    }
+}
@@ -0,0 +1,429 @@
+//! Semantic diff between two graph snapshots
+//!
+//! Reports which nodes and edges were added, removed, or changed between two
+//! snapshots, down to individual property keys. Node ids are resolved to
+//! human-readable entity names so a report reads "Alice -[knows]-> Bob"
+//! rather than a list of UUIDs.
+
+use crate::snapshot::GraphSnapshot;
+use crate::{Edge, Node};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Property keys consulted (in order) when resolving a node's display name
+const NAME_KEYS: [&str; 3] = ["name", "title", "display_name"];
+
+/// Change to a single top-level property key
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PropertyChange {
+    pub key: String,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+/// A node present in both snapshots whose content differs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NodeChange {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<(String, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partition_id: Option<(String, String)>,
+    pub properties: Vec<PropertyChange>,
+}
+
+/// An edge present in both snapshots whose weight or partition differs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EdgeChange {
+    pub edge: EdgeRef,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<(f32, f32)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partition_id: Option<(String, String)>,
+}
+
+/// Edge identity (source, relation, target) with resolved entity names
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EdgeRef {
+    pub source: String,
+    pub source_name: String,
+    pub relation: String,
+    pub target: String,
+    pub target_name: String,
+}
+
+/// Node summary used for added/removed entries
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NodeRef {
+    pub id: String,
+    pub name: String,
+    pub label: String,
+    pub partition_id: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct GraphDiff {
+    pub added_nodes: Vec<NodeRef>,
+    pub removed_nodes: Vec<NodeRef>,
+    pub changed_nodes: Vec<NodeChange>,
+    pub added_edges: Vec<EdgeRef>,
+    pub removed_edges: Vec<EdgeRef>,
+    pub changed_edges: Vec<EdgeChange>,
+}
+
+impl GraphDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.changed_nodes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+            && self.changed_edges.is_empty()
+    }
+}
+
+/// Resolves the human-readable name of a node, falling back to its id
+pub fn display_name(node: &Node) -> String {
+    NAME_KEYS
+        .iter()
+        .find_map(|key| node.properties.get(*key).and_then(|v| v.as_str()))
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| node.id.clone())
+}
+
+type EdgeKey<'a> = (&'a str, &'a str, &'a str);
+
+fn edge_key(edge: &Edge) -> EdgeKey<'_> {
+    (&edge.source, &edge.relation, &edge.target)
+}
+
+/// Computes the diff needed to go from `before` to `after`
+pub fn diff_snapshots(before: &GraphSnapshot, after: &GraphSnapshot) -> GraphDiff {
+    let old_nodes: BTreeMap<&str, &Node> =
+        before.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+    let new_nodes: BTreeMap<&str, &Node> = after.nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+
+    // Prefer the newest name for an entity, but keep names of removed nodes
+    // resolvable so removed edges still read well.
+    let mut names: HashMap<&str, String> = HashMap::new();
+    for (id, node) in old_nodes.iter().chain(new_nodes.iter()) {
+        names.insert(*id, display_name(node));
+    }
+    let resolve = |id: &str| names.get(id).cloned().unwrap_or_else(|| id.to_string());
+
+    let mut diff = GraphDiff::default();
+
+    for (id, node) in &new_nodes {
+        match old_nodes.get(id) {
+            None => diff.added_nodes.push(node_ref(node)),
+            Some(old) => {
+                if let Some(change) = diff_node(old, node) {
+                    diff.changed_nodes.push(change);
+                }
+            }
+        }
+    }
+
+    for (id, node) in &old_nodes {
+        if !new_nodes.contains_key(id) {
+            diff.removed_nodes.push(node_ref(node));
+        }
+    }
+
+    let old_edges: BTreeMap<EdgeKey, &Edge> =
+        before.edges.iter().map(|e| (edge_key(e), e)).collect();
+    let new_edges: BTreeMap<EdgeKey, &Edge> =
+        after.edges.iter().map(|e| (edge_key(e), e)).collect();
+
+    let edge_ref = |edge: &Edge| EdgeRef {
+        source: edge.source.clone(),
+        source_name: resolve(&edge.source),
+        relation: edge.relation.clone(),
+        target: edge.target.clone(),
+        target_name: resolve(&edge.target),
+    };
+
+    for (key, edge) in &new_edges {
+        match old_edges.get(key) {
+            None => diff.added_edges.push(edge_ref(edge)),
+            Some(old) => {
+                let weight = ((old.weight - edge.weight).abs() > f32::EPSILON)
+                    .then_some((old.weight, edge.weight));
+                let partition_id = (old.partition_id != edge.partition_id)
+                    .then(|| (old.partition_id.clone(), edge.partition_id.clone()));
+
+                if weight.is_some() || partition_id.is_some() {
+                    diff.changed_edges.push(EdgeChange {
+                        edge: edge_ref(edge),
+                        weight,
+                        partition_id,
+                    });
+                }
+            }
+        }
+    }
+
+    for (key, edge) in &old_edges {
+        if !new_edges.contains_key(key) {
+            diff.removed_edges.push(edge_ref(edge));
+        }
+    }
+
+    diff
+}
+
+fn node_ref(node: &Node) -> NodeRef {
+    NodeRef {
+        id: node.id.clone(),
+        name: display_name(node),
+        label: node.label.clone(),
+        partition_id: node.partition_id.clone(),
+    }
+}
+
+fn diff_node(old: &Node, new: &Node) -> Option<NodeChange> {
+    let label = (old.label != new.label).then(|| (old.label.clone(), new.label.clone()));
+    let partition_id = (old.partition_id != new.partition_id)
+        .then(|| (old.partition_id.clone(), new.partition_id.clone()));
+    let properties = diff_properties(&old.properties, &new.properties);
+
+    if label.is_none() && partition_id.is_none() && properties.is_empty() {
+        return None;
+    }
+
+    Some(NodeChange {
+        id: new.id.clone(),
+        name: display_name(new),
+        label,
+        partition_id,
+        properties,
+    })
+}
+
+fn diff_properties(old: &serde_json::Value, new: &serde_json::Value) -> Vec<PropertyChange> {
+    let (old_map, new_map) = match (old.as_object(), new.as_object()) {
+        (Some(o), Some(n)) => (o, n),
+        // Non-object payloads are compared as a whole
+        _ if old != new => {
+            return vec![PropertyChange {
+                key: String::new(),
+                before: Some(old.clone()),
+                after: Some(new.clone()),
+            }]
+        }
+        _ => return vec![],
+    };
+
+    let mut keys: Vec<&String> = old_map.keys().chain(new_map.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    keys.into_iter()
+        .filter_map(|key| {
+            let before = old_map.get(key);
+            let after = new_map.get(key);
+            (before != after).then(|| PropertyChange {
+                key: key.clone(),
+                before: before.cloned(),
+                after: after.cloned(),
+            })
+        })
+        .collect()
+}
+
+fn format_value(value: &Option<serde_json::Value>) -> String {
+    match value {
+        Some(v) => v.to_string(),
+        None => "<unset>".to_string(),
+    }
+}
+
+impl fmt::Display for EdgeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} -[{}]-> {}",
+            self.source_name, self.relation, self.target_name
+        )
+    }
+}
+
+impl fmt::Display for GraphDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No changes");
+        }
+
+        writeln!(
+            f,
+            "Nodes: +{} -{} ~{}   Edges: +{} -{} ~{}",
+            self.added_nodes.len(),
+            self.removed_nodes.len(),
+            self.changed_nodes.len(),
+            self.added_edges.len(),
+            self.removed_edges.len(),
+            self.changed_edges.len()
+        )?;
+
+        for node in &self.added_nodes {
+            writeln!(f, "+ node {} ({}) [{}]", node.name, node.label, node.id)?;
+        }
+        for node in &self.removed_nodes {
+            writeln!(f, "- node {} ({}) [{}]", node.name, node.label, node.id)?;
+        }
+        for change in &self.changed_nodes {
+            writeln!(f, "~ node {} [{}]", change.name, change.id)?;
+            if let Some((old, new)) = &change.label {
+                writeln!(f, "    label: {} -> {}", old, new)?;
+            }
+            if let Some((old, new)) = &change.partition_id {
+                writeln!(f, "    partition: {} -> {}", old, new)?;
+            }
+            for prop in &change.properties {
+                writeln!(
+                    f,
+                    "    {}: {} -> {}",
+                    prop.key,
+                    format_value(&prop.before),
+                    format_value(&prop.after)
+                )?;
+            }
+        }
+
+        for edge in &self.added_edges {
+            writeln!(f, "+ edge {}", edge)?;
+        }
+        for edge in &self.removed_edges {
+            writeln!(f, "- edge {}", edge)?;
+        }
+        for change in &self.changed_edges {
+            writeln!(f, "~ edge {}", change.edge)?;
+            if let Some((old, new)) = change.weight {
+                writeln!(f, "    weight: {} -> {}", old, new)?;
+            }
+            if let Some((old, new)) = &change.partition_id {
+                writeln!(f, "    partition: {} -> {}", old, new)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn node(id: &str, name: &str, props: serde_json::Value) -> Node {
+        let mut properties = props;
+        properties["name"] = json!(name);
+        Node {
+            id: id.to_string(),
+            label: "Person".to_string(),
+            properties,
+            partition_id: "personal".to_string(),
+        }
+    }
+
+    fn edge(source: &str, target: &str, weight: f32) -> Edge {
+        Edge {
+            source: source.to_string(),
+            target: target.to_string(),
+            relation: "knows".to_string(),
+            weight,
+            partition_id: "personal".to_string(),
+            valid_from: None,
+            valid_to: None,
+            cross_partition: false,
+        }
+    }
+
+    #[test]
+    fn test_identical_snapshots_have_no_diff() {
+        let snapshot = GraphSnapshot::new(
+            vec![node("1", "Alice", json!({})), node("2", "Bob", json!({}))],
+            vec![edge("1", "2", 1.0)],
+        );
+
+        let diff = diff_snapshots(&snapshot, &snapshot);
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "No changes\n");
+    }
+
+    #[test]
+    fn test_added_removed_and_changed_nodes() {
+        let before = GraphSnapshot::new(
+            vec![
+                node("1", "Alice", json!({"age": 30, "city": "Paris"})),
+                node("2", "Bob", json!({})),
+            ],
+            vec![],
+        );
+        let after = GraphSnapshot::new(
+            vec![
+                node(
+                    "1",
+                    "Alice",
+                    json!({"age": 31, "email": "alice@example.com"}),
+                ),
+                node("3", "Carol", json!({})),
+            ],
+            vec![],
+        );
+
+        let diff = diff_snapshots(&before, &after);
+
+        assert_eq!(diff.added_nodes.len(), 1);
+        assert_eq!(diff.added_nodes[0].name, "Carol");
+        assert_eq!(diff.removed_nodes.len(), 1);
+        assert_eq!(diff.removed_nodes[0].name, "Bob");
+
+        assert_eq!(diff.changed_nodes.len(), 1);
+        let change = &diff.changed_nodes[0];
+        assert_eq!(change.name, "Alice");
+        let keys: Vec<&str> = change.properties.iter().map(|p| p.key.as_str()).collect();
+        assert_eq!(keys, vec!["age", "city", "email"]);
+        assert_eq!(change.properties[1].after, None);
+        assert_eq!(change.properties[2].before, None);
+    }
+
+    #[test]
+    fn test_edge_changes_resolve_entity_names() {
+        let nodes = vec![node("1", "Alice", json!({})), node("2", "Bob", json!({}))];
+        let before = GraphSnapshot::new(nodes.clone(), vec![edge("1", "2", 0.5)]);
+        let after = GraphSnapshot::new(nodes, vec![edge("1", "2", 0.9), edge("2", "1", 1.0)]);
+
+        let diff = diff_snapshots(&before, &after);
+
+        assert_eq!(diff.added_edges.len(), 1);
+        assert_eq!(diff.added_edges[0].to_string(), "Bob -[knows]-> Alice");
+        assert_eq!(diff.changed_edges.len(), 1);
+        assert_eq!(diff.changed_edges[0].weight, Some((0.5, 0.9)));
+    }
+
+    #[test]
+    fn test_removed_edge_keeps_removed_node_name() {
+        let before = GraphSnapshot::new(
+            vec![node("1", "Alice", json!({})), node("2", "Bob", json!({}))],
+            vec![edge("1", "2", 1.0)],
+        );
+        let after = GraphSnapshot::new(vec![node("1", "Alice", json!({}))], vec![]);
+
+        let diff = diff_snapshots(&before, &after);
+        assert_eq!(diff.removed_edges[0].target_name, "Bob");
+    }
+
+    #[test]
+    fn test_display_name_falls_back_to_id() {
+        let node = Node {
+            id: "doc-42".to_string(),
+            label: "Document".to_string(),
+            properties: json!({"length": 10}),
+            partition_id: "work".to_string(),
+        };
+        assert_eq!(display_name(&node), "doc-42");
+    }
+}
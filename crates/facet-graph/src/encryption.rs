@@ -0,0 +1,119 @@
+//! Encryption at rest for node payloads
+//!
+//! With a key set, `SurrealStore` seals node properties and embeddings with
+//! the AES-256-GCM file encryption from the profile crypto module before
+//! writing them. A copy of the data directory then only exposes ids, labels,
+//! partitions and edges.
+
+use crate::GraphError;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use facet_types::profiles::crypto::{decrypt_file, encrypt_file, EncryptionKey};
+
+/// Marks a sealed payload; the base64 ciphertext follows
+const SEALED_PREFIX: &str = "sealed:v1:";
+
+#[derive(Clone)]
+pub(crate) struct PayloadCipher {
+    key: EncryptionKey,
+}
+
+impl PayloadCipher {
+    pub(crate) fn new(key: EncryptionKey) -> Self {
+        Self { key }
+    }
+
+    fn seal(&self, plaintext: &[u8]) -> Result<String, GraphError> {
+        let sealed =
+            encrypt_file(plaintext, &self.key).map_err(|e| GraphError::Storage(e.to_string()))?;
+        Ok(format!("{SEALED_PREFIX}{}", STANDARD.encode(sealed)))
+    }
+
+    /// Plaintext of a sealed payload, or `None` if `value` was not sealed
+    fn open(&self, value: &str) -> Result<Option<Vec<u8>>, GraphError> {
+        let Some(encoded) = value.strip_prefix(SEALED_PREFIX) else {
+            return Ok(None);
+        };
+        let sealed = STANDARD
+            .decode(encoded)
+            .map_err(|e| GraphError::Storage(format!("Corrupt sealed payload: {}", e)))?;
+        decrypt_file(&sealed, &self.key)
+            .map(Some)
+            .map_err(|e| GraphError::Storage(e.to_string()))
+    }
+
+    pub(crate) fn seal_properties(
+        &self,
+        properties: &serde_json::Value,
+    ) -> Result<serde_json::Value, GraphError> {
+        let plaintext =
+            serde_json::to_vec(properties).map_err(|e| GraphError::Storage(e.to_string()))?;
+        Ok(serde_json::Value::String(self.seal(&plaintext)?))
+    }
+
+    /// Properties written before encryption was turned on are returned as
+    /// they are
+    pub(crate) fn open_properties(
+        &self,
+        value: serde_json::Value,
+    ) -> Result<serde_json::Value, GraphError> {
+        let serde_json::Value::String(text) = &value else {
+            return Ok(value);
+        };
+        match self.open(text)? {
+            Some(plaintext) => serde_json::from_slice(&plaintext)
+                .map_err(|e| GraphError::Storage(format!("Corrupt sealed payload: {}", e))),
+            None => Ok(value),
+        }
+    }
+
+    pub(crate) fn seal_vector(&self, vector: &[f32]) -> Result<String, GraphError> {
+        let bytes: Vec<u8> = vector.iter().flat_map(|v| v.to_le_bytes()).collect();
+        self.seal(&bytes)
+    }
+
+    pub(crate) fn open_vector(&self, sealed: &str) -> Result<Vec<f32>, GraphError> {
+        let bytes = self
+            .open(sealed)?
+            .ok_or_else(|| GraphError::Storage("Embedding is not sealed".to_string()))?;
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cipher(byte: u8) -> PayloadCipher {
+        PayloadCipher::new(EncryptionKey::from_bytes(vec![byte; 32]))
+    }
+
+    #[test]
+    fn test_properties_round_trip() {
+        let properties = json!({"name": "Alice", "tags": ["a", "b"]});
+
+        let sealed = cipher(7).seal_properties(&properties).unwrap();
+        assert!(!sealed.to_string().contains("Alice"));
+        assert_eq!(
+            cipher(7).open_properties(sealed.clone()).unwrap(),
+            properties
+        );
+
+        // Plain values pass through; other keys are rejected
+        let plain = json!({"name": "Bob"});
+        assert_eq!(cipher(7).open_properties(plain.clone()).unwrap(), plain);
+        assert!(cipher(8).open_properties(sealed).is_err());
+    }
+
+    #[test]
+    fn test_vector_round_trip() {
+        let cipher = cipher(7);
+        let sealed = cipher.seal_vector(&[0.25, -1.0, 3.5]).unwrap();
+        assert_eq!(cipher.open_vector(&sealed).unwrap(), vec![0.25, -1.0, 3.5]);
+        assert!(cipher.open_vector("[0.25]").is_err());
+    }
+}
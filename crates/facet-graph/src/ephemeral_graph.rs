@@ -1,6 +1,8 @@
 use crate::{Edge, Node};
 use petgraph::algo::kosaraju_scc;
 use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
 use std::collections::HashMap;
 
 pub struct EphemeralGraph {
@@ -65,7 +67,129 @@ impl EphemeralGraph {
             .collect()
     }
 
-    // Add more algorithms as needed (PageRank, Community Detection, etc.)
+    /// Weighted PageRank by power iteration. Rank flows along edges in
+    /// proportion to their weight; dangling nodes spread theirs evenly.
+    /// Scores sum to 1.
+    pub fn pagerank(&self, damping: f64, iterations: usize) -> HashMap<String, f64> {
+        let n = self.graph.node_count();
+        if n == 0 {
+            return HashMap::new();
+        }
+
+        let out_weight: Vec<f64> = self
+            .graph
+            .node_indices()
+            .map(|idx| {
+                self.graph
+                    .edges(idx)
+                    .map(|e| f64::from(e.weight().max(0.0)))
+                    .sum()
+            })
+            .collect();
+
+        let mut ranks = vec![1.0 / n as f64; n];
+        for _ in 0..iterations {
+            let dangling: f64 = (0..n)
+                .filter(|&i| out_weight[i] == 0.0)
+                .map(|i| ranks[i])
+                .sum();
+            let base = (1.0 - damping + damping * dangling) / n as f64;
+
+            let mut next = vec![base; n];
+            for edge in self.graph.edge_references() {
+                let source = edge.source().index();
+                if out_weight[source] > 0.0 {
+                    let share = f64::from(edge.weight().max(0.0)) / out_weight[source];
+                    next[edge.target().index()] += damping * ranks[source] * share;
+                }
+            }
+            ranks = next;
+        }
+
+        self.graph
+            .node_indices()
+            .map(|idx| (self.graph[idx].id.clone(), ranks[idx.index()]))
+            .collect()
+    }
+
+    /// Number of edges touching each node (in and out), divided by the
+    /// number of other nodes
+    pub fn degree_centrality(&self) -> HashMap<String, f64> {
+        let n = self.graph.node_count();
+        let scale = if n > 1 { 1.0 / (n - 1) as f64 } else { 0.0 };
+
+        self.graph
+            .node_indices()
+            .map(|idx| {
+                let degree = self.graph.edges_directed(idx, Direction::Outgoing).count()
+                    + self.graph.edges_directed(idx, Direction::Incoming).count();
+                (self.graph[idx].id.clone(), degree as f64 * scale)
+            })
+            .collect()
+    }
+
+    /// Label propagation over the graph taken as undirected. Every node
+    /// starts in its own community and repeatedly joins the one with the
+    /// most edge weight among its neighbours, keeping its own on ties.
+    /// Nodes are visited in insertion order, so results are deterministic.
+    ///
+    /// Returns the communities, largest first, each as a list of node ids.
+    pub fn label_propagation(&self, max_iterations: usize) -> Vec<Vec<String>> {
+        let n = self.graph.node_count();
+        let mut labels: Vec<usize> = (0..n).collect();
+
+        for _ in 0..max_iterations {
+            let mut changed = false;
+
+            for idx in self.graph.node_indices() {
+                let mut weights: HashMap<usize, f64> = HashMap::new();
+                for edge in self.graph.edges_directed(idx, Direction::Outgoing) {
+                    *weights.entry(labels[edge.target().index()]).or_default() +=
+                        f64::from(*edge.weight());
+                }
+                for edge in self.graph.edges_directed(idx, Direction::Incoming) {
+                    *weights.entry(labels[edge.source().index()]).or_default() +=
+                        f64::from(*edge.weight());
+                }
+
+                let current = labels[idx.index()];
+                let Some(best) = weights.values().copied().reduce(f64::max) else {
+                    continue;
+                };
+                if weights.get(&current) == Some(&best) {
+                    continue;
+                }
+                let chosen = weights
+                    .iter()
+                    .filter(|(_, w)| **w == best)
+                    .map(|(label, _)| *label)
+                    .min()
+                    .unwrap_or(current);
+                labels[idx.index()] = chosen;
+                changed = true;
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        let mut groups: HashMap<usize, Vec<String>> = HashMap::new();
+        for idx in self.graph.node_indices() {
+            groups
+                .entry(labels[idx.index()])
+                .or_default()
+                .push(self.graph[idx].id.clone());
+        }
+        let mut communities: Vec<Vec<String>> = groups.into_values().collect();
+        for members in &mut communities {
+            members.sort();
+        }
+        communities.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        communities
+    }
+
+    // Add more algorithms as needed
 }
 
 #[cfg(test)]
@@ -89,6 +213,9 @@ mod tests {
             relation: "LINKS".to_string(),
             weight: 1.0,
             partition_id: "default".to_string(),
+            valid_from: None,
+            valid_to: None,
+            cross_partition: false,
         }
     }
 
@@ -143,6 +270,62 @@ mod tests {
         assert_eq!(scc.len(), 4);
     }
 
+    #[test]
+    fn test_pagerank_favours_linked_nodes() {
+        // 1 -> 3, 2 -> 3, 3 -> 1, 4 dangling
+        let nodes = vec![
+            create_node("1"),
+            create_node("2"),
+            create_node("3"),
+            create_node("4"),
+        ];
+        let edges = vec![
+            create_edge("1", "3"),
+            create_edge("2", "3"),
+            create_edge("3", "1"),
+        ];
+
+        let graph = EphemeralGraph::from_nodes_and_edges(nodes, edges);
+        let ranks = graph.pagerank(0.85, 50);
+
+        assert!((ranks.values().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(ranks["3"] > ranks["1"]);
+        assert!(ranks["1"] > ranks["2"]);
+        assert!((ranks["2"] - ranks["4"]).abs() < 1e-9);
+
+        let degree = graph.degree_centrality();
+        assert!((degree["3"] - 1.0).abs() < 1e-9);
+        assert_eq!(degree["4"], 0.0);
+    }
+
+    #[test]
+    fn test_label_propagation_splits_clusters() {
+        // Two triangles joined by a single weak edge, plus a loner
+        let nodes = ["1", "2", "3", "4", "5", "6", "7"]
+            .into_iter()
+            .map(create_node)
+            .collect();
+        let mut bridge = create_edge("3", "4");
+        bridge.weight = 0.1;
+        let edges = vec![
+            create_edge("1", "2"),
+            create_edge("2", "3"),
+            create_edge("3", "1"),
+            create_edge("4", "5"),
+            create_edge("5", "6"),
+            create_edge("6", "4"),
+            bridge,
+        ];
+
+        let graph = EphemeralGraph::from_nodes_and_edges(nodes, edges);
+        let communities = graph.label_propagation(20);
+
+        assert_eq!(communities.len(), 3);
+        assert_eq!(communities[0], vec!["1", "2", "3"]);
+        assert_eq!(communities[1], vec!["4", "5", "6"]);
+        assert_eq!(communities[2], vec!["7"]);
+    }
+
     #[test]
     fn test_duplicate_nodes() {
         let mut graph = EphemeralGraph::new();
@@ -0,0 +1,243 @@
+//! Export graph snapshots to GraphML and Graphviz DOT
+//!
+//! Both formats can be opened in external tools: GraphML in Gephi, yEd or
+//! Cytoscape, DOT in Graphviz. Use `GraphStore::snapshot` to take the full
+//! graph or a single partition, then render it here.
+
+use crate::diff::display_name;
+use crate::snapshot::GraphSnapshot;
+use crate::GraphError;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    GraphMl,
+    Dot,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::GraphMl => "graphml",
+            ExportFormat::Dot => "dot",
+        }
+    }
+
+    pub fn render(&self, snapshot: &GraphSnapshot) -> String {
+        match self {
+            ExportFormat::GraphMl => to_graphml(snapshot),
+            ExportFormat::Dot => to_dot(snapshot),
+        }
+    }
+
+    pub fn write(&self, snapshot: &GraphSnapshot, path: &Path) -> Result<(), GraphError> {
+        std::fs::write(path, self.render(snapshot)).map_err(|e| {
+            GraphError::Storage(format!("Failed to write export {}: {}", path.display(), e))
+        })
+    }
+}
+
+/// Renders the snapshot as GraphML. Node properties are kept as a JSON string
+/// attribute so nothing is lost for nested values.
+pub fn to_graphml(snapshot: &GraphSnapshot) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    out.push_str("  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n");
+    out.push_str("  <key id=\"type\" for=\"node\" attr.name=\"type\" attr.type=\"string\"/>\n");
+    out.push_str(
+        "  <key id=\"properties\" for=\"node\" attr.name=\"properties\" attr.type=\"string\"/>\n",
+    );
+    out.push_str(
+        "  <key id=\"partition\" for=\"all\" attr.name=\"partition_id\" attr.type=\"string\"/>\n",
+    );
+    out.push_str(
+        "  <key id=\"relation\" for=\"edge\" attr.name=\"relation\" attr.type=\"string\"/>\n",
+    );
+    out.push_str("  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"double\"/>\n");
+    out.push_str("  <graph id=\"G\" edgedefault=\"directed\">\n");
+
+    for node in &snapshot.nodes {
+        let _ = writeln!(out, "    <node id=\"{}\">", escape_xml(&node.id));
+        let _ = writeln!(
+            out,
+            "      <data key=\"label\">{}</data>",
+            escape_xml(&display_name(node))
+        );
+        let _ = writeln!(
+            out,
+            "      <data key=\"type\">{}</data>",
+            escape_xml(&node.label)
+        );
+        let _ = writeln!(
+            out,
+            "      <data key=\"properties\">{}</data>",
+            escape_xml(&node.properties.to_string())
+        );
+        let _ = writeln!(
+            out,
+            "      <data key=\"partition\">{}</data>",
+            escape_xml(&node.partition_id)
+        );
+        out.push_str("    </node>\n");
+    }
+
+    for (i, edge) in snapshot.edges.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "    <edge id=\"e{}\" source=\"{}\" target=\"{}\">",
+            i,
+            escape_xml(&edge.source),
+            escape_xml(&edge.target)
+        );
+        let _ = writeln!(
+            out,
+            "      <data key=\"relation\">{}</data>",
+            escape_xml(&edge.relation)
+        );
+        let _ = writeln!(out, "      <data key=\"weight\">{}</data>", edge.weight);
+        let _ = writeln!(
+            out,
+            "      <data key=\"partition\">{}</data>",
+            escape_xml(&edge.partition_id)
+        );
+        out.push_str("    </edge>\n");
+    }
+
+    out.push_str("  </graph>\n");
+    out.push_str("</graphml>\n");
+    out
+}
+
+/// Renders the snapshot as a Graphviz digraph, labelling nodes with their
+/// display name and edges with their relation.
+pub fn to_dot(snapshot: &GraphSnapshot) -> String {
+    let mut out = String::from("digraph G {\n");
+
+    for node in &snapshot.nodes {
+        let _ = writeln!(
+            out,
+            "  \"{}\" [label=\"{}\", type=\"{}\", partition=\"{}\"];",
+            escape_dot(&node.id),
+            escape_dot(&display_name(node)),
+            escape_dot(&node.label),
+            escape_dot(&node.partition_id)
+        );
+    }
+
+    for edge in &snapshot.edges {
+        let _ = writeln!(
+            out,
+            "  \"{}\" -> \"{}\" [label=\"{}\", weight={}];",
+            escape_dot(&edge.source),
+            escape_dot(&edge.target),
+            escape_dot(&edge.relation),
+            edge.weight
+        );
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn escape_dot(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Edge, Node};
+    use serde_json::json;
+
+    fn snapshot() -> GraphSnapshot {
+        GraphSnapshot::new(
+            vec![
+                Node {
+                    id: "1".to_string(),
+                    label: "Person".to_string(),
+                    properties: json!({"name": "Alice \"Al\" <A&B>"}),
+                    partition_id: "personal".to_string(),
+                },
+                Node {
+                    id: "2".to_string(),
+                    label: "Company".to_string(),
+                    properties: json!({}),
+                    partition_id: "work".to_string(),
+                },
+            ],
+            vec![Edge {
+                source: "1".to_string(),
+                target: "2".to_string(),
+                relation: "works_at".to_string(),
+                weight: 0.5,
+                partition_id: "work".to_string(),
+                valid_from: None,
+                valid_to: None,
+                cross_partition: false,
+            }],
+        )
+    }
+
+    #[test]
+    fn test_graphml_escapes_and_lists_elements() {
+        let xml = to_graphml(&snapshot());
+
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains("<node id=\"1\">"));
+        assert!(xml.contains("Alice &quot;Al&quot; &lt;A&amp;B&gt;"));
+        assert!(xml.contains("<edge id=\"e0\" source=\"1\" target=\"2\">"));
+        assert!(xml.contains("<data key=\"weight\">0.5</data>"));
+        assert_eq!(xml.matches("<node ").count(), 2);
+        assert!(xml.trim_end().ends_with("</graphml>"));
+    }
+
+    #[test]
+    fn test_dot_labels_and_escaping() {
+        let dot = to_dot(&snapshot());
+
+        assert!(dot.starts_with("digraph G {"));
+        assert!(dot.contains("\"1\" [label=\"Alice \\\"Al\\\" <A&B>\""));
+        // Nodes without a name fall back to their id
+        assert!(dot.contains("\"2\" [label=\"2\""));
+        assert!(dot.contains("\"1\" -> \"2\" [label=\"works_at\", weight=0.5];"));
+    }
+
+    #[test]
+    fn test_write_uses_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let format = ExportFormat::Dot;
+        let path = dir.path().join(format!("graph.{}", format.extension()));
+
+        format.write(&snapshot(), &path).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written, to_dot(&snapshot()));
+    }
+}
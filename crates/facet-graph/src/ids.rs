@@ -0,0 +1,150 @@
+//! Node id generation
+//!
+//! `new_node_id` gives every call a fresh UUIDv7, which sorts by creation
+//! time. `deterministic_node_id` derives the id from what identifies an
+//! entity, its label and key properties, so ingesting the same entity twice
+//! lands on the same node instead of creating a duplicate.
+
+use crate::GraphError;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use uuid::{Builder, Uuid};
+
+/// A new UUIDv7
+pub fn new_node_id() -> String {
+    Uuid::now_v7().to_string()
+}
+
+/// A UUID (version 8) built from the SHA-256 of `label` and the values of
+/// the `keys` properties. The order of `keys` does not matter and other
+/// properties are ignored, so the same entity always gets the same id.
+/// Values are compared exactly: "Alice" and "alice" give different ids.
+/// Fails with `SchemaViolation` if a key property is missing or null.
+pub fn deterministic_node_id(
+    label: &str,
+    properties: &Value,
+    keys: &[&str],
+) -> Result<String, GraphError> {
+    if keys.is_empty() {
+        return Err(GraphError::SchemaViolation(
+            "At least one key property is required".to_string(),
+        ));
+    }
+    let mut keys = keys.to_vec();
+    keys.sort_unstable();
+    keys.dedup();
+
+    let mut hasher = Sha256::new();
+    hasher.update(label.as_bytes());
+    for key in keys {
+        let value = match properties.get(key) {
+            Some(value) if !value.is_null() => value,
+            _ => {
+                return Err(GraphError::SchemaViolation(format!(
+                    "Key property {} is missing on {}",
+                    key, label
+                )))
+            }
+        };
+        // Keys and values are JSON-encoded, so no two inputs run together
+        hasher.update([0]);
+        hasher.update(Value::from(key).to_string().as_bytes());
+        hasher.update([0]);
+        hasher.update(canonical_json(value).as_bytes());
+    }
+
+    let digest = hasher.finalize();
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    Ok(Builder::from_custom_bytes(bytes).into_uuid().to_string())
+}
+
+/// `value` as JSON with object keys sorted, whatever order the map keeps
+fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            let fields: Vec<String> = entries
+                .into_iter()
+                .map(|(key, value)| {
+                    format!("{}:{}", Value::from(key.as_str()), canonical_json(value))
+                })
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_new_node_ids_are_unique_v7() {
+        let a = new_node_id();
+        let b = new_node_id();
+        assert_ne!(a, b);
+        assert_eq!(Uuid::parse_str(&a).unwrap().get_version_num(), 7);
+    }
+
+    #[test]
+    fn test_deterministic_node_id() {
+        let id = |label: &str, properties: Value, keys: &[&str]| {
+            deterministic_node_id(label, &properties, keys).unwrap()
+        };
+        let alice = id(
+            "Person",
+            json!({"name": "Alice", "email": "a@x.org", "age": 30}),
+            &["name", "email"],
+        );
+        assert_eq!(Uuid::parse_str(&alice).unwrap().get_version_num(), 8);
+
+        // Other properties and the order of keys do not matter
+        assert_eq!(
+            alice,
+            id(
+                "Person",
+                json!({"email": "a@x.org", "name": "Alice"}),
+                &["email", "name"]
+            )
+        );
+        assert_ne!(
+            alice,
+            id(
+                "Company",
+                json!({"name": "Alice", "email": "a@x.org"}),
+                &["name", "email"]
+            )
+        );
+        assert_ne!(
+            alice,
+            id(
+                "Person",
+                json!({"name": "alice", "email": "a@x.org"}),
+                &["name", "email"]
+            )
+        );
+        assert_eq!(
+            id("Place", json!({"at": {"lat": 1, "lon": 2}}), &["at"]),
+            id("Place", json!({"at": {"lon": 2, "lat": 1}}), &["at"])
+        );
+
+        for (properties, keys) in [
+            (json!({"name": "Alice"}), &["email"][..]),
+            (json!({"name": null}), &["name"][..]),
+            (json!({"name": "Alice"}), &[][..]),
+        ] {
+            assert!(matches!(
+                deterministic_node_id("Person", &properties, keys),
+                Err(GraphError::SchemaViolation(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,556 @@
+//! Bulk import of nodes and edges from JSON Lines or CSV
+//!
+//! Records are read one at a time and written through `GraphStore::add_nodes`
+//! / `add_edges` in batches, so large files never have to fit in memory. A dry
+//! run parses and validates every record without touching the store and
+//! reports all problems at once; a real import stops at the first bad record
+//! (earlier batches stay committed).
+//!
+//! JSONL records use the `Node` / `Edge` serde shape. CSV files need a header
+//! row; `ColumnMapping` says which columns hold which field, and any other
+//! column of a node file becomes a string property.
+
+use crate::{Edge, GraphError, GraphStore, Node};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    JsonLines,
+    Csv,
+}
+
+impl ImportFormat {
+    /// Picks the format from a `.jsonl` / `.ndjson` / `.csv` extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "jsonl" | "ndjson" => Some(ImportFormat::JsonLines),
+            "csv" => Some(ImportFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// CSV header names for each record field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    pub id: String,
+    pub label: String,
+    pub partition_id: String,
+    pub source: String,
+    pub target: String,
+    pub relation: String,
+    pub weight: String,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        Self {
+            id: "id".to_string(),
+            label: "label".to_string(),
+            partition_id: "partition_id".to_string(),
+            source: "source".to_string(),
+            target: "target".to_string(),
+            relation: "relation".to_string(),
+            weight: "weight".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportOptions {
+    pub format: ImportFormat,
+    /// Records per `add_nodes` / `add_edges` call
+    pub batch_size: usize,
+    /// Validate only; nothing is written
+    pub dry_run: bool,
+    /// Partition used when a record doesn't name one
+    pub default_partition: String,
+    pub columns: ColumnMapping,
+}
+
+impl ImportOptions {
+    pub fn new(format: ImportFormat) -> Self {
+        Self {
+            format,
+            batch_size: 500,
+            dry_run: false,
+            default_partition: "personal".to_string(),
+            columns: ColumnMapping::default(),
+        }
+    }
+}
+
+/// A record that failed to parse or validate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportIssue {
+    /// 1-based line number in the input
+    pub line: u64,
+    pub message: String,
+}
+
+/// Running totals passed to the progress callback after every batch
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportProgress {
+    /// Records read so far, valid or not
+    pub records: usize,
+    /// Records written (or, in a dry run, that would be written)
+    pub imported: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub records: usize,
+    pub imported: usize,
+    /// Always empty after a real import, which fails on the first issue
+    pub issues: Vec<ImportIssue>,
+}
+
+impl ImportReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+type ProgressFn<'a> = Box<dyn FnMut(ImportProgress) + Send + 'a>;
+
+pub struct Importer<'a, S: GraphStore + ?Sized> {
+    store: &'a S,
+    options: ImportOptions,
+    progress: Option<ProgressFn<'a>>,
+}
+
+impl<'a, S: GraphStore + ?Sized> Importer<'a, S> {
+    pub fn new(store: &'a S, options: ImportOptions) -> Self {
+        Self {
+            store,
+            options,
+            progress: None,
+        }
+    }
+
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(ImportProgress) + Send + 'a,
+    {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    pub async fn import_nodes_file(&mut self, path: &Path) -> Result<ImportReport, GraphError> {
+        let file = open(path)?;
+        self.import_nodes(file).await
+    }
+
+    pub async fn import_edges_file(&mut self, path: &Path) -> Result<ImportReport, GraphError> {
+        let file = open(path)?;
+        self.import_edges(file).await
+    }
+
+    pub async fn import_nodes<R: Read>(&mut self, reader: R) -> Result<ImportReport, GraphError> {
+        let mut seen = HashSet::new();
+        let mut report = ImportReport::default();
+        let mut batch = Vec::new();
+
+        for (line, record) in self.records(reader)? {
+            report.records += 1;
+            let node = record
+                .and_then(|record| self.parse_node(record))
+                .and_then(|node| {
+                    if seen.insert(node.id.clone()) {
+                        Ok(node)
+                    } else {
+                        Err(format!("Duplicate node id: {}", node.id))
+                    }
+                });
+
+            match node {
+                Ok(node) => batch.push(node),
+                Err(message) => self.reject(&mut report, line, message)?,
+            }
+
+            if batch.len() >= self.options.batch_size {
+                self.flush_nodes(&mut report, &mut batch).await?;
+            }
+        }
+        self.flush_nodes(&mut report, &mut batch).await?;
+
+        Ok(report)
+    }
+
+    pub async fn import_edges<R: Read>(&mut self, reader: R) -> Result<ImportReport, GraphError> {
+        let mut report = ImportReport::default();
+        let mut batch = Vec::new();
+
+        for (line, record) in self.records(reader)? {
+            report.records += 1;
+            match record.and_then(|record| self.parse_edge(record)) {
+                Ok(edge) => batch.push(edge),
+                Err(message) => self.reject(&mut report, line, message)?,
+            }
+
+            if batch.len() >= self.options.batch_size {
+                self.flush_edges(&mut report, &mut batch).await?;
+            }
+        }
+        self.flush_edges(&mut report, &mut batch).await?;
+
+        Ok(report)
+    }
+
+    /// Yields each record as a JSON object along with its line number
+    fn records<R: Read>(&self, reader: R) -> Result<RecordIter<R>, GraphError> {
+        match self.options.format {
+            ImportFormat::JsonLines => Ok(RecordIter::Json(BufReader::new(reader).lines(), 0)),
+            ImportFormat::Csv => {
+                let mut csv = csv::Reader::from_reader(reader);
+                let headers = csv
+                    .headers()
+                    .map_err(|e| GraphError::Storage(format!("Invalid CSV header: {}", e)))?
+                    .clone();
+                Ok(RecordIter::Csv(csv.into_records(), headers))
+            }
+        }
+    }
+
+    fn parse_node(
+        &self,
+        mut record: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Node, String> {
+        let node = match self.options.format {
+            ImportFormat::JsonLines => {
+                record
+                    .entry("partition_id")
+                    .or_insert_with(|| self.options.default_partition.clone().into());
+                serde_json::from_value::<Node>(record.into()).map_err(|e| e.to_string())?
+            }
+            ImportFormat::Csv => {
+                let columns = &self.options.columns;
+                let id = take_string(&mut record, &columns.id)
+                    .ok_or_else(|| format!("Missing column: {}", columns.id))?;
+                let label = take_string(&mut record, &columns.label)
+                    .ok_or_else(|| format!("Missing column: {}", columns.label))?;
+                let partition_id = take_string(&mut record, &columns.partition_id)
+                    .unwrap_or_else(|| self.options.default_partition.clone());
+                record.retain(|_, v| v.as_str().is_some_and(|s| !s.is_empty()));
+
+                Node {
+                    id,
+                    label,
+                    properties: record.into(),
+                    partition_id,
+                }
+            }
+        };
+
+        if node.id.trim().is_empty() {
+            return Err("Node id is empty".to_string());
+        }
+        Ok(node)
+    }
+
+    fn parse_edge(
+        &self,
+        mut record: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<Edge, String> {
+        let edge = match self.options.format {
+            ImportFormat::JsonLines => {
+                record
+                    .entry("partition_id")
+                    .or_insert_with(|| self.options.default_partition.clone().into());
+                record.entry("weight").or_insert_with(|| 1.0.into());
+                serde_json::from_value::<Edge>(record.into()).map_err(|e| e.to_string())?
+            }
+            ImportFormat::Csv => {
+                let columns = &self.options.columns;
+                let mut required = |column: &str| {
+                    take_string(&mut record, column)
+                        .ok_or_else(|| format!("Missing column: {}", column))
+                };
+                let source = required(&columns.source)?;
+                let target = required(&columns.target)?;
+                let relation = required(&columns.relation)?;
+                let weight = match take_string(&mut record, &columns.weight) {
+                    Some(w) if !w.is_empty() => w
+                        .parse::<f32>()
+                        .map_err(|_| format!("Invalid weight: {}", w))?,
+                    _ => 1.0,
+                };
+                let partition_id = take_string(&mut record, &columns.partition_id)
+                    .unwrap_or_else(|| self.options.default_partition.clone());
+
+                Edge {
+                    source,
+                    target,
+                    relation,
+                    weight,
+                    partition_id,
+                    valid_from: None,
+                    valid_to: None,
+                    cross_partition: false,
+                }
+            }
+        };
+
+        if edge.source.is_empty() || edge.target.is_empty() {
+            return Err("Edge source and target are required".to_string());
+        }
+        if edge.relation.is_empty() {
+            return Err("Edge relation is required".to_string());
+        }
+        if !edge.weight.is_finite() {
+            return Err(format!("Invalid weight: {}", edge.weight));
+        }
+        Ok(edge)
+    }
+
+    /// Records the issue in a dry run, fails the import otherwise
+    fn reject(
+        &self,
+        report: &mut ImportReport,
+        line: u64,
+        message: String,
+    ) -> Result<(), GraphError> {
+        if self.options.dry_run {
+            report.issues.push(ImportIssue { line, message });
+            Ok(())
+        } else {
+            Err(GraphError::Storage(format!("Line {}: {}", line, message)))
+        }
+    }
+
+    async fn flush_nodes(
+        &mut self,
+        report: &mut ImportReport,
+        batch: &mut Vec<Node>,
+    ) -> Result<(), GraphError> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let count = batch.len();
+        if self.options.dry_run {
+            batch.clear();
+        } else {
+            self.store.add_nodes(std::mem::take(batch)).await?;
+        }
+        self.advance(report, count);
+        Ok(())
+    }
+
+    async fn flush_edges(
+        &mut self,
+        report: &mut ImportReport,
+        batch: &mut Vec<Edge>,
+    ) -> Result<(), GraphError> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let count = batch.len();
+        if self.options.dry_run {
+            batch.clear();
+        } else {
+            self.store.add_edges(std::mem::take(batch)).await?;
+        }
+        self.advance(report, count);
+        Ok(())
+    }
+
+    fn advance(&mut self, report: &mut ImportReport, count: usize) {
+        report.imported += count;
+        if let Some(progress) = self.progress.as_mut() {
+            progress(ImportProgress {
+                records: report.records,
+                imported: report.imported,
+            });
+        }
+    }
+}
+
+type Record = Result<serde_json::Map<String, serde_json::Value>, String>;
+
+enum RecordIter<R: Read> {
+    Json(std::io::Lines<BufReader<R>>, u64),
+    Csv(csv::StringRecordsIntoIter<R>, csv::StringRecord),
+}
+
+impl<R: Read> Iterator for RecordIter<R> {
+    type Item = (u64, Record);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            RecordIter::Json(lines, line_no) => loop {
+                *line_no += 1;
+                let line = match lines.next()? {
+                    Ok(line) => line,
+                    Err(e) => return Some((*line_no, Err(e.to_string()))),
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record = match serde_json::from_str::<serde_json::Value>(&line) {
+                    Ok(serde_json::Value::Object(map)) => Ok(map),
+                    Ok(_) => Err("Expected a JSON object".to_string()),
+                    Err(e) => Err(format!("Invalid JSON: {}", e)),
+                };
+                return Some((*line_no, record));
+            },
+            RecordIter::Csv(records, headers) => {
+                let record = records.next()?;
+                let line = record
+                    .as_ref()
+                    .ok()
+                    .and_then(|r| r.position())
+                    .map(|p| p.line())
+                    .unwrap_or(0);
+                let record = record
+                    .map(|record| {
+                        headers
+                            .iter()
+                            .zip(record.iter())
+                            .map(|(h, v)| (h.to_string(), serde_json::Value::from(v)))
+                            .collect()
+                    })
+                    .map_err(|e| format!("Invalid CSV: {}", e));
+                Some((line, record))
+            }
+        }
+    }
+}
+
+fn take_string(
+    record: &mut serde_json::Map<String, serde_json::Value>,
+    key: &str,
+) -> Option<String> {
+    match record.remove(key)? {
+        serde_json::Value::String(s) => Some(s),
+        other => Some(other.to_string()),
+    }
+}
+
+fn open(path: &Path) -> Result<std::fs::File, GraphError> {
+    std::fs::File::open(path)
+        .map_err(|e| GraphError::Storage(format!("Failed to open {}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mocks::MockGraphStore;
+    use std::sync::{Arc, Mutex};
+
+    const NODES_JSONL: &str = r#"{"id": "a", "label": "Person", "properties": {"name": "Alice"}}
+{"id": "b", "label": "Person", "properties": {}, "partition_id": "work"}
+
+{"id": "c", "label": "Topic", "properties": {}}
+"#;
+
+    #[tokio::test]
+    async fn test_import_jsonl_in_batches_with_progress() {
+        let store = MockGraphStore::new();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let seen = calls.clone();
+
+        let mut options = ImportOptions::new(ImportFormat::JsonLines);
+        options.batch_size = 2;
+        let report = Importer::new(&store, options)
+            .on_progress(move |p| seen.lock().unwrap().push(p.imported))
+            .import_nodes(NODES_JSONL.as_bytes())
+            .await
+            .unwrap();
+
+        assert_eq!(report.imported, 3);
+        assert!(report.is_valid());
+        assert_eq!(*calls.lock().unwrap(), vec![2, 3]);
+        assert_eq!(store.get_node("a").await.unwrap().partition_id, "personal");
+        assert_eq!(store.get_node("b").await.unwrap().partition_id, "work");
+    }
+
+    #[tokio::test]
+    async fn test_import_csv_with_column_mapping() {
+        let store = MockGraphStore::new();
+        let nodes = "key,kind,name,city\nalice,Person,Alice,Berlin\nacme,Company,Acme,\n";
+        let edges = "from,to,rel,strength\nalice,acme,works_at,0.8\n";
+
+        let mut options = ImportOptions::new(ImportFormat::Csv);
+        options.default_partition = "work".to_string();
+        options.columns.id = "key".to_string();
+        options.columns.label = "kind".to_string();
+        options.columns.source = "from".to_string();
+        options.columns.target = "to".to_string();
+        options.columns.relation = "rel".to_string();
+        options.columns.weight = "strength".to_string();
+
+        let mut importer = Importer::new(&store, options);
+        importer.import_nodes(nodes.as_bytes()).await.unwrap();
+        let report = importer.import_edges(edges.as_bytes()).await.unwrap();
+        assert_eq!(report.imported, 1);
+
+        let alice = store.get_node("alice").await.unwrap();
+        assert_eq!(alice.label, "Person");
+        assert_eq!(alice.partition_id, "work");
+        assert_eq!(
+            alice.properties,
+            serde_json::json!({"name": "Alice", "city": "Berlin"})
+        );
+        // Empty cells are not stored as properties
+        assert_eq!(
+            store.get_node("acme").await.unwrap().properties,
+            serde_json::json!({"name": "Acme"})
+        );
+
+        let neighbors = store.get_neighbors("alice").await.unwrap();
+        assert_eq!(neighbors[0].0.relation, "works_at");
+        assert_eq!(neighbors[0].0.weight, 0.8);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_reports_every_issue_without_writing() {
+        let store = MockGraphStore::new();
+        let input = r#"{"id": "a", "label": "Person", "properties": {}}
+not json
+{"id": "a", "label": "Person", "properties": {}}
+{"id": "", "label": "Person", "properties": {}}
+"#;
+
+        let mut options = ImportOptions::new(ImportFormat::JsonLines);
+        options.dry_run = true;
+        let report = Importer::new(&store, options)
+            .import_nodes(input.as_bytes())
+            .await
+            .unwrap();
+
+        assert_eq!(report.records, 4);
+        assert_eq!(report.imported, 1);
+        let lines: Vec<u64> = report.issues.iter().map(|i| i.line).collect();
+        assert_eq!(lines, vec![2, 3, 4]);
+        assert!(store.get_node("a").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_real_import_fails_on_first_bad_record() {
+        let store = MockGraphStore::new();
+        let edges = "source,target,relation\na,b,knows\na,,knows\n";
+
+        let err = Importer::new(&store, ImportOptions::new(ImportFormat::Csv))
+            .import_edges(edges.as_bytes())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Line 3"));
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            ImportFormat::from_path(Path::new("nodes.JSONL")),
+            Some(ImportFormat::JsonLines)
+        );
+        assert_eq!(
+            ImportFormat::from_path(Path::new("edges.csv")),
+            Some(ImportFormat::Csv)
+        );
+        assert_eq!(ImportFormat::from_path(Path::new("graph.xml")), None);
+    }
+}
@@ -29,6 +29,7 @@ impl<S: GraphStore + VectorStore> IngestionPipeline<S> {
             label: "Document".to_string(),
             properties: serde_json::json!({
                 "title": title,
+                "content": content,
                 "content_preview": content.chars().take(100).collect::<String>(),
                 "length": content.len()
             }),
@@ -44,7 +45,9 @@ impl<S: GraphStore + VectorStore> IngestionPipeline<S> {
             .map_err(|e| GraphError::Storage(format!("Embedding failed: {}", e)))?;
 
         if let Some(embedding) = embeddings.first() {
-            self.store.add_embedding(&doc_id, embedding.clone()).await?;
+            self.store
+                .add_embedding(&doc_id, embedding.clone(), None)
+                .await?;
         }
 
         Ok(doc_id)
@@ -68,7 +71,8 @@ impl<S: GraphStore + VectorStore> IngestionPipeline<S> {
 mod tests {
     use super::*;
     use crate::mocks::{MockGraphStore, MockVectorStore};
-    use crate::{Edge, Node};
+    use crate::snapshot::GraphSnapshot;
+    use crate::{Direction, Edge, Node, PageRequest};
     use async_trait::async_trait;
 
     // Combined mock for testing
@@ -100,14 +104,59 @@ mod tests {
         async fn get_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
             self.graph.get_neighbors(id).await
         }
+        async fn get_incoming_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+            self.graph.get_incoming_neighbors(id).await
+        }
+        async fn traverse(
+            &self,
+            id: &str,
+            depth: usize,
+            direction: Direction,
+            relation_filter: Option<&[&str]>,
+        ) -> Result<Vec<(Edge, Node)>, GraphError> {
+            self.graph
+                .traverse(id, depth, direction, relation_filter)
+                .await
+        }
         async fn update_node(&self, node: Node) -> Result<(), GraphError> {
             self.graph.update_node(node).await
         }
+        async fn delete_node(&self, id: &str, cascade: bool) -> Result<(), GraphError> {
+            self.graph.delete_node(id, cascade).await
+        }
+        async fn delete_edge(
+            &self,
+            source: &str,
+            relation: &str,
+            target: &str,
+        ) -> Result<(), GraphError> {
+            self.graph.delete_edge(source, relation, target).await
+        }
+
+        async fn search_text(
+            &self,
+            query: &str,
+            limit: usize,
+        ) -> Result<Vec<(Node, f32)>, GraphError> {
+            self.graph.search_text(query, limit).await
+        }
 
         async fn query_by_partition(&self, partition_id: &str) -> Result<Vec<Node>, GraphError> {
             self.graph.query_by_partition(partition_id).await
         }
 
+        async fn snapshot(&self, partition_id: Option<&str>) -> Result<GraphSnapshot, GraphError> {
+            self.graph.snapshot(partition_id).await
+        }
+
+        async fn query_by_partition_page(
+            &self,
+            partition_id: &str,
+            page: PageRequest,
+        ) -> Result<Vec<Node>, GraphError> {
+            self.graph.query_by_partition_page(partition_id, page).await
+        }
+
         async fn get_neighbors_in_partition(
             &self,
             id: &str,
@@ -119,15 +168,30 @@ mod tests {
 
     #[async_trait]
     impl VectorStore for MockStore {
-        async fn add_embedding(&self, id: &str, vector: Vec<f32>) -> Result<(), GraphError> {
-            self.vector.add_embedding(id, vector).await
+        async fn add_embedding(
+            &self,
+            id: &str,
+            vector: Vec<f32>,
+            field: Option<&str>,
+        ) -> Result<(), GraphError> {
+            self.vector.add_embedding(id, vector, field).await
         }
         async fn search(
             &self,
             vector: Vec<f32>,
             limit: usize,
+            field: Option<&str>,
         ) -> Result<Vec<(String, f32)>, GraphError> {
-            self.vector.search(vector, limit).await
+            self.vector.search(vector, limit, field).await
+        }
+        async fn remove_embedding(&self, id: &str) -> Result<(), GraphError> {
+            self.vector.remove_embedding(id).await
+        }
+        async fn clear_embeddings(&self, partition_id: &str) -> Result<(), GraphError> {
+            for node in self.graph.query_by_partition(partition_id).await? {
+                self.vector.remove_embedding(&node.id).await?;
+            }
+            Ok(())
         }
     }
 
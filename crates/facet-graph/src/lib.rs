@@ -1,11 +1,36 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use snapshot::GraphSnapshot;
 use thiserror::Error;
+use transaction::GraphTransaction;
 
+pub mod analytics;
+pub mod backend;
+pub mod backup;
+pub mod cache;
+pub mod diff;
+mod encryption;
 pub mod ephemeral_graph;
+pub mod export;
+pub mod ids;
+pub mod import;
 pub mod ingest;
+pub mod merge;
+pub mod metrics;
+pub mod migrations;
 pub mod query;
+pub mod retriever;
+pub mod runs;
+pub mod schema;
+pub mod snapshot;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
+pub mod stream;
+mod surreal_query;
 pub mod surreal_store;
+pub mod transaction;
+mod traversal;
 
 #[derive(Error, Debug)]
 pub enum GraphError {
@@ -13,6 +38,12 @@ pub enum GraphError {
     Storage(String),
     #[error("Node not found: {0}")]
     NotFound(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Schema violation: {0}")]
+    SchemaViolation(String),
+    #[error("Embedding dimension mismatch: expected {expected}, got {actual}")]
+    DimensionMismatch { expected: usize, actual: usize },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -32,37 +63,692 @@ pub struct Edge {
     pub weight: f32,
     #[serde(default = "default_partition")]
     pub partition_id: String, // Same as nodes - edges belong to partitions
+    /// When the fact starts to hold; `None` means it always has
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_from: Option<DateTime<Utc>>,
+    /// When the fact stops holding (exclusive); `None` means it still does
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_to: Option<DateTime<Utc>>,
+    /// Set by `CrossPartitionPolicy::Flag` on edges linking two partitions
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cross_partition: bool,
+}
+
+impl Edge {
+    /// Whether the edge's validity window covers `at`
+    pub fn is_valid_at(&self, at: DateTime<Utc>) -> bool {
+        self.valid_from.is_none_or(|from| from <= at) && self.valid_to.is_none_or(|to| at < to)
+    }
+}
+
+/// What a store does with an edge whose endpoints are in different
+/// partitions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CrossPartitionPolicy {
+    /// Store it as is
+    #[default]
+    Allow,
+    /// Reject it with `GraphError::Conflict`
+    Deny,
+    /// Store it with `Edge::cross_partition` set
+    Flag,
+}
+
+impl CrossPartitionPolicy {
+    /// Applies the policy to `edge`, given the partitions of its endpoints.
+    /// Endpoints that are not stored yet (`None`) are not checked.
+    pub fn check(
+        &self,
+        edge: &mut Edge,
+        source_partition: Option<&str>,
+        target_partition: Option<&str>,
+    ) -> Result<(), GraphError> {
+        let (Some(source), Some(target)) = (source_partition, target_partition) else {
+            return Ok(());
+        };
+        match self {
+            CrossPartitionPolicy::Allow => {}
+            CrossPartitionPolicy::Deny if source != target => {
+                return Err(GraphError::Conflict(format!(
+                    "Edge {}-[{}]->{} links partitions {} and {}",
+                    edge.source, edge.relation, edge.target, source, target
+                )));
+            }
+            CrossPartitionPolicy::Deny => {}
+            CrossPartitionPolicy::Flag => edge.cross_partition = source != target,
+        }
+        Ok(())
+    }
+}
+
+/// A past state of a node, kept while history mode is on
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NodeVersion {
+    pub node: Node,
+    /// When this version was overwritten or deleted
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// A partition as listed by `GraphStore::list_partitions`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PartitionInfo {
+    pub id: String,
+    pub node_count: usize,
+}
+
+/// An embedding as listed by `VectorStore::list_embeddings`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StoredEmbedding {
+    pub id: String,
+    /// Named embedding space; `None` is the default space
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    pub vector: Vec<f32>,
+}
+
+/// Which edges to follow when walking the graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Direction {
+    /// Edges leaving the node
+    #[default]
+    Outgoing,
+    /// Edges pointing at the node
+    Incoming,
+    Both,
+}
+
+/// A chain of nodes joined by edges, as returned by path queries.
+///
+/// `nodes` has one more entry than `edges`; `edges[i]` connects `nodes[i]` and
+/// `nodes[i + 1]` in either orientation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GraphPath {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+/// How `weighted_path` turns an edge's weight into the cost of following it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum EdgeCost {
+    /// Weight is the strength of an association, so strong edges are cheap:
+    /// the cost is `1 / weight`. Edges without a positive weight are skipped.
+    #[default]
+    Strength,
+    /// Weight is the cost itself, e.g. a distance. Negative weights are
+    /// skipped.
+    Weight,
+}
+
+impl EdgeCost {
+    /// Cost of following an edge of `weight`, or `None` if it cannot be used
+    pub fn of(&self, weight: f32) -> Option<f32> {
+        match self {
+            EdgeCost::Strength if weight > 0.0 => Some(1.0 / weight),
+            EdgeCost::Weight if weight >= 0.0 => Some(weight),
+            _ => None,
+        }
+    }
+}
+
+/// Sort key for paged node queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NodeOrder {
+    /// Insertion time, as recorded by the store
+    #[default]
+    CreatedAt,
+    Label,
+    Id,
+}
+
+/// One page of a node listing. Ties in the sort key are broken by id, so
+/// consecutive pages never overlap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageRequest {
+    pub offset: usize,
+    pub limit: usize,
+    pub order: NodeOrder,
+    pub descending: bool,
+}
+
+impl PageRequest {
+    pub const DEFAULT_LIMIT: usize = 100;
+
+    pub fn new(offset: usize, limit: usize) -> Self {
+        Self {
+            offset,
+            limit,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_order(mut self, order: NodeOrder, descending: bool) -> Self {
+        self.order = order;
+        self.descending = descending;
+        self
+    }
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            limit: Self::DEFAULT_LIMIT,
+            order: NodeOrder::default(),
+            descending: false,
+        }
+    }
 }
 
 fn default_partition() -> String {
     "personal".to_string()
 }
 
+/// Deep-merges `patch` into `target`. Nested objects are merged key by key;
+/// any other value in `patch` replaces the one in `target`.
+pub fn merge_properties(target: &mut serde_json::Value, patch: serde_json::Value) {
+    match (target, patch) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(patch)) => {
+            for (key, value) in patch {
+                match target.get_mut(&key) {
+                    Some(existing) => merge_properties(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+/// Scores how well the string values of `properties` match `terms`
+/// (lowercase words). Every term must appear; the score is the total number
+/// of occurrences. Backends without a full-text index use this for
+/// `search_text`.
+pub fn text_match_score(properties: &serde_json::Value, terms: &[String]) -> Option<f32> {
+    let text = match properties {
+        serde_json::Value::Object(map) => map
+            .values()
+            .filter_map(|v| v.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase(),
+        _ => return None,
+    };
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let mut score = 0.0;
+    for term in terms {
+        let hits = words.iter().filter(|w| *w == term).count();
+        if hits == 0 {
+            return None;
+        }
+        score += hits as f32;
+    }
+    Some(score)
+}
+
 #[async_trait]
 pub trait GraphStore: Send + Sync {
+    /// Inserts a new node. Fails with `GraphError::Conflict` if a node with
+    /// the same id already exists; use `upsert_node` to merge instead.
     async fn add_node(&self, node: Node) -> Result<(), GraphError>;
     async fn add_edge(&self, edge: Edge) -> Result<(), GraphError>;
     async fn get_node(&self, id: &str) -> Result<Node, GraphError>;
     async fn get_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError>;
+    /// Edges pointing at `id`, each paired with its source node
+    async fn get_incoming_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError>;
+
+    /// One-hop neighbors in the given direction. `Outgoing` is the same as
+    /// `get_neighbors`; `Both` returns outgoing edges first, then incoming.
+    async fn get_neighbors_directed(
+        &self,
+        id: &str,
+        direction: Direction,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        match direction {
+            Direction::Outgoing => self.get_neighbors(id).await,
+            Direction::Incoming => self.get_incoming_neighbors(id).await,
+            Direction::Both => {
+                let mut neighbors = self.get_neighbors(id).await?;
+                neighbors.extend(self.get_incoming_neighbors(id).await?);
+                Ok(neighbors)
+            }
+        }
+    }
+
+    /// `get_neighbors_directed` keeping only edges valid at `at`, so
+    /// superseded facts can stay in the graph without being returned
+    async fn get_neighbors_at(
+        &self,
+        id: &str,
+        direction: Direction,
+        at: DateTime<Utc>,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        Ok(self
+            .get_neighbors_directed(id, direction)
+            .await?
+            .into_iter()
+            .filter(|(e, _)| e.is_valid_at(at))
+            .collect())
+    }
+
     async fn update_node(&self, node: Node) -> Result<(), GraphError>;
 
+    /// Earlier versions of `id`, oldest first: the node as it was just before
+    /// each update or delete. Only writes made while the backend's history
+    /// mode is on are recorded.
+    async fn get_node_history(&self, _id: &str) -> Result<Vec<NodeVersion>, GraphError> {
+        Err(history_unsupported())
+    }
+
+    /// Puts back the version of `id` recorded at `recorded_at`, recreating the
+    /// node if it has been deleted since. With history on, the state being
+    /// replaced is recorded too, so a restore can itself be undone.
+    async fn restore_node_version(
+        &self,
+        id: &str,
+        recorded_at: DateTime<Utc>,
+    ) -> Result<(), GraphError> {
+        let version = self
+            .get_node_history(id)
+            .await?
+            .into_iter()
+            .rev()
+            .find(|v| v.recorded_at == recorded_at)
+            .ok_or_else(|| GraphError::NotFound(format!("{} at {}", id, recorded_at)))?;
+
+        match self.get_node(id).await {
+            Ok(_) => self.update_node(version.node).await,
+            Err(GraphError::NotFound(_)) => self.add_node(version.node).await,
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Expands up to `depth` hops from `id`, following edges in `direction`
+    /// and, if given, only the relations in `relation_filter`.
+    ///
+    /// Returns each edge reached (once, in hop order) with the node on its far
+    /// side. The start node itself is not included unless a cycle leads back
+    /// to it. Fails with `NotFound` if `id` does not exist.
+    async fn traverse(
+        &self,
+        id: &str,
+        depth: usize,
+        direction: Direction,
+        relation_filter: Option<&[&str]>,
+    ) -> Result<Vec<(Edge, Node)>, GraphError>;
+
+    /// Inserts the node, or merges it into the existing node with the same id.
+    /// Label and partition are replaced; properties are merged with
+    /// `merge_properties`, so incoming keys win and other keys are kept.
+    async fn upsert_node(&self, node: Node) -> Result<(), GraphError> {
+        match self.get_node(&node.id).await {
+            Ok(mut existing) => {
+                merge_properties(&mut existing.properties, node.properties);
+                existing.label = node.label;
+                existing.partition_id = node.partition_id;
+                self.update_node(existing).await
+            }
+            Err(GraphError::NotFound(_)) => self.add_node(node).await,
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Adds `node` under a new UUIDv7 id, replacing whatever `node.id` holds,
+    /// and returns the id. Should the id be taken after all, the node gets
+    /// another one; existing nodes are never overwritten.
+    async fn add_node_auto(&self, mut node: Node) -> Result<String, GraphError> {
+        let mut attempts = 0;
+        loop {
+            node.id = ids::new_node_id();
+            match self.add_node(node.clone()).await {
+                Ok(()) => return Ok(node.id),
+                Err(GraphError::Conflict(_)) if attempts < 3 => attempts += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Upserts `node` under the id `ids::deterministic_node_id` derives from
+    /// its label and the `keys` properties, and returns the id. Ingesting
+    /// the same entity again merges into the node instead of duplicating it.
+    async fn upsert_node_by_key(
+        &self,
+        mut node: Node,
+        keys: &[&str],
+    ) -> Result<String, GraphError> {
+        node.id = ids::deterministic_node_id(&node.label, &node.properties, keys)?;
+        let id = node.id.clone();
+        self.upsert_node(node).await?;
+        Ok(id)
+    }
+
+    /// Merges `patch` into the properties of node `id` with
+    /// `merge_properties` and returns the patched node. Keys not in `patch`
+    /// are kept, so writers touching different keys do not overwrite each
+    /// other. Backends should override this to apply the patch in a single
+    /// write. Fails with `NotFound` if `id` does not exist.
+    async fn patch_node(&self, id: &str, patch: serde_json::Value) -> Result<Node, GraphError> {
+        let mut node = self.get_node(id).await?;
+        merge_properties(&mut node.properties, patch);
+        self.update_node(node.clone()).await?;
+        Ok(node)
+    }
+
+    /// Inserts many nodes at once. Backends should override this with a
+    /// single batched write; the default falls back to one call per node.
+    async fn add_nodes(&self, nodes: Vec<Node>) -> Result<(), GraphError> {
+        for node in nodes {
+            self.add_node(node).await?;
+        }
+        Ok(())
+    }
+
+    /// Inserts many edges at once. See `add_nodes`.
+    async fn add_edges(&self, edges: Vec<Edge>) -> Result<(), GraphError> {
+        for edge in edges {
+            self.add_edge(edge).await?;
+        }
+        Ok(())
+    }
+
+    /// Deletes a node. Fails with `Conflict` if edges are still attached,
+    /// unless `cascade` is set, in which case they are removed as well.
+    async fn delete_node(&self, id: &str, cascade: bool) -> Result<(), GraphError>;
+    async fn delete_edge(
+        &self,
+        source: &str,
+        relation: &str,
+        target: &str,
+    ) -> Result<(), GraphError>;
+
+    /// Replaces the weight, partition and validity of the stored
+    /// `source-[relation]->target` edges with those of `edge`. Endpoints and
+    /// the `cross_partition` flag are kept. Fails with `NotFound` if there is
+    /// no such edge.
+    async fn update_edge(&self, _edge: Edge) -> Result<(), GraphError> {
+        Err(edge_updates_unsupported())
+    }
+
+    /// Adds `delta` to the weight of the `source-[relation]->target` edges in
+    /// one write and returns the new weight (the largest, if the pair has
+    /// several such edges). Fails with `NotFound` if there is none, so a
+    /// caller recording co-occurrences can fall back to `add_edge`.
+    async fn increment_edge_weight(
+        &self,
+        _source: &str,
+        _relation: &str,
+        _target: &str,
+        _delta: f32,
+    ) -> Result<f32, GraphError> {
+        Err(edge_updates_unsupported())
+    }
+
+    /// Finds a path with the fewest hops between `from` and `to`, at most
+    /// `max_depth` edges long. Edges are followed in either direction. With a
+    /// `partition_id`, only nodes and edges in that partition are used.
+    ///
+    /// Returns `None` if the nodes are not connected within `max_depth`.
+    async fn shortest_path(
+        &self,
+        from: &str,
+        to: &str,
+        max_depth: usize,
+        partition_id: Option<&str>,
+    ) -> Result<Option<GraphPath>, GraphError> {
+        traversal::shortest_path(self, from, to, max_depth, partition_id).await
+    }
+
+    /// Finds the cheapest path between `from` and `to` with Dijkstra's
+    /// algorithm, pricing each edge with `cost`. Edges are followed in either
+    /// direction. With a `partition_id`, only nodes and edges in that
+    /// partition are used.
+    ///
+    /// Returns the path with its total cost, or `None` if the nodes are not
+    /// connected.
+    async fn weighted_path(
+        &self,
+        from: &str,
+        to: &str,
+        cost: EdgeCost,
+        partition_id: Option<&str>,
+    ) -> Result<Option<(GraphPath, f32)>, GraphError> {
+        traversal::weighted_path(self, from, to, cost, partition_id).await
+    }
+
+    /// Collects the neighbourhood within `depth` hops (either direction) of
+    /// every seed into one self-contained snapshot: each node once, seeds
+    /// first, and only edges whose ends are both included.
+    async fn extract_subgraph(
+        &self,
+        seeds: &[String],
+        depth: usize,
+    ) -> Result<GraphSnapshot, GraphError> {
+        traversal::extract_subgraph(self, seeds, depth).await
+    }
+
+    /// Applies every write in `tx` or none of them. The default applies ops
+    /// one at a time and is only atomic if the backend overrides it.
+    async fn commit_transaction(&self, tx: GraphTransaction) -> Result<(), GraphError> {
+        transaction::apply_ops(self, tx.into_ops()).await
+    }
+
+    /// Keyword search over the string values of node properties. Returns
+    /// nodes containing every term of `query`, best match first, with a
+    /// relevance score.
+    async fn search_text(&self, query: &str, limit: usize) -> Result<Vec<(Node, f32)>, GraphError>;
+
+    /// Whether node `id` exists, without loading it
+    async fn node_exists(&self, id: &str) -> Result<bool, GraphError> {
+        match self.get_node(id).await {
+            Ok(_) => Ok(true),
+            Err(GraphError::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Number of nodes, in every partition or only in `partition_id`. The
+    /// defaults load the records they count; backends should override them.
+    async fn count_nodes(&self, partition_id: Option<&str>) -> Result<usize, GraphError> {
+        match partition_id {
+            Some(partition_id) => Ok(self.query_by_partition(partition_id).await?.len()),
+            None => Ok(self.snapshot(None).await?.nodes.len()),
+        }
+    }
+
+    /// Number of edges, of every relation or only of `relation`
+    async fn count_edges(&self, relation: Option<&str>) -> Result<usize, GraphError> {
+        Ok(self
+            .snapshot(None)
+            .await?
+            .edges
+            .iter()
+            .filter(|e| relation.is_none_or(|r| e.relation == r))
+            .count())
+    }
+
     // Partition-aware queries
+    /// Every node in `partition_id`, loaded at once. Large scans should use
+    /// `stream::partition_nodes` instead.
     async fn query_by_partition(&self, partition_id: &str) -> Result<Vec<Node>, GraphError>;
+    /// Up to `limit` nodes with `label`, across every partition or only in
+    /// `partition_id`, ordered by id. The default scans; the SQLite and
+    /// SurrealDB stores answer from an index on the label.
+    async fn query_by_label(
+        &self,
+        label: &str,
+        partition_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Node>, GraphError> {
+        let nodes = match partition_id {
+            Some(partition_id) => self.query_by_partition(partition_id).await?,
+            None => self.snapshot(None).await?.nodes,
+        };
+        let mut nodes: Vec<Node> = nodes.into_iter().filter(|n| n.label == label).collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        nodes.truncate(limit);
+        Ok(nodes)
+    }
+    /// Copies the whole graph, or only `partition_id`, into a snapshot. A
+    /// partition snapshot keeps only edges whose endpoints are both inside it.
+    async fn snapshot(&self, partition_id: Option<&str>) -> Result<GraphSnapshot, GraphError>;
+    /// Like `query_by_partition`, but returns a single sorted page
+    async fn query_by_partition_page(
+        &self,
+        partition_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Node>, GraphError>;
     async fn get_neighbors_in_partition(
         &self,
         id: &str,
         partition_id: &str,
     ) -> Result<Vec<(Edge, Node)>, GraphError>;
+
+    /// `get_neighbors_directed` restricted to edges and nodes in `partition_id`
+    async fn get_neighbors_in_partition_directed(
+        &self,
+        id: &str,
+        partition_id: &str,
+        direction: Direction,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        if direction == Direction::Outgoing {
+            return self.get_neighbors_in_partition(id, partition_id).await;
+        }
+        Ok(self
+            .get_neighbors_directed(id, direction)
+            .await?
+            .into_iter()
+            .filter(|(e, n)| e.partition_id == partition_id && n.partition_id == partition_id)
+            .collect())
+    }
+
+    // Partition management. A partition exists once it has been created or
+    // any node is stored in it.
+
+    /// Registers an empty partition. Fails with `Conflict` if it exists.
+    async fn create_partition(&self, _partition_id: &str) -> Result<(), GraphError> {
+        Err(partitions_unsupported())
+    }
+
+    /// Every partition, sorted by id, with the number of nodes in it
+    async fn list_partitions(&self) -> Result<Vec<PartitionInfo>, GraphError> {
+        Err(partitions_unsupported())
+    }
+
+    /// Moves the nodes of `from`, and their edges tagged `from`, into `to`.
+    /// Fails with `NotFound` if `from` does not exist and with `Conflict` if
+    /// `to` already does.
+    async fn rename_partition(&self, _from: &str, _to: &str) -> Result<(), GraphError> {
+        Err(partitions_unsupported())
+    }
+
+    /// Removes a partition. Fails with `Conflict` if it still holds nodes,
+    /// unless `cascade` is set, in which case the nodes go too, along with
+    /// their edges and embeddings.
+    async fn delete_partition(
+        &self,
+        _partition_id: &str,
+        _cascade: bool,
+    ) -> Result<(), GraphError> {
+        Err(partitions_unsupported())
+    }
+}
+
+fn partitions_unsupported() -> GraphError {
+    GraphError::Storage("partition management is not supported by this store".to_string())
+}
+
+const HISTORY_UNSUPPORTED: &str = "node history is not supported by this store";
+
+fn history_unsupported() -> GraphError {
+    GraphError::Storage(HISTORY_UNSUPPORTED.to_string())
+}
+
+/// Whether `get_node_history` failed because the store keeps no history,
+/// rather than because reading it did
+pub(crate) fn is_history_unsupported(e: &GraphError) -> bool {
+    matches!(e, GraphError::Storage(message) if message == HISTORY_UNSUPPORTED)
+}
+
+fn edge_updates_unsupported() -> GraphError {
+    GraphError::Storage("edge updates are not supported by this store".to_string())
 }
 
 #[async_trait]
 pub trait VectorStore: Send + Sync {
-    async fn add_embedding(&self, id: &str, vector: Vec<f32>) -> Result<(), GraphError>;
+    /// Stores the embedding of `id`, replacing any previous one. `field`
+    /// picks a named embedding space (e.g. "title"); `None` is the default
+    /// space. Each space is indexed and searched on its own.
+    async fn add_embedding(
+        &self,
+        id: &str,
+        vector: Vec<f32>,
+        field: Option<&str>,
+    ) -> Result<(), GraphError>;
     async fn search(
         &self,
         vector: Vec<f32>,
         limit: usize,
+        field: Option<&str>,
     ) -> Result<Vec<(String, f32)>, GraphError>;
+
+    /// Stores many embeddings at once, e.g. every chunk of a document.
+    /// Backends should override this with a single batched write; the
+    /// default falls back to one call per embedding.
+    async fn add_embeddings(
+        &self,
+        embeddings: Vec<(String, Vec<f32>)>,
+        field: Option<&str>,
+    ) -> Result<(), GraphError> {
+        for (id, vector) in embeddings {
+            self.add_embedding(&id, vector, field).await?;
+        }
+        Ok(())
+    }
+
+    /// Drops every embedding stored for `id`, in all spaces
+    async fn remove_embedding(&self, id: &str) -> Result<(), GraphError>;
+
+    /// Drops the embeddings of every node in the partition, in all spaces,
+    /// e.g. before a document set is re-chunked
+    async fn clear_embeddings(&self, partition_id: &str) -> Result<(), GraphError>;
+
+    /// Like `search`, but returns the matching nodes with their properties
+    /// in one round trip. Stores that only hold vectors do not support it.
+    async fn search_nodes(
+        &self,
+        _vector: Vec<f32>,
+        _limit: usize,
+        _field: Option<&str>,
+    ) -> Result<Vec<(Node, f32)>, GraphError> {
+        Err(GraphError::Storage(
+            "search_nodes is not supported by this store".to_string(),
+        ))
+    }
+
+    /// The embeddings of `id` in every space it has one, default space
+    /// first. The default scans `list_embeddings`; backends should override
+    /// it with a lookup by id.
+    async fn get_embeddings(&self, id: &str) -> Result<Vec<StoredEmbedding>, GraphError> {
+        Ok(self
+            .list_embeddings()
+            .await?
+            .into_iter()
+            .filter(|e| e.id == id)
+            .collect())
+    }
+
+    /// Every stored embedding, in all spaces, e.g. to back them up
+    async fn list_embeddings(&self) -> Result<Vec<StoredEmbedding>, GraphError> {
+        Err(GraphError::Storage(
+            "listing embeddings is not supported by this store".to_string(),
+        ))
+    }
 }
 
 #[cfg(any(test, feature = "test-utils"))]
@@ -73,6 +759,11 @@ pub mod mocks {
     pub struct MockGraphStore {
         nodes: std::sync::RwLock<std::collections::HashMap<String, Node>>,
         edges: std::sync::RwLock<Vec<Edge>>,
+        // Insertion sequence per node id, standing in for created_at
+        created: std::sync::RwLock<std::collections::HashMap<String, u64>>,
+        next_seq: std::sync::atomic::AtomicU64,
+        // Partitions registered with create_partition
+        partitions: std::sync::RwLock<std::collections::BTreeSet<String>>,
     }
 
     impl Default for MockGraphStore {
@@ -86,14 +777,37 @@ pub mod mocks {
             Self {
                 nodes: std::sync::RwLock::new(std::collections::HashMap::new()),
                 edges: std::sync::RwLock::new(Vec::new()),
+                created: std::sync::RwLock::new(std::collections::HashMap::new()),
+                next_seq: std::sync::atomic::AtomicU64::new(0),
+                partitions: std::sync::RwLock::new(std::collections::BTreeSet::new()),
             }
         }
+
+        fn partition_exists(&self, partition_id: &str) -> bool {
+            self.partitions.read().unwrap().contains(partition_id)
+                || self
+                    .nodes
+                    .read()
+                    .unwrap()
+                    .values()
+                    .any(|n| n.partition_id == partition_id)
+        }
     }
 
     #[async_trait]
     impl GraphStore for MockGraphStore {
         async fn add_node(&self, node: Node) -> Result<(), GraphError> {
             let mut nodes = self.nodes.write().unwrap();
+            if nodes.contains_key(&node.id) {
+                return Err(GraphError::Conflict(format!(
+                    "Node already exists: {}",
+                    node.id
+                )));
+            }
+            let seq = self
+                .next_seq
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            self.created.write().unwrap().insert(node.id.clone(), seq);
             nodes.insert(node.id.clone(), node);
             Ok(())
         }
@@ -127,6 +841,69 @@ pub mod mocks {
             Ok(result)
         }
 
+        async fn get_incoming_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+            let edges = self.edges.read().unwrap();
+            let nodes = self.nodes.read().unwrap();
+
+            Ok(edges
+                .iter()
+                .filter(|edge| edge.target == id)
+                .filter_map(|edge| {
+                    nodes
+                        .get(&edge.source)
+                        .map(|source| (edge.clone(), source.clone()))
+                })
+                .collect())
+        }
+
+        async fn traverse(
+            &self,
+            id: &str,
+            depth: usize,
+            direction: Direction,
+            relation_filter: Option<&[&str]>,
+        ) -> Result<Vec<(Edge, Node)>, GraphError> {
+            let edges = self.edges.read().unwrap();
+            let nodes = self.nodes.read().unwrap();
+            if !nodes.contains_key(id) {
+                return Err(GraphError::NotFound(id.to_string()));
+            }
+
+            Ok(
+                traversal::expand(&edges, id, depth, direction, relation_filter)
+                    .into_iter()
+                    .filter_map(|(edge, far)| nodes.get(far).map(|n| (edge.clone(), n.clone())))
+                    .collect(),
+            )
+        }
+
+        async fn search_text(
+            &self,
+            query: &str,
+            limit: usize,
+        ) -> Result<Vec<(Node, f32)>, GraphError> {
+            let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+            if terms.is_empty() {
+                return Ok(vec![]);
+            }
+
+            let nodes = self.nodes.read().unwrap();
+            let mut results: Vec<(Node, f32)> = nodes
+                .values()
+                .filter_map(|node| {
+                    text_match_score(&node.properties, &terms).map(|score| (node.clone(), score))
+                })
+                .collect();
+
+            results.sort_by(|a, b| {
+                b.1.partial_cmp(&a.1)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then_with(|| a.0.id.cmp(&b.0.id))
+            });
+            results.truncate(limit);
+            Ok(results)
+        }
+
         async fn update_node(&self, node: Node) -> Result<(), GraphError> {
             let mut nodes = self.nodes.write().unwrap();
             if !nodes.contains_key(&node.id) {
@@ -136,6 +913,113 @@ pub mod mocks {
             Ok(())
         }
 
+        async fn delete_node(&self, id: &str, cascade: bool) -> Result<(), GraphError> {
+            let mut nodes = self.nodes.write().unwrap();
+            let mut edges = self.edges.write().unwrap();
+            if !nodes.contains_key(id) {
+                return Err(GraphError::NotFound(id.to_string()));
+            }
+
+            let attached = edges
+                .iter()
+                .filter(|e| e.source == id || e.target == id)
+                .count();
+            if attached > 0 && !cascade {
+                return Err(GraphError::Conflict(format!(
+                    "Node {} has {} attached edges",
+                    id, attached
+                )));
+            }
+
+            edges.retain(|e| e.source != id && e.target != id);
+            nodes.remove(id);
+            self.created.write().unwrap().remove(id);
+            Ok(())
+        }
+
+        async fn delete_edge(
+            &self,
+            source: &str,
+            relation: &str,
+            target: &str,
+        ) -> Result<(), GraphError> {
+            let mut edges = self.edges.write().unwrap();
+            let before = edges.len();
+            edges.retain(|e| !(e.source == source && e.relation == relation && e.target == target));
+            if edges.len() == before {
+                return Err(GraphError::NotFound(format!(
+                    "{}-[{}]->{}",
+                    source, relation, target
+                )));
+            }
+            Ok(())
+        }
+
+        async fn update_edge(&self, edge: Edge) -> Result<(), GraphError> {
+            let mut edges = self.edges.write().unwrap();
+            let mut found = false;
+            for e in edges.iter_mut().filter(|e| {
+                e.source == edge.source && e.relation == edge.relation && e.target == edge.target
+            }) {
+                e.weight = edge.weight;
+                e.partition_id = edge.partition_id.clone();
+                e.valid_from = edge.valid_from;
+                e.valid_to = edge.valid_to;
+                found = true;
+            }
+            if !found {
+                return Err(GraphError::NotFound(format!(
+                    "{}-[{}]->{}",
+                    edge.source, edge.relation, edge.target
+                )));
+            }
+            Ok(())
+        }
+
+        async fn increment_edge_weight(
+            &self,
+            source: &str,
+            relation: &str,
+            target: &str,
+            delta: f32,
+        ) -> Result<f32, GraphError> {
+            let mut edges = self.edges.write().unwrap();
+            edges
+                .iter_mut()
+                .filter(|e| e.source == source && e.relation == relation && e.target == target)
+                .map(|e| {
+                    e.weight += delta;
+                    e.weight
+                })
+                .reduce(f32::max)
+                .ok_or_else(|| {
+                    GraphError::NotFound(format!("{}-[{}]->{}", source, relation, target))
+                })
+        }
+
+        async fn commit_transaction(&self, tx: GraphTransaction) -> Result<(), GraphError> {
+            // Apply to a staged copy and only publish it if every op succeeds
+            let staged = MockGraphStore {
+                nodes: std::sync::RwLock::new(self.nodes.read().unwrap().clone()),
+                edges: std::sync::RwLock::new(self.edges.read().unwrap().clone()),
+                created: std::sync::RwLock::new(self.created.read().unwrap().clone()),
+                next_seq: std::sync::atomic::AtomicU64::new(
+                    self.next_seq.load(std::sync::atomic::Ordering::Relaxed),
+                ),
+                partitions: std::sync::RwLock::new(std::collections::BTreeSet::new()),
+            };
+            transaction::apply_ops(&staged, tx.into_ops()).await?;
+
+            *self.nodes.write().unwrap() = staged.nodes.into_inner().unwrap();
+            *self.edges.write().unwrap() = staged.edges.into_inner().unwrap();
+            *self.created.write().unwrap() = staged.created.into_inner().unwrap();
+            self.next_seq.store(
+                staged.next_seq.into_inner(),
+                std::sync::atomic::Ordering::Relaxed,
+            );
+            Ok(())
+        }
+
         async fn query_by_partition(&self, partition_id: &str) -> Result<Vec<Node>, GraphError> {
             let nodes = self.nodes.read().unwrap();
             let filtered: Vec<Node> = nodes
@@ -146,6 +1030,64 @@ pub mod mocks {
             Ok(filtered)
         }
 
+        async fn snapshot(&self, partition_id: Option<&str>) -> Result<GraphSnapshot, GraphError> {
+            let nodes = self.nodes.read().unwrap();
+            let edges = self.edges.read().unwrap();
+            let in_scope = |partition: &str| partition_id.is_none_or(|p| p == partition);
+
+            let mut snapshot_nodes: Vec<Node> = nodes
+                .values()
+                .filter(|n| in_scope(&n.partition_id))
+                .cloned()
+                .collect();
+            snapshot_nodes.sort_by(|a, b| a.id.cmp(&b.id));
+
+            let snapshot_edges = edges
+                .iter()
+                .filter(|e| {
+                    in_scope(&e.partition_id)
+                        && [&e.source, &e.target]
+                            .iter()
+                            .all(|id| nodes.get(*id).is_some_and(|n| in_scope(&n.partition_id)))
+                })
+                .cloned()
+                .collect();
+
+            Ok(GraphSnapshot::new(snapshot_nodes, snapshot_edges))
+        }
+
+        async fn query_by_partition_page(
+            &self,
+            partition_id: &str,
+            page: PageRequest,
+        ) -> Result<Vec<Node>, GraphError> {
+            let nodes = self.nodes.read().unwrap();
+            let created = self.created.read().unwrap();
+            let mut filtered: Vec<&Node> = nodes
+                .values()
+                .filter(|n| n.partition_id == partition_id)
+                .collect();
+
+            filtered.sort_by(|a, b| {
+                let key = match page.order {
+                    NodeOrder::CreatedAt => created.get(&a.id).cmp(&created.get(&b.id)),
+                    NodeOrder::Label => a.label.cmp(&b.label),
+                    NodeOrder::Id => std::cmp::Ordering::Equal,
+                };
+                key.then_with(|| a.id.cmp(&b.id))
+            });
+            if page.descending {
+                filtered.reverse();
+            }
+
+            Ok(filtered
+                .into_iter()
+                .skip(page.offset)
+                .take(page.limit)
+                .cloned()
+                .collect())
+        }
+
         async fn get_neighbors_in_partition(
             &self,
             id: &str,
@@ -166,10 +1108,113 @@ pub mod mocks {
             }
             Ok(result)
         }
+
+        async fn create_partition(&self, partition_id: &str) -> Result<(), GraphError> {
+            if self.partition_exists(partition_id) {
+                return Err(GraphError::Conflict(format!(
+                    "Partition already exists: {}",
+                    partition_id
+                )));
+            }
+            self.partitions
+                .write()
+                .unwrap()
+                .insert(partition_id.to_string());
+            Ok(())
+        }
+
+        async fn list_partitions(&self) -> Result<Vec<PartitionInfo>, GraphError> {
+            let mut counts: std::collections::BTreeMap<String, usize> = self
+                .partitions
+                .read()
+                .unwrap()
+                .iter()
+                .map(|p| (p.clone(), 0))
+                .collect();
+            for node in self.nodes.read().unwrap().values() {
+                *counts.entry(node.partition_id.clone()).or_default() += 1;
+            }
+            Ok(counts
+                .into_iter()
+                .map(|(id, node_count)| PartitionInfo { id, node_count })
+                .collect())
+        }
+
+        async fn rename_partition(&self, from: &str, to: &str) -> Result<(), GraphError> {
+            if !self.partition_exists(from) {
+                return Err(GraphError::NotFound(format!("Partition {}", from)));
+            }
+            if self.partition_exists(to) {
+                return Err(GraphError::Conflict(format!(
+                    "Partition already exists: {}",
+                    to
+                )));
+            }
+
+            let mut nodes = self.nodes.write().unwrap();
+            let mut edges = self.edges.write().unwrap();
+            let moved: std::collections::HashSet<String> = nodes
+                .values_mut()
+                .filter(|n| n.partition_id == from)
+                .map(|n| {
+                    n.partition_id = to.to_string();
+                    n.id.clone()
+                })
+                .collect();
+            for edge in edges.iter_mut() {
+                if edge.partition_id == from
+                    && (moved.contains(&edge.source) || moved.contains(&edge.target))
+                {
+                    edge.partition_id = to.to_string();
+                }
+            }
+
+            let mut partitions = self.partitions.write().unwrap();
+            partitions.remove(from);
+            partitions.insert(to.to_string());
+            Ok(())
+        }
+
+        async fn delete_partition(
+            &self,
+            partition_id: &str,
+            cascade: bool,
+        ) -> Result<(), GraphError> {
+            if !self.partition_exists(partition_id) {
+                return Err(GraphError::NotFound(format!("Partition {}", partition_id)));
+            }
+
+            let mut nodes = self.nodes.write().unwrap();
+            let doomed: std::collections::HashSet<String> = nodes
+                .values()
+                .filter(|n| n.partition_id == partition_id)
+                .map(|n| n.id.clone())
+                .collect();
+            if !doomed.is_empty() && !cascade {
+                return Err(GraphError::Conflict(format!(
+                    "Partition {} still has {} nodes",
+                    partition_id,
+                    doomed.len()
+                )));
+            }
+
+            self.edges
+                .write()
+                .unwrap()
+                .retain(|e| !doomed.contains(&e.source) && !doomed.contains(&e.target));
+            nodes.retain(|id, _| !doomed.contains(id));
+            self.created
+                .write()
+                .unwrap()
+                .retain(|id, _| !doomed.contains(id));
+            self.partitions.write().unwrap().remove(partition_id);
+            Ok(())
+        }
     }
 
     pub struct MockVectorStore {
-        vectors: std::sync::RwLock<std::collections::HashMap<String, Vec<f32>>>,
+        // Keyed by (field, id), with "" standing for the default field
+        vectors: std::sync::RwLock<std::collections::HashMap<(String, String), Vec<f32>>>,
     }
 
     impl Default for MockVectorStore {
@@ -199,9 +1244,15 @@ pub mod mocks {
 
     #[async_trait]
     impl VectorStore for MockVectorStore {
-        async fn add_embedding(&self, id: &str, vector: Vec<f32>) -> Result<(), GraphError> {
+        async fn add_embedding(
+            &self,
+            id: &str,
+            vector: Vec<f32>,
+            field: Option<&str>,
+        ) -> Result<(), GraphError> {
             let mut vectors = self.vectors.write().unwrap();
-            vectors.insert(id.to_string(), vector);
+            let key = (field.unwrap_or_default().to_string(), id.to_string());
+            vectors.insert(key, vector);
             Ok(())
         }
 
@@ -209,17 +1260,47 @@ pub mod mocks {
             &self,
             query: Vec<f32>,
             limit: usize,
+            field: Option<&str>,
         ) -> Result<Vec<(String, f32)>, GraphError> {
+            let field = field.unwrap_or_default();
             let vectors = self.vectors.read().unwrap();
             let mut results: Vec<(String, f32)> = vectors
                 .iter()
-                .map(|(id, vec)| (id.clone(), Self::cosine_similarity(&query, vec)))
+                .filter(|((f, _), _)| f == field)
+                .map(|((_, id), vec)| (id.clone(), Self::cosine_similarity(&query, vec)))
                 .collect();
 
             results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
             results.truncate(limit);
             Ok(results)
         }
+
+        async fn remove_embedding(&self, id: &str) -> Result<(), GraphError> {
+            self.vectors.write().unwrap().retain(|(_, v), _| v != id);
+            Ok(())
+        }
+
+        async fn clear_embeddings(&self, _partition_id: &str) -> Result<(), GraphError> {
+            Err(GraphError::Storage(
+                "MockVectorStore does not track partitions".to_string(),
+            ))
+        }
+
+        async fn list_embeddings(&self) -> Result<Vec<StoredEmbedding>, GraphError> {
+            let mut embeddings: Vec<StoredEmbedding> = self
+                .vectors
+                .read()
+                .unwrap()
+                .iter()
+                .map(|((field, id), vector)| StoredEmbedding {
+                    id: id.clone(),
+                    field: (!field.is_empty()).then(|| field.clone()),
+                    vector: vector.clone(),
+                })
+                .collect();
+            embeddings.sort_by(|a, b| a.id.cmp(&b.id).then_with(|| a.field.cmp(&b.field)));
+            Ok(embeddings)
+        }
     }
 }
 
@@ -252,6 +1333,9 @@ mod tests {
             relation: "KNOWS".to_string(),
             weight: 1.0,
             partition_id: "personal".to_string(),
+            valid_from: None,
+            valid_to: None,
+            cross_partition: false,
         };
 
         store.add_node(node1.clone()).await.unwrap();
@@ -267,20 +1351,647 @@ mod tests {
         assert_eq!(neighbors[0].0.relation, "KNOWS");
     }
 
+    #[tokio::test]
+    async fn test_add_node_conflict_and_upsert() {
+        let store = MockGraphStore::new();
+        let node = Node {
+            id: "1".to_string(),
+            label: "Person".to_string(),
+            properties: serde_json::json!({"name": "Alice", "meta": {"source": "chat"}}),
+            partition_id: "personal".to_string(),
+        };
+        store.add_node(node.clone()).await.unwrap();
+
+        assert!(matches!(
+            store.add_node(node.clone()).await,
+            Err(GraphError::Conflict(_))
+        ));
+
+        store
+            .upsert_node(Node {
+                properties: serde_json::json!({"email": "a@example.com", "meta": {"seen": 2}}),
+                ..node.clone()
+            })
+            .await
+            .unwrap();
+
+        let merged = store.get_node("1").await.unwrap();
+        assert_eq!(
+            merged.properties,
+            serde_json::json!({
+                "name": "Alice",
+                "email": "a@example.com",
+                "meta": {"source": "chat", "seen": 2}
+            })
+        );
+
+        // Upsert of a new id inserts it
+        store
+            .upsert_node(Node {
+                id: "2".to_string(),
+                ..node
+            })
+            .await
+            .unwrap();
+        assert!(store.get_node("2").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_add_node_auto_and_upsert_by_key() {
+        let store = MockGraphStore::new();
+        let person = |properties: serde_json::Value| Node {
+            id: String::new(),
+            label: "Person".to_string(),
+            properties,
+            partition_id: "personal".to_string(),
+        };
+
+        let first = store
+            .add_node_auto(person(serde_json::json!({"name": "Alice"})))
+            .await
+            .unwrap();
+        let second = store
+            .add_node_auto(person(serde_json::json!({"name": "Alice"})))
+            .await
+            .unwrap();
+        assert_ne!(first, second);
+        assert_eq!(store.get_node(&first).await.unwrap().id, first);
+
+        let id = store
+            .upsert_node_by_key(
+                person(serde_json::json!({"name": "Bob", "city": "Berlin"})),
+                &["name"],
+            )
+            .await
+            .unwrap();
+        let again = store
+            .upsert_node_by_key(
+                person(serde_json::json!({"name": "Bob", "age": 40})),
+                &["name"],
+            )
+            .await
+            .unwrap();
+        assert_eq!(id, again);
+        assert_eq!(
+            store.get_node(&id).await.unwrap().properties,
+            serde_json::json!({"name": "Bob", "city": "Berlin", "age": 40})
+        );
+        assert!(store
+            .upsert_node_by_key(person(serde_json::json!({})), &["name"])
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_patch_node_merges_properties() {
+        let store = MockGraphStore::new();
+        store
+            .add_node(Node {
+                id: "a".to_string(),
+                label: "Person".to_string(),
+                properties: serde_json::json!({"name": "Alice", "address": {"city": "Berlin"}}),
+                partition_id: "personal".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let patched = store
+            .patch_node(
+                "a",
+                serde_json::json!({"age": 30, "address": {"zip": "10115"}}),
+            )
+            .await
+            .unwrap();
+        let expected = serde_json::json!({
+            "name": "Alice",
+            "age": 30,
+            "address": {"city": "Berlin", "zip": "10115"}
+        });
+        assert_eq!(patched.properties, expected);
+        assert_eq!(store.get_node("a").await.unwrap().properties, expected);
+        assert!(matches!(
+            store.patch_node("missing", serde_json::json!({})).await,
+            Err(GraphError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_incoming_and_directed_neighbors() {
+        let store = MockGraphStore::new();
+        for (id, partition) in [("a", "personal"), ("b", "personal"), ("c", "work")] {
+            store
+                .add_node(Node {
+                    id: id.to_string(),
+                    label: "Person".to_string(),
+                    properties: serde_json::json!({}),
+                    partition_id: partition.to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        for (source, target, partition) in [("a", "b", "personal"), ("c", "b", "work")] {
+            store
+                .add_edge(Edge {
+                    source: source.to_string(),
+                    target: target.to_string(),
+                    relation: "KNOWS".to_string(),
+                    weight: 1.0,
+                    partition_id: partition.to_string(),
+                    valid_from: None,
+                    valid_to: None,
+                    cross_partition: false,
+                })
+                .await
+                .unwrap();
+        }
+
+        let incoming = store.get_incoming_neighbors("b").await.unwrap();
+        let mut sources: Vec<_> = incoming.iter().map(|(_, n)| n.id.as_str()).collect();
+        sources.sort();
+        assert_eq!(sources, vec!["a", "c"]);
+
+        let both = store
+            .get_neighbors_directed("a", Direction::Both)
+            .await
+            .unwrap();
+        assert_eq!(both.len(), 1);
+        assert_eq!(both[0].1.id, "b");
+
+        let personal = store
+            .get_neighbors_in_partition_directed("b", "personal", Direction::Incoming)
+            .await
+            .unwrap();
+        assert_eq!(personal.len(), 1);
+        assert_eq!(personal[0].1.id, "a");
+    }
+
+    #[tokio::test]
+    async fn test_search_text() {
+        let store = MockGraphStore::new();
+        let report = serde_json::json!({
+            "name": "Quarterly report",
+            "content": "Revenue grew in Berlin"
+        });
+        let office = serde_json::json!({"name": "Berlin office", "notes": "Berlin, Germany"});
+        for (id, properties) in [
+            ("1", report),
+            ("2", office),
+            ("3", serde_json::json!({"count": 3})),
+        ] {
+            store
+                .add_node(Node {
+                    id: id.to_string(),
+                    label: "Document".to_string(),
+                    properties,
+                    partition_id: "personal".to_string(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let hits = store.search_text("berlin", 10).await.unwrap();
+        let ids: Vec<_> = hits.iter().map(|(n, _)| n.id.as_str()).collect();
+        assert_eq!(ids, vec!["2", "1"]);
+
+        let hits = store.search_text("Berlin revenue", 10).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].0.id, "1");
+
+        assert_eq!(store.search_text("berlin", 1).await.unwrap().len(), 1);
+        assert!(store.search_text("  ", 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_query_by_partition_page() {
+        let store = MockGraphStore::new();
+        for (id, label) in [("c", "Topic"), ("a", "Person"), ("b", "Person")] {
+            store
+                .add_node(Node {
+                    id: id.to_string(),
+                    label: label.to_string(),
+                    properties: serde_json::json!({}),
+                    partition_id: "personal".to_string(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let ids = |nodes: Vec<Node>| nodes.into_iter().map(|n| n.id).collect::<Vec<_>>();
+
+        let first = store
+            .query_by_partition_page("personal", PageRequest::new(0, 2))
+            .await
+            .unwrap();
+        assert_eq!(ids(first), vec!["c", "a"]);
+        let second = store
+            .query_by_partition_page("personal", PageRequest::new(2, 2))
+            .await
+            .unwrap();
+        assert_eq!(ids(second), vec!["b"]);
+
+        let by_label = store
+            .query_by_partition_page(
+                "personal",
+                PageRequest::default().with_order(NodeOrder::Label, true),
+            )
+            .await
+            .unwrap();
+        assert_eq!(ids(by_label), vec!["c", "b", "a"]);
+
+        let other = store
+            .query_by_partition_page("work", PageRequest::default())
+            .await
+            .unwrap();
+        assert!(other.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_query_by_label() {
+        let store = MockGraphStore::new();
+        for (id, label, partition) in [
+            ("c", "Person", "personal"),
+            ("a", "Person", "personal"),
+            ("t", "Topic", "personal"),
+            ("w", "Person", "work"),
+        ] {
+            store
+                .add_node(Node {
+                    id: id.to_string(),
+                    label: label.to_string(),
+                    properties: serde_json::json!({}),
+                    partition_id: partition.to_string(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let ids = |nodes: Vec<Node>| nodes.into_iter().map(|n| n.id).collect::<Vec<_>>();
+
+        let people = store.query_by_label("Person", None, 10).await.unwrap();
+        assert_eq!(ids(people), vec!["a", "c", "w"]);
+        let work = store
+            .query_by_label("Person", Some("work"), 10)
+            .await
+            .unwrap();
+        assert_eq!(ids(work), vec!["w"]);
+        let first = store
+            .query_by_label("Person", Some("personal"), 1)
+            .await
+            .unwrap();
+        assert_eq!(ids(first), vec!["a"]);
+        assert!(store
+            .query_by_label("Event", None, 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_counts_and_existence() {
+        let store = MockGraphStore::new();
+        for (id, partition) in [("a", "personal"), ("b", "personal"), ("w", "work")] {
+            store
+                .add_node(Node {
+                    id: id.to_string(),
+                    label: "Person".to_string(),
+                    properties: serde_json::json!({}),
+                    partition_id: partition.to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        for (source, relation, target) in [
+            ("a", "knows", "b"),
+            ("b", "knows", "a"),
+            ("w", "likes", "a"),
+        ] {
+            store
+                .add_edge(Edge {
+                    source: source.to_string(),
+                    target: target.to_string(),
+                    relation: relation.to_string(),
+                    weight: 1.0,
+                    partition_id: "personal".to_string(),
+                    valid_from: None,
+                    valid_to: None,
+                    cross_partition: false,
+                })
+                .await
+                .unwrap();
+        }
+
+        assert!(store.node_exists("a").await.unwrap());
+        assert!(!store.node_exists("missing").await.unwrap());
+        assert_eq!(store.count_nodes(None).await.unwrap(), 3);
+        assert_eq!(store.count_nodes(Some("personal")).await.unwrap(), 2);
+        assert_eq!(store.count_nodes(Some("empty")).await.unwrap(), 0);
+        assert_eq!(store.count_edges(None).await.unwrap(), 3);
+        assert_eq!(store.count_edges(Some("knows")).await.unwrap(), 2);
+        assert_eq!(store.count_edges(Some("hates")).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_scopes_to_partition() {
+        let store = MockGraphStore::new();
+        for (id, partition) in [("a", "personal"), ("b", "personal"), ("w", "work")] {
+            store
+                .add_node(Node {
+                    id: id.to_string(),
+                    label: "Person".to_string(),
+                    properties: serde_json::json!({}),
+                    partition_id: partition.to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        for (source, target, partition) in [("a", "b", "personal"), ("a", "w", "personal")] {
+            store
+                .add_edge(Edge {
+                    source: source.to_string(),
+                    target: target.to_string(),
+                    relation: "KNOWS".to_string(),
+                    weight: 1.0,
+                    partition_id: partition.to_string(),
+                    valid_from: None,
+                    valid_to: None,
+                    cross_partition: false,
+                })
+                .await
+                .unwrap();
+        }
+
+        let full = store.snapshot(None).await.unwrap();
+        assert_eq!(full.nodes.len(), 3);
+        assert_eq!(full.edges.len(), 2);
+
+        // The edge into the work partition is dropped
+        let personal = store.snapshot(Some("personal")).await.unwrap();
+        assert_eq!(personal.nodes.len(), 2);
+        assert_eq!(personal.edges.len(), 1);
+        assert_eq!(personal.edges[0].target, "b");
+    }
+
+    #[tokio::test]
+    async fn test_partition_management() {
+        let store = MockGraphStore::new();
+        for (id, partition) in [("a", "personal"), ("b", "personal"), ("w", "work")] {
+            store
+                .add_node(Node {
+                    id: id.to_string(),
+                    label: "Person".to_string(),
+                    properties: serde_json::json!({}),
+                    partition_id: partition.to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        for (source, target) in [("a", "b"), ("w", "a")] {
+            store
+                .add_edge(Edge {
+                    source: source.to_string(),
+                    target: target.to_string(),
+                    relation: "KNOWS".to_string(),
+                    weight: 1.0,
+                    partition_id: "personal".to_string(),
+                    valid_from: None,
+                    valid_to: None,
+                    cross_partition: false,
+                })
+                .await
+                .unwrap();
+        }
+
+        store.create_partition("archive").await.unwrap();
+        assert!(matches!(
+            store.create_partition("work").await,
+            Err(GraphError::Conflict(_))
+        ));
+        let listed: Vec<(String, usize)> = store
+            .list_partitions()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|p| (p.id, p.node_count))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("archive".to_string(), 0),
+                ("personal".to_string(), 2),
+                ("work".to_string(), 1)
+            ]
+        );
+
+        assert!(matches!(
+            store.rename_partition("personal", "work").await,
+            Err(GraphError::Conflict(_))
+        ));
+        store.rename_partition("personal", "home").await.unwrap();
+        assert_eq!(store.query_by_partition("home").await.unwrap().len(), 2);
+        let neighbors = store.get_neighbors_in_partition("a", "home").await.unwrap();
+        assert_eq!(neighbors.len(), 1);
+
+        assert!(matches!(
+            store.delete_partition("home", false).await,
+            Err(GraphError::Conflict(_))
+        ));
+        store.delete_partition("home", true).await.unwrap();
+        store.delete_partition("archive", false).await.unwrap();
+        assert!(matches!(
+            store.delete_partition("home", true).await,
+            Err(GraphError::NotFound(_))
+        ));
+
+        let listed = store.list_partitions().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, "work");
+        // Edges into the deleted partition went with it
+        assert!(store.get_neighbors("w").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_node_and_edge() {
+        let store = MockGraphStore::new();
+        for id in ["1", "2"] {
+            store
+                .add_node(Node {
+                    id: id.to_string(),
+                    label: "Person".to_string(),
+                    properties: serde_json::json!({}),
+                    partition_id: "personal".to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        store
+            .add_edge(Edge {
+                source: "1".to_string(),
+                target: "2".to_string(),
+                relation: "KNOWS".to_string(),
+                weight: 1.0,
+                partition_id: "personal".to_string(),
+                valid_from: None,
+                valid_to: None,
+                cross_partition: false,
+            })
+            .await
+            .unwrap();
+
+        // Attached edges block a plain delete
+        assert!(matches!(
+            store.delete_node("2", false).await,
+            Err(GraphError::Conflict(_))
+        ));
+
+        store.delete_edge("1", "KNOWS", "2").await.unwrap();
+        assert!(matches!(
+            store.delete_edge("1", "KNOWS", "2").await,
+            Err(GraphError::NotFound(_))
+        ));
+
+        store.delete_node("2", false).await.unwrap();
+        assert!(store.get_node("2").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_edge_and_increment_weight() {
+        let store = MockGraphStore::new();
+        for id in ["1", "2"] {
+            store
+                .add_node(Node {
+                    id: id.to_string(),
+                    label: "Person".to_string(),
+                    properties: serde_json::json!({}),
+                    partition_id: "personal".to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        let edge = Edge {
+            source: "1".to_string(),
+            target: "2".to_string(),
+            relation: "KNOWS".to_string(),
+            weight: 1.0,
+            partition_id: "personal".to_string(),
+            valid_from: None,
+            valid_to: None,
+            cross_partition: false,
+        };
+        store.add_edge(edge.clone()).await.unwrap();
+
+        assert_eq!(
+            store
+                .increment_edge_weight("1", "KNOWS", "2", 0.5)
+                .await
+                .unwrap(),
+            1.5
+        );
+        assert!(matches!(
+            store.increment_edge_weight("2", "KNOWS", "1", 0.5).await,
+            Err(GraphError::NotFound(_))
+        ));
+
+        let valid_from = chrono::Utc::now();
+        store
+            .update_edge(Edge {
+                weight: 0.2,
+                valid_from: Some(valid_from),
+                ..edge.clone()
+            })
+            .await
+            .unwrap();
+        let neighbors = store.get_neighbors("1").await.unwrap();
+        assert_eq!(neighbors.len(), 1);
+        assert_eq!(neighbors[0].0.weight, 0.2);
+        assert_eq!(neighbors[0].0.valid_from, Some(valid_from));
+
+        assert!(matches!(
+            store
+                .update_edge(Edge {
+                    relation: "WORKS_WITH".to_string(),
+                    ..edge
+                })
+                .await,
+            Err(GraphError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_neighbors_at_filters_by_validity() {
+        use chrono::TimeZone;
+
+        let store = MockGraphStore::new();
+        for id in ["alice", "acme", "globex"] {
+            store
+                .add_node(Node {
+                    id: id.to_string(),
+                    label: "Entity".to_string(),
+                    properties: serde_json::json!({}),
+                    partition_id: "personal".to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        let switched = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        for (target, valid_from, valid_to) in [
+            ("acme", None, Some(switched)),
+            ("globex", Some(switched), None),
+        ] {
+            store
+                .add_edge(Edge {
+                    source: "alice".to_string(),
+                    target: target.to_string(),
+                    relation: "works_at".to_string(),
+                    weight: 1.0,
+                    partition_id: "personal".to_string(),
+                    valid_from,
+                    valid_to,
+                    cross_partition: false,
+                })
+                .await
+                .unwrap();
+        }
+
+        let before = switched - chrono::Duration::days(1);
+        let neighbors = store
+            .get_neighbors_at("alice", Direction::Outgoing, before)
+            .await
+            .unwrap();
+        assert_eq!(neighbors.len(), 1);
+        assert_eq!(neighbors[0].1.id, "acme");
+
+        // valid_to is exclusive, valid_from inclusive
+        let neighbors = store
+            .get_neighbors_at("alice", Direction::Outgoing, switched)
+            .await
+            .unwrap();
+        assert_eq!(neighbors.len(), 1);
+        assert_eq!(neighbors[0].1.id, "globex");
+
+        // History is still there
+        assert_eq!(store.get_neighbors("alice").await.unwrap().len(), 2);
+    }
+
     #[tokio::test]
     async fn test_vector_operations() {
         let store = MockVectorStore::new();
 
         // Simple 2D vectors for testing
-        store.add_embedding("vec1", vec![1.0, 0.0]).await.unwrap();
-        store.add_embedding("vec2", vec![0.0, 1.0]).await.unwrap();
         store
-            .add_embedding("vec3", vec![0.707, 0.707])
+            .add_embedding("vec1", vec![1.0, 0.0], None)
+            .await
+            .unwrap();
+        store
+            .add_embedding("vec2", vec![0.0, 1.0], None)
+            .await
+            .unwrap();
+        store
+            .add_embedding("vec3", vec![0.707, 0.707], None)
             .await
             .unwrap(); // ~45 degrees
 
         let query = vec![1.0, 0.0];
-        let results = store.search(query, 3).await.unwrap();
+        let results = store.search(query, 3, None).await.unwrap();
 
         assert_eq!(results[0].0, "vec1");
         assert!((results[0].1 - 1.0).abs() < 0.001); // Exact match
@@ -288,4 +1999,45 @@ mod tests {
         assert_eq!(results[1].0, "vec3"); // Closer than vec2
         assert!((results[1].1 - 0.707).abs() < 0.001);
     }
+
+    #[tokio::test]
+    async fn test_named_embedding_fields() {
+        let store = MockVectorStore::new();
+
+        store
+            .add_embedding("doc", vec![1.0, 0.0], None)
+            .await
+            .unwrap();
+        store
+            .add_embedding("doc", vec![0.0, 1.0], Some("title"))
+            .await
+            .unwrap();
+        store
+            .add_embedding("other", vec![1.0, 0.0], Some("title"))
+            .await
+            .unwrap();
+
+        // Each field is searched on its own
+        let results = store.search(vec![1.0, 0.0], 5, None).await.unwrap();
+        assert_eq!(results.len(), 1);
+        let results = store
+            .search(vec![0.0, 1.0], 5, Some("title"))
+            .await
+            .unwrap();
+        assert_eq!(results[0].0, "doc");
+        assert_eq!(results.len(), 2);
+
+        // Removing an id drops it from every field
+        store.remove_embedding("doc").await.unwrap();
+        assert!(store
+            .search(vec![1.0, 0.0], 5, None)
+            .await
+            .unwrap()
+            .is_empty());
+        let results = store
+            .search(vec![0.0, 1.0], 5, Some("title"))
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+    }
 }
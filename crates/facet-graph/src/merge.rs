@@ -0,0 +1,145 @@
+//! Entity resolution
+//!
+//! Ingestion can create the same entity twice under different names, e.g.
+//! "Alice Smith" and "A. Smith". `merge_nodes` folds the duplicate into the
+//! node that is kept, so the rest of the graph only sees one of them.
+
+use crate::transaction::GraphTransaction;
+use crate::{merge_properties, Edge, GraphError, GraphStore, Node, VectorStore};
+use std::collections::BTreeMap;
+
+type EdgeKey = (String, String, String);
+
+fn edge_key(edge: &Edge) -> EdgeKey {
+    (
+        edge.source.clone(),
+        edge.relation.clone(),
+        edge.target.clone(),
+    )
+}
+
+/// Merges node `merge_id` into `keep_id` and returns the kept node.
+///
+/// - Properties are deep-merged with `merge_properties`; where both nodes
+///   set a key, the kept node's value wins. Label and partition are kept.
+/// - Edges of the merged node are moved onto the kept one. Edges between the
+///   two nodes are dropped. Where the kept node already has an edge with the
+///   same relation to the same node, the two become one edge with their
+///   weights summed.
+/// - Embeddings are combined per space: the mean of both vectors where both
+///   nodes have one of the same length, else whichever vector exists, the
+///   kept node's first.
+///
+/// Node and edge changes are committed in one transaction, which also
+/// deletes the merged node; the embeddings are written after it. Fails with
+/// `NotFound` if either node is missing and with `Conflict` if both ids are
+/// the same.
+pub async fn merge_nodes<S: GraphStore + VectorStore + ?Sized>(
+    store: &S,
+    keep_id: &str,
+    merge_id: &str,
+) -> Result<Node, GraphError> {
+    if keep_id == merge_id {
+        return Err(GraphError::Conflict(format!(
+            "Cannot merge node {} into itself",
+            keep_id
+        )));
+    }
+    let keep = store.get_node(keep_id).await?;
+    let merge = store.get_node(merge_id).await?;
+
+    let mut properties = merge.properties;
+    merge_properties(&mut properties, keep.properties);
+    let merged = Node { properties, ..keep };
+
+    // Edges of the merged node, pointed at the kept one. A self-loop shows
+    // up both ways, so incoming edges from the node itself are skipped.
+    let outgoing = store.get_neighbors(merge_id).await?;
+    let incoming = store.get_incoming_neighbors(merge_id).await?;
+    let mut moved: BTreeMap<EdgeKey, Edge> = BTreeMap::new();
+    for (mut edge, _) in outgoing
+        .into_iter()
+        .chain(incoming.into_iter().filter(|(e, _)| e.source != merge_id))
+    {
+        if edge.source == keep_id || edge.target == keep_id {
+            continue;
+        }
+        if edge.source == merge_id {
+            edge.source = keep_id.to_string();
+        }
+        if edge.target == merge_id {
+            edge.target = keep_id.to_string();
+        }
+        match moved.get_mut(&edge_key(&edge)) {
+            Some(existing) => existing.weight += edge.weight,
+            None => {
+                moved.insert(edge_key(&edge), edge);
+            }
+        }
+    }
+
+    let mut existing: BTreeMap<EdgeKey, Edge> = BTreeMap::new();
+    let kept_edges = store.get_neighbors(keep_id).await?.into_iter().chain(
+        store
+            .get_incoming_neighbors(keep_id)
+            .await?
+            .into_iter()
+            .filter(|(e, _)| e.source != keep_id),
+    );
+    for (edge, _) in kept_edges {
+        if edge.source == merge_id || edge.target == merge_id {
+            continue;
+        }
+        match existing.get_mut(&edge_key(&edge)) {
+            Some(first) => first.weight += edge.weight,
+            None => {
+                existing.insert(edge_key(&edge), edge);
+            }
+        }
+    }
+
+    // Kept edges that absorb a moved one are replaced by the combined edge
+    let mut tx = GraphTransaction::new();
+    let mut combined = Vec::with_capacity(moved.len());
+    for (key, edge) in moved {
+        match existing.remove(&key) {
+            Some(mut kept) => {
+                tx.delete_edge(&key.0, &key.1, &key.2);
+                kept.weight += edge.weight;
+                combined.push(kept);
+            }
+            None => combined.push(edge),
+        }
+    }
+    tx.update_node(merged.clone());
+    tx.delete_node(merge_id, true);
+    for edge in combined {
+        tx.add_edge(edge);
+    }
+
+    // Read before the commit, which removes the merged node's embeddings.
+    // All of the kept node's are written back, as updating a node may
+    // replace the record they are stored on.
+    let mut vectors = store.get_embeddings(keep_id).await?;
+    let merged_vectors = store.get_embeddings(merge_id).await?;
+    store.commit_transaction(tx).await?;
+
+    for embedding in merged_vectors {
+        match vectors.iter_mut().find(|e| e.field == embedding.field) {
+            Some(kept) if kept.vector.len() == embedding.vector.len() => {
+                for (a, b) in kept.vector.iter_mut().zip(&embedding.vector) {
+                    *a = (*a + b) / 2.0;
+                }
+            }
+            Some(_) => {}
+            None => vectors.push(embedding),
+        }
+    }
+    for embedding in vectors {
+        store
+            .add_embedding(keep_id, embedding.vector, embedding.field.as_deref())
+            .await?;
+    }
+
+    Ok(merged)
+}
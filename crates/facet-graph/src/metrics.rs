@@ -0,0 +1,805 @@
+//! Tracing and metrics for graph and vector stores
+//!
+//! `InstrumentedStore` wraps any store and runs every `GraphStore` and
+//! `VectorStore` call inside a `graph_store` tracing span. It also records
+//! the call's latency, the number of results it returned and whether it
+//! failed into a shared `StoreMetrics`. The server can render that with
+//! `StoreMetrics::to_prometheus` on its metrics endpoint.
+
+use crate::snapshot::GraphSnapshot;
+use crate::transaction::GraphTransaction;
+use crate::{
+    Direction, Edge, EdgeCost, GraphError, GraphPath, GraphStore, Node, NodeVersion, PageRequest,
+    PartitionInfo, StoredEmbedding, VectorStore,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::Instrument;
+
+/// Upper bounds of the latency buckets, in seconds
+pub const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+];
+
+/// Upper bounds of the result size buckets
+pub const RESULT_SIZE_BUCKETS: &[f64] = &[0.0, 1.0, 5.0, 10.0, 50.0, 100.0, 500.0, 1000.0];
+
+/// Observations sorted into fixed buckets
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Histogram {
+    /// Upper bound of each bucket; anything larger is only in `count`
+    pub bounds: &'static [f64],
+    /// Observations per bucket, not cumulative
+    pub buckets: Vec<u64>,
+    pub count: u64,
+    pub sum: f64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: vec![0; bounds.len()],
+            count: 0,
+            sum: 0.0,
+        }
+    }
+
+    pub fn observe(&mut self, value: f64) {
+        if let Some(i) = self.bounds.iter().position(|&bound| value <= bound) {
+            self.buckets[i] += 1;
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+}
+
+/// What was recorded for one store method
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OperationMetrics {
+    pub calls: u64,
+    pub errors: u64,
+    /// Seconds per call, failed calls included
+    pub latency: Histogram,
+    /// Items returned by successful calls of methods that return any
+    pub result_size: Histogram,
+}
+
+impl Default for OperationMetrics {
+    fn default() -> Self {
+        Self {
+            calls: 0,
+            errors: 0,
+            latency: Histogram::new(LATENCY_BUCKETS),
+            result_size: Histogram::new(RESULT_SIZE_BUCKETS),
+        }
+    }
+}
+
+impl OperationMetrics {
+    /// Share of calls that failed
+    pub fn error_rate(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.calls as f64
+        }
+    }
+}
+
+/// Metrics per store method, shared by every clone of an `InstrumentedStore`
+#[derive(Debug, Default)]
+pub struct StoreMetrics {
+    operations: Mutex<BTreeMap<&'static str, OperationMetrics>>,
+}
+
+impl StoreMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn record(
+        &self,
+        operation: &'static str,
+        elapsed: Duration,
+        outcome: Result<Option<usize>, ()>,
+    ) {
+        let mut operations = self.operations.lock().unwrap();
+        let metrics = operations.entry(operation).or_default();
+        metrics.calls += 1;
+        metrics.latency.observe(elapsed.as_secs_f64());
+        match outcome {
+            Ok(Some(size)) => metrics.result_size.observe(size as f64),
+            Ok(None) => {}
+            Err(()) => metrics.errors += 1,
+        }
+    }
+
+    /// Metrics of one method, by name (e.g. `get_neighbors`)
+    pub fn operation(&self, name: &str) -> Option<OperationMetrics> {
+        self.operations.lock().unwrap().get(name).cloned()
+    }
+
+    /// Metrics of every method called so far, by name
+    pub fn snapshot(&self) -> BTreeMap<String, OperationMetrics> {
+        self.operations
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, metrics)| (name.to_string(), metrics.clone()))
+            .collect()
+    }
+
+    pub fn reset(&self) {
+        self.operations.lock().unwrap().clear();
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let operations = self.snapshot();
+        let mut out = String::new();
+
+        out.push_str("# HELP facet_graph_calls_total Store calls by operation\n");
+        out.push_str("# TYPE facet_graph_calls_total counter\n");
+        for (op, metrics) in &operations {
+            let _ = writeln!(
+                out,
+                "facet_graph_calls_total{{op=\"{}\"}} {}",
+                op, metrics.calls
+            );
+        }
+        out.push_str("# HELP facet_graph_errors_total Failed store calls by operation\n");
+        out.push_str("# TYPE facet_graph_errors_total counter\n");
+        for (op, metrics) in &operations {
+            let _ = writeln!(
+                out,
+                "facet_graph_errors_total{{op=\"{}\"}} {}",
+                op, metrics.errors
+            );
+        }
+        write_histogram(
+            &mut out,
+            "facet_graph_latency_seconds",
+            "Store call latency by operation",
+            operations.iter().map(|(op, m)| (op, &m.latency)),
+        );
+        write_histogram(
+            &mut out,
+            "facet_graph_result_size",
+            "Items returned by store calls by operation",
+            operations.iter().map(|(op, m)| (op, &m.result_size)),
+        );
+        out
+    }
+}
+
+fn write_histogram<'a>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    histograms: impl Iterator<Item = (&'a String, &'a Histogram)>,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} histogram", name);
+    for (op, histogram) in histograms {
+        let mut cumulative = 0;
+        for (bound, count) in histogram.bounds.iter().zip(&histogram.buckets) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{op=\"{}\",le=\"{}\"}} {}",
+                name, op, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{op=\"{}\",le=\"+Inf\"}} {}",
+            name, op, histogram.count
+        );
+        let _ = writeln!(out, "{}_sum{{op=\"{}\"}} {}", name, op, histogram.sum);
+        let _ = writeln!(out, "{}_count{{op=\"{}\"}} {}", name, op, histogram.count);
+    }
+}
+
+/// Number of items in a call's result, where that is meaningful
+trait ResultSize {
+    fn result_size(&self) -> Option<usize>;
+}
+
+impl ResultSize for () {
+    fn result_size(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl ResultSize for bool {
+    fn result_size(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl ResultSize for usize {
+    fn result_size(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl ResultSize for f32 {
+    fn result_size(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl ResultSize for Node {
+    fn result_size(&self) -> Option<usize> {
+        Some(1)
+    }
+}
+
+impl<T> ResultSize for Vec<T> {
+    fn result_size(&self) -> Option<usize> {
+        Some(self.len())
+    }
+}
+
+impl ResultSize for Option<GraphPath> {
+    fn result_size(&self) -> Option<usize> {
+        Some(self.as_ref().map_or(0, |path| path.nodes.len()))
+    }
+}
+
+impl ResultSize for Option<(GraphPath, f32)> {
+    fn result_size(&self) -> Option<usize> {
+        Some(self.as_ref().map_or(0, |(path, _)| path.nodes.len()))
+    }
+}
+
+impl ResultSize for GraphSnapshot {
+    fn result_size(&self) -> Option<usize> {
+        Some(self.nodes.len())
+    }
+}
+
+/// A store that traces and measures every call and passes it on unchanged.
+///
+/// Calls slower than the slow-call threshold, if one is set, are also
+/// logged as warnings.
+#[derive(Clone)]
+pub struct InstrumentedStore<S> {
+    inner: S,
+    metrics: Arc<StoreMetrics>,
+    slow_threshold: Option<Duration>,
+}
+
+impl<S> InstrumentedStore<S> {
+    pub fn new(inner: S) -> Self {
+        Self::with_metrics(inner, Arc::new(StoreMetrics::new()))
+    }
+
+    /// Records into existing metrics, e.g. ones shared by several stores
+    pub fn with_metrics(inner: S, metrics: Arc<StoreMetrics>) -> Self {
+        Self {
+            inner,
+            metrics,
+            slow_threshold: None,
+        }
+    }
+
+    pub fn with_slow_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_threshold = Some(threshold);
+        self
+    }
+
+    pub fn metrics(&self) -> &Arc<StoreMetrics> {
+        &self.metrics
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    async fn observe<T: ResultSize>(
+        &self,
+        operation: &'static str,
+        call: impl Future<Output = Result<T, GraphError>>,
+    ) -> Result<T, GraphError> {
+        let span = tracing::debug_span!(
+            "graph_store",
+            operation,
+            results = tracing::field::Empty,
+            error = tracing::field::Empty,
+        );
+        let start = Instant::now();
+        let result = call.instrument(span.clone()).await;
+        let elapsed = start.elapsed();
+
+        let outcome = match &result {
+            Ok(value) => {
+                let size = value.result_size();
+                if let Some(size) = size {
+                    span.record("results", size);
+                }
+                Ok(size)
+            }
+            Err(e) => {
+                span.record("error", tracing::field::display(e));
+                Err(())
+            }
+        };
+        self.metrics.record(operation, elapsed, outcome);
+
+        let elapsed_ms = elapsed.as_secs_f64() * 1000.0;
+        if self.slow_threshold.is_some_and(|t| elapsed >= t) {
+            tracing::warn!(parent: &span, elapsed_ms, "slow graph store call");
+        } else {
+            tracing::debug!(parent: &span, elapsed_ms, "graph store call");
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl<S: GraphStore> GraphStore for InstrumentedStore<S> {
+    async fn add_node(&self, node: Node) -> Result<(), GraphError> {
+        self.observe("add_node", self.inner.add_node(node)).await
+    }
+
+    async fn add_edge(&self, edge: Edge) -> Result<(), GraphError> {
+        self.observe("add_edge", self.inner.add_edge(edge)).await
+    }
+
+    async fn get_node(&self, id: &str) -> Result<Node, GraphError> {
+        self.observe("get_node", self.inner.get_node(id)).await
+    }
+
+    async fn get_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.observe("get_neighbors", self.inner.get_neighbors(id))
+            .await
+    }
+
+    async fn get_incoming_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.observe(
+            "get_incoming_neighbors",
+            self.inner.get_incoming_neighbors(id),
+        )
+        .await
+    }
+
+    async fn get_neighbors_directed(
+        &self,
+        id: &str,
+        direction: Direction,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.observe(
+            "get_neighbors_directed",
+            self.inner.get_neighbors_directed(id, direction),
+        )
+        .await
+    }
+
+    async fn get_neighbors_at(
+        &self,
+        id: &str,
+        direction: Direction,
+        at: DateTime<Utc>,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.observe(
+            "get_neighbors_at",
+            self.inner.get_neighbors_at(id, direction, at),
+        )
+        .await
+    }
+
+    async fn update_node(&self, node: Node) -> Result<(), GraphError> {
+        self.observe("update_node", self.inner.update_node(node))
+            .await
+    }
+
+    async fn get_node_history(&self, id: &str) -> Result<Vec<NodeVersion>, GraphError> {
+        self.observe("get_node_history", self.inner.get_node_history(id))
+            .await
+    }
+
+    async fn restore_node_version(
+        &self,
+        id: &str,
+        recorded_at: DateTime<Utc>,
+    ) -> Result<(), GraphError> {
+        self.observe(
+            "restore_node_version",
+            self.inner.restore_node_version(id, recorded_at),
+        )
+        .await
+    }
+
+    async fn traverse(
+        &self,
+        id: &str,
+        depth: usize,
+        direction: Direction,
+        relation_filter: Option<&[&str]>,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.observe(
+            "traverse",
+            self.inner.traverse(id, depth, direction, relation_filter),
+        )
+        .await
+    }
+
+    async fn upsert_node(&self, node: Node) -> Result<(), GraphError> {
+        self.observe("upsert_node", self.inner.upsert_node(node))
+            .await
+    }
+
+    async fn patch_node(&self, id: &str, patch: serde_json::Value) -> Result<Node, GraphError> {
+        self.observe("patch_node", self.inner.patch_node(id, patch))
+            .await
+    }
+
+    async fn add_nodes(&self, nodes: Vec<Node>) -> Result<(), GraphError> {
+        self.observe("add_nodes", self.inner.add_nodes(nodes)).await
+    }
+
+    async fn add_edges(&self, edges: Vec<Edge>) -> Result<(), GraphError> {
+        self.observe("add_edges", self.inner.add_edges(edges)).await
+    }
+
+    async fn delete_node(&self, id: &str, cascade: bool) -> Result<(), GraphError> {
+        self.observe("delete_node", self.inner.delete_node(id, cascade))
+            .await
+    }
+
+    async fn delete_edge(
+        &self,
+        source: &str,
+        relation: &str,
+        target: &str,
+    ) -> Result<(), GraphError> {
+        self.observe(
+            "delete_edge",
+            self.inner.delete_edge(source, relation, target),
+        )
+        .await
+    }
+
+    async fn update_edge(&self, edge: Edge) -> Result<(), GraphError> {
+        self.observe("update_edge", self.inner.update_edge(edge))
+            .await
+    }
+
+    async fn increment_edge_weight(
+        &self,
+        source: &str,
+        relation: &str,
+        target: &str,
+        delta: f32,
+    ) -> Result<f32, GraphError> {
+        self.observe(
+            "increment_edge_weight",
+            self.inner
+                .increment_edge_weight(source, relation, target, delta),
+        )
+        .await
+    }
+
+    async fn shortest_path(
+        &self,
+        from: &str,
+        to: &str,
+        max_depth: usize,
+        partition_id: Option<&str>,
+    ) -> Result<Option<GraphPath>, GraphError> {
+        self.observe(
+            "shortest_path",
+            self.inner.shortest_path(from, to, max_depth, partition_id),
+        )
+        .await
+    }
+
+    async fn weighted_path(
+        &self,
+        from: &str,
+        to: &str,
+        cost: EdgeCost,
+        partition_id: Option<&str>,
+    ) -> Result<Option<(GraphPath, f32)>, GraphError> {
+        self.observe(
+            "weighted_path",
+            self.inner.weighted_path(from, to, cost, partition_id),
+        )
+        .await
+    }
+
+    async fn extract_subgraph(
+        &self,
+        seeds: &[String],
+        depth: usize,
+    ) -> Result<GraphSnapshot, GraphError> {
+        self.observe(
+            "extract_subgraph",
+            self.inner.extract_subgraph(seeds, depth),
+        )
+        .await
+    }
+
+    async fn commit_transaction(&self, tx: GraphTransaction) -> Result<(), GraphError> {
+        self.observe("commit_transaction", self.inner.commit_transaction(tx))
+            .await
+    }
+
+    async fn search_text(&self, query: &str, limit: usize) -> Result<Vec<(Node, f32)>, GraphError> {
+        self.observe("search_text", self.inner.search_text(query, limit))
+            .await
+    }
+
+    async fn query_by_partition(&self, partition_id: &str) -> Result<Vec<Node>, GraphError> {
+        self.observe(
+            "query_by_partition",
+            self.inner.query_by_partition(partition_id),
+        )
+        .await
+    }
+
+    async fn query_by_label(
+        &self,
+        label: &str,
+        partition_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Node>, GraphError> {
+        self.observe(
+            "query_by_label",
+            self.inner.query_by_label(label, partition_id, limit),
+        )
+        .await
+    }
+
+    async fn node_exists(&self, id: &str) -> Result<bool, GraphError> {
+        self.observe("node_exists", self.inner.node_exists(id))
+            .await
+    }
+
+    async fn count_nodes(&self, partition_id: Option<&str>) -> Result<usize, GraphError> {
+        self.observe("count_nodes", self.inner.count_nodes(partition_id))
+            .await
+    }
+
+    async fn count_edges(&self, relation: Option<&str>) -> Result<usize, GraphError> {
+        self.observe("count_edges", self.inner.count_edges(relation))
+            .await
+    }
+
+    async fn snapshot(&self, partition_id: Option<&str>) -> Result<GraphSnapshot, GraphError> {
+        self.observe("snapshot", self.inner.snapshot(partition_id))
+            .await
+    }
+
+    async fn query_by_partition_page(
+        &self,
+        partition_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Node>, GraphError> {
+        self.observe(
+            "query_by_partition_page",
+            self.inner.query_by_partition_page(partition_id, page),
+        )
+        .await
+    }
+
+    async fn get_neighbors_in_partition(
+        &self,
+        id: &str,
+        partition_id: &str,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.observe(
+            "get_neighbors_in_partition",
+            self.inner.get_neighbors_in_partition(id, partition_id),
+        )
+        .await
+    }
+
+    async fn get_neighbors_in_partition_directed(
+        &self,
+        id: &str,
+        partition_id: &str,
+        direction: Direction,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.observe(
+            "get_neighbors_in_partition_directed",
+            self.inner
+                .get_neighbors_in_partition_directed(id, partition_id, direction),
+        )
+        .await
+    }
+
+    async fn create_partition(&self, partition_id: &str) -> Result<(), GraphError> {
+        self.observe(
+            "create_partition",
+            self.inner.create_partition(partition_id),
+        )
+        .await
+    }
+
+    async fn list_partitions(&self) -> Result<Vec<PartitionInfo>, GraphError> {
+        self.observe("list_partitions", self.inner.list_partitions())
+            .await
+    }
+
+    async fn rename_partition(&self, from: &str, to: &str) -> Result<(), GraphError> {
+        self.observe("rename_partition", self.inner.rename_partition(from, to))
+            .await
+    }
+
+    async fn delete_partition(&self, partition_id: &str, cascade: bool) -> Result<(), GraphError> {
+        self.observe(
+            "delete_partition",
+            self.inner.delete_partition(partition_id, cascade),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl<S: VectorStore> VectorStore for InstrumentedStore<S> {
+    async fn add_embedding(
+        &self,
+        id: &str,
+        vector: Vec<f32>,
+        field: Option<&str>,
+    ) -> Result<(), GraphError> {
+        self.observe("add_embedding", self.inner.add_embedding(id, vector, field))
+            .await
+    }
+
+    async fn search(
+        &self,
+        vector: Vec<f32>,
+        limit: usize,
+        field: Option<&str>,
+    ) -> Result<Vec<(String, f32)>, GraphError> {
+        self.observe("search", self.inner.search(vector, limit, field))
+            .await
+    }
+
+    async fn add_embeddings(
+        &self,
+        embeddings: Vec<(String, Vec<f32>)>,
+        field: Option<&str>,
+    ) -> Result<(), GraphError> {
+        self.observe(
+            "add_embeddings",
+            self.inner.add_embeddings(embeddings, field),
+        )
+        .await
+    }
+
+    async fn remove_embedding(&self, id: &str) -> Result<(), GraphError> {
+        self.observe("remove_embedding", self.inner.remove_embedding(id))
+            .await
+    }
+
+    async fn clear_embeddings(&self, partition_id: &str) -> Result<(), GraphError> {
+        self.observe(
+            "clear_embeddings",
+            self.inner.clear_embeddings(partition_id),
+        )
+        .await
+    }
+
+    async fn search_nodes(
+        &self,
+        vector: Vec<f32>,
+        limit: usize,
+        field: Option<&str>,
+    ) -> Result<Vec<(Node, f32)>, GraphError> {
+        self.observe(
+            "search_nodes",
+            self.inner.search_nodes(vector, limit, field),
+        )
+        .await
+    }
+
+    async fn get_embeddings(&self, id: &str) -> Result<Vec<StoredEmbedding>, GraphError> {
+        self.observe("get_embeddings", self.inner.get_embeddings(id))
+            .await
+    }
+
+    async fn list_embeddings(&self) -> Result<Vec<StoredEmbedding>, GraphError> {
+        self.observe("list_embeddings", self.inner.list_embeddings())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mocks::{MockGraphStore, MockVectorStore};
+    use serde_json::json;
+
+    fn node(id: &str) -> Node {
+        Node {
+            id: id.to_string(),
+            label: "Person".to_string(),
+            properties: json!({}),
+            partition_id: "personal".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_histogram_buckets() {
+        let mut histogram = Histogram::new(&[1.0, 10.0]);
+        for value in [0.5, 1.0, 7.0, 12.0] {
+            histogram.observe(value);
+        }
+        assert_eq!(histogram.buckets, [2, 1]);
+        assert_eq!(histogram.count, 4);
+        assert_eq!(histogram.sum, 20.5);
+    }
+
+    #[tokio::test]
+    async fn test_records_calls_errors_and_sizes() {
+        let store = InstrumentedStore::new(MockGraphStore::new());
+        let metrics = store.metrics().clone();
+        for id in ["a", "b", "c"] {
+            store.add_node(node(id)).await.unwrap();
+        }
+        store.get_node("a").await.unwrap();
+        assert!(store.get_node("missing").await.is_err());
+        assert_eq!(store.query_by_partition("personal").await.unwrap().len(), 3);
+
+        let add = metrics.operation("add_node").unwrap();
+        assert_eq!((add.calls, add.errors), (3, 0));
+        assert_eq!(add.latency.count, 3);
+        assert_eq!(add.result_size.count, 0);
+
+        let get = metrics.operation("get_node").unwrap();
+        assert_eq!((get.calls, get.errors), (2, 1));
+        assert_eq!(get.error_rate(), 0.5);
+        assert_eq!(get.result_size.count, 1);
+
+        let scan = metrics.operation("query_by_partition").unwrap();
+        assert_eq!(scan.result_size.sum, 3.0);
+        assert!(metrics.operation("get_neighbors").is_none());
+
+        // Stores built on the same metrics add to them
+        let other = InstrumentedStore::with_metrics(MockGraphStore::new(), metrics.clone());
+        assert!(other.get_node("a").await.is_err());
+        assert_eq!(metrics.operation("get_node").unwrap().errors, 2);
+
+        metrics.reset();
+        assert!(metrics.snapshot().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_vector_calls_and_prometheus_text() {
+        let store = InstrumentedStore::new(MockVectorStore::new());
+        store
+            .add_embedding("a", vec![1.0, 0.0], None)
+            .await
+            .unwrap();
+        store
+            .add_embedding("b", vec![0.0, 1.0], None)
+            .await
+            .unwrap();
+        let hits = store.search(vec![1.0, 0.0], 5, None).await.unwrap();
+        assert_eq!(hits.len(), 2);
+
+        let text = store.metrics().to_prometheus();
+        assert!(text.contains("# TYPE facet_graph_calls_total counter"));
+        assert!(text.contains("facet_graph_calls_total{op=\"add_embedding\"} 2"));
+        assert!(text.contains("facet_graph_errors_total{op=\"search\"} 0"));
+        assert!(text.contains("facet_graph_latency_seconds_count{op=\"search\"} 1"));
+        assert!(text.contains("facet_graph_result_size_bucket{op=\"search\",le=\"1\"} 0"));
+        assert!(text.contains("facet_graph_result_size_bucket{op=\"search\",le=\"5\"} 1"));
+        assert!(text.contains("facet_graph_result_size_bucket{op=\"search\",le=\"+Inf\"} 1"));
+    }
+}
@@ -0,0 +1,149 @@
+//! Data migrations for `SurrealStore`
+//!
+//! The database records the version of the last migration applied to it in
+//! `meta:schema`. Opening a store runs every newer step in `MIGRATIONS`, in
+//! order, each in its own transaction together with the version bump, so a
+//! failed step leaves the database at the previous version.
+//!
+//! To change how records are stored, append a step with the next version;
+//! never edit or reorder steps that have shipped.
+
+use crate::surreal_query::SurrealQuery;
+use crate::GraphError;
+use serde::Deserialize;
+use surrealdb::engine::local::Db;
+use surrealdb::sql::Thing;
+use surrealdb::Surreal;
+
+/// One ordered change to the stored data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    /// SurrealQL statements, run inside the migration's transaction
+    pub sql: &'static str,
+}
+
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "Default the partition and properties of nodes written before they existed",
+    sql: "UPDATE node SET partition_id = 'personal' WHERE partition_id = NONE RETURN NONE; \
+          UPDATE node SET properties = {} WHERE properties = NONE RETURN NONE;",
+}];
+
+/// Version a fully migrated database is at
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+fn schema_thing() -> Thing {
+    Thing::from(("meta", "schema"))
+}
+
+/// Version recorded in the database; 0 if no migration has run yet
+pub(crate) async fn current_version(db: &Surreal<Db>) -> Result<u32, GraphError> {
+    #[derive(Deserialize)]
+    struct SchemaRow {
+        version: u32,
+    }
+
+    let row: Option<SchemaRow> = db
+        .select(("meta", "schema"))
+        .await
+        .map_err(|e| GraphError::Storage(e.to_string()))?;
+    Ok(row.map_or(0, |r| r.version))
+}
+
+/// Applies the steps newer than the database's version and returns the
+/// version it ends at. Fails without changes if the database was written by
+/// a newer build.
+pub(crate) async fn run(db: &Surreal<Db>, migrations: &[Migration]) -> Result<u32, GraphError> {
+    let current = current_version(db).await?;
+    let latest = migrations.last().map_or(0, |m| m.version);
+    if current > latest {
+        return Err(GraphError::Storage(format!(
+            "Database schema version {} is newer than this build supports ({})",
+            current, latest
+        )));
+    }
+
+    let mut version = current;
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        SurrealQuery::new("BEGIN TRANSACTION; ")
+            .push(migration.sql)
+            .push(" UPSERT $schema SET version = $version RETURN NONE; COMMIT TRANSACTION;")
+            .bind("schema", schema_thing())
+            .bind("version", migration.version)
+            .send(db)
+            .await?
+            .check()
+            .map_err(|e| {
+                GraphError::Storage(format!(
+                    "Migration {} ({}) failed: {}",
+                    migration.version, migration.description, e
+                ))
+            })?;
+        version = migration.version;
+    }
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surreal_store::SurrealStore;
+    use crate::GraphStore;
+    use serde_json::json;
+
+    #[test]
+    fn test_migrations_are_ordered() {
+        assert!(MIGRATIONS.windows(2).all(|w| w[0].version < w[1].version));
+        assert_eq!(latest_version(), MIGRATIONS.len() as u32);
+    }
+
+    #[tokio::test]
+    async fn test_open_migrates_legacy_nodes() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SurrealStore::new(dir.path().join("test.db")).await.unwrap();
+        let db = store.db();
+        assert_eq!(store.schema_version().await.unwrap(), latest_version());
+
+        // A node as written before partitions, on a database at version 0
+        db.query("CREATE node:old SET label = 'Person'; DELETE meta:schema;")
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        assert!(store.get_node("old").await.is_err());
+
+        assert_eq!(run(db, MIGRATIONS).await.unwrap(), latest_version());
+        let node = store.get_node("old").await.unwrap();
+        assert_eq!(node.partition_id, "personal");
+        assert_eq!(node.properties, json!({}));
+    }
+
+    #[tokio::test]
+    async fn test_failed_step_keeps_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SurrealStore::new(dir.path().join("test.db")).await.unwrap();
+        let db = store.db();
+        let steps = [
+            MIGRATIONS[0],
+            Migration {
+                version: 2,
+                description: "broken",
+                sql: "THROW 'boom';",
+            },
+        ];
+
+        assert!(run(db, &steps).await.is_err());
+        assert_eq!(current_version(db).await.unwrap(), 1);
+
+        db.query("UPSERT meta:schema SET version = 9;")
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+        assert!(run(db, MIGRATIONS).await.is_err());
+    }
+}
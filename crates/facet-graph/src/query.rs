@@ -1,6 +1,27 @@
 use crate::ephemeral_graph::EphemeralGraph;
 use crate::{GraphError, GraphStore, Node, VectorStore};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+
+/// How `hybrid_search` combines the full-text and vector rankings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fusion {
+    /// Reciprocal rank fusion: a hit at rank `r` (from 1) in either list
+    /// adds `1 / (k + r)`. Ignores raw scores, so BM25 and cosine need no
+    /// calibration against each other.
+    Rrf { k: f32 },
+    /// Weighted sum of min-max normalised scores, with `text_weight` in
+    /// `0.0..=1.0` and the rest going to the vector score
+    Weighted { text_weight: f32 },
+}
+
+impl Default for Fusion {
+    fn default() -> Self {
+        Fusion::Rrf { k: 60.0 }
+    }
+}
+
+/// Each side fetches this many times `limit` candidates before fusing
+const CANDIDATE_FACTOR: usize = 2;
 
 pub struct GraphQuery<S: GraphStore + VectorStore> {
     store: S,
@@ -17,7 +38,7 @@ impl<S: GraphStore + VectorStore> GraphQuery<S> {
         limit: usize,
     ) -> Result<Vec<Node>, GraphError> {
         // 1. Vector Search to get entry points
-        let initial_results = self.store.search(query_vector, limit).await?;
+        let initial_results = self.store.search(query_vector, limit, None).await?;
 
         let mut visited = HashSet::new();
         let mut subgraph_nodes = Vec::new();
@@ -54,13 +75,128 @@ impl<S: GraphStore + VectorStore> GraphQuery<S> {
         // In v1.0, we would run PageRank or Community Detection here
         Ok(subgraph_nodes)
     }
+
+    /// Runs full-text and vector search and fuses both rankings with
+    /// reciprocal rank fusion, so exact name matches surface even when
+    /// their embedding is not the closest.
+    pub async fn hybrid_search(
+        &self,
+        query_text: &str,
+        query_vector: Vec<f32>,
+        limit: usize,
+    ) -> Result<Vec<(Node, f32)>, GraphError> {
+        self.hybrid_search_with(query_text, query_vector, limit, Fusion::default())
+            .await
+    }
+
+    pub async fn hybrid_search_with(
+        &self,
+        query_text: &str,
+        query_vector: Vec<f32>,
+        limit: usize,
+        fusion: Fusion,
+    ) -> Result<Vec<(Node, f32)>, GraphError> {
+        let candidates = limit.saturating_mul(CANDIDATE_FACTOR);
+        let text_hits = self.store.search_text(query_text, candidates).await?;
+        let vector_hits = self.store.search(query_vector, candidates, None).await?;
+
+        let text_ranking: Vec<(String, f32)> = text_hits
+            .iter()
+            .map(|(node, score)| (node.id.clone(), *score))
+            .collect();
+        let mut nodes: HashMap<String, Node> = text_hits
+            .into_iter()
+            .map(|(node, _)| (node.id.clone(), node))
+            .collect();
+
+        let mut results = Vec::new();
+        for (id, score) in fuse(&text_ranking, &vector_hits, fusion) {
+            if results.len() == limit {
+                break;
+            }
+            let node = match nodes.remove(&id) {
+                Some(node) => node,
+                // Embeddings can outlive their node
+                None => match self.store.get_node(&id).await {
+                    Ok(node) => node,
+                    Err(GraphError::NotFound(_)) => continue,
+                    Err(e) => return Err(e),
+                },
+            };
+            results.push((node, score));
+        }
+        Ok(results)
+    }
+}
+
+/// Merges two rankings (best first) into one, best first. Ties are broken
+/// by id so the order is stable.
+pub fn fuse(
+    text: &[(String, f32)],
+    vector: &[(String, f32)],
+    fusion: Fusion,
+) -> Vec<(String, f32)> {
+    let mut scores: HashMap<&str, f32> = HashMap::new();
+
+    match fusion {
+        Fusion::Rrf { k } => {
+            for ranking in [text, vector] {
+                for (rank, (id, _)) in ranking.iter().enumerate() {
+                    *scores.entry(id.as_str()).or_default() += 1.0 / (k + rank as f32 + 1.0);
+                }
+            }
+        }
+        Fusion::Weighted { text_weight } => {
+            let text_weight = text_weight.clamp(0.0, 1.0);
+            for (ranking, weight) in [(text, text_weight), (vector, 1.0 - text_weight)] {
+                for (id, score) in normalize(ranking) {
+                    *scores.entry(id).or_default() += weight * score;
+                }
+            }
+        }
+    }
+
+    let mut fused: Vec<(String, f32)> = scores
+        .into_iter()
+        .map(|(id, score)| (id.to_string(), score))
+        .collect();
+    fused.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.0.cmp(&b.0))
+    });
+    fused
+}
+
+/// Min-max scales scores to `0.0..=1.0`; a list of equal scores maps to 1.0
+fn normalize(ranking: &[(String, f32)]) -> Vec<(&str, f32)> {
+    let min = ranking
+        .iter()
+        .map(|(_, s)| *s)
+        .fold(f32::INFINITY, f32::min);
+    let max = ranking
+        .iter()
+        .map(|(_, s)| *s)
+        .fold(f32::NEG_INFINITY, f32::max);
+    ranking
+        .iter()
+        .map(|(id, score)| {
+            let scaled = if max > min {
+                (score - min) / (max - min)
+            } else {
+                1.0
+            };
+            (id.as_str(), scaled)
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::mocks::{MockGraphStore, MockVectorStore};
-    use crate::Edge;
+    use crate::snapshot::GraphSnapshot;
+    use crate::{Direction, Edge, PageRequest};
     use async_trait::async_trait;
 
     // Combined mock for testing
@@ -92,14 +228,59 @@ mod tests {
         async fn get_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
             self.graph.get_neighbors(id).await
         }
+        async fn get_incoming_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+            self.graph.get_incoming_neighbors(id).await
+        }
+        async fn traverse(
+            &self,
+            id: &str,
+            depth: usize,
+            direction: Direction,
+            relation_filter: Option<&[&str]>,
+        ) -> Result<Vec<(Edge, Node)>, GraphError> {
+            self.graph
+                .traverse(id, depth, direction, relation_filter)
+                .await
+        }
         async fn update_node(&self, node: Node) -> Result<(), GraphError> {
             self.graph.update_node(node).await
         }
+        async fn delete_node(&self, id: &str, cascade: bool) -> Result<(), GraphError> {
+            self.graph.delete_node(id, cascade).await
+        }
+        async fn delete_edge(
+            &self,
+            source: &str,
+            relation: &str,
+            target: &str,
+        ) -> Result<(), GraphError> {
+            self.graph.delete_edge(source, relation, target).await
+        }
+
+        async fn search_text(
+            &self,
+            query: &str,
+            limit: usize,
+        ) -> Result<Vec<(Node, f32)>, GraphError> {
+            self.graph.search_text(query, limit).await
+        }
 
         async fn query_by_partition(&self, partition_id: &str) -> Result<Vec<Node>, GraphError> {
             self.graph.query_by_partition(partition_id).await
         }
 
+        async fn snapshot(&self, partition_id: Option<&str>) -> Result<GraphSnapshot, GraphError> {
+            self.graph.snapshot(partition_id).await
+        }
+
+        async fn query_by_partition_page(
+            &self,
+            partition_id: &str,
+            page: PageRequest,
+        ) -> Result<Vec<Node>, GraphError> {
+            self.graph.query_by_partition_page(partition_id, page).await
+        }
+
         async fn get_neighbors_in_partition(
             &self,
             id: &str,
@@ -111,15 +292,30 @@ mod tests {
 
     #[async_trait]
     impl VectorStore for MockStore {
-        async fn add_embedding(&self, id: &str, vector: Vec<f32>) -> Result<(), GraphError> {
-            self.vector.add_embedding(id, vector).await
+        async fn add_embedding(
+            &self,
+            id: &str,
+            vector: Vec<f32>,
+            field: Option<&str>,
+        ) -> Result<(), GraphError> {
+            self.vector.add_embedding(id, vector, field).await
         }
         async fn search(
             &self,
             vector: Vec<f32>,
             limit: usize,
+            field: Option<&str>,
         ) -> Result<Vec<(String, f32)>, GraphError> {
-            self.vector.search(vector, limit).await
+            self.vector.search(vector, limit, field).await
+        }
+        async fn remove_embedding(&self, id: &str) -> Result<(), GraphError> {
+            self.vector.remove_embedding(id).await
+        }
+        async fn clear_embeddings(&self, partition_id: &str) -> Result<(), GraphError> {
+            for node in self.graph.query_by_partition(partition_id).await? {
+                self.vector.remove_embedding(&node.id).await?;
+            }
+            Ok(())
         }
     }
 
@@ -144,7 +340,10 @@ mod tests {
         store.add_node(node2.clone()).await.unwrap();
 
         // Mock vector search result
-        store.add_embedding("1", vec![1.0, 0.0]).await.unwrap();
+        store
+            .add_embedding("1", vec![1.0, 0.0], None)
+            .await
+            .unwrap();
 
         let query = GraphQuery::new(store);
         let results = query.search(vec![1.0, 0.0], 1).await.unwrap();
@@ -152,4 +351,78 @@ mod tests {
         assert!(!results.is_empty());
         assert_eq!(results[0].id, "1");
     }
+
+    fn ranking(ids: &[(&str, f32)]) -> Vec<(String, f32)> {
+        ids.iter().map(|(id, s)| (id.to_string(), *s)).collect()
+    }
+
+    #[test]
+    fn test_fuse_rrf_rewards_agreement() {
+        let text = ranking(&[("a", 12.0), ("b", 3.0)]);
+        let vector = ranking(&[("c", 0.9), ("b", 0.8), ("a", 0.1)]);
+
+        let fused = fuse(&text, &vector, Fusion::default());
+        let ids: Vec<&str> = fused.iter().map(|(id, _)| id.as_str()).collect();
+        // a: 1/61 + 1/63, b: 1/62 + 1/62, c: 1/61
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert!((fused[0].1 - (1.0 / 61.0 + 1.0 / 63.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fuse_weighted_uses_normalized_scores() {
+        let text = ranking(&[("a", 10.0), ("b", 5.0)]);
+        let vector = ranking(&[("b", 0.9), ("a", 0.5)]);
+
+        let fused = fuse(&text, &vector, Fusion::Weighted { text_weight: 0.8 });
+        assert_eq!(fused[0].0, "a");
+        assert!((fused[0].1 - 0.8).abs() < 1e-6);
+
+        let fused = fuse(&text, &vector, Fusion::Weighted { text_weight: 0.2 });
+        assert_eq!(fused[0].0, "b");
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search_surfaces_exact_name_match() {
+        let store = MockStore::new();
+        for (id, name) in [("alice", "Alice Smith"), ("bob", "Bob"), ("carol", "Carol")] {
+            store
+                .add_node(Node {
+                    id: id.to_string(),
+                    label: "Person".to_string(),
+                    properties: serde_json::json!({"name": name}),
+                    partition_id: "default".to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        store
+            .add_embedding("bob", vec![1.0, 0.0], None)
+            .await
+            .unwrap();
+        store
+            .add_embedding("carol", vec![0.9, 0.1], None)
+            .await
+            .unwrap();
+        store
+            .add_embedding("alice", vec![0.0, 1.0], None)
+            .await
+            .unwrap();
+        // An embedding whose node was deleted is skipped
+        store
+            .add_embedding("gone", vec![1.0, 0.0], None)
+            .await
+            .unwrap();
+
+        let query = GraphQuery::new(store);
+
+        // Pure vector search would rank alice last
+        let results = query
+            .hybrid_search("alice", vec![1.0, 0.0], 2)
+            .await
+            .unwrap();
+        let ids: Vec<&str> = results.iter().map(|(n, _)| n.id.as_str()).collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&"alice"));
+        assert!(!ids.contains(&"gone"));
+    }
 }
@@ -0,0 +1,510 @@
+//! GraphRAG retrieval
+//!
+//! `Retriever` runs the whole retrieval step for a question: embed it, take
+//! the closest nodes by vector search as seeds, expand a few hops around
+//! them, and return the nodes found as ranked context chunks. Each chunk
+//! records which seed it was reached from and along which edges, so answers
+//! can cite where their context came from.
+
+use crate::ingest::IngestionPipeline;
+use crate::{Direction, Edge, GraphError, GraphStore, Node, VectorStore};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Turns query text into a vector in the same space as the stored
+/// embeddings
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, GraphError>;
+}
+
+#[async_trait]
+impl<S: GraphStore + VectorStore> Embedder for IngestionPipeline<S> {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, GraphError> {
+        self.embed_text(text).await
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetrieverConfig {
+    /// Vector hits used as seeds
+    pub seeds: usize,
+    /// How far to expand around each seed
+    pub hops: usize,
+    /// Edge direction followed when expanding
+    pub direction: Direction,
+    /// A node `n` hops from its seed scores `similarity * hop_decay^n`
+    pub hop_decay: f32,
+    /// Chunks returned at most
+    pub limit: usize,
+    /// Embedding space searched; `None` is the default space
+    pub field: Option<String>,
+    /// Only seeds and neighbours in this partition are used
+    pub partition_id: Option<String>,
+}
+
+impl Default for RetrieverConfig {
+    fn default() -> Self {
+        Self {
+            seeds: 5,
+            hops: 1,
+            direction: Direction::Both,
+            hop_decay: 0.5,
+            limit: 10,
+            field: None,
+            partition_id: None,
+        }
+    }
+}
+
+/// Where a chunk came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Id of the vector hit the chunk was reached from
+    pub seed: String,
+    /// Similarity of that seed to the query
+    pub similarity: f32,
+    /// Edges walked from the seed to the chunk's node; empty for a seed
+    pub path: Vec<Edge>,
+}
+
+impl Provenance {
+    pub fn hops(&self) -> usize {
+        self.path.len()
+    }
+}
+
+/// One ranked piece of context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextChunk {
+    pub node: Node,
+    /// The node as prompt text, see `chunk_text`
+    pub text: String,
+    pub score: f32,
+    pub provenance: Provenance,
+}
+
+pub struct Retriever<S: GraphStore + VectorStore> {
+    store: S,
+    config: RetrieverConfig,
+}
+
+impl<S: GraphStore + VectorStore> Retriever<S> {
+    pub fn new(store: S) -> Self {
+        Self::with_config(store, RetrieverConfig::default())
+    }
+
+    pub fn with_config(store: S, config: RetrieverConfig) -> Self {
+        Self { store, config }
+    }
+
+    pub fn config(&self) -> &RetrieverConfig {
+        &self.config
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Embeds `query` with `embedder` and retrieves context for it
+    pub async fn retrieve<E: Embedder + ?Sized>(
+        &self,
+        embedder: &E,
+        query: &str,
+    ) -> Result<Vec<ContextChunk>, GraphError> {
+        let vector = embedder.embed(query).await?;
+        self.retrieve_vector(vector).await
+    }
+
+    /// Retrieves context for an already embedded query. Every node appears
+    /// once, under its best score; chunks are ordered best first, ties by
+    /// id.
+    pub async fn retrieve_vector(
+        &self,
+        query_vector: Vec<f32>,
+    ) -> Result<Vec<ContextChunk>, GraphError> {
+        let hits = self
+            .store
+            .search(
+                query_vector,
+                self.config.seeds,
+                self.config.field.as_deref(),
+            )
+            .await?;
+
+        let mut best: HashMap<String, ContextChunk> = HashMap::new();
+        for (id, similarity) in hits {
+            let seed = match self.store.get_node(&id).await {
+                Ok(node) => node,
+                // Embeddings can outlive their node
+                Err(GraphError::NotFound(_)) => continue,
+                Err(e) => return Err(e),
+            };
+            if !self.in_scope(&seed) {
+                continue;
+            }
+
+            let mut reached = vec![(seed, Vec::new())];
+            reached.extend(self.expand(&id).await?);
+            for (node, path) in reached {
+                let score = similarity * self.config.hop_decay.powi(path.len() as i32);
+                if best.get(&node.id).is_some_and(|c| c.score >= score) {
+                    continue;
+                }
+                let chunk = ContextChunk {
+                    text: chunk_text(&node),
+                    score,
+                    provenance: Provenance {
+                        seed: id.clone(),
+                        similarity,
+                        path,
+                    },
+                    node,
+                };
+                best.insert(chunk.node.id.clone(), chunk);
+            }
+        }
+
+        let mut chunks: Vec<ContextChunk> = best.into_values().collect();
+        chunks.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.node.id.cmp(&b.node.id))
+        });
+        chunks.truncate(self.config.limit);
+        Ok(chunks)
+    }
+
+    fn in_scope(&self, node: &Node) -> bool {
+        self.config
+            .partition_id
+            .as_deref()
+            .is_none_or(|p| node.partition_id == p)
+    }
+
+    /// Nodes within `hops` of `seed`, each with the edges of the first path
+    /// found to it, nearest first
+    async fn expand(&self, seed: &str) -> Result<Vec<(Node, Vec<Edge>)>, GraphError> {
+        let mut seen = HashSet::from([seed.to_string()]);
+        let mut frontier = vec![(seed.to_string(), Vec::new())];
+        let mut reached = Vec::new();
+        for _ in 0..self.config.hops {
+            let mut next = Vec::new();
+            for (id, path) in frontier {
+                let neighbors = match &self.config.partition_id {
+                    Some(partition_id) => {
+                        self.store
+                            .get_neighbors_in_partition_directed(
+                                &id,
+                                partition_id,
+                                self.config.direction,
+                            )
+                            .await?
+                    }
+                    None => {
+                        self.store
+                            .get_neighbors_directed(&id, self.config.direction)
+                            .await?
+                    }
+                };
+                for (edge, node) in neighbors {
+                    if !seen.insert(node.id.clone()) {
+                        continue;
+                    }
+                    let mut path: Vec<Edge> = path.clone();
+                    path.push(edge);
+                    next.push((node.id.clone(), path.clone()));
+                    reached.push((node, path));
+                }
+            }
+            frontier = next;
+        }
+        Ok(reached)
+    }
+}
+
+/// A node as prompt text: its `content` or `text` property if it has one,
+/// else its label followed by its string properties
+pub fn chunk_text(node: &Node) -> String {
+    for key in ["content", "text"] {
+        if let Some(text) = node.properties.get(key).and_then(|v| v.as_str()) {
+            return text.to_string();
+        }
+    }
+    let values: Vec<&str> = node
+        .properties
+        .as_object()
+        .into_iter()
+        .flat_map(|properties| properties.values())
+        .filter_map(|v| v.as_str())
+        .collect();
+    if values.is_empty() {
+        node.label.clone()
+    } else {
+        format!("{}: {}", node.label, values.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mocks::{MockGraphStore, MockVectorStore};
+    use crate::snapshot::GraphSnapshot;
+    use crate::PageRequest;
+    use serde_json::json;
+
+    // Combined mock for testing
+    struct MockStore {
+        graph: MockGraphStore,
+        vector: MockVectorStore,
+    }
+
+    impl MockStore {
+        fn new() -> Self {
+            Self {
+                graph: MockGraphStore::new(),
+                vector: MockVectorStore::new(),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl GraphStore for MockStore {
+        async fn add_node(&self, node: Node) -> Result<(), GraphError> {
+            self.graph.add_node(node).await
+        }
+        async fn add_edge(&self, edge: Edge) -> Result<(), GraphError> {
+            self.graph.add_edge(edge).await
+        }
+        async fn get_node(&self, id: &str) -> Result<Node, GraphError> {
+            self.graph.get_node(id).await
+        }
+        async fn get_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+            self.graph.get_neighbors(id).await
+        }
+        async fn get_incoming_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+            self.graph.get_incoming_neighbors(id).await
+        }
+        async fn traverse(
+            &self,
+            id: &str,
+            depth: usize,
+            direction: Direction,
+            relation_filter: Option<&[&str]>,
+        ) -> Result<Vec<(Edge, Node)>, GraphError> {
+            self.graph
+                .traverse(id, depth, direction, relation_filter)
+                .await
+        }
+        async fn update_node(&self, node: Node) -> Result<(), GraphError> {
+            self.graph.update_node(node).await
+        }
+        async fn delete_node(&self, id: &str, cascade: bool) -> Result<(), GraphError> {
+            self.graph.delete_node(id, cascade).await
+        }
+        async fn delete_edge(
+            &self,
+            source: &str,
+            relation: &str,
+            target: &str,
+        ) -> Result<(), GraphError> {
+            self.graph.delete_edge(source, relation, target).await
+        }
+        async fn search_text(
+            &self,
+            query: &str,
+            limit: usize,
+        ) -> Result<Vec<(Node, f32)>, GraphError> {
+            self.graph.search_text(query, limit).await
+        }
+        async fn query_by_partition(&self, partition_id: &str) -> Result<Vec<Node>, GraphError> {
+            self.graph.query_by_partition(partition_id).await
+        }
+        async fn snapshot(&self, partition_id: Option<&str>) -> Result<GraphSnapshot, GraphError> {
+            self.graph.snapshot(partition_id).await
+        }
+        async fn query_by_partition_page(
+            &self,
+            partition_id: &str,
+            page: PageRequest,
+        ) -> Result<Vec<Node>, GraphError> {
+            self.graph.query_by_partition_page(partition_id, page).await
+        }
+        async fn get_neighbors_in_partition(
+            &self,
+            id: &str,
+            partition_id: &str,
+        ) -> Result<Vec<(Edge, Node)>, GraphError> {
+            self.graph
+                .get_neighbors_in_partition(id, partition_id)
+                .await
+        }
+    }
+
+    #[async_trait]
+    impl VectorStore for MockStore {
+        async fn add_embedding(
+            &self,
+            id: &str,
+            vector: Vec<f32>,
+            field: Option<&str>,
+        ) -> Result<(), GraphError> {
+            self.vector.add_embedding(id, vector, field).await
+        }
+        async fn search(
+            &self,
+            vector: Vec<f32>,
+            limit: usize,
+            field: Option<&str>,
+        ) -> Result<Vec<(String, f32)>, GraphError> {
+            self.vector.search(vector, limit, field).await
+        }
+        async fn remove_embedding(&self, id: &str) -> Result<(), GraphError> {
+            self.vector.remove_embedding(id).await
+        }
+        async fn clear_embeddings(&self, _partition_id: &str) -> Result<(), GraphError> {
+            Ok(())
+        }
+    }
+
+    /// Embeds every query as the same fixed vector
+    struct FixedEmbedder(Vec<f32>);
+
+    #[async_trait]
+    impl Embedder for FixedEmbedder {
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>, GraphError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn edge(source: &str, relation: &str, target: &str, partition: &str) -> Edge {
+        Edge {
+            source: source.to_string(),
+            target: target.to_string(),
+            relation: relation.to_string(),
+            weight: 1.0,
+            partition_id: partition.to_string(),
+            valid_from: None,
+            valid_to: None,
+            cross_partition: false,
+        }
+    }
+
+    /// doc1 -mentions-> alice -works_at-> acme, doc2 -mentions-> acme, and
+    /// bob in work linked to alice. Only the docs have embeddings.
+    async fn store() -> MockStore {
+        let store = MockStore::new();
+        for (id, label, properties, partition) in [
+            (
+                "doc1",
+                "Document",
+                json!({"content": "Alice joined Acme"}),
+                "personal",
+            ),
+            (
+                "doc2",
+                "Document",
+                json!({"content": "Acme makes anvils"}),
+                "personal",
+            ),
+            ("alice", "Person", json!({"name": "Alice"}), "personal"),
+            ("acme", "Company", json!({"name": "Acme"}), "personal"),
+            ("bob", "Person", json!({"name": "Bob"}), "work"),
+        ] {
+            store
+                .add_node(Node {
+                    id: id.to_string(),
+                    label: label.to_string(),
+                    properties,
+                    partition_id: partition.to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        for (source, relation, target, partition) in [
+            ("doc1", "mentions", "alice", "personal"),
+            ("alice", "works_at", "acme", "personal"),
+            ("doc2", "mentions", "acme", "personal"),
+            ("bob", "knows", "alice", "work"),
+        ] {
+            store
+                .add_edge(edge(source, relation, target, partition))
+                .await
+                .unwrap();
+        }
+        store
+            .add_embedding("doc1", vec![1.0, 0.0], None)
+            .await
+            .unwrap();
+        store
+            .add_embedding("doc2", vec![0.6, 0.8], None)
+            .await
+            .unwrap();
+        store
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_expands_seeds() {
+        let retriever = Retriever::new(store().await);
+        let chunks = retriever
+            .retrieve(&FixedEmbedder(vec![1.0, 0.0]), "where does alice work")
+            .await
+            .unwrap();
+
+        let ranked: Vec<(&str, usize)> = chunks
+            .iter()
+            .map(|c| (c.node.id.as_str(), c.provenance.hops()))
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![("doc1", 0), ("doc2", 0), ("alice", 1), ("acme", 1)]
+        );
+        assert_eq!(chunks[0].text, "Alice joined Acme");
+        assert_eq!(chunks[2].text, "Person: Alice");
+        assert_eq!(chunks[2].provenance.seed, "doc1");
+        assert_eq!(chunks[2].provenance.path[0].relation, "mentions");
+        assert!((chunks[2].score - 0.5).abs() < 1e-6);
+        assert_eq!(chunks[3].provenance.seed, "doc2");
+        assert!((chunks[3].score - 0.3).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_dedupes_and_scopes() {
+        let config = RetrieverConfig {
+            hops: 2,
+            ..RetrieverConfig::default()
+        };
+        let retriever = Retriever::with_config(store().await, config);
+        let chunks = retriever.retrieve_vector(vec![1.0, 0.0]).await.unwrap();
+        let bob = chunks.iter().find(|c| c.node.id == "bob").unwrap();
+        let relations: Vec<&str> = bob
+            .provenance
+            .path
+            .iter()
+            .map(|e| e.relation.as_str())
+            .collect();
+        assert_eq!(relations, vec!["mentions", "knows"]);
+        // acme is reached from both seeds and keeps its best score: one hop
+        // from doc2 (0.6 * 0.5) beats two from doc1 (1.0 * 0.25)
+        assert_eq!(chunks.iter().filter(|c| c.node.id == "acme").count(), 1);
+        let acme = chunks.iter().find(|c| c.node.id == "acme").unwrap();
+        assert_eq!(acme.provenance.seed, "doc2");
+        assert!((acme.score - 0.3).abs() < 1e-6);
+
+        let config = RetrieverConfig {
+            hops: 2,
+            partition_id: Some("personal".to_string()),
+            ..RetrieverConfig::default()
+        };
+        let retriever = Retriever::with_config(store().await, config.clone());
+        let chunks = retriever.retrieve_vector(vec![1.0, 0.0]).await.unwrap();
+        let ids: Vec<&str> = chunks.iter().map(|c| c.node.id.as_str()).collect();
+        assert_eq!(ids, vec!["doc1", "doc2", "alice", "acme"]);
+
+        let config = RetrieverConfig { limit: 2, ..config };
+        let retriever = Retriever::with_config(store().await, config);
+        let chunks = retriever.retrieve_vector(vec![1.0, 0.0]).await.unwrap();
+        assert_eq!(chunks.len(), 2);
+    }
+}
@@ -0,0 +1,873 @@
+//! Run-scoped writes and rollback
+//!
+//! Automated jobs write through a `RunStore`; today that is facet-server,
+//! which writes each request's transcript as a run named after its session.
+//! The `RunStore` tags every node the job writes with its run id (the
+//! `_run_id` property). It also logs each node, edge and embedding it is
+//! about to change, and how that thing was before, in the graph itself
+//! (`RunLog` nodes in the `_runs` partition). `rollback_run` reads the log
+//! back and reverts exactly that run:
+//!
+//! - nodes the run created are deleted, with their edges and embeddings;
+//! - nodes it updated or deleted get back the version they had before it,
+//!   through `restore_node_version`, so the store needs history mode on;
+//! - edges and embeddings it added, changed or removed are put back as they
+//!   were.
+//!
+//! Nodes written by anyone else since the run are left alone and reported,
+//! so a rollback never throws away later work.
+
+use crate::snapshot::GraphSnapshot;
+use crate::transaction::{GraphOp, GraphTransaction};
+use crate::{
+    is_history_unsupported, Direction, Edge, GraphError, GraphStore, Node, NodeVersion,
+    PageRequest, PartitionInfo, StoredEmbedding, VectorStore,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Property holding the id of the run that last wrote a node
+pub const RUN_ID_PROPERTY: &str = "_run_id";
+
+/// Partition the run logs are kept in
+pub const RUNS_PARTITION: &str = "_runs";
+
+const RUN_LOG_LABEL: &str = "RunLog";
+
+/// Id of the `seq`th chunk of a run's log. Chunks are numbered from 0
+/// without gaps, so the log is read back by id alone.
+fn log_id(run_id: &str, seq: u64) -> String {
+    format!("run:{}:{}", run_id, seq)
+}
+
+/// The first write of a run to something, with what it takes to undo it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum RunWrite {
+    Node {
+        id: String,
+        /// Whether the node existed before the run wrote it
+        existed: bool,
+        /// Index in `get_node_history` of the version the run's first write
+        /// records, i.e. the node as it was before the run. `None` if the
+        /// store keeps no history.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<usize>,
+    },
+    Edges {
+        source: String,
+        relation: String,
+        target: String,
+        /// The `source-[relation]->target` edges before the run; empty if
+        /// it added them
+        before: Vec<Edge>,
+    },
+    Embedding {
+        id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        field: Option<String>,
+        /// The vector before the run; `None` if it added it
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        before: Option<Vec<f32>>,
+    },
+}
+
+type EdgeKey = (String, String, String);
+
+/// An embedding space of a node and its vector before the run
+type EmbeddingWrite = (Option<String>, Option<Vec<f32>>);
+
+/// What a run has written to, so only its first write to each is logged
+#[derive(Default)]
+struct Touched {
+    nodes: HashSet<String>,
+    edges: HashSet<EdgeKey>,
+    embeddings: HashSet<(String, Option<String>)>,
+}
+
+impl Touched {
+    fn extend(&mut self, other: Touched) {
+        self.nodes.extend(other.nodes);
+        self.edges.extend(other.edges);
+        self.embeddings.extend(other.embeddings);
+    }
+}
+
+#[derive(Default)]
+struct RunState {
+    next_seq: u64,
+    touched: Touched,
+}
+
+/// First writes of one call, captured before the call is made
+#[derive(Default)]
+struct Pending {
+    writes: Vec<RunWrite>,
+    touched: Touched,
+}
+
+/// A store whose writes all belong to one run and can be rolled back with
+/// `rollback_run`. Reads pass straight through.
+///
+/// Writes go through one at a time, so each is logged with the state it
+/// replaces. Clones share the log. Partitions cannot be renamed or deleted
+/// during a run, since that cannot be undone.
+#[derive(Clone)]
+pub struct RunStore<S> {
+    inner: S,
+    run_id: String,
+    state: Arc<Mutex<RunState>>,
+}
+
+impl<S> RunStore<S> {
+    /// Starts, or carries on with, the run `run_id`
+    pub fn new(inner: S, run_id: impl Into<String>) -> Self {
+        Self {
+            inner,
+            run_id: run_id.into(),
+            state: Arc::new(Mutex::new(RunState::default())),
+        }
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn tag(&self, mut node: Node) -> Node {
+        if node.properties.is_null() {
+            node.properties = serde_json::json!({});
+        }
+        if let Some(properties) = node.properties.as_object_mut() {
+            properties.insert(RUN_ID_PROPERTY.to_string(), self.run_id.clone().into());
+        }
+        node
+    }
+}
+
+impl<S: GraphStore + VectorStore> RunStore<S> {
+    async fn capture_node(
+        &self,
+        state: &RunState,
+        pending: &mut Pending,
+        id: &str,
+    ) -> Result<(), GraphError> {
+        if state.touched.nodes.contains(id) || !pending.touched.nodes.insert(id.to_string()) {
+            return Ok(());
+        }
+        let existed = self.inner.node_exists(id).await?;
+        let version = if existed {
+            match self.inner.get_node_history(id).await {
+                Ok(history) => Some(history.len()),
+                // No history support; the node can't be restored
+                Err(e) if is_history_unsupported(&e) => None,
+                Err(e) => return Err(e),
+            }
+        } else {
+            None
+        };
+        pending.writes.push(RunWrite::Node {
+            id: id.to_string(),
+            existed,
+            version,
+        });
+        Ok(())
+    }
+
+    async fn capture_edges(
+        &self,
+        state: &RunState,
+        pending: &mut Pending,
+        source: &str,
+        relation: &str,
+        target: &str,
+    ) -> Result<(), GraphError> {
+        let key = (source.to_string(), relation.to_string(), target.to_string());
+        if state.touched.edges.contains(&key) || !pending.touched.edges.insert(key) {
+            return Ok(());
+        }
+        let before = match self.inner.get_neighbors(source).await {
+            Ok(neighbors) => neighbors
+                .into_iter()
+                .map(|(edge, _)| edge)
+                .filter(|edge| edge.relation == relation && edge.target == target)
+                .collect(),
+            Err(GraphError::NotFound(_)) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        pending.writes.push(RunWrite::Edges {
+            source: source.to_string(),
+            relation: relation.to_string(),
+            target: target.to_string(),
+            before,
+        });
+        Ok(())
+    }
+
+    async fn capture_embedding(
+        &self,
+        state: &RunState,
+        pending: &mut Pending,
+        id: &str,
+        field: Option<&str>,
+    ) -> Result<(), GraphError> {
+        let key = (id.to_string(), field.map(str::to_string));
+        if state.touched.embeddings.contains(&key) || pending.touched.embeddings.contains(&key) {
+            return Ok(());
+        }
+        let before = self
+            .inner
+            .get_embeddings(id)
+            .await?
+            .into_iter()
+            .find(|e| e.field.as_deref() == field)
+            .map(|e| e.vector);
+        pending.touched.embeddings.insert(key);
+        pending.writes.push(RunWrite::Embedding {
+            id: id.to_string(),
+            field: field.map(str::to_string),
+            before,
+        });
+        Ok(())
+    }
+
+    /// Captures every embedding of `id`, before they are all removed
+    async fn capture_embeddings(
+        &self,
+        state: &RunState,
+        pending: &mut Pending,
+        id: &str,
+    ) -> Result<(), GraphError> {
+        for embedding in self.inner.get_embeddings(id).await? {
+            self.capture_embedding(state, pending, id, embedding.field.as_deref())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Captures a node and what goes with it, before it is deleted
+    async fn capture_delete(
+        &self,
+        state: &RunState,
+        pending: &mut Pending,
+        id: &str,
+        cascade: bool,
+    ) -> Result<(), GraphError> {
+        self.capture_node(state, pending, id).await?;
+        self.capture_embeddings(state, pending, id).await?;
+        if cascade {
+            let attached = match self.inner.get_neighbors_directed(id, Direction::Both).await {
+                Ok(attached) => attached,
+                Err(GraphError::NotFound(_)) => Vec::new(),
+                Err(e) => return Err(e),
+            };
+            for (edge, _) in attached {
+                self.capture_edges(state, pending, &edge.source, &edge.relation, &edge.target)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the captured first writes to the run's log, ahead of the
+    /// writes themselves, so a crash in between loses nothing
+    async fn log(&self, state: &mut RunState, pending: Pending) -> Result<(), GraphError> {
+        if !pending.writes.is_empty() {
+            let writes = serde_json::to_value(&pending.writes)
+                .map_err(|e| GraphError::Storage(format!("Failed to encode run log: {}", e)))?;
+            let mut chunk = Node {
+                id: String::new(),
+                label: RUN_LOG_LABEL.to_string(),
+                properties: serde_json::json!({
+                    "run_id": self.run_id,
+                    "writes": writes,
+                }),
+                partition_id: RUNS_PARTITION.to_string(),
+            };
+            loop {
+                chunk.id = log_id(&self.run_id, state.next_seq);
+                state.next_seq += 1;
+                match self.inner.add_node(chunk.clone()).await {
+                    Ok(()) => break,
+                    // Logged by an earlier store carrying on with this run
+                    Err(GraphError::Conflict(_)) => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        state.touched.extend(pending.touched);
+        Ok(())
+    }
+}
+
+fn irreversible(what: &str) -> GraphError {
+    GraphError::Storage(format!(
+        "{} cannot be rolled back, so runs may not do it",
+        what
+    ))
+}
+
+#[async_trait]
+impl<S: GraphStore + VectorStore> GraphStore for RunStore<S> {
+    async fn add_node(&self, node: Node) -> Result<(), GraphError> {
+        let mut state = self.state.lock().await;
+        let mut pending = Pending::default();
+        self.capture_node(&state, &mut pending, &node.id).await?;
+        self.log(&mut state, pending).await?;
+        self.inner.add_node(self.tag(node)).await
+    }
+
+    async fn add_edge(&self, edge: Edge) -> Result<(), GraphError> {
+        let mut state = self.state.lock().await;
+        let mut pending = Pending::default();
+        self.capture_edges(
+            &state,
+            &mut pending,
+            &edge.source,
+            &edge.relation,
+            &edge.target,
+        )
+        .await?;
+        self.log(&mut state, pending).await?;
+        self.inner.add_edge(edge).await
+    }
+
+    async fn get_node(&self, id: &str) -> Result<Node, GraphError> {
+        self.inner.get_node(id).await
+    }
+
+    async fn get_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.inner.get_neighbors(id).await
+    }
+
+    async fn get_incoming_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.inner.get_incoming_neighbors(id).await
+    }
+
+    async fn update_node(&self, node: Node) -> Result<(), GraphError> {
+        let mut state = self.state.lock().await;
+        let mut pending = Pending::default();
+        self.capture_node(&state, &mut pending, &node.id).await?;
+        self.log(&mut state, pending).await?;
+        self.inner.update_node(self.tag(node)).await
+    }
+
+    async fn get_node_history(&self, id: &str) -> Result<Vec<NodeVersion>, GraphError> {
+        self.inner.get_node_history(id).await
+    }
+
+    async fn traverse(
+        &self,
+        id: &str,
+        depth: usize,
+        direction: Direction,
+        relation_filter: Option<&[&str]>,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.inner
+            .traverse(id, depth, direction, relation_filter)
+            .await
+    }
+
+    async fn add_nodes(&self, nodes: Vec<Node>) -> Result<(), GraphError> {
+        let mut state = self.state.lock().await;
+        let mut pending = Pending::default();
+        for node in &nodes {
+            self.capture_node(&state, &mut pending, &node.id).await?;
+        }
+        self.log(&mut state, pending).await?;
+        let nodes = nodes.into_iter().map(|node| self.tag(node)).collect();
+        self.inner.add_nodes(nodes).await
+    }
+
+    async fn add_edges(&self, edges: Vec<Edge>) -> Result<(), GraphError> {
+        let mut state = self.state.lock().await;
+        let mut pending = Pending::default();
+        for edge in &edges {
+            self.capture_edges(
+                &state,
+                &mut pending,
+                &edge.source,
+                &edge.relation,
+                &edge.target,
+            )
+            .await?;
+        }
+        self.log(&mut state, pending).await?;
+        self.inner.add_edges(edges).await
+    }
+
+    async fn delete_node(&self, id: &str, cascade: bool) -> Result<(), GraphError> {
+        let mut state = self.state.lock().await;
+        let mut pending = Pending::default();
+        self.capture_delete(&state, &mut pending, id, cascade)
+            .await?;
+        self.log(&mut state, pending).await?;
+        self.inner.delete_node(id, cascade).await
+    }
+
+    async fn delete_edge(
+        &self,
+        source: &str,
+        relation: &str,
+        target: &str,
+    ) -> Result<(), GraphError> {
+        let mut state = self.state.lock().await;
+        let mut pending = Pending::default();
+        self.capture_edges(&state, &mut pending, source, relation, target)
+            .await?;
+        self.log(&mut state, pending).await?;
+        self.inner.delete_edge(source, relation, target).await
+    }
+
+    async fn update_edge(&self, edge: Edge) -> Result<(), GraphError> {
+        let mut state = self.state.lock().await;
+        let mut pending = Pending::default();
+        self.capture_edges(
+            &state,
+            &mut pending,
+            &edge.source,
+            &edge.relation,
+            &edge.target,
+        )
+        .await?;
+        self.log(&mut state, pending).await?;
+        self.inner.update_edge(edge).await
+    }
+
+    async fn increment_edge_weight(
+        &self,
+        source: &str,
+        relation: &str,
+        target: &str,
+        delta: f32,
+    ) -> Result<f32, GraphError> {
+        let mut state = self.state.lock().await;
+        let mut pending = Pending::default();
+        self.capture_edges(&state, &mut pending, source, relation, target)
+            .await?;
+        self.log(&mut state, pending).await?;
+        self.inner
+            .increment_edge_weight(source, relation, target, delta)
+            .await
+    }
+
+    async fn commit_transaction(&self, tx: GraphTransaction) -> Result<(), GraphError> {
+        let mut state = self.state.lock().await;
+        let mut pending = Pending::default();
+        let mut tagged = GraphTransaction::new();
+        for op in tx.into_ops() {
+            match op {
+                GraphOp::AddNode(node) => {
+                    self.capture_node(&state, &mut pending, &node.id).await?;
+                    tagged.add_node(self.tag(node));
+                }
+                GraphOp::UpdateNode(node) => {
+                    self.capture_node(&state, &mut pending, &node.id).await?;
+                    tagged.update_node(self.tag(node));
+                }
+                GraphOp::DeleteNode { id, cascade } => {
+                    self.capture_delete(&state, &mut pending, &id, cascade)
+                        .await?;
+                    tagged.delete_node(&id, cascade);
+                }
+                GraphOp::AddEdge(edge) => {
+                    self.capture_edges(
+                        &state,
+                        &mut pending,
+                        &edge.source,
+                        &edge.relation,
+                        &edge.target,
+                    )
+                    .await?;
+                    tagged.add_edge(edge);
+                }
+                GraphOp::DeleteEdge {
+                    source,
+                    relation,
+                    target,
+                } => {
+                    self.capture_edges(&state, &mut pending, &source, &relation, &target)
+                        .await?;
+                    tagged.delete_edge(&source, &relation, &target);
+                }
+            }
+        }
+        self.log(&mut state, pending).await?;
+        self.inner.commit_transaction(tagged).await
+    }
+
+    async fn search_text(&self, query: &str, limit: usize) -> Result<Vec<(Node, f32)>, GraphError> {
+        self.inner.search_text(query, limit).await
+    }
+
+    async fn query_by_partition(&self, partition_id: &str) -> Result<Vec<Node>, GraphError> {
+        self.inner.query_by_partition(partition_id).await
+    }
+
+    async fn query_by_label(
+        &self,
+        label: &str,
+        partition_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Node>, GraphError> {
+        self.inner.query_by_label(label, partition_id, limit).await
+    }
+
+    async fn node_exists(&self, id: &str) -> Result<bool, GraphError> {
+        self.inner.node_exists(id).await
+    }
+
+    async fn count_nodes(&self, partition_id: Option<&str>) -> Result<usize, GraphError> {
+        self.inner.count_nodes(partition_id).await
+    }
+
+    async fn count_edges(&self, relation: Option<&str>) -> Result<usize, GraphError> {
+        self.inner.count_edges(relation).await
+    }
+
+    async fn snapshot(&self, partition_id: Option<&str>) -> Result<GraphSnapshot, GraphError> {
+        self.inner.snapshot(partition_id).await
+    }
+
+    async fn query_by_partition_page(
+        &self,
+        partition_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Node>, GraphError> {
+        self.inner.query_by_partition_page(partition_id, page).await
+    }
+
+    async fn get_neighbors_in_partition(
+        &self,
+        id: &str,
+        partition_id: &str,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.inner
+            .get_neighbors_in_partition(id, partition_id)
+            .await
+    }
+
+    async fn create_partition(&self, partition_id: &str) -> Result<(), GraphError> {
+        self.inner.create_partition(partition_id).await
+    }
+
+    async fn list_partitions(&self) -> Result<Vec<PartitionInfo>, GraphError> {
+        self.inner.list_partitions().await
+    }
+
+    async fn rename_partition(&self, _from: &str, _to: &str) -> Result<(), GraphError> {
+        Err(irreversible("Renaming a partition"))
+    }
+
+    async fn delete_partition(
+        &self,
+        _partition_id: &str,
+        _cascade: bool,
+    ) -> Result<(), GraphError> {
+        Err(irreversible("Deleting a partition"))
+    }
+}
+
+#[async_trait]
+impl<S: GraphStore + VectorStore> VectorStore for RunStore<S> {
+    async fn add_embedding(
+        &self,
+        id: &str,
+        vector: Vec<f32>,
+        field: Option<&str>,
+    ) -> Result<(), GraphError> {
+        let mut state = self.state.lock().await;
+        let mut pending = Pending::default();
+        self.capture_embedding(&state, &mut pending, id, field)
+            .await?;
+        self.log(&mut state, pending).await?;
+        self.inner.add_embedding(id, vector, field).await
+    }
+
+    async fn search(
+        &self,
+        vector: Vec<f32>,
+        limit: usize,
+        field: Option<&str>,
+    ) -> Result<Vec<(String, f32)>, GraphError> {
+        self.inner.search(vector, limit, field).await
+    }
+
+    async fn add_embeddings(
+        &self,
+        embeddings: Vec<(String, Vec<f32>)>,
+        field: Option<&str>,
+    ) -> Result<(), GraphError> {
+        let mut state = self.state.lock().await;
+        let mut pending = Pending::default();
+        for (id, _) in &embeddings {
+            self.capture_embedding(&state, &mut pending, id, field)
+                .await?;
+        }
+        self.log(&mut state, pending).await?;
+        self.inner.add_embeddings(embeddings, field).await
+    }
+
+    async fn remove_embedding(&self, id: &str) -> Result<(), GraphError> {
+        let mut state = self.state.lock().await;
+        let mut pending = Pending::default();
+        self.capture_embeddings(&state, &mut pending, id).await?;
+        self.log(&mut state, pending).await?;
+        self.inner.remove_embedding(id).await
+    }
+
+    async fn clear_embeddings(&self, partition_id: &str) -> Result<(), GraphError> {
+        let mut state = self.state.lock().await;
+        let mut pending = Pending::default();
+        for node in self.inner.query_by_partition(partition_id).await? {
+            self.capture_embeddings(&state, &mut pending, &node.id)
+                .await?;
+        }
+        self.log(&mut state, pending).await?;
+        self.inner.clear_embeddings(partition_id).await
+    }
+
+    async fn search_nodes(
+        &self,
+        vector: Vec<f32>,
+        limit: usize,
+        field: Option<&str>,
+    ) -> Result<Vec<(Node, f32)>, GraphError> {
+        self.inner.search_nodes(vector, limit, field).await
+    }
+
+    async fn get_embeddings(&self, id: &str) -> Result<Vec<StoredEmbedding>, GraphError> {
+        self.inner.get_embeddings(id).await
+    }
+
+    async fn list_embeddings(&self) -> Result<Vec<StoredEmbedding>, GraphError> {
+        self.inner.list_embeddings().await
+    }
+}
+
+/// A node `rollback_run` left as it is
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SkippedNode {
+    pub id: String,
+    pub reason: String,
+}
+
+/// What `rollback_run` reverted
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RollbackReport {
+    pub run_id: String,
+    /// Nodes the run created, now deleted
+    pub nodes_deleted: usize,
+    /// Nodes the run updated or deleted, now back as they were before it
+    pub nodes_restored: usize,
+    /// `source-[relation]->target` edges put back as they were
+    pub edges_reverted: usize,
+    /// Nodes whose embeddings were put back as they were
+    pub embeddings_reverted: usize,
+    pub skipped: Vec<SkippedNode>,
+}
+
+impl fmt::Display for RollbackReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Rolled back run {}", self.run_id)?;
+        writeln!(
+            f,
+            "Nodes: -{} ~{}   Edges: ~{}   Embeddings: ~{}",
+            self.nodes_deleted, self.nodes_restored, self.edges_reverted, self.embeddings_reverted
+        )?;
+        for skipped in &self.skipped {
+            writeln!(f, "! node {} left as is: {}", skipped.id, skipped.reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a run's log: the ids of its chunks and its writes, oldest first
+async fn read_log<S: GraphStore + ?Sized>(
+    store: &S,
+    run_id: &str,
+) -> Result<(Vec<String>, Vec<RunWrite>), GraphError> {
+    let mut chunks = Vec::new();
+    let mut writes = Vec::new();
+    loop {
+        let id = log_id(run_id, chunks.len() as u64);
+        let chunk = match store.get_node(&id).await {
+            Ok(chunk) => chunk,
+            Err(GraphError::NotFound(_)) => break,
+            Err(e) => return Err(e),
+        };
+        let chunk_writes: Vec<RunWrite> =
+            serde_json::from_value(chunk.properties["writes"].clone()).map_err(|e| {
+                GraphError::Storage(format!("Failed to decode run log {}: {}", id, e))
+            })?;
+        writes.extend(chunk_writes);
+        chunks.push(id);
+    }
+    Ok((chunks, writes))
+}
+
+fn ignore_not_found(result: Result<(), GraphError>) -> Result<(), GraphError> {
+    match result {
+        Err(GraphError::NotFound(_)) => Ok(()),
+        result => result,
+    }
+}
+
+/// Reverts everything run `run_id` wrote through a `RunStore`, then drops
+/// its log. See the module docs for what is put back and what is left.
+///
+/// Fails with `NotFound` if there is no log for the run, e.g. because it
+/// has already been rolled back.
+pub async fn rollback_run<S>(store: &S, run_id: &str) -> Result<RollbackReport, GraphError>
+where
+    S: GraphStore + VectorStore + ?Sized,
+{
+    let (chunks, writes) = read_log(store, run_id).await?;
+    if chunks.is_empty() {
+        return Err(GraphError::NotFound(format!("run {}", run_id)));
+    }
+
+    // A run carried on by a later store logs things again; the first entry
+    // is the one with the state from before the run
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    let mut embeddings: BTreeMap<String, Vec<EmbeddingWrite>> = BTreeMap::new();
+    let mut touched = Touched::default();
+    for write in writes {
+        match write {
+            RunWrite::Node {
+                id,
+                existed,
+                version,
+            } => {
+                if touched.nodes.insert(id.clone()) {
+                    nodes.push((id, existed, version));
+                }
+            }
+            RunWrite::Edges {
+                source,
+                relation,
+                target,
+                before,
+            } => {
+                let key = (source, relation, target);
+                if touched.edges.insert(key.clone()) {
+                    edges.push((key, before));
+                }
+            }
+            RunWrite::Embedding { id, field, before } => {
+                if touched.embeddings.insert((id.clone(), field.clone())) {
+                    embeddings.entry(id).or_default().push((field, before));
+                }
+            }
+        }
+    }
+
+    let mut report = RollbackReport {
+        run_id: run_id.to_string(),
+        ..Default::default()
+    };
+
+    // Edges first, while the nodes they hang off are still there
+    for ((source, relation, target), _) in &edges {
+        ignore_not_found(store.delete_edge(source, relation, target).await)?;
+    }
+
+    // Nodes whose embeddings are already right, or are to be left alone
+    let mut settled = HashSet::new();
+    for (id, existed, version) in nodes {
+        let current = match store.get_node(&id).await {
+            Ok(node) => Some(node),
+            Err(GraphError::NotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+        if let Some(current) = &current {
+            let written_by = current.properties.get(RUN_ID_PROPERTY);
+            if written_by.and_then(|v| v.as_str()) != Some(run_id) {
+                report.skipped.push(SkippedNode {
+                    id: id.clone(),
+                    reason: "last written by something other than this run".to_string(),
+                });
+                settled.insert(id);
+                continue;
+            }
+        }
+
+        if !existed {
+            if current.is_some() {
+                store.delete_node(&id, true).await?;
+                store.remove_embedding(&id).await?;
+                report.nodes_deleted += 1;
+            }
+            settled.insert(id);
+            continue;
+        }
+
+        let before = match version {
+            Some(version) => store.get_node_history(&id).await?.into_iter().nth(version),
+            None => None,
+        };
+        match before {
+            Some(before) => {
+                store.restore_node_version(&id, before.recorded_at).await?;
+                report.nodes_restored += 1;
+            }
+            None => {
+                report.skipped.push(SkippedNode {
+                    id: id.clone(),
+                    reason: "no version from before the run; is history on?".to_string(),
+                });
+                settled.insert(id);
+            }
+        }
+    }
+
+    for (_, before) in edges {
+        for edge in before {
+            store.add_edge(edge).await?;
+        }
+        report.edges_reverted += 1;
+    }
+
+    for (id, fields) in embeddings {
+        if settled.contains(&id) {
+            continue;
+        }
+        let current: BTreeMap<Option<String>, Vec<f32>> = store
+            .get_embeddings(&id)
+            .await?
+            .into_iter()
+            .map(|e| (e.field, e.vector))
+            .collect();
+        let mut vectors = current.clone();
+        for (field, before) in fields {
+            match before {
+                Some(vector) => vectors.insert(field, vector),
+                None => vectors.remove(&field),
+            };
+        }
+        if vectors != current {
+            store.remove_embedding(&id).await?;
+            for (field, vector) in vectors {
+                store.add_embedding(&id, vector, field.as_deref()).await?;
+            }
+            report.embeddings_reverted += 1;
+        }
+    }
+
+    for chunk in chunks {
+        store.delete_node(&chunk, false).await?;
+    }
+    Ok(report)
+}
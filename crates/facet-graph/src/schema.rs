@@ -0,0 +1,596 @@
+//! Optional schema layer
+//!
+//! `GraphSchema` lists the node labels and edge relations a graph may hold,
+//! and the properties each label expects. Wrapping a store in `SchemaStore`
+//! checks every write against it and rejects bad ones with
+//! `GraphError::SchemaViolation` before they reach the backend.
+
+use crate::snapshot::GraphSnapshot;
+use crate::transaction::{GraphOp, GraphTransaction};
+use crate::{
+    merge_properties, Direction, Edge, GraphError, GraphStore, Node, NodeVersion, PageRequest,
+    PartitionInfo, StoredEmbedding, VectorStore,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PropertyType {
+    String,
+    Number,
+    Integer,
+    Boolean,
+    Array,
+    Object,
+    Any,
+}
+
+impl PropertyType {
+    fn matches(&self, value: &Value) -> bool {
+        match self {
+            PropertyType::String => value.is_string(),
+            PropertyType::Number => value.is_number(),
+            PropertyType::Integer => value.is_i64() || value.is_u64(),
+            PropertyType::Boolean => value.is_boolean(),
+            PropertyType::Array => value.is_array(),
+            PropertyType::Object => value.is_object(),
+            PropertyType::Any => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PropertySpec {
+    #[serde(rename = "type")]
+    pub kind: PropertyType,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// Properties expected on nodes with one label
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LabelSchema {
+    #[serde(default)]
+    pub properties: BTreeMap<String, PropertySpec>,
+    /// Accept properties that are not listed in `properties`
+    #[serde(default)]
+    pub allow_extra: bool,
+}
+
+impl LabelSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn required(mut self, key: &str, kind: PropertyType) -> Self {
+        self.properties.insert(
+            key.to_string(),
+            PropertySpec {
+                kind,
+                required: true,
+            },
+        );
+        self
+    }
+
+    pub fn optional(mut self, key: &str, kind: PropertyType) -> Self {
+        self.properties.insert(
+            key.to_string(),
+            PropertySpec {
+                kind,
+                required: false,
+            },
+        );
+        self
+    }
+
+    pub fn allow_extra(mut self) -> Self {
+        self.allow_extra = true;
+        self
+    }
+}
+
+/// The labels and relations a graph may contain. Anything not registered is
+/// rejected.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphSchema {
+    #[serde(default)]
+    pub labels: BTreeMap<String, LabelSchema>,
+    #[serde(default)]
+    pub relations: BTreeSet<String>,
+}
+
+impl GraphSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register_label(&mut self, label: &str, schema: LabelSchema) -> &mut Self {
+        self.labels.insert(label.to_string(), schema);
+        self
+    }
+
+    pub fn register_relation(&mut self, relation: &str) -> &mut Self {
+        self.relations.insert(relation.to_string());
+        self
+    }
+
+    /// Checks the node's label and properties. A `null` value counts as
+    /// missing.
+    pub fn validate_node(&self, node: &Node) -> Result<(), GraphError> {
+        let violation = |msg: String| GraphError::SchemaViolation(format!("{}: {}", node.id, msg));
+
+        let Some(schema) = self.labels.get(&node.label) else {
+            return Err(violation(format!("unknown label '{}'", node.label)));
+        };
+
+        let empty = serde_json::Map::new();
+        let properties = match &node.properties {
+            Value::Object(map) => map,
+            Value::Null => &empty,
+            _ => return Err(violation("properties must be an object".to_string())),
+        };
+
+        for (key, spec) in &schema.properties {
+            match properties.get(key) {
+                None | Some(Value::Null) if spec.required => {
+                    return Err(violation(format!("missing required property '{}'", key)));
+                }
+                Some(value) if !value.is_null() && !spec.kind.matches(value) => {
+                    return Err(violation(format!(
+                        "property '{}' should be {:?}, got {}",
+                        key, spec.kind, value
+                    )));
+                }
+                _ => {}
+            }
+        }
+
+        if !schema.allow_extra {
+            if let Some(key) = properties
+                .keys()
+                .find(|k| !schema.properties.contains_key(*k))
+            {
+                return Err(violation(format!(
+                    "unexpected property '{}' on {}",
+                    key, node.label
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_edge(&self, edge: &Edge) -> Result<(), GraphError> {
+        if self.relations.contains(&edge.relation) {
+            Ok(())
+        } else {
+            Err(GraphError::SchemaViolation(format!(
+                "{}->{}: unknown relation '{}'",
+                edge.source, edge.target, edge.relation
+            )))
+        }
+    }
+
+    /// Validates the node or edge written by `op`; deletes always pass
+    pub fn validate_op(&self, op: &GraphOp) -> Result<(), GraphError> {
+        match op {
+            GraphOp::AddNode(node) | GraphOp::UpdateNode(node) => self.validate_node(node),
+            GraphOp::AddEdge(edge) => self.validate_edge(edge),
+            GraphOp::DeleteNode { .. } | GraphOp::DeleteEdge { .. } => Ok(()),
+        }
+    }
+}
+
+/// A store that validates writes against a `GraphSchema` and passes
+/// everything else straight through.
+///
+/// `upsert_node` validates the merged node, so a partial update is fine as
+/// long as the result is complete. It goes through `get_node` and
+/// `update_node` rather than the inner store's own upsert.
+#[derive(Clone)]
+pub struct SchemaStore<S> {
+    inner: S,
+    schema: GraphSchema,
+}
+
+impl<S> SchemaStore<S> {
+    pub fn new(inner: S, schema: GraphSchema) -> Self {
+        Self { inner, schema }
+    }
+
+    pub fn schema(&self) -> &GraphSchema {
+        &self.schema
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<S: GraphStore> GraphStore for SchemaStore<S> {
+    async fn add_node(&self, node: Node) -> Result<(), GraphError> {
+        self.schema.validate_node(&node)?;
+        self.inner.add_node(node).await
+    }
+
+    async fn add_edge(&self, edge: Edge) -> Result<(), GraphError> {
+        self.schema.validate_edge(&edge)?;
+        self.inner.add_edge(edge).await
+    }
+
+    async fn get_node(&self, id: &str) -> Result<Node, GraphError> {
+        self.inner.get_node(id).await
+    }
+
+    async fn get_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.inner.get_neighbors(id).await
+    }
+
+    async fn get_incoming_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.inner.get_incoming_neighbors(id).await
+    }
+
+    async fn get_node_history(&self, id: &str) -> Result<Vec<NodeVersion>, GraphError> {
+        self.inner.get_node_history(id).await
+    }
+
+    async fn update_node(&self, node: Node) -> Result<(), GraphError> {
+        self.schema.validate_node(&node)?;
+        self.inner.update_node(node).await
+    }
+
+    async fn patch_node(&self, id: &str, patch: Value) -> Result<Node, GraphError> {
+        // Checked against the current node; the store applies the patch
+        let mut node = self.inner.get_node(id).await?;
+        merge_properties(&mut node.properties, patch.clone());
+        self.schema.validate_node(&node)?;
+        self.inner.patch_node(id, patch).await
+    }
+
+    async fn traverse(
+        &self,
+        id: &str,
+        depth: usize,
+        direction: Direction,
+        relation_filter: Option<&[&str]>,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.inner
+            .traverse(id, depth, direction, relation_filter)
+            .await
+    }
+
+    async fn add_nodes(&self, nodes: Vec<Node>) -> Result<(), GraphError> {
+        for node in &nodes {
+            self.schema.validate_node(node)?;
+        }
+        self.inner.add_nodes(nodes).await
+    }
+
+    async fn add_edges(&self, edges: Vec<Edge>) -> Result<(), GraphError> {
+        for edge in &edges {
+            self.schema.validate_edge(edge)?;
+        }
+        self.inner.add_edges(edges).await
+    }
+
+    async fn delete_node(&self, id: &str, cascade: bool) -> Result<(), GraphError> {
+        self.inner.delete_node(id, cascade).await
+    }
+
+    async fn delete_edge(
+        &self,
+        source: &str,
+        relation: &str,
+        target: &str,
+    ) -> Result<(), GraphError> {
+        self.inner.delete_edge(source, relation, target).await
+    }
+
+    async fn update_edge(&self, edge: Edge) -> Result<(), GraphError> {
+        self.schema.validate_edge(&edge)?;
+        self.inner.update_edge(edge).await
+    }
+
+    async fn increment_edge_weight(
+        &self,
+        source: &str,
+        relation: &str,
+        target: &str,
+        delta: f32,
+    ) -> Result<f32, GraphError> {
+        self.inner
+            .increment_edge_weight(source, relation, target, delta)
+            .await
+    }
+
+    async fn commit_transaction(&self, tx: GraphTransaction) -> Result<(), GraphError> {
+        for op in tx.ops() {
+            self.schema.validate_op(op)?;
+        }
+        self.inner.commit_transaction(tx).await
+    }
+
+    async fn search_text(&self, query: &str, limit: usize) -> Result<Vec<(Node, f32)>, GraphError> {
+        self.inner.search_text(query, limit).await
+    }
+
+    async fn query_by_partition(&self, partition_id: &str) -> Result<Vec<Node>, GraphError> {
+        self.inner.query_by_partition(partition_id).await
+    }
+
+    async fn query_by_label(
+        &self,
+        label: &str,
+        partition_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Node>, GraphError> {
+        self.inner.query_by_label(label, partition_id, limit).await
+    }
+
+    async fn node_exists(&self, id: &str) -> Result<bool, GraphError> {
+        self.inner.node_exists(id).await
+    }
+
+    async fn count_nodes(&self, partition_id: Option<&str>) -> Result<usize, GraphError> {
+        self.inner.count_nodes(partition_id).await
+    }
+
+    async fn count_edges(&self, relation: Option<&str>) -> Result<usize, GraphError> {
+        self.inner.count_edges(relation).await
+    }
+
+    async fn snapshot(&self, partition_id: Option<&str>) -> Result<GraphSnapshot, GraphError> {
+        self.inner.snapshot(partition_id).await
+    }
+
+    async fn query_by_partition_page(
+        &self,
+        partition_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Node>, GraphError> {
+        self.inner.query_by_partition_page(partition_id, page).await
+    }
+
+    async fn get_neighbors_in_partition(
+        &self,
+        id: &str,
+        partition_id: &str,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.inner
+            .get_neighbors_in_partition(id, partition_id)
+            .await
+    }
+
+    async fn create_partition(&self, partition_id: &str) -> Result<(), GraphError> {
+        self.inner.create_partition(partition_id).await
+    }
+
+    async fn list_partitions(&self) -> Result<Vec<PartitionInfo>, GraphError> {
+        self.inner.list_partitions().await
+    }
+
+    async fn rename_partition(&self, from: &str, to: &str) -> Result<(), GraphError> {
+        self.inner.rename_partition(from, to).await
+    }
+
+    async fn delete_partition(&self, partition_id: &str, cascade: bool) -> Result<(), GraphError> {
+        self.inner.delete_partition(partition_id, cascade).await
+    }
+}
+
+#[async_trait]
+impl<S: VectorStore> VectorStore for SchemaStore<S> {
+    async fn add_embedding(
+        &self,
+        id: &str,
+        vector: Vec<f32>,
+        field: Option<&str>,
+    ) -> Result<(), GraphError> {
+        self.inner.add_embedding(id, vector, field).await
+    }
+
+    async fn search(
+        &self,
+        vector: Vec<f32>,
+        limit: usize,
+        field: Option<&str>,
+    ) -> Result<Vec<(String, f32)>, GraphError> {
+        self.inner.search(vector, limit, field).await
+    }
+
+    async fn add_embeddings(
+        &self,
+        embeddings: Vec<(String, Vec<f32>)>,
+        field: Option<&str>,
+    ) -> Result<(), GraphError> {
+        self.inner.add_embeddings(embeddings, field).await
+    }
+
+    async fn remove_embedding(&self, id: &str) -> Result<(), GraphError> {
+        self.inner.remove_embedding(id).await
+    }
+
+    async fn clear_embeddings(&self, partition_id: &str) -> Result<(), GraphError> {
+        self.inner.clear_embeddings(partition_id).await
+    }
+
+    async fn search_nodes(
+        &self,
+        vector: Vec<f32>,
+        limit: usize,
+        field: Option<&str>,
+    ) -> Result<Vec<(Node, f32)>, GraphError> {
+        self.inner.search_nodes(vector, limit, field).await
+    }
+
+    async fn get_embeddings(&self, id: &str) -> Result<Vec<StoredEmbedding>, GraphError> {
+        self.inner.get_embeddings(id).await
+    }
+
+    async fn list_embeddings(&self) -> Result<Vec<StoredEmbedding>, GraphError> {
+        self.inner.list_embeddings().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mocks::MockGraphStore;
+    use serde_json::json;
+
+    fn schema() -> GraphSchema {
+        let mut schema = GraphSchema::new();
+        schema
+            .register_label(
+                "Person",
+                LabelSchema::new()
+                    .required("name", PropertyType::String)
+                    .optional("age", PropertyType::Integer),
+            )
+            .register_label("Note", LabelSchema::new().allow_extra())
+            .register_relation("knows");
+        schema
+    }
+
+    fn person(id: &str, properties: Value) -> Node {
+        Node {
+            id: id.to_string(),
+            label: "Person".to_string(),
+            properties,
+            partition_id: "personal".to_string(),
+        }
+    }
+
+    fn edge(relation: &str) -> Edge {
+        Edge {
+            source: "alice".to_string(),
+            target: "bob".to_string(),
+            relation: relation.to_string(),
+            weight: 1.0,
+            partition_id: "personal".to_string(),
+            valid_from: None,
+            valid_to: None,
+            cross_partition: false,
+        }
+    }
+
+    #[test]
+    fn test_validate_node() {
+        let schema = schema();
+
+        assert!(schema
+            .validate_node(&person("a", json!({"name": "Alice", "age": 30})))
+            .is_ok());
+        assert!(schema
+            .validate_node(&person("a", json!({"name": "Alice", "age": null})))
+            .is_ok());
+
+        for bad in [
+            json!({}),
+            json!({"name": null}),
+            json!({"name": 42}),
+            json!({"name": "Alice", "age": 30.5}),
+            json!({"name": "Alice", "nickname": "Al"}),
+            json!(["Alice"]),
+        ] {
+            assert!(matches!(
+                schema.validate_node(&person("a", bad)),
+                Err(GraphError::SchemaViolation(_))
+            ));
+        }
+
+        let note = Node {
+            label: "Note".to_string(),
+            ..person("n", json!({"anything": [1, 2]}))
+        };
+        assert!(schema.validate_node(&note).is_ok());
+        let unknown = Node {
+            label: "Robot".to_string(),
+            ..person("r", json!({}))
+        };
+        assert!(schema.validate_node(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_schema_from_json() {
+        let schema: GraphSchema = serde_json::from_value(json!({
+            "labels": {
+                "Person": {
+                    "properties": {
+                        "name": {"type": "string", "required": true},
+                        "age": {"type": "integer"}
+                    }
+                }
+            },
+            "relations": ["knows"]
+        }))
+        .unwrap();
+
+        let mut expected = GraphSchema::new();
+        expected
+            .register_label(
+                "Person",
+                LabelSchema::new()
+                    .required("name", PropertyType::String)
+                    .optional("age", PropertyType::Integer),
+            )
+            .register_relation("knows");
+        assert_eq!(schema, expected);
+    }
+
+    #[tokio::test]
+    async fn test_schema_store_rejects_invalid_writes() {
+        let store = SchemaStore::new(MockGraphStore::new(), schema());
+
+        store
+            .add_nodes(vec![
+                person("alice", json!({"name": "Alice"})),
+                person("bob", json!({"name": "Bob"})),
+            ])
+            .await
+            .unwrap();
+        store.add_edge(edge("knows")).await.unwrap();
+
+        assert!(matches!(
+            store.add_edge(edge("hates")).await,
+            Err(GraphError::SchemaViolation(_))
+        ));
+        assert!(matches!(
+            store.update_node(person("bob", json!({"name": 7}))).await,
+            Err(GraphError::SchemaViolation(_))
+        ));
+
+        // A partial upsert is checked after merging
+        store
+            .upsert_node(person("bob", json!({"age": 40})))
+            .await
+            .unwrap();
+        let bob = store.get_node("bob").await.unwrap();
+        assert_eq!(bob.properties, json!({"name": "Bob", "age": 40}));
+        // So is a patch
+        assert!(matches!(
+            store.patch_node("bob", json!({"age": "forty"})).await,
+            Err(GraphError::SchemaViolation(_))
+        ));
+        let bob = store.patch_node("bob", json!({"age": 41})).await.unwrap();
+        assert_eq!(bob.properties, json!({"name": "Bob", "age": 41}));
+
+        // One bad op fails the whole transaction before anything is written
+        let mut tx = GraphTransaction::new();
+        tx.add_node(person("carol", json!({"name": "Carol"})))
+            .add_edge(edge("likes"));
+        assert!(store.commit_transaction(tx).await.is_err());
+        assert!(store.get_node("carol").await.is_err());
+
+        assert_eq!(store.inner().get_neighbors("alice").await.unwrap().len(), 1);
+    }
+}
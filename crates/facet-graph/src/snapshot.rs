@@ -0,0 +1,79 @@
+use crate::{Edge, GraphError, Node};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Point-in-time copy of a graph (or a slice of it) that can be written to
+/// disk and compared against another snapshot.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct GraphSnapshot {
+    #[serde(default)]
+    pub nodes: Vec<Node>,
+    #[serde(default)]
+    pub edges: Vec<Edge>,
+}
+
+impl GraphSnapshot {
+    pub fn new(nodes: Vec<Node>, edges: Vec<Edge>) -> Self {
+        Self { nodes, edges }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, GraphError> {
+        serde_json::from_str(json)
+            .map_err(|e| GraphError::Storage(format!("Invalid snapshot: {}", e)))
+    }
+
+    pub fn to_json(&self) -> Result<String, GraphError> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| GraphError::Storage(format!("Failed to serialize snapshot: {}", e)))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, GraphError> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            GraphError::Storage(format!("Failed to read snapshot {}: {}", path.display(), e))
+        })?;
+        Self::from_json(&contents)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), GraphError> {
+        std::fs::write(path, self.to_json()?).map_err(|e| {
+            GraphError::Storage(format!(
+                "Failed to write snapshot {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let snapshot = GraphSnapshot::new(
+            vec![Node {
+                id: "1".to_string(),
+                label: "Person".to_string(),
+                properties: json!({"name": "Alice"}),
+                partition_id: "personal".to_string(),
+            }],
+            vec![],
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.json");
+        snapshot.save(&path).unwrap();
+
+        let loaded = GraphSnapshot::load(&path).unwrap();
+        assert_eq!(loaded, snapshot);
+    }
+
+    #[test]
+    fn test_snapshot_missing_sections_default_to_empty() {
+        let snapshot = GraphSnapshot::from_json("{}").unwrap();
+        assert!(snapshot.nodes.is_empty());
+        assert!(snapshot.edges.is_empty());
+    }
+}
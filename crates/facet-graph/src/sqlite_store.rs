@@ -0,0 +1,1185 @@
+//! Lightweight SQLite backend
+//!
+//! Nodes, edges and embeddings live in plain tables, next to the
+//! `node_history` table filled while history mode is on. Traversals walk
+//! the edge table one hop at a time and vector search is a brute-force
+//! cosine scan, which is fine for personal graphs of a few thousand nodes.
+//! rusqlite is synchronous, so each call runs on a blocking thread rather
+//! than on the async runtime. Enabled with the `sqlite` feature.
+
+use crate::snapshot::GraphSnapshot;
+use crate::transaction::{GraphOp, GraphTransaction};
+use crate::{
+    merge_properties, text_match_score, traversal, CrossPartitionPolicy, Direction, Edge,
+    GraphError, GraphStore, Node, NodeOrder, NodeVersion, PageRequest, PartitionInfo,
+    StoredEmbedding, VectorStore,
+};
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS nodes (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        id TEXT NOT NULL UNIQUE,
+        label TEXT NOT NULL,
+        properties TEXT NOT NULL,
+        partition_id TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS nodes_partition ON nodes(partition_id);
+    CREATE INDEX IF NOT EXISTS nodes_label ON nodes(label, partition_id);
+    CREATE TABLE IF NOT EXISTS edges (
+        source TEXT NOT NULL,
+        relation TEXT NOT NULL,
+        target TEXT NOT NULL,
+        weight REAL NOT NULL,
+        partition_id TEXT NOT NULL,
+        valid_from TEXT,
+        valid_to TEXT,
+        cross_partition INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX IF NOT EXISTS edges_source ON edges(source);
+    CREATE INDEX IF NOT EXISTS edges_target ON edges(target);
+    CREATE TABLE IF NOT EXISTS embeddings (
+        id TEXT NOT NULL,
+        field TEXT NOT NULL,
+        vector BLOB NOT NULL,
+        PRIMARY KEY (id, field)
+    );
+    CREATE INDEX IF NOT EXISTS embeddings_field ON embeddings(field);
+    CREATE TABLE IF NOT EXISTS node_history (
+        id TEXT NOT NULL,
+        label TEXT NOT NULL,
+        properties TEXT NOT NULL,
+        partition_id TEXT NOT NULL,
+        recorded_at TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS node_history_id ON node_history(id);
+    CREATE TABLE IF NOT EXISTS partitions (
+        id TEXT PRIMARY KEY
+    );
+";
+
+/// History mode: copies a node into `node_history` just before an update
+/// changes it or a delete removes it. Timestamps only have millisecond
+/// precision, so each version is stamped at least 2ms after the previous
+/// one of the same node to keep them distinct for `restore_node_version`.
+const HISTORY_TRIGGERS: &str = "
+    CREATE TRIGGER IF NOT EXISTS node_history_update AFTER UPDATE ON nodes
+    WHEN OLD.label IS NOT NEW.label
+        OR OLD.properties IS NOT NEW.properties
+        OR OLD.partition_id IS NOT NEW.partition_id
+    BEGIN
+        INSERT INTO node_history (id, label, properties, partition_id, recorded_at)
+        VALUES (OLD.id, OLD.label, OLD.properties, OLD.partition_id,
+                strftime('%Y-%m-%dT%H:%M:%fZ', max(julianday('now'), coalesce(
+                    (SELECT julianday(max(recorded_at)) + 0.002 / 86400.0
+                     FROM node_history WHERE id = OLD.id), 0))));
+    END;
+    CREATE TRIGGER IF NOT EXISTS node_history_delete AFTER DELETE ON nodes
+    BEGIN
+        INSERT INTO node_history (id, label, properties, partition_id, recorded_at)
+        VALUES (OLD.id, OLD.label, OLD.properties, OLD.partition_id,
+                strftime('%Y-%m-%dT%H:%M:%fZ', max(julianday('now'), coalesce(
+                    (SELECT julianday(max(recorded_at)) + 0.002 / 86400.0
+                     FROM node_history WHERE id = OLD.id), 0))));
+    END;
+";
+
+/// Value of `embeddings.field` for the default embedding space
+const DEFAULT_FIELD: &str = "";
+
+const NODE_COLUMNS: &str = "id, label, properties, partition_id";
+const EDGE_COLUMNS: &str =
+    "rowid, source, relation, target, weight, partition_id, valid_from, valid_to, cross_partition";
+
+#[derive(Clone)]
+pub struct SqliteStore {
+    conn: Arc<Mutex<Connection>>,
+    cross_partition: CrossPartitionPolicy,
+}
+
+impl SqliteStore {
+    pub fn new(path: PathBuf) -> Result<Self, GraphError> {
+        Self::init(Connection::open(path).map_err(storage)?)
+    }
+
+    pub fn in_memory() -> Result<Self, GraphError> {
+        Self::init(Connection::open_in_memory().map_err(storage)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, GraphError> {
+        conn.execute_batch(SCHEMA).map_err(storage)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            cross_partition: CrossPartitionPolicy::default(),
+        })
+    }
+
+    /// Sets what `add_edge`, `add_edges` and `commit_transaction` do with
+    /// edges between partitions (allowed by default)
+    pub fn with_cross_partition_policy(mut self, policy: CrossPartitionPolicy) -> Self {
+        self.cross_partition = policy;
+        self
+    }
+
+    /// Turns history mode on or off (see `GraphStore::get_node_history`).
+    /// The triggers live in the database file, so the setting survives
+    /// reopening; turning it off keeps the versions recorded so far.
+    ///
+    /// Blocks on the database, so call it off the async runtime.
+    pub fn set_history(&self, enabled: bool) -> Result<(), GraphError> {
+        let sql = if enabled {
+            HISTORY_TRIGGERS
+        } else {
+            "DROP TRIGGER IF EXISTS node_history_update; DROP TRIGGER IF EXISTS node_history_delete;"
+        };
+        lock(&self.conn).execute_batch(sql).map_err(storage)
+    }
+
+    /// Runs `f` on the connection on a blocking thread, so a slow query
+    /// holds up neither the async runtime nor the callers waiting for the
+    /// lock on it
+    async fn with_conn<T, F>(&self, f: F) -> Result<T, GraphError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T, GraphError> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        match tokio::task::spawn_blocking(move || f(&mut lock(&conn))).await {
+            Ok(result) => result,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(e) => Err(GraphError::Storage(e.to_string())),
+        }
+    }
+}
+
+fn lock(conn: &Mutex<Connection>) -> MutexGuard<'_, Connection> {
+    conn.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn storage(e: rusqlite::Error) -> GraphError {
+    GraphError::Storage(e.to_string())
+}
+
+fn node_from_row(row: &Row) -> rusqlite::Result<Node> {
+    let properties: String = row.get(2)?;
+    Ok(Node {
+        id: row.get(0)?,
+        label: row.get(1)?,
+        properties: serde_json::from_str(&properties).unwrap_or(serde_json::Value::Null),
+        partition_id: row.get(3)?,
+    })
+}
+
+/// Reads an edge row selected with `EDGE_COLUMNS`, keyed by its rowid
+fn edge_from_row(row: &Row) -> rusqlite::Result<(i64, Edge)> {
+    Ok((
+        row.get(0)?,
+        Edge {
+            source: row.get(1)?,
+            relation: row.get(2)?,
+            target: row.get(3)?,
+            weight: row.get::<_, f64>(4)? as f32,
+            partition_id: row.get(5)?,
+            valid_from: row.get(6)?,
+            valid_to: row.get(7)?,
+            cross_partition: row.get(8)?,
+        },
+    ))
+}
+
+fn load_node(conn: &Connection, id: &str) -> Result<Option<Node>, GraphError> {
+    conn.query_row(
+        &format!("SELECT {NODE_COLUMNS} FROM nodes WHERE id = ?1"),
+        [id],
+        node_from_row,
+    )
+    .optional()
+    .map_err(storage)
+}
+
+fn insert_node(conn: &Connection, node: &Node) -> Result<(), GraphError> {
+    let result = conn.execute(
+        "INSERT INTO nodes (id, label, properties, partition_id) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            node.id,
+            node.label,
+            node.properties.to_string(),
+            node.partition_id
+        ],
+    );
+    match result {
+        Ok(_) => Ok(()),
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            Err(GraphError::Conflict(format!(
+                "Node already exists: {}",
+                node.id
+            )))
+        }
+        Err(e) => Err(storage(e)),
+    }
+}
+
+fn insert_edge(
+    conn: &Connection,
+    edge: &Edge,
+    policy: CrossPartitionPolicy,
+) -> Result<(), GraphError> {
+    let mut edge = edge.clone();
+    if policy != CrossPartitionPolicy::Allow {
+        let source = load_node(conn, &edge.source)?.map(|n| n.partition_id);
+        let target = load_node(conn, &edge.target)?.map(|n| n.partition_id);
+        policy.check(&mut edge, source.as_deref(), target.as_deref())?;
+    }
+
+    conn.execute(
+        "INSERT INTO edges (source, relation, target, weight, partition_id, valid_from, valid_to, \
+                            cross_partition) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            edge.source,
+            edge.relation,
+            edge.target,
+            edge.weight as f64,
+            edge.partition_id,
+            edge.valid_from,
+            edge.valid_to,
+            edge.cross_partition
+        ],
+    )
+    .map_err(storage)?;
+    Ok(())
+}
+
+fn update_node(conn: &Connection, node: &Node) -> Result<(), GraphError> {
+    let changed = conn
+        .execute(
+            "UPDATE nodes SET label = ?2, properties = ?3, partition_id = ?4 WHERE id = ?1",
+            params![
+                node.id,
+                node.label,
+                node.properties.to_string(),
+                node.partition_id
+            ],
+        )
+        .map_err(storage)?;
+    if changed == 0 {
+        return Err(GraphError::NotFound(node.id.clone()));
+    }
+    Ok(())
+}
+
+fn delete_node(conn: &Connection, id: &str, cascade: bool) -> Result<(), GraphError> {
+    if load_node(conn, id)?.is_none() {
+        return Err(GraphError::NotFound(id.to_string()));
+    }
+
+    let attached: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM edges WHERE source = ?1 OR target = ?1",
+            [id],
+            |row| row.get(0),
+        )
+        .map_err(storage)?;
+    if attached > 0 && !cascade {
+        return Err(GraphError::Conflict(format!(
+            "Node {} has {} attached edges",
+            id, attached
+        )));
+    }
+
+    conn.execute("DELETE FROM edges WHERE source = ?1 OR target = ?1", [id])
+        .map_err(storage)?;
+    conn.execute("DELETE FROM embeddings WHERE id = ?1", [id])
+        .map_err(storage)?;
+    conn.execute("DELETE FROM nodes WHERE id = ?1", [id])
+        .map_err(storage)?;
+    Ok(())
+}
+
+fn delete_edge(
+    conn: &Connection,
+    source: &str,
+    relation: &str,
+    target: &str,
+) -> Result<(), GraphError> {
+    let deleted = conn
+        .execute(
+            "DELETE FROM edges WHERE source = ?1 AND relation = ?2 AND target = ?3",
+            params![source, relation, target],
+        )
+        .map_err(storage)?;
+    if deleted == 0 {
+        return Err(edge_not_found(source, relation, target));
+    }
+    Ok(())
+}
+
+fn edge_not_found(source: &str, relation: &str, target: &str) -> GraphError {
+    GraphError::NotFound(format!("{}-[{}]->{}", source, relation, target))
+}
+
+fn update_edge(conn: &Connection, edge: &Edge) -> Result<(), GraphError> {
+    let changed = conn
+        .execute(
+            "UPDATE edges SET weight = ?4, partition_id = ?5, valid_from = ?6, valid_to = ?7 \
+             WHERE source = ?1 AND relation = ?2 AND target = ?3",
+            params![
+                edge.source,
+                edge.relation,
+                edge.target,
+                edge.weight as f64,
+                edge.partition_id,
+                edge.valid_from,
+                edge.valid_to
+            ],
+        )
+        .map_err(storage)?;
+    if changed == 0 {
+        return Err(edge_not_found(&edge.source, &edge.relation, &edge.target));
+    }
+    Ok(())
+}
+
+fn increment_edge_weight(
+    conn: &Connection,
+    source: &str,
+    relation: &str,
+    target: &str,
+    delta: f32,
+) -> Result<f32, GraphError> {
+    let mut stmt = conn
+        .prepare(
+            "UPDATE edges SET weight = weight + ?4 \
+             WHERE source = ?1 AND relation = ?2 AND target = ?3 RETURNING weight",
+        )
+        .map_err(storage)?;
+    let weights = stmt
+        .query_map(params![source, relation, target, delta as f64], |row| {
+            row.get::<_, f64>(0)
+        })
+        .map_err(storage)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(storage)?;
+    weights
+        .into_iter()
+        .map(|w| w as f32)
+        .reduce(f32::max)
+        .ok_or_else(|| edge_not_found(source, relation, target))
+}
+
+fn apply_op(
+    conn: &Connection,
+    op: &GraphOp,
+    policy: CrossPartitionPolicy,
+) -> Result<(), GraphError> {
+    match op {
+        GraphOp::AddNode(node) => insert_node(conn, node),
+        GraphOp::AddEdge(edge) => insert_edge(conn, edge, policy),
+        GraphOp::UpdateNode(node) => update_node(conn, node),
+        GraphOp::DeleteNode { id, cascade } => delete_node(conn, id, *cascade),
+        GraphOp::DeleteEdge {
+            source,
+            relation,
+            target,
+        } => delete_edge(conn, source, relation, target),
+    }
+}
+
+/// Runs `ops` inside one SQLite transaction, rolling back on the first error
+fn apply_atomically(
+    conn: &mut Connection,
+    ops: &[GraphOp],
+    policy: CrossPartitionPolicy,
+) -> Result<(), GraphError> {
+    let tx = conn.transaction().map_err(storage)?;
+    for op in ops {
+        apply_op(&tx, op, policy)?;
+    }
+    tx.commit().map_err(storage)
+}
+
+/// Number of nodes in the partition, or `None` if it does not exist
+fn partition_node_count(
+    conn: &Connection,
+    partition_id: &str,
+) -> Result<Option<usize>, GraphError> {
+    let (count, registered): (i64, bool) = conn
+        .query_row(
+            "SELECT (SELECT COUNT(*) FROM nodes WHERE partition_id = ?1), \
+                    EXISTS (SELECT 1 FROM partitions WHERE id = ?1)",
+            [partition_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(storage)?;
+    Ok((count > 0 || registered).then_some(count as usize))
+}
+
+/// One-hop edges of `id`, paired with the node on the far side
+fn neighbors(conn: &Connection, id: &str, incoming: bool) -> Result<Vec<(Edge, Node)>, GraphError> {
+    let (near, far) = if incoming {
+        ("target", "source")
+    } else {
+        ("source", "target")
+    };
+    let sql = format!(
+        "SELECT e.source, e.relation, e.target, e.weight, e.partition_id, \
+                e.valid_from, e.valid_to, e.cross_partition, \
+                n.id, n.label, n.properties, n.partition_id \
+         FROM edges e JOIN nodes n ON n.id = e.{far} \
+         WHERE e.{near} = ?1 ORDER BY e.rowid"
+    );
+
+    let mut stmt = conn.prepare(&sql).map_err(storage)?;
+    let rows = stmt
+        .query_map([id], |row| {
+            let properties: String = row.get(10)?;
+            Ok((
+                Edge {
+                    source: row.get(0)?,
+                    relation: row.get(1)?,
+                    target: row.get(2)?,
+                    weight: row.get::<_, f64>(3)? as f32,
+                    partition_id: row.get(4)?,
+                    valid_from: row.get(5)?,
+                    valid_to: row.get(6)?,
+                    cross_partition: row.get(7)?,
+                },
+                Node {
+                    id: row.get(8)?,
+                    label: row.get(9)?,
+                    properties: serde_json::from_str(&properties)
+                        .unwrap_or(serde_json::Value::Null),
+                    partition_id: row.get(11)?,
+                },
+            ))
+        })
+        .map_err(storage)?;
+    rows.collect::<Result<_, _>>().map_err(storage)
+}
+
+fn select_nodes<P: rusqlite::Params>(
+    conn: &Connection,
+    sql: &str,
+    params: P,
+) -> Result<Vec<Node>, GraphError> {
+    let mut stmt = conn.prepare(sql).map_err(storage)?;
+    let rows = stmt.query_map(params, node_from_row).map_err(storage)?;
+    rows.collect::<Result<_, _>>().map_err(storage)
+}
+
+fn select_edges<P: rusqlite::Params>(
+    conn: &Connection,
+    sql: &str,
+    params: P,
+) -> Result<Vec<(i64, Edge)>, GraphError> {
+    let mut stmt = conn.prepare(sql).map_err(storage)?;
+    let rows = stmt.query_map(params, edge_from_row).map_err(storage)?;
+    rows.collect::<Result<_, _>>().map_err(storage)
+}
+
+fn select_embeddings<P: rusqlite::Params>(
+    conn: &Connection,
+    sql: &str,
+    params: P,
+) -> Result<Vec<StoredEmbedding>, GraphError> {
+    let mut stmt = conn.prepare(sql).map_err(storage)?;
+    let rows = stmt
+        .query_map(params, |row| {
+            let field: String = row.get(1)?;
+            let blob: Vec<u8> = row.get(2)?;
+            Ok(StoredEmbedding {
+                id: row.get(0)?,
+                field: (field != DEFAULT_FIELD).then_some(field),
+                vector: blob_to_vector(&blob),
+            })
+        })
+        .map_err(storage)?;
+    rows.collect::<Result<_, _>>().map_err(storage)
+}
+
+fn vector_to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn blob_to_vector(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+fn cosine_similarity(v1: &[f32], v2: &[f32]) -> f32 {
+    let dot_product: f32 = v1.iter().zip(v2.iter()).map(|(a, b)| a * b).sum();
+    let norm_a: f32 = v1.iter().map(|a| a * a).sum::<f32>().sqrt();
+    let norm_b: f32 = v2.iter().map(|b| b * b).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot_product / (norm_a * norm_b)
+    }
+}
+
+/// Collects every edge within `depth` hops of `id`, then pairs each with the
+/// node at its far end
+fn traverse(
+    conn: &Connection,
+    id: &str,
+    depth: usize,
+    direction: Direction,
+    relation_filter: Option<&[&str]>,
+) -> Result<Vec<(Edge, Node)>, GraphError> {
+    if load_node(conn, id)?.is_none() {
+        return Err(GraphError::NotFound(id.to_string()));
+    }
+
+    // Collect every edge within reach, one hop at a time
+    let mut seen_edges = HashSet::new();
+    let mut edges = Vec::new();
+    let mut visited = HashSet::from([id.to_string()]);
+    let mut frontier = vec![id.to_string()];
+
+    for _ in 0..depth {
+        let mut next = Vec::new();
+        for node_id in &frontier {
+            let hop = select_edges(
+                conn,
+                &format!(
+                    "SELECT {EDGE_COLUMNS} FROM edges \
+                     WHERE (?2 AND source = ?1) OR (?3 AND target = ?1)"
+                ),
+                params![
+                    node_id,
+                    direction != Direction::Incoming,
+                    direction != Direction::Outgoing
+                ],
+            )?;
+            for (rowid, edge) in hop {
+                if relation_filter.is_some_and(|r| !r.contains(&edge.relation.as_str())) {
+                    continue;
+                }
+                if !seen_edges.insert(rowid) {
+                    continue;
+                }
+                for end in [&edge.source, &edge.target] {
+                    if visited.insert(end.clone()) {
+                        next.push(end.clone());
+                    }
+                }
+                edges.push(edge);
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+
+    let mut nodes = HashMap::new();
+    let mut result = Vec::new();
+    for (edge, far) in traversal::expand(&edges, id, depth, direction, relation_filter) {
+        if !nodes.contains_key(far) {
+            let node = load_node(conn, far)?;
+            nodes.insert(far.to_string(), node);
+        }
+        if let Some(Some(node)) = nodes.get(far) {
+            result.push((edge.clone(), node.clone()));
+        }
+    }
+    Ok(result)
+}
+
+#[async_trait]
+impl GraphStore for SqliteStore {
+    async fn add_node(&self, node: Node) -> Result<(), GraphError> {
+        self.with_conn(move |conn| insert_node(conn, &node)).await
+    }
+
+    async fn add_edge(&self, edge: Edge) -> Result<(), GraphError> {
+        let policy = self.cross_partition;
+        self.with_conn(move |conn| insert_edge(conn, &edge, policy))
+            .await
+    }
+
+    async fn get_node(&self, id: &str) -> Result<Node, GraphError> {
+        let id = id.to_string();
+        self.with_conn(move |conn| load_node(conn, &id)?.ok_or(GraphError::NotFound(id)))
+            .await
+    }
+
+    async fn get_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+        let id = id.to_string();
+        self.with_conn(move |conn| neighbors(conn, &id, false))
+            .await
+    }
+
+    async fn get_incoming_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+        let id = id.to_string();
+        self.with_conn(move |conn| neighbors(conn, &id, true)).await
+    }
+
+    async fn update_node(&self, node: Node) -> Result<(), GraphError> {
+        self.with_conn(move |conn| update_node(conn, &node)).await
+    }
+
+    async fn patch_node(&self, id: &str, patch: serde_json::Value) -> Result<Node, GraphError> {
+        let id = id.to_string();
+        // Read and write under one lock, so no other write lands in between
+        self.with_conn(move |conn| {
+            let mut node = load_node(conn, &id)?.ok_or_else(|| GraphError::NotFound(id.clone()))?;
+            merge_properties(&mut node.properties, patch);
+            update_node(conn, &node)?;
+            Ok(node)
+        })
+        .await
+    }
+
+    async fn get_node_history(&self, id: &str) -> Result<Vec<NodeVersion>, GraphError> {
+        let id = id.to_string();
+        self.with_conn(move |conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, label, properties, partition_id, recorded_at FROM node_history \
+                     WHERE id = ?1 ORDER BY rowid",
+                )
+                .map_err(storage)?;
+            let rows = stmt
+                .query_map([id], |row| {
+                    Ok(NodeVersion {
+                        node: node_from_row(row)?,
+                        recorded_at: row.get(4)?,
+                    })
+                })
+                .map_err(storage)?;
+            rows.collect::<Result<_, _>>().map_err(storage)
+        })
+        .await
+    }
+
+    async fn traverse(
+        &self,
+        id: &str,
+        depth: usize,
+        direction: Direction,
+        relation_filter: Option<&[&str]>,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        let id = id.to_string();
+        let relations: Option<Vec<String>> =
+            relation_filter.map(|r| r.iter().map(|s| s.to_string()).collect());
+        self.with_conn(move |conn| {
+            let relations: Option<Vec<&str>> = relations
+                .as_ref()
+                .map(|r| r.iter().map(String::as_str).collect());
+            traverse(conn, &id, depth, direction, relations.as_deref())
+        })
+        .await
+    }
+
+    async fn add_nodes(&self, nodes: Vec<Node>) -> Result<(), GraphError> {
+        let ops: Vec<GraphOp> = nodes.into_iter().map(GraphOp::AddNode).collect();
+        let policy = self.cross_partition;
+        self.with_conn(move |conn| apply_atomically(conn, &ops, policy))
+            .await
+    }
+
+    async fn add_edges(&self, edges: Vec<Edge>) -> Result<(), GraphError> {
+        let ops: Vec<GraphOp> = edges.into_iter().map(GraphOp::AddEdge).collect();
+        let policy = self.cross_partition;
+        self.with_conn(move |conn| apply_atomically(conn, &ops, policy))
+            .await
+    }
+
+    async fn delete_node(&self, id: &str, cascade: bool) -> Result<(), GraphError> {
+        let id = id.to_string();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction().map_err(storage)?;
+            delete_node(&tx, &id, cascade)?;
+            tx.commit().map_err(storage)
+        })
+        .await
+    }
+
+    async fn delete_edge(
+        &self,
+        source: &str,
+        relation: &str,
+        target: &str,
+    ) -> Result<(), GraphError> {
+        let (source, relation, target) =
+            (source.to_string(), relation.to_string(), target.to_string());
+        self.with_conn(move |conn| delete_edge(conn, &source, &relation, &target))
+            .await
+    }
+
+    async fn update_edge(&self, edge: Edge) -> Result<(), GraphError> {
+        self.with_conn(move |conn| update_edge(conn, &edge)).await
+    }
+
+    async fn increment_edge_weight(
+        &self,
+        source: &str,
+        relation: &str,
+        target: &str,
+        delta: f32,
+    ) -> Result<f32, GraphError> {
+        let (source, relation, target) =
+            (source.to_string(), relation.to_string(), target.to_string());
+        self.with_conn(move |conn| increment_edge_weight(conn, &source, &relation, &target, delta))
+            .await
+    }
+
+    async fn commit_transaction(&self, tx: GraphTransaction) -> Result<(), GraphError> {
+        let policy = self.cross_partition;
+        self.with_conn(move |conn| apply_atomically(conn, tx.ops(), policy))
+            .await
+    }
+
+    async fn search_text(&self, query: &str, limit: usize) -> Result<Vec<(Node, f32)>, GraphError> {
+        let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        let Some(first) = terms.first() else {
+            return Ok(vec![]);
+        };
+
+        // Narrow down with LIKE, then score the candidates exactly
+        let pattern = format!("%{}%", first);
+        let candidates = self
+            .with_conn(move |conn| {
+                select_nodes(
+                    conn,
+                    &format!("SELECT {NODE_COLUMNS} FROM nodes WHERE lower(properties) LIKE ?1"),
+                    [pattern],
+                )
+            })
+            .await?;
+
+        let mut results: Vec<(Node, f32)> = candidates
+            .into_iter()
+            .filter_map(|node| text_match_score(&node.properties, &terms).map(|s| (node, s)))
+            .collect();
+        results.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.0.id.cmp(&b.0.id))
+        });
+        results.truncate(limit);
+        Ok(results)
+    }
+
+    async fn query_by_partition(&self, partition_id: &str) -> Result<Vec<Node>, GraphError> {
+        let partition_id = partition_id.to_string();
+        self.with_conn(move |conn| {
+            select_nodes(
+                conn,
+                &format!("SELECT {NODE_COLUMNS} FROM nodes WHERE partition_id = ?1 ORDER BY seq"),
+                [partition_id],
+            )
+        })
+        .await
+    }
+
+    async fn query_by_label(
+        &self,
+        label: &str,
+        partition_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Node>, GraphError> {
+        let label = label.to_string();
+        let partition_id = partition_id.map(str::to_string);
+        self.with_conn(move |conn| {
+            select_nodes(
+                conn,
+                &format!(
+                    "SELECT {NODE_COLUMNS} FROM nodes \
+                     WHERE label = ?1 AND (?2 IS NULL OR partition_id = ?2) ORDER BY id LIMIT ?3"
+                ),
+                params![label, partition_id, limit as i64],
+            )
+        })
+        .await
+    }
+
+    async fn node_exists(&self, id: &str) -> Result<bool, GraphError> {
+        let id = id.to_string();
+        self.with_conn(move |conn| {
+            conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM nodes WHERE id = ?1)",
+                [id],
+                |row| row.get(0),
+            )
+            .map_err(storage)
+        })
+        .await
+    }
+
+    async fn count_nodes(&self, partition_id: Option<&str>) -> Result<usize, GraphError> {
+        let partition_id = partition_id.map(str::to_string);
+        let count: i64 = self
+            .with_conn(move |conn| {
+                conn.query_row(
+                    "SELECT COUNT(*) FROM nodes WHERE ?1 IS NULL OR partition_id = ?1",
+                    [partition_id],
+                    |row| row.get(0),
+                )
+                .map_err(storage)
+            })
+            .await?;
+        Ok(count as usize)
+    }
+
+    async fn count_edges(&self, relation: Option<&str>) -> Result<usize, GraphError> {
+        let relation = relation.map(str::to_string);
+        let count: i64 = self
+            .with_conn(move |conn| {
+                conn.query_row(
+                    "SELECT COUNT(*) FROM edges WHERE ?1 IS NULL OR relation = ?1",
+                    [relation],
+                    |row| row.get(0),
+                )
+                .map_err(storage)
+            })
+            .await?;
+        Ok(count as usize)
+    }
+
+    async fn snapshot(&self, partition_id: Option<&str>) -> Result<GraphSnapshot, GraphError> {
+        let partition_id = partition_id.map(str::to_string);
+        self.with_conn(move |conn| {
+            let nodes = select_nodes(
+                conn,
+                &format!(
+                    "SELECT {NODE_COLUMNS} FROM nodes \
+                     WHERE ?1 IS NULL OR partition_id = ?1 ORDER BY id"
+                ),
+                [&partition_id],
+            )?;
+            let edges = select_edges(
+                conn,
+                &format!(
+                    "SELECT {EDGE_COLUMNS} FROM edges e \
+                     WHERE ?1 IS NULL OR (e.partition_id = ?1 \
+                        AND EXISTS (SELECT 1 FROM nodes n WHERE n.id = e.source AND n.partition_id = ?1) \
+                        AND EXISTS (SELECT 1 FROM nodes n WHERE n.id = e.target AND n.partition_id = ?1)) \
+                     ORDER BY e.rowid"
+                ),
+                [&partition_id],
+            )?;
+
+            Ok(GraphSnapshot::new(
+                nodes,
+                edges.into_iter().map(|(_, edge)| edge).collect(),
+            ))
+        })
+        .await
+    }
+
+    async fn query_by_partition_page(
+        &self,
+        partition_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Node>, GraphError> {
+        let field = match page.order {
+            NodeOrder::CreatedAt => "seq",
+            NodeOrder::Label => "label",
+            NodeOrder::Id => "id",
+        };
+        let dir = if page.descending { "DESC" } else { "ASC" };
+        let partition_id = partition_id.to_string();
+        self.with_conn(move |conn| {
+            select_nodes(
+                conn,
+                &format!(
+                    "SELECT {NODE_COLUMNS} FROM nodes WHERE partition_id = ?1 \
+                     ORDER BY {field} {dir}, id {dir} LIMIT ?2 OFFSET ?3"
+                ),
+                params![partition_id, page.limit as i64, page.offset as i64],
+            )
+        })
+        .await
+    }
+
+    async fn get_neighbors_in_partition(
+        &self,
+        id: &str,
+        partition_id: &str,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        Ok(self
+            .get_neighbors(id)
+            .await?
+            .into_iter()
+            .filter(|(e, n)| e.partition_id == partition_id && n.partition_id == partition_id)
+            .collect())
+    }
+
+    async fn create_partition(&self, partition_id: &str) -> Result<(), GraphError> {
+        let partition_id = partition_id.to_string();
+        self.with_conn(move |conn| {
+            if partition_node_count(conn, &partition_id)?.is_some() {
+                return Err(GraphError::Conflict(format!(
+                    "Partition already exists: {}",
+                    partition_id
+                )));
+            }
+            conn.execute("INSERT INTO partitions (id) VALUES (?1)", [&partition_id])
+                .map_err(storage)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list_partitions(&self) -> Result<Vec<PartitionInfo>, GraphError> {
+        self.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, SUM(n) FROM ( \
+                         SELECT partition_id AS id, 1 AS n FROM nodes \
+                         UNION ALL SELECT id, 0 FROM partitions \
+                     ) GROUP BY id ORDER BY id",
+                )
+                .map_err(storage)?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok(PartitionInfo {
+                        id: row.get(0)?,
+                        node_count: row.get::<_, i64>(1)? as usize,
+                    })
+                })
+                .map_err(storage)?;
+            rows.collect::<Result<_, _>>().map_err(storage)
+        })
+        .await
+    }
+
+    async fn rename_partition(&self, from: &str, to: &str) -> Result<(), GraphError> {
+        let (from, to) = (from.to_string(), to.to_string());
+        self.with_conn(move |conn| {
+            if partition_node_count(conn, &from)?.is_none() {
+                return Err(GraphError::NotFound(format!("Partition {}", from)));
+            }
+            if partition_node_count(conn, &to)?.is_some() {
+                return Err(GraphError::Conflict(format!(
+                    "Partition already exists: {}",
+                    to
+                )));
+            }
+
+            let tx = conn.transaction().map_err(storage)?;
+            tx.execute(
+                "UPDATE edges SET partition_id = ?2 WHERE partition_id = ?1 \
+                 AND (source IN (SELECT id FROM nodes WHERE partition_id = ?1) \
+                      OR target IN (SELECT id FROM nodes WHERE partition_id = ?1))",
+                [&from, &to],
+            )
+            .map_err(storage)?;
+            tx.execute(
+                "UPDATE nodes SET partition_id = ?2 WHERE partition_id = ?1",
+                [&from, &to],
+            )
+            .map_err(storage)?;
+            tx.execute("DELETE FROM partitions WHERE id = ?1", [&from])
+                .map_err(storage)?;
+            tx.execute("INSERT INTO partitions (id) VALUES (?1)", [&to])
+                .map_err(storage)?;
+            tx.commit().map_err(storage)
+        })
+        .await
+    }
+
+    async fn delete_partition(&self, partition_id: &str, cascade: bool) -> Result<(), GraphError> {
+        let partition_id = partition_id.to_string();
+        self.with_conn(move |conn| {
+            let Some(node_count) = partition_node_count(conn, &partition_id)? else {
+                return Err(GraphError::NotFound(format!("Partition {}", partition_id)));
+            };
+            if node_count > 0 && !cascade {
+                return Err(GraphError::Conflict(format!(
+                    "Partition {} still has {} nodes",
+                    partition_id, node_count
+                )));
+            }
+
+            let tx = conn.transaction().map_err(storage)?;
+            tx.execute(
+                "DELETE FROM edges \
+                 WHERE source IN (SELECT id FROM nodes WHERE partition_id = ?1) \
+                    OR target IN (SELECT id FROM nodes WHERE partition_id = ?1)",
+                [&partition_id],
+            )
+            .map_err(storage)?;
+            tx.execute(
+                "DELETE FROM embeddings WHERE id IN (SELECT id FROM nodes WHERE partition_id = ?1)",
+                [&partition_id],
+            )
+            .map_err(storage)?;
+            tx.execute("DELETE FROM nodes WHERE partition_id = ?1", [&partition_id])
+                .map_err(storage)?;
+            tx.execute("DELETE FROM partitions WHERE id = ?1", [&partition_id])
+                .map_err(storage)?;
+            tx.commit().map_err(storage)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl VectorStore for SqliteStore {
+    async fn add_embedding(
+        &self,
+        id: &str,
+        vector: Vec<f32>,
+        field: Option<&str>,
+    ) -> Result<(), GraphError> {
+        let id = id.to_string();
+        let field = field.unwrap_or(DEFAULT_FIELD).to_string();
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO embeddings (id, field, vector) VALUES (?1, ?2, ?3)",
+                params![id, field, vector_to_blob(&vector)],
+            )
+            .map_err(storage)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn search(
+        &self,
+        vector: Vec<f32>,
+        limit: usize,
+        field: Option<&str>,
+    ) -> Result<Vec<(String, f32)>, GraphError> {
+        let field = field.unwrap_or(DEFAULT_FIELD).to_string();
+        self.with_conn(move |conn| {
+            let mut stmt = conn
+                .prepare("SELECT id, vector FROM embeddings WHERE field = ?1")
+                .map_err(storage)?;
+            let rows = stmt
+                .query_map([field], |row| {
+                    let id: String = row.get(0)?;
+                    let blob: Vec<u8> = row.get(1)?;
+                    Ok((id, blob))
+                })
+                .map_err(storage)?;
+
+            let mut results = Vec::new();
+            for row in rows {
+                let (id, blob) = row.map_err(storage)?;
+                results.push((id, cosine_similarity(&vector, &blob_to_vector(&blob))));
+            }
+
+            results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            results.truncate(limit);
+            Ok(results)
+        })
+        .await
+    }
+
+    async fn add_embeddings(
+        &self,
+        embeddings: Vec<(String, Vec<f32>)>,
+        field: Option<&str>,
+    ) -> Result<(), GraphError> {
+        let field = field.unwrap_or(DEFAULT_FIELD).to_string();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction().map_err(storage)?;
+            {
+                let mut stmt = tx
+                    .prepare(
+                        "INSERT OR REPLACE INTO embeddings (id, field, vector) VALUES (?1, ?2, ?3)",
+                    )
+                    .map_err(storage)?;
+                for (id, vector) in &embeddings {
+                    stmt.execute(params![id, field, vector_to_blob(vector)])
+                        .map_err(storage)?;
+                }
+            }
+            tx.commit().map_err(storage)
+        })
+        .await
+    }
+
+    async fn remove_embedding(&self, id: &str) -> Result<(), GraphError> {
+        let id = id.to_string();
+        self.with_conn(move |conn| {
+            conn.execute("DELETE FROM embeddings WHERE id = ?1", [id])
+                .map_err(storage)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn clear_embeddings(&self, partition_id: &str) -> Result<(), GraphError> {
+        let partition_id = partition_id.to_string();
+        self.with_conn(move |conn| {
+            conn.execute(
+                "DELETE FROM embeddings \
+                 WHERE id IN (SELECT id FROM nodes WHERE partition_id = ?1)",
+                [partition_id],
+            )
+            .map_err(storage)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn search_nodes(
+        &self,
+        vector: Vec<f32>,
+        limit: usize,
+        field: Option<&str>,
+    ) -> Result<Vec<(Node, f32)>, GraphError> {
+        let field = field.unwrap_or(DEFAULT_FIELD).to_string();
+        self.with_conn(move |conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT n.id, n.label, n.properties, n.partition_id, e.vector \
+                     FROM embeddings e JOIN nodes n ON n.id = e.id WHERE e.field = ?1",
+                )
+                .map_err(storage)?;
+            let rows = stmt
+                .query_map([field], |row| {
+                    let blob: Vec<u8> = row.get(4)?;
+                    Ok((node_from_row(row)?, blob))
+                })
+                .map_err(storage)?;
+
+            let mut results = Vec::new();
+            for row in rows {
+                let (node, blob) = row.map_err(storage)?;
+                let score = cosine_similarity(&vector, &blob_to_vector(&blob));
+                results.push((node, score));
+            }
+
+            results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            results.truncate(limit);
+            Ok(results)
+        })
+        .await
+    }
+
+    async fn get_embeddings(&self, id: &str) -> Result<Vec<StoredEmbedding>, GraphError> {
+        let id = id.to_string();
+        self.with_conn(move |conn| {
+            select_embeddings(
+                conn,
+                "SELECT id, field, vector FROM embeddings WHERE id = ?1 ORDER BY field",
+                [id],
+            )
+        })
+        .await
+    }
+
+    async fn list_embeddings(&self) -> Result<Vec<StoredEmbedding>, GraphError> {
+        self.with_conn(|conn| {
+            select_embeddings(
+                conn,
+                "SELECT id, field, vector FROM embeddings ORDER BY id, field",
+                [],
+            )
+        })
+        .await
+    }
+}
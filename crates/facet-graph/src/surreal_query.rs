@@ -0,0 +1,181 @@
+//! Parameterised SurrealQL for `SurrealStore`
+//!
+//! A `SurrealQuery` is put together from fixed statement text, `Ident`s and
+//! bound values. Text can only be added as `&'static str`, so ids,
+//! partitions and other caller data always travel as parameters. The few
+//! names that have to appear in the text, such as relation tables and
+//! embedding fields, are checked and quoted by `Ident` first.
+
+use crate::GraphError;
+use serde::Serialize;
+use std::fmt;
+use surrealdb::engine::local::Db;
+use surrealdb::method::Query;
+use surrealdb::{Response, Surreal};
+
+/// A table or field name that is safe to place in statement text: one or
+/// more dot-separated segments of letters, digits and `_`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Ident(String);
+
+impl Ident {
+    /// `name` as a single segment, or `None` if it is not a valid one
+    pub(crate) fn new(name: &str) -> Option<Self> {
+        valid_segment(name).then(|| Ident(name.to_string()))
+    }
+
+    /// A dotted field path such as `embeddings.topic`
+    pub(crate) fn path(path: &str) -> Option<Self> {
+        path.split('.')
+            .all(valid_segment)
+            .then(|| Ident(path.to_string()))
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+fn valid_segment(segment: &str) -> bool {
+    !segment.is_empty() && segment.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Quotes every segment, so names that clash with keywords stay names
+impl fmt::Display for Ident {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.split('.').enumerate() {
+            if i > 0 {
+                f.write_str(".")?;
+            }
+            write!(f, "`{segment}`")?;
+        }
+        Ok(())
+    }
+}
+
+type Binding = Box<dyn for<'r> FnOnce(Query<'r, Db>) -> Query<'r, Db> + Send>;
+
+#[must_use]
+pub(crate) struct SurrealQuery {
+    sql: String,
+    bindings: Vec<Binding>,
+}
+
+impl SurrealQuery {
+    pub(crate) fn new(sql: &'static str) -> Self {
+        Self {
+            sql: sql.to_string(),
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Appends fixed statement text
+    pub(crate) fn push(mut self, sql: &'static str) -> Self {
+        self.sql.push_str(sql);
+        self
+    }
+
+    /// Appends a quoted table or field name
+    pub(crate) fn ident(mut self, ident: &Ident) -> Self {
+        self.sql.push_str(&ident.to_string());
+        self
+    }
+
+    /// Appends fixed text in which every `{i}` stands for `index`, for
+    /// statements repeated with numbered parameters such as `$n{i}`
+    pub(crate) fn numbered(mut self, sql: &'static str, index: usize) -> Self {
+        self.sql.push_str(&sql.replace("{i}", &index.to_string()));
+        self
+    }
+
+    /// Appends an integer, for the few clauses that take no parameters
+    /// (index definitions, KNN operators)
+    pub(crate) fn number(mut self, value: usize) -> Self {
+        self.sql.push_str(&value.to_string());
+        self
+    }
+
+    /// Binds `$name` to `value`
+    pub(crate) fn bind(self, name: &'static str, value: impl Serialize + Send + 'static) -> Self {
+        self.bind_named(name.to_string(), value)
+    }
+
+    /// Binds `$name{index}`, a parameter written by `numbered`
+    pub(crate) fn bind_nth(
+        self,
+        name: &'static str,
+        index: usize,
+        value: impl Serialize + Send + 'static,
+    ) -> Self {
+        self.bind_named(format!("{name}{index}"), value)
+    }
+
+    fn bind_named(mut self, name: String, value: impl Serialize + Send + 'static) -> Self {
+        self.bindings
+            .push(Box::new(move |query| query.bind((name, value))));
+        self
+    }
+
+    /// The statement text, without the bound values
+    #[cfg(test)]
+    pub(crate) fn text(&self) -> &str {
+        &self.sql
+    }
+
+    /// Sends the query. Errors of individual statements stay in the
+    /// response, for callers that inspect them.
+    pub(crate) async fn send(self, db: &Surreal<Db>) -> Result<Response, GraphError> {
+        let mut query = db.query(self.sql);
+        for binding in self.bindings {
+            query = binding(query);
+        }
+        query.await.map_err(|e| GraphError::Storage(e.to_string()))
+    }
+
+    /// Sends the query and fails if any statement failed
+    pub(crate) async fn run(self, db: &Surreal<Db>) -> Result<Response, GraphError> {
+        self.send(db)
+            .await?
+            .check()
+            .map_err(|e| GraphError::Storage(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ident_validation() {
+        assert_eq!(Ident::new("knows").unwrap().to_string(), "`knows`");
+        assert_eq!(Ident::new("works_at2").unwrap().as_str(), "works_at2");
+        for bad in ["", "a b", "a-b", "a.b", "x`; DELETE node; `", "a\nb"] {
+            assert!(Ident::new(bad).is_none(), "{bad:?}");
+        }
+
+        let path = Ident::path("embeddings.topic").unwrap();
+        assert_eq!(path.to_string(), "`embeddings`.`topic`");
+        for bad in ["", "embeddings.", ".topic", "embeddings..topic", "a.b c"] {
+            assert!(Ident::path(bad).is_none(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn test_query_text() {
+        let relation = Ident::new("select").unwrap();
+        let query = SurrealQuery::new("RELATE ")
+            .numbered("$s{i}->", 3)
+            .ident(&relation)
+            .numbered("->$t{i}", 3)
+            .push(" SET weight = $weight; SELECT * FROM node LIMIT ")
+            .number(10)
+            .bind_nth("s", 3, "a")
+            .bind_nth("t", 3, "b")
+            .bind("weight", 0.5);
+        assert_eq!(
+            query.text(),
+            "RELATE $s3->`select`->$t3 SET weight = $weight; SELECT * FROM node LIMIT 10"
+        );
+        assert_eq!(query.bindings.len(), 3);
+    }
+}
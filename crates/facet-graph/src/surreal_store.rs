@@ -1,17 +1,192 @@
-use crate::{Edge, GraphError, GraphStore, Node, VectorStore};
+use crate::encryption::PayloadCipher;
+use crate::migrations::{self, MIGRATIONS};
+use crate::snapshot::GraphSnapshot;
+use crate::surreal_query::{Ident, SurrealQuery};
+use crate::transaction::{enforce_partition_policy, GraphOp, GraphTransaction};
+use crate::{
+    merge_properties, text_match_score, traversal, CrossPartitionPolicy, Direction, Edge,
+    GraphError, GraphStore, Node, NodeOrder, NodeVersion, PageRequest, PartitionInfo,
+    StoredEmbedding, VectorStore,
+};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use facet_types::profiles::crypto::EncryptionKey;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use surrealdb::engine::local::{Db, RocksDb};
+use surrealdb::sql::{Id, Thing};
 use surrealdb::Surreal;
 
+/// Full-text index over the string values of `properties`. `search_text` is
+/// recomputed by the database on every write, whichever path made it.
+/// `created_at` is set on first write and kept on later updates.
+const SCHEMA: &str = "
+    DEFINE ANALYZER IF NOT EXISTS node_text TOKENIZERS blank, class, punct FILTERS lowercase, ascii;
+    DEFINE FIELD IF NOT EXISTS search_text ON node VALUE
+        IF type::is::object(properties) THEN
+            array::join(array::filter(object::values(properties), |$v| type::is::string($v)), ' ')
+        ELSE '' END;
+    DEFINE INDEX IF NOT EXISTS node_text_idx ON node FIELDS search_text SEARCH ANALYZER node_text BM25;
+    DEFINE INDEX IF NOT EXISTS node_label_idx ON node FIELDS label;
+    DEFINE FIELD IF NOT EXISTS created_at ON node VALUE $before OR time::now();
+    DEFINE INDEX IF NOT EXISTS node_expiry_at_idx ON node_expiry FIELDS expires_at;
+    DEFINE EVENT IF NOT EXISTS node_expiry_cleanup ON TABLE node WHEN $event = 'DELETE'
+        THEN { DELETE type::thing('node_expiry', record::id($before.id)) };
+";
+
+/// History mode: copies a node into `node_history` just before an update
+/// changes it or a delete removes it. Embedding-only writes are skipped.
+const HISTORY_SCHEMA: &str = "
+    DEFINE INDEX IF NOT EXISTS node_history_node_idx ON node_history FIELDS node;
+    DEFINE EVENT IF NOT EXISTS node_history ON TABLE node
+        WHEN $event = 'DELETE' OR ($event = 'UPDATE' AND (
+            $before.label != $after.label
+            OR $before.properties != $after.properties
+            OR $before.partition_id != $after.partition_id
+        ))
+        THEN {
+            CREATE node_history SET node = record::id($before.id), label = $before.label,
+                properties = $before.properties, partition_id = $before.partition_id,
+                recorded_at = time::now()
+        };
+";
+
+/// Approximate nearest-neighbour index over node embeddings, used by
+/// `VectorStore::search`.
+///
+/// The default embedding space is indexed when the store is opened; each
+/// named space gets its own index, with the same parameters, on its first
+/// write. Indexes are defined once: to change their parameters later, remove
+/// them (e.g. `node_embedding_idx`) and reopen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum VectorIndex {
+    /// HNSW graph: `m` links per node, `efc` candidates at build time and
+    /// `ef` candidates at query time
+    Hnsw {
+        dimension: usize,
+        m: usize,
+        efc: usize,
+        ef: usize,
+    },
+    /// M-tree with `capacity` entries per tree node
+    Mtree { dimension: usize, capacity: usize },
+}
+
+impl VectorIndex {
+    pub fn hnsw(dimension: usize) -> Self {
+        VectorIndex::Hnsw {
+            dimension,
+            m: 12,
+            efc: 150,
+            ef: 40,
+        }
+    }
+
+    pub fn mtree(dimension: usize) -> Self {
+        VectorIndex::Mtree {
+            dimension,
+            capacity: 40,
+        }
+    }
+
+    pub fn dimension(&self) -> usize {
+        match self {
+            VectorIndex::Hnsw { dimension, .. } | VectorIndex::Mtree { dimension, .. } => {
+                *dimension
+            }
+        }
+    }
+
+    /// `DEFINE INDEX` statement for the embedding column at `path`
+    fn definition(&self, path: &Ident) -> SurrealQuery {
+        let name = Ident::new(&format!("node_{}_idx", path.as_str().replace('.', "_")))
+            .expect("index names are built from valid field paths");
+        let query = SurrealQuery::new("DEFINE INDEX IF NOT EXISTS ")
+            .ident(&name)
+            .push(" ON node FIELDS ")
+            .ident(path);
+        match self {
+            VectorIndex::Hnsw {
+                dimension, m, efc, ..
+            } => query
+                .push(" HNSW DIMENSION ")
+                .number(*dimension)
+                .push(" DIST COSINE EFC ")
+                .number(*efc)
+                .push(" M ")
+                .number(*m),
+            VectorIndex::Mtree {
+                dimension,
+                capacity,
+            } => query
+                .push(" MTREE DIMENSION ")
+                .number(*dimension)
+                .push(" DIST COSINE CAPACITY ")
+                .number(*capacity),
+        }
+        .push(";")
+    }
+}
+
+/// Where the vectors of an embedding space live: the default space in
+/// `embedding`, named spaces under `embeddings.<field>`
+fn embedding_path(field: Option<&str>) -> Result<Ident, GraphError> {
+    let path = match field {
+        None => Ident::new("embedding"),
+        Some(name) => Ident::new(name).and_then(|_| Ident::path(&format!("embeddings.{name}"))),
+    };
+    path.ok_or_else(|| {
+        GraphError::Storage(format!(
+            "Invalid embedding field: {}",
+            field.unwrap_or_default()
+        ))
+    })
+}
+
+/// Appends the KNN operator for `search`. Without an index SurrealDB falls
+/// back to a brute-force scan, which is still cheaper than sorting every node.
+fn knn_operator(query: SurrealQuery, index: Option<&VectorIndex>, limit: usize) -> SurrealQuery {
+    let query = query.push("<|").number(limit);
+    match index {
+        Some(VectorIndex::Hnsw { ef, .. }) => query.push(",").number(*ef).push("|>"),
+        Some(VectorIndex::Mtree { .. }) => query.push("|>"),
+        None => query.push(",COSINE|>"),
+    }
+}
+
+/// Graph and vector store on an embedded SurrealDB
+///
+/// Clones share one datastore. The embedded engine runs each request on its
+/// own task, so calls made through clones from different tasks proceed
+/// concurrently instead of queueing behind one another; hand a clone to each
+/// task rather than wrapping the store in a lock.
 #[derive(Clone)]
 pub struct SurrealStore {
     db: Surreal<Db>,
+    vector_index: Option<VectorIndex>,
+    // Embedding paths whose index is known to exist
+    indexed: Arc<Mutex<HashSet<String>>>,
+    cross_partition: CrossPartitionPolicy,
+    cipher: Option<PayloadCipher>,
+    embedding_dimension: Option<usize>,
 }
 
 impl SurrealStore {
     pub async fn new(path: PathBuf) -> Result<Self, GraphError> {
+        Self::open(path, None).await
+    }
+
+    /// Opens the store and makes sure `index` exists on the default
+    /// embedding space
+    pub async fn with_vector_index(path: PathBuf, index: VectorIndex) -> Result<Self, GraphError> {
+        Self::open(path, Some(index)).await
+    }
+
+    async fn open(path: PathBuf, vector_index: Option<VectorIndex>) -> Result<Self, GraphError> {
         let db = Surreal::new::<RocksDb>(path)
             .await
             .map_err(|e| GraphError::Storage(e.to_string()))?;
@@ -21,7 +196,396 @@ impl SurrealStore {
             .await
             .map_err(|e| GraphError::Storage(e.to_string()))?;
 
-        Ok(Self { db })
+        SurrealQuery::new(SCHEMA).run(&db).await?;
+        migrations::run(&db, MIGRATIONS).await?;
+
+        let store = Self {
+            db,
+            vector_index,
+            indexed: Arc::new(Mutex::new(HashSet::new())),
+            cross_partition: CrossPartitionPolicy::default(),
+            cipher: None,
+            embedding_dimension: None,
+        };
+        store.ensure_vector_index(&embedding_path(None)?).await?;
+        Ok(store)
+    }
+
+    /// Version of the last migration applied to the database (see the
+    /// `migrations` module)
+    pub async fn schema_version(&self) -> Result<u32, GraphError> {
+        migrations::current_version(&self.db).await
+    }
+
+    #[cfg(test)]
+    pub(crate) fn db(&self) -> &Surreal<Db> {
+        &self.db
+    }
+
+    /// Sets what `add_edge`, `add_edges` and `commit_transaction` do with
+    /// edges between partitions (allowed by default)
+    pub fn with_cross_partition_policy(mut self, policy: CrossPartitionPolicy) -> Self {
+        self.cross_partition = policy;
+        self
+    }
+
+    /// Encrypts node properties and embeddings with `key` before they are
+    /// written; the same key has to be used every time the store is opened.
+    /// Sealed payloads cannot be indexed, so `search_text` and vector search
+    /// decrypt and scan every candidate node instead.
+    pub fn with_encryption(mut self, key: EncryptionKey) -> Self {
+        self.cipher = Some(PayloadCipher::new(key));
+        self
+    }
+
+    /// Rejects embeddings and search vectors whose length is not
+    /// `dimension`, in every embedding space, with `DimensionMismatch`.
+    /// Without it, a store with a vector index expects the index dimension
+    /// and any other store accepts any length.
+    pub fn with_embedding_dimension(mut self, dimension: usize) -> Self {
+        self.embedding_dimension = Some(dimension);
+        self
+    }
+
+    /// Length every vector must have, if the store checks it
+    pub fn embedding_dimension(&self) -> Option<usize> {
+        self.embedding_dimension
+            .or_else(|| self.vector_index.map(|index| index.dimension()))
+    }
+
+    fn check_dimension(&self, vector: &[f32]) -> Result<(), GraphError> {
+        match self.embedding_dimension() {
+            Some(expected) if vector.len() != expected => Err(GraphError::DimensionMismatch {
+                expected,
+                actual: vector.len(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Turns history mode on or off (see `GraphStore::get_node_history`).
+    /// The setting lives in the database, so it survives reopening; turning
+    /// it off keeps the versions recorded so far.
+    pub async fn set_history(&self, enabled: bool) -> Result<(), GraphError> {
+        let sql = if enabled {
+            HISTORY_SCHEMA
+        } else {
+            "REMOVE EVENT IF EXISTS node_history ON TABLE node;"
+        };
+        SurrealQuery::new(sql).run(&self.db).await?;
+        Ok(())
+    }
+
+    /// Schedules `id` to be deleted by `purge_expired` once `expires_at` has
+    /// passed, or cancels that with `None`. Expiry is stored apart from the
+    /// node record, so later updates to the node keep it.
+    pub async fn set_expiry(
+        &self,
+        id: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(), GraphError> {
+        let sql = match expires_at {
+            Some(_) => "UPSERT $expiry SET expires_at = <datetime> $at RETURN NONE",
+            None => "DELETE $expiry",
+        };
+        SurrealQuery::new(sql)
+            .bind("expiry", Thing::from(("node_expiry", id)))
+            .bind("at", expires_at)
+            .run(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    /// `set_expiry` to `ttl` from now
+    pub async fn expire_after(&self, id: &str, ttl: Duration) -> Result<(), GraphError> {
+        let ttl = chrono::Duration::from_std(ttl)
+            .map_err(|e| GraphError::Storage(format!("Invalid TTL: {}", e)))?;
+        self.set_expiry(id, Some(Utc::now() + ttl)).await
+    }
+
+    pub async fn get_expiry(&self, id: &str) -> Result<Option<DateTime<Utc>>, GraphError> {
+        let mut response = SurrealQuery::new("SELECT VALUE expires_at FROM $expiry")
+            .bind("expiry", Thing::from(("node_expiry", id)))
+            .send(&self.db)
+            .await?;
+        let expires_at: Option<DateTime<Utc>> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+        Ok(expires_at)
+    }
+
+    /// Deletes every node whose expiry has passed, along with its edges and
+    /// embeddings. Returns how many nodes were removed.
+    pub async fn purge_expired(&self) -> Result<usize, GraphError> {
+        let sql = "BEGIN TRANSACTION; \
+                   LET $due = (SELECT VALUE id FROM node_expiry WHERE expires_at <= time::now()); \
+                   LET $nodes = $due.map(|$e| type::thing('node', record::id($e))); \
+                   DELETE array::flatten((SELECT VALUE array::union(->?, <-?) FROM $nodes)); \
+                   LET $purged = (DELETE $nodes RETURN BEFORE); \
+                   DELETE $due; \
+                   RETURN array::len($purged); \
+                   COMMIT TRANSACTION;";
+
+        let mut response = SurrealQuery::new(sql).send(&self.db).await?;
+        let last = response.num_statements().saturating_sub(1);
+        let purged: Option<usize> = response
+            .take(last)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+        Ok(purged.unwrap_or(0))
+    }
+
+    /// Runs `purge_expired` every `interval` in the background until the
+    /// returned handle is aborted
+    pub fn spawn_expiry_sweeper(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                // A failed sweep is simply retried on the next tick
+                let _ = store.purge_expired().await;
+            }
+        })
+    }
+
+    /// Partition of each stored node in `ids`; missing nodes are left out
+    async fn node_partitions(
+        &self,
+        ids: impl IntoIterator<Item = &str>,
+    ) -> Result<HashMap<String, String>, GraphError> {
+        #[derive(Deserialize)]
+        struct PartitionRow {
+            id: String,
+            partition_id: String,
+        }
+
+        let things: Vec<Thing> = ids.into_iter().map(node_thing).collect();
+        let mut response =
+            SurrealQuery::new("SELECT record::id(id) AS id, partition_id FROM $nodes")
+                .bind("nodes", things)
+                .send(&self.db)
+                .await?;
+        let rows: Vec<PartitionRow> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+        Ok(rows.into_iter().map(|r| (r.id, r.partition_id)).collect())
+    }
+
+    /// Applies the cross-partition policy to `edges`
+    async fn check_edges(&self, edges: &mut [Edge]) -> Result<(), GraphError> {
+        if self.cross_partition == CrossPartitionPolicy::Allow {
+            return Ok(());
+        }
+        let stored = self
+            .node_partitions(
+                edges
+                    .iter()
+                    .flat_map(|e| [e.source.as_str(), e.target.as_str()]),
+            )
+            .await?;
+        for edge in edges.iter_mut() {
+            let source = stored.get(&edge.source).cloned();
+            let target = stored.get(&edge.target).cloned();
+            self.cross_partition
+                .check(edge, source.as_deref(), target.as_deref())?;
+        }
+        Ok(())
+    }
+
+    /// Applies the cross-partition policy to the edges in `ops`
+    async fn check_partitions(&self, ops: &mut [GraphOp]) -> Result<(), GraphError> {
+        if self.cross_partition == CrossPartitionPolicy::Allow {
+            return Ok(());
+        }
+        let stored = self
+            .node_partitions(ops.iter().flat_map(|op| match op {
+                GraphOp::AddEdge(edge) => vec![edge.source.as_str(), edge.target.as_str()],
+                _ => Vec::new(),
+            }))
+            .await?;
+        enforce_partition_policy(self.cross_partition, ops, &stored)
+    }
+
+    /// Number of nodes in the partition, or `None` if it does not exist
+    async fn partition_node_count(&self, partition_id: &str) -> Result<Option<usize>, GraphError> {
+        let mut response = SurrealQuery::new(
+            "LET $count = (SELECT count() FROM node WHERE partition_id = $partition GROUP ALL)[0].count OR 0; \
+             RETURN IF $count > 0 OR array::len((SELECT VALUE id FROM $record)) > 0 \
+             THEN $count ELSE NONE END;",
+        )
+        .bind("partition", partition_id.to_string())
+        .bind("record", partition_thing(partition_id))
+        .send(&self.db)
+        .await?;
+        let last = response.num_statements().saturating_sub(1);
+        response
+            .take(last)
+            .map_err(|e| GraphError::Storage(e.to_string()))
+    }
+
+    fn seal_properties(
+        &self,
+        properties: serde_json::Value,
+    ) -> Result<serde_json::Value, GraphError> {
+        match &self.cipher {
+            Some(cipher) => cipher.seal_properties(&properties),
+            None => Ok(properties),
+        }
+    }
+
+    fn node_content(&self, node: Node) -> Result<NodeContent, GraphError> {
+        Ok(NodeContent {
+            label: node.label,
+            properties: self.seal_properties(node.properties)?,
+            partition_id: node.partition_id,
+        })
+    }
+
+    /// Decrypts the properties of a node read back from the database
+    fn open_node(&self, node: impl Into<Node>) -> Result<Node, GraphError> {
+        let mut node = node.into();
+        if let Some(cipher) = &self.cipher {
+            node.properties = cipher.open_properties(node.properties)?;
+        }
+        Ok(node)
+    }
+
+    fn open_nodes(&self, nodes: Vec<SurrealNode>) -> Result<Vec<Node>, GraphError> {
+        nodes.into_iter().map(|sn| self.open_node(sn)).collect()
+    }
+
+    /// Every node with a sealed embedding at `path`, scored against `query`
+    /// by cosine similarity, best first
+    async fn scan_sealed(
+        &self,
+        cipher: &PayloadCipher,
+        path: &Ident,
+        query: &[f32],
+        limit: usize,
+    ) -> Result<Vec<(Node, f32)>, GraphError> {
+        #[derive(Deserialize)]
+        struct SealedRow {
+            id: Thing,
+            label: String,
+            properties: serde_json::Value,
+            partition_id: String,
+            sealed: String,
+        }
+
+        let sealed = sealed_path(path);
+        let mut response = SurrealQuery::new("SELECT id, label, properties, partition_id, ")
+            .ident(&sealed)
+            .push(" AS sealed FROM node WHERE ")
+            .ident(&sealed)
+            .push(" != NONE")
+            .send(&self.db)
+            .await?;
+        let rows: Vec<SealedRow> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+
+        let mut hits = Vec::with_capacity(rows.len());
+        for row in rows {
+            let vector = cipher.open_vector(&row.sealed)?;
+            let node = self.open_node(SurrealNode {
+                id: row.id,
+                label: row.label,
+                properties: row.properties,
+                partition_id: row.partition_id,
+            })?;
+            hits.push((node, cosine_similarity(query, &vector)));
+        }
+        hits.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.0.id.cmp(&b.0.id))
+        });
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    /// Defines the configured vector index on `path` the first time the
+    /// embedding space is written to
+    async fn ensure_vector_index(&self, path: &Ident) -> Result<(), GraphError> {
+        let Some(index) = &self.vector_index else {
+            return Ok(());
+        };
+        if self.indexed.lock().unwrap().contains(path.as_str()) {
+            return Ok(());
+        }
+
+        index.definition(path).run(&self.db).await?;
+        self.indexed
+            .lock()
+            .unwrap()
+            .insert(path.as_str().to_string());
+        Ok(())
+    }
+
+    /// Embeddings in every space, of node `id` or of all nodes, ordered by
+    /// node and then space
+    async fn stored_embeddings(
+        &self,
+        id: Option<&str>,
+    ) -> Result<Vec<StoredEmbedding>, GraphError> {
+        #[derive(Deserialize)]
+        struct EmbeddingRow {
+            id: String,
+            embedding: Option<Vec<f32>>,
+            embeddings: Option<BTreeMap<String, Vec<f32>>>,
+            sealed_embedding: Option<String>,
+            sealed_embeddings: Option<BTreeMap<String, String>>,
+        }
+
+        let query = SurrealQuery::new(
+            "SELECT record::id(id) AS id, embedding, embeddings, sealed_embedding, \
+                    sealed_embeddings FROM ",
+        );
+        let query = match id {
+            Some(id) => query.push("$node").bind("node", node_thing(id)),
+            None => query.push("node"),
+        };
+        let mut response = query
+            .push(
+                " WHERE embedding != NONE OR embeddings != NONE \
+                    OR sealed_embedding != NONE OR sealed_embeddings != NONE \
+                 ORDER BY id",
+            )
+            .send(&self.db)
+            .await?;
+        let rows: Vec<EmbeddingRow> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+
+        let open = |sealed: String| match &self.cipher {
+            Some(cipher) => cipher.open_vector(&sealed),
+            None => Err(GraphError::Storage(
+                "Embedding is sealed and no encryption key is set".to_string(),
+            )),
+        };
+        let mut embeddings = Vec::new();
+        for row in rows {
+            let mut spaces: Vec<(Option<String>, Vec<f32>)> = Vec::new();
+            if let Some(vector) = row.embedding {
+                spaces.push((None, vector));
+            }
+            if let Some(sealed) = row.sealed_embedding {
+                spaces.push((None, open(sealed)?));
+            }
+            for (field, vector) in row.embeddings.unwrap_or_default() {
+                spaces.push((Some(field), vector));
+            }
+            for (field, sealed) in row.sealed_embeddings.unwrap_or_default() {
+                spaces.push((Some(field), open(sealed)?));
+            }
+            spaces.sort_by(|a, b| a.0.cmp(&b.0));
+            embeddings.extend(spaces.into_iter().map(|(field, vector)| StoredEmbedding {
+                id: row.id.clone(),
+                field,
+                vector,
+            }));
+        }
+        Ok(embeddings)
     }
 }
 
@@ -40,10 +604,220 @@ struct NodeContent {
     partition_id: String,
 }
 
+#[derive(Serialize)]
+struct NodeRecord {
+    id: String,
+    label: String,
+    properties: serde_json::Value,
+    partition_id: String,
+}
+
+#[derive(Serialize)]
+struct EdgeRecord {
+    #[serde(rename = "in")]
+    source: Thing,
+    #[serde(rename = "out")]
+    target: Thing,
+    weight: f32,
+    partition_id: String,
+    valid_from: Option<DateTime<Utc>>,
+    valid_to: Option<DateTime<Utc>>,
+    cross_partition: bool,
+}
+
+/// A vector to write, or its sealed form when encryption is on
+#[derive(Serialize)]
+struct EmbeddingRecord<V> {
+    id: String,
+    vector: V,
+}
+
+/// A relation row as selected from an edge table
+#[derive(Deserialize)]
+struct EdgeRow {
+    id: Thing,
+    #[serde(rename = "in")]
+    source: Thing,
+    #[serde(rename = "out")]
+    target: Thing,
+    weight: Option<f32>,
+    partition_id: Option<String>,
+    valid_from: Option<DateTime<Utc>>,
+    valid_to: Option<DateTime<Utc>>,
+    cross_partition: Option<bool>,
+}
+
+impl From<EdgeRow> for Edge {
+    fn from(row: EdgeRow) -> Self {
+        Edge {
+            source: record_key(&row.source),
+            target: record_key(&row.target),
+            relation: relation_name(&row.id.tb),
+            weight: row.weight.unwrap_or(1.0),
+            partition_id: row.partition_id.unwrap_or_else(|| "personal".to_string()),
+            valid_from: row.valid_from,
+            valid_to: row.valid_to,
+            cross_partition: row.cross_partition.unwrap_or(false),
+        }
+    }
+}
+
+/// How often a write that lost a transaction conflict is attempted
+const WRITE_ATTEMPTS: u32 = 10;
+
+/// Whether `e` is a transaction conflict, which leaves nothing written
+fn is_write_conflict(e: &GraphError) -> bool {
+    matches!(e, GraphError::Storage(message) if message.contains("can be retried"))
+}
+
+/// Maps write errors, turning duplicate-record failures into `Conflict`
+fn write_error(e: surrealdb::Error) -> GraphError {
+    match e {
+        surrealdb::Error::Db(surrealdb::error::Db::RecordExists { thing }) => {
+            GraphError::Conflict(format!("Node already exists: {}", thing))
+        }
+        e => GraphError::Storage(e.to_string()),
+    }
+}
+
+/// Where sealed vectors of the embedding space at `path` live. They are
+/// kept apart from plain vectors, whose fields may carry a vector index.
+fn sealed_path(path: &Ident) -> Ident {
+    Ident::path(&format!("sealed_{}", path.as_str())).expect("prefixing keeps a path valid")
+}
+
+fn cosine_similarity(v1: &[f32], v2: &[f32]) -> f32 {
+    let dot_product: f32 = v1.iter().zip(v2.iter()).map(|(a, b)| a * b).sum();
+    let norm_a: f32 = v1.iter().map(|a| a * a).sum::<f32>().sqrt();
+    let norm_b: f32 = v2.iter().map(|b| b * b).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot_product / (norm_a * norm_b)
+    }
+}
+
+fn node_thing(id: &str) -> Thing {
+    Thing::from(("node", id))
+}
+
+/// The id a record was stored under, as given by the caller. `Id`'s own
+/// `Display` quotes ids with special characters, e.g. `⟨a-b⟩`.
+fn record_key(thing: &Thing) -> String {
+    match &thing.id {
+        Id::String(id) => id.clone(),
+        id => id.to_string(),
+    }
+}
+
+fn partition_thing(partition_id: &str) -> Thing {
+    Thing::from(("partition", partition_id))
+}
+
+/// Prefix of edge tables whose relation name was escaped
+const ESCAPED_RELATION: &str = "__";
+
+/// The edge table of `relation`. Names that are not plain identifiers, such
+/// as `works at`, are escaped: `__` followed by the name with every character
+/// other than a letter or digit written as `_<hex code>_`, e.g.
+/// `__works_20_at`. Names that already start with `__` are escaped too, so
+/// `relation_name` maps each table back to exactly one name.
+fn relation_table(relation: &str) -> Result<Ident, GraphError> {
+    if relation.is_empty() {
+        return Err(GraphError::Storage("Relation name is empty".to_string()));
+    }
+    if !relation.starts_with(ESCAPED_RELATION) {
+        if let Some(table) = Ident::new(relation) {
+            return Ok(table);
+        }
+    }
+    let mut table = ESCAPED_RELATION.to_string();
+    for c in relation.chars() {
+        if c.is_alphanumeric() {
+            table.push(c);
+        } else {
+            table.push_str(&format!("_{:x}_", c as u32));
+        }
+    }
+    Ok(Ident::new(&table).expect("escaped relation names are identifiers"))
+}
+
+/// The relation stored in edge table `table`, undoing `relation_table`
+fn relation_name(table: &str) -> String {
+    let Some(mut rest) = table.strip_prefix(ESCAPED_RELATION) else {
+        return table.to_string();
+    };
+    let mut name = String::new();
+    while let Some(start) = rest.find('_') {
+        name.push_str(&rest[..start]);
+        let code = &rest[start + 1..];
+        let Some(end) = code.find('_') else {
+            return table.to_string();
+        };
+        match u32::from_str_radix(&code[..end], 16)
+            .ok()
+            .and_then(char::from_u32)
+        {
+            Some(c) => name.push(c),
+            None => return table.to_string(),
+        }
+        rest = &code[end + 1..];
+    }
+    name.push_str(rest);
+    name
+}
+
+/// A node row with a `score` column from a search query. Spelled out rather
+/// than flattening `SurrealNode`, which serde cannot do with a `Thing` id.
+#[derive(Deserialize)]
+struct ScoredNode {
+    id: Thing,
+    label: String,
+    properties: serde_json::Value,
+    partition_id: String,
+    score: f32,
+}
+
+impl From<ScoredNode> for (Node, f32) {
+    fn from(hit: ScoredNode) -> Self {
+        let node = Node::from(SurrealNode {
+            id: hit.id,
+            label: hit.label,
+            properties: hit.properties,
+            partition_id: hit.partition_id,
+        });
+        (node, hit.score)
+    }
+}
+
+/// A `node_history` row
+#[derive(Deserialize)]
+struct HistoryRow {
+    node: String,
+    label: String,
+    properties: serde_json::Value,
+    partition_id: String,
+    recorded_at: DateTime<Utc>,
+}
+
+impl From<HistoryRow> for NodeVersion {
+    fn from(row: HistoryRow) -> Self {
+        NodeVersion {
+            node: Node {
+                id: row.node,
+                label: row.label,
+                properties: row.properties,
+                partition_id: row.partition_id,
+            },
+            recorded_at: row.recorded_at,
+        }
+    }
+}
+
 impl From<SurrealNode> for Node {
     fn from(sn: SurrealNode) -> Self {
         Node {
-            id: sn.id.id.to_string(), 
+            id: record_key(&sn.id),
             label: sn.label,
             properties: sn.properties,
             partition_id: sn.partition_id,
@@ -56,7 +830,7 @@ impl GraphStore for SurrealStore {
     async fn add_node(&self, node: Node) -> Result<(), GraphError> {
         let content = NodeContent {
             label: node.label,
-            properties: node.properties,
+            properties: self.seal_properties(node.properties)?,
             partition_id: node.partition_id,
         };
 
@@ -65,27 +839,29 @@ impl GraphStore for SurrealStore {
             .create(("node", &node.id))
             .content(content)
             .await
-            .map_err(|e| GraphError::Storage(e.to_string()))?;
+            .map_err(write_error)?;
         Ok(())
     }
 
-    async fn add_edge(&self, edge: Edge) -> Result<(), GraphError> {
-        // Validate relation name
-        if !edge.relation.chars().all(|c| c.is_alphanumeric() || c == '_') {
-             return Err(GraphError::Storage(format!("Invalid relation name: {}", edge.relation)));
-        }
-
-        let sql = format!(
-            "RELATE node:{}->{}->node:{} SET weight = $weight, partition_id = $partition",
-            edge.source, edge.relation, edge.target
-        );
+    async fn add_edge(&self, mut edge: Edge) -> Result<(), GraphError> {
+        let table = relation_table(&edge.relation)?;
+        self.check_edges(std::slice::from_mut(&mut edge)).await?;
 
-        self.db
-            .query(sql)
-            .bind(("weight", edge.weight))
-            .bind(("partition", edge.partition_id))
-            .await
-            .map_err(|e| GraphError::Storage(e.to_string()))?;
+        SurrealQuery::new("RELATE $source->")
+            .ident(&table)
+            .push(
+                "->$target SET weight = $weight, partition_id = $partition, \
+                 valid_from = $valid_from, valid_to = $valid_to, cross_partition = $cross_partition",
+            )
+            .bind("source", node_thing(&edge.source))
+            .bind("target", node_thing(&edge.target))
+            .bind("weight", edge.weight)
+            .bind("partition", edge.partition_id)
+            .bind("valid_from", edge.valid_from)
+            .bind("valid_to", edge.valid_to)
+            .bind("cross_partition", edge.cross_partition)
+            .send(&self.db)
+            .await?;
 
         Ok(())
     }
@@ -97,14 +873,16 @@ impl GraphStore for SurrealStore {
             .await
             .map_err(|e| GraphError::Storage(e.to_string()))?;
 
-        node.map(Node::from)
-            .ok_or(GraphError::NotFound(id.to_string()))
+        match node {
+            Some(sn) => self.open_node(sn),
+            None => Err(GraphError::NotFound(id.to_string())),
+        }
     }
 
     async fn update_node(&self, node: Node) -> Result<(), GraphError> {
         let content = NodeContent {
             label: node.label,
-            properties: node.properties,
+            properties: self.seal_properties(node.properties)?,
             partition_id: node.partition_id,
         };
 
@@ -117,18 +895,479 @@ impl GraphStore for SurrealStore {
         Ok(())
     }
 
-    async fn get_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
-        let sql = format!(
-            "SELECT ->? FROM node:{}",
-            id
-        );
+    async fn get_node_history(&self, id: &str) -> Result<Vec<NodeVersion>, GraphError> {
+        let mut response = SurrealQuery::new(
+            "SELECT node, label, properties, partition_id, recorded_at FROM node_history \
+             WHERE node = $id ORDER BY recorded_at",
+        )
+        .bind("id", id.to_string())
+        .send(&self.db)
+        .await?;
 
-        let mut response = self
-            .db
-            .query(sql)
-            .await
+        let rows: Vec<HistoryRow> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+        rows.into_iter()
+            .map(|row| {
+                let mut version = NodeVersion::from(row);
+                version.node = self.open_node(version.node)?;
+                Ok(version)
+            })
+            .collect()
+    }
+
+    async fn get_incoming_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+        let sql = "LET $edges = array::flatten(SELECT VALUE <-? FROM $node); \
+                   SELECT id, in, out, weight, partition_id, valid_from, valid_to, cross_partition FROM $edges; \
+                   LET $ids = array::distinct(SELECT VALUE in FROM $edges); \
+                   SELECT * FROM $ids;";
+
+        let mut response = SurrealQuery::new(sql)
+            .bind("node", node_thing(id))
+            .run(&self.db)
+            .await?;
+
+        let rows: Vec<EdgeRow> = response
+            .take(1)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+        let sources: Vec<SurrealNode> = response
+            .take(3)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+
+        let sources: std::collections::HashMap<String, Node> = self
+            .open_nodes(sources)?
+            .into_iter()
+            .map(|node| (node.id.clone(), node))
+            .collect();
+
+        Ok(rows
+            .into_iter()
+            .map(Edge::from)
+            .filter_map(|edge| {
+                let source = sources.get(&edge.source)?.clone();
+                Some((edge, source))
+            })
+            .collect())
+    }
+
+    async fn traverse(
+        &self,
+        id: &str,
+        depth: usize,
+        direction: Direction,
+        relation_filter: Option<&[&str]>,
+    ) -> Result<Vec<(Edge, Node)>, GraphError> {
+        self.get_node(id).await?;
+        if depth == 0 {
+            return Ok(vec![]);
+        }
+
+        // One graph path per hop count, e.g. `->?`, `->?->?->?` for depth 2.
+        // Every path ends on an edge, so the union is the set of reachable edges.
+        let arrow = match direction {
+            Direction::Outgoing => "->",
+            Direction::Incoming => "<-",
+            Direction::Both => "<->",
+        };
+        let step = match relation_filter {
+            Some(_) => "(? WHERE meta::tb(id) INSIDE $relations)",
+            None => "?",
+        };
+        let mut query =
+            SurrealQuery::new("LET $edges = array::distinct(array::flatten(SELECT VALUE [");
+        for hop in 1..=depth {
+            if hop > 1 {
+                query = query.push(", ");
+            }
+            query = query.push(arrow).push(step);
+            for _ in 1..hop {
+                query = query.push(arrow).push("?").push(arrow).push(step);
+            }
+        }
+        let relations = relation_filter
+            .unwrap_or_default()
+            .iter()
+            .map(|r| relation_table(r).map(|table| table.as_str().to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut response = query
+            .push(
+                "] FROM ONLY $node)); \
+                 SELECT id, in, out, weight, partition_id, valid_from, valid_to, cross_partition FROM $edges; \
+                 LET $ids = array::distinct(array::flatten(SELECT VALUE [in, out] FROM $edges)); \
+                 SELECT * FROM $ids;",
+            )
+            .bind("node", node_thing(id))
+            .bind("relations", relations)
+            .run(&self.db)
+            .await?;
+
+        let rows: Vec<EdgeRow> = response
+            .take(1)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+        let nodes: Vec<SurrealNode> = response
+            .take(3)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+
+        let edges: Vec<Edge> = rows.into_iter().map(Edge::from).collect();
+        let nodes: std::collections::HashMap<String, Node> = self
+            .open_nodes(nodes)?
+            .into_iter()
+            .map(|node| (node.id.clone(), node))
+            .collect();
+
+        // Order by hop and pick the far side of each edge
+        Ok(
+            traversal::expand(&edges, id, depth, direction, relation_filter)
+                .into_iter()
+                .filter_map(|(edge, far)| nodes.get(far).map(|n| (edge.clone(), n.clone())))
+                .collect(),
+        )
+    }
+
+    async fn upsert_node(&self, node: Node) -> Result<(), GraphError> {
+        // Sealed properties cannot be merged in the database
+        if self.cipher.is_some() {
+            return match self.get_node(&node.id).await {
+                Ok(mut existing) => {
+                    merge_properties(&mut existing.properties, node.properties);
+                    existing.label = node.label;
+                    existing.partition_id = node.partition_id;
+                    self.update_node(existing).await
+                }
+                Err(GraphError::NotFound(_)) => self.add_node(node).await,
+                Err(e) => Err(e),
+            };
+        }
+
+        // MERGE deep-merges objects, matching `merge_properties`
+        SurrealQuery::new("UPSERT $node MERGE $content")
+            .bind("node", node_thing(&node.id))
+            .bind("content", self.node_content(node)?)
+            .run(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn patch_node(&self, id: &str, patch: serde_json::Value) -> Result<Node, GraphError> {
+        // Sealed properties cannot be merged in the database
+        if self.cipher.is_some() {
+            let mut node = self.get_node(id).await?;
+            merge_properties(&mut node.properties, patch);
+            self.update_node(node.clone()).await?;
+            return Ok(node);
+        }
+
+        // Concurrent patches of one node conflict rather than overwrite each
+        // other; the loser wrote nothing and applies its patch again
+        let mut attempt = 1;
+        let mut response = loop {
+            let result =
+                SurrealQuery::new("UPDATE $node MERGE { properties: $patch } RETURN AFTER")
+                    .bind("node", node_thing(id))
+                    .bind("patch", patch.clone())
+                    .run(&self.db)
+                    .await;
+            match result {
+                Err(e) if is_write_conflict(&e) && attempt < WRITE_ATTEMPTS => {
+                    tokio::time::sleep(Duration::from_millis(attempt as u64)).await;
+                    attempt += 1;
+                }
+                result => break result?,
+            }
+        };
+        let nodes: Vec<SurrealNode> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+        match nodes.into_iter().next() {
+            Some(node) => self.open_node(node),
+            None => Err(GraphError::NotFound(id.to_string())),
+        }
+    }
+
+    async fn add_nodes(&self, nodes: Vec<Node>) -> Result<(), GraphError> {
+        if nodes.is_empty() {
+            return Ok(());
+        }
+
+        let records: Vec<NodeRecord> = nodes
+            .into_iter()
+            .map(|n| {
+                Ok(NodeRecord {
+                    id: n.id,
+                    label: n.label,
+                    properties: self.seal_properties(n.properties)?,
+                    partition_id: n.partition_id,
+                })
+            })
+            .collect::<Result<_, GraphError>>()?;
+
+        SurrealQuery::new("BEGIN TRANSACTION; INSERT INTO node $nodes; COMMIT TRANSACTION;")
+            .bind("nodes", records)
+            .send(&self.db)
+            .await?
+            .check()
+            .map_err(write_error)?;
+
+        Ok(())
+    }
+
+    async fn add_edges(&self, mut edges: Vec<Edge>) -> Result<(), GraphError> {
+        if edges.is_empty() {
+            return Ok(());
+        }
+
+        // Each relation is its own table, so group edges per relation and
+        // issue one INSERT RELATION per table inside a single transaction
+        self.check_edges(&mut edges).await?;
+
+        let mut by_relation: std::collections::BTreeMap<Ident, Vec<EdgeRecord>> =
+            std::collections::BTreeMap::new();
+        for edge in edges {
+            by_relation
+                .entry(relation_table(&edge.relation)?)
+                .or_default()
+                .push(EdgeRecord {
+                    source: node_thing(&edge.source),
+                    target: node_thing(&edge.target),
+                    weight: edge.weight,
+                    partition_id: edge.partition_id,
+                    valid_from: edge.valid_from,
+                    valid_to: edge.valid_to,
+                    cross_partition: edge.cross_partition,
+                });
+        }
+
+        let mut query = SurrealQuery::new("BEGIN TRANSACTION;");
+        for (i, (table, records)) in by_relation.into_iter().enumerate() {
+            query = query
+                .push(" INSERT RELATION INTO ")
+                .ident(&table)
+                .numbered(" $edges{i};", i)
+                .bind_nth("edges", i, records);
+        }
+        query.push(" COMMIT TRANSACTION;").run(&self.db).await?;
+
+        Ok(())
+    }
+
+    async fn commit_transaction(&self, tx: GraphTransaction) -> Result<(), GraphError> {
+        if tx.is_empty() {
+            return Ok(());
+        }
+
+        // Compile every op into one SurrealQL transaction. Each op gets its own
+        // numbered parameters so values are bound rather than interpolated.
+        let mut query = SurrealQuery::new("BEGIN TRANSACTION;\n");
+
+        let mut ops = tx.into_ops();
+        self.check_partitions(&mut ops).await?;
+        for (i, op) in ops.into_iter().enumerate() {
+            query = match op {
+                GraphOp::AddNode(node) => query
+                    .numbered("CREATE $n{i} CONTENT $c{i};\n", i)
+                    .bind_nth("n", i, node_thing(&node.id))
+                    .bind_nth("c", i, self.node_content(node)?),
+                GraphOp::UpdateNode(node) => query
+                    .numbered(
+                        "IF (SELECT VALUE id FROM $n{i}) = [] { THROW \"Node not found: \" + <string> $n{i} };\n\
+                         UPDATE $n{i} CONTENT $c{i};\n",
+                        i,
+                    )
+                    .bind_nth("n", i, node_thing(&node.id))
+                    .bind_nth("c", i, self.node_content(node)?),
+                GraphOp::AddEdge(edge) => query
+                    .numbered("RELATE $s{i}->", i)
+                    .ident(&relation_table(&edge.relation)?)
+                    .numbered(
+                        "->$t{i} SET weight = $w{i}, partition_id = $p{i}, \
+                         valid_from = $vf{i}, valid_to = $vt{i}, cross_partition = $x{i};\n",
+                        i,
+                    )
+                    .bind_nth("s", i, node_thing(&edge.source))
+                    .bind_nth("t", i, node_thing(&edge.target))
+                    .bind_nth("w", i, edge.weight)
+                    .bind_nth("p", i, edge.partition_id)
+                    .bind_nth("vf", i, edge.valid_from)
+                    .bind_nth("vt", i, edge.valid_to)
+                    .bind_nth("x", i, edge.cross_partition),
+                GraphOp::DeleteNode { id, cascade } => {
+                    query = query.numbered(
+                        "IF (SELECT VALUE id FROM $n{i}) = [] { THROW \"Node not found: \" + <string> $n{i} };\n\
+                         LET $e{i} = array::union((SELECT VALUE ->? FROM ONLY $n{i}), (SELECT VALUE <-? FROM ONLY $n{i}));\n",
+                        i,
+                    );
+                    if !cascade {
+                        query = query.numbered(
+                            "IF array::len($e{i}) > 0 { THROW \"Node has attached edges: \" + <string> $n{i} };\n",
+                            i,
+                        );
+                    }
+                    query
+                        .numbered("DELETE $e{i};\nDELETE $n{i};\n", i)
+                        .bind_nth("n", i, node_thing(&id))
+                }
+                GraphOp::DeleteEdge {
+                    source,
+                    relation,
+                    target,
+                } => query
+                    .push("IF array::len((DELETE ")
+                    .ident(&relation_table(&relation)?)
+                    .numbered(
+                        " WHERE in = $s{i} AND out = $t{i} RETURN BEFORE)) = 0 \
+                         { THROW \"Edge not found: \" + $r{i} };\n",
+                        i,
+                    )
+                    .bind_nth("s", i, node_thing(&source))
+                    .bind_nth("t", i, node_thing(&target))
+                    .bind_nth("r", i, relation),
+            };
+        }
+
+        query
+            .push("COMMIT TRANSACTION;")
+            .send(&self.db)
+            .await?
+            .check()
+            .map_err(|e| GraphError::Storage(format!("Transaction failed: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn delete_node(&self, id: &str, cascade: bool) -> Result<(), GraphError> {
+        #[derive(Deserialize)]
+        struct Attached {
+            outgoing: Vec<Thing>,
+            incoming: Vec<Thing>,
+        }
+
+        let mut response = SurrealQuery::new("SELECT ->? AS outgoing, <-? AS incoming FROM $node")
+            .bind("node", node_thing(id))
+            .send(&self.db)
+            .await?;
+
+        let attached: Option<Attached> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+        let attached = attached.ok_or(GraphError::NotFound(id.to_string()))?;
+
+        // Self-loops show up in both directions
+        let mut edges = attached.outgoing;
+        for thing in attached.incoming {
+            if !edges.contains(&thing) {
+                edges.push(thing);
+            }
+        }
+
+        if !edges.is_empty() && !cascade {
+            return Err(GraphError::Conflict(format!(
+                "Node {} has {} attached edges",
+                id,
+                edges.len()
+            )));
+        }
+
+        // Remove relation records together with the node so no dangling
+        // RELATE rows are left behind
+        SurrealQuery::new("BEGIN TRANSACTION; DELETE $edges; DELETE $node; COMMIT TRANSACTION;")
+            .bind("edges", edges)
+            .bind("node", node_thing(id))
+            .run(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_edge(
+        &self,
+        source: &str,
+        relation: &str,
+        target: &str,
+    ) -> Result<(), GraphError> {
+        let mut response = SurrealQuery::new("DELETE ")
+            .ident(&relation_table(relation)?)
+            .push(" WHERE in = $source AND out = $target RETURN BEFORE")
+            .bind("source", node_thing(source))
+            .bind("target", node_thing(target))
+            .send(&self.db)
+            .await?;
+
+        let deleted: Vec<serde::de::IgnoredAny> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+
+        if deleted.is_empty() {
+            return Err(GraphError::NotFound(format!(
+                "{}-[{}]->{}",
+                source, relation, target
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn update_edge(&self, edge: Edge) -> Result<(), GraphError> {
+        let mut response = SurrealQuery::new("UPDATE ")
+            .ident(&relation_table(&edge.relation)?)
+            .push(
+                " SET weight = $weight, partition_id = $partition, valid_from = $valid_from, \
+                 valid_to = $valid_to WHERE in = $source AND out = $target RETURN BEFORE",
+            )
+            .bind("source", node_thing(&edge.source))
+            .bind("target", node_thing(&edge.target))
+            .bind("weight", edge.weight)
+            .bind("partition", edge.partition_id)
+            .bind("valid_from", edge.valid_from)
+            .bind("valid_to", edge.valid_to)
+            .run(&self.db)
+            .await?;
+
+        let updated: Vec<serde::de::IgnoredAny> = response
+            .take(0)
             .map_err(|e| GraphError::Storage(e.to_string()))?;
 
+        if updated.is_empty() {
+            return Err(GraphError::NotFound(format!(
+                "{}-[{}]->{}",
+                edge.source, edge.relation, edge.target
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn increment_edge_weight(
+        &self,
+        source: &str,
+        relation: &str,
+        target: &str,
+        delta: f32,
+    ) -> Result<f32, GraphError> {
+        let mut response = SurrealQuery::new("UPDATE ")
+            .ident(&relation_table(relation)?)
+            .push(" SET weight += $delta WHERE in = $source AND out = $target RETURN VALUE weight")
+            .bind("source", node_thing(source))
+            .bind("target", node_thing(target))
+            .bind("delta", delta)
+            .run(&self.db)
+            .await?;
+
+        let weights: Vec<f32> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+
+        weights.into_iter().reduce(f32::max).ok_or_else(|| {
+            GraphError::NotFound(format!("{}-[{}]->{}", source, relation, target))
+        })
+    }
+
+    async fn get_neighbors(&self, id: &str) -> Result<Vec<(Edge, Node)>, GraphError> {
+        let mut response = SurrealQuery::new("SELECT ->? FROM $node")
+            .bind("node", node_thing(id))
+            .send(&self.db)
+            .await?;
+
         // Deserialize into HashMap to capture dynamic keys like "->knows"
         type RelationMap = std::collections::HashMap<String, Vec<surrealdb::sql::Thing>>;
 
@@ -155,22 +1394,20 @@ impl GraphStore for SurrealStore {
             struct RelationRecord {
                 #[serde(alias = "relation")]
                 id: surrealdb::sql::Thing,
-                #[serde(rename = "in")]
-                source: surrealdb::sql::Thing,
                 #[serde(rename = "out")]
                 target: surrealdb::sql::Thing,
                 
                 weight: Option<f32>,
                 partition_id: Option<String>,
+                valid_from: Option<DateTime<Utc>>,
+                valid_to: Option<DateTime<Utc>>,
+                cross_partition: Option<bool>,
             }
 
-            let rels_sql = "SELECT * FROM $ids";
-            let mut rels_response = self
-                .db
-                .query(rels_sql)
-                .bind(("ids", relation_things))
-                .await
-                .map_err(|e| GraphError::Storage(e.to_string()))?;
+            let mut rels_response = SurrealQuery::new("SELECT * FROM $ids")
+                .bind("ids", relation_things)
+                .send(&self.db)
+                .await?;
 
             let relations: Vec<RelationRecord> = rels_response
                 .take(0)
@@ -187,33 +1424,33 @@ impl GraphStore for SurrealStore {
                 .collect();
 
             // Batch fetch nodes
-            let nodes_sql = "SELECT * FROM $ids";
-            let mut nodes_response = self
-                .db
-                .query(nodes_sql)
-                .bind(("ids", target_ids))
-                .await
-                .map_err(|e| GraphError::Storage(e.to_string()))?;
+            let mut nodes_response = SurrealQuery::new("SELECT * FROM $ids")
+                .bind("ids", target_ids)
+                .send(&self.db)
+                .await?;
 
             let nodes: Vec<SurrealNode> = nodes_response
                 .take(0)
                 .map_err(|e| GraphError::Storage(e.to_string()))?;
 
-            // Map Thing -> Node
+            // Map record key -> Node
             let mut node_map = std::collections::HashMap::new();
             for sn in nodes {
-                node_map.insert(sn.id.clone(), Node::from(sn));
+                let node = self.open_node(sn)?;
+                node_map.insert(node.id.clone(), node);
             }
 
             for rel in relations {
-                if let Some(target_node) = node_map.get(&rel.target) {
-                    let relation_name = rel.id.tb.clone();
+                if let Some(target_node) = node_map.get(&record_key(&rel.target)) {
                     let edge = Edge {
                         source: id.to_string(), // Use the method argument 'id'
-                        target: rel.target.id.to_string(), // Keep ID string
-                        relation: relation_name,
+                        target: record_key(&rel.target),
+                        relation: relation_name(&rel.id.tb),
                         weight: rel.weight.unwrap_or(1.0),
                         partition_id: rel.partition_id.unwrap_or_else(|| "personal".to_string()),
+                        valid_from: rel.valid_from,
+                        valid_to: rel.valid_to,
+                        cross_partition: rel.cross_partition.unwrap_or(false),
                     };
                     neighbors.push((edge, target_node.clone()));
                 }
@@ -223,22 +1460,207 @@ impl GraphStore for SurrealStore {
         Ok(neighbors)
     }
 
+    async fn search_text(&self, query: &str, limit: usize) -> Result<Vec<(Node, f32)>, GraphError> {
+        if query.trim().is_empty() {
+            return Ok(vec![]);
+        }
+
+        // The full-text index only sees sealed strings, so score every node
+        if self.cipher.is_some() {
+            let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+            let mut response = SurrealQuery::new("SELECT * FROM node")
+                .send(&self.db)
+                .await?;
+            let nodes: Vec<SurrealNode> = response
+                .take(0)
+                .map_err(|e| GraphError::Storage(e.to_string()))?;
+            let mut results: Vec<(Node, f32)> = self
+                .open_nodes(nodes)?
+                .into_iter()
+                .filter_map(|node| text_match_score(&node.properties, &terms).map(|s| (node, s)))
+                .collect();
+            results.sort_by(|a, b| {
+                b.1.partial_cmp(&a.1)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then_with(|| a.0.id.cmp(&b.0.id))
+            });
+            results.truncate(limit);
+            return Ok(results);
+        }
+
+        let sql = "SELECT *, search::score(1) AS score FROM node \
+                   WHERE search_text @1@ $query ORDER BY score DESC LIMIT $limit";
+
+        let mut response = SurrealQuery::new(sql)
+            .bind("query", query.to_string())
+            .bind("limit", limit)
+            .send(&self.db)
+            .await?;
+
+        let hits: Vec<ScoredNode> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+
+        Ok(hits.into_iter().map(<(Node, f32)>::from).collect())
+    }
+
     async fn query_by_partition(&self, partition_id: &str) -> Result<Vec<Node>, GraphError> {
-        let sql = "SELECT * FROM node WHERE partition_id = $partition";
-        let pid = partition_id.to_string();
+        let mut response = SurrealQuery::new("SELECT * FROM node WHERE partition_id = $partition")
+            .bind("partition", partition_id.to_string())
+            .send(&self.db)
+            .await?;
 
-        let mut response = self
-            .db
-            .query(sql)
-            .bind(("partition", pid))
-            .await
+        let nodes: Vec<SurrealNode> = response
+            .take(0)
             .map_err(|e| GraphError::Storage(e.to_string()))?;
 
+        self.open_nodes(nodes)
+    }
+
+    async fn query_by_label(
+        &self,
+        label: &str,
+        partition_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<Node>, GraphError> {
+        let filter = if partition_id.is_some() {
+            " AND partition_id = $partition"
+        } else {
+            ""
+        };
+        let mut response = SurrealQuery::new("SELECT * FROM node WHERE label = $label")
+            .push(filter)
+            .push(" ORDER BY id LIMIT $limit")
+            .bind("label", label.to_string())
+            .bind("partition", partition_id.map(str::to_string))
+            .bind("limit", limit)
+            .send(&self.db)
+            .await?;
+
         let nodes: Vec<SurrealNode> = response
             .take(0)
             .map_err(|e| GraphError::Storage(e.to_string()))?;
 
-        Ok(nodes.into_iter().map(Node::from).collect())
+        self.open_nodes(nodes)
+    }
+
+    async fn node_exists(&self, id: &str) -> Result<bool, GraphError> {
+        let mut response = SurrealQuery::new("SELECT VALUE id FROM $node")
+            .bind("node", node_thing(id))
+            .run(&self.db)
+            .await?;
+        let ids: Vec<Thing> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+        Ok(!ids.is_empty())
+    }
+
+    async fn count_nodes(&self, partition_id: Option<&str>) -> Result<usize, GraphError> {
+        let filter = if partition_id.is_some() {
+            " WHERE partition_id = $partition"
+        } else {
+            ""
+        };
+        let mut response = SurrealQuery::new("RETURN (SELECT count() FROM node")
+            .push(filter)
+            .push(" GROUP ALL)[0].count OR 0")
+            .bind("partition", partition_id.map(str::to_string))
+            .run(&self.db)
+            .await?;
+        let count: Option<usize> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+        Ok(count.unwrap_or(0))
+    }
+
+    async fn count_edges(&self, relation: Option<&str>) -> Result<usize, GraphError> {
+        // Every relation is its own table; without one, count the outgoing
+        // edges of every node
+        let query = match relation {
+            Some(relation) => SurrealQuery::new("RETURN (SELECT count() FROM ")
+                .ident(&relation_table(relation)?)
+                .push(" GROUP ALL)[0].count OR 0"),
+            None => SurrealQuery::new("RETURN math::sum(SELECT VALUE array::len(->?) FROM node)"),
+        };
+        let mut response = query.run(&self.db).await?;
+        let count: Option<usize> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+        Ok(count.unwrap_or(0))
+    }
+
+    async fn snapshot(&self, partition_id: Option<&str>) -> Result<GraphSnapshot, GraphError> {
+        let filter = if partition_id.is_some() {
+            "WHERE partition_id = $partition"
+        } else {
+            ""
+        };
+        let mut response = SurrealQuery::new("SELECT * FROM node ")
+            .push(filter)
+            .push(" ORDER BY id; LET $edges = array::flatten(SELECT VALUE ->? FROM node ")
+            .push(filter)
+            .push(
+                "); SELECT id, in, out, weight, partition_id, valid_from, valid_to, cross_partition FROM $edges;",
+            )
+            .bind("partition", partition_id.map(str::to_string))
+            .run(&self.db)
+            .await?;
+
+        let nodes: Vec<SurrealNode> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+        let rows: Vec<EdgeRow> = response
+            .take(2)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+
+        let nodes = self.open_nodes(nodes)?;
+        let mut edges: Vec<Edge> = rows.into_iter().map(Edge::from).collect();
+
+        // Drop edges leaving the partition
+        if let Some(partition) = partition_id {
+            let ids: std::collections::HashSet<&str> =
+                nodes.iter().map(|n| n.id.as_str()).collect();
+            edges.retain(|e| {
+                e.partition_id == partition
+                    && ids.contains(e.source.as_str())
+                    && ids.contains(e.target.as_str())
+            });
+        }
+
+        Ok(GraphSnapshot::new(nodes, edges))
+    }
+
+    async fn query_by_partition_page(
+        &self,
+        partition_id: &str,
+        page: PageRequest,
+    ) -> Result<Vec<Node>, GraphError> {
+        let field = match page.order {
+            NodeOrder::CreatedAt => "created_at",
+            NodeOrder::Label => "label",
+            NodeOrder::Id => "id",
+        };
+        let dir = if page.descending { "DESC" } else { "ASC" };
+        let query = SurrealQuery::new("SELECT * FROM node WHERE partition_id = $partition");
+        let mut response = query
+            .push(" ORDER BY ")
+            .push(field)
+            .push(" ")
+            .push(dir)
+            .push(", id ")
+            .push(dir)
+            .push(" LIMIT $limit START $offset")
+            .bind("partition", partition_id.to_string())
+            .bind("limit", page.limit)
+            .bind("offset", page.offset)
+            .send(&self.db)
+            .await?;
+
+        let nodes: Vec<SurrealNode> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+
+        self.open_nodes(nodes)
     }
 
     async fn get_neighbors_in_partition(
@@ -253,17 +1675,207 @@ impl GraphStore for SurrealStore {
             
         Ok(filtered)
     }
+
+    async fn create_partition(&self, partition_id: &str) -> Result<(), GraphError> {
+        if self.partition_node_count(partition_id).await?.is_some() {
+            return Err(GraphError::Conflict(format!(
+                "Partition already exists: {}",
+                partition_id
+            )));
+        }
+        SurrealQuery::new("CREATE $partition RETURN NONE")
+            .bind("partition", partition_thing(partition_id))
+            .run(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn list_partitions(&self) -> Result<Vec<PartitionInfo>, GraphError> {
+        #[derive(Deserialize)]
+        struct PartitionCount {
+            partition_id: String,
+            node_count: usize,
+        }
+
+        let mut response = SurrealQuery::new(
+            "SELECT partition_id, count() AS node_count FROM node GROUP BY partition_id; \
+             SELECT VALUE record::id(id) FROM partition;",
+        )
+        .send(&self.db)
+        .await?;
+        let counts: Vec<PartitionCount> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+        let registered: Vec<String> = response
+            .take(1)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+
+        let mut partitions: std::collections::BTreeMap<String, usize> =
+            registered.into_iter().map(|id| (id, 0)).collect();
+        for count in counts {
+            partitions.insert(count.partition_id, count.node_count);
+        }
+        Ok(partitions
+            .into_iter()
+            .map(|(id, node_count)| PartitionInfo { id, node_count })
+            .collect())
+    }
+
+    async fn rename_partition(&self, from: &str, to: &str) -> Result<(), GraphError> {
+        if self.partition_node_count(from).await?.is_none() {
+            return Err(GraphError::NotFound(format!("Partition {}", from)));
+        }
+        if self.partition_node_count(to).await?.is_some() {
+            return Err(GraphError::Conflict(format!(
+                "Partition already exists: {}",
+                to
+            )));
+        }
+
+        let sql = "BEGIN TRANSACTION; \
+                   LET $nodes = (SELECT VALUE id FROM node WHERE partition_id = $from); \
+                   LET $edges = array::distinct(array::flatten((SELECT VALUE array::union(->?, <-?) FROM $nodes))); \
+                   UPDATE $edges SET partition_id = $to WHERE partition_id = $from RETURN NONE; \
+                   UPDATE $nodes SET partition_id = $to RETURN NONE; \
+                   DELETE $from_record; \
+                   UPSERT $to_record RETURN NONE; \
+                   COMMIT TRANSACTION;";
+        SurrealQuery::new(sql)
+            .bind("from", from.to_string())
+            .bind("to", to.to_string())
+            .bind("from_record", partition_thing(from))
+            .bind("to_record", partition_thing(to))
+            .run(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_partition(&self, partition_id: &str, cascade: bool) -> Result<(), GraphError> {
+        let Some(node_count) = self.partition_node_count(partition_id).await? else {
+            return Err(GraphError::NotFound(format!("Partition {}", partition_id)));
+        };
+        if node_count > 0 && !cascade {
+            return Err(GraphError::Conflict(format!(
+                "Partition {} still has {} nodes",
+                partition_id, node_count
+            )));
+        }
+
+        // Embeddings live on the node records and go with them
+        let sql = "BEGIN TRANSACTION; \
+                   LET $nodes = (SELECT VALUE id FROM node WHERE partition_id = $partition); \
+                   DELETE array::flatten((SELECT VALUE array::union(->?, <-?) FROM $nodes)); \
+                   DELETE $nodes; \
+                   DELETE $record; \
+                   COMMIT TRANSACTION;";
+        SurrealQuery::new(sql)
+            .bind("partition", partition_id.to_string())
+            .bind("record", partition_thing(partition_id))
+            .run(&self.db)
+            .await?;
+        Ok(())
+    }
 }
 
 #[async_trait]
 impl VectorStore for SurrealStore {
-    async fn add_embedding(&self, id: &str, vector: Vec<f32>) -> Result<(), GraphError> {
-        let sql = format!("UPDATE node:{} SET embedding = $vector", id);
-        self.db
-            .query(sql)
-            .bind(("vector", vector))
-            .await
-            .map_err(|e| GraphError::Storage(e.to_string()))?;
+    async fn add_embedding(
+        &self,
+        id: &str,
+        vector: Vec<f32>,
+        field: Option<&str>,
+    ) -> Result<(), GraphError> {
+        self.check_dimension(&vector)?;
+        let path = embedding_path(field)?;
+        let query = SurrealQuery::new("UPDATE $node SET ");
+        let query = match &self.cipher {
+            Some(cipher) => query
+                .ident(&sealed_path(&path))
+                .bind("vector", cipher.seal_vector(&vector)?),
+            None => {
+                self.ensure_vector_index(&path).await?;
+                query.ident(&path).bind("vector", vector)
+            }
+        };
+
+        query
+            .push(" = $vector RETURN NONE")
+            .bind("node", node_thing(id))
+            .run(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn add_embeddings(
+        &self,
+        embeddings: Vec<(String, Vec<f32>)>,
+        field: Option<&str>,
+    ) -> Result<(), GraphError> {
+        if embeddings.is_empty() {
+            return Ok(());
+        }
+        for (_, vector) in &embeddings {
+            self.check_dimension(vector)?;
+        }
+        let path = embedding_path(field)?;
+        let query = |path: &Ident| {
+            SurrealQuery::new(
+                "BEGIN TRANSACTION; \
+                 FOR $e IN $embeddings { UPDATE type::thing('node', $e.id) SET ",
+            )
+            .ident(path)
+            .push(" = $e.vector RETURN NONE; }; COMMIT TRANSACTION;")
+        };
+
+        let query = match &self.cipher {
+            Some(cipher) => {
+                let records = embeddings
+                    .into_iter()
+                    .map(|(id, vector)| {
+                        Ok(EmbeddingRecord {
+                            id,
+                            vector: cipher.seal_vector(&vector)?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, GraphError>>()?;
+                query(&sealed_path(&path)).bind("embeddings", records)
+            }
+            None => {
+                self.ensure_vector_index(&path).await?;
+                let records: Vec<EmbeddingRecord<Vec<f32>>> = embeddings
+                    .into_iter()
+                    .map(|(id, vector)| EmbeddingRecord { id, vector })
+                    .collect();
+                query(&path).bind("embeddings", records)
+            }
+        };
+
+        query.run(&self.db).await?;
+
+        Ok(())
+    }
+
+    async fn remove_embedding(&self, id: &str) -> Result<(), GraphError> {
+        SurrealQuery::new(
+            "UPDATE $node UNSET embedding, embeddings, sealed_embedding, sealed_embeddings \
+             RETURN NONE",
+        )
+        .bind("node", node_thing(id))
+        .run(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn clear_embeddings(&self, partition_id: &str) -> Result<(), GraphError> {
+        SurrealQuery::new(
+            "UPDATE node UNSET embedding, embeddings, sealed_embedding, sealed_embeddings \
+             WHERE partition_id = $partition \
+                AND (embedding != NONE OR embeddings != NONE \
+                    OR sealed_embedding != NONE OR sealed_embeddings != NONE) RETURN NONE",
+        )
+        .bind("partition", partition_id.to_string())
+        .run(&self.db)
+        .await?;
         Ok(())
     }
 
@@ -271,16 +1883,30 @@ impl VectorStore for SurrealStore {
         &self,
         vector: Vec<f32>,
         limit: usize,
+        field: Option<&str>,
     ) -> Result<Vec<(String, f32)>, GraphError> {
-        let sql = "SELECT id, vector::similarity::cosine(embedding, $query) as score FROM node ORDER BY score DESC LIMIT $limit";
-
-        let mut response = self
-            .db
-            .query(sql)
-            .bind(("query", vector))
-            .bind(("limit", limit))
-            .await
-            .map_err(|e| GraphError::Storage(e.to_string()))?;
+        self.check_dimension(&vector)?;
+        let path = embedding_path(field)?;
+        if limit == 0 {
+            return Ok(vec![]);
+        }
+        if let Some(cipher) = &self.cipher {
+            let hits = self.scan_sealed(cipher, &path, &vector, limit).await?;
+            return Ok(hits
+                .into_iter()
+                .map(|(node, score)| (node.id, score))
+                .collect());
+        }
+        let query = SurrealQuery::new("SELECT id, vector::similarity::cosine(")
+            .ident(&path)
+            .push(", $query) AS score FROM node WHERE ")
+            .ident(&path)
+            .push(" ");
+        let mut response = knn_operator(query, self.vector_index.as_ref(), limit)
+            .push(" $query ORDER BY score DESC")
+            .bind("query", vector)
+            .send(&self.db)
+            .await?;
 
         #[derive(Deserialize)]
         struct SearchResult {
@@ -294,7 +1920,135 @@ impl VectorStore for SurrealStore {
 
         Ok(results
             .into_iter()
-            .map(|r| (r.id.id.to_string(), r.score))
+            .map(|r| (record_key(&r.id), r.score))
             .collect())
     }
+
+    async fn search_nodes(
+        &self,
+        vector: Vec<f32>,
+        limit: usize,
+        field: Option<&str>,
+    ) -> Result<Vec<(Node, f32)>, GraphError> {
+        self.check_dimension(&vector)?;
+        let path = embedding_path(field)?;
+        if limit == 0 {
+            return Ok(vec![]);
+        }
+        if let Some(cipher) = &self.cipher {
+            return self.scan_sealed(cipher, &path, &vector, limit).await;
+        }
+        let query = SurrealQuery::new(
+            "SELECT id, label, properties, partition_id, vector::similarity::cosine(",
+        )
+        .ident(&path)
+        .push(", $query) AS score FROM node WHERE ")
+        .ident(&path)
+        .push(" ");
+        let mut response = knn_operator(query, self.vector_index.as_ref(), limit)
+            .push(" $query ORDER BY score DESC")
+            .bind("query", vector)
+            .send(&self.db)
+            .await?;
+
+        let hits: Vec<ScoredNode> = response
+            .take(0)
+            .map_err(|e| GraphError::Storage(e.to_string()))?;
+
+        Ok(hits.into_iter().map(<(Node, f32)>::from).collect())
+    }
+
+    async fn get_embeddings(&self, id: &str) -> Result<Vec<StoredEmbedding>, GraphError> {
+        self.stored_embeddings(Some(id)).await
+    }
+
+    async fn list_embeddings(&self) -> Result<Vec<StoredEmbedding>, GraphError> {
+        self.stored_embeddings(None).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_relation_names_round_trip() {
+        assert_eq!(relation_table("works_at").unwrap().as_str(), "works_at");
+        assert_eq!(
+            relation_table("works at").unwrap().as_str(),
+            "__works_20_at"
+        );
+        assert_eq!(
+            relation_table("__works_20_at").unwrap().as_str(),
+            "___5f__5f_works_5f_20_5f_at"
+        );
+        for name in ["works_at", "works at", "__works_20_at", "für", "a`b", "_"] {
+            let table = relation_table(name).unwrap();
+            assert_eq!(relation_name(table.as_str()), name);
+        }
+        assert!(relation_table("").is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_clones_do_not_wait_on_each_other() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SurrealStore::new(dir.path().join("test.db")).await.unwrap();
+        store
+            .add_node(Node {
+                id: "a".to_string(),
+                label: "Person".to_string(),
+                properties: json!({"name": "Alice"}),
+                partition_id: "personal".to_string(),
+            })
+            .await
+            .unwrap();
+
+        // Keep one request busy, until told to stop, while a clone reads. The
+        // request marks itself started before it sleeps.
+        let busy = store.clone();
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+        let sleeper = tokio::spawn(async move {
+            tokio::select! {
+                result = busy.db().query("CREATE sleeper:started; SLEEP 1h") => {
+                    panic!("sleep ended: {:?}", result)
+                }
+                _ = released => {}
+            }
+        });
+
+        let wait = Duration::from_secs(10);
+        tokio::time::timeout(wait, async {
+            loop {
+                let mut response = store
+                    .db()
+                    .query("SELECT VALUE true FROM sleeper:started")
+                    .await
+                    .unwrap();
+                let started: Vec<bool> = response.take(0).unwrap();
+                if !started.is_empty() {
+                    break;
+                }
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("sleeper never started");
+
+        // Would time out if clones waited on each other
+        let node = tokio::time::timeout(wait, store.get_node("a"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(node.id, "a");
+        let neighbors = tokio::time::timeout(wait, store.get_neighbors("a"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(neighbors.is_empty());
+        assert!(!sleeper.is_finished());
+
+        release.send(()).unwrap();
+        sleeper.await.unwrap();
+    }
 }
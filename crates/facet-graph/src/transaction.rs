@@ -0,0 +1,194 @@
+use crate::{CrossPartitionPolicy, Edge, GraphError, GraphStore, Node};
+use std::collections::HashMap;
+
+/// A single write queued in a `GraphTransaction`
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphOp {
+    AddNode(Node),
+    AddEdge(Edge),
+    UpdateNode(Node),
+    DeleteNode {
+        id: String,
+        cascade: bool,
+    },
+    DeleteEdge {
+        source: String,
+        relation: String,
+        target: String,
+    },
+}
+
+/// A batch of writes applied atomically by `GraphStore::commit_transaction`.
+///
+/// Nothing touches the store until the transaction is committed; dropping it
+/// (or calling `rollback`) discards the queued writes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GraphTransaction {
+    ops: Vec<GraphOp>,
+}
+
+impl GraphTransaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_node(&mut self, node: Node) -> &mut Self {
+        self.ops.push(GraphOp::AddNode(node));
+        self
+    }
+
+    pub fn add_edge(&mut self, edge: Edge) -> &mut Self {
+        self.ops.push(GraphOp::AddEdge(edge));
+        self
+    }
+
+    pub fn update_node(&mut self, node: Node) -> &mut Self {
+        self.ops.push(GraphOp::UpdateNode(node));
+        self
+    }
+
+    pub fn delete_node(&mut self, id: &str, cascade: bool) -> &mut Self {
+        self.ops.push(GraphOp::DeleteNode {
+            id: id.to_string(),
+            cascade,
+        });
+        self
+    }
+
+    pub fn delete_edge(&mut self, source: &str, relation: &str, target: &str) -> &mut Self {
+        self.ops.push(GraphOp::DeleteEdge {
+            source: source.to_string(),
+            relation: relation.to_string(),
+            target: target.to_string(),
+        });
+        self
+    }
+
+    /// Discards all queued writes
+    pub fn rollback(self) {}
+
+    pub fn ops(&self) -> &[GraphOp] {
+        &self.ops
+    }
+
+    pub fn into_ops(self) -> Vec<GraphOp> {
+        self.ops
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+}
+
+/// Applies ops one by one through the regular store methods, stopping at the
+/// first error. Not atomic on its own.
+pub(crate) async fn apply_ops<S: GraphStore + ?Sized>(
+    store: &S,
+    ops: Vec<GraphOp>,
+) -> Result<(), GraphError> {
+    for op in ops {
+        match op {
+            GraphOp::AddNode(node) => store.add_node(node).await?,
+            GraphOp::AddEdge(edge) => store.add_edge(edge).await?,
+            GraphOp::UpdateNode(node) => store.update_node(node).await?,
+            GraphOp::DeleteNode { id, cascade } => store.delete_node(&id, cascade).await?,
+            GraphOp::DeleteEdge {
+                source,
+                relation,
+                target,
+            } => store.delete_edge(&source, &relation, &target).await?,
+        }
+    }
+    Ok(())
+}
+
+/// Applies `policy` to every edge in `ops`. An endpoint's partition is the
+/// one given by an earlier node op in the batch, else its entry in `stored`.
+pub(crate) fn enforce_partition_policy(
+    policy: CrossPartitionPolicy,
+    ops: &mut [GraphOp],
+    stored: &HashMap<String, String>,
+) -> Result<(), GraphError> {
+    let mut pending: HashMap<String, Option<String>> = HashMap::new();
+    for op in ops.iter_mut() {
+        match op {
+            GraphOp::AddNode(node) | GraphOp::UpdateNode(node) => {
+                pending.insert(node.id.clone(), Some(node.partition_id.clone()));
+            }
+            GraphOp::DeleteNode { id, .. } => {
+                pending.insert(id.clone(), None);
+            }
+            GraphOp::AddEdge(edge) => {
+                let partition = |id: &str| match pending.get(id) {
+                    Some(partition) => partition.clone(),
+                    None => stored.get(id).cloned(),
+                };
+                let (source, target) = (partition(&edge.source), partition(&edge.target));
+                policy.check(edge, source.as_deref(), target.as_deref())?;
+            }
+            GraphOp::DeleteEdge { .. } => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mocks::MockGraphStore;
+    use serde_json::json;
+
+    fn node(id: &str) -> Node {
+        Node {
+            id: id.to_string(),
+            label: "Person".to_string(),
+            properties: json!({}),
+            partition_id: "personal".to_string(),
+        }
+    }
+
+    fn edge(source: &str, target: &str) -> Edge {
+        Edge {
+            source: source.to_string(),
+            target: target.to_string(),
+            relation: "knows".to_string(),
+            weight: 1.0,
+            partition_id: "personal".to_string(),
+            valid_from: None,
+            valid_to: None,
+            cross_partition: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_commit_applies_all_ops() {
+        let store = MockGraphStore::new();
+
+        let mut tx = GraphTransaction::new();
+        tx.add_node(node("a"))
+            .add_node(node("b"))
+            .add_edge(edge("a", "b"));
+        assert_eq!(tx.len(), 3);
+
+        store.commit_transaction(tx).await.unwrap();
+        assert_eq!(store.get_neighbors("a").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_commit_leaves_store_untouched() {
+        let store = MockGraphStore::new();
+        store.add_node(node("a")).await.unwrap();
+
+        // The update of a missing node fails after the add has been applied
+        let mut tx = GraphTransaction::new();
+        tx.add_node(node("b")).update_node(node("missing"));
+
+        assert!(store.commit_transaction(tx).await.is_err());
+        assert!(store.get_node("b").await.is_err());
+        assert!(store.get_node("a").await.is_ok());
+    }
+}
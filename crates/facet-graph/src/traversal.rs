@@ -0,0 +1,511 @@
+use crate::snapshot::GraphSnapshot;
+use crate::{Direction, Edge, EdgeCost, GraphError, GraphPath, GraphStore, Node};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Breadth-first expansion over an in-memory edge list.
+///
+/// Returns every edge reached within `depth` hops of `seed`, in hop order,
+/// paired with the id of the node on its far side. Each edge is visited once,
+/// so cycles terminate. Edges whose relation is not in `relation_filter` are
+/// neither returned nor walked through.
+pub(crate) fn expand<'a>(
+    edges: &'a [Edge],
+    seed: &'a str,
+    depth: usize,
+    direction: Direction,
+    relation_filter: Option<&[&str]>,
+) -> Vec<(&'a Edge, &'a str)> {
+    let mut visited: HashSet<&str> = HashSet::from([seed]);
+    let mut frontier: HashSet<&str> = HashSet::from([seed]);
+    let mut used = vec![false; edges.len()];
+    let mut result = Vec::new();
+
+    for _ in 0..depth {
+        let mut next = HashSet::new();
+
+        for (i, edge) in edges.iter().enumerate() {
+            if used[i] {
+                continue;
+            }
+            if let Some(relations) = relation_filter {
+                if !relations.contains(&edge.relation.as_str()) {
+                    continue;
+                }
+            }
+
+            let far = if direction != Direction::Incoming && frontier.contains(edge.source.as_str())
+            {
+                edge.target.as_str()
+            } else if direction != Direction::Outgoing && frontier.contains(edge.target.as_str()) {
+                edge.source.as_str()
+            } else {
+                continue;
+            };
+
+            used[i] = true;
+            result.push((edge, far));
+            if visited.insert(far) {
+                next.insert(far);
+            }
+        }
+
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+
+    result
+}
+
+/// Breadth-first search through `get_neighbors_directed`, one store call per
+/// visited node.
+pub(crate) async fn shortest_path<S: GraphStore + ?Sized>(
+    store: &S,
+    from: &str,
+    to: &str,
+    max_depth: usize,
+    partition_id: Option<&str>,
+) -> Result<Option<GraphPath>, GraphError> {
+    let start = store.get_node(from).await?;
+    let end = store.get_node(to).await?;
+    if let Some(partition) = partition_id {
+        if start.partition_id != partition || end.partition_id != partition {
+            return Ok(None);
+        }
+    }
+    if from == to {
+        return Ok(Some(GraphPath {
+            nodes: vec![start],
+            edges: vec![],
+        }));
+    }
+
+    // Node id -> (node, edge it was reached by, previous node id)
+    let mut parents: HashMap<String, (Node, Edge, String)> = HashMap::new();
+    let mut visited: HashSet<String> = HashSet::from([from.to_string()]);
+    let mut frontier = vec![from.to_string()];
+
+    for _ in 0..max_depth {
+        let mut next = Vec::new();
+
+        for id in &frontier {
+            let neighbors = match partition_id {
+                Some(partition) => {
+                    store
+                        .get_neighbors_in_partition_directed(id, partition, Direction::Both)
+                        .await?
+                }
+                None => store.get_neighbors_directed(id, Direction::Both).await?,
+            };
+
+            for (edge, node) in neighbors {
+                if !visited.insert(node.id.clone()) {
+                    continue;
+                }
+                let reached = node.id == to;
+                next.push(node.id.clone());
+                parents.insert(node.id.clone(), (node, edge, id.clone()));
+
+                if reached {
+                    return Ok(Some(build_path(start, to, parents)));
+                }
+            }
+        }
+
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+
+    Ok(None)
+}
+
+/// A node waiting to be settled, ordered by the cost of reaching it
+#[derive(PartialEq)]
+struct Candidate {
+    cost: f32,
+    id: String,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cost
+            .total_cmp(&other.cost)
+            .then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Dijkstra's algorithm through `get_neighbors_directed`, one store call per
+/// settled node.
+pub(crate) async fn weighted_path<S: GraphStore + ?Sized>(
+    store: &S,
+    from: &str,
+    to: &str,
+    cost: EdgeCost,
+    partition_id: Option<&str>,
+) -> Result<Option<(GraphPath, f32)>, GraphError> {
+    let start = store.get_node(from).await?;
+    let end = store.get_node(to).await?;
+    if let Some(partition) = partition_id {
+        if start.partition_id != partition || end.partition_id != partition {
+            return Ok(None);
+        }
+    }
+
+    // Node id -> (node, edge it was reached by, previous node id)
+    let mut parents: HashMap<String, (Node, Edge, String)> = HashMap::new();
+    let mut best: HashMap<String, f32> = HashMap::from([(from.to_string(), 0.0)]);
+    let mut settled: HashSet<String> = HashSet::new();
+    let mut queue = BinaryHeap::from([Reverse(Candidate {
+        cost: 0.0,
+        id: from.to_string(),
+    })]);
+
+    while let Some(Reverse(Candidate { cost: reached, id })) = queue.pop() {
+        if id == to {
+            return Ok(Some((build_path(start, to, parents), reached)));
+        }
+        if !settled.insert(id.clone()) {
+            continue;
+        }
+
+        let neighbors = match partition_id {
+            Some(partition) => {
+                store
+                    .get_neighbors_in_partition_directed(&id, partition, Direction::Both)
+                    .await?
+            }
+            None => store.get_neighbors_directed(&id, Direction::Both).await?,
+        };
+
+        for (edge, node) in neighbors {
+            let Some(step) = cost.of(edge.weight) else {
+                continue;
+            };
+            let total = reached + step;
+            if settled.contains(&node.id) || best.get(&node.id).is_some_and(|&b| b <= total) {
+                continue;
+            }
+            best.insert(node.id.clone(), total);
+            queue.push(Reverse(Candidate {
+                cost: total,
+                id: node.id.clone(),
+            }));
+            parents.insert(node.id.clone(), (node, edge, id.clone()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Unions one `traverse` per seed, deduplicating nodes by id and edges by
+/// (source, relation, target).
+pub(crate) async fn extract_subgraph<S: GraphStore + ?Sized>(
+    store: &S,
+    seeds: &[String],
+    depth: usize,
+) -> Result<GraphSnapshot, GraphError> {
+    let mut node_ids = HashSet::new();
+    let mut nodes = Vec::new();
+    for seed in seeds {
+        if node_ids.insert(seed.clone()) {
+            nodes.push(store.get_node(seed).await?);
+        }
+    }
+
+    let mut edge_keys = HashSet::new();
+    let mut edges = Vec::new();
+    for seed in seeds {
+        for (edge, node) in store.traverse(seed, depth, Direction::Both, None).await? {
+            if node_ids.insert(node.id.clone()) {
+                nodes.push(node);
+            }
+            let key = (
+                edge.source.clone(),
+                edge.relation.clone(),
+                edge.target.clone(),
+            );
+            if edge_keys.insert(key) {
+                edges.push(edge);
+            }
+        }
+    }
+
+    Ok(GraphSnapshot::new(nodes, edges))
+}
+
+/// Walks parent links back from `to` and returns the path in forward order
+fn build_path(
+    start: Node,
+    to: &str,
+    mut parents: HashMap<String, (Node, Edge, String)>,
+) -> GraphPath {
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    let mut current = to.to_string();
+
+    while let Some((node, edge, previous)) = parents.remove(&current) {
+        nodes.push(node);
+        edges.push(edge);
+        current = previous;
+    }
+    nodes.push(start);
+
+    nodes.reverse();
+    edges.reverse();
+    GraphPath { nodes, edges }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mocks::MockGraphStore;
+    use serde_json::json;
+
+    fn edge(source: &str, relation: &str, target: &str) -> Edge {
+        Edge {
+            source: source.to_string(),
+            target: target.to_string(),
+            relation: relation.to_string(),
+            weight: 1.0,
+            partition_id: "personal".to_string(),
+            valid_from: None,
+            valid_to: None,
+            cross_partition: false,
+        }
+    }
+
+    fn far_ids<'a>(hits: &[(&Edge, &'a str)]) -> Vec<&'a str> {
+        hits.iter().map(|(_, far)| *far).collect()
+    }
+
+    #[test]
+    fn test_expand_respects_depth_and_direction() {
+        let edges = vec![
+            edge("a", "knows", "b"),
+            edge("b", "knows", "c"),
+            edge("c", "knows", "d"),
+            edge("x", "knows", "a"),
+        ];
+
+        assert_eq!(
+            far_ids(&expand(&edges, "a", 2, Direction::Outgoing, None)),
+            vec!["b", "c"]
+        );
+        assert_eq!(
+            far_ids(&expand(&edges, "a", 3, Direction::Incoming, None)),
+            vec!["x"]
+        );
+        assert_eq!(
+            far_ids(&expand(&edges, "a", 1, Direction::Both, None)),
+            vec!["b", "x"]
+        );
+        assert!(expand(&edges, "a", 0, Direction::Both, None).is_empty());
+    }
+
+    #[test]
+    fn test_expand_filters_relations_and_handles_cycles() {
+        let edges = vec![
+            edge("a", "knows", "b"),
+            edge("b", "works_at", "c"),
+            edge("b", "knows", "a"),
+        ];
+
+        // The filtered relation blocks the path through it
+        let hits = expand(&edges, "a", 3, Direction::Outgoing, Some(&["knows"]));
+        assert_eq!(far_ids(&hits), vec!["b", "a"]);
+
+        let hits = expand(&edges, "a", 3, Direction::Outgoing, None);
+        assert_eq!(hits.len(), 3);
+    }
+
+    async fn path_store() -> MockGraphStore {
+        let store = MockGraphStore::new();
+        for (id, partition) in [
+            ("a", "personal"),
+            ("b", "personal"),
+            ("c", "personal"),
+            ("d", "personal"),
+            ("w", "work"),
+        ] {
+            store
+                .add_node(Node {
+                    id: id.to_string(),
+                    label: "Person".to_string(),
+                    properties: json!({}),
+                    partition_id: partition.to_string(),
+                })
+                .await
+                .unwrap();
+        }
+
+        // a -> b -> c -> d, plus a shortcut through the work partition: a -> w <- d
+        let mut work_edge = edge("a", "knows", "w");
+        work_edge.partition_id = "work".to_string();
+        let mut work_edge_back = edge("d", "knows", "w");
+        work_edge_back.partition_id = "work".to_string();
+        for e in [
+            edge("a", "knows", "b"),
+            edge("b", "knows", "c"),
+            edge("c", "knows", "d"),
+            work_edge,
+            work_edge_back,
+        ] {
+            store.add_edge(e).await.unwrap();
+        }
+        store
+    }
+
+    fn path_ids(path: &GraphPath) -> Vec<&str> {
+        path.nodes.iter().map(|n| n.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_shortest_path_follows_edges_both_ways() {
+        let store = path_store().await;
+
+        let path = store
+            .shortest_path("a", "d", 5, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(path_ids(&path), vec!["a", "w", "d"]);
+        assert_eq!(path.edges.len(), 2);
+        assert_eq!(path.edges[1].source, "d");
+
+        let same = store
+            .shortest_path("a", "a", 5, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(path_ids(&same), vec!["a"]);
+
+        assert!(store
+            .shortest_path("a", "d", 1, None)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_shortest_path_respects_partition() {
+        let store = path_store().await;
+
+        let path = store
+            .shortest_path("a", "d", 5, Some("personal"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(path_ids(&path), vec!["a", "b", "c", "d"]);
+
+        // Endpoint outside the partition
+        assert!(store
+            .shortest_path("a", "w", 5, Some("personal"))
+            .await
+            .unwrap()
+            .is_none());
+
+        assert!(matches!(
+            store.shortest_path("a", "missing", 5, None).await,
+            Err(GraphError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_weighted_path_prices_edges() {
+        let store = MockGraphStore::new();
+        for id in ["a", "b", "c", "d", "w", "x"] {
+            store
+                .add_node(Node {
+                    id: id.to_string(),
+                    label: "Person".to_string(),
+                    properties: json!({}),
+                    partition_id: "personal".to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        // Strong chain a - b - c - d and a weak shortcut a - w - d
+        for (source, target, weight) in [
+            ("a", "b", 1.0),
+            ("b", "c", 1.0),
+            ("d", "c", 1.0),
+            ("a", "w", 0.1),
+            ("w", "d", 0.1),
+            ("x", "a", 0.0),
+        ] {
+            let mut e = edge(source, "knows", target);
+            e.weight = weight;
+            store.add_edge(e).await.unwrap();
+        }
+
+        let (path, cost) = store
+            .weighted_path("a", "d", EdgeCost::Strength, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(path_ids(&path), vec!["a", "b", "c", "d"]);
+        assert!((cost - 3.0).abs() < 1e-6);
+
+        let (path, cost) = store
+            .weighted_path("a", "d", EdgeCost::Weight, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(path_ids(&path), vec!["a", "w", "d"]);
+        assert!((cost - 0.2).abs() < 1e-6);
+
+        // A zero-strength edge is no connection at all
+        assert!(store
+            .weighted_path("x", "d", EdgeCost::Strength, None)
+            .await
+            .unwrap()
+            .is_none());
+        let (same, cost) = store
+            .weighted_path("a", "a", EdgeCost::Strength, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((path_ids(&same), cost), (vec!["a"], 0.0));
+    }
+
+    #[tokio::test]
+    async fn test_extract_subgraph_unions_seeds() {
+        let store = path_store().await;
+
+        // a -> b -> c -> d and a -> w <- d: one hop around b and d
+        let seeds = vec!["b".to_string(), "d".to_string(), "b".to_string()];
+        let subgraph = store.extract_subgraph(&seeds, 1).await.unwrap();
+
+        let mut ids: Vec<&str> = subgraph.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(&ids[..2], &["b", "d"]);
+        ids.sort();
+        assert_eq!(ids, vec!["a", "b", "c", "d", "w"]);
+        // b-c is reached from both seeds but listed once
+        assert_eq!(subgraph.edges.len(), 4);
+        for edge in &subgraph.edges {
+            assert!(ids.contains(&edge.source.as_str()));
+            assert!(ids.contains(&edge.target.as_str()));
+        }
+
+        let seed_only = store.extract_subgraph(&["a".to_string()], 0).await.unwrap();
+        assert_eq!(seed_only.nodes.len(), 1);
+        assert!(seed_only.edges.is_empty());
+
+        assert!(matches!(
+            store.extract_subgraph(&["missing".to_string()], 1).await,
+            Err(GraphError::NotFound(_))
+        ));
+    }
+}
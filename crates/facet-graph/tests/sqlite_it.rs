@@ -0,0 +1,764 @@
+#![cfg(feature = "sqlite")]
+
+use facet_graph::backend::{AnyStore, BackendKind, StoreConfig};
+use facet_graph::runs::{rollback_run, RunStore, RUNS_PARTITION, RUN_ID_PROPERTY};
+use facet_graph::snapshot::GraphSnapshot;
+use facet_graph::sqlite_store::SqliteStore;
+use facet_graph::transaction::GraphTransaction;
+use facet_graph::{
+    CrossPartitionPolicy, Direction, Edge, GraphError, GraphStore, Node, NodeOrder, PageRequest,
+    VectorStore,
+};
+use serde_json::json;
+use tempfile::tempdir;
+
+fn node(id: &str, name: &str, partition: &str) -> Node {
+    Node {
+        id: id.to_string(),
+        label: "Person".to_string(),
+        properties: json!({"name": name}),
+        partition_id: partition.to_string(),
+    }
+}
+
+fn edge(source: &str, relation: &str, target: &str) -> Edge {
+    Edge {
+        source: source.to_string(),
+        target: target.to_string(),
+        relation: relation.to_string(),
+        weight: 0.5,
+        partition_id: "personal".to_string(),
+        valid_from: None,
+        valid_to: None,
+        cross_partition: false,
+    }
+}
+
+async fn seeded() -> SqliteStore {
+    let store = SqliteStore::in_memory().unwrap();
+    store
+        .add_nodes(vec![
+            node("a", "Alice", "personal"),
+            node("b", "Bob", "personal"),
+            node("c", "Carol", "personal"),
+            node("w", "Walter", "work"),
+        ])
+        .await
+        .unwrap();
+    store
+        .add_edges(vec![
+            edge("a", "knows", "b"),
+            edge("b", "knows", "c"),
+            edge("w", "works_with", "a"),
+        ])
+        .await
+        .unwrap();
+    store
+}
+
+#[tokio::test]
+async fn test_sqlite_graph_ops() {
+    let store = seeded().await;
+
+    let alice = store.get_node("a").await.unwrap();
+    assert_eq!(alice.properties["name"], "Alice");
+    assert!(matches!(
+        store.add_node(node("a", "Again", "personal")).await,
+        Err(GraphError::Conflict(_))
+    ));
+
+    let neighbors = store.get_neighbors("a").await.unwrap();
+    assert_eq!(neighbors.len(), 1);
+    assert_eq!(neighbors[0].1.id, "b");
+    assert_eq!(neighbors[0].0.weight, 0.5);
+
+    let incoming = store.get_incoming_neighbors("a").await.unwrap();
+    assert_eq!(incoming[0].1.id, "w");
+
+    store
+        .upsert_node(Node {
+            properties: json!({"age": 30}),
+            ..node("a", "", "personal")
+        })
+        .await
+        .unwrap();
+    let alice = store.get_node("a").await.unwrap();
+    assert_eq!(alice.properties["name"], "Alice");
+    assert_eq!(alice.properties["age"], 30);
+
+    assert!(matches!(
+        store.delete_node("a", false).await,
+        Err(GraphError::Conflict(_))
+    ));
+    store.delete_node("a", true).await.unwrap();
+    assert!(store.get_neighbors("w").await.unwrap().is_empty());
+    assert!(matches!(
+        store.delete_edge("a", "knows", "b").await,
+        Err(GraphError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_sqlite_patch_node() {
+    let store = seeded().await;
+
+    let patched = store
+        .patch_node("a", json!({"age": 30, "address": {"city": "Berlin"}}))
+        .await
+        .unwrap();
+    assert_eq!(
+        patched.properties,
+        json!({"name": "Alice", "age": 30, "address": {"city": "Berlin"}})
+    );
+    store
+        .patch_node("a", json!({"address": {"zip": "10115"}}))
+        .await
+        .unwrap();
+    assert_eq!(
+        store.get_node("a").await.unwrap().properties,
+        json!({"name": "Alice", "age": 30, "address": {"city": "Berlin", "zip": "10115"}})
+    );
+    assert!(matches!(
+        store.patch_node("missing", json!({"age": 1})).await,
+        Err(GraphError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_sqlite_update_edge() {
+    let store = seeded().await;
+
+    assert_eq!(
+        store
+            .increment_edge_weight("a", "knows", "b", 0.25)
+            .await
+            .unwrap(),
+        0.75
+    );
+    assert_eq!(
+        store
+            .increment_edge_weight("a", "knows", "b", 0.25)
+            .await
+            .unwrap(),
+        1.0
+    );
+    assert!(matches!(
+        store.increment_edge_weight("b", "knows", "a", 1.0).await,
+        Err(GraphError::NotFound(_))
+    ));
+
+    store
+        .update_edge(Edge {
+            weight: 0.1,
+            partition_id: "work".to_string(),
+            ..edge("b", "knows", "c")
+        })
+        .await
+        .unwrap();
+    let (updated, _) = store.get_neighbors("b").await.unwrap().remove(0);
+    assert_eq!(updated.weight, 0.1);
+    assert_eq!(updated.partition_id, "work");
+    assert!(matches!(
+        store.update_edge(edge("c", "knows", "b")).await,
+        Err(GraphError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_sqlite_merge_nodes() {
+    use facet_graph::merge::merge_nodes;
+
+    let store = seeded().await;
+    store
+        .add_node(Node {
+            properties: json!({"name": "A. Smith", "email": "alice@example.com"}),
+            ..node("a2", "", "personal")
+        })
+        .await
+        .unwrap();
+    store
+        .add_edges(vec![
+            edge("a2", "knows", "b"),
+            edge("a2", "knows", "c"),
+            edge("c", "likes", "a2"),
+            edge("a2", "same_as", "a"),
+        ])
+        .await
+        .unwrap();
+    store
+        .add_embedding("a", vec![1.0, 0.0], None)
+        .await
+        .unwrap();
+    store
+        .add_embedding("a2", vec![0.0, 1.0], None)
+        .await
+        .unwrap();
+    store
+        .add_embedding("a2", vec![1.0, 1.0], Some("topic"))
+        .await
+        .unwrap();
+
+    let merged = merge_nodes(&store, "a", "a2").await.unwrap();
+    assert_eq!(merged.properties["name"], "Alice");
+    assert_eq!(merged.properties["email"], "alice@example.com");
+    assert_eq!(store.get_node("a").await.unwrap(), merged);
+    assert!(matches!(
+        store.get_node("a2").await,
+        Err(GraphError::NotFound(_))
+    ));
+
+    // The shared "knows b" edge is combined rather than duplicated
+    let mut outgoing: Vec<(String, f32)> = store
+        .get_neighbors("a")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(e, n)| (n.id, e.weight))
+        .collect();
+    outgoing.sort_by(|x, y| x.0.cmp(&y.0));
+    assert_eq!(outgoing, [("b".to_string(), 1.0), ("c".to_string(), 0.5)]);
+    let mut incoming: Vec<String> = store
+        .get_incoming_neighbors("a")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(_, n)| n.id)
+        .collect();
+    incoming.sort();
+    assert_eq!(incoming, ["c", "w"]);
+
+    let embeddings = store.get_embeddings("a").await.unwrap();
+    assert_eq!(embeddings.len(), 2);
+    assert_eq!(embeddings[0].field, None);
+    assert_eq!(embeddings[0].vector, [0.5, 0.5]);
+    assert_eq!(embeddings[1].field.as_deref(), Some("topic"));
+    assert!(store.get_embeddings("a2").await.unwrap().is_empty());
+
+    assert!(matches!(
+        merge_nodes(&store, "a", "a").await,
+        Err(GraphError::Conflict(_))
+    ));
+    assert!(matches!(
+        merge_nodes(&store, "a", "a2").await,
+        Err(GraphError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_sqlite_traverse_and_paths() {
+    let store = seeded().await;
+
+    let hits = store
+        .traverse("a", 2, Direction::Outgoing, None)
+        .await
+        .unwrap();
+    let ids: Vec<&str> = hits.iter().map(|(_, n)| n.id.as_str()).collect();
+    assert_eq!(ids, vec!["b", "c"]);
+
+    let hits = store
+        .traverse("a", 2, Direction::Both, Some(&["works_with"]))
+        .await
+        .unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].1.id, "w");
+
+    let path = store
+        .shortest_path("w", "c", 5, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(path.nodes.len(), 4);
+
+    assert!(matches!(
+        store.traverse("missing", 1, Direction::Both, None).await,
+        Err(GraphError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_sqlite_transaction_rolls_back() {
+    let store = seeded().await;
+
+    let mut tx = GraphTransaction::new();
+    tx.add_node(node("d", "Dave", "personal"))
+        .update_node(node("missing", "Nobody", "personal"));
+    assert!(store.commit_transaction(tx).await.is_err());
+    assert!(store.get_node("d").await.is_err());
+
+    let mut tx = GraphTransaction::new();
+    tx.add_node(node("d", "Dave", "personal"))
+        .add_edge(edge("c", "knows", "d"));
+    store.commit_transaction(tx).await.unwrap();
+    assert_eq!(store.get_neighbors("c").await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_sqlite_partitions_and_search() {
+    let store = seeded().await;
+
+    let page = store
+        .query_by_partition_page("personal", PageRequest::new(1, 1))
+        .await
+        .unwrap();
+    assert_eq!(page[0].id, "b");
+    let page = store
+        .query_by_partition_page(
+            "personal",
+            PageRequest::default().with_order(NodeOrder::Label, true),
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.len(), 3);
+    assert_eq!(page[0].id, "c");
+
+    let snapshot = store.snapshot(Some("personal")).await.unwrap();
+    assert_eq!(snapshot.nodes.len(), 3);
+    assert_eq!(snapshot.edges.len(), 2);
+
+    let people = store
+        .query_by_label("Person", Some("personal"), 2)
+        .await
+        .unwrap();
+    let ids: Vec<_> = people.iter().map(|n| n.id.as_str()).collect();
+    assert_eq!(ids, vec!["a", "b"]);
+    assert_eq!(
+        store
+            .query_by_label("Person", None, 10)
+            .await
+            .unwrap()
+            .len(),
+        4
+    );
+    assert!(store
+        .query_by_label("Topic", None, 10)
+        .await
+        .unwrap()
+        .is_empty());
+
+    assert!(store.node_exists("w").await.unwrap());
+    assert!(!store.node_exists("z").await.unwrap());
+    assert_eq!(store.count_nodes(None).await.unwrap(), 4);
+    assert_eq!(store.count_nodes(Some("work")).await.unwrap(), 1);
+    assert_eq!(store.count_edges(None).await.unwrap(), 3);
+    assert_eq!(store.count_edges(Some("knows")).await.unwrap(), 2);
+    assert_eq!(store.count_edges(Some("hates")).await.unwrap(), 0);
+
+    let hits = store.search_text("alice", 10).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].0.id, "a");
+    assert!(store.search_text("alice bob", 10).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_sqlite_temporal_edges() {
+    use chrono::{TimeZone, Utc};
+
+    let store = seeded().await;
+    let until = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+    store
+        .add_edge(Edge {
+            valid_to: Some(until),
+            ..edge("a", "works_with", "c")
+        })
+        .await
+        .unwrap();
+
+    let neighbors = store.get_neighbors("a").await.unwrap();
+    assert_eq!(neighbors.len(), 2);
+    assert_eq!(neighbors[1].0.valid_to, Some(until));
+
+    let current = store
+        .get_neighbors_at("a", Direction::Outgoing, until)
+        .await
+        .unwrap();
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0].1.id, "b");
+
+    let snapshot = store.snapshot(None).await.unwrap();
+    let closed = snapshot
+        .edges
+        .iter()
+        .find(|e| e.target == "c" && e.source == "a");
+    assert_eq!(closed.unwrap().valid_to, Some(until));
+}
+
+#[tokio::test]
+async fn test_sqlite_node_history() {
+    let dir = tempdir().unwrap();
+    let config = StoreConfig::new(BackendKind::Sqlite, dir.path().join("graph.sqlite"));
+    let store = config.with_history().open().await.unwrap();
+
+    store
+        .add_node(node("a", "Alice", "personal"))
+        .await
+        .unwrap();
+    store
+        .update_node(node("a", "Alicia", "personal"))
+        .await
+        .unwrap();
+    store
+        .update_node(node("a", "Alicia", "work"))
+        .await
+        .unwrap();
+
+    let history = store.get_node_history("a").await.unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].node, node("a", "Alice", "personal"));
+    assert_eq!(history[1].node.partition_id, "personal");
+    assert!(history[0].recorded_at < history[1].recorded_at);
+
+    store.delete_node("a", false).await.unwrap();
+    store
+        .restore_node_version("a", history[0].recorded_at)
+        .await
+        .unwrap();
+    assert_eq!(
+        store.get_node("a").await.unwrap(),
+        node("a", "Alice", "personal")
+    );
+    assert_eq!(store.get_node_history("a").await.unwrap().len(), 3);
+
+    assert!(matches!(
+        store
+            .restore_node_version("missing", history[0].recorded_at)
+            .await,
+        Err(GraphError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_sqlite_partition_management() {
+    let store = seeded().await;
+    store
+        .add_embedding("a", vec![1.0, 0.0], None)
+        .await
+        .unwrap();
+
+    store.create_partition("archive").await.unwrap();
+    assert!(matches!(
+        store.create_partition("archive").await,
+        Err(GraphError::Conflict(_))
+    ));
+    let counts: Vec<(String, usize)> = store
+        .list_partitions()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|p| (p.id, p.node_count))
+        .collect();
+    assert_eq!(
+        counts,
+        vec![
+            ("archive".to_string(), 0),
+            ("personal".to_string(), 3),
+            ("work".to_string(), 1)
+        ]
+    );
+
+    store.rename_partition("personal", "home").await.unwrap();
+    assert_eq!(store.query_by_partition("home").await.unwrap().len(), 3);
+    assert_eq!(
+        store.get_neighbors("a").await.unwrap()[0].0.partition_id,
+        "home"
+    );
+
+    assert!(matches!(
+        store.delete_partition("home", false).await,
+        Err(GraphError::Conflict(_))
+    ));
+    store.delete_partition("home", true).await.unwrap();
+    assert!(matches!(
+        store.delete_partition("home", true).await,
+        Err(GraphError::NotFound(_))
+    ));
+    assert!(store.get_neighbors("w").await.unwrap().is_empty());
+    assert!(store
+        .search(vec![1.0, 0.0], 5, None)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_sqlite_cross_partition_policy() {
+    let store = SqliteStore::in_memory()
+        .unwrap()
+        .with_cross_partition_policy(CrossPartitionPolicy::Deny);
+    store
+        .add_nodes(vec![
+            node("a", "Alice", "personal"),
+            node("b", "Bob", "personal"),
+            node("w", "Walter", "work"),
+        ])
+        .await
+        .unwrap();
+
+    store.add_edge(edge("a", "knows", "b")).await.unwrap();
+    assert!(matches!(
+        store.add_edge(edge("w", "works_with", "a")).await,
+        Err(GraphError::Conflict(_))
+    ));
+    assert!(matches!(
+        store
+            .add_edges(vec![edge("b", "knows", "a"), edge("b", "knows", "w")])
+            .await,
+        Err(GraphError::Conflict(_))
+    ));
+    assert!(store.get_neighbors("b").await.unwrap().is_empty());
+
+    let mut tx = GraphTransaction::new();
+    tx.add_node(node("c", "Carol", "work"))
+        .add_edge(edge("a", "knows", "c"));
+    assert!(matches!(
+        store.commit_transaction(tx).await,
+        Err(GraphError::Conflict(_))
+    ));
+    assert!(store.get_node("c").await.is_err());
+
+    let store = store.with_cross_partition_policy(CrossPartitionPolicy::Flag);
+    store.add_edge(edge("w", "works_with", "a")).await.unwrap();
+    let incoming = store.get_incoming_neighbors("a").await.unwrap();
+    assert!(incoming[0].0.cross_partition);
+    let snapshot = store.snapshot(None).await.unwrap();
+    assert_eq!(
+        snapshot.edges.iter().filter(|e| e.cross_partition).count(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_sqlite_backup_and_restore() {
+    use facet_graph::backup::{self, BackupSummary, GraphBackup, BACKUP_VERSION};
+
+    let store = seeded().await;
+    store
+        .add_embeddings(
+            vec![
+                ("a".to_string(), vec![1.0, 0.0]),
+                ("b".to_string(), vec![0.0, 1.0]),
+            ],
+            None,
+        )
+        .await
+        .unwrap();
+    store
+        .add_embedding("a", vec![0.5, 0.5], Some("title"))
+        .await
+        .unwrap();
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("graph.backup");
+    let summary = backup::backup(&store, &path).await.unwrap();
+    assert_eq!(
+        summary,
+        BackupSummary {
+            nodes: 4,
+            edges: 3,
+            embeddings: 3
+        }
+    );
+    // gzip magic bytes
+    assert_eq!(&std::fs::read(&path).unwrap()[..2], &[0x1f, 0x8b]);
+
+    let restored = SqliteStore::in_memory().unwrap();
+    assert_eq!(backup::restore(&restored, &path).await.unwrap(), summary);
+    assert_eq!(
+        restored.snapshot(None).await.unwrap(),
+        store.snapshot(None).await.unwrap()
+    );
+    assert_eq!(
+        restored.list_embeddings().await.unwrap(),
+        store.list_embeddings().await.unwrap()
+    );
+    assert!(backup::restore(&restored, &path).await.is_err());
+
+    let mut future = GraphBackup::load(&path).unwrap();
+    future.version = BACKUP_VERSION + 1;
+    future.save(&path).unwrap();
+    assert!(matches!(
+        backup::restore(&SqliteStore::in_memory().unwrap(), &path).await,
+        Err(GraphError::Storage(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_sqlite_vector_search_persists() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("graph.sqlite");
+
+    {
+        let store = SqliteStore::new(path.clone()).unwrap();
+        store
+            .add_embedding("a", vec![1.0, 0.0], None)
+            .await
+            .unwrap();
+        store
+            .add_embedding("b", vec![0.0, 1.0], None)
+            .await
+            .unwrap();
+        store
+            .add_embedding("a", vec![0.6, 0.8], None)
+            .await
+            .unwrap();
+    }
+
+    let config = StoreConfig::new(BackendKind::Sqlite, path);
+    let store = config.open().await.unwrap();
+    assert!(matches!(store, AnyStore::Sqlite(_)));
+
+    let results = store.search(vec![0.0, 1.0], 2, None).await.unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].0, "b");
+    assert!((results[1].1 - 0.8).abs() < 1e-6);
+
+    // Embeddings without a node are left out of search_nodes
+    store
+        .add_node(node("a", "Alice", "personal"))
+        .await
+        .unwrap();
+    let hits = store.search_nodes(vec![0.0, 1.0], 2, None).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].0.properties["name"], "Alice");
+
+    store.add_node(node("b", "Bob", "work")).await.unwrap();
+    store.clear_embeddings("personal").await.unwrap();
+    let results = store.search(vec![0.0, 1.0], 2, None).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0, "b");
+
+    store.remove_embedding("b").await.unwrap();
+    assert!(store
+        .search(vec![0.0, 1.0], 2, None)
+        .await
+        .unwrap()
+        .is_empty());
+
+    store
+        .add_embeddings(
+            vec![
+                ("a".to_string(), vec![1.0, 0.0]),
+                ("b".to_string(), vec![0.0, 1.0]),
+            ],
+            None,
+        )
+        .await
+        .unwrap();
+    let results = store.search(vec![1.0, 0.0], 2, None).await.unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].0, "a");
+}
+
+#[tokio::test]
+async fn test_sqlite_rollback_run() {
+    let dir = tempdir().unwrap();
+    let config = StoreConfig::new(BackendKind::Sqlite, dir.path().join("graph.sqlite"));
+    let store = config.with_history().open().await.unwrap();
+    for (id, name) in [("a", "Alice"), ("b", "Bob"), ("d", "Dave"), ("e", "Eve")] {
+        store.add_node(node(id, name, "personal")).await.unwrap();
+    }
+    store.add_edge(edge("a", "KNOWS", "b")).await.unwrap();
+    store
+        .add_embedding("a", vec![1.0, 0.0], None)
+        .await
+        .unwrap();
+    store
+        .add_embedding("d", vec![0.0, 1.0], None)
+        .await
+        .unwrap();
+    // Restored nodes and edges are stored anew, so compare them sorted
+    let sorted = |mut snapshot: GraphSnapshot| {
+        snapshot.nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        snapshot
+            .edges
+            .sort_by(|a, b| (&a.source, &a.target).cmp(&(&b.source, &b.target)));
+        snapshot
+    };
+    let before = sorted(store.snapshot(Some("personal")).await.unwrap());
+
+    let run = RunStore::new(store.clone(), "run-1");
+    run.add_node(node("c", "Carol", "personal")).await.unwrap();
+    run.add_edge(edge("a", "KNOWS", "c")).await.unwrap();
+    run.add_embedding("c", vec![0.5, 0.5], None).await.unwrap();
+    run.update_node(node("a", "Alicia", "personal"))
+        .await
+        .unwrap();
+    run.patch_node("a", json!({"age": 30})).await.unwrap();
+    run.increment_edge_weight("a", "KNOWS", "b", 1.0)
+        .await
+        .unwrap();
+    run.add_embedding("a", vec![0.6, 0.8], Some("title"))
+        .await
+        .unwrap();
+    run.delete_node("d", false).await.unwrap();
+    run.update_node(node("e", "Eva", "personal")).await.unwrap();
+    let mut tx = GraphTransaction::new();
+    tx.add_node(node("f", "Frank", "personal"))
+        .add_edge(edge("f", "KNOWS", "a"));
+    run.commit_transaction(tx).await.unwrap();
+    assert_eq!(
+        store.get_node("c").await.unwrap().properties[RUN_ID_PROPERTY],
+        "run-1"
+    );
+    assert!(run.delete_partition("personal", true).await.is_err());
+
+    // Written by someone else since, so kept
+    store
+        .update_node(node("e", "Evelyn", "personal"))
+        .await
+        .unwrap();
+
+    let report = rollback_run(&store, "run-1").await.unwrap();
+    assert_eq!(report.nodes_deleted, 2);
+    assert_eq!(report.nodes_restored, 2);
+    assert_eq!(report.edges_reverted, 3);
+    assert_eq!(report.embeddings_reverted, 2);
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].id, "e");
+
+    let mut after = sorted(store.snapshot(Some("personal")).await.unwrap());
+    let evelyn = after.nodes.iter_mut().find(|n| n.id == "e").unwrap();
+    assert_eq!(evelyn.properties["name"], "Evelyn");
+    *evelyn = node("e", "Eve", "personal");
+    assert_eq!(after, before);
+    assert_eq!(
+        store.get_embeddings("a").await.unwrap()[0].vector,
+        vec![1.0, 0.0]
+    );
+    assert_eq!(store.get_embeddings("a").await.unwrap().len(), 1);
+    assert_eq!(
+        store.get_embeddings("d").await.unwrap()[0].vector,
+        vec![0.0, 1.0]
+    );
+    assert!(store.get_embeddings("c").await.unwrap().is_empty());
+
+    // The log goes with the run
+    assert_eq!(store.count_nodes(Some(RUNS_PARTITION)).await.unwrap(), 0);
+    assert!(matches!(
+        rollback_run(&store, "run-1").await,
+        Err(GraphError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_sqlite_run_fails_when_history_unreadable() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("graph.sqlite");
+    let store = SqliteStore::new(path.clone()).unwrap();
+    store.add_node(node("a", "Alice", "personal")).await.unwrap();
+
+    // The store keeps history, but reading it breaks
+    rusqlite::Connection::open(&path)
+        .unwrap()
+        .execute_batch("DROP TABLE node_history")
+        .unwrap();
+
+    let run = RunStore::new(store.clone(), "run-1");
+    assert!(matches!(
+        run.update_node(node("a", "Alicia", "personal")).await,
+        Err(GraphError::Storage(_))
+    ));
+    assert_eq!(
+        store.get_node("a").await.unwrap().properties["name"],
+        "Alice"
+    );
+}
@@ -1,5 +1,6 @@
-use facet_graph::{GraphStore, Node, Edge, VectorStore};
-use facet_graph::surreal_store::SurrealStore;
+use facet_graph::{Direction, GraphError, GraphStore, Node, NodeOrder, PageRequest, Edge, VectorStore};
+use facet_graph::export::ExportFormat;
+use facet_graph::surreal_store::{SurrealStore, VectorIndex};
 use serde_json::json;
 use tempfile::tempdir;
 
@@ -39,6 +40,9 @@ async fn test_surreal_graph_ops() {
         relation: "knows".to_string(),
         weight: 0.8,
         partition_id: "personal".to_string(),
+        valid_from: None,
+        valid_to: None,
+        cross_partition: false,
     };
     store.add_edge(edge.clone()).await.unwrap();
 
@@ -58,6 +62,474 @@ async fn test_surreal_graph_ops() {
     assert_eq!(work_nodes[0].id, "p2");
 }
 
+#[tokio::test]
+async fn test_surreal_temporal_edges() {
+    use chrono::{TimeZone, Utc};
+    use facet_graph::transaction::GraphTransaction;
+
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test.db")).await.unwrap();
+
+    for id in ["alice", "acme", "globex", "initech"] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: "Entity".to_string(),
+                properties: json!({}),
+                partition_id: "personal".to_string(),
+            })
+            .await
+            .unwrap();
+    }
+
+    let t1 = Utc.with_ymd_and_hms(2022, 1, 1, 0, 0, 0).unwrap();
+    let t2 = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+    let works_at = |target: &str, valid_from, valid_to| Edge {
+        source: "alice".to_string(),
+        target: target.to_string(),
+        relation: "works_at".to_string(),
+        weight: 1.0,
+        partition_id: "personal".to_string(),
+        valid_from,
+        valid_to,
+        cross_partition: false,
+    };
+
+    // Each write path has to keep the window
+    store.add_edge(works_at("acme", None, Some(t1))).await.unwrap();
+    store
+        .add_edges(vec![works_at("globex", Some(t1), Some(t2))])
+        .await
+        .unwrap();
+    let mut tx = GraphTransaction::new();
+    tx.add_edge(works_at("initech", Some(t2), None));
+    store.commit_transaction(tx).await.unwrap();
+
+    let all = store.get_neighbors("alice").await.unwrap();
+    assert_eq!(all.len(), 3);
+    let globex = all.iter().find(|(_, n)| n.id == "globex").unwrap();
+    assert_eq!(globex.0.valid_from, Some(t1));
+    assert_eq!(globex.0.valid_to, Some(t2));
+
+    for (at, expected) in [
+        (t1 - chrono::Duration::days(1), "acme"),
+        (t1, "globex"),
+        (t2, "initech"),
+    ] {
+        let neighbors = store
+            .get_neighbors_at("alice", Direction::Outgoing, at)
+            .await
+            .unwrap();
+        assert_eq!(neighbors.len(), 1);
+        assert_eq!(neighbors[0].1.id, expected);
+    }
+
+    let incoming = store
+        .get_neighbors_at("globex", Direction::Incoming, t1)
+        .await
+        .unwrap();
+    assert_eq!(incoming.len(), 1);
+    assert_eq!(incoming[0].0.valid_to, Some(t2));
+
+    let snapshot = store.snapshot(None).await.unwrap();
+    let acme = snapshot.edges.iter().find(|e| e.target == "acme").unwrap();
+    assert_eq!(acme.valid_from, None);
+    assert_eq!(acme.valid_to, Some(t1));
+}
+
+#[tokio::test]
+async fn test_surreal_node_history() {
+    use facet_graph::transaction::GraphTransaction;
+
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test.db")).await.unwrap();
+    let person = |name: &str| Node {
+        id: "p1".to_string(),
+        label: "Person".to_string(),
+        properties: json!({"name": name}),
+        partition_id: "personal".to_string(),
+    };
+
+    // Off by default
+    store.add_node(person("Alice")).await.unwrap();
+    store.update_node(person("Alicia")).await.unwrap();
+    assert!(store.get_node_history("p1").await.unwrap().is_empty());
+
+    store.set_history(true).await.unwrap();
+    store.update_node(person("Ali")).await.unwrap();
+    store
+        .add_embedding("p1", vec![1.0, 0.0], None)
+        .await
+        .unwrap();
+    let mut tx = GraphTransaction::new();
+    tx.update_node(person("Al"));
+    store.commit_transaction(tx).await.unwrap();
+
+    let history = store.get_node_history("p1").await.unwrap();
+    let names: Vec<_> = history.iter().map(|v| &v.node.properties["name"]).collect();
+    assert_eq!(names, vec!["Alicia", "Ali"]);
+    assert!(history[0].recorded_at < history[1].recorded_at);
+
+    store
+        .restore_node_version("p1", history[0].recorded_at)
+        .await
+        .unwrap();
+    assert_eq!(store.get_node("p1").await.unwrap().properties["name"], "Alicia");
+    assert_eq!(store.get_node_history("p1").await.unwrap().len(), 3);
+
+    // Deleted nodes can be brought back
+    store.delete_node("p1", false).await.unwrap();
+    let history = store.get_node_history("p1").await.unwrap();
+    assert_eq!(history.len(), 4);
+    store
+        .restore_node_version("p1", history[1].recorded_at)
+        .await
+        .unwrap();
+    assert_eq!(store.get_node("p1").await.unwrap().properties["name"], "Ali");
+
+    store.set_history(false).await.unwrap();
+    store.update_node(person("Bob")).await.unwrap();
+    assert_eq!(store.get_node_history("p1").await.unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_surreal_expiry() {
+    use chrono::{Duration, Utc};
+
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test.db")).await.unwrap();
+    for id in ["clip", "session", "keep"] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: "Context".to_string(),
+                properties: json!({"name": id}),
+                partition_id: "personal".to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    store
+        .add_edge(Edge {
+            source: "keep".to_string(),
+            target: "clip".to_string(),
+            relation: "mentions".to_string(),
+            weight: 1.0,
+            partition_id: "personal".to_string(),
+            valid_from: None,
+            valid_to: None,
+            cross_partition: false,
+        })
+        .await
+        .unwrap();
+
+    let past = Utc::now() - Duration::minutes(5);
+    store.set_expiry("clip", Some(past)).await.unwrap();
+    store
+        .expire_after("session", std::time::Duration::from_secs(3600))
+        .await
+        .unwrap();
+    assert!(store.get_expiry("session").await.unwrap().unwrap() > Utc::now());
+    assert_eq!(store.get_expiry("keep").await.unwrap(), None);
+
+    // Updates leave the expiry alone
+    let mut clip = store.get_node("clip").await.unwrap();
+    clip.properties = json!({"name": "clip", "text": "copied"});
+    store.update_node(clip).await.unwrap();
+
+    assert_eq!(store.purge_expired().await.unwrap(), 1);
+    assert!(matches!(store.get_node("clip").await, Err(GraphError::NotFound(_))));
+    assert!(store.get_neighbors("keep").await.unwrap().is_empty());
+    assert!(store.get_node("session").await.is_ok());
+    assert_eq!(store.purge_expired().await.unwrap(), 0);
+
+    // Deleting a node drops its expiry, so a new node with the id is safe
+    store.set_expiry("session", Some(past)).await.unwrap();
+    store.delete_node("session", false).await.unwrap();
+    assert_eq!(store.get_expiry("session").await.unwrap(), None);
+
+    store.set_expiry("keep", Some(past)).await.unwrap();
+    store.set_expiry("keep", None).await.unwrap();
+    assert_eq!(store.purge_expired().await.unwrap(), 0);
+
+    store.set_expiry("keep", Some(past)).await.unwrap();
+    let sweeper = store.spawn_expiry_sweeper(std::time::Duration::from_millis(20));
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    sweeper.abort();
+    assert!(store.get_node("keep").await.is_err());
+}
+
+#[tokio::test]
+async fn test_surreal_partition_management() {
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test.db")).await.unwrap();
+    for (id, partition) in [("a", "personal"), ("b", "personal"), ("w", "work")] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: "Person".to_string(),
+                properties: json!({"name": id}),
+                partition_id: partition.to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    for (source, target) in [("a", "b"), ("w", "a")] {
+        store
+            .add_edge(Edge {
+                source: source.to_string(),
+                target: target.to_string(),
+                relation: "knows".to_string(),
+                weight: 1.0,
+                partition_id: "personal".to_string(),
+                valid_from: None,
+                valid_to: None,
+                cross_partition: false,
+            })
+            .await
+            .unwrap();
+    }
+    store
+        .add_embedding("a", vec![1.0, 0.0], None)
+        .await
+        .unwrap();
+
+    store.create_partition("archive").await.unwrap();
+    assert!(matches!(
+        store.create_partition("personal").await,
+        Err(GraphError::Conflict(_))
+    ));
+    let listed: Vec<(String, usize)> = store
+        .list_partitions()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|p| (p.id, p.node_count))
+        .collect();
+    assert_eq!(
+        listed,
+        vec![
+            ("archive".to_string(), 0),
+            ("personal".to_string(), 2),
+            ("work".to_string(), 1)
+        ]
+    );
+
+    assert!(matches!(
+        store.rename_partition("missing", "other").await,
+        Err(GraphError::NotFound(_))
+    ));
+    assert!(matches!(
+        store.rename_partition("personal", "archive").await,
+        Err(GraphError::Conflict(_))
+    ));
+    store.rename_partition("personal", "home").await.unwrap();
+    assert_eq!(store.query_by_partition("home").await.unwrap().len(), 2);
+    assert!(store.query_by_partition("personal").await.unwrap().is_empty());
+    let incoming = store.get_incoming_neighbors("a").await.unwrap();
+    assert_eq!(incoming[0].0.partition_id, "home");
+
+    assert!(matches!(
+        store.delete_partition("home", false).await,
+        Err(GraphError::Conflict(_))
+    ));
+    store.delete_partition("home", true).await.unwrap();
+    store.delete_partition("archive", false).await.unwrap();
+
+    let listed = store.list_partitions().await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].id, "work");
+    assert!(store.get_node("a").await.is_err());
+    assert!(store.get_neighbors("w").await.unwrap().is_empty());
+    assert!(store.search(vec![1.0, 0.0], 5, None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_surreal_cross_partition_policy() {
+    use facet_graph::transaction::GraphTransaction;
+    use facet_graph::CrossPartitionPolicy;
+
+    let person = |id: &str, partition: &str| Node {
+        id: id.to_string(),
+        label: "Person".to_string(),
+        properties: json!({}),
+        partition_id: partition.to_string(),
+    };
+    let knows = |source: &str, target: &str| Edge {
+        source: source.to_string(),
+        target: target.to_string(),
+        relation: "knows".to_string(),
+        weight: 1.0,
+        partition_id: "personal".to_string(),
+        valid_from: None,
+        valid_to: None,
+        cross_partition: false,
+    };
+
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test.db"))
+        .await
+        .unwrap()
+        .with_cross_partition_policy(CrossPartitionPolicy::Deny);
+    store
+        .add_nodes(vec![person("a", "personal"), person("b", "personal"), person("w", "work")])
+        .await
+        .unwrap();
+
+    store.add_edge(knows("a", "b")).await.unwrap();
+    assert!(matches!(
+        store.add_edge(knows("a", "w")).await,
+        Err(GraphError::Conflict(_))
+    ));
+    assert!(matches!(
+        store.add_edges(vec![knows("b", "a"), knows("w", "b")]).await,
+        Err(GraphError::Conflict(_))
+    ));
+    assert!(store.get_neighbors("b").await.unwrap().is_empty());
+
+    // Partitions written earlier in the transaction count
+    let mut tx = GraphTransaction::new();
+    tx.add_node(person("c", "work")).add_edge(knows("a", "c"));
+    assert!(matches!(
+        store.commit_transaction(tx).await,
+        Err(GraphError::Conflict(_))
+    ));
+    assert!(store.get_node("c").await.is_err());
+    let mut tx = GraphTransaction::new();
+    tx.update_node(person("w", "personal")).add_edge(knows("a", "w"));
+    store.commit_transaction(tx).await.unwrap();
+    assert_eq!(store.get_neighbors("a").await.unwrap().len(), 2);
+
+    let store = store.with_cross_partition_policy(CrossPartitionPolicy::Flag);
+    store.add_node(person("x", "work")).await.unwrap();
+    store.add_edges(vec![knows("b", "x"), knows("b", "a")]).await.unwrap();
+    let mut flagged: Vec<(String, bool)> = store
+        .get_neighbors("b")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(e, n)| (n.id, e.cross_partition))
+        .collect();
+    flagged.sort();
+    assert_eq!(
+        flagged,
+        vec![("a".to_string(), false), ("x".to_string(), true)]
+    );
+}
+
+#[tokio::test]
+async fn test_surreal_encryption_at_rest() {
+    use facet_types::profiles::crypto::EncryptionKey;
+
+    let dir = tempdir().unwrap();
+    let plain = SurrealStore::new(dir.path().join("test.db")).await.unwrap();
+    // Shares the database with `plain`, which shows what is on disk
+    let store = plain
+        .clone()
+        .with_encryption(EncryptionKey::from_bytes(vec![7; 32]));
+
+    store
+        .add_node(Node {
+            id: "a".to_string(),
+            label: "Person".to_string(),
+            properties: json!({"name": "Alice", "notes": {"salary": 100}}),
+            partition_id: "personal".to_string(),
+        })
+        .await
+        .unwrap();
+    store
+        .upsert_node(Node {
+            id: "a".to_string(),
+            label: "Person".to_string(),
+            properties: json!({"notes": {"city": "Lisbon"}}),
+            partition_id: "personal".to_string(),
+        })
+        .await
+        .unwrap();
+    store.add_embedding("a", vec![1.0, 0.0], None).await.unwrap();
+    store
+        .add_embeddings(vec![("a".to_string(), vec![0.0, 1.0])], Some("title"))
+        .await
+        .unwrap();
+
+    let alice = store.get_node("a").await.unwrap();
+    assert_eq!(
+        alice.properties,
+        json!({"name": "Alice", "notes": {"salary": 100, "city": "Lisbon"}})
+    );
+    let raw = plain.get_node("a").await.unwrap().properties;
+    assert!(raw.is_string());
+    assert!(!raw.to_string().contains("Alice"));
+
+    let hits = store.search_text("alice", 5).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert!(plain.search_text("alice", 5).await.unwrap().is_empty());
+
+    let hits = store.search_nodes(vec![1.0, 0.0], 5, None).await.unwrap();
+    assert_eq!(hits[0].0.properties["name"], "Alice");
+    assert!((hits[0].1 - 1.0).abs() < 1e-6);
+    let hits = store.search(vec![0.0, 1.0], 5, Some("title")).await.unwrap();
+    assert_eq!(hits[0].0, "a");
+    assert!(plain.search(vec![1.0, 0.0], 5, None).await.unwrap().is_empty());
+
+    store.remove_embedding("a").await.unwrap();
+    assert!(store.search(vec![1.0, 0.0], 5, None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_surreal_backup_and_restore() {
+    use facet_graph::backup;
+
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test.db")).await.unwrap();
+    for id in ["a", "b"] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: "Person".to_string(),
+                properties: json!({"name": id}),
+                partition_id: "personal".to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    store
+        .add_edge(Edge {
+            source: "a".to_string(),
+            target: "b".to_string(),
+            relation: "knows".to_string(),
+            weight: 0.5,
+            partition_id: "personal".to_string(),
+            valid_from: None,
+            valid_to: None,
+            cross_partition: false,
+        })
+        .await
+        .unwrap();
+    store.add_embedding("a", vec![1.0, 0.0], None).await.unwrap();
+    store
+        .add_embedding("b", vec![0.0, 1.0], Some("title"))
+        .await
+        .unwrap();
+
+    let path = dir.path().join("graph.backup");
+    let summary = backup::backup(&store, &path).await.unwrap();
+    assert_eq!((summary.nodes, summary.edges, summary.embeddings), (2, 1, 2));
+
+    let restored = SurrealStore::new(dir.path().join("restored.db")).await.unwrap();
+    backup::restore(&restored, &path).await.unwrap();
+    assert_eq!(
+        restored.snapshot(None).await.unwrap(),
+        store.snapshot(None).await.unwrap()
+    );
+    assert_eq!(
+        restored.list_embeddings().await.unwrap(),
+        store.list_embeddings().await.unwrap()
+    );
+    let hits = restored.search(vec![0.0, 1.0], 1, Some("title")).await.unwrap();
+    assert_eq!(hits[0].0, "b");
+}
+
 #[tokio::test]
 async fn test_surreal_vector_ops() {
     let dir = tempdir().unwrap();
@@ -76,12 +548,1438 @@ async fn test_surreal_vector_ops() {
 
     // Add embedding
     let vec = vec![1.0, 0.0, 0.5];
-    store.add_embedding("doc1", vec.clone()).await.unwrap();
+    store.add_embedding("doc1", vec.clone(), None).await.unwrap();
 
     // Search
-    let results = store.search(vec, 1).await.unwrap();
+    let results = store.search(vec, 1, None).await.unwrap();
     assert_eq!(results.len(), 1);
     assert_eq!(results[0].0, "doc1");
     // Cosine similarity of identical vectors should be ~1.0
     assert!((results[0].1 - 1.0).abs() < 0.001);
+
+    // Full nodes come back in the same query
+    let hits = store.search_nodes(vec![1.0, 0.0, 0.5], 5, None).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].0.id, "doc1");
+    assert_eq!(hits[0].0.properties["content"], "Hello world");
+    assert!((hits[0].1 - 1.0).abs() < 0.001);
+}
+
+#[tokio::test]
+async fn test_surreal_vector_index_knn() {
+    for index in [VectorIndex::hnsw(3), VectorIndex::mtree(3)] {
+        let dir = tempdir().unwrap();
+        let store = SurrealStore::with_vector_index(dir.path().join("test_knn.db"), index)
+            .await
+            .unwrap();
+
+        for (id, vector) in [
+            ("a", vec![1.0, 0.0, 0.0]),
+            ("b", vec![0.0, 1.0, 0.0]),
+            ("c", vec![0.7, 0.7, 0.0]),
+        ] {
+            store
+                .add_node(Node {
+                    id: id.to_string(),
+                    label: "Document".to_string(),
+                    properties: json!({}),
+                    partition_id: "personal".to_string(),
+                })
+                .await
+                .unwrap();
+            store.add_embedding(id, vector, None).await.unwrap();
+        }
+        // Nodes without an embedding are skipped by the index
+        store
+            .add_node(Node {
+                id: "bare".to_string(),
+                label: "Document".to_string(),
+                properties: json!({}),
+                partition_id: "personal".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let results = store.search(vec![1.0, 0.1, 0.0], 2, None).await.unwrap();
+        let ids: Vec<&str> = results.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "c"], "{:?}", index);
+        assert!(results[0].1 > results[1].1);
+
+        // The index enforces its dimension
+        assert!(matches!(
+            store.add_embedding("b", vec![1.0], None).await,
+            Err(GraphError::DimensionMismatch {
+                expected: 3,
+                actual: 1
+            })
+        ));
+    }
+}
+
+#[tokio::test]
+async fn test_surreal_embedding_dimension() {
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_dimension.db"))
+        .await
+        .unwrap()
+        .with_embedding_dimension(3);
+    assert_eq!(store.embedding_dimension(), Some(3));
+
+    for id in ["a", "b"] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: "Document".to_string(),
+                properties: json!({}),
+                partition_id: "personal".to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    store
+        .add_embedding("a", vec![1.0, 0.0, 0.0], None)
+        .await
+        .unwrap();
+
+    let mismatch = |result: Result<_, GraphError>| {
+        matches!(
+            result,
+            Err(GraphError::DimensionMismatch {
+                expected: 3,
+                actual: 2
+            })
+        )
+    };
+    assert!(mismatch(
+        store.add_embedding("b", vec![1.0, 0.0], None).await
+    ));
+    assert!(mismatch(
+        store
+            .add_embedding("b", vec![1.0, 0.0], Some("title"))
+            .await
+    ));
+    // One bad vector rejects the whole batch
+    assert!(mismatch(
+        store
+            .add_embeddings(
+                vec![
+                    ("a".to_string(), vec![0.0, 1.0, 0.0]),
+                    ("b".to_string(), vec![0.0, 1.0]),
+                ],
+                None,
+            )
+            .await
+    ));
+    assert!(mismatch(
+        store.search(vec![1.0, 0.0], 5, None).await.map(|_| ())
+    ));
+
+    let hits = store.search(vec![1.0, 0.0, 0.0], 5, None).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].0, "a");
+    assert!(hits[0].1 > 0.99);
+}
+
+#[tokio::test]
+async fn test_surreal_remove_and_clear_embeddings() {
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_clear.db"))
+        .await
+        .unwrap();
+
+    for (id, partition) in [("a", "personal"), ("b", "personal"), ("w", "work")] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: "Chunk".to_string(),
+                properties: json!({}),
+                partition_id: partition.to_string(),
+            })
+            .await
+            .unwrap();
+        store.add_embedding(id, vec![1.0, 0.0], None).await.unwrap();
+    }
+
+    store.remove_embedding("a").await.unwrap();
+    // Removing twice, or from a missing node, is a no-op
+    store.remove_embedding("a").await.unwrap();
+    store.remove_embedding("missing").await.unwrap();
+    assert!(store.get_node("missing").await.is_err());
+
+    let ids: Vec<String> = store
+        .search(vec![1.0, 0.0], 10, None)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+    assert_eq!(ids.len(), 2);
+    assert!(!ids.contains(&"a".to_string()));
+
+    store.clear_embeddings("personal").await.unwrap();
+    let results = store.search(vec![1.0, 0.0], 10, None).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0, "w");
+    // The nodes themselves stay
+    assert!(store.get_node("b").await.is_ok());
+}
+
+#[tokio::test]
+async fn test_surreal_batch_embeddings() {
+    let dir = tempdir().unwrap();
+    let store =
+        SurrealStore::with_vector_index(dir.path().join("test_batch_vec.db"), VectorIndex::hnsw(2))
+            .await
+            .unwrap();
+
+    let chunks: Vec<Node> = (0..50)
+        .map(|i| Node {
+            id: format!("chunk{}", i),
+            label: "Chunk".to_string(),
+            properties: json!({"index": i}),
+            partition_id: "personal".to_string(),
+        })
+        .collect();
+    store.add_nodes(chunks).await.unwrap();
+
+    let embeddings: Vec<(String, Vec<f32>)> = (0..50)
+        .map(|i| (format!("chunk{}", i), vec![1.0, i as f32]))
+        .collect();
+    store.add_embeddings(embeddings, None).await.unwrap();
+    store.add_embeddings(vec![], None).await.unwrap();
+
+    let results = store.search(vec![1.0, 0.0], 3, None).await.unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].0, "chunk0");
+
+    // One bad vector rejects the whole batch
+    let bad = vec![
+        ("chunk0".to_string(), vec![0.0, 1.0]),
+        ("chunk1".to_string(), vec![1.0]),
+    ];
+    assert!(store.add_embeddings(bad, None).await.is_err());
+    let results = store.search(vec![1.0, 0.0], 1, None).await.unwrap();
+    assert_eq!(results[0].0, "chunk0");
+}
+
+#[tokio::test]
+async fn test_surreal_named_embedding_fields() {
+    let dir = tempdir().unwrap();
+    let store =
+        SurrealStore::with_vector_index(dir.path().join("test_fields.db"), VectorIndex::hnsw(2))
+            .await
+            .unwrap();
+
+    for id in ["a", "b"] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: "Document".to_string(),
+                properties: json!({"title": id}),
+                partition_id: "personal".to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    store.add_embedding("a", vec![1.0, 0.0], None).await.unwrap();
+    store
+        .add_embeddings(
+            vec![
+                ("a".to_string(), vec![0.0, 1.0]),
+                ("b".to_string(), vec![1.0, 0.0]),
+            ],
+            Some("title"),
+        )
+        .await
+        .unwrap();
+
+    // Each space is searched on its own
+    let results = store.search(vec![1.0, 0.0], 5, None).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0, "a");
+    let hits = store
+        .search_nodes(vec![1.0, 0.0], 5, Some("title"))
+        .await
+        .unwrap();
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[0].0.id, "b");
+
+    // The named space got its own index with the configured dimension
+    assert!(store
+        .add_embedding("a", vec![1.0, 0.0, 0.0], Some("title"))
+        .await
+        .is_err());
+    assert!(store
+        .search(vec![1.0, 0.0], 5, Some("bad field"))
+        .await
+        .is_err());
+
+    // Removal covers every space
+    store.remove_embedding("a").await.unwrap();
+    assert!(store.search(vec![1.0, 0.0], 5, None).await.unwrap().is_empty());
+    let results = store.search(vec![0.0, 1.0], 5, Some("title")).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0, "b");
+}
+
+#[tokio::test]
+async fn test_surreal_extract_subgraph() {
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_subgraph.db"))
+        .await
+        .unwrap();
+
+    for id in ["a", "b", "c", "d"] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: "Person".to_string(),
+                properties: json!({}),
+                partition_id: "personal".to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    for (source, target) in [("a", "b"), ("b", "c"), ("c", "d")] {
+        store
+            .add_edge(Edge {
+                source: source.to_string(),
+                target: target.to_string(),
+                relation: "knows".to_string(),
+                weight: 1.0,
+                partition_id: "personal".to_string(),
+                valid_from: None,
+                valid_to: None,
+                cross_partition: false,
+            })
+            .await
+            .unwrap();
+    }
+
+    let subgraph = store
+        .extract_subgraph(&["b".to_string()], 1)
+        .await
+        .unwrap();
+    let mut ids: Vec<&str> = subgraph.nodes.iter().map(|n| n.id.as_str()).collect();
+    assert_eq!(ids[0], "b");
+    ids.sort();
+    assert_eq!(ids, vec!["a", "b", "c"]);
+    assert_eq!(subgraph.edges.len(), 2);
+}
+
+#[tokio::test]
+async fn test_surreal_delete_ops() {
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_delete.db")).await.unwrap();
+
+    for id in ["a", "b", "c"] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: "Person".to_string(),
+                properties: json!({}),
+                partition_id: "personal".to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    for (source, target) in [("a", "b"), ("c", "b")] {
+        store
+            .add_edge(Edge {
+                source: source.to_string(),
+                target: target.to_string(),
+                relation: "knows".to_string(),
+                weight: 1.0,
+                partition_id: "personal".to_string(),
+                valid_from: None,
+                valid_to: None,
+                cross_partition: false,
+            })
+            .await
+            .unwrap();
+    }
+
+    // Edges attached to "b" block a non-cascading delete
+    assert!(matches!(
+        store.delete_node("b", false).await,
+        Err(GraphError::Conflict(_))
+    ));
+
+    // Deleting a single edge
+    store.delete_edge("a", "knows", "b").await.unwrap();
+    assert!(store.get_neighbors("a").await.unwrap().is_empty());
+    assert!(matches!(
+        store.delete_edge("a", "knows", "b").await,
+        Err(GraphError::NotFound(_))
+    ));
+
+    // Cascade removes the remaining incoming edge from "c"
+    store.delete_node("b", true).await.unwrap();
+    assert!(matches!(
+        store.get_node("b").await,
+        Err(GraphError::NotFound(_))
+    ));
+
+    // Re-creating "b" must not resurrect a dangling relation record
+    store
+        .add_node(Node {
+            id: "b".to_string(),
+            label: "Person".to_string(),
+            properties: json!({}),
+            partition_id: "personal".to_string(),
+        })
+        .await
+        .unwrap();
+    assert!(store.get_neighbors("c").await.unwrap().is_empty());
+
+    assert!(matches!(
+        store.delete_node("missing", true).await,
+        Err(GraphError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_surreal_patch_node() {
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_patch.db")).await.unwrap();
+    store
+        .add_node(Node {
+            id: "a".to_string(),
+            label: "Person".to_string(),
+            properties: json!({"name": "Alice", "address": {"city": "Berlin"}}),
+            partition_id: "personal".to_string(),
+        })
+        .await
+        .unwrap();
+
+    // Writers patching different keys at the same time keep each other's
+    let writers: Vec<_> = (0..8)
+        .map(|i| {
+            let store = store.clone();
+            tokio::spawn(async move {
+                let mut patch = serde_json::Map::new();
+                patch.insert(format!("k{i}"), json!(i));
+                store.patch_node("a", patch.into()).await.unwrap()
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.await.unwrap();
+    }
+    let node = store.get_node("a").await.unwrap();
+    for i in 0..8 {
+        assert_eq!(node.properties[format!("k{i}")], json!(i));
+    }
+
+    let patched = store
+        .patch_node("a", json!({"address": {"zip": "10115"}}))
+        .await
+        .unwrap();
+    assert_eq!(patched.properties["name"], "Alice");
+    assert_eq!(
+        patched.properties["address"],
+        json!({"city": "Berlin", "zip": "10115"})
+    );
+    assert_eq!(store.get_node("a").await.unwrap(), patched);
+    assert!(matches!(
+        store.patch_node("missing", json!({"age": 1})).await,
+        Err(GraphError::NotFound(_))
+    ));
+    assert!(store.get_node("missing").await.is_err());
+}
+
+#[tokio::test]
+async fn test_surreal_update_edge() {
+    use chrono::{TimeZone, Utc};
+
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_update_edge.db")).await.unwrap();
+
+    for id in ["a", "b"] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: "Person".to_string(),
+                properties: json!({}),
+                partition_id: "personal".to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    let edge = Edge {
+        source: "a".to_string(),
+        target: "b".to_string(),
+        relation: "knows".to_string(),
+        weight: 1.0,
+        partition_id: "personal".to_string(),
+        valid_from: None,
+        valid_to: None,
+        cross_partition: false,
+    };
+    store.add_edge(edge.clone()).await.unwrap();
+
+    // Repeated co-occurrences strengthen the one edge
+    for expected in [1.5, 2.0] {
+        assert_eq!(
+            store
+                .increment_edge_weight("a", "knows", "b", 0.5)
+                .await
+                .unwrap(),
+            expected
+        );
+    }
+    let neighbors = store.get_neighbors("a").await.unwrap();
+    assert_eq!(neighbors.len(), 1);
+    assert_eq!(neighbors[0].0.weight, 2.0);
+    assert!(matches!(
+        store.increment_edge_weight("b", "knows", "a", 0.5).await,
+        Err(GraphError::NotFound(_))
+    ));
+
+    let valid_to = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+    store
+        .update_edge(Edge {
+            weight: 0.3,
+            valid_to: Some(valid_to),
+            ..edge.clone()
+        })
+        .await
+        .unwrap();
+    let (updated, _) = store.get_neighbors("a").await.unwrap().remove(0);
+    assert_eq!(updated.weight, 0.3);
+    assert_eq!(updated.valid_to, Some(valid_to));
+
+    assert!(matches!(
+        store
+            .update_edge(Edge {
+                relation: "likes".to_string(),
+                ..edge
+            })
+            .await,
+        Err(GraphError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_surreal_merge_nodes() {
+    use facet_graph::merge::merge_nodes;
+
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_merge.db")).await.unwrap();
+
+    for (id, properties) in [
+        (
+            "alice",
+            json!({"name": "Alice Smith", "tags": {"role": "engineer"}}),
+        ),
+        (
+            "a_smith",
+            json!({"name": "A. Smith", "tags": {"team": "graph"}}),
+        ),
+        ("acme", json!({"name": "Acme"})),
+        ("bob", json!({"name": "Bob"})),
+    ] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: "Person".to_string(),
+                properties,
+                partition_id: "personal".to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    let edge = |source: &str, relation: &str, target: &str, weight: f32| Edge {
+        source: source.to_string(),
+        target: target.to_string(),
+        relation: relation.to_string(),
+        weight,
+        partition_id: "personal".to_string(),
+        valid_from: None,
+        valid_to: None,
+        cross_partition: false,
+    };
+    store
+        .add_edges(vec![
+            edge("alice", "works_at", "acme", 1.0),
+            edge("a_smith", "works_at", "acme", 2.0),
+            edge("bob", "knows", "a_smith", 1.0),
+            edge("a_smith", "same_as", "alice", 1.0),
+        ])
+        .await
+        .unwrap();
+    store
+        .add_embedding("alice", vec![1.0, 0.0], None)
+        .await
+        .unwrap();
+    store
+        .add_embedding("alice", vec![0.2, 0.4], Some("topic"))
+        .await
+        .unwrap();
+    store
+        .add_embedding("a_smith", vec![0.0, 1.0], None)
+        .await
+        .unwrap();
+
+    let merged = merge_nodes(&store, "alice", "a_smith").await.unwrap();
+    assert_eq!(
+        merged.properties,
+        json!({"name": "Alice Smith", "tags": {"role": "engineer", "team": "graph"}})
+    );
+    assert!(matches!(
+        store.get_node("a_smith").await,
+        Err(GraphError::NotFound(_))
+    ));
+
+    let outgoing = store.get_neighbors("alice").await.unwrap();
+    assert_eq!(outgoing.len(), 1);
+    assert_eq!(outgoing[0].1.id, "acme");
+    assert_eq!(outgoing[0].0.weight, 3.0);
+    let incoming = store.get_incoming_neighbors("alice").await.unwrap();
+    assert_eq!(incoming.len(), 1);
+    assert_eq!(incoming[0].1.id, "bob");
+
+    let embeddings = store.get_embeddings("alice").await.unwrap();
+    assert_eq!(embeddings.len(), 2);
+    assert_eq!(embeddings[0].vector, [0.5, 0.5]);
+    assert_eq!(embeddings[1].field.as_deref(), Some("topic"));
+    assert_eq!(embeddings[1].vector, [0.2, 0.4]);
+    assert!(store.get_embeddings("a_smith").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_surreal_batch_insert() {
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_batch.db")).await.unwrap();
+
+    let nodes: Vec<Node> = (0..50)
+        .map(|i| Node {
+            id: format!("n{}", i),
+            label: "Chunk".to_string(),
+            properties: json!({"index": i}),
+            partition_id: "work".to_string(),
+        })
+        .collect();
+    store.add_nodes(nodes).await.unwrap();
+    assert_eq!(store.query_by_partition("work").await.unwrap().len(), 50);
+
+    let edges: Vec<Edge> = (1..50)
+        .map(|i| Edge {
+            source: "n0".to_string(),
+            target: format!("n{}", i),
+            relation: if i % 2 == 0 { "next" } else { "mentions" }.to_string(),
+            weight: 0.5,
+            partition_id: "work".to_string(),
+            valid_from: None,
+            valid_to: None,
+            cross_partition: false,
+        })
+        .collect();
+    store.add_edges(edges).await.unwrap();
+
+    let neighbors = store.get_neighbors("n0").await.unwrap();
+    assert_eq!(neighbors.len(), 49);
+    assert!(neighbors.iter().any(|(e, _)| e.relation == "next"));
+    assert!(neighbors.iter().any(|(e, _)| e.relation == "mentions"));
+
+    // A duplicate id fails the whole batch
+    let batch = vec![
+        Node {
+            id: "fresh".to_string(),
+            label: "Chunk".to_string(),
+            properties: json!({}),
+            partition_id: "work".to_string(),
+        },
+        Node {
+            id: "n1".to_string(),
+            label: "Chunk".to_string(),
+            properties: json!({}),
+            partition_id: "work".to_string(),
+        },
+    ];
+    assert!(store.add_nodes(batch).await.is_err());
+    assert!(store.get_node("fresh").await.is_err());
+}
+
+#[tokio::test]
+async fn test_surreal_transaction() {
+    use facet_graph::transaction::GraphTransaction;
+
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_tx.db")).await.unwrap();
+
+    let node = |id: &str| Node {
+        id: id.to_string(),
+        label: "Person".to_string(),
+        properties: json!({"name": id}),
+        partition_id: "personal".to_string(),
+    };
+    let edge = |source: &str, target: &str| Edge {
+        source: source.to_string(),
+        target: target.to_string(),
+        relation: "knows".to_string(),
+        weight: 0.5,
+        partition_id: "personal".to_string(),
+        valid_from: None,
+        valid_to: None,
+        cross_partition: false,
+    };
+
+    // Node plus several edges in one atomic step
+    let mut tx = GraphTransaction::new();
+    tx.add_node(node("a"))
+        .add_node(node("b"))
+        .add_node(node("c"))
+        .add_edge(edge("a", "b"))
+        .add_edge(edge("a", "c"));
+    store.commit_transaction(tx).await.unwrap();
+    assert_eq!(store.get_neighbors("a").await.unwrap().len(), 2);
+
+    // A failing op rolls back everything queued before it
+    let mut tx = GraphTransaction::new();
+    tx.add_node(node("d"))
+        .add_edge(edge("a", "d"))
+        .update_node(node("missing"));
+    assert!(store.commit_transaction(tx).await.is_err());
+    assert!(store.get_node("d").await.is_err());
+    assert_eq!(store.get_neighbors("a").await.unwrap().len(), 2);
+
+    // Non-cascading delete of a connected node fails and leaves it in place
+    let mut tx = GraphTransaction::new();
+    tx.delete_node("b", false);
+    assert!(store.commit_transaction(tx).await.is_err());
+    assert!(store.get_node("b").await.is_ok());
+
+    // Edge and node deletes
+    let mut tx = GraphTransaction::new();
+    tx.delete_edge("a", "knows", "c").delete_node("b", true);
+    store.commit_transaction(tx).await.unwrap();
+    assert!(store.get_node("b").await.is_err());
+    assert!(store.get_neighbors("a").await.unwrap().is_empty());
+
+    // Deleting an edge that doesn't exist aborts the transaction
+    let mut tx = GraphTransaction::new();
+    tx.add_node(node("e")).delete_edge("a", "knows", "c");
+    assert!(store.commit_transaction(tx).await.is_err());
+    assert!(store.get_node("e").await.is_err());
+}
+
+#[tokio::test]
+async fn test_surreal_add_conflict_and_upsert() {
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_upsert.db")).await.unwrap();
+
+    let node = Node {
+        id: "p1".to_string(),
+        label: "Person".to_string(),
+        properties: json!({"name": "Alice", "meta": {"source": "chat"}}),
+        partition_id: "personal".to_string(),
+    };
+    store.add_node(node.clone()).await.unwrap();
+
+    // Adding the same id twice is a conflict, not an overwrite
+    assert!(matches!(
+        store.add_node(node.clone()).await,
+        Err(GraphError::Conflict(_))
+    ));
+    assert!(matches!(
+        store.add_nodes(vec![node.clone()]).await,
+        Err(GraphError::Conflict(_))
+    ));
+
+    store
+        .upsert_node(Node {
+            label: "Employee".to_string(),
+            properties: json!({"email": "a@example.com", "meta": {"seen": 2}}),
+            ..node.clone()
+        })
+        .await
+        .unwrap();
+
+    let merged = store.get_node("p1").await.unwrap();
+    assert_eq!(merged.label, "Employee");
+    assert_eq!(
+        merged.properties,
+        json!({
+            "name": "Alice",
+            "email": "a@example.com",
+            "meta": {"source": "chat", "seen": 2}
+        })
+    );
+
+    // Upsert of an unknown id creates the node
+    store
+        .upsert_node(Node {
+            id: "p2".to_string(),
+            ..node.clone()
+        })
+        .await
+        .unwrap();
+    assert_eq!(store.get_node("p2").await.unwrap().properties["name"], "Alice");
+
+    // Generated ids: fresh ones never collide, keyed ones are stable
+    let first = store.add_node_auto(node.clone()).await.unwrap();
+    let second = store.add_node_auto(node.clone()).await.unwrap();
+    assert_ne!(first, second);
+    assert!(store.node_exists(&first).await.unwrap());
+    let keyed = store
+        .upsert_node_by_key(node.clone(), &["name"])
+        .await
+        .unwrap();
+    assert_eq!(
+        store.upsert_node_by_key(node, &["name"]).await.unwrap(),
+        keyed
+    );
+    assert_eq!(store.count_nodes(None).await.unwrap(), 5);
+}
+
+#[tokio::test]
+async fn test_surreal_traverse() {
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_traverse.db")).await.unwrap();
+
+    let node = |id: &str| Node {
+        id: id.to_string(),
+        label: "Person".to_string(),
+        properties: json!({}),
+        partition_id: "personal".to_string(),
+    };
+    let edge = |source: &str, relation: &str, target: &str| Edge {
+        source: source.to_string(),
+        target: target.to_string(),
+        relation: relation.to_string(),
+        weight: 1.0,
+        partition_id: "personal".to_string(),
+        valid_from: None,
+        valid_to: None,
+        cross_partition: false,
+    };
+
+    store
+        .add_nodes(["a", "b", "c", "d", "x"].iter().map(|id| node(id)).collect())
+        .await
+        .unwrap();
+    store
+        .add_edges(vec![
+            edge("a", "knows", "b"),
+            edge("b", "works_at", "c"),
+            edge("c", "knows", "d"),
+            edge("x", "knows", "a"),
+        ])
+        .await
+        .unwrap();
+
+    let far = |hits: Vec<(Edge, Node)>| hits.into_iter().map(|(_, n)| n.id).collect::<Vec<_>>();
+
+    let hits = store.traverse("a", 1, Direction::Outgoing, None).await.unwrap();
+    assert_eq!(far(hits), vec!["b"]);
+
+    let hits = store.traverse("a", 3, Direction::Outgoing, None).await.unwrap();
+    assert_eq!(hits[2].0.relation, "knows");
+    assert_eq!(hits[2].0.source, "c");
+    assert_eq!(far(hits), vec!["b", "c", "d"]);
+
+    // The filter stops expansion at the works_at edge
+    let hits = store
+        .traverse("a", 3, Direction::Outgoing, Some(&["knows"]))
+        .await
+        .unwrap();
+    assert_eq!(far(hits), vec!["b"]);
+
+    let hits = store.traverse("b", 2, Direction::Incoming, None).await.unwrap();
+    assert_eq!(far(hits), vec!["a", "x"]);
+
+    let mut both = far(store.traverse("a", 1, Direction::Both, None).await.unwrap());
+    both.sort();
+    assert_eq!(both, vec!["b", "x"]);
+
+    assert!(matches!(
+        store.traverse("missing", 2, Direction::Outgoing, None).await,
+        Err(GraphError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_surreal_incoming_neighbors() {
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_incoming.db")).await.unwrap();
+
+    for (id, partition) in [("a", "personal"), ("b", "personal"), ("c", "work")] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: "Person".to_string(),
+                properties: json!({}),
+                partition_id: partition.to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    for (source, relation, target, partition) in [
+        ("a", "knows", "b", "personal"),
+        ("c", "manages", "b", "work"),
+        ("b", "knows", "a", "personal"),
+    ] {
+        store
+            .add_edge(Edge {
+                source: source.to_string(),
+                target: target.to_string(),
+                relation: relation.to_string(),
+                weight: 0.5,
+                partition_id: partition.to_string(),
+                valid_from: None,
+                valid_to: None,
+                cross_partition: false,
+            })
+            .await
+            .unwrap();
+    }
+
+    let mut incoming = store.get_incoming_neighbors("b").await.unwrap();
+    incoming.sort_by(|x, y| x.1.id.cmp(&y.1.id));
+    assert_eq!(incoming.len(), 2);
+    assert_eq!(incoming[0].1.id, "a");
+    assert_eq!(incoming[1].0.relation, "manages");
+    assert_eq!(incoming[1].0.source, "c");
+    assert_eq!(incoming[1].0.target, "b");
+    assert_eq!(incoming[1].0.weight, 0.5);
+
+    let both = store.get_neighbors_directed("b", Direction::Both).await.unwrap();
+    assert_eq!(both.len(), 3);
+
+    let personal = store
+        .get_neighbors_in_partition_directed("b", "personal", Direction::Incoming)
+        .await
+        .unwrap();
+    assert_eq!(personal.len(), 1);
+    assert_eq!(personal[0].1.id, "a");
+
+    assert!(store.get_incoming_neighbors("missing").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_surreal_shortest_path() {
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_path.db")).await.unwrap();
+
+    for id in ["alice", "bob", "acme", "carol"] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: "Entity".to_string(),
+                properties: json!({}),
+                partition_id: "work".to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    for (source, relation, target) in [
+        ("alice", "works_at", "acme"),
+        ("carol", "works_at", "acme"),
+        ("alice", "knows", "bob"),
+    ] {
+        store
+            .add_edge(Edge {
+                source: source.to_string(),
+                target: target.to_string(),
+                relation: relation.to_string(),
+                weight: 1.0,
+                partition_id: "work".to_string(),
+                valid_from: None,
+                valid_to: None,
+                cross_partition: false,
+            })
+            .await
+            .unwrap();
+    }
+
+    let path = store
+        .shortest_path("alice", "carol", 3, Some("work"))
+        .await
+        .unwrap()
+        .unwrap();
+    let ids: Vec<_> = path.nodes.iter().map(|n| n.id.as_str()).collect();
+    assert_eq!(ids, vec!["alice", "acme", "carol"]);
+    assert!(path.edges.iter().all(|e| e.relation == "works_at"));
+
+    assert!(store
+        .shortest_path("bob", "carol", 3, Some("personal"))
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_surreal_search_text() {
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_text.db")).await.unwrap();
+
+    let doc = |id: &str, properties: serde_json::Value| Node {
+        id: id.to_string(),
+        label: "Document".to_string(),
+        properties,
+        partition_id: "personal".to_string(),
+    };
+    store
+        .add_node(doc("d1", json!({"name": "Quarterly report", "content": "Revenue grew in Berlin"})))
+        .await
+        .unwrap();
+    store
+        .add_nodes(vec![
+            doc("d2", json!({"name": "Berlin office"})),
+            doc("d3", json!({"count": 3})),
+        ])
+        .await
+        .unwrap();
+
+    let hits = store.search_text("revenue BERLIN", 10).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].0.id, "d1");
+    assert_eq!(hits[0].0.properties["name"], "Quarterly report");
+
+    let hits = store.search_text("berlin", 10).await.unwrap();
+    assert_eq!(hits.len(), 2);
+    assert_eq!(store.search_text("berlin", 1).await.unwrap().len(), 1);
+
+    // Index follows updates and merges
+    store
+        .upsert_node(doc("d2", json!({"content": "Revenue forecast"})))
+        .await
+        .unwrap();
+    assert_eq!(store.search_text("berlin revenue", 10).await.unwrap().len(), 2);
+
+    assert!(store.search_text("munich", 10).await.unwrap().is_empty());
+
+    // Re-opening an existing database keeps the schema definitions valid
+    drop(store);
+    let store = SurrealStore::new(dir.path().join("test_text.db")).await.unwrap();
+    assert!(store.search_text("", 10).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_surreal_partition_paging() {
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_paging.db")).await.unwrap();
+
+    // Inserted one at a time so creation times differ
+    for (id, label) in [("n3", "Topic"), ("n1", "Person"), ("n2", "Event")] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: label.to_string(),
+                properties: json!({}),
+                partition_id: "work".to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    let ids = |nodes: Vec<Node>| nodes.into_iter().map(|n| n.id).collect::<Vec<_>>();
+
+    let page = store
+        .query_by_partition_page("work", PageRequest::new(0, 2))
+        .await
+        .unwrap();
+    assert_eq!(ids(page), vec!["n3", "n1"]);
+    let page = store
+        .query_by_partition_page("work", PageRequest::new(2, 2))
+        .await
+        .unwrap();
+    assert_eq!(ids(page), vec!["n2"]);
+
+    // Updates keep the original creation time
+    let mut first = store.get_node("n3").await.unwrap();
+    first.label = "Renamed".to_string();
+    store.update_node(first).await.unwrap();
+    let page = store
+        .query_by_partition_page("work", PageRequest::new(0, 1))
+        .await
+        .unwrap();
+    assert_eq!(ids(page), vec!["n3"]);
+
+    let by_label = store
+        .query_by_partition_page(
+            "work",
+            PageRequest::default().with_order(NodeOrder::Label, false),
+        )
+        .await
+        .unwrap();
+    assert_eq!(ids(by_label), vec!["n2", "n1", "n3"]);
+
+    let by_id_desc = store
+        .query_by_partition_page("work", PageRequest::default().with_order(NodeOrder::Id, true))
+        .await
+        .unwrap();
+    assert_eq!(ids(by_id_desc), vec!["n3", "n2", "n1"]);
+}
+
+#[tokio::test]
+async fn test_surreal_counts_and_existence() {
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_counts.db")).await.unwrap();
+
+    assert_eq!(store.count_nodes(None).await.unwrap(), 0);
+    assert_eq!(store.count_edges(None).await.unwrap(), 0);
+
+    for (id, partition) in [("a", "personal"), ("b", "personal"), ("w", "work")] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: "Person".to_string(),
+                properties: json!({}),
+                partition_id: partition.to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    for (source, relation, target) in [
+        ("a", "knows", "b"),
+        ("b", "knows", "a"),
+        ("w", "works with", "a"),
+    ] {
+        store
+            .add_edge(Edge {
+                source: source.to_string(),
+                target: target.to_string(),
+                relation: relation.to_string(),
+                weight: 1.0,
+                partition_id: "personal".to_string(),
+                valid_from: None,
+                valid_to: None,
+                cross_partition: false,
+            })
+            .await
+            .unwrap();
+    }
+
+    assert!(store.node_exists("a").await.unwrap());
+    assert!(!store.node_exists("missing").await.unwrap());
+    assert_eq!(store.count_nodes(None).await.unwrap(), 3);
+    assert_eq!(store.count_nodes(Some("personal")).await.unwrap(), 2);
+    assert_eq!(store.count_nodes(Some("empty")).await.unwrap(), 0);
+    assert_eq!(store.count_edges(None).await.unwrap(), 3);
+    assert_eq!(store.count_edges(Some("knows")).await.unwrap(), 2);
+    assert_eq!(store.count_edges(Some("works with")).await.unwrap(), 1);
+    assert_eq!(store.count_edges(Some("hates")).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_surreal_query_by_label() {
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_label.db")).await.unwrap();
+
+    for (id, label, partition) in [
+        ("p2", "Person", "work"),
+        ("p1", "Person", "work"),
+        ("t1", "Topic", "work"),
+        ("p3", "Person", "personal"),
+    ] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: label.to_string(),
+                properties: json!({}),
+                partition_id: partition.to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    let ids = |nodes: Vec<Node>| nodes.into_iter().map(|n| n.id).collect::<Vec<_>>();
+
+    let work = store
+        .query_by_label("Person", Some("work"), 10)
+        .await
+        .unwrap();
+    assert_eq!(ids(work), vec!["p1", "p2"]);
+    let all = store.query_by_label("Person", None, 10).await.unwrap();
+    assert_eq!(ids(all), vec!["p1", "p2", "p3"]);
+    let first = store.query_by_label("Person", None, 1).await.unwrap();
+    assert_eq!(ids(first), vec!["p1"]);
+    assert!(store
+        .query_by_label("Event", Some("work"), 10)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_surreal_snapshot_export() {
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_export.db")).await.unwrap();
+
+    for (id, name, partition) in [
+        ("alice", "Alice", "personal"),
+        ("bob", "Bob", "personal"),
+        ("acme", "Acme", "work"),
+    ] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: "Entity".to_string(),
+                properties: json!({"name": name}),
+                partition_id: partition.to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    for (source, relation, target, partition) in [
+        ("alice", "knows", "bob", "personal"),
+        ("alice", "works_at", "acme", "work"),
+    ] {
+        store
+            .add_edge(Edge {
+                source: source.to_string(),
+                target: target.to_string(),
+                relation: relation.to_string(),
+                weight: 1.0,
+                partition_id: partition.to_string(),
+                valid_from: None,
+                valid_to: None,
+                cross_partition: false,
+            })
+            .await
+            .unwrap();
+    }
+
+    let full = store.snapshot(None).await.unwrap();
+    assert_eq!(full.nodes.len(), 3);
+    assert_eq!(full.edges.len(), 2);
+
+    let personal = store.snapshot(Some("personal")).await.unwrap();
+    let ids: Vec<_> = personal.nodes.iter().map(|n| n.id.as_str()).collect();
+    assert_eq!(ids, vec!["alice", "bob"]);
+    assert_eq!(personal.edges.len(), 1);
+    assert_eq!(personal.edges[0].relation, "knows");
+
+    let dot = ExportFormat::Dot.render(&personal);
+    assert!(dot.contains("\"alice\" -> \"bob\" [label=\"knows\""));
+    let graphml = ExportFormat::GraphMl.render(&full);
+    assert_eq!(graphml.matches("<edge ").count(), 2);
+}
+
+#[tokio::test]
+async fn test_surreal_stream_partition() {
+    use futures::TryStreamExt;
+
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_stream.db")).await.unwrap();
+
+    let nodes: Vec<Node> = (0..120)
+        .map(|i| Node {
+            id: format!("n{:03}", i),
+            label: "Chunk".to_string(),
+            properties: json!({"index": i}),
+            partition_id: if i % 4 == 0 { "personal" } else { "work" }.to_string(),
+        })
+        .collect();
+    store.add_nodes(nodes).await.unwrap();
+
+    let work: Vec<Node> = facet_graph::stream::partition_nodes(&store, "work", 25)
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(work.len(), 90);
+    assert!(work.windows(2).all(|w| w[0].id < w[1].id));
+
+    let all: Vec<Node> = facet_graph::stream::all_nodes(&store, 25)
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(all.len(), 120);
+}
+
+#[tokio::test]
+async fn test_surreal_special_character_ids() {
+    use facet_graph::transaction::GraphTransaction;
+
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_ids.db")).await.unwrap();
+
+    let ids = [
+        "a-b",
+        "with space",
+        "42",
+        "quote'\"`",
+        "node:other",
+        "⟨angle⟩",
+        "x; DELETE node; --",
+    ];
+    let node = |id: &str| Node {
+        id: id.to_string(),
+        label: "Entity".to_string(),
+        properties: json!({"name": id}),
+        partition_id: "personal".to_string(),
+    };
+    let edge = |source: &str, relation: &str, target: &str| Edge {
+        source: source.to_string(),
+        target: target.to_string(),
+        relation: relation.to_string(),
+        weight: 1.0,
+        partition_id: "personal".to_string(),
+        valid_from: None,
+        valid_to: None,
+        cross_partition: false,
+    };
+
+    store.add_node(node("hub")).await.unwrap();
+    for id in ids {
+        store.add_node(node(id)).await.unwrap();
+        assert_eq!(store.get_node(id).await.unwrap().id, id);
+        store.add_edge(edge("hub", "links", id)).await.unwrap();
+        store.add_edge(edge(id, "select", "hub")).await.unwrap();
+        store.add_embedding(id, vec![1.0, 0.0], None).await.unwrap();
+    }
+
+    // Nothing was injected: every node is still there, under its own id
+    let mut stored: Vec<String> = store
+        .query_by_partition("personal")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|n| n.id)
+        .collect();
+    stored.sort();
+    let mut expected: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+    expected.push("hub".to_string());
+    expected.sort();
+    assert_eq!(stored, expected);
+
+    let mut targets: Vec<String> = store
+        .get_neighbors("hub")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(e, n)| {
+            assert_eq!(e.target, n.id);
+            n.id
+        })
+        .collect();
+    targets.sort();
+    expected.retain(|id| id != "hub");
+    assert_eq!(targets, expected);
+
+    for id in ids {
+        let incoming = store.get_incoming_neighbors(id).await.unwrap();
+        assert_eq!(incoming.len(), 1);
+        assert_eq!(incoming[0].0.source, "hub");
+        let outgoing = store.get_neighbors(id).await.unwrap();
+        assert_eq!(outgoing.len(), 1);
+        assert_eq!(outgoing[0].0.relation, "select");
+        let reached = store
+            .traverse(id, 2, Direction::Outgoing, Some(&["select"]))
+            .await
+            .unwrap();
+        assert_eq!(reached.len(), 1);
+    }
+
+    let hits = store.search(vec![1.0, 0.0], 20, None).await.unwrap();
+    assert_eq!(hits.len(), ids.len());
+    assert!(hits.iter().any(|(id, _)| id == "x; DELETE node; --"));
+
+    store.delete_edge("hub", "links", "a-b").await.unwrap();
+    let mut tx = GraphTransaction::new();
+    tx.delete_edge("quote'\"`", "select", "hub");
+    tx.delete_node("quote'\"`", true);
+    tx.add_edge(edge("42", "links", "⟨angle⟩"));
+    store.commit_transaction(tx).await.unwrap();
+    assert!(store.get_node("quote'\"`").await.is_err());
+    assert_eq!(store.get_neighbors("42").await.unwrap().len(), 2);
+    assert_eq!(
+        store.get_neighbors("hub").await.unwrap().len(),
+        ids.len() - 2
+    );
+
+    // Any relation name is accepted, except an empty one
+    assert!(store.add_edge(edge("hub", "", "42")).await.is_err());
+}
+
+#[tokio::test]
+async fn test_surreal_relation_names() {
+    use facet_graph::transaction::GraphTransaction;
+
+    let dir = tempdir().unwrap();
+    let store = SurrealStore::new(dir.path().join("test_relations.db")).await.unwrap();
+
+    for id in ["alice", "acme"] {
+        store
+            .add_node(Node {
+                id: id.to_string(),
+                label: "Entity".to_string(),
+                properties: json!({}),
+                partition_id: "personal".to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    let edge = |relation: &str| Edge {
+        source: "alice".to_string(),
+        target: "acme".to_string(),
+        relation: relation.to_string(),
+        weight: 1.0,
+        partition_id: "personal".to_string(),
+        valid_from: None,
+        valid_to: None,
+        cross_partition: false,
+    };
+
+    let relations = [
+        "works at",
+        "co-founder of",
+        "links`x; DELETE node; --",
+        "__escaped",
+        "works_at",
+        "arbeitet für",
+    ];
+    for relation in &relations[..4] {
+        store.add_edge(edge(relation)).await.unwrap();
+    }
+    store.add_edges(vec![edge("works_at")]).await.unwrap();
+    let mut tx = GraphTransaction::new();
+    tx.add_edge(edge("arbeitet für"));
+    store.commit_transaction(tx).await.unwrap();
+
+    // Names come back as written, and similar names stay apart
+    let mut stored: Vec<String> = store
+        .get_neighbors("alice")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(e, _)| e.relation)
+        .collect();
+    stored.sort();
+    let mut expected: Vec<String> = relations.iter().map(|r| r.to_string()).collect();
+    expected.sort();
+    assert_eq!(stored, expected);
+    let mut incoming: Vec<String> = store
+        .get_incoming_neighbors("acme")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(e, _)| e.relation)
+        .collect();
+    incoming.sort();
+    assert_eq!(incoming, expected);
+
+    let reached = store
+        .traverse("alice", 1, Direction::Outgoing, Some(&["works at"]))
+        .await
+        .unwrap();
+    assert_eq!(reached.len(), 1);
+    assert_eq!(reached[0].0.relation, "works at");
+
+    let weight = store
+        .increment_edge_weight("alice", "works at", "acme", 0.5)
+        .await
+        .unwrap();
+    assert_eq!(weight, 1.5);
+    store
+        .delete_edge("alice", "co-founder of", "acme")
+        .await
+        .unwrap();
+    let snapshot = store.snapshot(None).await.unwrap();
+    assert_eq!(snapshot.edges.len(), relations.len() - 1);
+    assert!(snapshot
+        .edges
+        .iter()
+        .any(|e| e.relation == "works at" && e.weight == 1.5));
+    assert!(store.get_node("alice").await.is_ok());
 }
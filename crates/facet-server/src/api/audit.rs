@@ -0,0 +1,143 @@
+//! Audit log endpoints
+//!
+//! Who ran which prompt, when, with which tools and how it ended, as kept
+//! by the audit log, and a check of its hash chain.
+
+use crate::api::sessions::error_to_response;
+use crate::audit::{AuditEntry, AuditLog, AuditQuery};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use warp::{http::StatusCode, reply, Reply};
+
+/// Entries matching a query
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditResponse {
+    /// Matching entries, oldest first
+    pub entries: Vec<AuditEntry>,
+}
+
+/// GET /api/v1/audit handler
+///
+/// # Arguments
+/// * `query` - Filter from the query string: session_id, actor, profile,
+///   outcome, since and limit
+/// * `log` - Audit log
+///
+/// # Returns
+/// JSON list of the latest matching entries, or an error response
+///
+/// # Example Response
+/// ```json
+/// {
+///   "entries": [
+///     {
+///       "seq": 42,
+///       "timestamp": "2025-10-17T10:31:12Z",
+///       "session_id": "550e8400-e29b-41d4-a716-446655440000",
+///       "actor": "api_key:0a1b2c3d4e5f",
+///       "profile": "work",
+///       "prompt": "Summarize this page",
+///       "tools": ["cdp_command"],
+///       "outcome": "completed",
+///       "started_at": "2025-10-17T10:30:00Z",
+///       "usage": { ... },
+///       "prev_hash": "9f86d0...",
+///       "hash": "e3b0c4..."
+///     }
+///   ]
+/// }
+/// ```
+pub async fn audit_handler(
+    query: AuditQuery,
+    log: Arc<AuditLog>,
+) -> Result<impl Reply, warp::Rejection> {
+    match log.query(&query) {
+        Ok(entries) => Ok(reply::with_status(
+            reply::json(&AuditResponse { entries }),
+            StatusCode::OK,
+        )),
+        Err(e) => {
+            let (status, error_response) = error_to_response(e, None);
+            Ok(reply::with_status(reply::json(&error_response), status))
+        }
+    }
+}
+
+/// GET /api/v1/audit/verify handler
+///
+/// # Arguments
+/// * `log` - Audit log
+///
+/// # Returns
+/// JSON count of the entries checked and the first whose hash doesn't
+/// check out, if any, or an error response
+///
+/// # Example Response
+/// ```json
+/// {
+///   "entries": 1280,
+///   "broken_at": 977
+/// }
+/// ```
+pub async fn audit_verify_handler(log: Arc<AuditLog>) -> Result<impl Reply, warp::Rejection> {
+    match log.verify() {
+        Ok(verification) => Ok(reply::with_status(
+            reply::json(&verification),
+            StatusCode::OK,
+        )),
+        Err(e) => {
+            let (status, error_response) = error_to_response(e, None);
+            Ok(reply::with_status(reply::json(&error_response), status))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::ChainVerification;
+    use crate::models::SessionState;
+    use uuid::Uuid;
+
+    async fn body<T: serde::de::DeserializeOwned>(reply: impl Reply) -> T {
+        let body = warp::hyper::body::to_bytes(reply.into_response().into_body())
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_audit_handlers() {
+        let log = Arc::new(AuditLog::in_memory().with_hash_chain(true));
+        for profile in ["work", "shopping"] {
+            log.append(AuditEntry {
+                seq: 0,
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                session_id: Uuid::new_v4(),
+                actor: "token:0123456789ab".to_string(),
+                profile: Some(profile.to_string()),
+                prompt: "Summarize this page".to_string(),
+                tools: vec![],
+                outcome: SessionState::Completed,
+                error: None,
+                started_at: chrono::Utc::now().to_rfc3339(),
+                usage: None,
+                prev_hash: None,
+                hash: None,
+            })
+            .unwrap();
+        }
+
+        let query = AuditQuery {
+            profile: Some("shopping".to_string()),
+            ..Default::default()
+        };
+        let response: AuditResponse = body(audit_handler(query, log.clone()).await.unwrap()).await;
+        assert_eq!(response.entries.len(), 1);
+        assert_eq!(response.entries[0].seq, 2);
+
+        let verification: ChainVerification = body(audit_verify_handler(log).await.unwrap()).await;
+        assert_eq!(verification.entries, 2);
+        assert_eq!(verification.broken_at, None);
+    }
+}
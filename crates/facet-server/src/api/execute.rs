@@ -1,11 +1,15 @@
 //! Execute endpoint for running Claude CLI requests
 //!
-//! Handles POST /api/v1/execute with streaming SSE responses.
+//! Handles POST /api/v1/execute with streaming SSE responses. A request
+//! stopping at a `redaction_preview` event resumes once the client replies
+//! with POST /api/v1/requests/:id/redaction.
 
+use crate::auth::Caller;
 use crate::claude::Executor;
 use crate::config::Config;
 use crate::error::FacetError;
 use crate::models::{ClaudeEvent, FacetRequest};
+use crate::redaction;
 use crate::session::SessionManager;
 use futures::StreamExt;
 use std::convert::Infallible;
@@ -18,6 +22,7 @@ use warp::Reply;
 ///
 /// # Arguments
 /// * `request` - Validated Facet request
+/// * `caller` - Who the request comes from
 /// * `executor` - Claude executor (real or mock)
 /// * `session_manager` - Session tracking
 /// * `config` - Server configuration for validation limits
@@ -26,6 +31,7 @@ use warp::Reply;
 /// Server-Sent Events stream of Claude events
 pub async fn execute_handler(
     request: FacetRequest,
+    caller: Caller,
     executor: Arc<dyn Executor>,
     session_manager: Arc<SessionManager>,
     config: Arc<Config>,
@@ -45,14 +51,15 @@ pub async fn execute_handler(
 
     // Register session
     if let Err(e) = session_manager
-        .register(session_id, config.claude.max_concurrent_sessions)
+        .register_for(&request, caller, config.claude.max_concurrent_sessions)
         .await
     {
         return Err(warp::reject::custom(crate::auth::AuthRejection(e)));
     }
 
-    // Execute request and get event stream
-    let mut event_stream = executor.execute(request).await;
+    // Execute request, redacted if its profile asks, and get event stream
+    let mut event_stream =
+        redaction::execute(request, None, executor, session_manager.clone()).await;
 
     // Convert to SSE stream
     let session_manager_clone = session_manager.clone();
@@ -63,14 +70,23 @@ pub async fn execute_handler(
                     // Check if this is a terminal event
                     let is_complete = matches!(event, ClaudeEvent::Complete { .. });
                     let is_error = matches!(event, ClaudeEvent::Error { .. });
+                    let is_cancelled = matches!(event, ClaudeEvent::Cancelled { .. });
 
-                    // Update session status
+                    // Update session usage and status
+                    let _ = session_manager_clone.observe_event(session_id, &event).await;
                     if is_complete {
                         let _ = session_manager_clone.complete(session_id).await;
+                    } else if is_cancelled {
+                        // Already cancelled, unless the executor was told directly
+                        let _ = session_manager_clone.cancel(session_id).await;
                     } else if is_error {
                         if let ClaudeEvent::Error { message, .. } = &event {
                             let _ = session_manager_clone.fail(session_id, message.clone()).await;
                         }
+                    } else if let ClaudeEvent::TimedOut { timeout, after_seconds, .. } = &event {
+                        let message =
+                            format!("Timed out after {}s ({} timeout)", after_seconds, timeout);
+                        let _ = session_manager_clone.fail(session_id, message).await;
                     }
 
                     // Convert event to SSE format
@@ -144,7 +160,14 @@ mod tests {
         let request = create_test_request();
         let session_id = request.session_id;
 
-        let result = execute_handler(request, executor, session_manager.clone(), config).await;
+        let result = execute_handler(
+            request,
+            Caller::default(),
+            executor,
+            session_manager.clone(),
+            config,
+        )
+        .await;
         assert!(result.is_ok());
 
         // Give time for async processing
@@ -165,7 +188,14 @@ mod tests {
         // Make prompt too long
         request.prompt = "a".repeat(100000);
 
-        let result = execute_handler(request, executor, session_manager, config).await;
+        let result = execute_handler(
+            request,
+            Caller::default(),
+            executor,
+            session_manager,
+            config,
+        )
+        .await;
         assert!(result.is_err());
     }
 
@@ -186,7 +216,14 @@ mod tests {
 
         // Next request should fail
         let request = create_test_request();
-        let result = execute_handler(request, executor, session_manager, config).await;
+        let result = execute_handler(
+            request,
+            Caller::default(),
+            executor,
+            session_manager,
+            config,
+        )
+        .await;
         assert!(result.is_err());
     }
 }
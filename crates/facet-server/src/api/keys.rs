@@ -0,0 +1,143 @@
+//! API key management endpoints
+//!
+//! Create, list and revoke the keys other apps authenticate with. These
+//! endpoints require authentication themselves, so the first key is made
+//! with `facet keys create` or authorized by a configured token.
+
+use crate::api::sessions::error_to_response;
+use crate::api_keys::ApiKeyStore;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use warp::{http::StatusCode, reply, Reply};
+
+/// Body of POST /api/v1/keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateApiKeyRequest {
+    /// Name to list the key under, e.g. the app using it
+    pub name: String,
+}
+
+/// POST /api/v1/keys handler
+///
+/// # Arguments
+/// * `request` - Name of the new key
+/// * `store` - API key store
+///
+/// # Returns
+/// 201 with the key, which is not shown again, or an error response
+///
+/// # Example Response
+/// ```json
+/// {
+///   "id": "0a1b2c3d4e5f",
+///   "name": "editor plugin",
+///   "created_at": "2025-10-17T10:30:00Z",
+///   "key": "fk_0a1b2c3d4e5f_..."
+/// }
+/// ```
+pub async fn create_api_key_handler(
+    request: CreateApiKeyRequest,
+    store: Arc<ApiKeyStore>,
+) -> Result<impl Reply, warp::Rejection> {
+    match store.create(&request.name) {
+        Ok(created) => Ok(reply::with_status(
+            reply::json(&created),
+            StatusCode::CREATED,
+        )),
+        Err(e) => {
+            let (status, error_response) = error_to_response(e, None);
+            Ok(reply::with_status(reply::json(&error_response), status))
+        }
+    }
+}
+
+/// GET /api/v1/keys handler
+///
+/// # Arguments
+/// * `store` - API key store
+///
+/// # Returns
+/// JSON list of keys without their secrets, revoked ones included
+pub async fn list_api_keys_handler(store: Arc<ApiKeyStore>) -> Result<impl Reply, warp::Rejection> {
+    match store.list() {
+        Ok(keys) => Ok(reply::with_status(reply::json(&keys), StatusCode::OK)),
+        Err(e) => {
+            let (status, error_response) = error_to_response(e, None);
+            Ok(reply::with_status(reply::json(&error_response), status))
+        }
+    }
+}
+
+/// DELETE /api/v1/keys/:id handler
+///
+/// # Arguments
+/// * `id` - Id of the key to revoke
+/// * `store` - API key store
+///
+/// # Returns
+/// JSON details of the revoked key, or 404 if there is no such key
+pub async fn revoke_api_key_handler(
+    id: String,
+    store: Arc<ApiKeyStore>,
+) -> Result<impl Reply, warp::Rejection> {
+    match store.revoke(&id) {
+        Ok(info) => Ok(reply::with_status(reply::json(&info), StatusCode::OK)),
+        Err(e) => {
+            let (status, error_response) = error_to_response(e, None);
+            Ok(reply::with_status(reply::json(&error_response), status))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_keys::ApiKeyInfo;
+
+    async fn body(reply: impl Reply) -> (StatusCode, serde_json::Value) {
+        let response = reply.into_response();
+        let status = response.status();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_create_list_revoke() {
+        let store = Arc::new(ApiKeyStore::in_memory());
+
+        let request = CreateApiKeyRequest {
+            name: "editor plugin".to_string(),
+        };
+        let (status, created) = body(
+            create_api_key_handler(request, store.clone())
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let id = created["id"].as_str().unwrap().to_string();
+        assert!(store.verify(created["key"].as_str().unwrap()).is_some());
+
+        let (status, keys) = body(list_api_keys_handler(store.clone()).await.unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        let keys: Vec<ApiKeyInfo> = serde_json::from_value(keys).unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].id, id);
+
+        let (status, revoked) =
+            body(revoke_api_key_handler(id, store.clone()).await.unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(revoked["revoked_at"].is_string());
+
+        let (status, error) = body(
+            revoke_api_key_handler("missing".to_string(), store)
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error["code"], "API_KEY_NOT_FOUND");
+    }
+}
@@ -0,0 +1,54 @@
+//! Metrics endpoints
+//!
+//! Counters the server keeps for operators, starting with how many
+//! requests the rate limiter let through and throttled.
+
+use crate::auth::AuthState;
+use std::sync::Arc;
+use warp::{reply, Reply};
+
+/// GET /api/v1/metrics/rate-limits handler
+///
+/// # Arguments
+/// * `auth_state` - Authentication state holding the rate limiter
+///
+/// # Returns
+/// JSON rate limit metrics
+///
+/// # Example Response
+/// ```json
+/// {
+///   "allowed": 1520,
+///   "throttled": 12,
+///   "throttled_by_profile": {"work": 9}
+/// }
+/// ```
+pub async fn rate_limit_metrics_handler(
+    auth_state: Arc<AuthState>,
+) -> Result<impl Reply, warp::Rejection> {
+    Ok(reply::json(&auth_state.rate_limit_metrics().await))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rate_limit_metrics_handler() {
+        let auth_state = Arc::new(AuthState::new(vec![], false, 1));
+        auth_state.throttle("token", Some("work")).await.unwrap();
+        assert!(auth_state.throttle("token", Some("work")).await.is_err());
+
+        let response = rate_limit_metrics_handler(auth_state)
+            .await
+            .unwrap()
+            .into_response();
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let metrics: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(metrics["allowed"], 1);
+        assert_eq!(metrics["throttled"], 1);
+        assert_eq!(metrics["throttled_by_profile"]["work"], 1);
+    }
+}
@@ -2,12 +2,27 @@
 //!
 //! This module contains all HTTP endpoint handlers and route definitions.
 
+pub mod audit;
 pub mod execute;
 pub mod health;
 pub mod inference;
+pub mod keys;
+pub mod metrics;
+pub mod requests;
 pub mod sessions;
+pub mod usage;
+pub mod ws;
 
+pub use audit::{audit_handler, audit_verify_handler};
 pub use execute::execute_handler;
 pub use health::health_handler;
 pub use inference::inference_handler;
-pub use sessions::{delete_session_handler, get_session_handler};
+pub use keys::{create_api_key_handler, list_api_keys_handler, revoke_api_key_handler};
+pub use metrics::rate_limit_metrics_handler;
+pub use requests::{
+    cancel_request_handler, get_request_handler, redaction_reply_handler, request_events_handler,
+    submit_request_handler,
+};
+pub use sessions::{delete_session_handler, get_session_handler, get_session_history_handler};
+pub use usage::{profile_usage_handler, usage_handler};
+pub use ws::ws_handler;
@@ -0,0 +1,791 @@
+//! Request endpoints for submitting work and polling for its result
+//!
+//! Unlike POST /api/v1/execute, which streams events over SSE for as long
+//! as the request runs, POST /api/v1/requests returns as soon as the
+//! request is accepted. The executor runs in the background and the client
+//! polls GET /api/v1/requests/:id for its status and output, or follows
+//! GET /api/v1/requests/:id/events, which replays the events so far as SSE
+//! and then streams the rest as they come. DELETE /api/v1/requests/:id
+//! cancels a request, killing its claude-cli process, and
+//! POST /api/v1/requests/:id/redaction confirms or denies the redaction
+//! preview a request is waiting on. A request is only visible to the caller
+//! who submitted it; anyone else is told it doesn't exist.
+
+use crate::api::sessions::error_to_response;
+use crate::auth::Caller;
+use crate::claude::Executor;
+use crate::config::Config;
+use crate::error::FacetError;
+use crate::models::{ClaudeEvent, FacetRequest, SessionState, SessionStatus};
+use crate::redaction;
+use crate::session::SessionManager;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+use warp::{http::StatusCode, reply, Reply};
+
+/// Interval of SSE keep-alive comments, so proxies keep idle streams open
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Client's reply to a redaction preview
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RedactionReply {
+    /// Whether the redacted request may be sent
+    pub approved: bool,
+}
+
+/// Status of a submitted request with the output it has produced so far
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RequestStatus {
+    /// Session status of the request
+    #[serde(flatten)]
+    pub session: SessionStatus,
+
+    /// Text content streamed by the executor so far
+    pub output: String,
+}
+
+/// POST /api/v1/requests handler
+///
+/// Validates and registers a Facet request, then runs it in the background.
+///
+/// # Arguments
+/// * `request` - Facet request to run
+/// * `caller` - Who the request comes from
+/// * `executor` - Claude executor (real or mock)
+/// * `session_manager` - Session tracking
+/// * `config` - Server configuration for validation limits
+///
+/// # Returns
+/// 202 Accepted with the request status, or the error's status code with
+/// an error response
+pub async fn submit_request_handler(
+    request: FacetRequest,
+    caller: Caller,
+    executor: Arc<dyn Executor>,
+    session_manager: Arc<SessionManager>,
+    config: Arc<Config>,
+) -> Result<impl Reply, warp::Rejection> {
+    let session_id = request.session_id;
+
+    if let Err(e) = start_request(request, caller, executor, session_manager.clone(), &config).await
+    {
+        return Ok(error_reply(e, session_id));
+    }
+
+    match request_status(&session_manager, session_id).await {
+        Ok(status) => Ok(reply::with_status(
+            reply::json(&status),
+            StatusCode::ACCEPTED,
+        )),
+        Err(e) => Ok(error_reply(e, session_id)),
+    }
+}
+
+/// Validates and registers a request, then runs it in the background
+///
+/// A request resuming an earlier session runs as the next turn of that
+/// session's conversation, which must be the caller's own.
+///
+/// # Arguments
+/// * `request` - Facet request to run
+/// * `caller` - Who the request comes from
+/// * `executor` - Claude executor (real or mock)
+/// * `session_manager` - Session tracking
+/// * `config` - Server configuration for validation limits
+///
+/// # Returns
+/// Ok(()) once the request is running, Err if it is invalid, resumes an
+/// unknown session or someone else's, or the concurrent session limit is
+/// reached
+pub(crate) async fn start_request(
+    request: FacetRequest,
+    caller: Caller,
+    executor: Arc<dyn Executor>,
+    session_manager: Arc<SessionManager>,
+    config: &Config,
+) -> Result<(), FacetError> {
+    request
+        .validate(
+            config.limits.max_screenshot_count,
+            config.limits.max_prompt_length,
+            50000, // max intent length - could be configurable
+        )
+        .map_err(FacetError::InvalidRequest)?;
+
+    let resume = match request.options.resume {
+        Some(earlier) => {
+            session_manager.check_owner(earlier, &caller).await?;
+            Some(session_manager.conversation_id(earlier).await?)
+        }
+        None => None,
+    };
+
+    session_manager
+        .register_request(
+            &request,
+            caller,
+            resume.clone(),
+            config.claude.max_concurrent_sessions,
+        )
+        .await?;
+
+    tokio::spawn(async move {
+        run_request(request, resume, executor, session_manager).await;
+    });
+
+    Ok(())
+}
+
+/// GET /api/v1/requests/:id handler
+///
+/// # Arguments
+/// * `session_id` - UUID of the submitted request
+/// * `caller` - Who is asking
+/// * `manager` - Shared session manager
+///
+/// # Returns
+/// JSON request status, or 404 with an error response if not found
+pub async fn get_request_handler(
+    session_id: Uuid,
+    caller: Caller,
+    manager: Arc<SessionManager>,
+) -> Result<impl Reply, warp::Rejection> {
+    if let Err(e) = manager.check_owner(session_id, &caller).await {
+        return Ok(error_reply(e, session_id));
+    }
+
+    match request_status(&manager, session_id).await {
+        Ok(status) => Ok(reply::with_status(reply::json(&status), StatusCode::OK)),
+        Err(e) => Ok(error_reply(e, session_id)),
+    }
+}
+
+/// DELETE /api/v1/requests/:id handler
+///
+/// Cancels a running request: its claude-cli process is killed and its
+/// event stream ends with a `cancelled` event.
+///
+/// # Arguments
+/// * `session_id` - UUID of the submitted request
+/// * `caller` - Who is asking
+/// * `executor` - Claude executor running the request
+/// * `manager` - Shared session manager
+///
+/// # Returns
+/// JSON request status with the output produced before cancellation, or
+/// the error's status code with an error response
+pub async fn cancel_request_handler(
+    session_id: Uuid,
+    caller: Caller,
+    executor: Arc<dyn Executor>,
+    manager: Arc<SessionManager>,
+) -> Result<impl Reply, warp::Rejection> {
+    if let Err(e) = cancel_request(session_id, &caller, executor.as_ref(), &manager).await {
+        return Ok(error_reply(e, session_id));
+    }
+
+    match request_status(&manager, session_id).await {
+        Ok(status) => Ok(reply::with_status(reply::json(&status), StatusCode::OK)),
+        Err(e) => Ok(error_reply(e, session_id)),
+    }
+}
+
+/// POST /api/v1/requests/:id/redaction handler
+///
+/// Passes on the client's reply to the redaction preview a request is
+/// waiting on: an approved request runs with its PII redacted, a denied
+/// one fails without being sent.
+///
+/// # Arguments
+/// * `session_id` - UUID of the request
+/// * `caller` - Who is replying
+/// * `answer` - Whether the client approved the preview
+/// * `manager` - Shared session manager
+///
+/// # Returns
+/// JSON request status, or the error's status code with an error
+/// response if the request is not waiting for a reply
+pub async fn redaction_reply_handler(
+    session_id: Uuid,
+    caller: Caller,
+    answer: RedactionReply,
+    manager: Arc<SessionManager>,
+) -> Result<impl Reply, warp::Rejection> {
+    if let Err(e) = reply_to_redaction(session_id, &caller, answer.approved, &manager).await {
+        return Ok(error_reply(e, session_id));
+    }
+
+    match request_status(&manager, session_id).await {
+        Ok(status) => Ok(reply::with_status(reply::json(&status), StatusCode::OK)),
+        Err(e) => Ok(error_reply(e, session_id)),
+    }
+}
+
+/// Passes on the caller's reply to their request's redaction preview
+///
+/// # Arguments
+/// * `session_id` - UUID of the request
+/// * `caller` - Who is replying
+/// * `approved` - Whether the redacted request may be sent
+/// * `session_manager` - Session tracking
+///
+/// # Returns
+/// Ok(()) if the request was waiting for a reply, Err if not found, not
+/// the caller's or not waiting
+pub(crate) async fn reply_to_redaction(
+    session_id: Uuid,
+    caller: &Caller,
+    approved: bool,
+    session_manager: &SessionManager,
+) -> Result<(), FacetError> {
+    session_manager.check_owner(session_id, caller).await?;
+    session_manager
+        .reply_to_redaction(session_id, approved)
+        .await
+}
+
+/// Cancels a running request and kills its process
+///
+/// The session is marked cancelled first, so nothing the process emits
+/// while it dies is recorded.
+///
+/// # Arguments
+/// * `session_id` - UUID of the request
+/// * `caller` - Who is cancelling
+/// * `executor` - Claude executor running the request
+/// * `session_manager` - Session tracking
+///
+/// # Returns
+/// Ok(()) if the request was running, Err if not found, not the caller's
+/// or not running
+pub(crate) async fn cancel_request(
+    session_id: Uuid,
+    caller: &Caller,
+    executor: &dyn Executor,
+    session_manager: &SessionManager,
+) -> Result<(), FacetError> {
+    session_manager.check_owner(session_id, caller).await?;
+    session_manager.cancel(session_id).await?;
+    if let Err(e) = executor.cancel(session_id).await {
+        // It finished on its own meanwhile
+        tracing::debug!(
+            "Nothing to kill for cancelled session {}: {}",
+            session_id,
+            e
+        );
+    }
+    Ok(())
+}
+
+/// GET /api/v1/requests/:id/events handler
+///
+/// Streams the request's events as SSE, typed by event (`content`,
+/// `tool_use`, `complete`, ...) with the event JSON as data. Events emitted
+/// before the client connected are replayed first; the stream ends when
+/// the request finishes.
+///
+/// # Arguments
+/// * `session_id` - UUID of the submitted request
+/// * `caller` - Who is asking
+/// * `manager` - Shared session manager
+///
+/// # Returns
+/// Server-Sent Events stream, or 404 with an error response if not found
+pub async fn request_events_handler(
+    session_id: Uuid,
+    caller: Caller,
+    manager: Arc<SessionManager>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    if let Err(e) = manager.check_owner(session_id, &caller).await {
+        return Ok(error_reply(e, session_id).into_response());
+    }
+    let (past, live) = match manager.subscribe(session_id).await {
+        Ok(subscription) => subscription,
+        Err(e) => return Ok(error_reply(e, session_id).into_response()),
+    };
+
+    let sse_stream = event_stream(past, live).map(|event| {
+        let data = serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string());
+        Ok::<_, Infallible>(
+            warp::sse::Event::default()
+                .event(event.event_type())
+                .data(data),
+        )
+    });
+    let keep_alive = warp::sse::keep_alive().interval(HEARTBEAT_INTERVAL);
+
+    Ok(warp::sse::reply(keep_alive.stream(sse_stream)).into_response())
+}
+
+/// Past events followed by live ones until the feed closes
+///
+/// A subscriber that falls too far behind skips the events it missed.
+pub(crate) fn event_stream(
+    past: Vec<ClaudeEvent>,
+    live: Option<broadcast::Receiver<ClaudeEvent>>,
+) -> impl Stream<Item = ClaudeEvent> {
+    async_stream::stream! {
+        for event in past {
+            yield event;
+        }
+        if let Some(mut live) = live {
+            loop {
+                match live.recv().await {
+                    Ok(event) => yield event,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        }
+    }
+}
+
+/// Runs a request to the end, recording its output and final state
+///
+/// Stops early if the session is cancelled meanwhile.
+async fn run_request(
+    request: FacetRequest,
+    resume: Option<String>,
+    executor: Arc<dyn Executor>,
+    session_manager: Arc<SessionManager>,
+) {
+    let session_id = request.session_id;
+    let mut event_stream =
+        redaction::execute(request, resume, executor, session_manager.clone()).await;
+
+    while let Some(result) = event_stream.next().await {
+        match session_manager.get_status(session_id).await {
+            Ok(status) if status.status == SessionState::Running => {}
+            // Cancelled or gone
+            _ => return,
+        }
+
+        if let Ok(event) = &result {
+            // Cancelling the session records its own Cancelled event
+            if !matches!(event, ClaudeEvent::Cancelled { .. }) {
+                let _ = session_manager
+                    .record_event(session_id, event.clone())
+                    .await;
+            }
+        }
+
+        match result {
+            Ok(ClaudeEvent::Error { message, .. }) => {
+                let _ = session_manager.fail(session_id, message).await;
+                return;
+            }
+            Ok(ClaudeEvent::Complete { .. }) => {
+                let _ = session_manager.complete(session_id).await;
+                return;
+            }
+            Ok(ClaudeEvent::Cancelled { .. }) => {
+                let _ = session_manager.cancel(session_id).await;
+                return;
+            }
+            Ok(ClaudeEvent::TimedOut {
+                timeout,
+                after_seconds,
+                ..
+            }) => {
+                let message = format!("Timed out after {}s ({} timeout)", after_seconds, timeout);
+                let _ = session_manager.fail(session_id, message).await;
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                let error_event = ClaudeEvent::Error {
+                    code: e.error_code(),
+                    message: e.to_string(),
+                };
+                let _ = session_manager.record_event(session_id, error_event).await;
+                let _ = session_manager.fail(session_id, e.to_string()).await;
+                return;
+            }
+        }
+    }
+
+    // Stream ended without a terminal event
+    let _ = session_manager.complete(session_id).await;
+}
+
+/// Current status and output of a request
+async fn request_status(
+    manager: &SessionManager,
+    session_id: Uuid,
+) -> Result<RequestStatus, FacetError> {
+    Ok(RequestStatus {
+        session: manager.get_status(session_id).await?,
+        output: manager.get_output(session_id).await?,
+    })
+}
+
+/// Error response with the status code mapped from the error
+fn error_reply(error: FacetError, session_id: Uuid) -> reply::WithStatus<reply::Json> {
+    let (status, response) = error_to_response(error, Some(session_id.to_string()));
+    reply::with_status(reply::json(&response), status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::MockClaudeExecutor;
+    use crate::history::SessionHistory;
+    use crate::models::{
+        DomState, RequestContext, RequestOptions, Screenshot, ScreenshotMetadata, Viewport,
+    };
+
+    fn create_test_request() -> FacetRequest {
+        FacetRequest {
+            session_id: Uuid::new_v4(),
+            context: RequestContext {
+                screenshots: vec![Screenshot {
+                    timestamp: "2025-10-17T10:30:00Z".to_string(),
+                    image_data: {
+                        use base64::{engine::general_purpose, Engine as _};
+                        general_purpose::STANDARD.encode(b"test image")
+                    },
+                    metadata: ScreenshotMetadata {
+                        window_title: "Test".to_string(),
+                        url: Some("https://test.com".to_string()),
+                        viewport: Viewport {
+                            width: 1920,
+                            height: 1080,
+                        },
+                    },
+                }],
+                dom_state: DomState {
+                    accessible_tree: "test tree".to_string(),
+                    interactive_elements: vec![],
+                },
+                user_intent: "test intent".to_string(),
+            },
+            prompt: "test prompt".to_string(),
+            options: RequestOptions::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_submit_then_poll_until_complete() {
+        let config = Arc::new(Config::dev_default());
+        let executor: Arc<dyn Executor> = Arc::new(MockClaudeExecutor::with_delay(10));
+        let session_manager = Arc::new(SessionManager::new(100));
+        let request = create_test_request();
+        let session_id = request.session_id;
+
+        let response = submit_request_handler(
+            request,
+            Caller::default(),
+            executor,
+            session_manager.clone(),
+            config,
+        )
+        .await
+        .unwrap()
+        .into_response();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        // Give time for async processing
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+
+        let status = request_status(&session_manager, session_id).await.unwrap();
+        assert_eq!(status.session.status, SessionState::Completed);
+        assert_eq!(
+            status.output,
+            "Mock: Analyzing screenshot...Mock: Task completed successfully"
+        );
+
+        let response = get_request_handler(session_id, Caller::default(), session_manager)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_events_are_replayed_then_streamed() {
+        let config = Arc::new(Config::dev_default());
+        let executor: Arc<dyn Executor> = Arc::new(MockClaudeExecutor::with_delay(20));
+        let session_manager = Arc::new(SessionManager::new(100));
+        let request = create_test_request();
+        let session_id = request.session_id;
+
+        submit_request_handler(
+            request,
+            Caller::default(),
+            executor,
+            session_manager.clone(),
+            config,
+        )
+        .await
+        .unwrap();
+        // Joins midway: the events so far are replayed, the rest follow
+        tokio::time::sleep(tokio::time::Duration::from_millis(30)).await;
+        let (past, live) = session_manager.subscribe(session_id).await.unwrap();
+
+        let events: Vec<ClaudeEvent> = event_stream(past, live).collect().await;
+        let types: Vec<&str> = events.iter().map(ClaudeEvent::event_type).collect();
+        assert_eq!(
+            types,
+            [
+                "content",
+                "progress",
+                "tool_use",
+                "content",
+                "usage_delta",
+                "complete"
+            ]
+        );
+
+        let response =
+            request_events_handler(session_id, Caller::default(), session_manager.clone())
+                .await
+                .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = request_events_handler(Uuid::new_v4(), Caller::default(), session_manager)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_failed_request_is_recorded() {
+        let config = Arc::new(Config::dev_default());
+        let executor: Arc<dyn Executor> = Arc::new(MockClaudeExecutor::with_failure());
+        let session_manager = Arc::new(SessionManager::new(100));
+        let request = create_test_request();
+        let session_id = request.session_id;
+
+        submit_request_handler(
+            request,
+            Caller::default(),
+            executor,
+            session_manager.clone(),
+            config,
+        )
+        .await
+        .unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
+
+        let status = session_manager.get_status(session_id).await.unwrap();
+        assert_eq!(status.status, SessionState::Failed);
+        assert_eq!(
+            status.error.as_deref(),
+            Some("Simulated failure for testing")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_resumes_earlier_conversation() {
+        let config = Config::dev_default();
+        let executor: Arc<dyn Executor> = Arc::new(MockClaudeExecutor::with_delay(5));
+        let history = Arc::new(SessionHistory::in_memory().unwrap());
+        let session_manager = Arc::new(SessionManager::new(100).with_history(history.clone()));
+
+        let first = create_test_request();
+        let first_id = first.session_id;
+        start_request(
+            first,
+            Caller::default(),
+            executor.clone(),
+            session_manager.clone(),
+            &config,
+        )
+        .await
+        .unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let mut second = create_test_request();
+        second.options.resume = Some(first_id);
+        let second_id = second.session_id;
+        start_request(
+            second,
+            Caller::default(),
+            executor.clone(),
+            session_manager.clone(),
+            &config,
+        )
+        .await
+        .unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let output = session_manager.get_output(second_id).await.unwrap();
+        assert!(output.starts_with(&format!("Mock: Resuming conversation {}", first_id)));
+        let record = history.load(second_id).unwrap().unwrap();
+        assert_eq!(record.conversation_id, first_id.to_string());
+        assert_eq!(record.resumed_from, Some(first_id));
+        assert_eq!(record.status.status, SessionState::Completed);
+        assert_eq!(record.events.len(), 7);
+
+        let mut unknown = create_test_request();
+        unknown.options.resume = Some(Uuid::new_v4());
+        let result = start_request(
+            unknown,
+            Caller::default(),
+            executor,
+            session_manager,
+            &config,
+        )
+        .await;
+        assert!(matches!(result, Err(FacetError::SessionNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_request_stops_it() {
+        let config = Arc::new(Config::dev_default());
+        let executor: Arc<dyn Executor> = Arc::new(MockClaudeExecutor::with_delay(50));
+        let session_manager = Arc::new(SessionManager::new(100));
+        let request = create_test_request();
+        let session_id = request.session_id;
+
+        submit_request_handler(
+            request,
+            Caller::default(),
+            executor.clone(),
+            session_manager.clone(),
+            config,
+        )
+        .await
+        .unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(75)).await;
+        let (_, live) = session_manager.subscribe(session_id).await.unwrap();
+
+        let response = cancel_request_handler(
+            session_id,
+            Caller::default(),
+            executor.clone(),
+            session_manager.clone(),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Followers see the stream end with a cancelled event
+        let events: Vec<ClaudeEvent> = event_stream(vec![], live).collect().await;
+        assert_eq!(events, vec![ClaudeEvent::Cancelled { session_id }]);
+
+        // Nothing more is recorded once the execution is gone
+        tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
+        let status = request_status(&session_manager, session_id).await.unwrap();
+        assert_eq!(status.session.status, SessionState::Cancelled);
+        assert_eq!(status.output, "Mock: Analyzing screenshot...");
+        assert!(executor.cancel(session_id).await.is_err());
+
+        // Cancelling again fails: it's no longer running
+        let response =
+            cancel_request_handler(session_id, Caller::default(), executor, session_manager)
+                .await
+                .unwrap()
+                .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_errors_map_to_status_codes() {
+        let config = Arc::new(Config::dev_default());
+        let executor: Arc<dyn Executor> = Arc::new(MockClaudeExecutor::with_delay(10));
+        let session_manager = Arc::new(SessionManager::new(100));
+
+        let mut request = create_test_request();
+        request.prompt = "a".repeat(100000);
+        let response = submit_request_handler(
+            request,
+            Caller::default(),
+            executor,
+            session_manager.clone(),
+            config,
+        )
+        .await
+        .unwrap()
+        .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = get_request_handler(Uuid::new_v4(), Caller::default(), session_manager)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_requests_hidden_from_other_callers() {
+        let config = Config::dev_default();
+        let executor: Arc<dyn Executor> = Arc::new(MockClaudeExecutor::with_delay(50));
+        let session_manager = Arc::new(SessionManager::new(100));
+        let owner = Caller {
+            actor: "api_key:one".to_string(),
+            profile: None,
+        };
+        let other = Caller {
+            actor: "api_key:two".to_string(),
+            profile: None,
+        };
+        let request = create_test_request();
+        let session_id = request.session_id;
+        start_request(
+            request,
+            owner.clone(),
+            executor.clone(),
+            session_manager.clone(),
+            &config,
+        )
+        .await
+        .unwrap();
+
+        let response = get_request_handler(session_id, other.clone(), session_manager.clone())
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = request_events_handler(session_id, other.clone(), session_manager.clone())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = redaction_reply_handler(
+            session_id,
+            other.clone(),
+            RedactionReply { approved: true },
+            session_manager.clone(),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = cancel_request_handler(
+            session_id,
+            other.clone(),
+            executor.clone(),
+            session_manager.clone(),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let mut resumed = create_test_request();
+        resumed.options.resume = Some(session_id);
+        let result = start_request(
+            resumed,
+            other,
+            executor.clone(),
+            session_manager.clone(),
+            &config,
+        )
+        .await;
+        assert!(matches!(result, Err(FacetError::SessionNotFound(_))));
+
+        // Untouched, and still the owner's to see
+        let response = get_request_handler(session_id, owner, session_manager.clone())
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let status = session_manager.get_status(session_id).await.unwrap();
+        assert_eq!(status.status, SessionState::Running);
+    }
+}
@@ -1,7 +1,11 @@
 //! Session management endpoints
 //!
-//! Provides endpoints for querying and cancelling active sessions.
+//! Provides endpoints for querying and cancelling active sessions. A
+//! session is only visible to the caller whose request started it.
 
+use crate::api::requests::cancel_request;
+use crate::auth::Caller;
+use crate::claude::Executor;
 use crate::error::{ErrorResponse, FacetError};
 use crate::session::SessionManager;
 use std::sync::Arc;
@@ -14,10 +18,12 @@ use warp::{http::StatusCode, reply, Reply};
 ///
 /// # Arguments
 /// * `session_id` - UUID of the session to query
+/// * `caller` - Who is asking
 /// * `manager` - Shared session manager
 ///
 /// # Returns
-/// JSON response with session status or 404 if not found
+/// JSON response with session status, or 404 with an error response if
+/// not found
 ///
 /// # Example Response
 /// ```json
@@ -31,27 +37,36 @@ use warp::{http::StatusCode, reply, Reply};
 /// ```
 pub async fn get_session_handler(
     session_id: Uuid,
+    caller: Caller,
     manager: Arc<SessionManager>,
 ) -> Result<impl Reply, warp::Rejection> {
-    match manager.get_status(session_id).await {
-        Ok(status) => Ok(reply::json(&status)),
+    let status = match manager.check_owner(session_id, &caller).await {
+        Ok(()) => manager.get_status(session_id).await,
+        Err(e) => Err(e),
+    };
+    match status {
+        Ok(status) => Ok(reply::with_status(reply::json(&status), StatusCode::OK)),
         Err(e) => {
-            let error_response = e.to_error_response(Some(session_id.to_string()));
-            Ok(reply::json(&error_response))
+            let (status, error_response) = error_to_response(e, Some(session_id.to_string()));
+            Ok(reply::with_status(reply::json(&error_response), status))
         }
     }
 }
 
 /// DELETE /api/v1/sessions/:id handler
 ///
-/// Cancels a running session. The session must be in Running state.
+/// Cancels a running session, killing its claude-cli process. The session
+/// must be in Running state.
 ///
 /// # Arguments
 /// * `session_id` - UUID of the session to cancel
+/// * `caller` - Who is cancelling
+/// * `executor` - Claude executor running the session
 /// * `manager` - Shared session manager
 ///
 /// # Returns
-/// JSON response with updated session status or error
+/// JSON response with updated session status, or the error's status code
+/// with an error response
 ///
 /// # Example Response
 /// ```json
@@ -62,22 +77,51 @@ pub async fn get_session_handler(
 /// ```
 pub async fn delete_session_handler(
     session_id: Uuid,
+    caller: Caller,
+    executor: Arc<dyn Executor>,
     manager: Arc<SessionManager>,
 ) -> Result<impl Reply, warp::Rejection> {
-    match manager.cancel(session_id).await {
-        Ok(_) => {
-            // Fetch updated status
-            match manager.get_status(session_id).await {
-                Ok(status) => Ok(reply::json(&status)),
-                Err(e) => {
-                    let error_response = e.to_error_response(Some(session_id.to_string()));
-                    Ok(reply::json(&error_response))
-                }
-            }
+    let status = match cancel_request(session_id, &caller, executor.as_ref(), &manager).await {
+        // Fetch updated status
+        Ok(_) => manager.get_status(session_id).await,
+        Err(e) => Err(e),
+    };
+    match status {
+        Ok(status) => Ok(reply::with_status(reply::json(&status), StatusCode::OK)),
+        Err(e) => {
+            let (status, error_response) = error_to_response(e, Some(session_id.to_string()));
+            Ok(reply::with_status(reply::json(&error_response), status))
         }
+    }
+}
+
+/// GET /api/v1/sessions/:id/history handler
+///
+/// Returns a session with its prompt, conversation and every event it
+/// emitted. Sessions persisted to the history store are found even after
+/// a restart.
+///
+/// # Arguments
+/// * `session_id` - UUID of the session to query
+/// * `caller` - Who is asking
+/// * `manager` - Shared session manager
+///
+/// # Returns
+/// JSON session record, or the error's status code with an error response
+pub async fn get_session_history_handler(
+    session_id: Uuid,
+    caller: Caller,
+    manager: Arc<SessionManager>,
+) -> Result<impl Reply, warp::Rejection> {
+    let record = match manager.check_owner(session_id, &caller).await {
+        Ok(()) => manager.get_record(session_id).await,
+        Err(e) => Err(e),
+    };
+    match record {
+        Ok(record) => Ok(reply::with_status(reply::json(&record), StatusCode::OK)),
         Err(e) => {
-            let error_response = e.to_error_response(Some(session_id.to_string()));
-            Ok(reply::json(&error_response))
+            let (status, error_response) = error_to_response(e, Some(session_id.to_string()));
+            Ok(reply::with_status(reply::json(&error_response), status))
         }
     }
 }
@@ -105,8 +149,13 @@ pub fn error_to_response(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::claude::MockClaudeExecutor;
     use crate::models::SessionState;
 
+    fn mock_executor() -> Arc<dyn Executor> {
+        Arc::new(MockClaudeExecutor::new())
+    }
+
     #[tokio::test]
     async fn test_get_session_handler_found() {
         let manager = Arc::new(SessionManager::new(100));
@@ -116,7 +165,7 @@ mod tests {
         manager.register(session_id, 10).await.unwrap();
 
         // Query it
-        let result = get_session_handler(session_id, manager).await;
+        let result = get_session_handler(session_id, Caller::default(), manager).await;
         assert!(result.is_ok());
     }
 
@@ -126,10 +175,60 @@ mod tests {
         let session_id = Uuid::new_v4();
 
         // Query nonexistent session
-        let result = get_session_handler(session_id, manager).await;
+        let result = get_session_handler(session_id, Caller::default(), manager).await;
         assert!(result.is_ok()); // Handler doesn't reject, returns error JSON
     }
 
+    #[tokio::test]
+    async fn test_get_session_history_handler() {
+        let manager = Arc::new(SessionManager::new(100));
+        let session_id = Uuid::new_v4();
+        manager.register(session_id, 10).await.unwrap();
+
+        let response = get_session_history_handler(session_id, Caller::default(), manager.clone())
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = get_session_history_handler(Uuid::new_v4(), Caller::default(), manager)
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_sessions_hidden_from_other_callers() {
+        let manager = Arc::new(SessionManager::new(100));
+        let session_id = Uuid::new_v4();
+        manager.register(session_id, 10).await.unwrap();
+        let other = Caller {
+            actor: "api_key:other".to_string(),
+            profile: None,
+        };
+
+        let response = get_session_handler(session_id, other.clone(), manager.clone())
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = get_session_history_handler(session_id, other.clone(), manager.clone())
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = delete_session_handler(session_id, other, mock_executor(), manager.clone())
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let status = manager.get_status(session_id).await.unwrap();
+        assert_eq!(status.status, SessionState::Running);
+    }
+
     #[tokio::test]
     async fn test_delete_session_handler_success() {
         let manager = Arc::new(SessionManager::new(100));
@@ -139,7 +238,13 @@ mod tests {
         manager.register(session_id, 10).await.unwrap();
 
         // Cancel it
-        let result = delete_session_handler(session_id, manager.clone()).await;
+        let result = delete_session_handler(
+            session_id,
+            Caller::default(),
+            mock_executor(),
+            manager.clone(),
+        )
+        .await;
         assert!(result.is_ok());
 
         // Verify it's cancelled
@@ -157,7 +262,8 @@ mod tests {
         manager.complete(session_id).await.unwrap();
 
         // Try to cancel it
-        let result = delete_session_handler(session_id, manager).await;
+        let result =
+            delete_session_handler(session_id, Caller::default(), mock_executor(), manager).await;
         assert!(result.is_ok()); // Returns error JSON, not rejection
     }
 
@@ -167,7 +273,8 @@ mod tests {
         let session_id = Uuid::new_v4();
 
         // Try to cancel nonexistent session
-        let result = delete_session_handler(session_id, manager).await;
+        let result =
+            delete_session_handler(session_id, Caller::default(), mock_executor(), manager).await;
         assert!(result.is_ok()); // Returns error JSON
     }
 
@@ -0,0 +1,115 @@
+//! Usage endpoints
+//!
+//! Token usage and cost summed per profile over every request the server
+//! has finished, as kept by the profile usage store.
+
+use crate::profile_usage::ProfileUsageStore;
+use facet_types::profiles::UsageTotals;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use warp::{reply, Reply};
+
+/// Usage of every profile, and of all of them together
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UsageResponse {
+    /// Totals per profile
+    pub profiles: BTreeMap<String, UsageTotals>,
+
+    /// Totals over all profiles
+    pub total: UsageTotals,
+}
+
+/// GET /api/v1/usage handler
+///
+/// # Arguments
+/// * `store` - Usage totals per profile
+///
+/// # Returns
+/// JSON usage of every profile
+///
+/// # Example Response
+/// ```json
+/// {
+///   "profiles": {
+///     "work": {
+///       "requests": 12,
+///       "input_tokens": 48210,
+///       "output_tokens": 3904,
+///       "cache_creation_input_tokens": 0,
+///       "cache_read_input_tokens": 20400,
+///       "cost_usd": 0.41
+///     }
+///   },
+///   "total": { ... }
+/// }
+/// ```
+pub async fn usage_handler(store: Arc<ProfileUsageStore>) -> Result<impl Reply, warp::Rejection> {
+    let profiles = store.all();
+    let mut total = UsageTotals::default();
+    for totals in profiles.values() {
+        total.add(totals);
+    }
+    Ok(reply::json(&UsageResponse { profiles, total }))
+}
+
+/// GET /api/v1/usage/:profile handler
+///
+/// # Arguments
+/// * `profile` - Profile to report on
+/// * `store` - Usage totals per profile
+///
+/// # Returns
+/// JSON usage of the profile, all zero if it has made no requests
+pub async fn profile_usage_handler(
+    profile: String,
+    store: Arc<ProfileUsageStore>,
+) -> Result<impl Reply, warp::Rejection> {
+    Ok(reply::json(&store.get(&profile)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RequestUsage;
+
+    async fn body<T: serde::de::DeserializeOwned>(reply: impl Reply) -> T {
+        let body = warp::hyper::body::to_bytes(reply.into_response().into_body())
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_usage_handlers() {
+        let store = Arc::new(ProfileUsageStore::in_memory());
+        let usage = RequestUsage {
+            output_tokens: 100,
+            cost_usd: 0.5,
+            ..Default::default()
+        };
+        store.add(Some("work"), &usage).unwrap();
+        store.add(Some("shopping"), &usage).unwrap();
+
+        let response: UsageResponse = body(usage_handler(store.clone()).await.unwrap()).await;
+        assert_eq!(response.profiles.len(), 2);
+        assert_eq!(response.total.requests, 2);
+        assert_eq!(response.total.output_tokens, 200);
+        assert_eq!(response.total.cost_usd, 1.0);
+
+        let work: UsageTotals = body(
+            profile_usage_handler("work".to_string(), store.clone())
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(work.output_tokens, 100);
+        let unknown: UsageTotals = body(
+            profile_usage_handler("unknown".to_string(), store)
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(unknown, UsageTotals::default());
+    }
+}
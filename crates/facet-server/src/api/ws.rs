@@ -0,0 +1,489 @@
+//! WebSocket session endpoint
+//!
+//! GET /api/v1/ws upgrades to a WebSocket over which a client submits
+//! requests, receives their events as they stream, and stops them midway,
+//! all on one connection. Messages are JSON objects tagged by `type`.
+//!
+//! Client to server:
+//! ```json
+//! {"type": "submit", "request": { ...FacetRequest... }}
+//! {"type": "cancel", "session_id": "uuid"}
+//! {"type": "interrupt", "session_id": "uuid"}
+//! {"type": "confirm_redaction", "session_id": "uuid", "approved": true}
+//! ```
+//!
+//! `cancel` abandons a request, killing its process, and its events end
+//! with a `cancelled` event; `interrupt` stops it but keeps what it has
+//! produced as its result, so it ends as completed. `confirm_redaction`
+//! answers a request's `redaction_preview` event: approved, the redacted
+//! request is sent; denied, it fails without being sent. All three only
+//! apply to the caller's own requests.
+//!
+//! Server to client:
+//! ```json
+//! {"type": "accepted", "session_id": "uuid"}
+//! {"type": "event", "session_id": "uuid", "event": { ...ClaudeEvent... }}
+//! {"type": "stopped", "session_id": "uuid", "status": "cancelled"}
+//! {"type": "redaction_confirmed", "session_id": "uuid", "approved": true}
+//! {"type": "error", "error": { ...ErrorResponse... }}
+//! ```
+//!
+//! Requests still running when the connection closes are cancelled.
+
+use crate::api::requests::{cancel_request, event_stream, reply_to_redaction, start_request};
+use crate::auth::Caller;
+use crate::claude::Executor;
+use crate::config::Config;
+use crate::error::{ErrorResponse, FacetError};
+use crate::models::{ClaudeEvent, FacetRequest, SessionState};
+use crate::session::SessionManager;
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+use warp::ws::{Message, WebSocket, Ws};
+use warp::Reply;
+
+/// Message from the client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Run a request and stream its events
+    Submit { request: Box<FacetRequest> },
+
+    /// Stop a request and discard it
+    Cancel { session_id: Uuid },
+
+    /// Stop a request, keeping its output so far as the result
+    Interrupt { session_id: Uuid },
+
+    /// Approve or deny the redaction preview a request is waiting on
+    ConfirmRedaction { session_id: Uuid, approved: bool },
+}
+
+/// Message to the client
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// A submitted request is running
+    Accepted { session_id: Uuid },
+
+    /// An event of a running request
+    Event {
+        session_id: Uuid,
+        event: ClaudeEvent,
+    },
+
+    /// A request was stopped by cancel or interrupt
+    Stopped {
+        session_id: Uuid,
+        status: SessionState,
+    },
+
+    /// The client's reply to a redaction preview was passed on
+    RedactionConfirmed { session_id: Uuid, approved: bool },
+
+    /// A message failed or could not be understood
+    Error { error: ErrorResponse },
+}
+
+/// GET /api/v1/ws handler
+///
+/// # Arguments
+/// * `ws` - WebSocket upgrade request
+/// * `caller` - Who the connection's requests come from
+/// * `executor` - Claude executor (real or mock)
+/// * `session_manager` - Session tracking
+/// * `config` - Server configuration for validation limits
+///
+/// # Returns
+/// Upgrade response; the connection is then served by `handle_socket`
+pub fn ws_handler(
+    ws: Ws,
+    caller: Caller,
+    executor: Arc<dyn Executor>,
+    session_manager: Arc<SessionManager>,
+    config: Arc<Config>,
+) -> impl Reply {
+    ws.on_upgrade(move |socket| handle_socket(socket, caller, executor, session_manager, config))
+}
+
+/// Serves one WebSocket connection until the client closes it
+///
+/// # Arguments
+/// * `socket` - Upgraded WebSocket
+/// * `caller` - Who the connection's requests come from
+/// * `executor` - Claude executor (real or mock)
+/// * `session_manager` - Session tracking
+/// * `config` - Server configuration for validation limits
+pub async fn handle_socket(
+    socket: WebSocket,
+    caller: Caller,
+    executor: Arc<dyn Executor>,
+    session_manager: Arc<SessionManager>,
+    config: Arc<Config>,
+) {
+    let (mut sink, mut incoming) = socket.split();
+
+    // Everything sent goes through one channel, so request streams and
+    // replies to the client's messages don't interleave mid-frame
+    let (outgoing, mut to_send) = mpsc::unbounded_channel::<ServerMessage>();
+    let writer = tokio::spawn(async move {
+        while let Some(message) = to_send.recv().await {
+            let text = serde_json::to_string(&message).unwrap_or_else(|_| "{}".to_string());
+            if sink.send(Message::text(text)).await.is_err() {
+                break;
+            }
+        }
+        let _ = sink.close().await;
+    });
+
+    let mut submitted = Vec::new();
+    while let Some(Ok(message)) = incoming.next().await {
+        if message.is_close() {
+            break;
+        }
+        let Ok(text) = message.to_str() else {
+            // Pings, pongs and binary frames carry no requests
+            continue;
+        };
+
+        let reply = match serde_json::from_str::<ClientMessage>(text) {
+            Ok(ClientMessage::Submit { request }) => {
+                let session_id = request.session_id;
+                submit(
+                    *request,
+                    caller.clone(),
+                    executor.clone(),
+                    session_manager.clone(),
+                    &config,
+                    outgoing.clone(),
+                )
+                .await
+                .map(|()| {
+                    submitted.push(session_id);
+                    ServerMessage::Accepted { session_id }
+                })
+                .map_err(|e| (e, Some(session_id)))
+            }
+            Ok(ClientMessage::Cancel { session_id }) => {
+                cancel_request(session_id, &caller, executor.as_ref(), &session_manager)
+                    .await
+                    .map(|()| ServerMessage::Stopped {
+                        session_id,
+                        status: SessionState::Cancelled,
+                    })
+                    .map_err(|e| (e, Some(session_id)))
+            }
+            Ok(ClientMessage::Interrupt { session_id }) => {
+                interrupt(executor.as_ref(), &session_manager, session_id, &caller)
+                    .await
+                    .map(|()| ServerMessage::Stopped {
+                        session_id,
+                        status: SessionState::Completed,
+                    })
+                    .map_err(|e| (e, Some(session_id)))
+            }
+            Ok(ClientMessage::ConfirmRedaction {
+                session_id,
+                approved,
+            }) => reply_to_redaction(session_id, &caller, approved, &session_manager)
+                .await
+                .map(|()| ServerMessage::RedactionConfirmed {
+                    session_id,
+                    approved,
+                })
+                .map_err(|e| (e, Some(session_id))),
+            Err(e) => Err((
+                FacetError::InvalidRequest(format!("Invalid message: {}", e)),
+                None,
+            )),
+        };
+
+        let reply = reply.unwrap_or_else(|(e, session_id)| ServerMessage::Error {
+            error: e.to_error_response(session_id.map(|id| id.to_string())),
+        });
+        if outgoing.send(reply).is_err() {
+            break;
+        }
+    }
+
+    // The client is gone: nobody is left to receive these
+    for session_id in submitted {
+        let _ = cancel_request(session_id, &caller, executor.as_ref(), &session_manager).await;
+    }
+    drop(outgoing);
+    let _ = writer.await;
+}
+
+/// Starts a request and forwards its events to the client
+async fn submit(
+    request: FacetRequest,
+    caller: Caller,
+    executor: Arc<dyn Executor>,
+    session_manager: Arc<SessionManager>,
+    config: &Config,
+    outgoing: mpsc::UnboundedSender<ServerMessage>,
+) -> Result<(), FacetError> {
+    let session_id = request.session_id;
+    // Subscribed before anything can be missed: the request is registered
+    // and running by now, and its past events are replayed
+    start_request(request, caller, executor, session_manager.clone(), config).await?;
+    let (past, live) = session_manager.subscribe(session_id).await?;
+
+    tokio::spawn(async move {
+        let mut events = Box::pin(event_stream(past, live));
+        while let Some(event) = events.next().await {
+            if outgoing
+                .send(ServerMessage::Event { session_id, event })
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Stops the caller's running request, marking it completed with its
+/// output so far
+async fn interrupt(
+    executor: &dyn Executor,
+    session_manager: &SessionManager,
+    session_id: Uuid,
+    caller: &Caller,
+) -> Result<(), FacetError> {
+    session_manager.check_owner(session_id, caller).await?;
+    let status = session_manager.get_status(session_id).await?;
+    if status.status != SessionState::Running {
+        return Err(FacetError::InvalidRequest(format!(
+            "Session {} is not running (current state: {:?})",
+            session_id, status.status
+        )));
+    }
+    session_manager.complete(session_id).await?;
+    // Its process may have finished meanwhile
+    let _ = executor.cancel(session_id).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::MockClaudeExecutor;
+    use crate::models::{
+        DomState, RequestContext, RequestOptions, Screenshot, ScreenshotMetadata, Viewport,
+    };
+    use warp::Filter;
+
+    fn create_test_request() -> FacetRequest {
+        FacetRequest {
+            session_id: Uuid::new_v4(),
+            context: RequestContext {
+                screenshots: vec![Screenshot {
+                    timestamp: "2025-10-17T10:30:00Z".to_string(),
+                    image_data: {
+                        use base64::{engine::general_purpose, Engine as _};
+                        general_purpose::STANDARD.encode(b"test image")
+                    },
+                    metadata: ScreenshotMetadata {
+                        window_title: "Test".to_string(),
+                        url: Some("https://test.com".to_string()),
+                        viewport: Viewport {
+                            width: 1920,
+                            height: 1080,
+                        },
+                    },
+                }],
+                dom_state: DomState {
+                    accessible_tree: "test tree".to_string(),
+                    interactive_elements: vec![],
+                },
+                user_intent: "test intent".to_string(),
+            },
+            prompt: "test prompt".to_string(),
+            options: RequestOptions::default(),
+        }
+    }
+
+    fn ws_filter(
+        delay_ms: u64,
+        session_manager: Arc<SessionManager>,
+    ) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+        let executor: Arc<dyn Executor> = Arc::new(MockClaudeExecutor::with_delay(delay_ms));
+        let config = Arc::new(Config::dev_default());
+        warp::path!("ws").and(warp::ws()).map(move |ws: Ws| {
+            ws_handler(
+                ws,
+                Caller::default(),
+                executor.clone(),
+                session_manager.clone(),
+                config.clone(),
+            )
+        })
+    }
+
+    async fn recv(client: &mut warp::test::WsClient) -> ServerMessage {
+        let message = client.recv().await.unwrap();
+        serde_json::from_str(message.to_str().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_submit_streams_events() {
+        let session_manager = Arc::new(SessionManager::new(100));
+        let mut client = warp::test::ws()
+            .path("/ws")
+            .handshake(ws_filter(5, session_manager))
+            .await
+            .unwrap();
+
+        let request = create_test_request();
+        let session_id = request.session_id;
+        let submit = serde_json::to_string(&ClientMessage::Submit {
+            request: Box::new(request),
+        })
+        .unwrap();
+        client.send_text(submit).await;
+
+        // Accepted comes first, unless the first event beats it
+        let mut messages = Vec::new();
+        loop {
+            let message = recv(&mut client).await;
+            let done = matches!(
+                &message,
+                ServerMessage::Event {
+                    event: ClaudeEvent::Complete { .. },
+                    ..
+                }
+            );
+            messages.push(message);
+            if done {
+                break;
+            }
+        }
+        assert!(messages.contains(&ServerMessage::Accepted { session_id }));
+        let events = messages
+            .iter()
+            .filter(|m| matches!(m, ServerMessage::Event { .. }))
+            .count();
+        assert_eq!(events, 6);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_and_invalid_messages() {
+        let session_manager = Arc::new(SessionManager::new(100));
+        let mut client = warp::test::ws()
+            .path("/ws")
+            .handshake(ws_filter(200, session_manager.clone()))
+            .await
+            .unwrap();
+
+        let request = create_test_request();
+        let session_id = request.session_id;
+        let submit = serde_json::to_string(&ClientMessage::Submit {
+            request: Box::new(request),
+        })
+        .unwrap();
+        client.send_text(submit).await;
+        assert_eq!(
+            recv(&mut client).await,
+            ServerMessage::Accepted { session_id }
+        );
+
+        let cancel = serde_json::to_string(&ClientMessage::Cancel { session_id }).unwrap();
+        client.send_text(cancel).await;
+        // The reply and the request's last event, in either order
+        let mut replies = vec![recv(&mut client).await, recv(&mut client).await];
+        replies.sort_by_key(|m| matches!(m, ServerMessage::Event { .. }));
+        assert_eq!(
+            replies,
+            [
+                ServerMessage::Stopped {
+                    session_id,
+                    status: SessionState::Cancelled
+                },
+                ServerMessage::Event {
+                    session_id,
+                    event: ClaudeEvent::Cancelled { session_id }
+                }
+            ]
+        );
+        let status = session_manager.get_status(session_id).await.unwrap();
+        assert_eq!(status.status, SessionState::Cancelled);
+
+        // Stopping it again fails: it's no longer running
+        let interrupt = serde_json::to_string(&ClientMessage::Interrupt { session_id }).unwrap();
+        client.send_text(interrupt).await;
+        match recv(&mut client).await {
+            ServerMessage::Error { error } => assert_eq!(error.code, "INVALID_REQUEST"),
+            other => panic!("expected an error, got {:?}", other),
+        }
+
+        client.send_text("not json").await;
+        match recv(&mut client).await {
+            ServerMessage::Error { error } => assert_eq!(error.code, "INVALID_REQUEST"),
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_confirm_redaction() {
+        let session_manager = Arc::new(SessionManager::new(100));
+        let mut client = warp::test::ws()
+            .path("/ws")
+            .handshake(ws_filter(5, session_manager.clone()))
+            .await
+            .unwrap();
+
+        let mut request = create_test_request();
+        request.prompt = "Email bob@example.com".to_string();
+        request.options.redaction = Some(facet_types::profiles::RedactionPreferences {
+            enabled: true,
+            ..Default::default()
+        });
+        let session_id = request.session_id;
+        let submit = serde_json::to_string(&ClientMessage::Submit {
+            request: Box::new(request),
+        })
+        .unwrap();
+        client.send_text(submit).await;
+
+        // Accepted and the preview, in either order
+        let mut messages = [recv(&mut client).await, recv(&mut client).await];
+        messages.sort_by_key(|m| matches!(m, ServerMessage::Event { .. }));
+        assert_eq!(messages[0], ServerMessage::Accepted { session_id });
+        match &messages[1] {
+            ServerMessage::Event {
+                event:
+                    ClaudeEvent::RedactionPreview {
+                        redacted_prompt, ..
+                    },
+                ..
+            } => assert_eq!(redacted_prompt, "Email [EMAIL_1]"),
+            other => panic!("expected a redaction preview, got {:?}", other),
+        }
+
+        let confirm = serde_json::to_string(&ClientMessage::ConfirmRedaction {
+            session_id,
+            approved: true,
+        })
+        .unwrap();
+        client.send_text(confirm).await;
+        let mut confirmed = false;
+        loop {
+            match recv(&mut client).await {
+                ServerMessage::RedactionConfirmed { approved, .. } => confirmed = approved,
+                ServerMessage::Event {
+                    event: ClaudeEvent::Complete { .. },
+                    ..
+                } => break,
+                _ => {}
+            }
+        }
+        assert!(confirmed);
+        let status = session_manager.get_status(session_id).await.unwrap();
+        assert_eq!(status.status, SessionState::Completed);
+    }
+}
@@ -0,0 +1,318 @@
+//! API key store
+//!
+//! Keys are created, listed and revoked through /api/v1/keys or
+//! `facet keys`, and accepted as bearer tokens alongside the tokens in the
+//! config file. Only a hash of each key is kept (see
+//! `facet_types::profiles::crypto`), in a JSON file the server and the CLI
+//! share. The server rereads the file when it changes, so keys created or
+//! revoked from the CLI take effect without a restart.
+
+use crate::error::FacetError;
+use facet_types::profiles::{crypto, storage};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::SystemTime;
+
+/// File name of the store under ~/.facet
+const API_KEYS_FILE: &str = "api_keys.json";
+
+/// An API key as listed, without its secret
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApiKeyInfo {
+    /// Public id of the key, part of the key itself
+    pub id: String,
+
+    /// Name given to the key, e.g. the app using it
+    pub name: String,
+
+    /// ISO 8601 creation timestamp
+    pub created_at: String,
+
+    /// ISO 8601 revocation timestamp, if revoked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<String>,
+}
+
+impl ApiKeyInfo {
+    /// Whether requests may still authenticate with the key
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none()
+    }
+}
+
+/// A newly created API key
+///
+/// The only time the key itself is available: it cannot be recovered
+/// from the store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub info: ApiKeyInfo,
+
+    /// The key, to be sent as `Authorization: Bearer <key>`
+    pub key: String,
+}
+
+/// An API key as stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredKey {
+    #[serde(flatten)]
+    info: ApiKeyInfo,
+
+    /// SHA-256 of the key
+    hash: String,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    keys: Vec<StoredKey>,
+
+    /// Modification time of the file when it was last read or written
+    modified: Option<SystemTime>,
+}
+
+/// Store of API keys, backed by a JSON file or kept in memory
+#[derive(Debug)]
+pub struct ApiKeyStore {
+    path: Option<PathBuf>,
+    state: Mutex<State>,
+}
+
+impl ApiKeyStore {
+    /// Returns the store's default location, ~/.facet/api_keys.json
+    ///
+    /// # Errors
+    /// Returns FacetError::Config if the home directory cannot be found
+    pub fn default_path() -> Result<PathBuf, FacetError> {
+        storage::get_facet_dir(None)
+            .map(|dir| dir.join(API_KEYS_FILE))
+            .map_err(|e| FacetError::Config(e.to_string()))
+    }
+
+    /// Opens the store at `path`, which is created with the first key
+    ///
+    /// # Arguments
+    /// * `path` - Path to the JSON file of keys
+    ///
+    /// # Returns
+    /// ApiKeyStore backed by the file
+    ///
+    /// # Errors
+    /// Returns FacetError::Config if the file exists but cannot be read
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, FacetError> {
+        let store = Self {
+            path: Some(path.as_ref().to_path_buf()),
+            state: Mutex::new(State::default()),
+        };
+        store.reload(&mut store.state())?;
+        Ok(store)
+    }
+
+    /// Creates a store kept in memory, whose keys last until the server stops
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Creates a new key
+    ///
+    /// # Arguments
+    /// * `name` - Name to list the key under
+    ///
+    /// # Returns
+    /// The key and its details
+    pub fn create(&self, name: &str) -> Result<CreatedApiKey, FacetError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(FacetError::InvalidRequest(
+                "API key name cannot be empty".to_string(),
+            ));
+        }
+
+        let mut state = self.state();
+        self.refresh(&mut state)?;
+
+        let (id, key) = crypto::generate_api_key();
+        let info = ApiKeyInfo {
+            id,
+            name: name.to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            revoked_at: None,
+        };
+        state.keys.push(StoredKey {
+            info: info.clone(),
+            hash: crypto::hash_api_key(&key),
+        });
+        self.save(&mut state)?;
+
+        Ok(CreatedApiKey { info, key })
+    }
+
+    /// Lists all keys, revoked ones included, oldest first
+    pub fn list(&self) -> Result<Vec<ApiKeyInfo>, FacetError> {
+        let mut state = self.state();
+        self.refresh(&mut state)?;
+        Ok(state.keys.iter().map(|key| key.info.clone()).collect())
+    }
+
+    /// Revokes a key; revoking a revoked key changes nothing
+    ///
+    /// # Arguments
+    /// * `id` - Id of the key
+    ///
+    /// # Returns
+    /// The key's details after revocation
+    ///
+    /// # Errors
+    /// Returns FacetError::ApiKeyNotFound if there is no such key
+    pub fn revoke(&self, id: &str) -> Result<ApiKeyInfo, FacetError> {
+        let mut state = self.state();
+        self.refresh(&mut state)?;
+
+        let stored = state
+            .keys
+            .iter_mut()
+            .find(|key| key.info.id == id)
+            .ok_or_else(|| FacetError::ApiKeyNotFound(id.to_string()))?;
+        if stored.info.revoked_at.is_some() {
+            return Ok(stored.info.clone());
+        }
+        stored.info.revoked_at = Some(chrono::Utc::now().to_rfc3339());
+        let info = stored.info.clone();
+        self.save(&mut state)?;
+
+        Ok(info)
+    }
+
+    /// Checks a bearer token against the active keys
+    ///
+    /// # Arguments
+    /// * `key` - Token from the Authorization header
+    ///
+    /// # Returns
+    /// The key's details if it is an active key, None otherwise
+    pub fn verify(&self, key: &str) -> Option<ApiKeyInfo> {
+        let id = crypto::api_key_id(key)?;
+        let mut state = self.state();
+        if let Err(e) = self.refresh(&mut state) {
+            // Keep accepting the keys last read rather than locking everyone out
+            tracing::warn!("Failed to reload API keys: {}", e);
+        }
+
+        state
+            .keys
+            .iter()
+            .find(|stored| stored.info.id == id)
+            .filter(|stored| stored.info.is_active())
+            .filter(|stored| crypto::verify_api_key(key, &stored.hash))
+            .map(|stored| stored.info.clone())
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Rereads the file if it changed since it was last read or written
+    fn refresh(&self, state: &mut State) -> Result<(), FacetError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified.is_some() && modified == state.modified {
+            return Ok(());
+        }
+        self.reload(state)
+    }
+
+    fn reload(&self, state: &mut State) -> Result<(), FacetError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !path.exists() {
+            *state = State::default();
+            return Ok(());
+        }
+
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            FacetError::Config(format!("Failed to read API keys {}: {}", path.display(), e))
+        })?;
+        state.keys = serde_json::from_str(&contents).map_err(|e| {
+            FacetError::Config(format!(
+                "Failed to parse API keys {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        state.modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        Ok(())
+    }
+
+    /// Writes the keys to a temporary file and renames it over the store,
+    /// so a crash mid-write can't lose every key
+    fn save(&self, state: &mut State) -> Result<(), FacetError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let write_error =
+            |e: std::io::Error| FacetError::Internal(format!("Failed to save API keys: {}", e));
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(write_error)?;
+        }
+        let contents = serde_json::to_string_pretty(&state.keys)
+            .map_err(|e| FacetError::Internal(format!("Failed to serialize API keys: {}", e)))?;
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(&temp, contents).map_err(write_error)?;
+        std::fs::rename(&temp, path).map_err(write_error)?;
+
+        state.modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_verify_revoke() {
+        let store = ApiKeyStore::in_memory();
+        let created = store.create("editor plugin").unwrap();
+
+        let info = store.verify(&created.key).unwrap();
+        assert_eq!(info.name, "editor plugin");
+        assert!(store.verify("fk_000000000000_not-the-key").is_none());
+
+        let revoked = store.revoke(&created.info.id).unwrap();
+        assert!(!revoked.is_active());
+        assert!(store.verify(&created.key).is_none());
+        // Still listed, as revoked
+        assert_eq!(store.list().unwrap(), vec![revoked]);
+
+        assert!(matches!(
+            store.revoke("missing"),
+            Err(FacetError::ApiKeyNotFound(_))
+        ));
+        assert!(store.create("  ").is_err());
+    }
+
+    #[test]
+    fn test_keys_shared_through_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("api_keys.json");
+
+        let server = ApiKeyStore::open(&path).unwrap();
+        assert!(server.list().unwrap().is_empty());
+
+        // Created by another process, e.g. the CLI
+        let created = ApiKeyStore::open(&path).unwrap().create("cli").unwrap();
+        assert!(server.verify(&created.key).is_some());
+
+        // The file holds no secrets
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains(&created.key));
+    }
+}
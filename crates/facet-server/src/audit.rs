@@ -0,0 +1,612 @@
+//! Audit log of executions
+//!
+//! Every execution is appended once it ends, as one JSON line: who ran it
+//! (see `AuthState::identify`), for which profile, its prompt, the tools
+//! Claude called, how it ended and what it cost. Entries are only ever
+//! appended. With hash chaining on, each entry also carries the SHA-256 of
+//! the entry before it and of itself, so editing or dropping an entry
+//! breaks the chain from there on. Once the file reaches its size limit it
+//! is rotated to `<path>.1`, `<path>.2`, ..., the oldest beyond the limit
+//! being deleted.
+
+use crate::error::FacetError;
+use crate::models::{RequestUsage, SessionState};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::ffi::OsString;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+/// Entries returned by a query that doesn't set a limit
+const DEFAULT_QUERY_LIMIT: usize = 100;
+
+/// Most entries a query returns
+const MAX_QUERY_LIMIT: usize = 1000;
+
+/// An execution as audited
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditEntry {
+    /// Position in the log, from 1
+    pub seq: u64,
+
+    /// ISO 8601 timestamp of when the execution ended
+    pub timestamp: String,
+
+    /// Session the execution ran in
+    pub session_id: Uuid,
+
+    /// Who ran it, e.g. `api_key:<id>`
+    pub actor: String,
+
+    /// Profile it acted for, if the request named one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+
+    /// User's prompt
+    pub prompt: String,
+
+    /// Tools Claude called, each once, in order of first use
+    pub tools: Vec<String>,
+
+    /// How the execution ended
+    pub outcome: SessionState,
+
+    /// Error message, if it failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// ISO 8601 timestamp of when the execution started
+    pub started_at: String,
+
+    /// Tokens and cost, if claude-cli reported any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<RequestUsage>,
+
+    /// Hash of the entry before, if chained and there was one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev_hash: Option<String>,
+
+    /// Hash of this entry, `prev_hash` included, if chained
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+impl AuditEntry {
+    /// Hex-encoded SHA-256 of the entry as serialized without its hash
+    fn compute_hash(&self) -> Result<String, FacetError> {
+        let unhashed = AuditEntry {
+            hash: None,
+            ..self.clone()
+        };
+        let json = serde_json::to_string(&unhashed).map_err(serialize_error)?;
+        Ok(hex::encode(Sha256::digest(json.as_bytes())))
+    }
+}
+
+/// Filter of GET /api/v1/audit, every field optional
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AuditQuery {
+    /// Only entries of this session
+    pub session_id: Option<Uuid>,
+
+    /// Only entries of this actor
+    pub actor: Option<String>,
+
+    /// Only entries for this profile
+    pub profile: Option<String>,
+
+    /// Only entries that ended this way
+    pub outcome: Option<SessionState>,
+
+    /// Only entries that ended at or after this ISO 8601 timestamp
+    pub since: Option<String>,
+
+    /// Most entries to return, the latest ones (default 100, at most 1000)
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.session_id.is_none_or(|id| entry.session_id == id)
+            && self
+                .actor
+                .as_ref()
+                .is_none_or(|actor| &entry.actor == actor)
+            && self
+                .profile
+                .as_ref()
+                .is_none_or(|profile| entry.profile.as_ref() == Some(profile))
+            && self
+                .outcome
+                .as_ref()
+                .is_none_or(|outcome| &entry.outcome == outcome)
+            && self
+                .since
+                .as_ref()
+                .is_none_or(|since| entry.timestamp.as_str() >= since.as_str())
+    }
+}
+
+/// Result of checking a log's hash chain
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChainVerification {
+    /// Entries checked
+    pub entries: usize,
+
+    /// First entry whose hash or link doesn't check out, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub broken_at: Option<u64>,
+}
+
+/// Last entry written, which the next one follows
+#[derive(Debug, Default)]
+struct State {
+    seq: u64,
+    hash: Option<String>,
+
+    /// Entries of a log kept in memory
+    entries: Vec<AuditEntry>,
+}
+
+/// Append-only audit log, backed by JSONL files or kept in memory
+#[derive(Debug)]
+pub struct AuditLog {
+    path: Option<PathBuf>,
+    hash_chain: bool,
+    max_file_bytes: u64,
+    max_files: usize,
+    state: Mutex<State>,
+}
+
+impl AuditLog {
+    /// Opens the log at `path`, which is created with the first entry
+    ///
+    /// Entries follow on from the last one already in the log. A last line
+    /// that doesn't parse, as left by a crash mid-write, is cut off with a
+    /// warning. Files are rotated at 10 MB, keeping 5, unless
+    /// `with_rotation` says otherwise.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the current JSONL file
+    ///
+    /// # Returns
+    /// AuditLog appending to the file
+    ///
+    /// # Errors
+    /// Returns FacetError::Config if the log exists but cannot be read or
+    /// repaired
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, FacetError> {
+        let path = path.as_ref();
+        drop_incomplete_line(path)?;
+        let log = Self {
+            path: Some(path.to_path_buf()),
+            ..Self::in_memory()
+        };
+        let last = log
+            .files()
+            .into_iter()
+            .rev()
+            .map(|file| read_entries(&file).map(|entries| entries.into_iter().last()))
+            .find_map(Result::transpose)
+            .transpose()?;
+        if let Some(last) = last {
+            let mut state = log.state();
+            state.seq = last.seq;
+            state.hash = last.hash;
+        }
+        Ok(log)
+    }
+
+    /// Creates a log kept in memory, whose entries last until the server
+    /// stops
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            hash_chain: false,
+            max_file_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Chains entries by hash, making tampering detectable
+    ///
+    /// # Arguments
+    /// * `hash_chain` - Whether to chain entries
+    ///
+    /// # Returns
+    /// AuditLog hashing the entries it appends
+    pub fn with_hash_chain(mut self, hash_chain: bool) -> Self {
+        self.hash_chain = hash_chain;
+        self
+    }
+
+    /// Sets when files are rotated and how many old ones are kept
+    ///
+    /// # Arguments
+    /// * `max_file_bytes` - Size at which the current file is rotated
+    /// * `max_files` - Rotated files kept
+    ///
+    /// # Returns
+    /// AuditLog rotating its files accordingly
+    pub fn with_rotation(mut self, max_file_bytes: u64, max_files: usize) -> Self {
+        self.max_file_bytes = max_file_bytes;
+        self.max_files = max_files;
+        self
+    }
+
+    /// Appends an entry
+    ///
+    /// # Arguments
+    /// * `entry` - Entry to append; its `seq` and hashes are filled in
+    ///
+    /// # Returns
+    /// The entry as written
+    ///
+    /// # Errors
+    /// Returns FacetError::Internal if the entry cannot be written
+    pub fn append(&self, mut entry: AuditEntry) -> Result<AuditEntry, FacetError> {
+        let mut state = self.state();
+        entry.seq = state.seq + 1;
+        entry.prev_hash = None;
+        entry.hash = None;
+        if self.hash_chain {
+            entry.prev_hash = state.hash.clone();
+            entry.hash = Some(entry.compute_hash()?);
+        }
+
+        match &self.path {
+            Some(path) => {
+                let mut line = serde_json::to_string(&entry).map_err(serialize_error)?;
+                line.push('\n');
+                self.write_line(path, &line)?;
+            }
+            None => state.entries.push(entry.clone()),
+        }
+        state.seq = entry.seq;
+        state.hash = entry.hash.clone();
+
+        Ok(entry)
+    }
+
+    /// Looks up entries, oldest first
+    ///
+    /// # Arguments
+    /// * `query` - Which entries to return
+    ///
+    /// # Returns
+    /// The latest matching entries, up to the query's limit
+    ///
+    /// # Errors
+    /// Returns FacetError::Internal if the log cannot be read
+    pub fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, FacetError> {
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_QUERY_LIMIT)
+            .min(MAX_QUERY_LIMIT);
+        let mut entries: Vec<AuditEntry> = self
+            .entries()?
+            .into_iter()
+            .filter(|entry| query.matches(entry))
+            .collect();
+        let skip = entries.len().saturating_sub(limit);
+        entries.drain(..skip);
+        Ok(entries)
+    }
+
+    /// Checks the hash chain of every entry still kept
+    ///
+    /// The first entry's link isn't checked: the entry it follows may
+    /// have been rotated out.
+    ///
+    /// # Returns
+    /// How many entries were checked, and the first that didn't check out
+    ///
+    /// # Errors
+    /// Returns FacetError::Internal if the log cannot be read
+    pub fn verify(&self) -> Result<ChainVerification, FacetError> {
+        let entries = self.entries()?;
+        let mut broken_at = None;
+        for (i, entry) in entries.iter().enumerate() {
+            let linked = i == 0 || entry.prev_hash == entries[i - 1].hash;
+            if !linked || entry.hash.as_ref() != Some(&entry.compute_hash()?) {
+                broken_at = Some(entry.seq);
+                break;
+            }
+        }
+        Ok(ChainVerification {
+            entries: entries.len(),
+            broken_at,
+        })
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Every entry kept, oldest first
+    fn entries(&self) -> Result<Vec<AuditEntry>, FacetError> {
+        if self.path.is_none() {
+            return Ok(self.state().entries.clone());
+        }
+        // Nothing is written meanwhile
+        let _state = self.state();
+        let mut entries = Vec::new();
+        for file in self.files() {
+            entries.extend(read_entries(&file).map_err(|e| FacetError::Internal(e.to_string()))?);
+        }
+        Ok(entries)
+    }
+
+    /// Files of the log, oldest first, the current one last
+    fn files(&self) -> Vec<PathBuf> {
+        let Some(path) = &self.path else {
+            return Vec::new();
+        };
+        (1..=self.max_files)
+            .rev()
+            .map(|n| rotated(path, n))
+            .chain([path.clone()])
+            .filter(|file| file.exists())
+            .collect()
+    }
+
+    /// Appends a line to the current file, rotating it first if the line
+    /// would take it past its size limit
+    fn write_line(&self, path: &Path, line: &str) -> Result<(), FacetError> {
+        let write_error =
+            |e: std::io::Error| FacetError::Internal(format!("Failed to write audit log: {}", e));
+
+        let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 > self.max_file_bytes {
+            self.rotate(path).map_err(write_error)?;
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(write_error)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(write_error)?;
+        file.write_all(line.as_bytes()).map_err(write_error)
+    }
+
+    /// Shifts every file one place older, dropping the oldest
+    fn rotate(&self, path: &Path) -> std::io::Result<()> {
+        if self.max_files == 0 {
+            return std::fs::remove_file(path);
+        }
+        for n in (1..self.max_files).rev() {
+            let older = rotated(path, n);
+            if older.exists() {
+                std::fs::rename(&older, rotated(path, n + 1))?;
+            }
+        }
+        std::fs::rename(path, rotated(path, 1))
+    }
+}
+
+/// Path of the `n`th most recently rotated file
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut rotated = OsString::from(path.as_os_str());
+    rotated.push(format!(".{}", n));
+    PathBuf::from(rotated)
+}
+
+/// Cuts a last line that isn't an entry off the end of `file`, so the
+/// next entry starts on a line of its own
+fn drop_incomplete_line(file: &Path) -> Result<(), FacetError> {
+    let config_error = |e: std::io::Error| {
+        FacetError::Config(format!(
+            "Failed to repair audit log {}: {}",
+            file.display(),
+            e
+        ))
+    };
+    let contents = match std::fs::read(file) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(config_error(e)),
+    };
+
+    let body = contents.trim_ascii_end();
+    let start = body.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    if serde_json::from_slice::<AuditEntry>(&body[start..]).is_ok() || body.is_empty() {
+        return Ok(());
+    }
+
+    tracing::warn!(
+        "Dropping incomplete last entry of audit log {}",
+        file.display()
+    );
+    OpenOptions::new()
+        .write(true)
+        .open(file)
+        .and_then(|f| f.set_len(start as u64))
+        .map_err(config_error)
+}
+
+/// Reads the entries of one file, in order
+fn read_entries(file: &Path) -> Result<Vec<AuditEntry>, FacetError> {
+    let contents = std::fs::read_to_string(file).map_err(|e| {
+        FacetError::Config(format!(
+            "Failed to read audit log {}: {}",
+            file.display(),
+            e
+        ))
+    })?;
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| {
+                FacetError::Config(format!(
+                    "Corrupt entry in audit log {}: {}",
+                    file.display(),
+                    e
+                ))
+            })
+        })
+        .collect()
+}
+
+fn serialize_error(e: serde_json::Error) -> FacetError {
+    FacetError::Internal(format!("Failed to serialize audit entry: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(actor: &str, outcome: SessionState) -> AuditEntry {
+        AuditEntry {
+            seq: 0,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            session_id: Uuid::new_v4(),
+            actor: actor.to_string(),
+            profile: Some("work".to_string()),
+            prompt: "Summarize this page".to_string(),
+            tools: vec!["cdp_command".to_string()],
+            outcome,
+            error: None,
+            started_at: chrono::Utc::now().to_rfc3339(),
+            usage: None,
+            prev_hash: None,
+            hash: None,
+        }
+    }
+
+    #[test]
+    fn test_append_and_query() {
+        let log = AuditLog::in_memory();
+        log.append(entry("api_key:a", SessionState::Completed))
+            .unwrap();
+        let failed = log
+            .append(entry("api_key:b", SessionState::Failed))
+            .unwrap();
+        log.append(entry("api_key:a", SessionState::Cancelled))
+            .unwrap();
+        assert_eq!(failed.seq, 2);
+        assert!(failed.hash.is_none());
+
+        let by_actor = AuditQuery {
+            actor: Some("api_key:a".to_string()),
+            ..Default::default()
+        };
+        let seqs: Vec<u64> = log
+            .query(&by_actor)
+            .unwrap()
+            .iter()
+            .map(|e| e.seq)
+            .collect();
+        assert_eq!(seqs, vec![1, 3]);
+
+        let failures = AuditQuery {
+            outcome: Some(SessionState::Failed),
+            ..Default::default()
+        };
+        assert_eq!(log.query(&failures).unwrap(), vec![failed]);
+
+        let latest = AuditQuery {
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(log.query(&latest).unwrap()[0].seq, 3);
+    }
+
+    #[test]
+    fn test_hash_chain_detects_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+
+        let log = AuditLog::open(&path).unwrap().with_hash_chain(true);
+        let first = log
+            .append(entry("token:1", SessionState::Completed))
+            .unwrap();
+        drop(log);
+
+        // Reopened, as after a restart: the chain carries on
+        let log = AuditLog::open(&path).unwrap().with_hash_chain(true);
+        let second = log.append(entry("token:1", SessionState::Failed)).unwrap();
+        assert_eq!(second.seq, 2);
+        assert_eq!(second.prev_hash, first.hash);
+        assert_eq!(
+            log.verify().unwrap(),
+            ChainVerification {
+                entries: 2,
+                broken_at: None
+            }
+        );
+
+        // Pass the failure off as a success
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let tampered = contents.replace("\"outcome\":\"failed\"", "\"outcome\":\"completed\"");
+        assert_ne!(tampered, contents);
+        std::fs::write(&path, tampered).unwrap();
+        assert_eq!(log.verify().unwrap().broken_at, Some(2));
+    }
+
+    #[test]
+    fn test_reopen_drops_incomplete_last_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+
+        let log = AuditLog::open(&path).unwrap().with_hash_chain(true);
+        log.append(entry("token:1", SessionState::Completed))
+            .unwrap();
+        let second = log
+            .append(entry("token:1", SessionState::Completed))
+            .unwrap();
+        drop(log);
+
+        // Crashed partway through writing the third entry
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"seq\":3,\"timestamp\":\"2026-").unwrap();
+        drop(file);
+
+        let log = AuditLog::open(&path).unwrap().with_hash_chain(true);
+        assert_eq!(log.query(&AuditQuery::default()).unwrap().len(), 2);
+        let third = log.append(entry("token:1", SessionState::Failed)).unwrap();
+        assert_eq!(third.seq, 3);
+        assert_eq!(third.prev_hash, second.hash);
+        assert_eq!(
+            log.verify().unwrap(),
+            ChainVerification {
+                entries: 3,
+                broken_at: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_rotation_keeps_max_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        // Room for one entry per file
+        let log = AuditLog::open(&path)
+            .unwrap()
+            .with_hash_chain(true)
+            .with_rotation(1, 2);
+
+        for _ in 0..4 {
+            log.append(entry("token:1", SessionState::Completed))
+                .unwrap();
+        }
+
+        assert!(rotated(&path, 2).exists());
+        assert!(!rotated(&path, 3).exists());
+        let seqs: Vec<u64> = log
+            .query(&AuditQuery::default())
+            .unwrap()
+            .iter()
+            .map(|e| e.seq)
+            .collect();
+        assert_eq!(seqs, vec![2, 3, 4]);
+        // The chain holds across files, its start rotated out
+        assert_eq!(log.verify().unwrap().broken_at, None);
+    }
+}
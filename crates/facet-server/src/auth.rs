@@ -1,30 +1,46 @@
 //! Authentication and rate limiting middleware
 //!
-//! Provides bearer token authentication and per-token rate limiting
-//! for API endpoints. Supports development mode with relaxed requirements.
+//! Provides bearer token authentication and token-bucket rate limiting
+//! per API key and profile for API endpoints. Tokens are checked against
+//! the configured list and the API key store. Supports development mode
+//! with relaxed requirements.
 
+use crate::api_keys::ApiKeyStore;
 use crate::error::FacetError;
-use std::collections::HashMap;
+use crate::rate_limit::{RateLimitKey, RateLimitMetrics, RateLimiter};
+use facet_types::profiles::crypto;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::Duration;
 use warp::{reject, Filter, Rejection};
 
+/// Header naming the profile a request acts for, rate limited on its own
+pub const PROFILE_HEADER: &str = "x-facet-profile";
+
+/// Actor of requests made without a token, when authentication is off
+pub const ANONYMOUS: &str = "anonymous";
+
+/// Longest profile name the profile header may carry
+const MAX_PROFILE_LEN: usize = 64;
+
 /// Authentication state
 ///
-/// Tracks valid tokens and per-token request counts for rate limiting.
+/// Tracks valid tokens and the rate limiter they are counted against.
 #[derive(Clone)]
 pub struct AuthState {
     /// List of valid bearer tokens
     valid_tokens: Vec<String>,
 
+    /// API keys accepted in addition to `valid_tokens`
+    api_keys: Option<Arc<ApiKeyStore>>,
+
     /// Whether authentication is required
     require_auth: bool,
 
     /// Rate limit (requests per minute)
     rate_limit: u32,
 
-    /// Map of token to request timestamps (for rate limiting)
-    request_history: Arc<Mutex<HashMap<String, Vec<std::time::Instant>>>>,
+    /// Token buckets per API key and profile
+    limiter: Arc<RateLimiter>,
 }
 
 impl AuthState {
@@ -33,23 +49,49 @@ impl AuthState {
     /// # Arguments
     /// * `valid_tokens` - List of accepted bearer tokens
     /// * `require_auth` - Whether to enforce authentication (false for dev mode)
-    /// * `rate_limit` - Maximum requests per minute per token
+    /// * `rate_limit` - Maximum requests per minute per token, which is
+    ///   also the burst allowed after a token has been idle
     ///
     /// # Returns
     /// New AuthState instance
     pub fn new(valid_tokens: Vec<String>, require_auth: bool, rate_limit: u32) -> Self {
         Self {
             valid_tokens,
+            api_keys: None,
             require_auth,
             rate_limit,
-            request_history: Arc::new(Mutex::new(HashMap::new())),
+            limiter: Arc::new(RateLimiter::new(rate_limit, rate_limit)),
         }
     }
 
+    /// Sets the burst allowed after a token has been idle
+    ///
+    /// # Arguments
+    /// * `burst` - Requests let through at once
+    ///
+    /// # Returns
+    /// AuthState with fresh buckets of the new size
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.limiter = Arc::new(RateLimiter::new(self.rate_limit, burst));
+        self
+    }
+
+    /// Accepts the active keys of an API key store as bearer tokens
+    ///
+    /// # Arguments
+    /// * `api_keys` - Store of API keys
+    ///
+    /// # Returns
+    /// AuthState checking tokens against the store as well
+    pub fn with_api_keys(mut self, api_keys: Arc<ApiKeyStore>) -> Self {
+        self.api_keys = Some(api_keys);
+        self
+    }
+
     /// Validates bearer token
     ///
-    /// Checks if the provided token is in the list of valid tokens.
-    /// If require_auth is false, always returns Ok.
+    /// Checks if the provided token is in the list of valid tokens or is
+    /// an active API key. If require_auth is false, always returns Ok.
     ///
     /// # Arguments
     /// * `token` - Bearer token from Authorization header
@@ -61,17 +103,44 @@ impl AuthState {
             return Ok(token.to_string());
         }
 
-        if self.valid_tokens.contains(&token.to_string()) {
+        if self.valid_tokens.contains(&token.to_string()) || self.is_api_key(token) {
             Ok(token.to_string())
         } else {
             Err(FacetError::AuthFailed("Invalid token".to_string()))
         }
     }
 
+    /// Whether a token is an active API key rather than a configured token
+    ///
+    /// # Arguments
+    /// * `token` - Bearer token from Authorization header
+    pub fn is_api_key(&self, token: &str) -> bool {
+        self.api_keys
+            .as_ref()
+            .is_some_and(|keys| keys.verify(token).is_some())
+    }
+
+    /// Names whoever authenticated with a token, without giving it away
+    ///
+    /// # Arguments
+    /// * `token` - Validated bearer token
+    ///
+    /// # Returns
+    /// `api_key:<id>` for API keys, `token:<fingerprint>` for configured
+    /// tokens (the start of their SHA-256), `anonymous` without a token
+    pub fn identify(&self, token: &str) -> String {
+        if token.is_empty() {
+            return ANONYMOUS.to_string();
+        }
+        if let Some(key) = self.api_keys.as_ref().and_then(|keys| keys.verify(token)) {
+            return format!("api_key:{}", key.id);
+        }
+        format!("token:{}", &crypto::hash_api_key(token)[..12])
+    }
+
     /// Checks rate limit for a token
     ///
-    /// Examines request history for the token and determines if the
-    /// rate limit has been exceeded. Cleans up old entries (>1 minute).
+    /// Takes a token from the API key's bucket.
     ///
     /// # Arguments
     /// * `token` - Bearer token to check
@@ -79,62 +148,94 @@ impl AuthState {
     /// # Returns
     /// Ok(()) if within limit, Err if rate limit exceeded
     pub async fn check_rate_limit(&self, token: &str) -> Result<(), FacetError> {
-        let mut history = self.request_history.lock().await;
-        let now = std::time::Instant::now();
-        let one_minute_ago = now - std::time::Duration::from_secs(60);
-
-        // Get or create history for this token
-        let requests = history.entry(token.to_string()).or_insert_with(Vec::new);
-
-        // Remove requests older than 1 minute
-        requests.retain(|&timestamp| timestamp > one_minute_ago);
-
-        // Check if limit exceeded
-        if requests.len() >= self.rate_limit as usize {
-            return Err(FacetError::RateLimited(format!(
-                "Rate limit of {} requests per minute exceeded",
-                self.rate_limit
-            )));
-        }
+        self.throttle(token, None).await.map_err(|r| r.error)
+    }
 
-        // Record this request
-        requests.push(now);
+    /// Checks rate limits for a request
+    ///
+    /// Takes a token from the API key's bucket and, if the request names
+    /// one, the profile's.
+    ///
+    /// # Arguments
+    /// * `token` - Bearer token of the request
+    /// * `profile` - Profile the request acts for, if any
+    ///
+    /// # Returns
+    /// Ok(()) if within limits, Err with when to retry if throttled
+    pub async fn throttle(
+        &self,
+        token: &str,
+        profile: Option<&str>,
+    ) -> Result<(), RateLimitRejection> {
+        let mut keys = vec![RateLimitKey::ApiKey(token.to_string())];
+        if let Some(profile) = profile {
+            keys.push(RateLimitKey::Profile(profile.to_string()));
+        }
 
-        Ok(())
+        self.limiter.acquire(&keys).await.map_err(|retry_after| {
+            if let Some(profile) = profile {
+                tracing::warn!("Throttled request for profile {}", profile);
+            }
+            RateLimitRejection {
+                error: FacetError::RateLimited(format!(
+                    "Rate limit of {} requests per minute exceeded",
+                    self.rate_limit
+                )),
+                retry_after,
+            }
+        })
     }
 
     /// Clears rate limit history for a token
     ///
-    /// Useful for testing or administrative operations.
+    /// Refills the token's bucket. Useful for testing or administrative
+    /// operations.
     ///
     /// # Arguments
     /// * `token` - Token to clear history for
     pub async fn clear_rate_limit(&self, token: &str) {
-        let mut history = self.request_history.lock().await;
-        history.remove(token);
+        self.limiter
+            .reset(&RateLimitKey::ApiKey(token.to_string()))
+            .await;
     }
 
-    /// Returns current request count for a token in the last minute
+    /// Returns how many requests a token's bucket is down
     ///
     /// # Arguments
     /// * `token` - Token to check
     ///
     /// # Returns
-    /// Number of requests in the last minute
+    /// Requests taken from the bucket and not yet refilled
     pub async fn get_request_count(&self, token: &str) -> usize {
-        let history = self.request_history.lock().await;
-        let now = std::time::Instant::now();
-        let one_minute_ago = now - std::time::Duration::from_secs(60);
-
-        history
-            .get(token)
-            .map(|requests| {
-                requests
-                    .iter()
-                    .filter(|&&timestamp| timestamp > one_minute_ago)
-                    .count()
-            })
-            .unwrap_or(0)
+        let available = self
+            .limiter
+            .available(&RateLimitKey::ApiKey(token.to_string()))
+            .await;
+        (self.limiter.capacity() - available).round() as usize
+    }
+
+    /// Returns counts of allowed and throttled requests
+    pub async fn rate_limit_metrics(&self) -> RateLimitMetrics {
+        self.limiter.metrics().await
+    }
+}
+
+/// Who a request comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caller {
+    /// Who authenticated, as `AuthState::identify` names them
+    pub actor: String,
+
+    /// Profile the request acts for, if it named one
+    pub profile: Option<String>,
+}
+
+impl Default for Caller {
+    fn default() -> Self {
+        Self {
+            actor: ANONYMOUS.to_string(),
+            profile: None,
+        }
     }
 }
 
@@ -144,6 +245,18 @@ pub struct AuthRejection(pub FacetError);
 
 impl reject::Reject for AuthRejection {}
 
+/// Rejection for throttled requests, with when they may be retried
+#[derive(Debug)]
+pub struct RateLimitRejection {
+    /// The RateLimited error
+    pub error: FacetError,
+
+    /// How long until the request would be let through
+    pub retry_after: Duration,
+}
+
+impl reject::Reject for RateLimitRejection {}
+
 /// Extracts bearer token from Authorization header
 ///
 /// Parses "Bearer <token>" format and returns the token portion.
@@ -163,10 +276,39 @@ fn extract_bearer_token(auth_header: String) -> Result<String, Rejection> {
     }
 }
 
+/// Checks a profile name from the `X-Facet-Profile` header
+///
+/// Profile names follow the rules of usernames: letters, digits,
+/// underscores and dashes, at most 64 of them.
+///
+/// # Arguments
+/// * `profile` - Profile name to check
+///
+/// # Errors
+/// Returns FacetError::InvalidRequest if the name is empty, too long or
+/// has other characters
+pub fn validate_profile(profile: &str) -> Result<(), FacetError> {
+    let valid = !profile.is_empty()
+        && profile.len() <= MAX_PROFILE_LEN
+        && profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(FacetError::InvalidRequest(format!(
+            "Invalid profile name in {} header",
+            PROFILE_HEADER
+        )))
+    }
+}
+
 /// Creates authentication filter
 ///
-/// Warp filter that validates bearer tokens and enforces rate limits.
-/// Returns the validated token for downstream handlers.
+/// Warp filter that validates bearer tokens and enforces rate limits, per
+/// token and per the profile in the `X-Facet-Profile` header if present.
+/// Requests naming a malformed profile are rejected. Returns the validated
+/// token for downstream handlers.
 ///
 /// # Arguments
 /// * `auth_state` - Shared authentication state
@@ -176,36 +318,89 @@ fn extract_bearer_token(auth_header: String) -> Result<String, Rejection> {
 pub fn with_auth(
     auth_state: Arc<AuthState>,
 ) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
-    warp::header::optional::<String>("authorization").and_then(
-        move |auth_header: Option<String>| {
-            let auth_state = auth_state.clone();
-            async move {
-                // If auth not required and no header, use empty token
-                let token = if let Some(header) = auth_header {
-                    extract_bearer_token(header)?
-                } else if !auth_state.require_auth {
-                    String::new()
-                } else {
-                    return Err(reject::custom(AuthRejection(FacetError::AuthFailed(
-                        "Missing Authorization header".to_string(),
-                    ))));
-                };
-
-                // Validate token
-                auth_state
-                    .validate_token(&token)
-                    .map_err(|e| reject::custom(AuthRejection(e)))?;
-
-                // Check rate limit
-                auth_state
-                    .check_rate_limit(&token)
-                    .await
-                    .map_err(|e| reject::custom(AuthRejection(e)))?;
-
-                Ok::<String, Rejection>(token)
+    warp::header::optional::<String>("authorization")
+        .and(warp::header::optional::<String>(PROFILE_HEADER))
+        .and_then(
+            move |auth_header: Option<String>, profile: Option<String>| {
+                let auth_state = auth_state.clone();
+                async move {
+                    // If auth not required and no header, use empty token
+                    let token = if let Some(header) = auth_header {
+                        extract_bearer_token(header)?
+                    } else if !auth_state.require_auth {
+                        String::new()
+                    } else {
+                        return Err(reject::custom(AuthRejection(FacetError::AuthFailed(
+                            "Missing Authorization header".to_string(),
+                        ))));
+                    };
+
+                    // Validate token
+                    auth_state
+                        .validate_token(&token)
+                        .map_err(|e| reject::custom(AuthRejection(e)))?;
+                    if let Some(profile) = &profile {
+                        validate_profile(profile).map_err(|e| reject::custom(AuthRejection(e)))?;
+                    }
+
+                    // Check rate limits
+                    auth_state
+                        .throttle(&token, profile.as_deref())
+                        .await
+                        .map_err(reject::custom)?;
+
+                    Ok::<String, Rejection>(token)
+                }
+            },
+        )
+}
+
+/// Creates authentication filter for administration endpoints
+///
+/// Like `with_auth`, but only lets through the tokens in the
+/// configuration: API keys are issued to apps and may not manage keys
+/// themselves. Without auth required, requests without a token pass too.
+///
+/// # Arguments
+/// * `auth_state` - Shared authentication state
+///
+/// # Returns
+/// Warp filter that validates bearer tokens and rejects API keys with
+/// FacetError::Forbidden
+pub fn with_admin(
+    auth_state: Arc<AuthState>,
+) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    with_auth(auth_state.clone()).and_then(move |token: String| {
+        let auth_state = auth_state.clone();
+        async move {
+            if auth_state.is_api_key(&token) {
+                return Err(reject::custom(AuthRejection(FacetError::Forbidden(
+                    "API keys may not manage API keys".to_string(),
+                ))));
             }
-        },
-    )
+            Ok::<String, Rejection>(token)
+        }
+    })
+}
+
+/// Creates authentication filter that also tells who the request is from
+///
+/// Like `with_auth`, but extracts the caller rather than the token.
+///
+/// # Arguments
+/// * `auth_state` - Shared authentication state
+///
+/// # Returns
+/// Warp filter that validates bearer tokens and extracts the caller
+pub fn with_caller(
+    auth_state: Arc<AuthState>,
+) -> impl Filter<Extract = (Caller,), Error = Rejection> + Clone {
+    with_auth(auth_state.clone())
+        .and(warp::header::optional::<String>(PROFILE_HEADER))
+        .map(move |token: String, profile: Option<String>| Caller {
+            actor: auth_state.identify(&token),
+            profile,
+        })
 }
 
 #[cfg(test)]
@@ -235,6 +430,36 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_validate_api_key() {
+        let keys = Arc::new(ApiKeyStore::in_memory());
+        let auth_state = create_test_auth_state().with_api_keys(keys.clone());
+        let created = keys.create("test").unwrap();
+
+        assert!(auth_state.validate_token(&created.key).is_ok());
+        assert!(auth_state.validate_token("valid-token-1").is_ok());
+
+        keys.revoke(&created.info.id).unwrap();
+        assert!(auth_state.validate_token(&created.key).is_err());
+    }
+
+    #[test]
+    fn test_identify() {
+        let keys = Arc::new(ApiKeyStore::in_memory());
+        let auth_state = create_test_auth_state().with_api_keys(keys.clone());
+        let created = keys.create("test").unwrap();
+
+        assert_eq!(
+            auth_state.identify(&created.key),
+            format!("api_key:{}", created.info.id)
+        );
+        let actor = auth_state.identify("valid-token-1");
+        assert!(actor.starts_with("token:"));
+        assert!(!actor.contains("valid-token-1"));
+        assert_ne!(actor, auth_state.identify("valid-token-2"));
+        assert_eq!(auth_state.identify(""), ANONYMOUS);
+    }
+
     #[test]
     fn test_validate_token_without_auth_required() {
         let auth_state = AuthState::new(vec![], false, 10);
@@ -332,6 +557,62 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_validate_profile() {
+        assert!(validate_profile("work").is_ok());
+        assert!(validate_profile("side-project_2").is_ok());
+        assert!(validate_profile("").is_err());
+        assert!(validate_profile("../work").is_err());
+        assert!(validate_profile("two words").is_err());
+        assert!(validate_profile(&"a".repeat(MAX_PROFILE_LEN + 1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_with_auth_rejects_malformed_profile() {
+        let auth_state = Arc::new(create_test_auth_state());
+        let filter = with_auth(auth_state.clone());
+
+        let result = warp::test::request()
+            .header("authorization", "Bearer valid-token-1")
+            .header(PROFILE_HEADER, "not a profile")
+            .filter(&filter)
+            .await;
+        assert!(result.is_err());
+        // Rejected before it was counted
+        assert_eq!(auth_state.get_request_count("valid-token-1").await, 0);
+
+        let result = warp::test::request()
+            .header("authorization", "Bearer valid-token-1")
+            .header(PROFILE_HEADER, "work")
+            .filter(&filter)
+            .await;
+        assert_eq!(result.unwrap(), "valid-token-1");
+    }
+
+    #[tokio::test]
+    async fn test_with_admin_rejects_api_keys() {
+        let keys = Arc::new(ApiKeyStore::in_memory());
+        let auth_state = Arc::new(create_test_auth_state().with_api_keys(keys.clone()));
+        let created = keys.create("test").unwrap();
+        let filter = with_admin(auth_state);
+
+        let result = warp::test::request()
+            .header("authorization", "Bearer valid-token-1")
+            .filter(&filter)
+            .await;
+        assert_eq!(result.unwrap(), "valid-token-1");
+
+        let rejection = warp::test::request()
+            .header("authorization", format!("Bearer {}", created.key))
+            .filter(&filter)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            rejection.find::<AuthRejection>(),
+            Some(AuthRejection(FacetError::Forbidden(_)))
+        ));
+    }
+
     #[tokio::test]
     async fn test_with_auth_filter_valid_token() {
         let auth_state = Arc::new(create_test_auth_state());
@@ -0,0 +1,252 @@
+//! Cancellation of running executions
+//!
+//! Executors register every execution under its session id. Cancelling one
+//! kills the process group it runs in, so whatever claude-cli started goes
+//! with it, and wakes its event stream, which then ends with a Cancelled
+//! event. Registrations are dropped with their streams.
+
+use crate::error::FacetError;
+use crate::models::ClaudeEvent;
+use futures::{Stream, StreamExt};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+/// An execution that can be cancelled
+#[derive(Debug)]
+struct Execution {
+    /// Woken when the execution is cancelled
+    cancelled: Arc<Notify>,
+
+    /// Process group the execution runs in
+    process_group: ProcessGroup,
+}
+
+/// Process group an execution runs in, once it has spawned one
+///
+/// Shared by the execution, which sets it whenever it spawns a process
+/// (a retried execution spawns one per attempt), and whatever may have to
+/// kill it.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessGroup(Arc<Mutex<Option<u32>>>);
+
+impl ProcessGroup {
+    /// Creates a handle with no process group yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the process group the execution runs in now
+    pub fn set(&self, process_group: Option<u32>) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = process_group;
+    }
+
+    /// Returns the process group the execution runs in now, if any
+    pub fn get(&self) -> Option<u32> {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Kills every process in the group, if there is one
+    pub fn kill(&self) {
+        if let Some(process_group) = self.get() {
+            kill_process_group(process_group);
+        }
+    }
+}
+
+/// Executions currently running in an executor
+#[derive(Debug, Default)]
+pub struct RunningExecutions {
+    running: Mutex<HashMap<Uuid, Execution>>,
+}
+
+impl RunningExecutions {
+    /// Creates an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an execution
+    ///
+    /// # Arguments
+    /// * `session_id` - Session the execution runs for
+    /// * `process_group` - Process group to kill on cancellation
+    ///
+    /// # Returns
+    /// Handle the execution's stream holds until it ends
+    pub fn register(
+        self: &Arc<Self>,
+        session_id: Uuid,
+        process_group: ProcessGroup,
+    ) -> RunningExecution {
+        let cancelled = Arc::new(Notify::new());
+        self.running().insert(
+            session_id,
+            Execution {
+                cancelled: cancelled.clone(),
+                process_group,
+            },
+        );
+        RunningExecution {
+            session_id,
+            cancelled,
+            executions: self.clone(),
+        }
+    }
+
+    /// Cancels an execution
+    ///
+    /// # Arguments
+    /// * `session_id` - Session whose execution to cancel
+    ///
+    /// # Returns
+    /// Ok(()) once it is told to stop, Err if nothing runs for the session
+    pub fn cancel(&self, session_id: Uuid) -> Result<(), FacetError> {
+        let running = self.running();
+        let execution = running
+            .get(&session_id)
+            .ok_or_else(|| FacetError::SessionNotFound(session_id.to_string()))?;
+
+        execution.process_group.kill();
+        // Stored as a permit if the stream isn't waiting right now
+        execution.cancelled.notify_one();
+
+        Ok(())
+    }
+
+    /// Returns whether an execution is running for a session
+    pub fn is_running(&self, session_id: Uuid) -> bool {
+        self.running().contains_key(&session_id)
+    }
+
+    fn running(&self) -> MutexGuard<'_, HashMap<Uuid, Execution>> {
+        self.running.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Registration of a running execution; unregisters it when dropped
+#[derive(Debug)]
+pub struct RunningExecution {
+    session_id: Uuid,
+    cancelled: Arc<Notify>,
+    executions: Arc<RunningExecutions>,
+}
+
+impl Drop for RunningExecution {
+    fn drop(&mut self) {
+        // Leave a later execution registered for the same session alone
+        let mut running = self.executions.running();
+        if running
+            .get(&self.session_id)
+            .is_some_and(|execution| Arc::ptr_eq(&execution.cancelled, &self.cancelled))
+        {
+            running.remove(&self.session_id);
+        }
+    }
+}
+
+/// Ends an execution's stream with a Cancelled event once it is cancelled
+///
+/// The inner stream is dropped first, which kills a process still running
+/// if it was spawned with `kill_on_drop`.
+///
+/// # Arguments
+/// * `events` - Events of the execution
+/// * `execution` - Registration of the execution
+///
+/// # Returns
+/// The events, up to cancellation
+pub fn until_cancelled<S>(
+    events: S,
+    execution: RunningExecution,
+) -> impl Stream<Item = Result<ClaudeEvent, FacetError>> + Send + 'static
+where
+    S: Stream<Item = Result<ClaudeEvent, FacetError>> + Send + Unpin + 'static,
+{
+    async_stream::stream! {
+        let mut events = events;
+        loop {
+            // Cancellation first: the killed process's last output races it
+            let next = tokio::select! {
+                biased;
+                _ = execution.cancelled.notified() => None,
+                next = events.next() => Some(next),
+            };
+            match next {
+                Some(Some(event)) => yield event,
+                Some(None) => break,
+                None => {
+                    drop(events);
+                    yield Ok(ClaudeEvent::Cancelled {
+                        session_id: execution.session_id,
+                    });
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn kill_process_group(process_group: u32) {
+    // SAFETY: killpg only sends a signal; a group that has already exited
+    // makes it fail with ESRCH, which is fine
+    unsafe {
+        libc::killpg(process_group as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(_process_group: u32) {
+    // No process groups: the process is killed when its stream is dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    #[tokio::test]
+    async fn test_cancelled_stream_ends_with_cancelled_event() {
+        let executions = Arc::new(RunningExecutions::new());
+        let session_id = Uuid::new_v4();
+        let execution = executions.register(session_id, ProcessGroup::new());
+
+        let first = stream::iter([Ok(ClaudeEvent::Content {
+            text: "first".to_string(),
+        })]);
+        let events = first.chain(stream::pending());
+        let mut events = Box::pin(until_cancelled(events, execution));
+
+        assert!(matches!(
+            events.next().await,
+            Some(Ok(ClaudeEvent::Content { .. }))
+        ));
+        executions.cancel(session_id).unwrap();
+        assert_eq!(
+            events.next().await.unwrap().unwrap(),
+            ClaudeEvent::Cancelled { session_id }
+        );
+        assert!(events.next().await.is_none());
+
+        // Unregistered once the stream is gone
+        drop(events);
+        assert!(!executions.is_running(session_id));
+        assert!(executions.cancel(session_id).is_err());
+    }
+
+    #[test]
+    fn test_dropping_replaced_registration_keeps_new_one() {
+        let executions = Arc::new(RunningExecutions::new());
+        let session_id = Uuid::new_v4();
+        let first = executions.register(session_id, ProcessGroup::new());
+        let second = executions.register(session_id, ProcessGroup::new());
+
+        drop(first);
+        assert!(executions.is_running(session_id));
+
+        drop(second);
+        assert!(!executions.is_running(session_id));
+    }
+}
@@ -1,16 +1,25 @@
 //! Real Claude CLI executor
 //!
 //! Spawns headless claude-cli processes and streams stdout/stderr events.
-//! Handles timeouts, process cleanup, and error recovery.
+//! Handles timeouts, cancellation, process cleanup, and error recovery.
 
+use crate::claude::cancel::{until_cancelled, ProcessGroup, RunningExecutions};
+use crate::claude::pool::{PoolConfig, ProcessPool};
+use crate::claude::retry::{with_retries, RetryPolicy};
+use crate::claude::stream_json;
+use crate::claude::timeouts::{with_timeouts, TimeoutPolicy, DEFAULT_IDLE_TIMEOUT_SECONDS};
+use crate::claude::usage::{UsageTracker, DEFAULT_OUTPUT_COST_PER_MILLION};
 use crate::claude::Executor;
 use crate::error::FacetError;
 use crate::models::{ClaudeEvent, FacetRequest};
 use async_stream::stream;
+use futures::stream::BoxStream;
 use futures::Stream;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
 use tokio::process::{Child, Command};
 use tokio::time::{timeout, Duration};
+use uuid::Uuid;
 
 /// Real Claude CLI executor
 ///
@@ -23,6 +32,21 @@ pub struct ClaudeExecutor {
     /// Default timeout for executions
     #[allow(dead_code)]
     default_timeout: Duration,
+
+    /// Idle timeout for requests that don't set one
+    idle_timeout: Duration,
+
+    /// How transient claude-cli failures are retried
+    retry_policy: RetryPolicy,
+
+    /// Warm processes for new conversations, if pooling is enabled
+    pool: Option<Arc<ProcessPool>>,
+
+    /// Price per million output tokens (USD) for live cost estimates
+    output_cost_per_million: f64,
+
+    /// Processes running, by session, so they can be cancelled
+    executions: Arc<RunningExecutions>,
 }
 
 impl ClaudeExecutor {
@@ -38,7 +62,69 @@ impl ClaudeExecutor {
         Self {
             binary_path,
             default_timeout: Duration::from_secs(timeout_seconds),
+            idle_timeout: Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECONDS),
+            retry_policy: RetryPolicy::default(),
+            pool: None,
+            output_cost_per_million: DEFAULT_OUTPUT_COST_PER_MILLION,
+            executions: Arc::new(RunningExecutions::new()),
+        }
+    }
+
+    /// Sets the output token price used for `UsageDelta` cost estimates
+    ///
+    /// # Arguments
+    /// * `usd_per_million` - Price in USD per million output tokens
+    ///
+    /// # Returns
+    /// Executor with the updated price
+    pub fn with_output_cost(mut self, usd_per_million: f64) -> Self {
+        self.output_cost_per_million = usd_per_million;
+        self
+    }
+
+    /// Sets how long claude-cli may go without output, for requests that
+    /// don't set it themselves
+    ///
+    /// # Arguments
+    /// * `seconds` - Idle timeout in seconds
+    ///
+    /// # Returns
+    /// Executor with the updated idle timeout
+    pub fn with_idle_timeout(mut self, seconds: u64) -> Self {
+        self.idle_timeout = Duration::from_secs(seconds);
+        self
+    }
+
+    /// Sets how transient claude-cli failures are retried
+    ///
+    /// # Arguments
+    /// * `policy` - Attempts and backoff; one attempt disables retries
+    ///
+    /// # Returns
+    /// Executor with the updated retry policy
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Keeps claude-cli processes warm for new conversations
+    ///
+    /// Spawns the pool's processes straight away; resumed conversations
+    /// still get a process of their own.
+    ///
+    /// # Arguments
+    /// * `config` - Size and recycling of the pool
+    ///
+    /// # Returns
+    /// Executor that runs requests in pooled processes
+    pub fn with_pool(mut self, config: PoolConfig) -> Self {
+        let pool = ProcessPool::new(self.binary_path.clone(), config);
+        if let Err(e) = pool.warm() {
+            // Requests spawn the processes missing when they need them
+            tracing::warn!("Failed to warm claude-cli pool: {}", e);
         }
+        self.pool = Some(Arc::new(pool));
+        self
     }
 
     /// Spawns a claude-cli process
@@ -70,48 +156,102 @@ impl ClaudeExecutor {
             })
     }
 
-    /// Parses a line of stdout/stderr into a ClaudeEvent
+    /// Builds the claude-cli command for a request
     ///
-    /// Attempts to parse as JSON event, falls back to plain text.
+    /// A new conversation is given the session's id, so later requests can
+    /// resume it by that id. The process leads a process group of its own,
+    /// so cancelling kills everything it started, and dies with its stream.
     ///
     /// # Arguments
-    /// * `line` - Output line from claude-cli
+    /// * `session_id` - Session the request runs in
+    /// * `resume` - Conversation to continue, if any
     ///
     /// # Returns
-    /// Parsed ClaudeEvent or Content event with the line
-    #[allow(dead_code)]
-    fn parse_output_line(&self, line: &str) -> ClaudeEvent {
-        // Try to parse as JSON event
-        if let Ok(event) = serde_json::from_str::<ClaudeEvent>(line) {
-            return event;
-        }
-
-        // Fallback to content event
-        ClaudeEvent::Content {
-            text: line.to_string(),
-        }
+    /// Command ready to spawn
+    fn command(&self, session_id: Uuid, resume: Option<&str>) -> Command {
+        let mut command = Command::new(&self.binary_path);
+        command.arg("--headless").arg("--stream");
+        match resume {
+            Some(conversation_id) => command.arg("--resume").arg(conversation_id),
+            None => command.arg("--session-id").arg(session_id.to_string()),
+        };
+        command
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        #[cfg(unix)]
+        command.process_group(0);
+        command
     }
-}
 
-#[async_trait::async_trait]
-impl Executor for ClaudeExecutor {
-    async fn execute(
+    /// Spawns claude-cli for a request and streams its events
+    ///
+    /// Transient failures are retried per the executor's retry policy;
+    /// timeouts and cancellation apply to all attempts together.
+    ///
+    /// # Arguments
+    /// * `request` - Request to execute
+    /// * `resume` - Conversation to continue, if any
+    ///
+    /// # Returns
+    /// Async stream of ClaudeEvent instances
+    fn run(
         &self,
         request: FacetRequest,
+        resume: Option<String>,
     ) -> Box<dyn Stream<Item = Result<ClaudeEvent, FacetError>> + Send + Unpin + 'static> {
         let session_id = request.session_id;
-        let binary_path = self.binary_path.clone();
+        let prompt = request.prompt;
+        let policy = TimeoutPolicy::for_request(&request.options, self.idle_timeout);
+        let process_group = ProcessGroup::new();
+        let execution = self.executions.register(session_id, process_group.clone());
 
-        // Spawn process before creating stream
-        let child_result = Command::new(&binary_path)
-            .arg("--headless")
-            .arg("--stream")
-            .stdin(std::process::Stdio::piped())
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .spawn();
+        let executor = self.clone();
+        let attempts = {
+            let process_group = process_group.clone();
+            move |_| {
+                // Whatever a failed attempt left running
+                process_group.kill();
+                match (&executor.pool, &resume) {
+                    (Some(pool), None) => executor.pooled_attempt(
+                        pool.clone(),
+                        session_id,
+                        prompt.clone(),
+                        process_group.clone(),
+                    ),
+                    _ => executor.attempt(session_id, resume.clone(), process_group.clone()),
+                }
+            }
+        };
+        let stream = with_retries(attempts, self.retry_policy);
+        let stream = with_timeouts(Box::pin(stream), policy, session_id, process_group);
+        Box::new(Box::pin(until_cancelled(Box::pin(stream), execution)))
+    }
 
-        let stream = stream! {
+    /// Runs claude-cli once for a request
+    ///
+    /// # Arguments
+    /// * `session_id` - Session the request runs in
+    /// * `resume` - Conversation to continue, if any
+    /// * `process_group` - Set to the process group of the spawned process
+    ///
+    /// # Returns
+    /// Async stream of ClaudeEvent instances, ending with Complete, or an
+    /// error if the process could not be run
+    fn attempt(
+        &self,
+        session_id: Uuid,
+        resume: Option<String>,
+        process_group: ProcessGroup,
+    ) -> BoxStream<'static, Result<ClaudeEvent, FacetError>> {
+        let binary_path = self.binary_path.clone();
+        let output_cost_per_million = self.output_cost_per_million;
+        let child_result = self.command(session_id, resume.as_deref()).spawn();
+        // Leading its own group, the process's id is the group's
+        process_group.set(child_result.as_ref().ok().and_then(Child::id));
+
+        Box::pin(stream! {
             // Check if spawn succeeded
             let mut child = match child_result {
                 Ok(child) => child,
@@ -135,6 +275,16 @@ impl Executor for ClaudeExecutor {
                 }
             };
 
+            // Collect stderr alongside, so a full pipe can't stall the
+            // process, to tell why it failed
+            let stderr = child.stderr.take().map(|stderr| {
+                tokio::spawn(async move {
+                    let mut output = String::new();
+                    let _ = BufReader::new(stderr).read_to_string(&mut output).await;
+                    output
+                })
+            });
+
             // Create buffered reader for stdout
             let reader = BufReader::new(stdout);
             let mut lines = reader.lines();
@@ -145,48 +295,47 @@ impl Executor for ClaudeExecutor {
                 drop(stdin);
             }
 
-            // Stream output lines with timeout
+            // Track token usage so clients can show a live cost ticker
+            let mut tracker = UsageTracker::new(output_cost_per_million);
+
+            // Stream output lines; timeouts are applied around the stream
             loop {
-                let line_result = timeout(Duration::from_secs(5), lines.next_line()).await;
-
-                match line_result {
-                    Ok(Ok(Some(line))) => {
-                        // Parse output line inline instead of using self
-                        let event = if let Ok(event) = serde_json::from_str::<ClaudeEvent>(&line) {
-                            event
-                        } else {
-                            ClaudeEvent::Content { text: line }
-                        };
-                        yield Ok(event);
+                match lines.next_line().await {
+                    Ok(Some(line)) => {
+                        for event in line_events(&line, &mut tracker) {
+                            yield Ok(event);
+                        }
                     }
-                    Ok(Ok(None)) => {
+                    Ok(None) => {
                         // EOF reached
                         break;
                     }
-                    Ok(Err(e)) => {
+                    Err(e) => {
                         yield Err(FacetError::ExecutionError(format!(
                             "Failed to read output: {}",
                             e
                         )));
                         break;
                     }
-                    Err(_) => {
-                        // Timeout on individual line read
-                        yield Err(FacetError::Timeout(
-                            "No output received within timeout".to_string()
-                        ));
-                        break;
-                    }
                 }
             }
 
+            // Final usage totals before completion
+            yield Ok(tracker.delta());
+
             // Wait for process to complete
             match timeout(Duration::from_secs(5), child.wait()).await {
                 Ok(Ok(status)) => {
+                    // Reaped, so its id may be reused by an unrelated group
+                    process_group.set(None);
                     if !status.success() {
+                        let stderr = match stderr {
+                            Some(stderr) => stderr.await.unwrap_or_default(),
+                            None => String::new(),
+                        };
                         yield Ok(ClaudeEvent::Error {
                             code: "PROCESS_FAILED".to_string(),
-                            message: format!("Claude process exited with status: {}", status),
+                            message: failure_message(&status.to_string(), &stderr),
                         });
                         yield Ok(ClaudeEvent::Complete {
                             session_id,
@@ -208,14 +357,164 @@ impl Executor for ClaudeExecutor {
                 Err(_) => {
                     // Kill the process if it hasn't finished
                     let _ = child.kill().await;
+                    process_group.set(None);
                     yield Err(FacetError::Timeout(
                         "Process did not complete within timeout".to_string()
                     ));
                 }
             }
-        };
+        })
+    }
+
+    /// Runs a request once in a pooled process
+    ///
+    /// The process goes back to the pool if the request completes cleanly
+    /// and is replaced otherwise.
+    ///
+    /// # Arguments
+    /// * `pool` - Pool to lease the process from
+    /// * `session_id` - Session the request runs in
+    /// * `prompt` - User's prompt
+    /// * `process_group` - Set to the process group of the leased process
+    ///
+    /// # Returns
+    /// Async stream of ClaudeEvent instances, ending with Complete, or an
+    /// error if no process could be leased
+    fn pooled_attempt(
+        &self,
+        pool: Arc<ProcessPool>,
+        session_id: Uuid,
+        prompt: String,
+        process_group: ProcessGroup,
+    ) -> BoxStream<'static, Result<ClaudeEvent, FacetError>> {
+        let output_cost_per_million = self.output_cost_per_million;
+
+        Box::pin(stream! {
+            let mut process = match pool.lease() {
+                Ok(lease) => lease,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            process_group.set(process.id());
+            if let Err(e) = process.send(session_id, &prompt).await {
+                yield Err(e);
+                return;
+            }
 
-        Box::new(Box::pin(stream))
+            let mut tracker = UsageTracker::new(output_cost_per_million);
+            loop {
+                match process.next_line().await {
+                    Ok(Some(line)) => {
+                        for event in line_events(&line, &mut tracker) {
+                            // End of the turn, and of the process's only conversation
+                            let status = match &event {
+                                ClaudeEvent::Complete { status, .. } => status.clone(),
+                                ClaudeEvent::Summary { is_error: false, .. } => "success".to_string(),
+                                ClaudeEvent::Summary { is_error: true, .. } => "failed".to_string(),
+                                _ => {
+                                    yield Ok(event);
+                                    continue;
+                                }
+                            };
+                            if let ClaudeEvent::Summary { .. } = event {
+                                yield Ok(event);
+                            }
+                            yield Ok(tracker.delta());
+                            // No longer this request's to kill; the pool replaces it
+                            process_group.set(None);
+                            drop(process);
+                            yield Ok(ClaudeEvent::Complete { session_id, status });
+                            return;
+                        }
+                    }
+                    Ok(None) => {
+                        yield Ok(tracker.delta());
+                        let (status, stderr) = process.wait().await;
+                        yield Ok(ClaudeEvent::Error {
+                            code: "PROCESS_FAILED".to_string(),
+                            message: failure_message(&status, &stderr),
+                        });
+                        yield Ok(ClaudeEvent::Complete {
+                            session_id,
+                            status: "failed".to_string(),
+                        });
+                        return;
+                    }
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Parses a line of claude-cli output, counting its tokens
+///
+/// # Arguments
+/// * `line` - Output line from claude-cli
+/// * `tracker` - Usage of the request so far
+///
+/// # Returns
+/// The line's events, followed by a usage update if one is due
+fn line_events(line: &str, tracker: &mut UsageTracker) -> Vec<ClaudeEvent> {
+    let mut events = stream_json::parse_line(line);
+    for event in &events {
+        match event {
+            // Prefer usage reported by claude-cli over estimates
+            ClaudeEvent::Usage { output_tokens, .. } => tracker.record_reported(*output_tokens),
+            ClaudeEvent::Content { text } | ClaudeEvent::Thinking { text } => {
+                tracker.record_text(text)
+            }
+            _ => {}
+        }
+    }
+    events.extend(tracker.poll_delta());
+    events
+}
+
+/// Message for a claude-cli process that exited unsuccessfully
+///
+/// # Arguments
+/// * `status` - Exit status, as displayed
+/// * `stderr` - What the process wrote to stderr
+///
+/// # Returns
+/// The status, followed by the last line of stderr if there is one
+fn failure_message(status: &str, stderr: &str) -> String {
+    match stderr
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+    {
+        Some(line) => format!("Claude process exited with status: {}: {}", status, line),
+        None => format!("Claude process exited with status: {}", status),
+    }
+}
+
+#[async_trait::async_trait]
+impl Executor for ClaudeExecutor {
+    async fn execute(
+        &self,
+        request: FacetRequest,
+    ) -> Box<dyn Stream<Item = Result<ClaudeEvent, FacetError>> + Send + Unpin + 'static> {
+        self.run(request, None)
+    }
+
+    async fn resume(
+        &self,
+        request: FacetRequest,
+        conversation_id: String,
+    ) -> Box<dyn Stream<Item = Result<ClaudeEvent, FacetError>> + Send + Unpin + 'static> {
+        self.run(request, Some(conversation_id))
+    }
+
+    async fn cancel(&self, session_id: Uuid) -> Result<(), FacetError> {
+        self.executions.cancel(session_id)
     }
 }
 
@@ -223,10 +522,10 @@ impl Executor for ClaudeExecutor {
 mod tests {
     use super::*;
     use crate::models::{
-        DomState, RequestContext, RequestOptions, Screenshot, ScreenshotMetadata, Viewport,
+        DomState, RequestContext, RequestOptions, Screenshot, ScreenshotMetadata, TimeoutKind,
+        Viewport,
     };
     use futures::StreamExt;
-    use uuid::Uuid;
 
     fn create_test_request() -> FacetRequest {
         FacetRequest {
@@ -263,32 +562,38 @@ mod tests {
         let executor = ClaudeExecutor::new("claude".to_string(), 300);
         assert_eq!(executor.binary_path, "claude");
         assert_eq!(executor.default_timeout.as_secs(), 300);
+        assert_eq!(
+            executor.output_cost_per_million,
+            DEFAULT_OUTPUT_COST_PER_MILLION
+        );
     }
 
     #[test]
-    fn test_parse_output_line_json() {
-        let executor = ClaudeExecutor::new("claude".to_string(), 300);
-        let json_line = r#"{"type":"content","text":"Hello"}"#;
-        let event = executor.parse_output_line(json_line);
-
-        if let ClaudeEvent::Content { text } = event {
-            assert_eq!(text, "Hello");
-        } else {
-            panic!("Expected Content event");
-        }
+    fn test_executor_with_output_cost() {
+        let executor = ClaudeExecutor::new("claude".to_string(), 300).with_output_cost(3.0);
+        assert_eq!(executor.output_cost_per_million, 3.0);
     }
 
     #[test]
-    fn test_parse_output_line_plain_text() {
+    fn test_command_starts_or_resumes_conversation() {
         let executor = ClaudeExecutor::new("claude".to_string(), 300);
-        let plain_line = "Plain text output";
-        let event = executor.parse_output_line(plain_line);
+        let session_id = Uuid::new_v4();
+        let args = |command: Command| -> Vec<String> {
+            command
+                .as_std()
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect()
+        };
 
-        if let ClaudeEvent::Content { text } = event {
-            assert_eq!(text, "Plain text output");
-        } else {
-            panic!("Expected Content event");
-        }
+        let fresh = args(executor.command(session_id, None));
+        assert_eq!(
+            fresh[2..],
+            ["--session-id".to_string(), session_id.to_string()]
+        );
+
+        let resumed = args(executor.command(session_id, Some("earlier")));
+        assert_eq!(resumed[2..], ["--resume", "earlier"]);
     }
 
     #[tokio::test]
@@ -317,6 +622,217 @@ mod tests {
         assert!(first.is_some());
     }
 
+    #[tokio::test]
+    async fn test_process_group_cleared_once_reaped() {
+        let executor = ClaudeExecutor::new("echo".to_string(), 30);
+        let process_group = ProcessGroup::new();
+
+        let mut stream = executor.attempt(Uuid::new_v4(), None, process_group.clone());
+        assert!(process_group.get().is_some());
+        while stream.next().await.is_some() {}
+
+        // Killing it now must not signal whatever reuses the id
+        assert_eq!(process_group.get(), None);
+    }
+
+    /// Writes a claude-cli stand-in that stays silent until killed,
+    /// whatever its arguments
+    #[cfg(unix)]
+    fn silent_binary(dir: &std::path::Path) -> String {
+        use std::os::unix::fs::PermissionsExt;
+        let binary = dir.join("claude");
+        std::fs::write(&binary, "#!/bin/sh\nsleep 30\n").unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        binary.to_string_lossy().into_owned()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancel_kills_process() {
+        let dir = tempfile::tempdir().unwrap();
+        let executor = ClaudeExecutor::new(silent_binary(dir.path()), 30);
+        let request = create_test_request();
+        let session_id = request.session_id;
+
+        let mut stream = executor.execute(request).await;
+        executor.cancel(session_id).await.unwrap();
+
+        let mut last = None;
+        while let Some(event) = stream.next().await {
+            last = Some(event);
+        }
+        assert_eq!(
+            last.unwrap().unwrap(),
+            ClaudeEvent::Cancelled { session_id }
+        );
+        assert!(executor.cancel(session_id).await.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_transient_failure_retried() {
+        use std::os::unix::fs::PermissionsExt;
+        // Rate limited the first time it runs, answers the second
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("attempted");
+        let binary = dir.path().join("claude");
+        let script = format!(
+            "#!/bin/sh\n\
+             if [ ! -f '{marker}' ]; then\n\
+             touch '{marker}'\n\
+             echo 'API Error: Rate limit exceeded' >&2\n\
+             exit 1\n\
+             fi\n\
+             echo '{{\"type\":\"content\",\"text\":\"answer\"}}'\n",
+            marker = marker.display()
+        );
+        std::fs::write(&binary, script).unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let executor = ClaudeExecutor::new(binary.to_string_lossy().into_owned(), 30)
+            .with_retry_policy(RetryPolicy {
+                max_attempts: 2,
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(10),
+            });
+
+        let events: Vec<ClaudeEvent> = executor
+            .execute(create_test_request())
+            .await
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+        assert!(events.iter().any(|event| matches!(
+            event,
+            ClaudeEvent::Retry { attempt: 2, reason, .. } if reason.contains("Rate limit exceeded")
+        )));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, ClaudeEvent::Error { .. })));
+        assert!(events.contains(&ClaudeEvent::Content {
+            text: "answer".to_string()
+        }));
+        assert!(matches!(
+            events.last(),
+            Some(ClaudeEvent::Complete { status, .. }) if status == "success"
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pooled_process_serves_one_conversation() {
+        use std::os::unix::fs::PermissionsExt;
+        // Answers every request line with its pid, staying up in between
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("claude");
+        let script = r#"#!/bin/sh
+while read -r request; do
+  echo "{\"type\":\"content\",\"text\":\"$$\"}"
+  echo '{"type":"complete","session_id":"00000000-0000-0000-0000-000000000000","status":"success"}'
+done
+"#;
+        std::fs::write(&binary, script).unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let executor = ClaudeExecutor::new(binary.to_string_lossy().into_owned(), 30)
+            .with_pool(PoolConfig { size: 1 });
+
+        let mut answers = Vec::new();
+        for _ in 0..2 {
+            let request = create_test_request();
+            let session_id = request.session_id;
+            let events: Vec<ClaudeEvent> = executor
+                .execute(request)
+                .await
+                .map(|event| event.unwrap())
+                .collect()
+                .await;
+            assert_eq!(
+                events.last(),
+                Some(&ClaudeEvent::Complete {
+                    session_id,
+                    status: "success".to_string()
+                })
+            );
+            answers.extend(events.into_iter().filter_map(|event| match event {
+                ClaudeEvent::Content { text } => Some(text),
+                _ => None,
+            }));
+        }
+
+        // Each answered by a process of its own, and another is warm
+        assert_eq!(answers.len(), 2);
+        assert_ne!(answers[0], answers[1]);
+        assert_eq!(executor.pool.as_ref().unwrap().idle_count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pooled_process_failure_includes_stderr() {
+        use std::os::unix::fs::PermissionsExt;
+        // Fails on its first request
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("claude");
+        let script = "#!/bin/sh\nread -r request\necho 'Invalid API key' >&2\nexit 1\n";
+        std::fs::write(&binary, script).unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let executor = ClaudeExecutor::new(binary.to_string_lossy().into_owned(), 30)
+            .with_pool(PoolConfig { size: 1 });
+
+        let events: Vec<ClaudeEvent> = executor
+            .execute(create_test_request())
+            .await
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+        assert!(events.contains(&ClaudeEvent::Error {
+            code: "PROCESS_FAILED".to_string(),
+            message: "Claude process exited with status: exit status: 1: Invalid API key"
+                .to_string(),
+        }));
+    }
+
+    #[test]
+    fn test_failure_message_includes_stderr() {
+        assert_eq!(
+            failure_message("exit status: 1", "warming up\nAPI Error: Overloaded\n\n"),
+            "Claude process exited with status: exit status: 1: API Error: Overloaded"
+        );
+        assert_eq!(
+            failure_message("exit status: 1", ""),
+            "Claude process exited with status: exit status: 1"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_silent_process_warned_then_timed_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let executor = ClaudeExecutor::new(silent_binary(dir.path()), 30).with_idle_timeout(1);
+        let mut request = create_test_request();
+        request.options.soft_timeout_percent = 50;
+        let session_id = request.session_id;
+
+        let events: Vec<ClaudeEvent> = executor
+            .execute(request)
+            .await
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+        assert!(matches!(
+            &events[0],
+            ClaudeEvent::Warning { code, .. } if code == "IDLE_TIMEOUT_APPROACHING"
+        ));
+        assert_eq!(
+            events[1..],
+            [ClaudeEvent::TimedOut {
+                session_id,
+                timeout: TimeoutKind::Idle,
+                after_seconds: 1,
+            }]
+        );
+        // Unregistered: nothing left to cancel
+        assert!(executor.cancel(session_id).await.is_err());
+    }
+
     // Note: Full integration tests with real claude-cli would require
     // the binary to be installed and properly configured
 }
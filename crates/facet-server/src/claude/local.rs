@@ -0,0 +1,230 @@
+//! Local model executor
+//!
+//! Runs requests on a model served by Ollama instead of claude-cli, for
+//! the simple ones a routing policy sends its way. The model has no tools
+//! and sees only the prompt and the user's intent, not the screenshots or
+//! the DOM; its answer streams back as Content events, and it costs nothing,
+//! so no usage is reported.
+
+use crate::claude::cancel::{until_cancelled, ProcessGroup, RunningExecutions};
+use crate::claude::timeouts::{with_timeouts, TimeoutPolicy, DEFAULT_IDLE_TIMEOUT_SECONDS};
+use crate::claude::Executor;
+use crate::error::FacetError;
+use crate::models::{ClaudeEvent, FacetRequest};
+use async_stream::stream;
+use facet_core::llm::{GenerationParams, LlmHandle, OllamaLlm};
+use futures::Stream;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Executor answering requests with a local model
+#[derive(Clone)]
+pub struct LocalModelExecutor {
+    llm: LlmHandle,
+
+    /// Most tokens an answer may take, whatever the request allows
+    max_tokens: usize,
+
+    /// Executions running, by session, so they can be cancelled
+    executions: Arc<RunningExecutions>,
+}
+
+impl std::fmt::Debug for LocalModelExecutor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalModelExecutor")
+            .field("max_tokens", &self.max_tokens)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LocalModelExecutor {
+    /// Creates an executor generating with a model
+    ///
+    /// # Arguments
+    /// * `llm` - Handle to the model
+    /// * `max_tokens` - Most tokens an answer may take
+    ///
+    /// # Returns
+    /// New LocalModelExecutor
+    pub fn new(llm: LlmHandle, max_tokens: usize) -> Self {
+        Self {
+            llm,
+            max_tokens,
+            executions: Arc::new(RunningExecutions::new()),
+        }
+    }
+
+    /// Creates an executor generating with a model served by Ollama
+    ///
+    /// Ollama is only contacted once a request comes in. Its blocking
+    /// client is built on the model's worker thread, as it cannot be on
+    /// the runtime's.
+    ///
+    /// # Arguments
+    /// * `model` - Ollama model, e.g. "llama3.2:3b"
+    /// * `base_url` - Ollama's URL, if not on its default port
+    /// * `max_tokens` - Most tokens an answer may take
+    ///
+    /// # Returns
+    /// New LocalModelExecutor
+    ///
+    /// # Errors
+    /// Returns FacetError::Internal if the model's worker thread cannot be
+    /// started
+    pub async fn ollama(
+        model: &str,
+        base_url: Option<&str>,
+        max_tokens: usize,
+    ) -> Result<Self, FacetError> {
+        let model = model.to_string();
+        let base_url = base_url.map(str::to_string);
+        let llm = LlmHandle::load(move || {
+            let llm = OllamaLlm::new(model);
+            Ok(match base_url {
+                Some(base_url) => llm.with_base_url(base_url),
+                None => llm,
+            })
+        })
+        .await
+        .map_err(|e| FacetError::Internal(format!("Failed to start local model: {}", e)))?;
+        Ok(Self::new(llm, max_tokens))
+    }
+}
+
+/// The prompt as the model gets it, after the user's intent if any
+fn model_prompt(request: &FacetRequest) -> String {
+    let intent = request.context.user_intent.trim();
+    if intent.is_empty() {
+        request.prompt.clone()
+    } else {
+        format!("The user's goal: {}\n\n{}", intent, request.prompt)
+    }
+}
+
+#[async_trait::async_trait]
+impl Executor for LocalModelExecutor {
+    async fn execute(
+        &self,
+        request: FacetRequest,
+    ) -> Box<dyn Stream<Item = Result<ClaudeEvent, FacetError>> + Send + Unpin + 'static> {
+        let session_id = request.session_id;
+        let policy = TimeoutPolicy::for_request(
+            &request.options,
+            tokio::time::Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECONDS),
+        );
+        let execution = self.executions.register(session_id, ProcessGroup::new());
+        let params = GenerationParams {
+            max_tokens: self.max_tokens.min(request.options.max_tokens as usize),
+            ..Default::default()
+        };
+
+        // Generation stops once the stream, and with it `pieces`, is dropped
+        let (tokens, mut pieces) = mpsc::unbounded_channel();
+        let llm = self.llm.clone();
+        let prompt = model_prompt(&request);
+        let generation =
+            tokio::spawn(async move { llm.generate_stream(&prompt, params, tokens).await });
+
+        let stream = stream! {
+            while let Some(text) = pieces.recv().await {
+                yield Ok(ClaudeEvent::Content { text });
+            }
+            match generation.await {
+                Ok(Ok(_)) => yield Ok(ClaudeEvent::Complete {
+                    session_id,
+                    status: "success".to_string(),
+                }),
+                Ok(Err(e)) => yield Err(FacetError::ExecutionError(format!(
+                    "Local model failed: {:#}",
+                    e
+                ))),
+                Err(e) => yield Err(FacetError::Internal(format!(
+                    "Local model task failed: {}",
+                    e
+                ))),
+            }
+        };
+
+        let stream = with_timeouts(Box::pin(stream), policy, session_id, ProcessGroup::new());
+        Box::new(Box::pin(until_cancelled(Box::pin(stream), execution)))
+    }
+
+    async fn cancel(&self, session_id: Uuid) -> Result<(), FacetError> {
+        self.executions.cancel(session_id)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::models::{DomState, RequestContext, RequestOptions};
+    use facet_core::llm::Llm;
+    use futures::StreamExt;
+
+    /// Model answering every prompt with the prompt it got, a word at a time
+    pub(crate) struct EchoLlm;
+
+    impl Llm for EchoLlm {
+        fn generate_stream(
+            &mut self,
+            prompt: &str,
+            _params: &GenerationParams,
+            on_token: &mut dyn FnMut(&str) -> anyhow::Result<()>,
+        ) -> anyhow::Result<String> {
+            for word in prompt.split_inclusive(' ') {
+                on_token(word)?;
+            }
+            Ok(prompt.to_string())
+        }
+
+        fn count_tokens(&self, text: &str) -> anyhow::Result<usize> {
+            Ok(text.split_whitespace().count())
+        }
+    }
+
+    pub(crate) fn echo_executor() -> LocalModelExecutor {
+        LocalModelExecutor::new(LlmHandle::spawn(EchoLlm).unwrap(), 256)
+    }
+
+    #[tokio::test]
+    async fn test_local_model_streams_answer() {
+        let request = FacetRequest {
+            session_id: Uuid::new_v4(),
+            context: RequestContext {
+                screenshots: vec![],
+                dom_state: DomState {
+                    accessible_tree: String::new(),
+                    interactive_elements: vec![],
+                },
+                user_intent: "Plan a trip".to_string(),
+            },
+            prompt: "Which day is best?".to_string(),
+            options: RequestOptions::default(),
+        };
+        let session_id = request.session_id;
+
+        let events: Vec<ClaudeEvent> = echo_executor()
+            .execute(request)
+            .await
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        let answer: String = events
+            .iter()
+            .filter_map(|event| match event {
+                ClaudeEvent::Content { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(answer, "The user's goal: Plan a trip\n\nWhich day is best?");
+        assert_eq!(
+            events.last(),
+            Some(&ClaudeEvent::Complete {
+                session_id,
+                status: "success".to_string()
+            })
+        );
+    }
+}
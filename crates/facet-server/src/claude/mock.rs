@@ -4,11 +4,15 @@
 //! without requiring claude-cli to be installed. Useful for rapid
 //! development and automated testing.
 
+use crate::claude::cancel::{until_cancelled, ProcessGroup, RunningExecutions};
+use crate::claude::timeouts::{with_timeouts, TimeoutPolicy, DEFAULT_IDLE_TIMEOUT_SECONDS};
 use crate::claude::Executor;
 use crate::error::FacetError;
 use crate::models::{ClaudeEvent, FacetRequest};
 use async_stream::stream;
-use futures::Stream;
+use futures::{stream, Stream, StreamExt};
+use std::sync::Arc;
+use uuid::Uuid;
 
 /// Mock executor that returns predefined responses
 ///
@@ -21,6 +25,9 @@ pub struct MockClaudeExecutor {
 
     /// Whether to simulate an error
     should_fail: bool,
+
+    /// Executions running, by session, so they can be cancelled
+    executions: Arc<RunningExecutions>,
 }
 
 impl MockClaudeExecutor {
@@ -32,6 +39,7 @@ impl MockClaudeExecutor {
         Self {
             event_delay_ms: 100,
             should_fail: false,
+            executions: Arc::new(RunningExecutions::new()),
         }
     }
 
@@ -46,6 +54,7 @@ impl MockClaudeExecutor {
         Self {
             event_delay_ms: delay_ms,
             should_fail: false,
+            executions: Arc::new(RunningExecutions::new()),
         }
     }
 
@@ -57,6 +66,7 @@ impl MockClaudeExecutor {
         Self {
             event_delay_ms: 100,
             should_fail: true,
+            executions: Arc::new(RunningExecutions::new()),
         }
     }
 }
@@ -76,6 +86,11 @@ impl Executor for MockClaudeExecutor {
         let delay_ms = self.event_delay_ms;
         let should_fail = self.should_fail;
         let session_id = request.session_id;
+        let policy = TimeoutPolicy::for_request(
+            &request.options,
+            tokio::time::Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECONDS),
+        );
+        let execution = self.executions.register(session_id, ProcessGroup::new());
 
         let stream = stream! {
             // Simulate processing delay
@@ -111,6 +126,7 @@ impl Executor for MockClaudeExecutor {
 
             // Emit tool use event
             yield Ok(ClaudeEvent::ToolUse {
+                id: Some("toolu_mock".to_string()),
                 tool: "cdp_command".to_string(),
                 params: serde_json::json!({
                     "command": "click",
@@ -125,6 +141,13 @@ impl Executor for MockClaudeExecutor {
                 text: "Mock: Task completed successfully".to_string(),
             });
 
+            // Emit usage totals
+            yield Ok(ClaudeEvent::UsageDelta {
+                tokens_so_far: 16,
+                estimated_cost_usd: 0.00024,
+                tokens_per_second: 40.0,
+            });
+
             tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
 
             // Emit complete event
@@ -134,7 +157,25 @@ impl Executor for MockClaudeExecutor {
             });
         };
 
-        Box::new(Box::pin(stream))
+        let stream = with_timeouts(Box::pin(stream), policy, session_id, ProcessGroup::new());
+        Box::new(Box::pin(until_cancelled(Box::pin(stream), execution)))
+    }
+
+    async fn resume(
+        &self,
+        request: FacetRequest,
+        conversation_id: String,
+    ) -> Box<dyn Stream<Item = Result<ClaudeEvent, FacetError>> + Send + Unpin + 'static> {
+        // Same events as a new conversation, after one naming the old one
+        let resumed = ClaudeEvent::Content {
+            text: format!("Mock: Resuming conversation {}", conversation_id),
+        };
+        let events = self.execute(request).await;
+        Box::new(stream::iter([Ok(resumed)]).chain(events))
+    }
+
+    async fn cancel(&self, session_id: Uuid) -> Result<(), FacetError> {
+        self.executions.cancel(session_id)
     }
 }
 
@@ -144,8 +185,6 @@ mod tests {
     use crate::models::{
         DomState, RequestContext, RequestOptions, Screenshot, ScreenshotMetadata, Viewport,
     };
-    use futures::StreamExt;
-    use uuid::Uuid;
 
     fn create_test_request() -> FacetRequest {
         FacetRequest {
@@ -247,6 +286,23 @@ mod tests {
         assert!(has_progress, "Should emit at least one Progress event");
     }
 
+    #[tokio::test]
+    async fn test_mock_executor_emits_usage_delta() {
+        let executor = MockClaudeExecutor::with_delay(10);
+        let request = create_test_request();
+
+        let mut stream = executor.execute(request).await;
+
+        let mut has_usage = false;
+        while let Some(result) = stream.next().await {
+            if let Ok(ClaudeEvent::UsageDelta { .. }) = result {
+                has_usage = true;
+            }
+        }
+
+        assert!(has_usage, "Should emit at least one UsageDelta event");
+    }
+
     #[tokio::test]
     async fn test_mock_executor_with_failure() {
         let executor = MockClaudeExecutor::with_failure();
@@ -297,6 +353,24 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_mock_executor_cancel() {
+        let executor = MockClaudeExecutor::with_delay(50);
+        let request = create_test_request();
+        let session_id = request.session_id;
+
+        let mut stream = executor.execute(request).await;
+        let first = stream.next().await.unwrap().unwrap();
+        assert!(matches!(first, ClaudeEvent::Content { .. }));
+
+        executor.cancel(session_id).await.unwrap();
+        assert_eq!(
+            stream.next().await.unwrap().unwrap(),
+            ClaudeEvent::Cancelled { session_id }
+        );
+        assert!(stream.next().await.is_none());
+    }
+
     #[test]
     fn test_mock_executor_new() {
         let executor = MockClaudeExecutor::new();
@@ -311,6 +385,27 @@ mod tests {
         assert!(!executor.should_fail);
     }
 
+    #[tokio::test]
+    async fn test_mock_executor_resume() {
+        let executor = MockClaudeExecutor::with_delay(10);
+        let request = create_test_request();
+
+        let events: Vec<ClaudeEvent> = executor
+            .resume(request, "earlier".to_string())
+            .await
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(
+            events[0],
+            ClaudeEvent::Content {
+                text: "Mock: Resuming conversation earlier".to_string()
+            }
+        );
+        assert!(matches!(events.last(), Some(ClaudeEvent::Complete { .. })));
+    }
+
     #[tokio::test]
     async fn test_mock_executor_session_id_preservation() {
         let executor = MockClaudeExecutor::with_delay(10);
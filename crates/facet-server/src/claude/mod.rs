@@ -2,15 +2,26 @@
 //!
 //! Provides interfaces for executing claude-cli processes and streaming results.
 
+pub mod cancel;
 pub mod executor;
+pub mod local;
 pub mod mock;
+pub mod pool;
+pub mod retry;
+pub mod router;
+pub mod stream_json;
+pub mod timeouts;
+pub mod usage;
 
 pub use executor::ClaudeExecutor;
+pub use local::LocalModelExecutor;
 pub use mock::MockClaudeExecutor;
+pub use router::{Router, RoutingPolicy};
 
 use crate::error::FacetError;
 use crate::models::{ClaudeEvent, FacetRequest};
 use futures::Stream;
+use uuid::Uuid;
 
 /// Trait for Claude CLI executors
 ///
@@ -39,4 +50,36 @@ pub trait Executor: Send + Sync {
         &self,
         request: FacetRequest,
     ) -> Box<dyn Stream<Item = Result<ClaudeEvent, FacetError>> + Send + Unpin + 'static>;
+
+    /// Executes a Facet request as the next turn of an earlier conversation
+    ///
+    /// Executors without conversations run the request on its own, as
+    /// `execute` does.
+    ///
+    /// # Arguments
+    /// * `request` - The validated Facet request to execute
+    /// * `conversation_id` - Conversation to continue, as claude-cli knows it
+    ///
+    /// # Returns
+    /// Async stream of ClaudeEvent instances
+    async fn resume(
+        &self,
+        request: FacetRequest,
+        conversation_id: String,
+    ) -> Box<dyn Stream<Item = Result<ClaudeEvent, FacetError>> + Send + Unpin + 'static> {
+        let _ = conversation_id;
+        self.execute(request).await
+    }
+
+    /// Cancels a running execution
+    ///
+    /// Kills the process (and any it started) and ends the execution's
+    /// stream with a Cancelled event.
+    ///
+    /// # Arguments
+    /// * `session_id` - Session whose execution to cancel
+    ///
+    /// # Returns
+    /// Ok(()) once it is told to stop, Err if nothing runs for the session
+    async fn cancel(&self, session_id: Uuid) -> Result<(), FacetError>;
 }
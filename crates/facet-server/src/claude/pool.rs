@@ -0,0 +1,382 @@
+//! Warm claude-cli process pool
+//!
+//! Spawning claude-cli and its auth handshake add seconds to every request.
+//! With a pool, the executor keeps processes running in interactive mode,
+//! ready before requests arrive: a request leases one, sends its prompt as
+//! a JSON line and reads events up to the turn's result. An interactive
+//! process remembers every turn it has run, so it serves a single
+//! conversation: once the request is done with it, however it ended, the
+//! process is killed and replaced by a fresh one.
+
+use crate::error::FacetError;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use uuid::Uuid;
+
+/// Size of a process pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolConfig {
+    /// Processes kept warm
+    pub size: usize,
+}
+
+/// A request as sent to an interactive claude-cli, one JSON line each
+#[derive(Debug, Serialize)]
+struct TurnRequest<'a> {
+    session_id: Uuid,
+    prompt: &'a str,
+}
+
+/// A running interactive claude-cli process
+#[derive(Debug)]
+pub struct PooledProcess {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Lines<BufReader<ChildStdout>>,
+    /// Collects stderr, so a full pipe can't stall the process, to tell why
+    /// it failed
+    stderr: Option<JoinHandle<String>>,
+}
+
+impl PooledProcess {
+    /// Returns the process's id, which is also its process group's
+    pub fn id(&self) -> Option<u32> {
+        self.child.id()
+    }
+
+    /// Sends a request to the process
+    ///
+    /// # Arguments
+    /// * `session_id` - Session the request runs in
+    /// * `prompt` - User's prompt
+    ///
+    /// # Errors
+    /// Returns FacetError::ExecutionError if the process can't be written to
+    pub async fn send(&mut self, session_id: Uuid, prompt: &str) -> Result<(), FacetError> {
+        let mut line = serde_json::to_string(&TurnRequest { session_id, prompt })
+            .map_err(|e| FacetError::Internal(format!("Failed to serialize request: {}", e)))?;
+        line.push('\n');
+
+        self.stdin
+            .write_all(line.as_bytes())
+            .await
+            .map_err(write_error)?;
+        self.stdin.flush().await.map_err(write_error)
+    }
+
+    /// Reads the next line of output
+    ///
+    /// # Returns
+    /// The line, or None once the process has closed its output
+    pub async fn next_line(&mut self) -> Result<Option<String>, FacetError> {
+        self.lines
+            .next_line()
+            .await
+            .map_err(|e| FacetError::ExecutionError(format!("Failed to read output: {}", e)))
+    }
+
+    /// Waits for a process that has closed its output to exit
+    ///
+    /// # Returns
+    /// Its exit status, as displayed, and what it wrote to stderr; gives up
+    /// on either after a few seconds
+    pub async fn wait(&mut self) -> (String, String) {
+        let status = match timeout(Duration::from_secs(5), self.child.wait()).await {
+            Ok(Ok(status)) => status.to_string(),
+            _ => "unknown".to_string(),
+        };
+        let stderr = match self.stderr.take() {
+            Some(stderr) => match timeout(Duration::from_secs(1), stderr).await {
+                Ok(Ok(output)) => output,
+                _ => String::new(),
+            },
+            None => String::new(),
+        };
+        (status, stderr)
+    }
+
+    fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+fn write_error(e: std::io::Error) -> FacetError {
+    FacetError::ExecutionError(format!("Failed to send request to claude-cli: {}", e))
+}
+
+/// Processes of a pool
+#[derive(Debug, Default)]
+struct PoolState {
+    /// Processes ready for a request, oldest first
+    idle: VecDeque<PooledProcess>,
+
+    /// Processes leased to requests
+    leased: usize,
+
+    /// Processes being spawned to fill the pool
+    starting: usize,
+}
+
+/// Pool of warm claude-cli processes
+#[derive(Debug)]
+pub struct ProcessPool {
+    binary_path: String,
+    config: PoolConfig,
+    state: Mutex<PoolState>,
+}
+
+impl ProcessPool {
+    /// Creates an empty pool; `warm` fills it
+    ///
+    /// # Arguments
+    /// * `binary_path` - Path to claude executable
+    /// * `config` - Size of the pool
+    ///
+    /// # Returns
+    /// New ProcessPool
+    pub fn new(binary_path: String, config: PoolConfig) -> Self {
+        Self {
+            binary_path,
+            config,
+            state: Mutex::new(PoolState::default()),
+        }
+    }
+
+    /// Spawns processes until the pool holds `size`, leased ones included
+    ///
+    /// Only waits for the processes to start, not for them to be ready:
+    /// claude-cli reads its first request once it is.
+    ///
+    /// # Errors
+    /// Returns FacetError::ClaudeUnavailable if a process fails to spawn
+    pub fn warm(&self) -> Result<(), FacetError> {
+        // Spawned without the lock, so leases aren't held up meanwhile
+        let mut missing = {
+            let mut state = self.state();
+            let held = state.idle.len() + state.leased + state.starting;
+            let missing = self.config.size.saturating_sub(held);
+            state.starting += missing;
+            missing
+        };
+        while missing > 0 {
+            let spawned = self.spawn();
+            let mut state = self.state();
+            match spawned {
+                Ok(process) => {
+                    state.idle.push_back(process);
+                    state.starting -= 1;
+                    missing -= 1;
+                }
+                Err(e) => {
+                    state.starting -= missing;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Takes a warm process for a request, or spawns one if none is left
+    ///
+    /// # Returns
+    /// Lease of the process, which has never served a request; dropping it
+    /// kills the process and refills the pool
+    ///
+    /// # Errors
+    /// Returns FacetError::ClaudeUnavailable if a process has to be spawned
+    /// and fails to
+    pub fn lease(self: &Arc<Self>) -> Result<Lease, FacetError> {
+        let idle = {
+            let mut state = self.state();
+            state.leased += 1;
+            loop {
+                let Some(mut process) = state.idle.pop_front() else {
+                    break None;
+                };
+                // Skip one that exited while idle, e.g. its auth handshake
+                // failed
+                if process.is_running() {
+                    break Some(process);
+                }
+            }
+        };
+        let process = match idle {
+            Some(process) => process,
+            // All busy: one more, beyond the pool's size, spawned without
+            // the lock
+            None => self.spawn().inspect_err(|_| {
+                let mut state = self.state();
+                state.leased = state.leased.saturating_sub(1);
+            })?,
+        };
+
+        Ok(Lease {
+            process: Some(process),
+            pool: self.clone(),
+        })
+    }
+
+    /// Returns the number of processes ready for a request
+    pub fn idle_count(&self) -> usize {
+        self.state().idle.len()
+    }
+
+    /// Kills a process whose lease has ended and spawns its replacement
+    ///
+    /// Never put back: the process holds its conversation's history.
+    fn retire(&self, process: PooledProcess) {
+        {
+            let mut state = self.state();
+            state.leased = state.leased.saturating_sub(1);
+        }
+        // Killed as it is dropped; spawning needs the runtime, which may
+        // be shutting down
+        drop(process);
+        if tokio::runtime::Handle::try_current().is_ok() {
+            if let Err(e) = self.warm() {
+                tracing::warn!("Failed to refill claude-cli pool: {}", e);
+            }
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Spawns an interactive claude-cli process in a process group of its
+    /// own, which dies with its handle
+    fn spawn(&self) -> Result<PooledProcess, FacetError> {
+        let mut command = Command::new(&self.binary_path);
+        command
+            .arg("--headless")
+            .arg("--stream")
+            .arg("--interactive")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        #[cfg(unix)]
+        command.process_group(0);
+
+        let mut child = command.spawn().map_err(|e| {
+            FacetError::ClaudeUnavailable(format!(
+                "Failed to spawn claude-cli: {} (binary: {})",
+                e, self.binary_path
+            ))
+        })?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(FacetError::ExecutionError(
+                "Failed to capture claude-cli stdin/stdout".to_string(),
+            ));
+        };
+
+        let stderr = child.stderr.take().map(|stderr| {
+            tokio::spawn(async move {
+                let mut output = String::new();
+                let _ = BufReader::new(stderr).read_to_string(&mut output).await;
+                output
+            })
+        });
+
+        Ok(PooledProcess {
+            child,
+            stdin,
+            lines: BufReader::new(stdout).lines(),
+            stderr,
+        })
+    }
+}
+
+/// A process leased from a pool for one request
+///
+/// Dropping the lease kills the process and replaces it.
+#[derive(Debug)]
+pub struct Lease {
+    process: Option<PooledProcess>,
+    pool: Arc<ProcessPool>,
+}
+
+impl Deref for Lease {
+    type Target = PooledProcess;
+
+    fn deref(&self) -> &PooledProcess {
+        self.process
+            .as_ref()
+            .expect("process is only taken on drop")
+    }
+}
+
+impl DerefMut for Lease {
+    fn deref_mut(&mut self) -> &mut PooledProcess {
+        self.process
+            .as_mut()
+            .expect("process is only taken on drop")
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        if let Some(process) = self.process.take() {
+            self.pool.retire(process);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// Pool of processes that echo each request back, whatever their
+    /// arguments; the directory holds the script they run
+    fn echo_pool(size: usize) -> (tempfile::TempDir, Arc<ProcessPool>) {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("claude");
+        std::fs::write(&binary, "#!/bin/sh\nexec cat\n").unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let binary = binary.to_string_lossy().into_owned();
+        let pool = ProcessPool::new(binary, PoolConfig { size });
+        (dir, Arc::new(pool))
+    }
+
+    #[tokio::test]
+    async fn test_lease_takes_warm_process() {
+        let (_dir, pool) = echo_pool(1);
+        pool.warm().unwrap();
+        assert_eq!(pool.idle_count(), 1);
+
+        let mut lease = pool.lease().unwrap();
+        assert_eq!(pool.idle_count(), 0);
+        lease.send(Uuid::new_v4(), "hello").await.unwrap();
+        let line = lease.next_line().await.unwrap().unwrap();
+        assert!(line.contains("\"prompt\":\"hello\""));
+
+        // Refilled once the request is done
+        drop(lease);
+        assert_eq!(pool.idle_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_process_never_reused_across_sessions() {
+        let (_dir, pool) = echo_pool(2);
+        pool.warm().unwrap();
+
+        let mut served = Vec::new();
+        for _ in 0..4 {
+            let mut lease = pool.lease().unwrap();
+            let id = lease.id().unwrap();
+            assert!(!served.contains(&id), "process {} leased twice", id);
+            lease.send(Uuid::new_v4(), "hello").await.unwrap();
+            lease.next_line().await.unwrap().unwrap();
+            served.push(id);
+        }
+        assert_eq!(pool.idle_count(), 2);
+    }
+}
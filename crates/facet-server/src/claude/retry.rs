@@ -0,0 +1,291 @@
+//! Retries of transient claude-cli failures
+//!
+//! An attempt that fails with what looks like a transient error (rate
+//! limits, overload, network trouble) is run again after an exponentially
+//! growing delay, announced to the client with a Retry event. Only attempts
+//! that failed before producing any content or tool use are retried, so the
+//! client never sees output twice; later failures surface as they are.
+
+use crate::error::FacetError;
+use crate::models::ClaudeEvent;
+use futures::{Stream, StreamExt};
+use tokio::time::Duration;
+
+/// Error codes claude-cli reports for transient failures
+const TRANSIENT_CODES: &[&str] = &[
+    "rate_limit_error",
+    "overloaded_error",
+    "RATE_LIMITED",
+    "OVERLOADED",
+    "NETWORK_ERROR",
+];
+
+/// Phrases in error messages that mark a failure as transient
+const TRANSIENT_PATTERNS: &[&str] = &[
+    "rate limit",
+    "too many requests",
+    "overloaded",
+    "connection reset",
+    "connection refused",
+    "econnreset",
+    "etimedout",
+    "network error",
+    "temporarily unavailable",
+    "service unavailable",
+];
+
+/// How often and how soon failed attempts are retried
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts in total, the first included; 1 disables retries
+    pub max_attempts: u32,
+
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+
+    /// Longest delay between attempts
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retrying a failed attempt
+    ///
+    /// Doubles with every attempt, up to `max_backoff`.
+    ///
+    /// # Arguments
+    /// * `failed_attempt` - Number of the attempt that failed, from 1
+    ///
+    /// # Returns
+    /// How long to wait before the next attempt
+    pub fn backoff(&self, failed_attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(failed_attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Classifies an attempt's event as a transient failure
+///
+/// # Arguments
+/// * `result` - Event or error from an attempt
+///
+/// # Returns
+/// Why the attempt is worth retrying, or None if it isn't
+pub fn retry_reason(result: &Result<ClaudeEvent, FacetError>) -> Option<String> {
+    match result {
+        Ok(ClaudeEvent::Error { code, message }) if is_transient(code, message) => {
+            Some(message.clone())
+        }
+        Err(FacetError::ExecutionError(message)) if is_transient("", message) => {
+            Some(message.clone())
+        }
+        _ => None,
+    }
+}
+
+fn is_transient(code: &str, message: &str) -> bool {
+    let message = message.to_lowercase();
+    TRANSIENT_CODES.contains(&code)
+        || TRANSIENT_PATTERNS
+            .iter()
+            .any(|pattern| message.contains(pattern))
+}
+
+/// Whether an event shows the client output it must not see twice
+fn is_output(event: &ClaudeEvent) -> bool {
+    matches!(
+        event,
+        ClaudeEvent::Content { .. }
+            | ClaudeEvent::Thinking { .. }
+            | ClaudeEvent::ToolUse { .. }
+            | ClaudeEvent::ToolResult { .. }
+    )
+}
+
+/// Runs attempts until one doesn't fail transiently or attempts run out
+///
+/// The rest of a failed attempt's stream is dropped with it, which kills
+/// its process if it was spawned with `kill_on_drop`.
+///
+/// # Arguments
+/// * `attempt` - Starts attempt number n (from 1), returning its events
+/// * `policy` - How often and how soon to retry
+///
+/// # Returns
+/// Events of the attempts, with a Retry event before each retry
+pub fn with_retries<F, S>(
+    mut attempt: F,
+    policy: RetryPolicy,
+) -> impl Stream<Item = Result<ClaudeEvent, FacetError>> + Send + 'static
+where
+    F: FnMut(u32) -> S + Send + 'static,
+    S: Stream<Item = Result<ClaudeEvent, FacetError>> + Send + 'static,
+{
+    async_stream::stream! {
+        let max_attempts = policy.max_attempts.max(1);
+        let mut number = 1;
+        loop {
+            let mut events = Box::pin(attempt(number));
+            let mut produced_output = false;
+            let mut retry = None;
+
+            while let Some(result) = events.next().await {
+                if !produced_output && number < max_attempts {
+                    if let Some(reason) = retry_reason(&result) {
+                        retry = Some(reason);
+                        break;
+                    }
+                }
+                if matches!(&result, Ok(event) if is_output(event)) {
+                    produced_output = true;
+                }
+                yield result;
+            }
+            drop(events);
+
+            let Some(reason) = retry else {
+                break;
+            };
+            let delay = policy.backoff(number);
+            tracing::warn!(
+                "claude-cli attempt {} of {} failed, retrying in {:?}: {}",
+                number,
+                max_attempts,
+                delay,
+                reason
+            );
+            number += 1;
+            yield Ok(ClaudeEvent::Retry {
+                attempt: number,
+                max_attempts,
+                delay_ms: delay.as_millis() as u64,
+                reason,
+            });
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    fn rate_limited() -> Result<ClaudeEvent, FacetError> {
+        Ok(ClaudeEvent::Error {
+            code: "rate_limit_error".to_string(),
+            message: "Rate limit exceeded".to_string(),
+        })
+    }
+
+    fn content(text: &str) -> Result<ClaudeEvent, FacetError> {
+        Ok(ClaudeEvent::Content {
+            text: text.to_string(),
+        })
+    }
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+        }
+    }
+
+    async fn collect<S>(events: S) -> Vec<ClaudeEvent>
+    where
+        S: Stream<Item = Result<ClaudeEvent, FacetError>>,
+    {
+        events.map(|event| event.unwrap()).collect().await
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(10), Duration::from_secs(30));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_retry_classification() {
+        assert!(retry_reason(&rate_limited()).is_some());
+        assert!(retry_reason(&Ok(ClaudeEvent::Error {
+            code: "PROCESS_FAILED".to_string(),
+            message: "exited with status 1: API Error: 529 Overloaded".to_string(),
+        }))
+        .is_some());
+        assert!(retry_reason(&Ok(ClaudeEvent::Error {
+            code: "PROCESS_FAILED".to_string(),
+            message: "exited with status 1: invalid API key".to_string(),
+        }))
+        .is_none());
+        assert!(retry_reason(&Err(FacetError::ClaudeUnavailable(
+            "binary not found".to_string()
+        )))
+        .is_none());
+        assert!(retry_reason(&content("rate limit")).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_transient_failure_is_retried() {
+        let events = with_retries(
+            |attempt| match attempt {
+                1 => stream::iter(vec![rate_limited(), content("ignored")]),
+                _ => stream::iter(vec![content("answer")]),
+            },
+            policy(3),
+        );
+        let events = collect(events).await;
+
+        assert!(matches!(
+            events[0],
+            ClaudeEvent::Retry {
+                attempt: 2,
+                max_attempts: 3,
+                delay_ms: 1,
+                ..
+            }
+        ));
+        assert_eq!(events[1..], [content("answer").unwrap()]);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let events = collect(with_retries(
+            |_| stream::iter(vec![rate_limited()]),
+            policy(2),
+        ))
+        .await;
+
+        assert!(matches!(events[0], ClaudeEvent::Retry { attempt: 2, .. }));
+        assert_eq!(events[1..], [rate_limited().unwrap()]);
+    }
+
+    #[tokio::test]
+    async fn test_failure_after_output_is_not_retried() {
+        let events = collect(with_retries(
+            |_| stream::iter(vec![content("partial"), rate_limited()]),
+            policy(3),
+        ))
+        .await;
+
+        assert_eq!(
+            events,
+            vec![content("partial").unwrap(), rate_limited().unwrap()]
+        );
+    }
+}
@@ -0,0 +1,480 @@
+//! Routing requests between backends
+//!
+//! A routing policy, read from a TOML file, names the backends requests
+//! can go to and the rules choosing between them: the first rule whose
+//! conditions a request meets picks its backend, and requests no rule
+//! matches go to the default. Conditions look at the request's size, at
+//! whether it needs tools, at how private it is and at its cost budget, so
+//! a cheap local model can take the simple requests and claude-cli the
+//! rest:
+//!
+//! ```toml
+//! default = "claude"
+//!
+//! [backends.local]
+//! kind = "ollama"
+//! model = "llama3.2:3b"
+//!
+//! # Private requests never leave the machine
+//! [[rules]]
+//! backend = "local"
+//! privacy = "private"
+//!
+//! # Short questions without tools or more than one screenshot
+//! [[rules]]
+//! backend = "local"
+//! needs_tools = false
+//! max_prompt_chars = 2000
+//! max_screenshots = 1
+//! ```
+//!
+//! The server's own claude-cli executor is always there as `claude`.
+//! Resumed conversations go to it whatever the rules say: only claude-cli
+//! keeps them.
+
+use crate::claude::local::LocalModelExecutor;
+use crate::claude::Executor;
+use crate::error::FacetError;
+use crate::models::{ClaudeEvent, FacetRequest, PrivacyLevel};
+use async_stream::stream;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use uuid::Uuid;
+
+/// Name of the server's claude-cli executor in a routing policy
+pub const CLAUDE_BACKEND: &str = "claude";
+
+/// A backend a routing policy can send requests to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum BackendConfig {
+    /// A model served by Ollama
+    Ollama {
+        /// Ollama model, e.g. "llama3.2:3b"
+        model: String,
+
+        /// Ollama's URL, if not on its default port
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        base_url: Option<String>,
+
+        /// Most tokens an answer may take
+        #[serde(default = "default_local_max_tokens")]
+        max_tokens: usize,
+    },
+}
+
+fn default_local_max_tokens() -> usize {
+    1024
+}
+
+/// A rule of a routing policy; every condition set must hold for the rule
+/// to match
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RouteRule {
+    /// Backend matching requests go to
+    pub backend: String,
+
+    /// Longest prompt, in characters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_prompt_chars: Option<usize>,
+
+    /// Most screenshots
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_screenshots: Option<usize>,
+
+    /// Whether the request needs tools; requests that don't say are
+    /// taken to need them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub needs_tools: Option<bool>,
+
+    /// Least sensitive privacy level matched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub privacy: Option<PrivacyLevel>,
+
+    /// Largest cost budget matched, in USD; requests without a budget
+    /// don't match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_budget_usd: Option<f64>,
+}
+
+impl RouteRule {
+    /// Returns whether a request meets every condition of the rule
+    pub fn matches(&self, request: &FacetRequest) -> bool {
+        let options = &request.options;
+        self.max_prompt_chars
+            .is_none_or(|max| request.prompt.chars().count() <= max)
+            && self
+                .max_screenshots
+                .is_none_or(|max| request.context.screenshots.len() <= max)
+            && self
+                .needs_tools
+                .is_none_or(|needs_tools| options.needs_tools.unwrap_or(true) == needs_tools)
+            && self
+                .privacy
+                .is_none_or(|privacy| options.privacy >= privacy)
+            && self
+                .max_budget_usd
+                .is_none_or(|max| options.max_cost_usd.is_some_and(|budget| budget <= max))
+    }
+}
+
+/// Which backend each request goes to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RoutingPolicy {
+    /// Backend of requests no rule matches
+    #[serde(default = "default_backend")]
+    pub default: String,
+
+    /// Backends besides `claude`, by name
+    #[serde(default)]
+    pub backends: BTreeMap<String, BackendConfig>,
+
+    /// Rules, the first match winning
+    #[serde(default)]
+    pub rules: Vec<RouteRule>,
+}
+
+fn default_backend() -> String {
+    CLAUDE_BACKEND.to_string()
+}
+
+impl Default for RoutingPolicy {
+    fn default() -> Self {
+        Self {
+            default: default_backend(),
+            backends: BTreeMap::new(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl RoutingPolicy {
+    /// Loads a routing policy from a TOML file
+    ///
+    /// # Arguments
+    /// * `path` - Path to the policy file
+    ///
+    /// # Returns
+    /// Loaded and validated policy
+    ///
+    /// # Errors
+    /// Returns FacetError::Config if the file cannot be read or parsed, or
+    /// names a backend it doesn't define
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, FacetError> {
+        let contents = std::fs::read_to_string(path.as_ref())
+            .map_err(|e| FacetError::Config(format!("Failed to read routing policy: {}", e)))?;
+
+        let policy: RoutingPolicy = toml::from_str(&contents)
+            .map_err(|e| FacetError::Config(format!("Failed to parse routing policy: {}", e)))?;
+
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// Checks that the default and every rule name a defined backend
+    ///
+    /// # Errors
+    /// Returns FacetError::Config naming the first undefined backend
+    pub fn validate(&self) -> Result<(), FacetError> {
+        let backends = std::iter::once(&self.default).chain(self.rules.iter().map(|r| &r.backend));
+        for backend in backends {
+            if backend != CLAUDE_BACKEND && !self.backends.contains_key(backend) {
+                return Err(FacetError::Config(format!(
+                    "Routing policy names undefined backend: {}",
+                    backend
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Chooses the backend of a request
+    ///
+    /// # Arguments
+    /// * `request` - Request to route
+    ///
+    /// # Returns
+    /// Name of the backend of the first matching rule, or the default
+    pub fn route(&self, request: &FacetRequest) -> &str {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(request))
+            .map_or(&self.default, |rule| &rule.backend)
+    }
+}
+
+/// Executions running, by session, with the backend each runs on
+type Routes = Arc<Mutex<HashMap<Uuid, Arc<dyn Executor>>>>;
+
+/// Executor handing each request to the backend its policy chooses
+pub struct Router {
+    policy: RoutingPolicy,
+    backends: HashMap<String, Arc<dyn Executor>>,
+    routes: Routes,
+}
+
+impl Router {
+    /// Creates a router, starting the backends its policy defines
+    ///
+    /// # Arguments
+    /// * `policy` - Which backend each request goes to
+    /// * `claude` - The server's claude-cli executor
+    ///
+    /// # Returns
+    /// New Router
+    ///
+    /// # Errors
+    /// Returns FacetError::Config if the policy names an undefined
+    /// backend, or the error of a backend that fails to start
+    pub async fn new(policy: RoutingPolicy, claude: Arc<dyn Executor>) -> Result<Self, FacetError> {
+        policy.validate()?;
+        let mut backends = HashMap::new();
+        backends.insert(CLAUDE_BACKEND.to_string(), claude);
+        for (name, backend) in &policy.backends {
+            let executor: Arc<dyn Executor> = match backend {
+                BackendConfig::Ollama {
+                    model,
+                    base_url,
+                    max_tokens,
+                } => Arc::new(
+                    LocalModelExecutor::ollama(model, base_url.as_deref(), *max_tokens).await?,
+                ),
+            };
+            backends.insert(name.clone(), executor);
+        }
+
+        Ok(Self {
+            policy,
+            backends,
+            routes: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Replaces a backend, or adds one rules can name
+    ///
+    /// # Arguments
+    /// * `name` - Name rules know the backend by
+    /// * `executor` - Executor of the backend
+    ///
+    /// # Returns
+    /// Router sending `name`'s requests to `executor`
+    pub fn with_backend(mut self, name: &str, executor: Arc<dyn Executor>) -> Self {
+        self.backends.insert(name.to_string(), executor);
+        self
+    }
+
+    /// Runs a request's events through the router, which remembers the
+    /// backend running it until its stream is dropped
+    fn track(
+        &self,
+        session_id: Uuid,
+        backend: Arc<dyn Executor>,
+        mut events: Box<dyn Stream<Item = Result<ClaudeEvent, FacetError>> + Send + Unpin>,
+    ) -> Box<dyn Stream<Item = Result<ClaudeEvent, FacetError>> + Send + Unpin + 'static> {
+        lock(&self.routes).insert(session_id, backend);
+        let route = Route {
+            session_id,
+            routes: self.routes.clone(),
+        };
+        Box::new(Box::pin(stream! {
+            let _route = route;
+            while let Some(event) = events.next().await {
+                yield event;
+            }
+        }))
+    }
+
+    /// Returns a backend by name, falling back to claude-cli
+    fn backend(&self, name: &str) -> Arc<dyn Executor> {
+        self.backends
+            .get(name)
+            .or_else(|| self.backends.get(CLAUDE_BACKEND))
+            .cloned()
+            .expect("claude backend is always registered")
+    }
+}
+
+/// A request's entry in the router's routes, removed when dropped
+struct Route {
+    session_id: Uuid,
+    routes: Routes,
+}
+
+impl Drop for Route {
+    fn drop(&mut self) {
+        lock(&self.routes).remove(&self.session_id);
+    }
+}
+
+fn lock(routes: &Routes) -> MutexGuard<'_, HashMap<Uuid, Arc<dyn Executor>>> {
+    routes.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[async_trait::async_trait]
+impl Executor for Router {
+    async fn execute(
+        &self,
+        request: FacetRequest,
+    ) -> Box<dyn Stream<Item = Result<ClaudeEvent, FacetError>> + Send + Unpin + 'static> {
+        let name = self.policy.route(&request);
+        tracing::debug!("Routing session {} to {}", request.session_id, name);
+        let session_id = request.session_id;
+        let backend = self.backend(name);
+        let events = backend.execute(request).await;
+        self.track(session_id, backend, events)
+    }
+
+    async fn resume(
+        &self,
+        request: FacetRequest,
+        conversation_id: String,
+    ) -> Box<dyn Stream<Item = Result<ClaudeEvent, FacetError>> + Send + Unpin + 'static> {
+        let session_id = request.session_id;
+        let backend = self.backend(CLAUDE_BACKEND);
+        let events = backend.resume(request, conversation_id).await;
+        self.track(session_id, backend, events)
+    }
+
+    async fn cancel(&self, session_id: Uuid) -> Result<(), FacetError> {
+        let backend = lock(&self.routes)
+            .get(&session_id)
+            .cloned()
+            .ok_or_else(|| FacetError::SessionNotFound(session_id.to_string()))?;
+        backend.cancel(session_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::local::tests::echo_executor;
+    use crate::claude::MockClaudeExecutor;
+    use crate::models::{DomState, RequestContext, RequestOptions};
+
+    const POLICY: &str = r#"
+[backends.local]
+kind = "ollama"
+model = "llama3.2:3b"
+
+[[rules]]
+backend = "local"
+privacy = "private"
+
+[[rules]]
+backend = "local"
+needs_tools = false
+max_prompt_chars = 20
+max_screenshots = 1
+
+[[rules]]
+backend = "local"
+max_budget_usd = 0.001
+"#;
+
+    fn request(prompt: &str, options: RequestOptions) -> FacetRequest {
+        FacetRequest {
+            session_id: Uuid::new_v4(),
+            context: RequestContext {
+                screenshots: vec![],
+                dom_state: DomState {
+                    accessible_tree: String::new(),
+                    interactive_elements: vec![],
+                },
+                user_intent: String::new(),
+            },
+            prompt: prompt.to_string(),
+            options,
+        }
+    }
+
+    #[test]
+    fn test_policy_routes_by_request() {
+        let policy: RoutingPolicy = toml::from_str(POLICY).unwrap();
+        policy.validate().unwrap();
+        let route = |prompt: &str, options| policy.route(&request(prompt, options)).to_string();
+
+        // Needs tools unless it says otherwise
+        assert_eq!(route("What time is it?", Default::default()), "claude");
+        let no_tools = RequestOptions {
+            needs_tools: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(route("What time is it?", no_tools.clone()), "local");
+        assert_eq!(
+            route("Summarize the whole of this long page", no_tools),
+            "claude"
+        );
+        let private = RequestOptions {
+            privacy: PrivacyLevel::Private,
+            ..Default::default()
+        };
+        assert_eq!(route("Fill in my tax form", private), "local");
+        let budget = |max_cost_usd| RequestOptions {
+            max_cost_usd: Some(max_cost_usd),
+            ..Default::default()
+        };
+        assert_eq!(route("Fill in my tax form", budget(0.0005)), "local");
+        assert_eq!(route("Fill in my tax form", budget(0.5)), "claude");
+    }
+
+    #[test]
+    fn test_policy_rejects_undefined_backend() {
+        let policy = RoutingPolicy {
+            rules: vec![RouteRule {
+                backend: "gpt".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert!(matches!(policy.validate(), Err(FacetError::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn test_router_runs_and_cancels_on_chosen_backend() {
+        let policy: RoutingPolicy = toml::from_str(POLICY).unwrap();
+        let claude = Arc::new(MockClaudeExecutor::with_delay(50));
+        let router = Router::new(policy, claude)
+            .await
+            .unwrap()
+            .with_backend("local", Arc::new(echo_executor()));
+
+        let private = RequestOptions {
+            privacy: PrivacyLevel::Private,
+            ..Default::default()
+        };
+        let events: Vec<ClaudeEvent> = router
+            .execute(request("Hello there", private))
+            .await
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(
+            events[0],
+            ClaudeEvent::Content {
+                text: "Hello ".to_string()
+            }
+        );
+        assert!(lock(&router.routes).is_empty());
+
+        let request = request("Book a table", RequestOptions::default());
+        let session_id = request.session_id;
+        let mut events = router.execute(request).await;
+        let first = events.next().await.unwrap().unwrap();
+        assert_eq!(
+            first,
+            ClaudeEvent::Content {
+                text: "Mock: Analyzing screenshot...".to_string()
+            }
+        );
+        router.cancel(session_id).await.unwrap();
+        assert_eq!(
+            events.next().await.unwrap().unwrap(),
+            ClaudeEvent::Cancelled { session_id }
+        );
+        drop(events);
+        assert!(router.cancel(session_id).await.is_err());
+    }
+}
@@ -0,0 +1,355 @@
+//! Parsing of claude-cli stream-json output
+//!
+//! claude-cli streams one JSON message per line: assistant messages made of
+//! text, thinking and tool_use blocks, user messages carrying tool results,
+//! and a result summing up the turn. Each line becomes typed ClaudeEvents
+//! here, so nothing downstream has to look into raw JSON. Lines already in
+//! Facet's own event format pass through as they are, messages of types
+//! not handled here are skipped, and anything that isn't JSON is content.
+
+use crate::models::ClaudeEvent;
+use serde::Deserialize;
+
+/// A line of stream-json output
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamMessage {
+    /// Session setup: tools, model and the like
+    System {},
+
+    Assistant {
+        message: Message,
+    },
+
+    /// Input fed back to Claude, tool results included
+    User {
+        message: Message,
+    },
+
+    /// End of the turn
+    Result {
+        #[serde(default)]
+        subtype: String,
+        #[serde(default)]
+        is_error: bool,
+        #[serde(default)]
+        result: Option<String>,
+        #[serde(default)]
+        duration_ms: Option<u64>,
+        #[serde(default)]
+        total_cost_usd: Option<f64>,
+        #[serde(default)]
+        usage: Option<UsageBlock>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    #[serde(default)]
+    content: MessageContent,
+    #[serde(default)]
+    usage: Option<UsageBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum MessageContent {
+    /// A plain prompt; read only to tell it apart from blocks
+    #[allow(dead_code)]
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+impl Default for MessageContent {
+    fn default() -> Self {
+        MessageContent::Blocks(Vec::new())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        text: String,
+    },
+    Thinking {
+        thinking: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        #[serde(default)]
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        #[serde(default)]
+        content: Option<ToolResultContent>,
+        #[serde(default)]
+        is_error: bool,
+    },
+    /// Redacted thinking, images and whatever else clients can't show
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ToolResultContent {
+    Text(String),
+    Blocks(Vec<ToolResultBlock>),
+}
+
+impl ToolResultContent {
+    /// Text of the result, blocks joined by newlines
+    fn into_text(self) -> String {
+        match self {
+            ToolResultContent::Text(text) => text,
+            ToolResultContent::Blocks(blocks) => blocks
+                .into_iter()
+                .filter_map(|block| match block {
+                    ToolResultBlock::Text { text } => Some(text),
+                    ToolResultBlock::Other => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ToolResultBlock {
+    Text {
+        text: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct UsageBlock {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+    #[serde(default)]
+    cache_creation_input_tokens: u64,
+    #[serde(default)]
+    cache_read_input_tokens: u64,
+}
+
+impl From<UsageBlock> for ClaudeEvent {
+    fn from(usage: UsageBlock) -> Self {
+        ClaudeEvent::Usage {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            cache_creation_input_tokens: usage.cache_creation_input_tokens,
+            cache_read_input_tokens: usage.cache_read_input_tokens,
+        }
+    }
+}
+
+/// Parses a line of claude-cli output into events
+///
+/// # Arguments
+/// * `line` - Output line from claude-cli
+///
+/// # Returns
+/// The line's events, in order; none for blank lines and messages that
+/// carry nothing for clients
+pub fn parse_line(line: &str) -> Vec<ClaudeEvent> {
+    if line.trim().is_empty() {
+        return Vec::new();
+    }
+    if let Ok(event) = serde_json::from_str::<ClaudeEvent>(line) {
+        return vec![event];
+    }
+
+    let value: serde_json::Value = match serde_json::from_str(line) {
+        Ok(value @ serde_json::Value::Object(_)) => value,
+        _ => {
+            return vec![ClaudeEvent::Content {
+                text: line.to_string(),
+            }]
+        }
+    };
+    match serde_json::from_value::<StreamMessage>(value) {
+        Ok(message) => message_events(message),
+        Err(e) => {
+            tracing::debug!("Skipping unrecognized claude-cli message: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn message_events(message: StreamMessage) -> Vec<ClaudeEvent> {
+    match message {
+        StreamMessage::System {} => Vec::new(),
+        StreamMessage::Assistant { message } | StreamMessage::User { message } => {
+            let mut events = match message.content {
+                // A plain prompt, which clients already have
+                MessageContent::Text(_) => Vec::new(),
+                MessageContent::Blocks(blocks) => {
+                    blocks.into_iter().filter_map(block_event).collect()
+                }
+            };
+            events.extend(message.usage.map(ClaudeEvent::from));
+            events
+        }
+        StreamMessage::Result {
+            subtype,
+            is_error,
+            result,
+            duration_ms,
+            total_cost_usd,
+            usage,
+        } => {
+            let mut events = Vec::new();
+            if is_error {
+                events.push(ClaudeEvent::Error {
+                    code: subtype.to_uppercase(),
+                    message: result.unwrap_or_else(|| format!("claude-cli failed: {}", subtype)),
+                });
+            }
+            events.extend(usage.map(ClaudeEvent::from));
+            events.push(ClaudeEvent::Summary {
+                is_error,
+                duration_ms,
+                total_cost_usd,
+            });
+            events
+        }
+    }
+}
+
+fn block_event(block: ContentBlock) -> Option<ClaudeEvent> {
+    match block {
+        ContentBlock::Text { text } => Some(ClaudeEvent::Content { text }),
+        ContentBlock::Thinking { thinking } => Some(ClaudeEvent::Thinking { text: thinking }),
+        ContentBlock::ToolUse { id, name, input } => Some(ClaudeEvent::ToolUse {
+            id: Some(id),
+            tool: name,
+            params: input,
+        }),
+        ContentBlock::ToolResult {
+            tool_use_id,
+            content,
+            is_error,
+        } => Some(ClaudeEvent::ToolResult {
+            tool_use_id,
+            content: content
+                .map(ToolResultContent::into_text)
+                .unwrap_or_default(),
+            is_error,
+        }),
+        ContentBlock::Other => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOOL_USE_TURN: &str =
+        include_str!("../../tests/fixtures/stream_json/tool_use_turn.jsonl");
+    const FAILED_TURN: &str = include_str!("../../tests/fixtures/stream_json/failed_turn.jsonl");
+
+    fn parse_all(transcript: &str) -> Vec<ClaudeEvent> {
+        transcript.lines().flat_map(parse_line).collect()
+    }
+
+    #[test]
+    fn test_tool_use_turn() {
+        let events = parse_all(TOOL_USE_TURN);
+        let usage = |output_tokens, input_tokens, cache_read_input_tokens| ClaudeEvent::Usage {
+            input_tokens,
+            output_tokens,
+            cache_creation_input_tokens: 0,
+            cache_read_input_tokens,
+        };
+
+        assert_eq!(
+            events,
+            vec![
+                ClaudeEvent::Thinking {
+                    text: "The user wants the page title; reading the DOM is enough.".to_string()
+                },
+                usage(14, 1200, 800),
+                ClaudeEvent::ToolUse {
+                    id: Some("toolu_01".to_string()),
+                    tool: "cdp_command".to_string(),
+                    params: serde_json::json!({"command": "DOM.getDocument"}),
+                },
+                usage(38, 1200, 800),
+                ClaudeEvent::ToolResult {
+                    tool_use_id: "toolu_01".to_string(),
+                    content: "<title>Facet</title>".to_string(),
+                    is_error: false,
+                },
+                ClaudeEvent::Content {
+                    text: "The page is titled \"Facet\".".to_string()
+                },
+                usage(52, 1260, 800),
+                usage(52, 2460, 1600),
+                ClaudeEvent::Summary {
+                    is_error: false,
+                    duration_ms: Some(4210),
+                    total_cost_usd: Some(0.0042),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failed_turn() {
+        let events = parse_all(FAILED_TURN);
+
+        assert!(events.contains(&ClaudeEvent::ToolResult {
+            tool_use_id: "toolu_01".to_string(),
+            content: "cat: missing.txt: No such file or directory".to_string(),
+            is_error: true,
+        }));
+        assert_eq!(
+            events[events.len() - 3..],
+            [
+                ClaudeEvent::Error {
+                    code: "ERROR_DURING_EXECUTION".to_string(),
+                    message: "API Error: Rate limit exceeded".to_string(),
+                },
+                ClaudeEvent::Usage {
+                    input_tokens: 900,
+                    output_tokens: 20,
+                    cache_creation_input_tokens: 0,
+                    cache_read_input_tokens: 0,
+                },
+                ClaudeEvent::Summary {
+                    is_error: true,
+                    duration_ms: Some(1830),
+                    total_cost_usd: Some(0.0011),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_other_lines() {
+        // Facet's own events pass through
+        assert_eq!(
+            parse_line(r#"{"type":"content","text":"Hello"}"#),
+            vec![ClaudeEvent::Content {
+                text: "Hello".to_string()
+            }]
+        );
+        assert_eq!(
+            parse_line("Plain text output"),
+            vec![ClaudeEvent::Content {
+                text: "Plain text output".to_string()
+            }]
+        );
+        assert!(parse_line("").is_empty());
+        assert!(parse_line(r#"{"type":"stream_event","event":{}}"#).is_empty());
+        // A prompt echoed back carries nothing new
+        assert!(parse_line(r#"{"type":"user","message":{"content":"hi"}}"#).is_empty());
+    }
+}
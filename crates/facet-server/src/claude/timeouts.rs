@@ -0,0 +1,309 @@
+//! Per-request timeouts with soft and hard phases
+//!
+//! A request is limited in how long it may run overall and how long
+//! claude-cli may go without output. Once a share of either limit has
+//! passed (the request's `soft_timeout_percent`) its stream gets a Warning
+//! event; at the limit itself the process group is killed and the stream
+//! ends with a TimedOut event. Any event resets the idle limit, and with it
+//! its warning.
+
+use crate::claude::cancel::ProcessGroup;
+use crate::error::FacetError;
+use crate::models::{ClaudeEvent, RequestOptions, TimeoutKind};
+use futures::{Stream, StreamExt};
+use tokio::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Idle timeout for requests that don't set one (seconds)
+pub const DEFAULT_IDLE_TIMEOUT_SECONDS: u64 = 60;
+
+/// Timeouts a request runs under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeoutPolicy {
+    /// Longest the request may run
+    pub overall: Duration,
+
+    /// Longest claude-cli may go without output
+    pub idle: Duration,
+
+    /// Percent of a limit after which a warning is emitted
+    pub soft_percent: u8,
+}
+
+impl TimeoutPolicy {
+    /// Builds the policy for a request
+    ///
+    /// # Arguments
+    /// * `options` - Options of the request
+    /// * `default_idle` - Idle timeout if the request doesn't set one
+    ///
+    /// # Returns
+    /// TimeoutPolicy for the request
+    pub fn for_request(options: &RequestOptions, default_idle: Duration) -> Self {
+        Self {
+            overall: Duration::from_secs(options.timeout_seconds),
+            idle: options
+                .idle_timeout_seconds
+                .map(Duration::from_secs)
+                .unwrap_or(default_idle),
+            soft_percent: options.soft_timeout_percent,
+        }
+    }
+
+    /// Point within `limit` at which to warn
+    fn soft(&self, limit: Duration) -> Duration {
+        limit * u32::from(self.soft_percent.min(100)) / 100
+    }
+}
+
+/// What happened when a deadline passed
+enum Expiry {
+    Warning(ClaudeEvent),
+    TimedOut(ClaudeEvent),
+}
+
+/// Deadlines of one execution
+struct Deadlines {
+    policy: TimeoutPolicy,
+    started: Instant,
+    last_output: Instant,
+    overall_warned: bool,
+    idle_warned: bool,
+}
+
+impl Deadlines {
+    fn new(policy: TimeoutPolicy, now: Instant) -> Self {
+        Self {
+            policy,
+            started: now,
+            last_output: now,
+            overall_warned: false,
+            idle_warned: false,
+        }
+    }
+
+    /// Earliest deadline still to come
+    fn next(&self) -> Instant {
+        let policy = &self.policy;
+        let mut next = (self.started + policy.overall).min(self.last_output + policy.idle);
+        if !self.overall_warned {
+            next = next.min(self.started + policy.soft(policy.overall));
+        }
+        if !self.idle_warned {
+            next = next.min(self.last_output + policy.soft(policy.idle));
+        }
+        next
+    }
+
+    fn output(&mut self, now: Instant) {
+        self.last_output = now;
+        self.idle_warned = false;
+    }
+
+    /// Checks the deadlines, hard limits first
+    fn check(&mut self, now: Instant, session_id: Uuid) -> Option<Expiry> {
+        let policy = self.policy;
+        if now >= self.started + policy.overall {
+            return Some(Expiry::TimedOut(ClaudeEvent::TimedOut {
+                session_id,
+                timeout: TimeoutKind::Overall,
+                after_seconds: policy.overall.as_secs(),
+            }));
+        }
+        if now >= self.last_output + policy.idle {
+            return Some(Expiry::TimedOut(ClaudeEvent::TimedOut {
+                session_id,
+                timeout: TimeoutKind::Idle,
+                after_seconds: policy.idle.as_secs(),
+            }));
+        }
+
+        if !self.overall_warned && now >= self.started + policy.soft(policy.overall) {
+            self.overall_warned = true;
+            let left = (self.started + policy.overall).saturating_duration_since(now);
+            return Some(Expiry::Warning(ClaudeEvent::Warning {
+                code: "TIMEOUT_APPROACHING".to_string(),
+                message: format!("Request will be stopped in {}s", left.as_secs()),
+            }));
+        }
+        if !self.idle_warned && now >= self.last_output + policy.soft(policy.idle) {
+            self.idle_warned = true;
+            let left = (self.last_output + policy.idle).saturating_duration_since(now);
+            return Some(Expiry::Warning(ClaudeEvent::Warning {
+                code: "IDLE_TIMEOUT_APPROACHING".to_string(),
+                message: format!(
+                    "No output for {}s; request will be stopped in {}s without any",
+                    now.saturating_duration_since(self.last_output).as_secs(),
+                    left.as_secs()
+                ),
+            }));
+        }
+
+        None
+    }
+}
+
+/// Applies a timeout policy to an execution's stream
+///
+/// At a hard limit the process group is killed and the inner stream
+/// dropped, which also kills a process spawned with `kill_on_drop`.
+///
+/// # Arguments
+/// * `events` - Events of the execution
+/// * `policy` - Timeouts to apply
+/// * `session_id` - Session the execution runs for
+/// * `process_group` - Process group to kill at a hard limit
+///
+/// # Returns
+/// The events, with warnings, up to a TimedOut event if a limit is reached
+pub fn with_timeouts<S>(
+    events: S,
+    policy: TimeoutPolicy,
+    session_id: Uuid,
+    process_group: ProcessGroup,
+) -> impl Stream<Item = Result<ClaudeEvent, FacetError>> + Send + 'static
+where
+    S: Stream<Item = Result<ClaudeEvent, FacetError>> + Send + Unpin + 'static,
+{
+    async_stream::stream! {
+        let mut events = events;
+        let mut deadlines = Deadlines::new(policy, Instant::now());
+        loop {
+            let next = tokio::select! {
+                next = events.next() => Some(next),
+                _ = tokio::time::sleep_until(deadlines.next()) => None,
+            };
+            match next {
+                Some(Some(event)) => {
+                    deadlines.output(Instant::now());
+                    yield event;
+                }
+                Some(None) => break,
+                None => match deadlines.check(Instant::now(), session_id) {
+                    Some(Expiry::Warning(event)) => yield Ok(event),
+                    Some(Expiry::TimedOut(event)) => {
+                        process_group.kill();
+                        drop(events);
+                        yield Ok(event);
+                        break;
+                    }
+                    None => {}
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    fn policy(overall_ms: u64, idle_ms: u64) -> TimeoutPolicy {
+        TimeoutPolicy {
+            overall: Duration::from_millis(overall_ms),
+            idle: Duration::from_millis(idle_ms),
+            soft_percent: 50,
+        }
+    }
+
+    async fn collect<S>(events: S) -> Vec<ClaudeEvent>
+    where
+        S: Stream<Item = Result<ClaudeEvent, FacetError>>,
+    {
+        events.map(|event| event.unwrap()).collect().await
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_warns_then_times_out() {
+        let session_id = Uuid::new_v4();
+        let events = stream::pending();
+        let events = collect(with_timeouts(
+            events,
+            policy(10_000, 100),
+            session_id,
+            ProcessGroup::new(),
+        ))
+        .await;
+
+        assert!(matches!(
+            &events[0],
+            ClaudeEvent::Warning { code, .. } if code == "IDLE_TIMEOUT_APPROACHING"
+        ));
+        assert!(matches!(
+            events[1],
+            ClaudeEvent::TimedOut {
+                timeout: TimeoutKind::Idle,
+                ..
+            }
+        ));
+        assert_eq!(events.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_output_resets_idle_but_not_overall_timeout() {
+        let session_id = Uuid::new_v4();
+        // An event every 40ms keeps the 100ms idle timeout from expiring
+        let events = stream::unfold((), |_| async {
+            tokio::time::sleep(Duration::from_millis(40)).await;
+            Some((
+                Ok(ClaudeEvent::Content {
+                    text: "tick".to_string(),
+                }),
+                (),
+            ))
+        });
+        let events = collect(with_timeouts(
+            Box::pin(events),
+            policy(300, 100),
+            session_id,
+            ProcessGroup::new(),
+        ))
+        .await;
+
+        let warnings: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                ClaudeEvent::Warning { code, .. } => Some(code.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(warnings, vec!["TIMEOUT_APPROACHING"]);
+        assert_eq!(
+            events.last(),
+            Some(&ClaudeEvent::TimedOut {
+                session_id,
+                timeout: TimeoutKind::Overall,
+                after_seconds: 0,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_ending_in_time_is_unchanged() {
+        let content = ClaudeEvent::Content {
+            text: "done".to_string(),
+        };
+        let events = stream::iter([Ok(content.clone())]);
+        let events = collect(with_timeouts(
+            events,
+            policy(1000, 1000),
+            Uuid::new_v4(),
+            ProcessGroup::new(),
+        ))
+        .await;
+        assert_eq!(events, vec![content]);
+    }
+
+    #[test]
+    fn test_policy_for_request() {
+        let options = RequestOptions {
+            timeout_seconds: 120,
+            ..Default::default()
+        };
+        let policy = TimeoutPolicy::for_request(&options, Duration::from_secs(30));
+        assert_eq!(policy.overall, Duration::from_secs(120));
+        assert_eq!(policy.idle, Duration::from_secs(30));
+        assert_eq!(policy.soft(policy.overall), Duration::from_secs(96));
+    }
+}
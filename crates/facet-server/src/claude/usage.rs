@@ -0,0 +1,168 @@
+//! Live token usage tracking for streaming executions
+//!
+//! Accumulates output token counts while a claude-cli process streams and
+//! produces `UsageDelta` events so clients can render a running cost ticker.
+//! Counts come from usage blocks reported by claude-cli (see
+//! `stream_json`) when present and fall back to a character-based estimate
+//! otherwise.
+
+use crate::models::ClaudeEvent;
+use std::time::{Duration, Instant};
+
+/// Default price in USD per million output tokens
+pub const DEFAULT_OUTPUT_COST_PER_MILLION: f64 = 15.0;
+
+/// Minimum time between two emitted usage events
+const EMIT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Estimates the token count of a piece of text
+///
+/// Uses the common ~4 characters per token heuristic, which is close enough
+/// for a live ticker until claude-cli reports authoritative numbers.
+///
+/// # Arguments
+/// * `text` - Text to estimate
+///
+/// # Returns
+/// Estimated number of tokens
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+/// Running usage totals for a single execution
+#[derive(Debug, Clone)]
+pub struct UsageTracker {
+    /// When the execution started streaming
+    started_at: Instant,
+
+    /// Output tokens seen so far
+    tokens: u64,
+
+    /// Price in USD per million output tokens
+    cost_per_million: f64,
+
+    /// When the last usage event was emitted
+    last_emit: Option<Instant>,
+}
+
+impl UsageTracker {
+    /// Creates a new tracker starting now
+    ///
+    /// # Arguments
+    /// * `cost_per_million` - Price in USD per million output tokens
+    ///
+    /// # Returns
+    /// New UsageTracker with zero usage
+    pub fn new(cost_per_million: f64) -> Self {
+        Self {
+            started_at: Instant::now(),
+            tokens: 0,
+            cost_per_million,
+            last_emit: None,
+        }
+    }
+
+    /// Adds the estimated token count of streamed text
+    pub fn record_text(&mut self, text: &str) {
+        self.tokens += estimate_tokens(text);
+    }
+
+    /// Applies a token count reported by claude-cli
+    ///
+    /// Reported counts are cumulative and authoritative, so they replace the
+    /// estimate whenever they are ahead of it.
+    pub fn record_reported(&mut self, output_tokens: u64) {
+        self.tokens = self.tokens.max(output_tokens);
+    }
+
+    /// Returns output tokens seen so far
+    pub fn tokens(&self) -> u64 {
+        self.tokens
+    }
+
+    /// Returns the estimated cost so far in USD
+    pub fn estimated_cost_usd(&self) -> f64 {
+        self.tokens as f64 * self.cost_per_million / 1_000_000.0
+    }
+
+    /// Returns the average output rate since the execution started
+    pub fn tokens_per_second(&self) -> f64 {
+        let elapsed = self.started_at.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            self.tokens as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns a usage event if enough time has passed since the last one
+    ///
+    /// Throttles emission so a fast stream doesn't double its event count.
+    pub fn poll_delta(&mut self) -> Option<ClaudeEvent> {
+        let now = Instant::now();
+        if let Some(last) = self.last_emit {
+            if now.duration_since(last) < EMIT_INTERVAL {
+                return None;
+            }
+        }
+        self.last_emit = Some(now);
+        Some(self.delta())
+    }
+
+    /// Returns the current usage as an event, ignoring throttling
+    pub fn delta(&self) -> ClaudeEvent {
+        ClaudeEvent::UsageDelta {
+            tokens_so_far: self.tokens,
+            estimated_cost_usd: self.estimated_cost_usd(),
+            tokens_per_second: self.tokens_per_second(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn test_reported_tokens_override_estimate() {
+        let mut tracker = UsageTracker::new(DEFAULT_OUTPUT_COST_PER_MILLION);
+        tracker.record_text("abcdefgh");
+        assert_eq!(tracker.tokens(), 2);
+
+        tracker.record_reported(10);
+        assert_eq!(tracker.tokens(), 10);
+
+        // A stale report never moves the count backwards
+        tracker.record_reported(5);
+        assert_eq!(tracker.tokens(), 10);
+    }
+
+    #[test]
+    fn test_estimated_cost() {
+        let mut tracker = UsageTracker::new(10.0);
+        tracker.record_reported(500_000);
+        assert!((tracker.estimated_cost_usd() - 5.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_poll_delta_is_throttled() {
+        let mut tracker = UsageTracker::new(DEFAULT_OUTPUT_COST_PER_MILLION);
+        tracker.record_text("hello world");
+
+        assert!(tracker.poll_delta().is_some());
+        assert!(tracker.poll_delta().is_none());
+
+        if let ClaudeEvent::UsageDelta { tokens_so_far, .. } = tracker.delta() {
+            assert_eq!(tokens_so_far, 3);
+        } else {
+            panic!("Expected UsageDelta event");
+        }
+    }
+}
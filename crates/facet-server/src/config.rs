@@ -5,7 +5,9 @@
 //! for all optional settings.
 
 use crate::error::FacetError;
+use facet_types::profiles::RedactionPreferences;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 
 /// Server configuration
@@ -63,6 +65,12 @@ pub struct AuthConfig {
     #[serde(default)]
     pub tokens: Vec<String>,
 
+    /// Path to the API key store managed with `facet keys`, usually
+    /// ~/.facet/api_keys.json; its active keys are accepted as tokens.
+    /// Keys created through the API last until restart if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_keys_path: Option<String>,
+
     /// Require authentication (can be disabled for local dev)
     #[serde(default = "default_require_auth")]
     pub require_auth: bool,
@@ -70,6 +78,11 @@ pub struct AuthConfig {
     /// Rate limit per token/IP (requests per minute)
     #[serde(default = "default_rate_limit")]
     pub rate_limit_per_minute: u32,
+
+    /// Requests a token or profile may make at once after being idle
+    /// (defaults to the per-minute limit)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit_burst: Option<u32>,
 }
 
 fn default_require_auth() -> bool {
@@ -97,9 +110,41 @@ pub struct ClaudeConfig {
     #[serde(default = "default_timeout_seconds")]
     pub default_timeout_seconds: u64,
 
+    /// How long claude-cli may go without output before a request is
+    /// stopped, for requests that don't set it (seconds)
+    #[serde(default = "default_idle_timeout_seconds")]
+    pub idle_timeout_seconds: u64,
+
+    /// Attempts per request, the first included, when claude-cli fails
+    /// transiently (rate limits, network errors); 1 disables retries
+    #[serde(default = "default_retry_max_attempts")]
+    pub retry_max_attempts: u32,
+
+    /// Delay before the first retry (milliseconds); doubles every retry
+    #[serde(default = "default_retry_initial_backoff_ms")]
+    pub retry_initial_backoff_ms: u64,
+
+    /// Longest delay between retries (milliseconds)
+    #[serde(default = "default_retry_max_backoff_ms")]
+    pub retry_max_backoff_ms: u64,
+
+    /// claude-cli processes kept warm for new conversations; 0 spawns a
+    /// process per request
+    #[serde(default)]
+    pub pool_size: usize,
+
     /// Maximum concurrent sessions
     #[serde(default = "default_max_concurrent")]
     pub max_concurrent_sessions: usize,
+
+    /// Price per million output tokens (USD), used for live cost estimates
+    #[serde(default = "default_output_cost_per_million")]
+    pub output_cost_per_million: f64,
+
+    /// Path to a routing policy (TOML) sending some requests to local
+    /// models; without one every request goes to claude-cli
+    #[serde(default)]
+    pub routing_policy_path: Option<String>,
 }
 
 fn default_binary_path() -> String {
@@ -110,10 +155,30 @@ fn default_timeout_seconds() -> u64 {
     300
 }
 
+fn default_idle_timeout_seconds() -> u64 {
+    crate::claude::timeouts::DEFAULT_IDLE_TIMEOUT_SECONDS
+}
+
+fn default_retry_max_attempts() -> u32 {
+    3
+}
+
+fn default_retry_initial_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_retry_max_backoff_ms() -> u64 {
+    30000
+}
+
 fn default_max_concurrent() -> usize {
     20
 }
 
+fn default_output_cost_per_million() -> f64 {
+    crate::claude::usage::DEFAULT_OUTPUT_COST_PER_MILLION
+}
+
 /// Request size and content limits
 ///
 /// Enforces maximum sizes to prevent resource exhaustion.
@@ -170,6 +235,102 @@ fn default_sanitize() -> bool {
     true
 }
 
+/// Session history configuration
+///
+/// Sessions and usage totals are kept in memory only unless a history
+/// database and a usage file are set, and transcripts are only written to
+/// a knowledge graph that is set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionsConfig {
+    /// Path to the SQLite database persisting session history, which lets
+    /// conversations be resumed across server restarts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_path: Option<String>,
+
+    /// Path to the JSON file of token usage and cost per profile, usually
+    /// ~/.facet/usage.json; totals last until restart if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_path: Option<String>,
+
+    /// Path to the SQLite knowledge graph that completed requests are
+    /// written to, in their profile's partition; none are if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcripts_path: Option<String>,
+}
+
+/// Audit log configuration
+///
+/// Executions are audited in memory only unless a path is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditConfig {
+    /// Path to the JSONL audit log; rotated files get `.1`, `.2`, ...
+    /// appended
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// Chain entries by SHA-256 hash, so tampering can be detected
+    #[serde(default)]
+    pub hash_chain: bool,
+
+    /// Size in MB at which the log is rotated
+    #[serde(default = "default_audit_max_file_mb")]
+    pub max_file_mb: u64,
+
+    /// Rotated files kept
+    #[serde(default = "default_audit_max_files")]
+    pub max_files: usize,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            hash_chain: false,
+            max_file_mb: default_audit_max_file_mb(),
+            max_files: default_audit_max_files(),
+        }
+    }
+}
+
+fn default_audit_max_file_mb() -> u64 {
+    10
+}
+
+fn default_audit_max_files() -> usize {
+    5
+}
+
+/// PII redaction configuration
+///
+/// The redaction preferences the server enforces for each profile.
+/// Requests may ask for stricter ones, never for laxer.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RedactionConfig {
+    /// Preferences of profiles not listed in `profiles`, and of requests
+    /// naming no profile
+    #[serde(default)]
+    pub default: RedactionPreferences,
+
+    /// Preferences per profile, keyed by `X-Facet-Profile` name
+    #[serde(default)]
+    pub profiles: HashMap<String, RedactionPreferences>,
+}
+
+impl RedactionConfig {
+    /// Returns the preferences of a profile
+    ///
+    /// # Arguments
+    /// * `profile` - Profile a request acts for, if it named one
+    ///
+    /// # Returns
+    /// The profile's preferences, or the defaults if it has none
+    pub fn for_profile(&self, profile: Option<&str>) -> &RedactionPreferences {
+        profile
+            .and_then(|profile| self.profiles.get(profile))
+            .unwrap_or(&self.default)
+    }
+}
+
 /// Root configuration structure
 ///
 /// Aggregates all configuration sections and provides validation.
@@ -180,6 +341,12 @@ pub struct Config {
     pub claude: ClaudeConfig,
     pub limits: LimitsConfig,
     pub logging: LoggingConfig,
+    #[serde(default)]
+    pub sessions: SessionsConfig,
+    #[serde(default)]
+    pub audit: AuditConfig,
+    #[serde(default)]
+    pub redaction: RedactionConfig,
 }
 
 impl Config {
@@ -231,14 +398,23 @@ impl Config {
             auth: AuthConfig {
                 dev_token: Some("dev-token-12345".to_string()),
                 tokens: vec![],
+                api_keys_path: None,
                 require_auth: false,
                 rate_limit_per_minute: 100,
+                rate_limit_burst: None,
             },
             claude: ClaudeConfig {
                 binary_path: "claude".to_string(),
                 mock_mode: false,
                 default_timeout_seconds: 300,
+                idle_timeout_seconds: default_idle_timeout_seconds(),
+                retry_max_attempts: default_retry_max_attempts(),
+                retry_initial_backoff_ms: default_retry_initial_backoff_ms(),
+                retry_max_backoff_ms: default_retry_max_backoff_ms(),
+                pool_size: 0,
                 max_concurrent_sessions: 20,
+                output_cost_per_million: default_output_cost_per_million(),
+                routing_policy_path: None,
             },
             limits: LimitsConfig {
                 max_request_size_mb: 50,
@@ -250,6 +426,9 @@ impl Config {
                 pretty_print: true,
                 sanitize_sensitive_data: true,
             },
+            sessions: SessionsConfig::default(),
+            audit: AuditConfig::default(),
+            redaction: RedactionConfig::default(),
         }
     }
 
@@ -304,7 +483,11 @@ impl Config {
         }
 
         // Validate auth config
-        if self.auth.require_auth && self.auth.tokens.is_empty() && self.auth.dev_token.is_none() {
+        if self.auth.require_auth
+            && self.auth.tokens.is_empty()
+            && self.auth.dev_token.is_none()
+            && self.auth.api_keys_path.is_none()
+        {
             return Err(FacetError::Config(
                 "Authentication required but no tokens configured".to_string(),
             ));
@@ -316,6 +499,12 @@ impl Config {
             ));
         }
 
+        if self.auth.rate_limit_burst == Some(0) {
+            return Err(FacetError::Config(
+                "Rate limit burst must be greater than 0".to_string(),
+            ));
+        }
+
         // Validate claude config
         if self.claude.binary_path.is_empty() {
             return Err(FacetError::Config(
@@ -329,12 +518,30 @@ impl Config {
             ));
         }
 
+        if self.claude.idle_timeout_seconds == 0 {
+            return Err(FacetError::Config(
+                "Claude idle timeout must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.claude.retry_max_attempts == 0 {
+            return Err(FacetError::Config(
+                "Claude retry attempts must be at least 1".to_string(),
+            ));
+        }
+
         if self.claude.max_concurrent_sessions == 0 {
             return Err(FacetError::Config(
                 "Max concurrent sessions must be greater than 0".to_string(),
             ));
         }
 
+        if self.audit.max_file_mb == 0 {
+            return Err(FacetError::Config(
+                "Audit log max file size must be greater than 0".to_string(),
+            ));
+        }
+
         // Validate limits config
         if self.limits.max_request_size_mb == 0 {
             return Err(FacetError::Config(
@@ -465,6 +672,10 @@ mod tests {
             .unwrap_err()
             .to_string()
             .contains("no tokens configured"));
+
+        // API keys are enough
+        config.auth.api_keys_path = Some("/tmp/api_keys.json".to_string());
+        assert!(config.validate().is_ok());
     }
 
     #[test]
@@ -479,6 +690,13 @@ mod tests {
             .contains("Rate limit must be greater"));
     }
 
+    #[test]
+    fn test_config_validation_zero_rate_limit_burst() {
+        let mut config = Config::dev_default();
+        config.auth.rate_limit_burst = Some(0);
+        assert!(config.validate().is_err());
+    }
+
     #[test]
     fn test_config_validation_empty_binary_path() {
         let mut config = Config::dev_default();
@@ -614,5 +832,39 @@ sanitize_sensitive_data = false
         assert_eq!(config.claude.binary_path, "claude");
         assert_eq!(config.limits.max_screenshot_count, 10);
         assert_eq!(config.logging.level, "info");
+        assert!(config.sessions.history_path.is_none());
+        assert!(config.sessions.usage_path.is_none());
+        assert!(config.sessions.transcripts_path.is_none());
+        assert!(config.audit.path.is_none());
+        assert_eq!(config.audit.max_file_mb, 10);
+        assert!(!config.redaction.for_profile(None).enabled);
+    }
+
+    #[test]
+    fn test_redaction_per_profile() {
+        let toml_content = r#"
+[server]
+[auth]
+[claude]
+[limits]
+[logging]
+
+[redaction.profiles.work]
+enabled = true
+auto_approve_threshold = 2
+"#;
+
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(toml_content.as_bytes()).unwrap();
+        temp_file.flush().unwrap();
+
+        let config = Config::from_file(temp_file.path()).unwrap();
+        let work = config.redaction.for_profile(Some("work"));
+        assert!(work.enabled);
+        // Unset fields keep their defaults
+        assert!(work.confirm_before_send);
+        assert_eq!(work.auto_approve_threshold, 2);
+        assert!(!config.redaction.for_profile(Some("shopping")).enabled);
+        assert!(!config.redaction.for_profile(None).enabled);
     }
 }
@@ -19,6 +19,10 @@ pub enum FacetError {
     #[error("Authentication failed: {0}")]
     AuthFailed(String),
 
+    /// Authenticated, but not allowed to do this
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     /// Rate limit exceeded for this token/IP
     #[error("Rate limit exceeded: {0}")]
     RateLimited(String),
@@ -43,6 +47,10 @@ pub enum FacetError {
     #[error("Session not found: {0}")]
     SessionNotFound(String),
 
+    /// API key not found
+    #[error("API key not found: {0}")]
+    ApiKeyNotFound(String),
+
     /// Internal server error
     #[error("Internal error: {0}")]
     Internal(String),
@@ -59,7 +67,7 @@ impl Reject for FacetError {}
 /// This structure is serialized to JSON and sent to clients
 /// when an error occurs. It provides consistent error formatting
 /// across all endpoints.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ErrorResponse {
     /// Error code (matches FacetError variant name)
     pub code: String,
@@ -90,12 +98,14 @@ impl FacetError {
     pub fn status_code(&self) -> StatusCode {
         match self {
             FacetError::AuthFailed(_) => StatusCode::UNAUTHORIZED,
+            FacetError::Forbidden(_) => StatusCode::FORBIDDEN,
             FacetError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
             FacetError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
             FacetError::ClaudeUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
             FacetError::ExecutionError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             FacetError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
             FacetError::SessionNotFound(_) => StatusCode::NOT_FOUND,
+            FacetError::ApiKeyNotFound(_) => StatusCode::NOT_FOUND,
             FacetError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
             FacetError::Config(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
@@ -111,12 +121,14 @@ impl FacetError {
     pub fn error_code(&self) -> String {
         match self {
             FacetError::AuthFailed(_) => "AUTH_FAILED",
+            FacetError::Forbidden(_) => "FORBIDDEN",
             FacetError::RateLimited(_) => "RATE_LIMITED",
             FacetError::InvalidRequest(_) => "INVALID_REQUEST",
             FacetError::ClaudeUnavailable(_) => "CLAUDE_UNAVAILABLE",
             FacetError::ExecutionError(_) => "EXECUTION_ERROR",
             FacetError::Timeout(_) => "TIMEOUT",
             FacetError::SessionNotFound(_) => "SESSION_NOT_FOUND",
+            FacetError::ApiKeyNotFound(_) => "API_KEY_NOT_FOUND",
             FacetError::Internal(_) => "INTERNAL_ERROR",
             FacetError::Config(_) => "CONFIG_ERROR",
         }
@@ -160,6 +172,13 @@ mod tests {
         assert_eq!(err.error_code(), "AUTH_FAILED");
     }
 
+    #[test]
+    fn test_forbidden_status_code() {
+        let err = FacetError::Forbidden("admin only".to_string());
+        assert_eq!(err.status_code(), StatusCode::FORBIDDEN);
+        assert_eq!(err.error_code(), "FORBIDDEN");
+    }
+
     #[test]
     fn test_rate_limited_status_code() {
         let err = FacetError::RateLimited("too many requests".to_string());
@@ -202,6 +221,13 @@ mod tests {
         assert_eq!(err.error_code(), "SESSION_NOT_FOUND");
     }
 
+    #[test]
+    fn test_api_key_not_found_status_code() {
+        let err = FacetError::ApiKeyNotFound("0a1b2c3d4e5f".to_string());
+        assert_eq!(err.status_code(), StatusCode::NOT_FOUND);
+        assert_eq!(err.error_code(), "API_KEY_NOT_FOUND");
+    }
+
     #[test]
     fn test_error_response_without_session_id() {
         let err = FacetError::InvalidRequest("test error".to_string());
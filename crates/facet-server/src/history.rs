@@ -0,0 +1,452 @@
+//! Persistent session history
+//!
+//! Records every session's prompt, the claude-cli conversation it ran in,
+//! its events and its final state in SQLite, so a session can be looked up
+//! and its conversation resumed after the server restarts. Screenshots are
+//! not stored: they are large, and claude-cli keeps what it needs of them
+//! in its own conversation.
+
+use crate::auth::Caller;
+use crate::error::FacetError;
+use crate::models::{ClaudeEvent, FacetRequest, RequestUsage, SessionState, SessionStatus};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS sessions (
+        session_id TEXT PRIMARY KEY,
+        conversation_id TEXT NOT NULL,
+        resumed_from TEXT,
+        prompt TEXT NOT NULL,
+        user_intent TEXT NOT NULL,
+        state TEXT NOT NULL,
+        started_at TEXT NOT NULL,
+        completed_at TEXT,
+        error TEXT,
+        actor TEXT NOT NULL DEFAULT 'anonymous',
+        profile TEXT
+    );
+    CREATE TABLE IF NOT EXISTS session_events (
+        session_id TEXT NOT NULL,
+        seq INTEGER NOT NULL,
+        event TEXT NOT NULL,
+        PRIMARY KEY (session_id, seq)
+    );
+";
+
+/// A session as recorded in the history
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionRecord {
+    /// Status of the session
+    #[serde(flatten)]
+    pub status: SessionStatus,
+
+    /// Conversation the session ran in, as claude-cli knows it
+    pub conversation_id: String,
+
+    /// Session whose conversation this one continued
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resumed_from: Option<Uuid>,
+
+    /// User's prompt
+    pub prompt: String,
+
+    /// User's intent from the request context
+    pub user_intent: String,
+
+    /// Every event the session emitted, in order
+    pub events: Vec<ClaudeEvent>,
+}
+
+/// SQLite store of session history
+///
+/// Writes are small and local, so they run inline under a mutex rather
+/// than on a blocking thread.
+#[derive(Debug)]
+pub struct SessionHistory {
+    conn: Mutex<Connection>,
+}
+
+impl SessionHistory {
+    /// Opens (or creates) the history database at `path`
+    ///
+    /// # Arguments
+    /// * `path` - Path to the SQLite database file
+    ///
+    /// # Returns
+    /// SessionHistory backed by the file
+    ///
+    /// # Errors
+    /// Returns FacetError::Config if the database cannot be opened
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, FacetError> {
+        let conn = Connection::open(path.as_ref()).map_err(|e| {
+            FacetError::Config(format!(
+                "Failed to open session history {}: {}",
+                path.as_ref().display(),
+                e
+            ))
+        })?;
+        Self::init(conn)
+    }
+
+    /// Creates a history kept in memory, for tests
+    pub fn in_memory() -> Result<Self, FacetError> {
+        Self::init(Connection::open_in_memory().map_err(storage)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, FacetError> {
+        conn.execute_batch(SCHEMA).map_err(storage)?;
+        add_caller_columns(&conn).map_err(storage)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Records a new running session
+    ///
+    /// # Arguments
+    /// * `request` - Request the session runs
+    /// * `caller` - Who the request comes from
+    /// * `conversation_id` - Conversation the session runs in
+    /// * `started_at` - ISO 8601 start timestamp
+    pub fn record_session(
+        &self,
+        request: &FacetRequest,
+        caller: &Caller,
+        conversation_id: &str,
+        started_at: &str,
+    ) -> Result<(), FacetError> {
+        self.conn()
+            .execute(
+                "INSERT OR REPLACE INTO sessions
+                 (session_id, conversation_id, resumed_from, prompt, user_intent, state,
+                  started_at, actor, profile)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    request.session_id.to_string(),
+                    conversation_id,
+                    request.options.resume.map(|id| id.to_string()),
+                    request.prompt,
+                    request.context.user_intent,
+                    state_name(&SessionState::Running),
+                    started_at,
+                    caller.actor,
+                    caller.profile,
+                ],
+            )
+            .map_err(storage)?;
+        Ok(())
+    }
+
+    /// Appends an event to a session's history
+    pub fn record_event(&self, session_id: Uuid, event: &ClaudeEvent) -> Result<(), FacetError> {
+        let event = serde_json::to_string(event)
+            .map_err(|e| FacetError::Internal(format!("Failed to serialize event: {}", e)))?;
+        self.conn()
+            .execute(
+                "INSERT INTO session_events (session_id, seq, event)
+                 SELECT ?1, coalesce(max(seq), 0) + 1, ?2
+                 FROM session_events WHERE session_id = ?1",
+                params![session_id.to_string(), event],
+            )
+            .map_err(storage)?;
+        Ok(())
+    }
+
+    /// Records a session's final state
+    pub fn record_status(&self, status: &SessionStatus) -> Result<(), FacetError> {
+        self.conn()
+            .execute(
+                "UPDATE sessions SET state = ?2, completed_at = ?3, error = ?4
+                 WHERE session_id = ?1",
+                params![
+                    status.session_id.to_string(),
+                    state_name(&status.status),
+                    status.completed_at,
+                    status.error,
+                ],
+            )
+            .map_err(storage)?;
+        Ok(())
+    }
+
+    /// Conversation a recorded session ran in
+    ///
+    /// # Returns
+    /// Conversation id, or None if the session is not recorded
+    pub fn conversation_id(&self, session_id: Uuid) -> Result<Option<String>, FacetError> {
+        self.conn()
+            .query_row(
+                "SELECT conversation_id FROM sessions WHERE session_id = ?1",
+                params![session_id.to_string()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(storage)
+    }
+
+    /// Caller a recorded session's request came from
+    ///
+    /// Sessions recorded before callers were stored belong to `anonymous`.
+    ///
+    /// # Returns
+    /// Caller, or None if the session is not recorded
+    pub fn caller(&self, session_id: Uuid) -> Result<Option<Caller>, FacetError> {
+        self.conn()
+            .query_row(
+                "SELECT actor, profile FROM sessions WHERE session_id = ?1",
+                params![session_id.to_string()],
+                |row| {
+                    Ok(Caller {
+                        actor: row.get(0)?,
+                        profile: row.get(1)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(storage)
+    }
+
+    /// Loads a recorded session with its events
+    ///
+    /// # Returns
+    /// SessionRecord, or None if the session is not recorded
+    pub fn load(&self, session_id: Uuid) -> Result<Option<SessionRecord>, FacetError> {
+        let conn = self.conn();
+        let row = conn
+            .query_row(
+                "SELECT conversation_id, resumed_from, prompt, user_intent, state,
+                        started_at, completed_at, error
+                 FROM sessions WHERE session_id = ?1",
+                params![session_id.to_string()],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, Option<String>>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, String>(5)?,
+                        row.get::<_, Option<String>>(6)?,
+                        row.get::<_, Option<String>>(7)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(storage)?;
+        let Some((
+            conversation_id,
+            resumed_from,
+            prompt,
+            user_intent,
+            state,
+            started_at,
+            completed_at,
+            error,
+        )) = row
+        else {
+            return Ok(None);
+        };
+
+        let mut statement = conn
+            .prepare("SELECT event FROM session_events WHERE session_id = ?1 ORDER BY seq")
+            .map_err(storage)?;
+        let events = statement
+            .query_map(params![session_id.to_string()], |row| {
+                row.get::<_, String>(0)
+            })
+            .map_err(storage)?
+            .map(|event| {
+                let event = event.map_err(storage)?;
+                serde_json::from_str(&event).map_err(|e| {
+                    FacetError::Internal(format!("Corrupt event in session history: {}", e))
+                })
+            })
+            .collect::<Result<Vec<ClaudeEvent>, FacetError>>()?;
+
+        Ok(Some(SessionRecord {
+            status: SessionStatus {
+                session_id,
+                status: parse_state(&state)?,
+                started_at,
+                completed_at,
+                error,
+                // Not stored: it follows from the events
+                usage: RequestUsage::from_events(&events),
+            },
+            conversation_id,
+            resumed_from: resumed_from.and_then(|id| Uuid::parse_str(&id).ok()),
+            prompt,
+            user_intent,
+            events,
+        }))
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Adds the caller columns to a history created before they existed
+fn add_caller_columns(conn: &Connection) -> rusqlite::Result<()> {
+    let has_actor = conn
+        .prepare("SELECT 1 FROM pragma_table_info('sessions') WHERE name = 'actor'")?
+        .exists([])?;
+    if !has_actor {
+        conn.execute_batch(
+            "ALTER TABLE sessions ADD COLUMN actor TEXT NOT NULL DEFAULT 'anonymous';
+             ALTER TABLE sessions ADD COLUMN profile TEXT;",
+        )?;
+    }
+    Ok(())
+}
+
+fn storage(e: rusqlite::Error) -> FacetError {
+    FacetError::Internal(format!("Session history error: {}", e))
+}
+
+/// State as stored, matching its JSON form
+fn state_name(state: &SessionState) -> String {
+    serde_json::to_value(state)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn parse_state(name: &str) -> Result<SessionState, FacetError> {
+    serde_json::from_value(serde_json::Value::String(name.to_string()))
+        .map_err(|_| FacetError::Internal(format!("Unknown session state: {}", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{DomState, RequestContext, RequestOptions};
+
+    fn create_test_request() -> FacetRequest {
+        FacetRequest {
+            session_id: Uuid::new_v4(),
+            context: RequestContext {
+                screenshots: vec![],
+                dom_state: DomState {
+                    accessible_tree: "test tree".to_string(),
+                    interactive_elements: vec![],
+                },
+                user_intent: "test intent".to_string(),
+            },
+            prompt: "test prompt".to_string(),
+            options: RequestOptions::default(),
+        }
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.db");
+        let request = create_test_request();
+        let session_id = request.session_id;
+
+        {
+            let history = SessionHistory::open(&path).unwrap();
+            history
+                .record_session(
+                    &request,
+                    &Caller::default(),
+                    "conversation-1",
+                    "2025-10-17T10:30:00Z",
+                )
+                .unwrap();
+            for text in ["one", "two"] {
+                let event = ClaudeEvent::Content {
+                    text: text.to_string(),
+                };
+                history.record_event(session_id, &event).unwrap();
+            }
+            history
+                .record_status(&SessionStatus {
+                    session_id,
+                    status: SessionState::Completed,
+                    started_at: "2025-10-17T10:30:00Z".to_string(),
+                    completed_at: Some("2025-10-17T10:31:00Z".to_string()),
+                    error: None,
+                    usage: None,
+                })
+                .unwrap();
+        }
+
+        // Reopened, as after a restart
+        let history = SessionHistory::open(&path).unwrap();
+        let record = history.load(session_id).unwrap().unwrap();
+        assert_eq!(record.conversation_id, "conversation-1");
+        assert_eq!(record.prompt, "test prompt");
+        assert_eq!(record.status.status, SessionState::Completed);
+        assert_eq!(
+            record.events[1],
+            ClaudeEvent::Content {
+                text: "two".to_string()
+            }
+        );
+        assert_eq!(
+            history.conversation_id(session_id).unwrap().as_deref(),
+            Some("conversation-1")
+        );
+    }
+
+    #[test]
+    fn test_unknown_session() {
+        let history = SessionHistory::in_memory().unwrap();
+        assert!(history.load(Uuid::new_v4()).unwrap().is_none());
+        assert!(history.conversation_id(Uuid::new_v4()).unwrap().is_none());
+        assert!(history.caller(Uuid::new_v4()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_caller_recorded() {
+        let history = SessionHistory::in_memory().unwrap();
+        let request = create_test_request();
+        let caller = Caller {
+            actor: "api_key:abc".to_string(),
+            profile: Some("alice".to_string()),
+        };
+        history
+            .record_session(&request, &caller, "conversation-1", "2025-10-17T10:30:00Z")
+            .unwrap();
+
+        assert_eq!(history.caller(request.session_id).unwrap(), Some(caller));
+    }
+
+    #[test]
+    fn test_history_without_caller_columns_upgraded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.db");
+        let session_id = Uuid::new_v4();
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE sessions (
+                    session_id TEXT PRIMARY KEY,
+                    conversation_id TEXT NOT NULL,
+                    resumed_from TEXT,
+                    prompt TEXT NOT NULL,
+                    user_intent TEXT NOT NULL,
+                    state TEXT NOT NULL,
+                    started_at TEXT NOT NULL,
+                    completed_at TEXT,
+                    error TEXT
+                );",
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO sessions VALUES (?1, 'c', NULL, 'p', 'i', 'completed', 't', NULL, NULL)",
+                params![session_id.to_string()],
+            )
+            .unwrap();
+        }
+
+        let history = SessionHistory::open(&path).unwrap();
+        assert_eq!(history.caller(session_id).unwrap(), Some(Caller::default()));
+    }
+}
@@ -9,7 +9,7 @@
 //! The server implements a REST API with the following key components:
 //!
 //! - **HTTP/HTTPS Server**: Built on Warp with async Tokio runtime
-//! - **Authentication**: Bearer token validation with rate limiting
+//! - **Authentication**: Bearer tokens and managed API keys, with rate limiting
 //! - **Session Management**: Tracks concurrent claude-cli executions
 //! - **Streaming**: Server-Sent Events (SSE) for real-time response streaming
 //! - **Process Management**: Spawns and manages headless claude-cli processes
@@ -27,13 +27,20 @@
 //! ```
 
 pub mod api;
+pub mod api_keys;
+pub mod audit;
 pub mod auth;
 pub mod claude;
 pub mod config;
 pub mod error;
+pub mod history;
 pub mod models;
+pub mod profile_usage;
+pub mod rate_limit;
+pub mod redaction;
 pub mod server;
 pub mod session;
+pub mod transcripts;
 
 // Re-export commonly used types
 pub use config::Config;
@@ -19,10 +19,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         match args[i].as_str() {
             "--dev" => dev_mode = true,
             "--mock" => mock_mode = true,
-            "--config" => {
-                if i + 1 < args.len() {
-                    config_path = Some(PathBuf::from(&args[i + 1]));
-                }
+            "--config" if i + 1 < args.len() => {
+                config_path = Some(PathBuf::from(&args[i + 1]));
             }
             _ => {}
         }
@@ -4,6 +4,8 @@
 //! All types are designed for efficient serialization/deserialization
 //! and include comprehensive validation logic.
 
+use facet_core::redaction::Replacement;
+use facet_types::profiles::RedactionPreferences;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -188,10 +190,20 @@ impl RequestContext {
 /// Configures timeout, token limits, and streaming behavior.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct RequestOptions {
-    /// Timeout in seconds (overrides server default)
+    /// Timeout in seconds (overrides server default); the request is
+    /// stopped once it has run this long
     #[serde(default = "default_timeout")]
     pub timeout_seconds: u64,
 
+    /// Seconds claude-cli may go without output before the request is
+    /// stopped (server default if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout_seconds: Option<u64>,
+
+    /// Percent of either timeout after which a warning is emitted
+    #[serde(default = "default_soft_timeout_percent")]
+    pub soft_timeout_percent: u8,
+
     /// Maximum tokens for Claude response
     #[serde(default = "default_max_tokens")]
     pub max_tokens: u32,
@@ -199,12 +211,54 @@ pub struct RequestOptions {
     /// Enable streaming response
     #[serde(default = "default_stream")]
     pub stream: bool,
+
+    /// Session whose conversation this request continues, so Claude sees
+    /// the earlier prompts and answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume: Option<Uuid>,
+
+    /// Whether answering needs tools (browser control, files, ...); routing
+    /// assumes it does unless told otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub needs_tools: Option<bool>,
+
+    /// How sensitive the request is, which routing may keep it local for
+    #[serde(default)]
+    pub privacy: PrivacyLevel,
+
+    /// Most the request may cost, in USD; routing may send cheaper
+    /// requests to cheaper backends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost_usd: Option<f64>,
+
+    /// Redaction the request asks for on top of its profile's; it can
+    /// only make the server's preferences for the profile stricter. When
+    /// enabled, PII in the prompt and intent is replaced before the
+    /// request leaves the server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redaction: Option<RedactionPreferences>,
+}
+
+/// How sensitive a request is
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum PrivacyLevel {
+    /// May be sent to any backend
+    #[default]
+    Standard,
+
+    /// Should stay on this machine
+    Private,
 }
 
 fn default_timeout() -> u64 {
     300
 }
 
+fn default_soft_timeout_percent() -> u8 {
+    80
+}
+
 fn default_max_tokens() -> u32 {
     100000
 }
@@ -217,8 +271,15 @@ impl Default for RequestOptions {
     fn default() -> Self {
         Self {
             timeout_seconds: default_timeout(),
+            idle_timeout_seconds: None,
+            soft_timeout_percent: default_soft_timeout_percent(),
             max_tokens: default_max_tokens(),
             stream: default_stream(),
+            resume: None,
+            needs_tools: None,
+            privacy: PrivacyLevel::default(),
+            max_cost_usd: None,
+            redaction: None,
         }
     }
 }
@@ -229,7 +290,9 @@ impl Default for RequestOptions {
 /// including context, prompt, and execution options.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FacetRequest {
-    /// Unique session identifier (UUIDv4)
+    /// Unique session identifier (UUIDv4), assigned by the server if the
+    /// client leaves it out
+    #[serde(default = "Uuid::new_v4")]
     pub session_id: Uuid,
 
     /// Request context (screenshots, DOM, intent)
@@ -284,6 +347,20 @@ impl FacetRequest {
             return Err("Timeout cannot exceed 1 hour".to_string());
         }
 
+        if let Some(idle) = self.options.idle_timeout_seconds {
+            if idle == 0 || idle > 3600 {
+                return Err("Idle timeout must be between 1 second and 1 hour".to_string());
+            }
+        }
+
+        if self.options.soft_timeout_percent == 0 || self.options.soft_timeout_percent > 100 {
+            return Err("Soft timeout percent must be between 1 and 100".to_string());
+        }
+
+        if self.options.max_cost_usd.is_some_and(|budget| budget < 0.0) {
+            return Err("Cost budget cannot be negative".to_string());
+        }
+
         Ok(())
     }
 
@@ -311,20 +388,83 @@ pub enum ClaudeEvent {
     /// Text content from Claude
     Content { text: String },
 
-    /// Tool use event
+    /// Tool use event; `id` is what the call's ToolResult refers to
     ToolUse {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
         tool: String,
         params: serde_json::Value,
     },
 
+    /// Output of a tool call
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        is_error: bool,
+    },
+
+    /// Claude's reasoning before it answers
+    Thinking { text: String },
+
+    /// Token usage reported by claude-cli, cumulative for the turn
+    Usage {
+        input_tokens: u64,
+        output_tokens: u64,
+        cache_creation_input_tokens: u64,
+        cache_read_input_tokens: u64,
+    },
+
+    /// Summary claude-cli reports at the end of a turn
+    Summary {
+        is_error: bool,
+        duration_ms: Option<u64>,
+        total_cost_usd: Option<f64>,
+    },
+
     /// Error during execution
     Error { code: String, message: String },
 
     /// Execution complete
     Complete { session_id: Uuid, status: String },
 
+    /// Execution cancelled before it completed; the last event
+    Cancelled { session_id: Uuid },
+
+    /// Something the client should know about, e.g. a timeout drawing near
+    Warning { code: String, message: String },
+
+    /// A transient failure; attempt number `attempt` starts after `delay_ms`
+    Retry {
+        attempt: u32,
+        max_attempts: u32,
+        delay_ms: u64,
+        reason: String,
+    },
+
+    /// Execution stopped at one of its timeouts; the last event
+    TimedOut {
+        session_id: Uuid,
+        timeout: TimeoutKind,
+        after_seconds: u64,
+    },
+
     /// Progress update
     Progress { message: String, percent: u8 },
+
+    /// Running token usage and cost while a response streams
+    UsageDelta {
+        tokens_so_far: u64,
+        estimated_cost_usd: f64,
+        tokens_per_second: f64,
+    },
+
+    /// The prompt and intent as they would be sent, with the PII replaced;
+    /// the request waits until the client confirms or denies it
+    RedactionPreview {
+        redacted_prompt: String,
+        redacted_intent: String,
+        replacements: Vec<Replacement>,
+    },
 }
 
 impl ClaudeEvent {
@@ -336,17 +476,53 @@ impl ClaudeEvent {
     /// # Returns
     /// String in SSE format: "event: type\ndata: json\n\n"
     pub fn to_sse(&self) -> String {
-        let event_type = match self {
+        let data = serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string());
+
+        format!("event: {}\ndata: {}\n\n", self.event_type(), data)
+    }
+
+    /// SSE event type name for this event
+    ///
+    /// # Returns
+    /// Snake case variant name, matching the `type` field of the JSON
+    pub fn event_type(&self) -> &'static str {
+        match self {
             ClaudeEvent::Content { .. } => "content",
             ClaudeEvent::ToolUse { .. } => "tool_use",
+            ClaudeEvent::ToolResult { .. } => "tool_result",
+            ClaudeEvent::Thinking { .. } => "thinking",
+            ClaudeEvent::Usage { .. } => "usage",
+            ClaudeEvent::Summary { .. } => "summary",
             ClaudeEvent::Error { .. } => "error",
             ClaudeEvent::Complete { .. } => "complete",
+            ClaudeEvent::Cancelled { .. } => "cancelled",
+            ClaudeEvent::Warning { .. } => "warning",
+            ClaudeEvent::Retry { .. } => "retry",
+            ClaudeEvent::TimedOut { .. } => "timed_out",
             ClaudeEvent::Progress { .. } => "progress",
-        };
+            ClaudeEvent::UsageDelta { .. } => "usage_delta",
+            ClaudeEvent::RedactionPreview { .. } => "redaction_preview",
+        }
+    }
+}
 
-        let data = serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string());
+/// Which timeout stopped an execution
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutKind {
+    /// It ran for longer than its overall timeout
+    Overall,
+
+    /// claude-cli produced no output for longer than the idle timeout
+    Idle,
+}
 
-        format!("event: {}\ndata: {}\n\n", event_type, data)
+impl std::fmt::Display for TimeoutKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeoutKind::Overall => write!(f, "overall"),
+            TimeoutKind::Idle => write!(f, "idle"),
+        }
     }
 }
 
@@ -389,6 +565,110 @@ pub struct SessionStatus {
     /// Error message (if failed)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+
+    /// Tokens and cost so far, once claude-cli has reported any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<RequestUsage>,
+}
+
+/// Token usage and cost of a single request
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RequestUsage {
+    /// Input tokens, cached ones excluded
+    pub input_tokens: u64,
+
+    /// Output tokens
+    pub output_tokens: u64,
+
+    /// Input tokens written to the prompt cache
+    pub cache_creation_input_tokens: u64,
+
+    /// Input tokens read from the prompt cache
+    pub cache_read_input_tokens: u64,
+
+    /// Cost in USD
+    pub cost_usd: f64,
+
+    /// Whether `cost_usd` is estimated from output tokens rather than
+    /// reported by claude-cli, which it does at the end of a turn
+    pub cost_estimated: bool,
+}
+
+impl Default for RequestUsage {
+    fn default() -> Self {
+        Self {
+            input_tokens: 0,
+            output_tokens: 0,
+            cache_creation_input_tokens: 0,
+            cache_read_input_tokens: 0,
+            cost_usd: 0.0,
+            cost_estimated: true,
+        }
+    }
+}
+
+impl RequestUsage {
+    /// Takes in what an event says about the request's usage
+    ///
+    /// Reported token counts replace earlier ones, the last being the
+    /// turn's total; a reported cost replaces the running estimate.
+    ///
+    /// # Arguments
+    /// * `event` - Event of the request
+    ///
+    /// # Returns
+    /// Whether the event carried usage
+    pub fn record(&mut self, event: &ClaudeEvent) -> bool {
+        match event {
+            ClaudeEvent::Usage {
+                input_tokens,
+                output_tokens,
+                cache_creation_input_tokens,
+                cache_read_input_tokens,
+            } => {
+                self.input_tokens = *input_tokens;
+                self.output_tokens = *output_tokens;
+                self.cache_creation_input_tokens = *cache_creation_input_tokens;
+                self.cache_read_input_tokens = *cache_read_input_tokens;
+            }
+            ClaudeEvent::UsageDelta {
+                tokens_so_far,
+                estimated_cost_usd,
+                ..
+            } => {
+                self.output_tokens = self.output_tokens.max(*tokens_so_far);
+                if self.cost_estimated {
+                    self.cost_usd = *estimated_cost_usd;
+                }
+            }
+            ClaudeEvent::Summary {
+                total_cost_usd: Some(cost),
+                ..
+            } => {
+                self.cost_usd = *cost;
+                self.cost_estimated = false;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Sums up the usage of a request's events
+    ///
+    /// # Arguments
+    /// * `events` - Events of the request, in order
+    ///
+    /// # Returns
+    /// The request's usage, or None if no event carried any
+    pub fn from_events<'a>(events: impl IntoIterator<Item = &'a ClaudeEvent>) -> Option<Self> {
+        let mut usage = Self::default();
+        let mut recorded = false;
+        for event in events {
+            recorded |= usage.record(event);
+        }
+        recorded.then_some(usage)
+    }
 }
 
 /// Session execution state
@@ -610,10 +890,32 @@ mod tests {
         assert!(result.unwrap_err().contains("cannot exceed 1 hour"));
     }
 
+    #[test]
+    fn test_request_invalid_soft_timeouts() {
+        let mut request = FacetRequest {
+            session_id: Uuid::new_v4(),
+            context: create_valid_context(),
+            prompt: "test".to_string(),
+            options: RequestOptions {
+                idle_timeout_seconds: Some(0),
+                ..Default::default()
+            },
+        };
+        let result = request.validate(10, 50000, 1000);
+        assert!(result.unwrap_err().contains("Idle timeout"));
+
+        request.options.idle_timeout_seconds = Some(30);
+        request.options.soft_timeout_percent = 0;
+        let result = request.validate(10, 50000, 1000);
+        assert!(result.unwrap_err().contains("Soft timeout percent"));
+    }
+
     #[test]
     fn test_request_options_defaults() {
         let options = RequestOptions::default();
         assert_eq!(options.timeout_seconds, 300);
+        assert_eq!(options.idle_timeout_seconds, None);
+        assert_eq!(options.soft_timeout_percent, 80);
         assert_eq!(options.max_tokens, 100000);
         assert!(options.stream);
     }
@@ -633,6 +935,7 @@ mod tests {
     #[test]
     fn test_claude_event_to_sse_tool_use() {
         let event = ClaudeEvent::ToolUse {
+            id: None,
             tool: "cdp_command".to_string(),
             params: serde_json::json!({"command": "click"}),
         };
@@ -663,6 +966,53 @@ mod tests {
         assert!(sse.contains("success"));
     }
 
+    #[test]
+    fn test_claude_event_to_sse_cancelled() {
+        let session_id = Uuid::new_v4();
+        let event = ClaudeEvent::Cancelled { session_id };
+        let sse = event.to_sse();
+        assert!(sse.contains("event: cancelled"));
+        assert!(sse.contains(&session_id.to_string()));
+    }
+
+    #[test]
+    fn test_claude_event_to_sse_timed_out() {
+        let event = ClaudeEvent::TimedOut {
+            session_id: Uuid::new_v4(),
+            timeout: TimeoutKind::Idle,
+            after_seconds: 60,
+        };
+        let sse = event.to_sse();
+        assert!(sse.contains("event: timed_out"));
+        assert!(sse.contains("\"timeout\":\"idle\""));
+    }
+
+    #[test]
+    fn test_claude_event_to_sse_retry() {
+        let event = ClaudeEvent::Retry {
+            attempt: 2,
+            max_attempts: 3,
+            delay_ms: 1000,
+            reason: "Rate limit exceeded".to_string(),
+        };
+        let sse = event.to_sse();
+        assert!(sse.contains("event: retry"));
+        assert!(sse.contains("\"delay_ms\":1000"));
+    }
+
+    #[test]
+    fn test_claude_event_to_sse_usage_delta() {
+        let event = ClaudeEvent::UsageDelta {
+            tokens_so_far: 120,
+            estimated_cost_usd: 0.0018,
+            tokens_per_second: 40.0,
+        };
+        let sse = event.to_sse();
+        assert!(sse.contains("event: usage_delta"));
+        assert_eq!(event.event_type(), "usage_delta");
+        assert!(sse.contains("\"tokens_so_far\":120"));
+    }
+
     #[test]
     fn test_session_status_serialization() {
         let status = SessionStatus {
@@ -671,9 +1021,57 @@ mod tests {
             started_at: "2025-10-17T10:30:00Z".to_string(),
             completed_at: None,
             error: None,
+            usage: None,
         };
         let json = serde_json::to_string(&status).unwrap();
         assert!(json.contains("running"));
+        assert!(!json.contains("usage"));
+    }
+
+    #[test]
+    fn test_request_usage_from_events() {
+        let usage_delta = |tokens_so_far, estimated_cost_usd| ClaudeEvent::UsageDelta {
+            tokens_so_far,
+            estimated_cost_usd,
+            tokens_per_second: 10.0,
+        };
+        let content = ClaudeEvent::Content {
+            text: "Hello".to_string(),
+        };
+        assert!(RequestUsage::from_events([&content]).is_none());
+
+        // Estimated until claude-cli reports
+        let usage = RequestUsage::from_events([&content, &usage_delta(40, 0.0006)]).unwrap();
+        assert_eq!(usage.output_tokens, 40);
+        assert!(usage.cost_estimated);
+
+        let events = [
+            usage_delta(40, 0.0006),
+            ClaudeEvent::Usage {
+                input_tokens: 1200,
+                output_tokens: 52,
+                cache_creation_input_tokens: 0,
+                cache_read_input_tokens: 800,
+            },
+            ClaudeEvent::Summary {
+                is_error: false,
+                duration_ms: Some(4210),
+                total_cost_usd: Some(0.0042),
+            },
+            usage_delta(52, 0.0008),
+        ];
+        let usage = RequestUsage::from_events(&events).unwrap();
+        assert_eq!(
+            usage,
+            RequestUsage {
+                input_tokens: 1200,
+                output_tokens: 52,
+                cache_creation_input_tokens: 0,
+                cache_read_input_tokens: 800,
+                cost_usd: 0.0042,
+                cost_estimated: false,
+            }
+        );
     }
 
     #[test]
@@ -715,5 +1113,27 @@ mod tests {
         assert_eq!(request.options.timeout_seconds, 300);
         assert_eq!(request.options.max_tokens, 100000);
         assert!(request.options.stream);
+        assert!(request.options.resume.is_none());
+    }
+
+    #[test]
+    fn test_request_without_session_id_is_assigned_one() {
+        let json = r#"{
+            "context": {
+                "screenshots": [],
+                "dom_state": {"accessible_tree": "tree", "interactive_elements": []},
+                "user_intent": "test intent"
+            },
+            "prompt": "test prompt",
+            "options": {"resume": "550e8400-e29b-41d4-a716-446655440000"}
+        }"#;
+
+        let first: FacetRequest = serde_json::from_str(json).unwrap();
+        let second: FacetRequest = serde_json::from_str(json).unwrap();
+        assert_ne!(first.session_id, second.session_id);
+        assert_eq!(
+            first.options.resume,
+            Some(Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap())
+        );
     }
 }
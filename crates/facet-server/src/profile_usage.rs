@@ -0,0 +1,183 @@
+//! Token usage and cost per profile
+//!
+//! Every finished request adds its usage to the running totals of the
+//! profile it acted for (the `X-Facet-Profile` header, or `default`
+//! without one). Totals are kept in a JSON file, so they survive restarts,
+//! and served at /api/v1/usage; the desktop app folds them into the
+//! user's `UserStats`.
+
+use crate::error::FacetError;
+use crate::models::RequestUsage;
+use facet_types::profiles::{storage, UsageTotals};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// File name of the store under ~/.facet
+const USAGE_FILE: &str = "usage.json";
+
+/// Profile that requests naming none are counted under
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Usage totals per profile, backed by a JSON file or kept in memory
+#[derive(Debug)]
+pub struct ProfileUsageStore {
+    path: Option<PathBuf>,
+    totals: Mutex<BTreeMap<String, UsageTotals>>,
+}
+
+impl ProfileUsageStore {
+    /// Returns the store's default location, ~/.facet/usage.json
+    ///
+    /// # Errors
+    /// Returns FacetError::Config if the home directory cannot be found
+    pub fn default_path() -> Result<PathBuf, FacetError> {
+        storage::get_facet_dir(None)
+            .map(|dir| dir.join(USAGE_FILE))
+            .map_err(|e| FacetError::Config(e.to_string()))
+    }
+
+    /// Opens the store at `path`, which is created with the first request
+    ///
+    /// # Arguments
+    /// * `path` - Path to the JSON file of totals
+    ///
+    /// # Returns
+    /// ProfileUsageStore backed by the file
+    ///
+    /// # Errors
+    /// Returns FacetError::Config if the file exists but cannot be read
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, FacetError> {
+        let path = path.as_ref().to_path_buf();
+        let totals = if path.exists() {
+            let contents = std::fs::read_to_string(&path).map_err(|e| {
+                FacetError::Config(format!("Failed to read usage {}: {}", path.display(), e))
+            })?;
+            serde_json::from_str(&contents).map_err(|e| {
+                FacetError::Config(format!("Failed to parse usage {}: {}", path.display(), e))
+            })?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            path: Some(path),
+            totals: Mutex::new(totals),
+        })
+    }
+
+    /// Creates a store kept in memory, whose totals last until the server
+    /// stops
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            totals: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Counts a finished request against its profile
+    ///
+    /// # Arguments
+    /// * `profile` - Profile the request acted for, if it named one
+    /// * `usage` - Usage of the request
+    ///
+    /// # Errors
+    /// Returns FacetError::Internal if the totals cannot be saved; they
+    /// are updated in memory regardless
+    pub fn add(&self, profile: Option<&str>, usage: &RequestUsage) -> Result<(), FacetError> {
+        let mut totals = self.totals();
+        totals
+            .entry(profile.unwrap_or(DEFAULT_PROFILE).to_string())
+            .or_default()
+            .add(&UsageTotals {
+                requests: 1,
+                input_tokens: usage.input_tokens,
+                output_tokens: usage.output_tokens,
+                cache_creation_input_tokens: usage.cache_creation_input_tokens,
+                cache_read_input_tokens: usage.cache_read_input_tokens,
+                cost_usd: usage.cost_usd,
+            });
+        self.save(&totals)
+    }
+
+    /// Returns a profile's totals, all zero if it has made no requests
+    pub fn get(&self, profile: &str) -> UsageTotals {
+        self.totals().get(profile).cloned().unwrap_or_default()
+    }
+
+    /// Returns the totals of every profile that has made requests
+    pub fn all(&self) -> BTreeMap<String, UsageTotals> {
+        self.totals().clone()
+    }
+
+    fn totals(&self) -> MutexGuard<'_, BTreeMap<String, UsageTotals>> {
+        self.totals.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Writes the totals to a temporary file and renames it over the
+    /// store, so a crash mid-write can't lose them
+    fn save(&self, totals: &BTreeMap<String, UsageTotals>) -> Result<(), FacetError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let write_error =
+            |e: std::io::Error| FacetError::Internal(format!("Failed to save usage: {}", e));
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(write_error)?;
+        }
+        let contents = serde_json::to_string_pretty(totals)
+            .map_err(|e| FacetError::Internal(format!("Failed to serialize usage: {}", e)))?;
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(&temp, contents).map_err(write_error)?;
+        std::fs::rename(&temp, path).map_err(write_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(output_tokens: u64, cost_usd: f64) -> RequestUsage {
+        RequestUsage {
+            input_tokens: 1000,
+            output_tokens,
+            cost_usd,
+            cost_estimated: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_totals_per_profile() {
+        let store = ProfileUsageStore::in_memory();
+        store.add(Some("work"), &usage(50, 0.25)).unwrap();
+        store.add(Some("work"), &usage(30, 0.5)).unwrap();
+        store.add(None, &usage(10, 0.125)).unwrap();
+
+        let work = store.get("work");
+        assert_eq!(work.requests, 2);
+        assert_eq!(work.input_tokens, 2000);
+        assert_eq!(work.output_tokens, 80);
+        assert_eq!(work.cost_usd, 0.75);
+        assert_eq!(store.get(DEFAULT_PROFILE).requests, 1);
+        assert_eq!(store.get("shopping"), UsageTotals::default());
+        assert_eq!(store.all().len(), 2);
+    }
+
+    #[test]
+    fn test_totals_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("usage.json");
+
+        ProfileUsageStore::open(&path)
+            .unwrap()
+            .add(Some("work"), &usage(50, 0.25))
+            .unwrap();
+
+        let store = ProfileUsageStore::open(&path).unwrap();
+        assert_eq!(store.get("work").output_tokens, 50);
+        store.add(Some("work"), &usage(50, 0.25)).unwrap();
+        assert_eq!(store.get("work").requests, 2);
+    }
+}
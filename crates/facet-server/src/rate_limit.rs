@@ -0,0 +1,304 @@
+//! Token-bucket rate limiting
+//!
+//! Every API key, and every profile a request names in the
+//! `X-Facet-Profile` header, has a bucket that refills at the configured
+//! rate up to its burst size. A request takes one token from each bucket it
+//! is counted against and is throttled if any of them is empty, so a client
+//! can neither exceed its key's budget by spreading requests over profiles
+//! nor let several keys flood one profile. Throttled requests are counted
+//! for the metrics endpoint.
+//!
+//! Only requests let through create buckets, and buckets that have refilled
+//! to capacity, which are no different from having none, are dropped now
+//! and then, so clients naming ever new profiles don't grow the limiter.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// How often buckets that have refilled to capacity are dropped
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Most profiles counted by name in the metrics
+const MAX_PROFILE_METRICS: usize = 1000;
+
+/// Name the metrics count throttled requests of any further profiles under
+pub const OTHER_PROFILES: &str = "(other)";
+
+/// What a request is counted against
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    /// Bearer token the request authenticated with
+    ApiKey(String),
+
+    /// Profile the request acts for
+    Profile(String),
+}
+
+/// Counts of rate-limited requests since the server started
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RateLimitMetrics {
+    /// Requests let through
+    pub allowed: u64,
+
+    /// Requests throttled
+    pub throttled: u64,
+
+    /// Throttled requests per profile whose bucket was empty; API keys
+    /// are secrets, so requests throttled by key are only in the total.
+    /// Past 1000 profiles, the rest are counted under `(other)`.
+    pub throttled_by_profile: HashMap<String, u64>,
+}
+
+/// Tokens left in a bucket as of `updated`
+#[derive(Debug, Clone)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Buckets of every key not at capacity, give or take a sweep
+#[derive(Debug)]
+struct Buckets {
+    by_key: HashMap<RateLimitKey, Bucket>,
+
+    /// When full buckets were last dropped
+    swept: Instant,
+}
+
+/// Token-bucket rate limiter keyed by API key and profile
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Tokens a full bucket holds: the largest burst let through at once
+    capacity: f64,
+
+    /// Tokens added back per second
+    refill_per_second: f64,
+
+    buckets: Mutex<Buckets>,
+
+    metrics: Mutex<RateLimitMetrics>,
+}
+
+impl RateLimiter {
+    /// Creates a rate limiter
+    ///
+    /// # Arguments
+    /// * `requests_per_minute` - Sustained rate each key may make requests at
+    /// * `burst` - Requests each key may make at once after being idle
+    ///
+    /// # Returns
+    /// New RateLimiter with all buckets full
+    pub fn new(requests_per_minute: u32, burst: u32) -> Self {
+        Self {
+            capacity: f64::from(burst.max(1)),
+            refill_per_second: f64::from(requests_per_minute) / 60.0,
+            buckets: Mutex::new(Buckets {
+                by_key: HashMap::new(),
+                swept: Instant::now(),
+            }),
+            metrics: Mutex::new(RateLimitMetrics::default()),
+        }
+    }
+
+    /// Takes a token from the bucket of every key
+    ///
+    /// Takes nothing if any bucket is empty.
+    ///
+    /// # Arguments
+    /// * `keys` - Keys the request is counted against
+    ///
+    /// # Returns
+    /// Ok(()) if the request may go ahead, Err with how long until it may
+    /// be retried if it is throttled
+    pub async fn acquire(&self, keys: &[RateLimitKey]) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().await;
+        self.sweep(&mut buckets, now);
+
+        let mut wait = Duration::ZERO;
+        let mut empty = Vec::new();
+        for key in keys {
+            // A key without a bucket has a full one
+            let Some(bucket) = buckets.by_key.get_mut(key) else {
+                continue;
+            };
+            self.refill(bucket, now);
+            if bucket.tokens < 1.0 {
+                wait = wait.max(self.time_to_refill(1.0 - bucket.tokens));
+                empty.push(key);
+            }
+        }
+
+        let mut metrics = self.metrics.lock().await;
+        if wait > Duration::ZERO {
+            metrics.throttled += 1;
+            for key in empty {
+                if let RateLimitKey::Profile(profile) = key {
+                    let counted = &mut metrics.throttled_by_profile;
+                    let name =
+                        if counted.contains_key(profile) || counted.len() < MAX_PROFILE_METRICS {
+                            profile.as_str()
+                        } else {
+                            OTHER_PROFILES
+                        };
+                    *counted.entry(name.to_string()).or_insert(0) += 1;
+                }
+            }
+            return Err(wait);
+        }
+
+        for key in keys {
+            let bucket = buckets.by_key.entry(key.clone()).or_insert(Bucket {
+                tokens: self.capacity,
+                updated: now,
+            });
+            bucket.tokens -= 1.0;
+        }
+        metrics.allowed += 1;
+        Ok(())
+    }
+
+    /// Returns the tokens a key's bucket holds now
+    ///
+    /// # Arguments
+    /// * `key` - Key to check
+    ///
+    /// # Returns
+    /// Tokens left; a key not seen yet has a full bucket
+    pub async fn available(&self, key: &RateLimitKey) -> f64 {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().await;
+        match buckets.by_key.get_mut(key) {
+            Some(bucket) => {
+                self.refill(bucket, now);
+                bucket.tokens
+            }
+            None => self.capacity,
+        }
+    }
+
+    /// Refills a key's bucket
+    ///
+    /// # Arguments
+    /// * `key` - Key to reset
+    pub async fn reset(&self, key: &RateLimitKey) {
+        self.buckets.lock().await.by_key.remove(key);
+    }
+
+    /// Returns the number of tokens a full bucket holds
+    pub fn capacity(&self) -> f64 {
+        self.capacity
+    }
+
+    /// Returns counts of allowed and throttled requests
+    pub async fn metrics(&self) -> RateLimitMetrics {
+        self.metrics.lock().await.clone()
+    }
+
+    /// Drops the buckets that have refilled to capacity, at most once per
+    /// `SWEEP_INTERVAL`
+    fn sweep(&self, buckets: &mut Buckets, now: Instant) {
+        if now.saturating_duration_since(buckets.swept) < SWEEP_INTERVAL {
+            return;
+        }
+        buckets.swept = now;
+        buckets.by_key.retain(|_, bucket| {
+            self.refill(bucket, now);
+            bucket.tokens < self.capacity
+        });
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        bucket.updated = now;
+    }
+
+    fn time_to_refill(&self, tokens: f64) -> Duration {
+        if self.refill_per_second <= 0.0 {
+            return Duration::MAX;
+        }
+        Duration::from_secs_f64(tokens / self.refill_per_second)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(token: &str) -> RateLimitKey {
+        RateLimitKey::ApiKey(token.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_burst_then_throttled_until_refill() {
+        // One request a second, bursts of 2
+        let limiter = RateLimiter::new(60, 2);
+        let keys = [key("a")];
+
+        assert!(limiter.acquire(&keys).await.is_ok());
+        assert!(limiter.acquire(&keys).await.is_ok());
+        let wait = limiter.acquire(&keys).await.unwrap_err();
+        assert!(wait > Duration::from_millis(900) && wait <= Duration::from_secs(1));
+
+        // Other keys have their own bucket
+        assert!(limiter.acquire(&[key("b")]).await.is_ok());
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert!(limiter.acquire(&keys).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_profile_bucket_is_shared_across_keys() {
+        let limiter = RateLimiter::new(60, 1);
+        let profile = RateLimitKey::Profile("work".to_string());
+
+        assert!(limiter.acquire(&[key("a"), profile.clone()]).await.is_ok());
+        // The profile is empty: throttled, and key b keeps its token
+        assert!(limiter.acquire(&[key("b"), profile.clone()]).await.is_err());
+        assert_eq!(limiter.available(&key("b")).await, 1.0);
+
+        let metrics = limiter.metrics().await;
+        assert_eq!(metrics.allowed, 1);
+        assert_eq!(metrics.throttled, 1);
+        assert_eq!(metrics.throttled_by_profile["work"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_throttled_requests_create_no_buckets() {
+        let limiter = RateLimiter::new(60, 1);
+        assert!(limiter.acquire(&[key("a")]).await.is_ok());
+
+        // Throttled by its key, whatever profile it names
+        for i in 0..10 {
+            let profile = RateLimitKey::Profile(format!("profile-{}", i));
+            assert!(limiter.acquire(&[key("a"), profile]).await.is_err());
+        }
+        assert_eq!(limiter.buckets.lock().await.by_key.len(), 1);
+        assert!(limiter.metrics().await.throttled_by_profile.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sweep_drops_full_buckets() {
+        // One request a second, bursts of 5
+        let limiter = RateLimiter::new(60, 5);
+        assert!(limiter.acquire(&[key("a")]).await.is_ok());
+        for _ in 0..5 {
+            assert!(limiter.acquire(&[key("b")]).await.is_ok());
+        }
+
+        let mut buckets = limiter.buckets.lock().await;
+        assert_eq!(buckets.by_key.len(), 2);
+        // Two seconds on, "a" is full again and "b" isn't yet
+        let later = buckets.swept + SWEEP_INTERVAL;
+        for bucket in buckets.by_key.values_mut() {
+            bucket.updated = later - Duration::from_secs(2);
+        }
+        limiter.sweep(&mut buckets, later);
+
+        assert_eq!(buckets.by_key.len(), 1);
+        assert!(buckets.by_key.contains_key(&key("b")));
+    }
+}
@@ -0,0 +1,486 @@
+//! PII redaction before requests leave the server
+//!
+//! Each profile's redaction preferences come from the server's
+//! configuration (`[redaction]`), not from the client. A request may ask
+//! for stricter ones in its options, but never for laxer: it can turn
+//! redaction or confirmation on and lower the auto-approve threshold, not
+//! the reverse.
+//!
+//! A request with redaction enabled has the PII in its prompt and intent
+//! replaced by placeholders such as `[EMAIL_1]` before it is executed. If
+//! the preferences want the user to review that, the request first emits a
+//! `redaction_preview` event and waits until the client confirms or denies
+//! it, over the WebSocket or with POST /api/v1/requests/:id/redaction. The
+//! placeholders in Claude's answer are swapped back for the real values as
+//! it streams; tool calls only ever see the placeholders.
+
+use crate::claude::Executor;
+use crate::error::FacetError;
+use crate::models::{ClaudeEvent, FacetRequest};
+use crate::session::SessionManager;
+use async_stream::stream;
+use facet_core::pii::PiiDetector;
+use facet_core::redaction::{
+    RedactionDecision, RedactionError, RedactionPolicy, RedactionPreview, RedactionSession,
+};
+use facet_types::profiles::RedactionPreferences;
+use futures::{Stream, StreamExt};
+use std::sync::{Arc, LazyLock};
+
+/// Stream of events of one execution
+pub type EventStream = Box<dyn Stream<Item = Result<ClaudeEvent, FacetError>> + Send + Unpin>;
+
+/// Detector used for every request, compiled once
+static DETECTOR: LazyLock<PiiDetector> = LazyLock::new(PiiDetector::new);
+
+/// Tightens a profile's redaction preferences with a request's
+///
+/// # Arguments
+/// * `profile` - Preferences the server holds for the request's profile
+/// * `requested` - Preferences the request asks for, if any
+///
+/// # Returns
+/// Preferences at least as strict as both: redaction and confirmation are
+/// on if either asks for them, and the lower auto-approve threshold of
+/// those that confirm applies
+pub fn effective_preferences(
+    profile: &RedactionPreferences,
+    requested: Option<&RedactionPreferences>,
+) -> RedactionPreferences {
+    let requested = match requested {
+        Some(requested) if requested.enabled => requested,
+        _ => return profile.clone(),
+    };
+    if !profile.enabled {
+        return requested.clone();
+    }
+
+    let auto_approve_threshold = match (profile.confirm_before_send, requested.confirm_before_send)
+    {
+        (true, true) => profile
+            .auto_approve_threshold
+            .min(requested.auto_approve_threshold),
+        (true, false) => profile.auto_approve_threshold,
+        (false, _) => requested.auto_approve_threshold,
+    };
+    RedactionPreferences {
+        enabled: true,
+        confirm_before_send: profile.confirm_before_send || requested.confirm_before_send,
+        auto_approve_threshold,
+    }
+}
+
+/// Redacts a request's prompt and intent
+///
+/// The intent reuses the prompt's placeholders for the values they share.
+///
+/// # Arguments
+/// * `detector` - Detector finding the PII
+/// * `request` - Request to redact
+///
+/// # Returns
+/// Preview of the redacted prompt with every replacement made in either,
+/// and the redacted intent
+pub fn redact(detector: &PiiDetector, request: &FacetRequest) -> (RedactionPreview, String) {
+    let intent = &request.context.user_intent;
+    let mut prompt = detector.redact(&request.prompt);
+    let mut redacted_intent = RedactionPreview {
+        redacted_text: intent.clone(),
+        replacements: prompt.replacements.clone(),
+    };
+    for replacement in &prompt.replacements {
+        redacted_intent.redact_value(&replacement.category, &replacement.original);
+    }
+    for found in detector.detect(intent) {
+        redacted_intent.redact_value(&found.category, &found.value);
+    }
+    prompt.replacements = redacted_intent.replacements;
+    (prompt, redacted_intent.redacted_text)
+}
+
+/// Executes a request, redacting it first if its profile or the request
+/// asks for that
+///
+/// # Arguments
+/// * `request` - Request to execute
+/// * `resume` - Conversation the request continues, if any
+/// * `executor` - Executor to run the request with
+/// * `session_manager` - Session tracking, which holds the profile's
+///   redaction preferences and passes on the client's reply to a
+///   redaction preview
+///
+/// # Returns
+/// Events of the execution, preceded by the redaction preview if the
+/// client has to confirm it; a denied preview ends in a
+/// `REDACTION_REJECTED` error event without executing anything
+pub async fn execute(
+    request: FacetRequest,
+    resume: Option<String>,
+    executor: Arc<dyn Executor>,
+    session_manager: Arc<SessionManager>,
+) -> EventStream {
+    let profile = session_manager
+        .redaction_preferences(request.session_id)
+        .await;
+    let preferences = effective_preferences(&profile, request.options.redaction.as_ref());
+    if !preferences.enabled {
+        return run(executor.as_ref(), request, resume).await;
+    }
+
+    let (preview, redacted_intent) = redact(&DETECTOR, &request);
+    if preview.is_empty() {
+        return run(executor.as_ref(), request, resume).await;
+    }
+    let rehydrator = RedactionSession::new(&preview).and_then(|session| session.open());
+    let mut rehydrator = match rehydrator {
+        Ok(rehydrator) => rehydrator,
+        Err(e) => {
+            let error = FacetError::Internal(format!("Failed to redact request: {}", e));
+            return Box::new(futures::stream::iter([Err::<ClaudeEvent, _>(error)]));
+        }
+    };
+
+    let session_id = request.session_id;
+    let confirm = matches!(
+        RedactionPolicy::from(&preferences).review(preview.clone()),
+        RedactionDecision::NeedsConfirmation(_)
+    );
+    let mut request = request;
+    request.prompt = preview.redacted_text.clone();
+    request.context.user_intent = redacted_intent.clone();
+
+    let stream = stream! {
+        if confirm {
+            let reply = session_manager.await_redaction_reply(session_id).await;
+            yield Ok(ClaudeEvent::RedactionPreview {
+                redacted_prompt: preview.redacted_text,
+                redacted_intent,
+                replacements: preview.replacements,
+            });
+            match reply.await {
+                Ok(true) => {}
+                Ok(false) => {
+                    yield Ok(ClaudeEvent::Error {
+                        code: "REDACTION_REJECTED".to_string(),
+                        message: RedactionError::Rejected.to_string(),
+                    });
+                    return;
+                }
+                // The session finished while it waited
+                Err(_) => return,
+            }
+        }
+
+        let mut events = run(executor.as_ref(), request, resume).await;
+        while let Some(result) = events.next().await {
+            match result {
+                Ok(ClaudeEvent::Content { text }) => {
+                    let text = rehydrator.push(&text);
+                    if !text.is_empty() {
+                        yield Ok(ClaudeEvent::Content { text });
+                    }
+                }
+                Ok(ClaudeEvent::Thinking { text }) => yield Ok(ClaudeEvent::Thinking {
+                    text: rehydrator.rehydrate(&text),
+                }),
+                other => {
+                    // A placeholder cut short by anything else won't be completed
+                    let text = rehydrator.finish();
+                    if !text.is_empty() {
+                        yield Ok(ClaudeEvent::Content { text });
+                    }
+                    yield other;
+                }
+            }
+        }
+        let text = rehydrator.finish();
+        if !text.is_empty() {
+            yield Ok(ClaudeEvent::Content { text });
+        }
+    };
+
+    Box::new(Box::pin(stream))
+}
+
+/// Runs a request as the executor would without redaction
+async fn run(
+    executor: &dyn Executor,
+    request: FacetRequest,
+    resume: Option<String>,
+) -> EventStream {
+    match resume {
+        Some(conversation_id) => executor.resume(request, conversation_id).await,
+        None => executor.execute(request).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Caller;
+    use crate::config::RedactionConfig;
+    use crate::models::{DomState, RequestContext, RequestOptions};
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    /// Executor answering with the prompt it got, split mid-placeholder
+    #[derive(Default)]
+    struct EchoExecutor {
+        prompts: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Executor for EchoExecutor {
+        async fn execute(&self, request: FacetRequest) -> EventStream {
+            self.prompts.lock().unwrap().push(request.prompt.clone());
+            let (head, tail) = request.prompt.split_at(request.prompt.len() - 3);
+            let events = [
+                Ok(ClaudeEvent::Content {
+                    text: head.to_string(),
+                }),
+                Ok(ClaudeEvent::Content {
+                    text: tail.to_string(),
+                }),
+                Ok(ClaudeEvent::Complete {
+                    session_id: request.session_id,
+                    status: "success".to_string(),
+                }),
+            ];
+            Box::new(futures::stream::iter(events))
+        }
+
+        async fn cancel(&self, _session_id: Uuid) -> Result<(), FacetError> {
+            Ok(())
+        }
+    }
+
+    fn request(threshold: usize) -> FacetRequest {
+        FacetRequest {
+            session_id: Uuid::new_v4(),
+            context: RequestContext {
+                screenshots: vec![],
+                dom_state: DomState {
+                    accessible_tree: String::new(),
+                    interactive_elements: vec![],
+                },
+                user_intent: "Reply to bob@example.com or 555-123-4567".to_string(),
+            },
+            prompt: "Draft a note to bob@example.com".to_string(),
+            options: RequestOptions {
+                redaction: Some(RedactionPreferences {
+                    enabled: true,
+                    confirm_before_send: true,
+                    auto_approve_threshold: threshold,
+                }),
+                ..Default::default()
+            },
+        }
+    }
+
+    async fn start(request: FacetRequest) -> (Arc<EchoExecutor>, Arc<SessionManager>, EventStream) {
+        let executor = Arc::new(EchoExecutor::default());
+        let session_manager = Arc::new(SessionManager::new(10));
+        session_manager
+            .register_for(&request, Caller::default(), 10)
+            .await
+            .unwrap();
+        let events = execute(request, None, executor.clone(), session_manager.clone()).await;
+        (executor, session_manager, events)
+    }
+
+    fn content(events: &[Result<ClaudeEvent, FacetError>]) -> String {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Ok(ClaudeEvent::Content { text }) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_redact_shares_placeholders_with_intent() {
+        let (preview, intent) = redact(&DETECTOR, &request(0));
+        assert_eq!(preview.redacted_text, "Draft a note to [EMAIL_1]");
+        assert_eq!(intent, "Reply to [EMAIL_1] or [PHONE_1]");
+        assert_eq!(preview.replacements.len(), 2);
+    }
+
+    #[test]
+    fn test_effective_preferences_only_tighten() {
+        let preferences =
+            |enabled, confirm_before_send, auto_approve_threshold| RedactionPreferences {
+                enabled,
+                confirm_before_send,
+                auto_approve_threshold,
+            };
+        let profile = preferences(true, true, 3);
+
+        // A request can't turn the profile's redaction or confirmation off
+        assert_eq!(effective_preferences(&profile, None), profile);
+        assert_eq!(
+            effective_preferences(&profile, Some(&preferences(false, false, 10))),
+            profile
+        );
+        assert_eq!(
+            effective_preferences(&profile, Some(&preferences(true, false, 10))),
+            profile
+        );
+        // nor raise its threshold, but it can lower it
+        assert_eq!(
+            effective_preferences(&profile, Some(&preferences(true, true, 10))),
+            profile
+        );
+        assert_eq!(
+            effective_preferences(&profile, Some(&preferences(true, true, 1))),
+            preferences(true, true, 1)
+        );
+        // and it can turn on what the profile leaves off
+        assert_eq!(
+            effective_preferences(
+                &preferences(false, true, 0),
+                Some(&preferences(true, true, 2))
+            ),
+            preferences(true, true, 2)
+        );
+        assert_eq!(
+            effective_preferences(
+                &preferences(true, false, 0),
+                Some(&preferences(true, true, 2))
+            ),
+            preferences(true, true, 2)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_profile_preferences_override_request() {
+        let mut redaction = RedactionConfig::default();
+        redaction.profiles.insert(
+            "work".to_string(),
+            RedactionPreferences {
+                enabled: true,
+                confirm_before_send: true,
+                auto_approve_threshold: 0,
+            },
+        );
+        let session_manager = Arc::new(SessionManager::new(10).with_redaction(redaction));
+        let executor = Arc::new(EchoExecutor::default());
+
+        // The client asks for no redaction at all
+        let mut request = request(0);
+        request.options.redaction = Some(RedactionPreferences::default());
+        let caller = Caller {
+            profile: Some("work".to_string()),
+            ..Default::default()
+        };
+        session_manager
+            .register_for(&request, caller, 10)
+            .await
+            .unwrap();
+        let mut events = execute(request, None, executor.clone(), session_manager).await;
+
+        assert!(matches!(
+            events.next().await,
+            Some(Ok(ClaudeEvent::RedactionPreview { .. }))
+        ));
+        assert!(executor.prompts.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_confirmed_preview_sends_redacted_request() {
+        let request = request(0);
+        let session_id = request.session_id;
+        let (executor, session_manager, mut events) = start(request).await;
+
+        match events.next().await {
+            Some(Ok(ClaudeEvent::RedactionPreview {
+                redacted_prompt,
+                replacements,
+                ..
+            })) => {
+                assert_eq!(redacted_prompt, "Draft a note to [EMAIL_1]");
+                assert_eq!(replacements[0].original, "bob@example.com");
+            }
+            other => panic!("expected a redaction preview, got {:?}", other),
+        }
+        // Nothing runs until the client confirms
+        assert!(executor.prompts.lock().unwrap().is_empty());
+        session_manager
+            .reply_to_redaction(session_id, true)
+            .await
+            .unwrap();
+
+        let rest: Vec<_> = events.collect().await;
+        assert_eq!(
+            executor.prompts.lock().unwrap().as_slice(),
+            ["Draft a note to [EMAIL_1]"]
+        );
+        // The answer comes back with the real values
+        assert_eq!(content(&rest), "Draft a note to bob@example.com");
+        assert!(matches!(
+            rest.last(),
+            Some(Ok(ClaudeEvent::Complete { .. }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_denied_preview_sends_nothing() {
+        let request = request(0);
+        let session_id = request.session_id;
+        let (executor, session_manager, mut events) = start(request).await;
+
+        assert!(matches!(
+            events.next().await,
+            Some(Ok(ClaudeEvent::RedactionPreview { .. }))
+        ));
+        session_manager
+            .reply_to_redaction(session_id, false)
+            .await
+            .unwrap();
+
+        match events.next().await {
+            Some(Ok(ClaudeEvent::Error { code, .. })) => assert_eq!(code, "REDACTION_REJECTED"),
+            other => panic!("expected an error, got {:?}", other),
+        }
+        assert!(events.next().await.is_none());
+        assert!(executor.prompts.lock().unwrap().is_empty());
+
+        // Nothing waits for a second reply
+        assert!(session_manager
+            .reply_to_redaction(session_id, true)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_threshold_skips_preview() {
+        let (executor, _, events) = start(request(2)).await;
+        let events: Vec<_> = events.collect().await;
+
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, Ok(ClaudeEvent::RedactionPreview { .. }))));
+        assert_eq!(
+            executor.prompts.lock().unwrap().as_slice(),
+            ["Draft a note to [EMAIL_1]"]
+        );
+        assert_eq!(content(&events), "Draft a note to bob@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_while_waiting_ends_stream() {
+        let request = request(0);
+        let session_id = request.session_id;
+        let (executor, session_manager, mut events) = start(request).await;
+
+        assert!(matches!(
+            events.next().await,
+            Some(Ok(ClaudeEvent::RedactionPreview { .. }))
+        ));
+        session_manager.cancel(session_id).await.unwrap();
+
+        assert!(events.next().await.is_none());
+        assert!(executor.prompts.lock().unwrap().is_empty());
+    }
+}
@@ -4,19 +4,32 @@
 
 use crate::{
     api::{
-        delete_session_handler, execute_handler, get_session_handler, health::HealthState,
-        health_handler, inference_handler,
+        audit_handler, audit_verify_handler, cancel_request_handler, create_api_key_handler,
+        delete_session_handler, execute_handler, get_request_handler, get_session_handler,
+        get_session_history_handler, health::HealthState, health_handler, inference_handler,
+        list_api_keys_handler, profile_usage_handler, rate_limit_metrics_handler,
+        redaction_reply_handler, request_events_handler, revoke_api_key_handler,
+        submit_request_handler, usage_handler, ws_handler,
     },
-    auth::{with_auth, AuthState},
-    claude::{ClaudeExecutor, Executor, MockClaudeExecutor},
+    api_keys::ApiKeyStore,
+    audit::{AuditLog, AuditQuery},
+    auth::{with_admin, with_auth, with_caller, AuthRejection, AuthState, RateLimitRejection},
+    claude::{
+        pool::PoolConfig, retry::RetryPolicy, ClaudeExecutor, Executor, MockClaudeExecutor, Router,
+        RoutingPolicy,
+    },
+    history::SessionHistory,
+    profile_usage::ProfileUsageStore,
     session::SessionManager,
+    transcripts::TranscriptStore,
     Config,
 };
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::info;
 use uuid::Uuid;
-use warp::Filter;
+use warp::{Filter, Reply};
 
 /// Runs the Facet Server with the provided configuration.
 ///
@@ -49,12 +62,59 @@ pub async fn run(config: Config) -> Result<(), Box<dyn std::error::Error + Send
 
     // Create shared state
     let config = Arc::new(config);
-    let session_manager = Arc::new(SessionManager::new(1000)); // Keep 1000 completed sessions
-    let auth_state = Arc::new(AuthState::new(
+    let mut session_manager = SessionManager::new(1000); // Keep 1000 completed sessions
+    if let Some(path) = &config.sessions.history_path {
+        info!("  Session history: {}", path);
+        session_manager = session_manager.with_history(Arc::new(SessionHistory::open(path)?));
+    }
+    if let Some(path) = &config.sessions.transcripts_path {
+        info!("  Transcripts: {}", path);
+        session_manager = session_manager.with_transcripts(TranscriptStore::open(path)?);
+    }
+    let usage = Arc::new(match &config.sessions.usage_path {
+        Some(path) => {
+            info!("  Usage totals: {}", path);
+            ProfileUsageStore::open(path)?
+        }
+        None => ProfileUsageStore::in_memory(),
+    });
+    let audit = match &config.audit.path {
+        Some(path) => {
+            info!("  Audit log: {}", path);
+            AuditLog::open(path)?
+        }
+        None => AuditLog::in_memory(),
+    };
+    let audit = Arc::new(
+        audit
+            .with_hash_chain(config.audit.hash_chain)
+            .with_rotation(
+                config.audit.max_file_mb * 1024 * 1024,
+                config.audit.max_files,
+            ),
+    );
+    let session_manager = session_manager
+        .with_usage(usage.clone())
+        .with_audit(audit.clone())
+        .with_redaction(config.redaction.clone());
+    let session_manager = Arc::new(session_manager);
+    let api_keys = Arc::new(match &config.auth.api_keys_path {
+        Some(path) => {
+            info!("  API keys: {}", path);
+            ApiKeyStore::open(path)?
+        }
+        None => ApiKeyStore::in_memory(),
+    });
+    let mut auth_state = AuthState::new(
         config.valid_tokens(),
         config.auth.require_auth,
         config.auth.rate_limit_per_minute,
-    ));
+    )
+    .with_api_keys(api_keys.clone());
+    if let Some(burst) = config.auth.rate_limit_burst {
+        auth_state = auth_state.with_burst(burst);
+    }
+    let auth_state = Arc::new(auth_state);
     let health_state = Arc::new(HealthState::new(config.claude.binary_path.clone()));
 
     // Create executor (mock or real)
@@ -66,10 +126,34 @@ pub async fn run(config: Config) -> Result<(), Box<dyn std::error::Error + Send
             "Using real Claude CLI executor: {}",
             config.claude.binary_path
         );
-        Arc::new(ClaudeExecutor::new(
+        let mut executor = ClaudeExecutor::new(
             config.claude.binary_path.clone(),
             config.claude.default_timeout_seconds,
-        ))
+        )
+        .with_output_cost(config.claude.output_cost_per_million)
+        .with_idle_timeout(config.claude.idle_timeout_seconds)
+        .with_retry_policy(RetryPolicy {
+            max_attempts: config.claude.retry_max_attempts,
+            initial_backoff: Duration::from_millis(config.claude.retry_initial_backoff_ms),
+            max_backoff: Duration::from_millis(config.claude.retry_max_backoff_ms),
+        });
+        if config.claude.pool_size > 0 {
+            info!(
+                "Keeping {} claude-cli processes warm",
+                config.claude.pool_size
+            );
+            executor = executor.with_pool(PoolConfig {
+                size: config.claude.pool_size,
+            });
+        }
+        Arc::new(executor)
+    };
+    let executor: Arc<dyn Executor> = match &config.claude.routing_policy_path {
+        Some(path) => {
+            info!("Routing requests by policy: {}", path);
+            Arc::new(Router::new(RoutingPolicy::from_file(path)?, executor).await?)
+        }
+        None => executor,
     };
 
     // Build routes
@@ -78,11 +162,16 @@ pub async fn run(config: Config) -> Result<(), Box<dyn std::error::Error + Send
         executor,
         session_manager,
         auth_state,
+        api_keys,
+        usage,
+        audit,
         health_state,
     );
 
     // Add middleware
-    let routes = routes.with(warp::trace::request());
+    let routes = routes
+        .recover(handle_rejection)
+        .with(warp::trace::request());
 
     let cors = if config.server.dev_mode {
         warp::cors()
@@ -120,11 +209,15 @@ pub async fn run(config: Config) -> Result<(), Box<dyn std::error::Error + Send
 }
 
 /// Builds all API routes
+#[allow(clippy::too_many_arguments)]
 fn build_routes(
     config: Arc<Config>,
     executor: Arc<dyn Executor>,
     session_manager: Arc<SessionManager>,
     auth_state: Arc<AuthState>,
+    api_keys: Arc<ApiKeyStore>,
+    usage: Arc<ProfileUsageStore>,
+    audit: Arc<AuditLog>,
     health_state: Arc<HealthState>,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
     // Health endpoint (no auth required)
@@ -136,33 +229,160 @@ fn build_routes(
     // Execute endpoint (with auth)
     let execute = warp::path!("api" / "v1" / "execute")
         .and(warp::post())
-        .and(with_auth(auth_state.clone()))
+        .and(with_caller(auth_state.clone()))
         .and(warp::body::json())
         .and(with_executor(executor.clone()))
         .and(with_session_manager(session_manager.clone()))
         .and(with_config(config.clone()))
-        .and_then(
-            |_token: String, request, executor, session_manager, config| {
-                execute_handler(request, executor, session_manager, config)
-            },
-        );
+        .and_then(|caller, request, executor, session_manager, config| {
+            execute_handler(request, caller, executor, session_manager, config)
+        });
+
+    // Submit request endpoint (with auth), polled for its result
+    let submit_request = warp::path!("api" / "v1" / "requests")
+        .and(warp::post())
+        .and(with_caller(auth_state.clone()))
+        .and(warp::body::json())
+        .and(with_executor(executor.clone()))
+        .and(with_session_manager(session_manager.clone()))
+        .and(with_config(config.clone()))
+        .and_then(|caller, request, executor, session_manager, config| {
+            submit_request_handler(request, caller, executor, session_manager, config)
+        });
 
-    // Get session endpoint (with auth)
+    // Get request endpoint (with auth), for the caller's own requests
+    let get_request = warp::path!("api" / "v1" / "requests" / Uuid)
+        .and(warp::get())
+        .and(with_caller(auth_state.clone()))
+        .and(with_session_manager(session_manager.clone()))
+        .and_then(|session_id: Uuid, caller, manager| {
+            get_request_handler(session_id, caller, manager)
+        });
+
+    // Cancel request endpoint (with auth): kills the claude-cli process
+    let cancel_request = warp::path!("api" / "v1" / "requests" / Uuid)
+        .and(warp::delete())
+        .and(with_caller(auth_state.clone()))
+        .and(with_executor(executor.clone()))
+        .and(with_session_manager(session_manager.clone()))
+        .and_then(|session_id: Uuid, caller, executor, manager| {
+            cancel_request_handler(session_id, caller, executor, manager)
+        });
+
+    // Redaction reply endpoint (with auth): confirms or denies a preview
+    let redaction_reply = warp::path!("api" / "v1" / "requests" / Uuid / "redaction")
+        .and(warp::post())
+        .and(with_caller(auth_state.clone()))
+        .and(warp::body::json())
+        .and(with_session_manager(session_manager.clone()))
+        .and_then(|session_id: Uuid, caller, reply, manager| {
+            redaction_reply_handler(session_id, caller, reply, manager)
+        });
+
+    // Request events endpoint (with auth), streamed as SSE
+    let request_events = warp::path!("api" / "v1" / "requests" / Uuid / "events")
+        .and(warp::get())
+        .and(with_caller(auth_state.clone()))
+        .and(with_session_manager(session_manager.clone()))
+        .and_then(|session_id: Uuid, caller, manager| {
+            request_events_handler(session_id, caller, manager)
+        });
+
+    // WebSocket endpoint (with auth): submit, stream and stop requests
+    let websocket = warp::path!("api" / "v1" / "ws")
+        .and(warp::ws())
+        .and(with_caller(auth_state.clone()))
+        .and(with_executor(executor.clone()))
+        .and(with_session_manager(session_manager.clone()))
+        .and(with_config(config.clone()))
+        .map(|ws, caller, executor, session_manager, config| {
+            ws_handler(ws, caller, executor, session_manager, config)
+        });
+
+    // Get session endpoint (with auth), for the caller's own sessions
     let get_session = warp::path!("api" / "v1" / "sessions" / Uuid)
         .and(warp::get())
-        .and(with_auth(auth_state.clone()))
+        .and(with_caller(auth_state.clone()))
         .and(with_session_manager(session_manager.clone()))
-        .and_then(|session_id: Uuid, _token: String, manager| {
-            get_session_handler(session_id, manager)
+        .and_then(|session_id: Uuid, caller, manager| {
+            get_session_handler(session_id, caller, manager)
         });
 
-    // Delete session endpoint (with auth)
+    // Session history endpoint (with auth), for the caller's own sessions
+    let session_history = warp::path!("api" / "v1" / "sessions" / Uuid / "history")
+        .and(warp::get())
+        .and(with_caller(auth_state.clone()))
+        .and(with_session_manager(session_manager.clone()))
+        .and_then(|session_id: Uuid, caller, manager| {
+            get_session_history_handler(session_id, caller, manager)
+        });
+
+    // Rate limit metrics endpoint (with auth)
+    let rate_limit_metrics = warp::path!("api" / "v1" / "metrics" / "rate-limits")
+        .and(warp::get())
+        .and(with_auth(auth_state.clone()))
+        .and(with_auth_state(auth_state.clone()))
+        .and_then(|_token: String, auth_state| rate_limit_metrics_handler(auth_state));
+
+    // Usage per profile endpoint (with auth)
+    let usage_totals = warp::path!("api" / "v1" / "usage")
+        .and(warp::get())
+        .and(with_auth(auth_state.clone()))
+        .and(with_usage(usage.clone()))
+        .and_then(|_token: String, store| usage_handler(store));
+
+    // Usage of one profile endpoint (with auth)
+    let profile_usage = warp::path!("api" / "v1" / "usage" / String)
+        .and(warp::get())
+        .and(with_auth(auth_state.clone()))
+        .and(with_usage(usage))
+        .and_then(|profile: String, _token: String, store| profile_usage_handler(profile, store));
+
+    // Audit log endpoint (with auth)
+    let audit_entries = warp::path!("api" / "v1" / "audit")
+        .and(warp::get())
+        .and(with_auth(auth_state.clone()))
+        .and(warp::query::<AuditQuery>())
+        .and(with_audit(audit.clone()))
+        .and_then(|_token: String, query, log| audit_handler(query, log));
+
+    // Audit log hash chain check endpoint (with auth)
+    let audit_verify = warp::path!("api" / "v1" / "audit" / "verify")
+        .and(warp::get())
+        .and(with_auth(auth_state.clone()))
+        .and(with_audit(audit))
+        .and_then(|_token: String, log| audit_verify_handler(log));
+
+    // Create API key endpoint (configured tokens only)
+    let create_api_key = warp::path!("api" / "v1" / "keys")
+        .and(warp::post())
+        .and(with_admin(auth_state.clone()))
+        .and(warp::body::json())
+        .and(with_api_keys(api_keys.clone()))
+        .and_then(|_token: String, request, store| create_api_key_handler(request, store));
+
+    // List API keys endpoint (configured tokens only)
+    let list_api_keys = warp::path!("api" / "v1" / "keys")
+        .and(warp::get())
+        .and(with_admin(auth_state.clone()))
+        .and(with_api_keys(api_keys.clone()))
+        .and_then(|_token: String, store| list_api_keys_handler(store));
+
+    // Revoke API key endpoint (configured tokens only)
+    let revoke_api_key = warp::path!("api" / "v1" / "keys" / String)
+        .and(warp::delete())
+        .and(with_admin(auth_state.clone()))
+        .and(with_api_keys(api_keys))
+        .and_then(|id: String, _token: String, store| revoke_api_key_handler(id, store));
+
+    // Delete session endpoint (with auth), for the caller's own sessions
     let delete_session = warp::path!("api" / "v1" / "sessions" / Uuid)
         .and(warp::delete())
-        .and(with_auth(auth_state))
+        .and(with_caller(auth_state))
+        .and(with_executor(executor.clone()))
         .and(with_session_manager(session_manager))
-        .and_then(|session_id: Uuid, _token: String, manager| {
-            delete_session_handler(session_id, manager)
+        .and_then(|session_id: Uuid, caller, executor, manager| {
+            delete_session_handler(session_id, caller, executor, manager)
         });
 
     // Inference endpoint (simple JSON)
@@ -175,11 +395,68 @@ fn build_routes(
 
     health
         .or(execute)
+        .or(submit_request)
+        .or(get_request)
+        .or(cancel_request)
+        .or(redaction_reply)
+        .or(request_events)
+        .or(websocket)
         .or(get_session)
+        .or(session_history)
         .or(delete_session)
+        .or(rate_limit_metrics)
+        .or(usage_totals)
+        .or(profile_usage)
+        .or(audit_entries)
+        .or(audit_verify)
+        .or(create_api_key)
+        .or(list_api_keys)
+        .or(revoke_api_key)
         .or(inference)
 }
 
+/// Turns rejections into JSON error responses
+///
+/// Throttled requests get 429 with a Retry-After header; other errors
+/// the status code mapped from them. Rejections warp raised itself (not
+/// found, wrong method, ...) are passed on to its default handling.
+///
+/// # Arguments
+/// * `err` - Rejection from the routes
+///
+/// # Returns
+/// Error response, or the rejection if it isn't ours
+pub async fn handle_rejection(
+    err: warp::Rejection,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let (status, mut response, retry_after) =
+        if let Some(throttled) = err.find::<RateLimitRejection>() {
+            // Rounded up, so a client waiting that long is let through
+            let retry_after = throttled.retry_after.as_secs()
+                + u64::from(throttled.retry_after.subsec_nanos() > 0);
+            (
+                throttled.error.status_code(),
+                throttled.error.to_error_response(None),
+                Some(retry_after),
+            )
+        } else if let Some(AuthRejection(e)) = err.find::<AuthRejection>() {
+            (e.status_code(), e.to_error_response(None), None)
+        } else {
+            return Err(err);
+        };
+
+    if retry_after.is_some() {
+        response.retry_after_seconds = retry_after;
+    }
+    let reply = warp::reply::with_status(warp::reply::json(&response), status);
+    Ok(match retry_after {
+        Some(seconds) => {
+            warp::reply::with_header(reply, "retry-after", seconds.to_string()).into_response()
+        }
+        None => reply.into_response(),
+    })
+}
+
 /// Warp filter to inject executor
 fn with_executor(
     executor: Arc<dyn Executor>,
@@ -201,9 +478,71 @@ fn with_config(
     warp::any().map(move || config.clone())
 }
 
+/// Warp filter to inject authentication state
+fn with_auth_state(
+    auth_state: Arc<AuthState>,
+) -> impl Filter<Extract = (Arc<AuthState>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || auth_state.clone())
+}
+
+/// Warp filter to inject the API key store
+fn with_api_keys(
+    store: Arc<ApiKeyStore>,
+) -> impl Filter<Extract = (Arc<ApiKeyStore>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || store.clone())
+}
+
+/// Warp filter to inject the audit log
+fn with_audit(
+    log: Arc<AuditLog>,
+) -> impl Filter<Extract = (Arc<AuditLog>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || log.clone())
+}
+
+/// Warp filter to inject the usage store
+fn with_usage(
+    store: Arc<ProfileUsageStore>,
+) -> impl Filter<Extract = (Arc<ProfileUsageStore>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || store.clone())
+}
+
 /// Warp filter to inject health state
 fn with_health_state(
     state: Arc<HealthState>,
 ) -> impl Filter<Extract = (Arc<HealthState>,), Error = std::convert::Infallible> + Clone {
     warp::any().map(move || state.clone())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_throttled_requests_get_retry_after() {
+        // One request a minute
+        let auth_state = Arc::new(AuthState::new(vec![], false, 1));
+        let filter = warp::path("test")
+            .and(with_auth(auth_state))
+            .map(|_token: String| "ok")
+            .recover(handle_rejection);
+
+        let request = || {
+            warp::test::request()
+                .path("/test")
+                .header(crate::auth::PROFILE_HEADER, "work")
+        };
+        assert_eq!(request().reply(&filter).await.status(), 200);
+
+        let response = request().reply(&filter).await;
+        assert_eq!(response.status(), 429);
+        let retry_after: u64 = response.headers()["retry-after"]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(retry_after > 0 && retry_after <= 60);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["code"], "RATE_LIMITED");
+        assert_eq!(body["retry_after_seconds"], retry_after);
+    }
+}
@@ -4,13 +4,28 @@
 //! status updates, cancellation, and automatic cleanup. Uses Arc<Mutex<>>
 //! for shared state management across async tasks.
 
+use crate::audit::{AuditEntry, AuditLog};
+use crate::auth::Caller;
+use crate::config::RedactionConfig;
 use crate::error::FacetError;
-use crate::models::{SessionState, SessionStatus};
+use crate::history::{SessionHistory, SessionRecord};
+use crate::models::{ClaudeEvent, FacetRequest, RequestUsage, SessionState, SessionStatus};
+use crate::profile_usage::ProfileUsageStore;
+use crate::transcripts::{referenced_file, Transcript, TranscriptStore};
+use facet_types::profiles::RedactionPreferences;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, oneshot, Mutex};
+use tracing::warn;
 use uuid::Uuid;
 
+/// Events buffered per live subscriber before it starts lagging
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Events a session has emitted so far, and a receiver for the ones still
+/// to come (None once the session has finished)
+pub type EventSubscription = (Vec<ClaudeEvent>, Option<broadcast::Receiver<ClaudeEvent>>);
+
 /// Session metadata tracked for each execution
 ///
 /// Contains timing information, current state, and optional error details.
@@ -30,6 +45,41 @@ struct SessionInfo {
 
     /// Error message if session failed
     error: Option<String>,
+
+    /// Conversation the session runs in, as claude-cli knows it
+    conversation_id: String,
+
+    /// Session whose conversation this one continues
+    resumed_from: Option<Uuid>,
+
+    /// User's prompt, if registered with its request
+    prompt: String,
+
+    /// User's intent, if registered with its request
+    user_intent: String,
+
+    /// Who the session's request comes from
+    caller: Caller,
+
+    /// Tools called so far, each once, in order of first use
+    tools: Vec<String>,
+
+    /// Files the tools touched so far, each once, in order of first use
+    files: Vec<String>,
+
+    /// Tokens and cost so far, once any have been reported
+    usage: Option<RequestUsage>,
+
+    /// Text content streamed so far, for requests polled for their result
+    /// and for the transcript
+    output: String,
+
+    /// Every event emitted so far, replayed to late subscribers
+    events: Vec<ClaudeEvent>,
+
+    /// Live event feed; dropped when the session finishes, which ends the
+    /// subscribers' streams
+    events_tx: Option<broadcast::Sender<ClaudeEvent>>,
 }
 
 impl SessionInfo {
@@ -37,19 +87,49 @@ impl SessionInfo {
     ///
     /// # Arguments
     /// * `id` - Session UUID
+    /// * `conversation_id` - Conversation the session runs in
     ///
     /// # Returns
     /// New SessionInfo with current timestamp
-    fn new(id: Uuid) -> Self {
+    fn new(id: Uuid, conversation_id: String) -> Self {
         Self {
             id,
             state: SessionState::Running,
             started_at: chrono::Utc::now().to_rfc3339(),
             completed_at: None,
             error: None,
+            conversation_id,
+            resumed_from: None,
+            prompt: String::new(),
+            user_intent: String::new(),
+            caller: Caller::default(),
+            tools: Vec::new(),
+            files: Vec::new(),
+            usage: None,
+            output: String::new(),
+            events: Vec::new(),
+            events_tx: Some(broadcast::channel(EVENT_CHANNEL_CAPACITY).0),
         }
     }
 
+    /// Creates a new session in Running state for a request
+    ///
+    /// # Arguments
+    /// * `request` - Request the session runs
+    /// * `caller` - Who the request comes from
+    /// * `conversation_id` - Conversation the session runs in
+    ///
+    /// # Returns
+    /// New SessionInfo holding the request's prompt and intent
+    fn for_request(request: &FacetRequest, caller: Caller, conversation_id: String) -> Self {
+        let mut info = Self::new(request.session_id, conversation_id);
+        info.caller = caller;
+        info.resumed_from = request.options.resume;
+        info.prompt = request.prompt.clone();
+        info.user_intent = request.context.user_intent.clone();
+        info
+    }
+
     /// Converts to public SessionStatus
     ///
     /// # Returns
@@ -61,6 +141,92 @@ impl SessionInfo {
             started_at: self.started_at.clone(),
             completed_at: self.completed_at.clone(),
             error: self.error.clone(),
+            usage: self.usage.clone(),
+        }
+    }
+
+    /// Converts to a SessionRecord with the events so far
+    ///
+    /// # Returns
+    /// SessionRecord suitable for API responses
+    fn to_record(&self) -> SessionRecord {
+        SessionRecord {
+            status: self.to_status(),
+            conversation_id: self.conversation_id.clone(),
+            resumed_from: self.resumed_from,
+            prompt: self.prompt.clone(),
+            user_intent: self.user_intent.clone(),
+            events: self.events.clone(),
+        }
+    }
+
+    /// Converts to an AuditEntry, numbered once appended
+    ///
+    /// # Returns
+    /// AuditEntry of the finished session
+    fn to_audit_entry(&self) -> AuditEntry {
+        AuditEntry {
+            seq: 0,
+            timestamp: self
+                .completed_at
+                .clone()
+                .unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+            session_id: self.id,
+            actor: self.caller.actor.clone(),
+            profile: self.caller.profile.clone(),
+            prompt: self.prompt.clone(),
+            tools: self.tools.clone(),
+            outcome: self.state.clone(),
+            error: self.error.clone(),
+            started_at: self.started_at.clone(),
+            usage: self.usage.clone(),
+            prev_hash: None,
+            hash: None,
+        }
+    }
+
+    /// Converts to a Transcript of the completed session
+    ///
+    /// # Returns
+    /// Transcript of the session, or None if it wasn't registered with its
+    /// request
+    fn to_transcript(&self) -> Option<Transcript> {
+        if self.prompt.is_empty() {
+            return None;
+        }
+        Some(Transcript {
+            session_id: self.id,
+            conversation_id: self.conversation_id.clone(),
+            resumed_from: self.resumed_from,
+            profile: self.caller.profile.clone(),
+            prompt: self.prompt.clone(),
+            answer: self.output.clone(),
+            tools: self.tools.clone(),
+            files: self.files.clone(),
+            started_at: self.started_at.clone(),
+            completed_at: self.completed_at.clone()?,
+        })
+    }
+
+    /// Takes in the text, usage and tool call an event reports, if any
+    fn observe(&mut self, event: &ClaudeEvent) {
+        let mut usage = self.usage.clone().unwrap_or_default();
+        if usage.record(event) {
+            self.usage = Some(usage);
+        }
+        match event {
+            ClaudeEvent::Content { text } => self.output.push_str(text),
+            ClaudeEvent::ToolUse { tool, params, .. } => {
+                if !self.tools.contains(tool) {
+                    self.tools.push(tool.clone());
+                }
+                if let Some(path) = referenced_file(params) {
+                    if !self.files.iter().any(|file| file == path) {
+                        self.files.push(path.to_string());
+                    }
+                }
+            }
+            _ => {}
         }
     }
 }
@@ -76,6 +242,24 @@ pub struct SessionManager {
 
     /// Maximum number of sessions to keep in history
     max_history: usize,
+
+    /// Persistent history, outliving both cleanup and restarts
+    history: Option<Arc<SessionHistory>>,
+
+    /// Usage totals per profile, which finished sessions are counted in
+    usage: Option<Arc<ProfileUsageStore>>,
+
+    /// Audit log, which finished sessions are appended to
+    audit: Option<Arc<AuditLog>>,
+
+    /// Knowledge graph, which completed sessions are written to
+    transcripts: Option<TranscriptStore>,
+
+    /// Redaction preferences enforced per profile
+    redaction: RedactionConfig,
+
+    /// Sessions waiting for the client to confirm a redaction preview
+    redaction_replies: Arc<Mutex<HashMap<Uuid, oneshot::Sender<bool>>>>,
 }
 
 impl SessionManager {
@@ -90,9 +274,82 @@ impl SessionManager {
         Self {
             sessions: Arc::new(Mutex::new(HashMap::new())),
             max_history,
+            history: None,
+            usage: None,
+            audit: None,
+            transcripts: None,
+            redaction: RedactionConfig::default(),
+            redaction_replies: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    /// Persists sessions to a history store
+    ///
+    /// Sessions registered with their request are recorded there, with
+    /// their events and final state, and can be resumed from it after
+    /// they have been cleaned up or the server restarted.
+    ///
+    /// # Arguments
+    /// * `history` - History store to record sessions in
+    ///
+    /// # Returns
+    /// SessionManager persisting to `history`
+    pub fn with_history(mut self, history: Arc<SessionHistory>) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Counts the usage of finished sessions against their profiles
+    ///
+    /// # Arguments
+    /// * `usage` - Store of usage totals per profile
+    ///
+    /// # Returns
+    /// SessionManager adding to `usage`
+    pub fn with_usage(mut self, usage: Arc<ProfileUsageStore>) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Appends finished sessions to an audit log
+    ///
+    /// # Arguments
+    /// * `audit` - Audit log to append to
+    ///
+    /// # Returns
+    /// SessionManager auditing to `audit`
+    pub fn with_audit(mut self, audit: Arc<AuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Enforces per-profile redaction preferences
+    ///
+    /// # Arguments
+    /// * `redaction` - Redaction preferences of each profile
+    ///
+    /// # Returns
+    /// SessionManager redacting per `redaction`
+    pub fn with_redaction(mut self, redaction: RedactionConfig) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// Writes completed sessions to a knowledge graph
+    ///
+    /// Only sessions registered with their request are written: the
+    /// others have no prompt.
+    ///
+    /// # Arguments
+    /// * `transcripts` - Graph to write transcripts to
+    ///
+    /// # Returns
+    /// SessionManager writing to `transcripts`
+    pub fn with_transcripts(mut self, transcripts: TranscriptStore) -> Self {
+        self.transcripts = Some(transcripts);
+        self
+    }
+
     /// Registers a new session
     ///
     /// Creates a new session entry in Running state. If max concurrent
@@ -112,6 +369,102 @@ impl SessionManager {
         session_id: Uuid,
         max_concurrent: usize,
     ) -> Result<(), FacetError> {
+        let info = SessionInfo::new(session_id, session_id.to_string());
+        self.insert(info, max_concurrent).await
+    }
+
+    /// Registers a new session for a request whose events go straight to
+    /// the client
+    ///
+    /// Like `register`, and its usage is counted against the caller's
+    /// profile and it is audited; unlike `register_request`, it is not
+    /// recorded in the history.
+    ///
+    /// # Arguments
+    /// * `request` - Request the session runs
+    /// * `caller` - Who the request comes from
+    /// * `max_concurrent` - Maximum allowed concurrent running sessions
+    ///
+    /// # Returns
+    /// Ok(()) if session registered, Err if concurrent limit exceeded
+    pub async fn register_for(
+        &self,
+        request: &FacetRequest,
+        caller: Caller,
+        max_concurrent: usize,
+    ) -> Result<(), FacetError> {
+        let info = SessionInfo::for_request(request, caller, request.session_id.to_string());
+        self.insert(info, max_concurrent).await
+    }
+
+    /// Registers a new session for a request
+    ///
+    /// Like `register_for`, and also records the request in the history.
+    ///
+    /// # Arguments
+    /// * `request` - Request the session runs
+    /// * `caller` - Who the request comes from
+    /// * `conversation_id` - Conversation to continue, or None to start
+    ///   one named after the session
+    /// * `max_concurrent` - Maximum allowed concurrent running sessions
+    ///
+    /// # Returns
+    /// Ok(()) if session registered, Err if concurrent limit exceeded
+    pub async fn register_request(
+        &self,
+        request: &FacetRequest,
+        caller: Caller,
+        conversation_id: Option<String>,
+        max_concurrent: usize,
+    ) -> Result<(), FacetError> {
+        let conversation_id = conversation_id.unwrap_or_else(|| request.session_id.to_string());
+        let info = SessionInfo::for_request(request, caller, conversation_id);
+
+        let (caller, conversation_id, started_at) = (
+            info.caller.clone(),
+            info.conversation_id.clone(),
+            info.started_at.clone(),
+        );
+        self.insert(info, max_concurrent).await?;
+        self.persist(|history| {
+            history.record_session(request, &caller, &conversation_id, &started_at)
+        });
+        Ok(())
+    }
+
+    /// Checks that a session's request came from the caller
+    ///
+    /// Looks in the history store for sessions no longer in memory. A
+    /// session of someone else is reported as not found, so its id gives
+    /// nothing away.
+    ///
+    /// # Arguments
+    /// * `session_id` - Session UUID to check
+    /// * `caller` - Who is asking about the session
+    ///
+    /// # Returns
+    /// Ok(()) if the caller owns the session
+    ///
+    /// # Errors
+    /// Returns FacetError::SessionNotFound if the session is unknown or
+    /// belongs to another caller
+    pub async fn check_owner(&self, session_id: Uuid, caller: &Caller) -> Result<(), FacetError> {
+        let owner = match self.sessions.lock().await.get(&session_id) {
+            Some(session) => Some(session.caller.clone()),
+            None => match &self.history {
+                Some(history) => history.caller(session_id)?,
+                None => None,
+            },
+        };
+
+        match owner {
+            Some(owner) if owner == *caller => Ok(()),
+            _ => Err(FacetError::SessionNotFound(session_id.to_string())),
+        }
+    }
+
+    /// Inserts a session unless the concurrent limit is reached
+    async fn insert(&self, info: SessionInfo, max_concurrent: usize) -> Result<(), FacetError> {
         let mut sessions = self.sessions.lock().await;
 
         // Count running sessions
@@ -127,13 +480,45 @@ impl SessionManager {
             )));
         }
 
-        sessions.insert(session_id, SessionInfo::new(session_id));
+        sessions.insert(info.id, info);
         Ok(())
     }
 
+    /// Writes to the history store, if any
+    ///
+    /// A failed write is logged rather than failing the session: history
+    /// is a record, the live session is what the client is waiting for.
+    fn persist(&self, write: impl FnOnce(&SessionHistory) -> Result<(), FacetError>) {
+        if let Some(history) = &self.history {
+            if let Err(e) = write(history) {
+                warn!("Failed to record session history: {}", e);
+            }
+        }
+    }
+
+    /// Counts a session that just finished against its profile's usage
+    /// and appends it to the audit log
+    ///
+    /// Like history, a failed write is logged rather than failing the
+    /// session.
+    fn settle(&self, session: &SessionInfo) {
+        if let Some(store) = &self.usage {
+            let usage = session.usage.clone().unwrap_or_default();
+            if let Err(e) = store.add(session.caller.profile.as_deref(), &usage) {
+                warn!("Failed to record usage: {}", e);
+            }
+        }
+        if let Some(audit) = &self.audit {
+            if let Err(e) = audit.append(session.to_audit_entry()) {
+                warn!("Failed to write audit log: {}", e);
+            }
+        }
+    }
+
     /// Marks a session as completed successfully
     ///
-    /// Updates session state to Completed with current timestamp.
+    /// Updates session state to Completed with current timestamp, and
+    /// writes its transcript to the knowledge graph if there is one.
     ///
     /// # Arguments
     /// * `session_id` - Session UUID to mark complete
@@ -147,8 +532,27 @@ impl SessionManager {
             .get_mut(&session_id)
             .ok_or_else(|| FacetError::SessionNotFound(session_id.to_string()))?;
 
+        let finished = session.state == SessionState::Running;
+        self.redaction_replies.lock().await.remove(&session_id);
         session.state = SessionState::Completed;
         session.completed_at = Some(chrono::Utc::now().to_rfc3339());
+        session.events_tx = None;
+        if finished {
+            self.settle(session);
+        }
+
+        let status = session.to_status();
+        self.persist(|history| history.record_status(&status));
+
+        let transcript = finished.then(|| session.to_transcript()).flatten();
+        // Written without holding up other sessions
+        drop(sessions);
+        if let (Some(store), Some(transcript)) = (&self.transcripts, transcript) {
+            // Like history, a record: the client already has its answer
+            if let Err(e) = store.record(&transcript).await {
+                warn!("Failed to write transcript: {}", e);
+            }
+        }
 
         Ok(())
     }
@@ -170,17 +574,27 @@ impl SessionManager {
             .get_mut(&session_id)
             .ok_or_else(|| FacetError::SessionNotFound(session_id.to_string()))?;
 
+        let finished = session.state == SessionState::Running;
+        self.redaction_replies.lock().await.remove(&session_id);
         session.state = SessionState::Failed;
         session.error = Some(error);
         session.completed_at = Some(chrono::Utc::now().to_rfc3339());
+        session.events_tx = None;
+        if finished {
+            self.settle(session);
+        }
+
+        let status = session.to_status();
+        self.persist(|history| history.record_status(&status));
 
         Ok(())
     }
 
     /// Cancels a running session
     ///
-    /// Updates session state to Cancelled. The caller is responsible
-    /// for actually terminating the underlying process.
+    /// Updates session state to Cancelled and ends its event feed with a
+    /// Cancelled event. The caller is responsible for actually terminating
+    /// the underlying process.
     ///
     /// # Arguments
     /// * `session_id` - Session UUID to cancel
@@ -202,12 +616,214 @@ impl SessionManager {
             )));
         }
 
+        // A request waiting on its redaction preview gives up
+        self.redaction_replies.lock().await.remove(&session_id);
+        let cancelled = ClaudeEvent::Cancelled { session_id };
+        if let Some(tx) = session.events_tx.take() {
+            let _ = tx.send(cancelled.clone());
+        }
+        self.persist(|history| history.record_event(session_id, &cancelled));
+        session.events.push(cancelled);
+
         session.state = SessionState::Cancelled;
         session.completed_at = Some(chrono::Utc::now().to_rfc3339());
+        self.settle(session);
+
+        let status = session.to_status();
+        self.persist(|history| history.record_status(&status));
+
+        Ok(())
+    }
+
+    /// Appends streamed text content to a session's output
+    ///
+    /// # Arguments
+    /// * `session_id` - Session UUID the content belongs to
+    /// * `text` - Text to append
+    ///
+    /// # Returns
+    /// Ok(()) if session found and updated, Err if session not found
+    pub async fn append_output(&self, session_id: Uuid, text: &str) -> Result<(), FacetError> {
+        let mut sessions = self.sessions.lock().await;
+
+        let session = sessions
+            .get_mut(&session_id)
+            .ok_or_else(|| FacetError::SessionNotFound(session_id.to_string()))?;
+
+        session.output.push_str(text);
+
+        Ok(())
+    }
+
+    /// Records an event emitted by a session
+    ///
+    /// Keeps it for late subscribers and sends it to the live ones, and
+    /// takes in the usage and tool call it reports.
+    ///
+    /// # Arguments
+    /// * `session_id` - Session UUID the event belongs to
+    /// * `event` - Event to record
+    ///
+    /// # Returns
+    /// Ok(()) if session found and updated, Err if session not found
+    pub async fn record_event(
+        &self,
+        session_id: Uuid,
+        event: ClaudeEvent,
+    ) -> Result<(), FacetError> {
+        let mut sessions = self.sessions.lock().await;
+
+        let session = sessions
+            .get_mut(&session_id)
+            .ok_or_else(|| FacetError::SessionNotFound(session_id.to_string()))?;
+
+        if let Some(tx) = &session.events_tx {
+            // No live subscribers is fine
+            let _ = tx.send(event.clone());
+        }
+        self.persist(|history| history.record_event(session_id, &event));
+        session.observe(&event);
+        session.events.push(event);
+
+        Ok(())
+    }
+
+    /// Takes in the usage and tool call an event reports, without
+    /// recording the event
+    ///
+    /// For sessions whose events go straight to the client.
+    ///
+    /// # Arguments
+    /// * `session_id` - Session UUID the event belongs to
+    /// * `event` - Event of the session
+    ///
+    /// # Returns
+    /// Ok(()) if session found, Err if session not found
+    pub async fn observe_event(
+        &self,
+        session_id: Uuid,
+        event: &ClaudeEvent,
+    ) -> Result<(), FacetError> {
+        let mut sessions = self.sessions.lock().await;
+
+        let session = sessions
+            .get_mut(&session_id)
+            .ok_or_else(|| FacetError::SessionNotFound(session_id.to_string()))?;
+
+        session.observe(event);
+
+        Ok(())
+    }
+
+    /// Subscribes to a session's events
+    ///
+    /// Taken under one lock, so no event falls between the ones already
+    /// emitted and the receiver.
+    ///
+    /// # Arguments
+    /// * `session_id` - Session UUID to subscribe to
+    ///
+    /// # Returns
+    /// Past events and a receiver for future ones, Err if session not found
+    pub async fn subscribe(&self, session_id: Uuid) -> Result<EventSubscription, FacetError> {
+        let sessions = self.sessions.lock().await;
+
+        let session = sessions
+            .get(&session_id)
+            .ok_or_else(|| FacetError::SessionNotFound(session_id.to_string()))?;
+
+        Ok((
+            session.events.clone(),
+            session.events_tx.as_ref().map(broadcast::Sender::subscribe),
+        ))
+    }
 
+    /// Returns the redaction preferences of the profile a session acts for
+    ///
+    /// # Arguments
+    /// * `session_id` - Session UUID
+    ///
+    /// # Returns
+    /// The profile's preferences, or the defaults if the session names no
+    /// profile or isn't known
+    pub async fn redaction_preferences(&self, session_id: Uuid) -> RedactionPreferences {
+        let sessions = self.sessions.lock().await;
+        let profile = sessions
+            .get(&session_id)
+            .and_then(|session| session.caller.profile.as_deref());
+        self.redaction.for_profile(profile).clone()
+    }
+
+    /// Waits for the client's reply to a session's redaction preview
+    ///
+    /// Taken before the preview is sent, so a quick reply isn't missed.
+    /// The receiver fails if the session finishes before the client
+    /// replies.
+    ///
+    /// # Arguments
+    /// * `session_id` - Session UUID whose preview the client is shown
+    ///
+    /// # Returns
+    /// Receiver of whether the client approved the redacted request
+    pub async fn await_redaction_reply(&self, session_id: Uuid) -> oneshot::Receiver<bool> {
+        let (reply, receiver) = oneshot::channel();
+        self.redaction_replies
+            .lock()
+            .await
+            .insert(session_id, reply);
+        receiver
+    }
+
+    /// Passes on the client's reply to a session's redaction preview
+    ///
+    /// # Arguments
+    /// * `session_id` - Session UUID whose preview the client was shown
+    /// * `approved` - Whether the redacted request may be sent
+    ///
+    /// # Returns
+    /// Ok(()) if the session was waiting for a reply, Err otherwise
+    ///
+    /// # Errors
+    /// Returns FacetError::InvalidRequest if no preview of the session is
+    /// waiting for confirmation
+    pub async fn reply_to_redaction(
+        &self,
+        session_id: Uuid,
+        approved: bool,
+    ) -> Result<(), FacetError> {
+        let reply = self
+            .redaction_replies
+            .lock()
+            .await
+            .remove(&session_id)
+            .ok_or_else(|| {
+                FacetError::InvalidRequest(format!(
+                    "Session {} is not waiting for a redaction to be confirmed",
+                    session_id
+                ))
+            })?;
+        // The request may have given up meanwhile
+        let _ = reply.send(approved);
         Ok(())
     }
 
+    /// Retrieves the text content a session has produced so far
+    ///
+    /// # Arguments
+    /// * `session_id` - Session UUID to query
+    ///
+    /// # Returns
+    /// Output text if found, Err if session not found
+    pub async fn get_output(&self, session_id: Uuid) -> Result<String, FacetError> {
+        let sessions = self.sessions.lock().await;
+
+        let session = sessions
+            .get(&session_id)
+            .ok_or_else(|| FacetError::SessionNotFound(session_id.to_string()))?;
+
+        Ok(session.output.clone())
+    }
+
     /// Retrieves session status
     ///
     /// Returns current status information for the specified session.
@@ -227,6 +843,48 @@ impl SessionManager {
         Ok(session.to_status())
     }
 
+    /// Retrieves the conversation a session ran in
+    ///
+    /// Looks in the history store for sessions no longer in memory.
+    ///
+    /// # Arguments
+    /// * `session_id` - Session UUID to query
+    ///
+    /// # Returns
+    /// Conversation id if found, Err if session not found
+    pub async fn conversation_id(&self, session_id: Uuid) -> Result<String, FacetError> {
+        if let Some(session) = self.sessions.lock().await.get(&session_id) {
+            return Ok(session.conversation_id.clone());
+        }
+
+        let recorded = match &self.history {
+            Some(history) => history.conversation_id(session_id)?,
+            None => None,
+        };
+        recorded.ok_or_else(|| FacetError::SessionNotFound(session_id.to_string()))
+    }
+
+    /// Retrieves a session with its events
+    ///
+    /// Looks in the history store for sessions no longer in memory.
+    ///
+    /// # Arguments
+    /// * `session_id` - Session UUID to query
+    ///
+    /// # Returns
+    /// SessionRecord if found, Err if session not found
+    pub async fn get_record(&self, session_id: Uuid) -> Result<SessionRecord, FacetError> {
+        if let Some(session) = self.sessions.lock().await.get(&session_id) {
+            return Ok(session.to_record());
+        }
+
+        let recorded = match &self.history {
+            Some(history) => history.load(session_id)?,
+            None => None,
+        };
+        recorded.ok_or_else(|| FacetError::SessionNotFound(session_id.to_string()))
+    }
+
     /// Cleans up old completed sessions
     ///
     /// Removes oldest completed/failed/cancelled sessions to maintain
@@ -283,6 +941,25 @@ impl SessionManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::audit::AuditQuery;
+    use crate::models::{DomState, RequestContext, RequestOptions};
+    use facet_graph::GraphStore;
+
+    fn create_test_request() -> FacetRequest {
+        FacetRequest {
+            session_id: Uuid::new_v4(),
+            context: RequestContext {
+                screenshots: vec![],
+                dom_state: DomState {
+                    accessible_tree: "tree".to_string(),
+                    interactive_elements: vec![],
+                },
+                user_intent: "intent".to_string(),
+            },
+            prompt: "prompt".to_string(),
+            options: RequestOptions::default(),
+        }
+    }
 
     #[tokio::test]
     async fn test_register_session() {
@@ -299,6 +976,58 @@ mod tests {
         assert!(status.error.is_none());
     }
 
+    #[tokio::test]
+    async fn test_append_output() {
+        let manager = SessionManager::new(100);
+        let session_id = Uuid::new_v4();
+        manager.register(session_id, 10).await.unwrap();
+
+        manager.append_output(session_id, "Hello, ").await.unwrap();
+        manager.append_output(session_id, "world").await.unwrap();
+        assert_eq!(
+            manager.get_output(session_id).await.unwrap(),
+            "Hello, world"
+        );
+
+        let missing = manager.append_output(Uuid::new_v4(), "text").await;
+        assert!(matches!(missing, Err(FacetError::SessionNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_replays_then_follows_events() {
+        let manager = SessionManager::new(100);
+        let session_id = Uuid::new_v4();
+        manager.register(session_id, 10).await.unwrap();
+
+        let first = ClaudeEvent::Content {
+            text: "first".to_string(),
+        };
+        manager
+            .record_event(session_id, first.clone())
+            .await
+            .unwrap();
+
+        let (past, live) = manager.subscribe(session_id).await.unwrap();
+        assert_eq!(past, vec![first.clone()]);
+        let mut live = live.unwrap();
+
+        let second = ClaudeEvent::Content {
+            text: "second".to_string(),
+        };
+        manager
+            .record_event(session_id, second.clone())
+            .await
+            .unwrap();
+        manager.complete(session_id).await.unwrap();
+        assert_eq!(live.recv().await.unwrap(), second);
+        // Finishing closes the live feed
+        assert!(live.recv().await.is_err());
+
+        let (past, live) = manager.subscribe(session_id).await.unwrap();
+        assert_eq!(past, vec![first, second]);
+        assert!(live.is_none());
+    }
+
     #[tokio::test]
     async fn test_register_exceeds_max_concurrent() {
         let manager = SessionManager::new(100);
@@ -360,6 +1089,11 @@ mod tests {
         let status = manager.get_status(session_id).await.unwrap();
         assert_eq!(status.status, SessionState::Cancelled);
         assert!(status.completed_at.is_some());
+
+        // The feed ends with a Cancelled event
+        let (events, live) = manager.subscribe(session_id).await.unwrap();
+        assert_eq!(events, vec![ClaudeEvent::Cancelled { session_id }]);
+        assert!(live.is_none());
     }
 
     #[tokio::test]
@@ -406,6 +1140,205 @@ mod tests {
         assert_eq!(manager.running_count().await, 1);
     }
 
+    #[tokio::test]
+    async fn test_usage_counted_against_profile() {
+        let store = Arc::new(ProfileUsageStore::in_memory());
+        let manager = SessionManager::new(100).with_usage(store.clone());
+        let request = create_test_request();
+        let session_id = request.session_id;
+        let caller = Caller {
+            profile: Some("work".to_string()),
+            ..Default::default()
+        };
+
+        manager.register_for(&request, caller, 10).await.unwrap();
+        let usage = ClaudeEvent::Usage {
+            input_tokens: 900,
+            output_tokens: 40,
+            cache_creation_input_tokens: 0,
+            cache_read_input_tokens: 0,
+        };
+        manager.record_event(session_id, usage).await.unwrap();
+        let summary = ClaudeEvent::Summary {
+            is_error: false,
+            duration_ms: Some(1200),
+            total_cost_usd: Some(0.25),
+        };
+        manager.observe_event(session_id, &summary).await.unwrap();
+
+        let status = manager.get_status(session_id).await.unwrap();
+        let usage = status.usage.unwrap();
+        assert_eq!(usage.output_tokens, 40);
+        assert_eq!(usage.cost_usd, 0.25);
+        // Counted once the session finishes, and only once
+        assert_eq!(store.get("work").requests, 0);
+        manager.complete(session_id).await.unwrap();
+        manager.complete(session_id).await.unwrap();
+        let totals = store.get("work");
+        assert_eq!(totals.requests, 1);
+        assert_eq!(totals.input_tokens, 900);
+        assert_eq!(totals.cost_usd, 0.25);
+    }
+
+    #[tokio::test]
+    async fn test_finished_session_audited() {
+        let audit = Arc::new(AuditLog::in_memory());
+        let manager = SessionManager::new(100).with_audit(audit.clone());
+        let request = create_test_request();
+        let session_id = request.session_id;
+        let caller = Caller {
+            actor: "api_key:ci".to_string(),
+            profile: Some("work".to_string()),
+        };
+
+        manager.register_for(&request, caller, 10).await.unwrap();
+        for _ in 0..2 {
+            let tool_use = ClaudeEvent::ToolUse {
+                id: None,
+                tool: "cdp_command".to_string(),
+                params: serde_json::json!({}),
+            };
+            manager.record_event(session_id, tool_use).await.unwrap();
+        }
+        assert!(audit.query(&AuditQuery::default()).unwrap().is_empty());
+        manager
+            .fail(session_id, "claude-cli exited".to_string())
+            .await
+            .unwrap();
+
+        let entries = audit.query(&AuditQuery::default()).unwrap();
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.session_id, session_id);
+        assert_eq!(entry.actor, "api_key:ci");
+        assert_eq!(entry.profile.as_deref(), Some("work"));
+        assert_eq!(entry.prompt, request.prompt);
+        assert_eq!(entry.tools, vec!["cdp_command".to_string()]);
+        assert_eq!(entry.outcome, SessionState::Failed);
+        assert_eq!(entry.error.as_deref(), Some("claude-cli exited"));
+    }
+
+    #[tokio::test]
+    async fn test_completed_session_written_to_graph() {
+        let graph = facet_graph::sqlite_store::SqliteStore::in_memory().unwrap();
+        let manager =
+            SessionManager::new(100).with_transcripts(TranscriptStore::new(graph.clone()));
+        let request = create_test_request();
+        let session_id = request.session_id;
+
+        manager
+            .register_for(&request, Caller::default(), 10)
+            .await
+            .unwrap();
+        let events = [
+            ClaudeEvent::ToolUse {
+                id: None,
+                tool: "Read".to_string(),
+                params: serde_json::json!({"file_path": "README.md"}),
+            },
+            ClaudeEvent::Content {
+                text: "It's a ".to_string(),
+            },
+            ClaudeEvent::Content {
+                text: "readme.".to_string(),
+            },
+        ];
+        for event in &events {
+            manager.observe_event(session_id, event).await.unwrap();
+        }
+        manager.complete(session_id).await.unwrap();
+
+        let node = graph.get_node(&session_id.to_string()).await.unwrap();
+        assert_eq!(node.partition_id, crate::profile_usage::DEFAULT_PROFILE);
+        assert_eq!(node.properties["prompt"], request.prompt.as_str());
+        assert_eq!(node.properties["answer"], "It's a readme.");
+        let neighbors = graph.get_neighbors(&node.id).await.unwrap();
+        assert_eq!(neighbors[0].1.properties["path"], "README.md");
+    }
+
+    #[tokio::test]
+    async fn test_history_outlives_cleanup() {
+        let history = Arc::new(SessionHistory::in_memory().unwrap());
+        let manager = SessionManager::new(0).with_history(history);
+        let request = create_test_request();
+        let session_id = request.session_id;
+
+        manager
+            .register_request(
+                &request,
+                Caller::default(),
+                Some("conversation-1".to_string()),
+                10,
+            )
+            .await
+            .unwrap();
+        let event = ClaudeEvent::Content {
+            text: "answer".to_string(),
+        };
+        manager
+            .record_event(session_id, event.clone())
+            .await
+            .unwrap();
+        manager.complete(session_id).await.unwrap();
+        assert_eq!(manager.cleanup_old_sessions().await, 1);
+
+        assert_eq!(
+            manager.conversation_id(session_id).await.unwrap(),
+            "conversation-1"
+        );
+        let record = manager.get_record(session_id).await.unwrap();
+        assert_eq!(record.prompt, "prompt");
+        assert_eq!(record.status.status, SessionState::Completed);
+        assert_eq!(record.events, [event]);
+
+        let missing = manager.conversation_id(Uuid::new_v4()).await;
+        assert!(matches!(missing, Err(FacetError::SessionNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_check_owner() {
+        let history = Arc::new(SessionHistory::in_memory().unwrap());
+        let manager = SessionManager::new(0).with_history(history);
+        let request = create_test_request();
+        let session_id = request.session_id;
+        let owner = Caller {
+            actor: "api_key:one".to_string(),
+            profile: Some("alice".to_string()),
+        };
+        let others = [
+            Caller {
+                actor: "api_key:two".to_string(),
+                profile: Some("alice".to_string()),
+            },
+            Caller {
+                actor: "api_key:one".to_string(),
+                profile: Some("bob".to_string()),
+            },
+        ];
+
+        manager
+            .register_request(&request, owner.clone(), None, 10)
+            .await
+            .unwrap();
+        manager.check_owner(session_id, &owner).await.unwrap();
+        for other in &others {
+            let result = manager.check_owner(session_id, other).await;
+            assert!(matches!(result, Err(FacetError::SessionNotFound(_))));
+        }
+
+        // Still checked once only the history knows the session
+        manager.complete(session_id).await.unwrap();
+        assert_eq!(manager.cleanup_old_sessions().await, 1);
+        manager.check_owner(session_id, &owner).await.unwrap();
+        for other in &others {
+            let result = manager.check_owner(session_id, other).await;
+            assert!(matches!(result, Err(FacetError::SessionNotFound(_))));
+        }
+
+        let missing = manager.check_owner(Uuid::new_v4(), &owner).await;
+        assert!(matches!(missing, Err(FacetError::SessionNotFound(_))));
+    }
+
     #[tokio::test]
     async fn test_total_count() {
         let manager = SessionManager::new(100);
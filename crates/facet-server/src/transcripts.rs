@@ -0,0 +1,329 @@
+//! Request transcripts in the knowledge graph
+//!
+//! Once a request completes, its prompt and answer become an `Interaction`
+//! node in the partition of the profile it acted for (`default` without
+//! one), so past exchanges can be found with the graph's text search and
+//! pulled in as context. Interactions hang off a `Conversation` node, point back to
+//! the one they resumed, and link to a `File` node for every file Claude's
+//! tools touched:
+//!
+//! ```text
+//! Conversation -[contains]-> Interaction -[references]-> File
+//!                            Interaction -[follows]-> Interaction
+//! ```
+//!
+//! Conversations and files are keyed by profile and by conversation id or
+//! path, so every request of a conversation, and every request of a profile
+//! touching a file, lands on the same node.
+//!
+//! Each transcript is written as a run named after its session (see
+//! `facet_graph::runs`), with history on, so a bad one can be taken out
+//! again with `facet graph rollback-run <session id> --backend sqlite`.
+
+use crate::error::FacetError;
+use crate::profile_usage::DEFAULT_PROFILE;
+use facet_graph::runs::RunStore;
+use facet_graph::sqlite_store::SqliteStore;
+use facet_graph::transaction::GraphTransaction;
+use facet_graph::{Edge, GraphError, GraphStore, Node};
+use std::path::Path;
+use uuid::Uuid;
+
+/// Tool parameters naming a file the tool reads or writes
+const FILE_PARAMS: [&str; 3] = ["file_path", "notebook_path", "path"];
+
+/// A completed request, as written to the graph
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transcript {
+    /// Session the request ran in, which is also its node's id
+    pub session_id: Uuid,
+
+    /// Conversation the request ran in, as claude-cli knows it
+    pub conversation_id: String,
+
+    /// Session whose conversation this request continues
+    pub resumed_from: Option<Uuid>,
+
+    /// Profile the request acted for, if it named one
+    pub profile: Option<String>,
+
+    /// User's prompt
+    pub prompt: String,
+
+    /// Claude's answer, its text content joined
+    pub answer: String,
+
+    /// Tools Claude called, each once, in order of first use
+    pub tools: Vec<String>,
+
+    /// Files Claude's tools touched, each once, in order of first use
+    pub files: Vec<String>,
+
+    /// ISO 8601 timestamp of when the request started
+    pub started_at: String,
+
+    /// ISO 8601 timestamp of when the request completed
+    pub completed_at: String,
+}
+
+/// Returns the file a tool call reads or writes, if its parameters name one
+///
+/// # Arguments
+/// * `params` - Parameters of the tool call
+pub fn referenced_file(params: &serde_json::Value) -> Option<&str> {
+    FILE_PARAMS
+        .iter()
+        .find_map(|key| params.get(*key).and_then(|v| v.as_str()))
+        .filter(|path| !path.is_empty())
+}
+
+/// Writes transcripts of completed requests to a knowledge graph
+#[derive(Clone)]
+pub struct TranscriptStore {
+    graph: SqliteStore,
+}
+
+impl std::fmt::Debug for TranscriptStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TranscriptStore").finish_non_exhaustive()
+    }
+}
+
+impl TranscriptStore {
+    /// Writes transcripts to a graph
+    ///
+    /// # Arguments
+    /// * `graph` - Graph to write to; rolling a transcript back restores
+    ///   the nodes it updated only if the graph's history mode is on
+    ///
+    /// # Returns
+    /// TranscriptStore writing to `graph`
+    pub fn new(graph: SqliteStore) -> Self {
+        Self { graph }
+    }
+
+    /// Opens the SQLite graph at `path`, which is created if missing, and
+    /// turns its history mode on
+    ///
+    /// # Arguments
+    /// * `path` - Path to the graph database
+    ///
+    /// # Returns
+    /// TranscriptStore writing to the graph
+    ///
+    /// # Errors
+    /// Returns FacetError::Config if the database cannot be opened
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, FacetError> {
+        let path = path.as_ref();
+        let store = SqliteStore::new(path.to_path_buf())
+            .and_then(|store| store.set_history(true).map(|()| store))
+            .map_err(|e| {
+                FacetError::Config(format!("Failed to open graph {}: {}", path.display(), e))
+            })?;
+        Ok(Self::new(store))
+    }
+
+    /// Returns the graph transcripts are written to
+    pub fn graph(&self) -> &SqliteStore {
+        &self.graph
+    }
+
+    /// Writes a completed request to the graph
+    ///
+    /// # Arguments
+    /// * `transcript` - Request to write
+    ///
+    /// # Errors
+    /// Returns FacetError::Internal if the graph cannot be written to
+    pub async fn record(&self, transcript: &Transcript) -> Result<(), FacetError> {
+        self.write(transcript)
+            .await
+            .map_err(|e| FacetError::Internal(format!("Failed to write transcript: {}", e)))
+    }
+
+    async fn write(&self, transcript: &Transcript) -> Result<(), GraphError> {
+        let graph = RunStore::new(self.graph.clone(), transcript.session_id.to_string());
+        let partition = transcript
+            .profile
+            .as_deref()
+            .unwrap_or(DEFAULT_PROFILE)
+            .to_string();
+        let node = |label: &str, properties: serde_json::Value| Node {
+            id: String::new(),
+            label: label.to_string(),
+            properties,
+            partition_id: partition.clone(),
+        };
+        let edge = |source: &str, relation: &str, target: &str| Edge {
+            source: source.to_string(),
+            target: target.to_string(),
+            relation: relation.to_string(),
+            weight: 1.0,
+            partition_id: partition.clone(),
+            valid_from: None,
+            valid_to: None,
+            cross_partition: false,
+        };
+
+        // Shared with other requests, so merged into rather than added
+        let conversation = graph
+            .upsert_node_by_key(
+                node(
+                    "Conversation",
+                    serde_json::json!({
+                        "conversation_id": transcript.conversation_id,
+                        "profile": partition,
+                        "updated_at": transcript.completed_at,
+                    }),
+                ),
+                &["conversation_id", "profile"],
+            )
+            .await?;
+        let mut files = Vec::with_capacity(transcript.files.len());
+        for path in &transcript.files {
+            let file = node(
+                "File",
+                serde_json::json!({ "path": path, "profile": partition }),
+            );
+            files.push(graph.upsert_node_by_key(file, &["path", "profile"]).await?);
+        }
+
+        let id = transcript.session_id.to_string();
+        let mut tx = GraphTransaction::new();
+        tx.add_node(Node {
+            id: id.clone(),
+            ..node(
+                "Interaction",
+                serde_json::json!({
+                    "session_id": id,
+                    "prompt": transcript.prompt,
+                    "answer": transcript.answer,
+                    // What retrieval reads and shows
+                    "content": format!("{}\n\n{}", transcript.prompt, transcript.answer),
+                    "tools": transcript.tools,
+                    "started_at": transcript.started_at,
+                    "completed_at": transcript.completed_at,
+                }),
+            )
+        });
+        tx.add_edge(edge(&conversation, "contains", &id));
+        if let Some(previous) = transcript.resumed_from {
+            // Only if it was written: it may have failed or been cancelled
+            if graph.node_exists(&previous.to_string()).await? {
+                tx.add_edge(edge(&id, "follows", &previous.to_string()));
+            }
+        }
+        for file in &files {
+            tx.add_edge(edge(&id, "references", file));
+        }
+        graph.commit_transaction(tx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use facet_graph::runs::rollback_run;
+
+    fn transcript(profile: Option<&str>, resumed_from: Option<Uuid>) -> Transcript {
+        Transcript {
+            session_id: Uuid::new_v4(),
+            conversation_id: "conv-1".to_string(),
+            resumed_from,
+            profile: profile.map(str::to_string),
+            prompt: "What does main.rs do?".to_string(),
+            answer: "It starts the server.".to_string(),
+            tools: vec!["Read".to_string()],
+            files: vec!["src/main.rs".to_string()],
+            started_at: chrono::Utc::now().to_rfc3339(),
+            completed_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    #[test]
+    fn test_referenced_file() {
+        let read = serde_json::json!({"file_path": "src/main.rs", "limit": 20});
+        assert_eq!(referenced_file(&read), Some("src/main.rs"));
+        let cdp = serde_json::json!({"command": "DOM.getDocument"});
+        assert_eq!(referenced_file(&cdp), None);
+    }
+
+    #[tokio::test]
+    async fn test_transcripts_in_profile_partition() {
+        let store = TranscriptStore::new(SqliteStore::in_memory().unwrap());
+        let graph = store.graph();
+
+        let first = transcript(Some("work"), None);
+        store.record(&first).await.unwrap();
+        let second = transcript(Some("work"), Some(first.session_id));
+        store.record(&second).await.unwrap();
+
+        let interaction = graph
+            .get_node(&second.session_id.to_string())
+            .await
+            .unwrap();
+        assert_eq!(interaction.label, "Interaction");
+        assert_eq!(interaction.partition_id, "work");
+        assert_eq!(interaction.properties["answer"], "It starts the server.");
+
+        // One conversation and one file, shared by both requests
+        let nodes = graph.query_by_partition("work").await.unwrap();
+        assert_eq!(nodes.len(), 4);
+        let mut relations: Vec<String> = graph
+            .get_neighbors(&second.session_id.to_string())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(edge, node)| format!("{} {}", edge.relation, node.label))
+            .collect();
+        relations.sort();
+        assert_eq!(relations, vec!["follows Interaction", "references File"]);
+        let (_, conversation) = graph
+            .get_incoming_neighbors(&first.session_id.to_string())
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|(edge, _)| edge.relation == "contains")
+            .unwrap();
+        assert_eq!(conversation.label, "Conversation");
+        assert_eq!(
+            graph.get_neighbors(&conversation.id).await.unwrap().len(),
+            2
+        );
+
+        // Same conversation id and file, but nodes of their own
+        store.record(&transcript(None, None)).await.unwrap();
+        assert_eq!(graph.count_nodes(Some(DEFAULT_PROFILE)).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_transcript_rolled_back() {
+        let graph = SqliteStore::in_memory().unwrap();
+        graph.set_history(true).unwrap();
+        let store = TranscriptStore::new(graph.clone());
+
+        let first = transcript(Some("work"), None);
+        store.record(&first).await.unwrap();
+        let before = graph.query_by_partition("work").await.unwrap();
+        let mut second = transcript(Some("work"), Some(first.session_id));
+        second.completed_at = "2099-01-01T00:00:00Z".to_string();
+        store.record(&second).await.unwrap();
+
+        let report = rollback_run(&graph, &second.session_id.to_string())
+            .await
+            .unwrap();
+        assert_eq!(report.nodes_deleted, 1);
+        assert!(report.skipped.is_empty());
+
+        // The conversation and file are back as the first request left them
+        let mut after = graph.query_by_partition("work").await.unwrap();
+        let mut before = before;
+        after.sort_by(|a, b| a.id.cmp(&b.id));
+        before.sort_by(|a, b| a.id.cmp(&b.id));
+        assert_eq!(after, before);
+        assert!(!graph
+            .node_exists(&second.session_id.to_string())
+            .await
+            .unwrap());
+    }
+}
@@ -167,7 +167,9 @@ async fn test_session_lifecycle() {
     let filter = warp::path!("sessions" / Uuid)
         .and(warp::get())
         .and(warp::any().map(move || manager_clone.clone()))
-        .and_then(get_session_handler);
+        .and_then(|session_id, manager| {
+            get_session_handler(session_id, Default::default(), manager)
+        });
 
     let response = request()
         .method("GET")
@@ -191,7 +193,9 @@ async fn test_session_not_found() {
     let filter = warp::path!("sessions" / Uuid)
         .and(warp::get())
         .and(warp::any().map(move || session_manager.clone()))
-        .and_then(get_session_handler)
+        .and_then(|session_id, manager| {
+            get_session_handler(session_id, Default::default(), manager)
+        })
         .recover(handle_rejection);
 
     let response = request()
@@ -200,15 +204,10 @@ async fn test_session_not_found() {
         .reply(&filter)
         .await;
 
-    // The handler returns 200 OK with error JSON in the body (this is intentional)
-    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
 
-    // Verify the response contains an error
     let body: serde_json::Value = serde_json::from_slice(response.body()).expect("Valid JSON");
-    if let Some(code) = body.get("code") {
-        // Error response format
-        assert_eq!(code, "SESSION_NOT_FOUND");
-    }
+    assert_eq!(body["code"], "SESSION_NOT_FOUND");
 }
 
 /// Test execute endpoint with mock executor
@@ -227,7 +226,7 @@ async fn test_execute_endpoint() {
         .and(warp::any().map(move || session_manager.clone()))
         .and(warp::any().map(move || config.clone()))
         .and_then(|request, executor, manager, config| {
-            execute_handler(request, executor, manager, config)
+            execute_handler(request, Default::default(), executor, manager, config)
         });
 
     let response = request()
@@ -285,11 +285,13 @@ mod tests {
                 default_timeout_ms: 5000,
                 inference_mode: crate::profiles::types::InferenceMode::Local,
                 language: "en".to_string(),
+                redaction: crate::profiles::types::RedactionPreferences::default(),
             },
             stats: crate::profiles::types::UserStats {
                 total_commands_run: 0,
                 total_sessions: 0,
                 commands_created: 0,
+                usage: Default::default(),
             },
         };
 
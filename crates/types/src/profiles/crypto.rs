@@ -5,6 +5,8 @@
 /// - Argon2id for password hashing and key derivation (PHC winner)
 /// - AES-256-GCM for authenticated encryption (NIST standard)
 ///
+/// - SHA-256 for hashing API keys, which are random and need no stretching
+///
 /// Security properties:
 /// - Memory-hard password hashing resistant to GPU attacks
 /// - Authenticated encryption with integrity verification
@@ -26,6 +28,7 @@ use argon2::{
     Algorithm, Argon2, Params, Version,
 };
 use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
 use std::io;
 use thiserror::Error;
 use zeroize::{Zeroize, ZeroizeOnDrop};
@@ -56,6 +59,15 @@ const SALT_LENGTH: usize = 16;
 /// Nonce length for AES-GCM in bytes (12 bytes = 96 bits)
 const NONCE_LENGTH: usize = 12;
 
+/// Prefix of every API key, so a leaked key is recognizable as one
+pub const API_KEY_PREFIX: &str = "fk_";
+
+/// API key id length in bytes (6 bytes = 12 hex characters)
+const API_KEY_ID_LENGTH: usize = 6;
+
+/// API key secret length in bytes (32 bytes = 256 bits)
+const API_KEY_SECRET_LENGTH: usize = 32;
+
 // ============================================================================
 // Error Types
 // ============================================================================
@@ -260,7 +272,6 @@ pub fn verify_password(password: &str, salt: &[u8], expected_hash: &[u8]) -> Res
 /// This ensures that password verification takes the same amount of time
 /// regardless of where the first differing byte is, preventing attackers
 /// from using timing information to guess passwords.
-#[allow(dead_code)]
 fn constant_time_compare(a: &[u8], b: &[u8]) -> bool {
     if a.len() != b.len() {
         return false;
@@ -402,6 +413,73 @@ pub fn decrypt_file(encrypted: &[u8], key: &EncryptionKey) -> Result<Vec<u8>> {
     Ok(plaintext)
 }
 
+// ============================================================================
+// API Keys
+// ============================================================================
+
+/// Generate a new API key
+///
+/// Keys have the form `fk_<id>_<secret>`. The id is public and names the
+/// key when it is listed or revoked; only a hash of the whole key is ever
+/// stored, so the key itself is shown once, when it is created.
+///
+/// # Returns
+/// - `(String, String)`: Tuple of (id, key)
+///
+/// # Example
+/// ```
+/// use facet_types::profiles::crypto::{generate_api_key, hash_api_key, verify_api_key};
+///
+/// let (id, key) = generate_api_key();
+/// assert!(key.contains(&id));
+/// let hash = hash_api_key(&key);
+/// assert!(verify_api_key(&key, &hash));
+/// ```
+pub fn generate_api_key() -> (String, String) {
+    let mut id = [0u8; API_KEY_ID_LENGTH];
+    OsRng.fill_bytes(&mut id);
+    let mut secret = [0u8; API_KEY_SECRET_LENGTH];
+    OsRng.fill_bytes(&mut secret);
+
+    let id = hex::encode(id);
+    let key = format!("{}{}_{}", API_KEY_PREFIX, id, hex::encode(secret));
+    secret.zeroize();
+    (id, key)
+}
+
+/// Extract the id from an API key
+///
+/// # Returns
+/// - `Some(&str)`: The key's id
+/// - `None`: If the string is not shaped like an API key
+pub fn api_key_id(key: &str) -> Option<&str> {
+    let (id, secret) = key.strip_prefix(API_KEY_PREFIX)?.split_once('_')?;
+    if id.len() != API_KEY_ID_LENGTH * 2 || secret.len() != API_KEY_SECRET_LENGTH * 2 {
+        return None;
+    }
+    Some(id)
+}
+
+/// Hash an API key for storage
+///
+/// API keys carry 256 random bits, so unlike passwords they cannot be
+/// guessed and a plain SHA-256 is enough. It is also fast enough to run on
+/// every request, which Argon2id is not.
+///
+/// # Returns
+/// - `String`: Hex-encoded SHA-256 of the key
+pub fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Verify an API key against a stored hash
+///
+/// # Returns
+/// - `bool`: true if the key hashes to `hash`
+pub fn verify_api_key(key: &str, hash: &str) -> bool {
+    constant_time_compare(hash_api_key(key).as_bytes(), hash.as_bytes())
+}
+
 // ============================================================================
 // Unit Tests
 // ============================================================================
@@ -552,6 +630,21 @@ mod tests {
         assert!(!key_bytes.is_empty()); // Original bytes are still in our copy
     }
 
+    #[test]
+    fn test_api_key_round_trip() {
+        let (id, key) = generate_api_key();
+        assert!(key.starts_with(API_KEY_PREFIX));
+        assert_eq!(api_key_id(&key), Some(id.as_str()));
+
+        let hash = hash_api_key(&key);
+        assert!(verify_api_key(&key, &hash));
+
+        let (_, other) = generate_api_key();
+        assert!(!verify_api_key(&other, &hash));
+        assert_eq!(api_key_id("not-a-key"), None);
+        assert_eq!(api_key_id("fk_abc_def"), None);
+    }
+
     #[test]
     fn test_constant_time_compare_same_length() {
         let a = b"same_length_a";
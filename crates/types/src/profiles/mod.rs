@@ -18,5 +18,6 @@ pub mod storage;
 pub mod types;
 
 pub use types::{
-    CommandConfig, SimpleParameter, SimpleParameterType, UserConfig, UserPreferences, UserStats,
+    CommandConfig, RedactionPreferences, SimpleParameter, SimpleParameterType, UsageTotals,
+    UserConfig, UserPreferences, UserStats,
 };
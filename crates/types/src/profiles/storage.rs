@@ -22,8 +22,10 @@
 /// │   │   ├── browser-profiles/
 /// │   │   │   ├── default/     # Default browser profile
 /// │   │   │   └── named/       # Named browser profiles
-/// │   │   └── commands/
-/// │   │       └── *.md         # Command files (encrypted)
+/// │   │   ├── commands/
+/// │   │   │   └── *.md         # Command files (encrypted)
+/// │   │   ├── llm-cache/       # Cached model replies (encrypted)
+/// │   │   └── graph.sqlite     # Knowledge graph
 /// │   └── bob/
 /// │       └── ...
 /// └── .tmp/
@@ -66,6 +68,12 @@ const BROWSER_PROFILES_DIR: &str = "browser-profiles";
 /// Directory name for commands
 const COMMANDS_DIR: &str = "commands";
 
+/// Directory name for cached LLM replies
+const LLM_CACHE_DIR: &str = "llm-cache";
+
+/// Filename for the user's knowledge graph
+const GRAPH_FILE: &str = "graph.sqlite";
+
 /// Default browser profile name
 const DEFAULT_BROWSER_PROFILE: &str = "default";
 
@@ -196,6 +204,20 @@ pub fn get_command_path(
     Ok(get_commands_dir(username, base_dir)?.join(format!("{}.md", command_name)))
 }
 
+/// Get a user's LLM response cache directory
+///
+/// Returns `~/.facet/users/{username}/llm-cache/`
+pub fn get_llm_cache_dir(username: &str, base_dir: Option<&Path>) -> Result<PathBuf> {
+    Ok(get_user_dir(username, base_dir)?.join(LLM_CACHE_DIR))
+}
+
+/// Get a user's knowledge graph database path
+///
+/// Returns `~/.facet/users/{username}/graph.sqlite`
+pub fn get_graph_path(username: &str, base_dir: Option<&Path>) -> Result<PathBuf> {
+    Ok(get_user_dir(username, base_dir)?.join(GRAPH_FILE))
+}
+
 /// Get the salt file path for a user
 ///
 /// Returns `~/.facet/users/{username}/.salt`
@@ -831,6 +853,18 @@ mod tests {
         assert!(command_path.ends_with("commands/clothing-search.md"));
     }
 
+    #[test]
+    fn test_get_llm_cache_dir() {
+        let cache_dir = get_llm_cache_dir("alice", None).unwrap();
+        assert!(cache_dir.ends_with("users/alice/llm-cache"));
+    }
+
+    #[test]
+    fn test_get_graph_path() {
+        let graph_path = get_graph_path("alice", None).unwrap();
+        assert!(graph_path.ends_with("users/alice/graph.sqlite"));
+    }
+
     #[test]
     fn test_create_default_user_profile() {
         let profile = create_default_user_profile("alice");
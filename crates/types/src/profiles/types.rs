@@ -62,6 +62,28 @@ pub struct UserPreferences {
 
     /// UI language as ISO 639-1 code (e.g., "en", "es", "fr")
     pub language: String,
+
+    /// PII redaction settings for requests sent to remote backends
+    #[serde(default)]
+    pub redaction: RedactionPreferences,
+}
+
+/// PII redaction settings
+///
+/// Controls whether prompts are redacted before leaving the device and
+/// whether the user must review the replacements first.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct RedactionPreferences {
+    /// Redact PII from prompts before sending them to a remote backend
+    pub enabled: bool,
+
+    /// Show a redaction preview and wait for confirmation before sending
+    pub confirm_before_send: bool,
+
+    /// Previews with at most this many replacements are approved without
+    /// asking (0 means every redaction needs confirmation)
+    pub auto_approve_threshold: usize,
 }
 
 /// UI theme options
@@ -97,6 +119,46 @@ pub struct UserStats {
 
     /// Number of commands created by this user
     pub commands_created: u64,
+
+    /// Tokens and cost of requests run on facet-server
+    #[serde(default)]
+    pub usage: UsageTotals,
+}
+
+/// Token usage and cost of claude-cli requests, summed
+///
+/// facet-server keeps these per profile and serves them at /api/v1/usage.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    /// Number of requests counted
+    pub requests: u64,
+
+    /// Input tokens, cached ones excluded
+    pub input_tokens: u64,
+
+    /// Output tokens
+    pub output_tokens: u64,
+
+    /// Input tokens written to the prompt cache
+    pub cache_creation_input_tokens: u64,
+
+    /// Input tokens read from the prompt cache
+    pub cache_read_input_tokens: u64,
+
+    /// Cost in USD
+    pub cost_usd: f64,
+}
+
+impl UsageTotals {
+    /// Adds another set of totals to these
+    pub fn add(&mut self, other: &UsageTotals) {
+        self.requests += other.requests;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+        self.cost_usd += other.cost_usd;
+    }
 }
 
 // ============================================================================
@@ -559,6 +621,17 @@ impl Default for UserPreferences {
             default_timeout_ms: 5000,
             inference_mode: InferenceMode::Local,
             language: "en".to_string(),
+            redaction: RedactionPreferences::default(),
+        }
+    }
+}
+
+impl Default for RedactionPreferences {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            confirm_before_send: true,
+            auto_approve_threshold: 0,
         }
     }
 }